@@ -0,0 +1,89 @@
+extern crate wain_ast;
+extern crate wain_exec;
+extern crate wain_syntax_text;
+extern crate wain_validate;
+
+use std::io;
+use std::process::exit;
+use wain_ast::InsnKind;
+use wain_exec::{DefaultImporter, Invocation, Machine, Suspension};
+use wain_syntax_text::parse;
+use wain_validate::validate;
+
+const MODULE_LOOP: &str = r#"
+(module
+  (func $step (param i32) (result i32)
+    local.get 0
+    i32.const 1
+    i32.add)
+  (func (export "run") (result i32)
+    (local i32)
+    loop $l
+      local.get 0
+      call $step
+      local.tee 0
+      i32.const 10000
+      i32.lt_u
+      br_if $l
+    end
+    local.get 0))
+"#;
+
+fn main() {
+    // Parse WAT text into syntax tree
+    let tree = match parse(MODULE_LOOP) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("Parse failed: {}", err);
+            exit(1);
+        }
+    };
+
+    // Validate module
+    if let Err(err) = validate(&tree) {
+        eprintln!("This .wat file is invalid: {}", err);
+        exit(1);
+    }
+
+    let importer = DefaultImporter::with_stdio(io::empty(), io::sink());
+    let mut machine = match Machine::instantiate(&tree.module, importer) {
+        Ok(m) => m,
+        Err(err) => {
+            eprintln!("could not instantiate module: {}", err);
+            exit(1);
+        }
+    };
+
+    // Bound the execution with fuel. Calling functions costs more than other instructions.
+    // This is the snippet shown in wain-exec/README.md
+    machine.set_fuel(1_000_000);
+    machine.set_fuel_cost(|kind| match kind {
+        InsnKind::Call(_) | InsnKind::CallIndirect { .. } => 10,
+        _ => 1,
+    });
+    let result = machine.invoke("run", &[]);
+    println!("consumed {} fuel", machine.consumed_fuel());
+    if let Err(trap) = result {
+        eprintln!("Execution was trapped: {}", trap);
+    }
+
+    // Run the same function with small fuel and resume it each time the fuel runs out
+    machine.set_fuel(10000);
+    let mut state = machine.invoke_resumable("run", &[]);
+    let mut slices = 1;
+    while let Ok(Invocation::Suspended(Suspension::OutOfFuel)) = state {
+        // Run other tasks here...
+        machine.set_fuel(10000);
+        state = machine.resume();
+        slices += 1;
+    }
+    match state {
+        Ok(Invocation::Finished(ret)) => {
+            if let [ret] = ret.as_slice() {
+                println!("returned {} after {} time slices", ret, slices);
+            }
+        }
+        Ok(_) => unreachable!(),
+        Err(trap) => eprintln!("Execution was trapped: {}", trap),
+    }
+}
@@ -81,9 +81,9 @@ fn main() {
     // i32, i64, f32, f64 basic types.
     match machine.invoke("add", &[Value::I32(10), Value::I32(32)]) {
         Ok(ret) => {
-            // `ret` is type of `Option<Value>` where it contains `Some` value when the invoked
-            // function returned a value. Otherwise it's `None` value.
-            if let Some(Value::I32(i)) = ret {
+            // `ret` is type of `Vec<Value>` which contains values returned from the invoked
+            // function. It is empty when the function returned nothing.
+            if let [Value::I32(i)] = ret.as_slice() {
                 println!("10 + 32 = {}", i);
             } else {
                 unreachable!();
@@ -128,6 +128,7 @@ fn main() {
     match execute(&tree.module) {
         Ok(Run::Success) => {}
         Ok(Run::Warning(msg)) => eprintln!("Warning: {}", msg),
+        Ok(Run::Exit(status)) => exit(status),
         Err(trap) => eprintln!("Execution was trapped: {}", trap),
     }
 }
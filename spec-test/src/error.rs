@@ -54,7 +54,6 @@ pub enum ParseKind<'source> {
 }
 
 pub enum RunKind<'source> {
-    NotImplementedYet,
     ParseQuoteFailure(wat::Error<'source>),
     ParseBinaryFailure(binary::Error<'source>),
     InvalidText(validate::Error<TextSource<'source>>),
@@ -62,29 +61,27 @@ pub enum RunKind<'source> {
     ModuleNotFound(Option<&'source str>),
     Trapped(trap::Trap),
     InvokeUnexpectedReturn {
-        actual: Value,
-        expected: wast::Const,
+        actual: Vec<Value>,
+        expected: Vec<wast::Const>,
     },
     InvokeTrapExpected {
-        ret: Option<Value>,
+        ret: Vec<Value>,
         expected: String,
     },
     UnexpectedValid {
         expected: String,
     },
-    ExpectedParseError {
+    UnexpectedLinked {
         expected: String,
     },
-    GlobalNotFound(String),
-    DidNotCrash {
-        bin: PathBuf,
-        args: Box<[String]>,
-        stdout: String,
+    UnexpectedError {
+        actual: trap::Trap,
+        expected: String,
     },
-    UnexpectedCrash {
-        stderr: String,
+    ExpectedParseError {
         expected: String,
     },
+    GlobalNotFound(String),
 }
 
 pub struct Error<'source> {
@@ -198,7 +195,6 @@ impl<'s> fmt::Display for Error<'s> {
             ErrorKind::Run(kind) => {
                 use RunKind::*;
                 match kind {
-                    NotImplementedYet => write!(f, "this directive is not implemented yet")?,
                     ParseQuoteFailure(err) => write!(f, "cannot parse quoted module: {}", err)?,
                     ParseBinaryFailure(err) => write!(
                         f,
@@ -210,27 +206,46 @@ impl<'s> fmt::Display for Error<'s> {
                     ModuleNotFound(Some(id)) => write!(f, "module '{}' is not found", id)?,
                     ModuleNotFound(None) => write!(f, "no module is found")?,
                     Trapped(trap) => write!(f, "execution was unexpectedly trapped: {}", trap)?,
-                    InvokeUnexpectedReturn { actual, expected } => write!(
-                        f,
-                        "assert_return expected '{:?}' but got '{}'",
-                        expected, actual
-                    )?,
-                    InvokeTrapExpected { ret: None, expected } => write!(
+                    InvokeUnexpectedReturn { actual, expected } => {
+                        let expected: Vec<_> =
+                            expected.iter().map(|c| format!("{:?}", c)).collect();
+                        let actual: Vec<_> = actual.iter().map(|v| v.to_string()).collect();
+                        write!(
+                            f,
+                            "assert_return expected '[{}]' but got '[{}]'",
+                            expected.join(", "),
+                            actual.join(", "),
+                        )?
+                    }
+                    InvokeTrapExpected { ret, expected } if ret.is_empty() => write!(
                         f,
                         "expected trap with message '{}' while invocation but it unexpectedly returned successfully",
                         expected
                     )?,
-                    InvokeTrapExpected { ret: Some(ret), expected } => write!(
+                    InvokeTrapExpected { ret, expected } => {
+                        let ret: Vec<_> = ret.iter().map(|v| v.to_string()).collect();
+                        write!(
+                            f,
+                            "expected trap with message '{}' while invocation but it unexpectedly returned [{}] successfully",
+                            expected,
+                            ret.join(", "),
+                        )?
+                    }
+                    UnexpectedValid { expected } => write!(
                         f,
-                        "expected trap with message '{}' while invocation but it unexpectedly returned {} successfully",
+                        "expected invalid error with message '{}' but got no error",
                         expected,
-                        ret,
                     )?,
-                    UnexpectedValid { expected } => write!(
+                    UnexpectedLinked { expected } => write!(
                         f,
-                        "expected invalid error with message '{}' but got no error",
+                        "expected link error with message '{}' but module was successfully instantiated",
                         expected,
                     )?,
+                    UnexpectedError { actual, expected } => write!(
+                        f,
+                        "expected error with message '{}' but got other error: {}",
+                        expected, actual,
+                    )?,
                     ExpectedParseError { expected } => write!(
                         f,
                         "expected parse error with message '{}' but it was successfully done",
@@ -241,20 +256,6 @@ impl<'s> fmt::Display for Error<'s> {
                         "exported global variable '{}' is not found",
                         name,
                     )?,
-                    DidNotCrash {
-                        bin,
-                        args,
-                        stdout,
-                    } => write!(
-                        f,
-                        "running crash-tester did not crash: bin {:?} with args {:?}. stdout: '{}'", bin, args, stdout,
-                    )?,
-                    UnexpectedCrash{stderr, expected} => write!(
-                        f,
-                        "unexpected crash with stderr '{}' while expecting '{}'",
-                        stderr,
-                        expected,
-                    )?,
                 }
                 "running"
             }
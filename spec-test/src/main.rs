@@ -1,6 +1,5 @@
 #![forbid(unsafe_code)]
 
-mod crash;
 mod error;
 mod parser;
 mod runner;
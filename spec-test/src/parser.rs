@@ -339,6 +339,8 @@ impl<'s> Parse<'s> for Const {
                 }
             };
         }
+        parse_int_fn!(parse_i8, i8, u8);
+        parse_int_fn!(parse_i16, i16, u16);
         parse_int_fn!(parse_i32, i32, u32);
         parse_int_fn!(parse_i64, i64, u64);
 
@@ -422,6 +424,33 @@ impl<'s> Parse<'s> for Const {
         parse_float_fn!(parse_f32, f32);
         parse_float_fn!(parse_f64, f64);
 
+        macro_rules! parse_float_lane_fn {
+            ($name:ident, $parse:ident, $ty:ty) => {
+                fn $name<'s>(parser: &mut Parser<'s>) -> Result<'s, FloatLane> {
+                    let f = match parser.consume()? {
+                        Some(Token::Keyword("nan:canonical")) => return Ok(FloatLane::CanonicalNan),
+                        Some(Token::Keyword("nan:arithmetic")) => {
+                            return Ok(FloatLane::ArithmeticNan)
+                        }
+                        // Apply sign after conversion to keep sign of -0
+                        Some(Token::Int(s, b, d)) => {
+                            s.apply(parse_i64(parser, Sign::Plus, b, d)? as $ty)
+                        }
+                        Some(Token::Float(s, Float::Nan(_))) => s.apply(<$ty>::NAN),
+                        Some(Token::Float(Sign::Plus, Float::Inf)) => <$ty>::INFINITY,
+                        Some(Token::Float(Sign::Minus, Float::Inf)) => <$ty>::NEG_INFINITY,
+                        Some(Token::Float(sign, Float::Val { base, frac, exp })) => {
+                            $parse(parser, sign, base, frac, exp)?
+                        }
+                        x => return parser.unexpected_token(x, concat!(stringify!($ty), " lane")),
+                    };
+                    Ok(FloatLane::Bits(f.to_bits() as u64))
+                }
+            };
+        }
+        parse_float_lane_fn!(parse_f32_lane, parse_f32, f32);
+        parse_float_lane_fn!(parse_f64_lane, parse_f64, f64);
+
         expect!(parser, Token::LParen);
         let kw = expect!(parser, Token::Keyword(k) => k);
 
@@ -458,7 +487,55 @@ impl<'s> Parse<'s> for Const {
                 }
                 x => return parser.unexpected_token(x, "f64 value"),
             },
-            _ => return parser.unexpected("t.const for constant"),
+            "v128.const" => {
+                let mut bytes = [0; 16];
+                macro_rules! int_lanes {
+                    ($parse:ident, $size:expr) => {{
+                        for i in 0..16 / $size {
+                            let lane = match parser.consume()? {
+                                Some(Token::Int(s, b, d)) => $parse(parser, s, b, d)?,
+                                x => return parser.unexpected_token(x, "integer lane"),
+                            };
+                            bytes[i * $size..(i + 1) * $size].copy_from_slice(&lane.to_le_bytes());
+                        }
+                        Const::V128(i128::from_le_bytes(bytes))
+                    }};
+                }
+                match parser.consume()? {
+                    Some(Token::Keyword("i8x16")) => int_lanes!(parse_i8, 1),
+                    Some(Token::Keyword("i16x8")) => int_lanes!(parse_i16, 2),
+                    Some(Token::Keyword("i32x4")) => int_lanes!(parse_i32, 4),
+                    Some(Token::Keyword("i64x2")) => int_lanes!(parse_i64, 8),
+                    Some(Token::Keyword("f32x4")) => {
+                        let mut lanes = [FloatLane::Bits(0); 4];
+                        for lane in lanes.iter_mut() {
+                            *lane = parse_f32_lane(parser)?;
+                        }
+                        Const::F32x4(lanes)
+                    }
+                    Some(Token::Keyword("f64x2")) => {
+                        let mut lanes = [FloatLane::Bits(0); 2];
+                        for lane in lanes.iter_mut() {
+                            *lane = parse_f64_lane(parser)?;
+                        }
+                        Const::F64x2(lanes)
+                    }
+                    x => return parser.unexpected_token(x, "shape of v128 value"),
+                }
+            }
+            "ref.null" => match parser.consume()? {
+                Some(Token::Keyword("func")) => Const::RefNull(ast::RefType::FuncRef),
+                Some(Token::Keyword("extern")) => Const::RefNull(ast::RefType::ExternRef),
+                x => return parser.unexpected_token(x, "'func' or 'extern' for heap type"),
+            },
+            "ref.extern" => match parser.consume()? {
+                Some(Token::Int(Sign::Plus, b, d)) => {
+                    Const::RefExtern(parse_i32(parser, Sign::Plus, b, d)? as u32)
+                }
+                x => return parser.unexpected_token(x, "number for external reference"),
+            },
+            "ref.func" => Const::RefFunc,
+            _ => return parser.unexpected("t.const or reference for constant"),
         };
 
         expect!(parser, Token::RParen);
@@ -510,18 +587,17 @@ impl<'s> Parse<'s> for GetGlobal<'s> {
     }
 }
 
-// (assert_return (invoke {name} {constant}*) {constant}?)
+// (assert_return (invoke {name} {constant}*) {constant}*)
 impl<'s> Parse<'s> for AssertReturn<'s> {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
         let start = parser.parse_start("assert_return")?;
         match parser.peek()? {
             (Some(Token::LParen), Some(Token::Keyword("invoke"))) => {
                 let invoke = parser.parse()?;
-                let expected = if let (Some(Token::LParen), _) = parser.peek()? {
-                    Some(parser.parse()?)
-                } else {
-                    None
-                };
+                let mut expected = vec![];
+                while let (Some(Token::LParen), _) = parser.peek()? {
+                    expected.push(parser.parse()?);
+                }
                 expect!(parser, Token::RParen);
                 Ok(AssertReturn::Invoke {
                     start,
@@ -654,6 +730,16 @@ impl<'s> Parse<'s> for AssertExhaustion<'s> {
     }
 }
 
+// (assert_exception (invoke {name} {constant}*))
+impl<'s> Parse<'s> for AssertException<'s> {
+    fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
+        let start = parser.parse_start("assert_exception")?;
+        let invoke = parser.parse()?;
+        expect!(parser, Token::RParen);
+        Ok(AssertException { start, invoke })
+    }
+}
+
 impl<'s> Parse<'s> for Directive<'s> {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
         let (t1, t2) = parser.peek()?;
@@ -679,6 +765,9 @@ impl<'s> Parse<'s> for Directive<'s> {
             Some(Token::Keyword("assert_exhaustion")) => {
                 Ok(Directive::AssertExhaustion(parser.parse()?))
             }
+            Some(Token::Keyword("assert_exception")) => {
+                Ok(Directive::AssertException(parser.parse()?))
+            }
             Some(Token::Keyword("register")) => Ok(Directive::Register(parser.parse()?)),
             Some(Token::Keyword("invoke")) => Ok(Directive::Invoke(parser.parse()?)),
             Some(Token::Keyword("module")) => {
@@ -902,6 +991,43 @@ mod tests {
         assert_eq!(f, Const::F64(f64::INFINITY));
         let f = p("(f64.const -inf)").unwrap();
         assert_eq!(f, Const::F64(f64::NEG_INFINITY));
+
+        let r = p("(ref.null func)").unwrap();
+        assert_eq!(r, Const::RefNull(ast::RefType::FuncRef));
+        let r = p("(ref.null extern)").unwrap();
+        assert_eq!(r, Const::RefNull(ast::RefType::ExternRef));
+        assert_eq!(p("(ref.extern 42)").unwrap(), Const::RefExtern(42));
+        assert_eq!(p("(ref.func)").unwrap(), Const::RefFunc);
+        assert!(p("(ref.null any)").is_err());
+
+        let v = p("(v128.const i8x16 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 127)").unwrap();
+        assert_eq!(v, Const::V128(0x7f0e0d0c_0b0a0908_07060504_03020100));
+        let v = p("(v128.const i16x8 -1 0 0 0 0 0 0 0xff80)").unwrap();
+        assert_eq!(v, Const::V128(0xff80_0000_0000_0000_0000_0000_0000_ffff_u128 as i128));
+        let v = p("(v128.const i32x4 1 2 3 -1)").unwrap();
+        assert_eq!(v, Const::V128(0xffffffff_00000003_00000002_00000001_u128 as i128));
+        let v = p("(v128.const i64x2 0x10 -2)").unwrap();
+        assert_eq!(v, Const::V128(-2 << 64 | 0x10));
+        let v = p("(v128.const f32x4 1.0 -0 nan:canonical nan:arithmetic)").unwrap();
+        assert_eq!(
+            v,
+            Const::F32x4([
+                FloatLane::Bits(0x3f800000),
+                FloatLane::Bits(0x80000000),
+                FloatLane::CanonicalNan,
+                FloatLane::ArithmeticNan,
+            ])
+        );
+        let v = p("(v128.const f64x2 inf 0x1p-1)").unwrap();
+        assert_eq!(
+            v,
+            Const::F64x2([
+                FloatLane::Bits(f64::INFINITY.to_bits()),
+                FloatLane::Bits(0.5f64.to_bits()),
+            ])
+        );
+        assert!(p("(v128.const i32x4 1 2 3)").is_err());
+        assert!(p("(v128.const i32x2 1 2)").is_err());
     }
 
     #[test]
@@ -955,7 +1081,7 @@ mod tests {
                 assert_eq!(invoke.name, "8u_good1");
                 assert_eq!(invoke.args.len(), 1);
                 assert_eq!(invoke.args[0], Const::I32(0));
-                assert_eq!(expected, Some(Const::I32(97)));
+                assert_eq!(expected, vec![Const::I32(97)]);
             }
             _ => panic!("expected invoke"),
         }
@@ -970,7 +1096,20 @@ mod tests {
             } => {
                 assert_eq!(invoke.name, "type-i32");
                 assert!(invoke.args.is_empty());
-                assert_eq!(expected, None);
+                assert!(expected.is_empty());
+            }
+            _ => panic!("expected invoke"),
+        }
+
+        let a: AssertReturn = Parser::new(
+            r#"(assert_return (invoke "swap" (i32.const 1) (i64.const 2)) (i64.const 2) (i32.const 1))"#,
+        )
+        .parse()
+        .unwrap();
+
+        match a {
+            AssertReturn::Invoke { expected, .. } => {
+                assert_eq!(expected, vec![Const::I64(2), Const::I32(1)]);
             }
             _ => panic!("expected invoke"),
         }
@@ -1091,6 +1230,8 @@ mod tests {
         assert!(matches!(&m.memories[0], ast::Memory {
             ty: ast::MemType {
                 limit: ast::Limits::From(0),
+                shared: false,
+                memory64: false,
             },
             import: None,
             ..
@@ -1128,6 +1269,8 @@ mod tests {
         assert!(matches!(&m.memories[0], ast::Memory {
             ty: ast::MemType {
                 limit: ast::Limits::From(0),
+                shared: false,
+                memory64: false,
             },
             import: None,
             ..
@@ -1135,8 +1278,7 @@ mod tests {
 
         assert_eq!(m.data.len(), 1);
         assert!(matches!(&m.data[0], ast::DataSegment {
-            idx: 0,
-            offset,
+            mode: ast::DataMode::Active { idx: 0, offset },
             data,
             ..
         } if data.as_ref() == &b"a"[..] && offset.len() == 1));
@@ -1159,6 +1301,19 @@ mod tests {
         assert_eq!(a.invoke.args[0], Const::I64(1073741824));
     }
 
+    #[test]
+    fn assert_exception() {
+        let a: AssertException = Parser::new(
+            r#"(assert_exception (invoke "throw-param-i32" (i32.const 5)))"#,
+        )
+        .parse()
+        .unwrap();
+
+        assert_eq!(a.invoke.name, "throw-param-i32");
+        assert_eq!(a.invoke.args.len(), 1);
+        assert_eq!(a.invoke.args[0], Const::I32(5));
+    }
+
     #[test]
     fn directive() {
         let d: Directive = Parser::new(
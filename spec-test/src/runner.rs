@@ -1,4 +1,3 @@
-use crate::crash::CrashTester;
 use crate::error::{Error, ErrorKind, Result, RunKind};
 use crate::parser::Parser;
 use crate::wast;
@@ -9,12 +8,55 @@ use std::io::{self, Write};
 use std::path::Path;
 use std::time;
 use wain_ast as ast;
-use wain_exec::{DefaultImporter, Machine, Value};
+use wain_exec::trap::TrapReason;
+use wain_exec::{DefaultImporter, InstanceId, Machine, Value};
 use wain_syntax_binary as binary;
 use wain_syntax_text as wat;
 use wain_validate::validate;
 
-const SKIPPED: &[&str] = &["linking.wast"];
+const SKIPPED: &[&str] = &[];
+
+// Tests of post-MVP proposals supported by wain. They are put in proposals/{name} directory of the
+// test suite
+//
+// Tests of the following proposals are not run:
+// - exception-handling: Tests at the root of the directory are for `try_table` and `exnref`, which
+//   are not supported. Tests for the legacy `try`, `catch` and `delegate` instructions implemented
+//   by wain are run from its legacy/ directory
+// - threads: Tests spawn threads by `thread` and `wait` directives. wain runs guest code on one
+//   thread only so the tests for concurrent accesses to shared memory cannot be run
+const PROPOSALS: &[&str] = &[
+    "sign-extension-ops",
+    "nontrapping-float-to-int-conversions",
+    "bulk-memory-operations",
+    "reference-types",
+    "multi-memory",
+    "simd",
+    "tail-call",
+    "exception-handling/legacy",
+    "extended-const",
+    "memory64",
+];
+
+// Module registered as 'spectest' in each test
+// https://github.com/WebAssembly/spec/tree/master/interpreter#spectest-host-module
+const SPECTEST: &str = r#"
+(module
+  (global (export "global_i32") i32 (i32.const 666))
+  (global (export "global_i64") i64 (i64.const 666))
+  (global (export "global_f32") f32 (f32.const 666.6))
+  (global (export "global_f64") f64 (f64.const 666.6))
+  (table (export "table") 10 20 funcref)
+  (memory (export "memory") 1 2)
+  (func (export "print"))
+  (func (export "print_i32") (param i32))
+  (func (export "print_i64") (param i64))
+  (func (export "print_f32") (param f32))
+  (func (export "print_f64") (param f64))
+  (func (export "print_i32_f32") (param i32 f32))
+  (func (export "print_f64_f64") (param f64 f64))
+)
+"#;
 
 #[cfg(not(windows))]
 mod color {
@@ -113,20 +155,21 @@ impl Summary {
 // Test runner for one .wast file
 pub struct Runner<W: Write> {
     out: W,
-    crasher: CrashTester,
     fast_fail: bool,
+    spectest: ast::Module<'static>,
 }
 
 impl<W: Write> Runner<W> {
-    pub fn new(mut out: W, fast_fail: bool) -> Self {
-        write!(&mut out, "Building crash-tester...").unwrap();
-        let crasher = CrashTester::new();
-        writeln!(&mut out, "done").unwrap();
+    pub fn new(out: W, fast_fail: bool) -> Self {
+        let spectest = match wat::parse(SPECTEST) {
+            Ok(root) => root.module,
+            Err(err) => panic!("cannot parse 'spectest' module: {}", err),
+        };
 
         Runner {
             out,
-            crasher,
             fast_fail,
+            spectest,
         }
     }
 
@@ -143,9 +186,21 @@ impl<W: Write> Runner<W> {
         let mut num_files = 0;
         let start_time = time::SystemTime::now();
 
-        let entries = fs::read_dir(dir)?;
-        for entry in entries {
-            let path = entry?.path();
+        let mut paths = vec![];
+        let mut dirs = vec![dir.to_path_buf()];
+        for name in PROPOSALS {
+            let proposal = dir.join("proposals").join(name);
+            if proposal.is_dir() {
+                dirs.push(proposal);
+            }
+        }
+        for dir in dirs {
+            for entry in fs::read_dir(dir)? {
+                paths.push(entry?.path());
+            }
+        }
+
+        for path in paths {
             let file = if let Some(f) = path.file_name().and_then(|f| f.to_str()) {
                 f
             } else {
@@ -209,7 +264,7 @@ impl<W: Write> Runner<W> {
                         source: &source,
                         root: &root,
                     };
-                    tester.test(&self.crasher);
+                    tester.test(&self.spectest);
                     let num_errs = tester.errs.len();
                     for (idx, err) in tester.errs.iter_mut().enumerate() {
                         let nth = idx + 1;
@@ -232,33 +287,76 @@ impl<W: Write> Runner<W> {
     }
 }
 
+// Check the error is the one which the reference interpreter reports with the `expected` message.
+// Only prefixes of messages are compared since some messages are followed by details like
+// "uninitialized element 2". Messages in older test suites are also accepted
+fn error_matches(reason: &TrapReason, expected: &str) -> bool {
+    use TrapReason::*;
+    let messages: &[&str] = match reason {
+        UnknownImport { .. } => &["unknown import"],
+        IncompatibleImport { .. }
+        | FuncSignatureMismatch {
+            import: Some(_), ..
+        } => &["incompatible import type"],
+        FuncSignatureMismatch { import: None, .. } => &["indirect call type mismatch"],
+        OutOfBounds {
+            what: "table" | "element segment",
+            ..
+        } => &[
+            "out of bounds table access",
+            "elements segment does not fit",
+        ],
+        OutOfBounds { .. } | LoadMemoryOutOfRange { .. } => {
+            &["out of bounds memory access", "data segment does not fit"]
+        }
+        ReachUnreachable => &["unreachable"],
+        IdxOutOfTable { .. } => &["undefined element"],
+        UninitializedElem(_) => &["uninitialized element"],
+        RemZeroDivisor => &["integer divide by zero"],
+        DivByZeroOrOverflow => &["integer divide by zero", "integer overflow"],
+        InvalidConversionToInt => &["invalid conversion to integer"],
+        IntOverflow => &["integer overflow"],
+        StackExhausted { .. } => &["call stack exhausted"],
+        UnalignedAtomic { .. } => &["unaligned atomic"],
+        WaitOnUnsharedMemory => &["expected shared memory"],
+        _ => &[],
+    };
+    messages.iter().any(|m| expected.starts_with(m))
+}
+
 type MachineForTest<'m, 's> = Machine<'m, 's, DefaultImporter<Discard, Discard>>;
 type IndexToModule<'s> = HashMap<usize, (ast::Module<'s>, usize)>;
 
+// All modules in one .wast file are instantiated in the same store so that they can be linked
 struct Instances<'mods, 'src: 'mods> {
-    machines: Vec<(MachineForTest<'mods, 'src>, usize)>,
+    machine: MachineForTest<'mods, 'src>,
+    instances: Vec<(InstanceId, usize)>,
     idx_to_mod: &'mods IndexToModule<'src>,
     source: &'src str,
 }
 
 impl<'m, 's> Instances<'m, 's> {
-    fn new(idx_to_mod: &'m IndexToModule<'s>, source: &'s str) -> Self {
-        Instances {
-            machines: vec![],
+    fn new(
+        idx_to_mod: &'m IndexToModule<'s>,
+        spectest: &'m ast::Module<'s>,
+        source: &'s str,
+    ) -> Result<'s, Self> {
+        let importer = DefaultImporter::with_stdio(Discard, Discard);
+        let mut machine = Machine::instantiate(spectest, importer)
+            .map_err(|err| Error::run_error(RunKind::Trapped(*err), source, 0))?;
+        machine.register("spectest", machine.latest_instance());
+        Ok(Instances {
+            machine,
+            instances: vec![],
             idx_to_mod,
             source,
-        }
+        })
     }
 
-    fn new_machine(
-        &self,
-        m: &'m ast::Module<'s>,
-        pos: usize,
-    ) -> Result<'s, MachineForTest<'m, 's>> {
-        let importer = DefaultImporter::with_stdio(Discard, Discard);
-        let machine = Machine::instantiate(m, importer)
-            .map_err(|err| Error::run_error(RunKind::Trapped(*err), self.source, pos))?;
-        Ok(machine)
+    fn instantiate(&mut self, module: &'m ast::Module<'s>, pos: usize) -> Result<'s, InstanceId> {
+        self.machine
+            .instantiate_module(module)
+            .map_err(|err| Error::run_error(RunKind::Trapped(*err), self.source, pos))
     }
 
     fn push_with_idx(&mut self, directive_idx: usize) -> Result<'s, ()> {
@@ -267,43 +365,33 @@ impl<'m, 's> Instances<'m, 's> {
     }
 
     fn push(&mut self, module: &'m ast::Module<'s>, pos: usize) -> Result<'s, ()> {
-        let machine = self.new_machine(module, pos)?;
-        self.machines.push((machine, pos));
+        let instance = self.instantiate(module, pos)?;
+        self.instances.push((instance, pos));
         Ok(())
     }
 
-    fn find(
-        &mut self,
-        id: Option<&'s str>,
-        pos: usize,
-    ) -> Result<'s, (&mut MachineForTest<'m, 's>, usize)> {
+    fn find(&self, id: Option<&'s str>, pos: usize) -> Result<'s, (InstanceId, usize)> {
         let searched = if let Some(id) = id {
-            self.machines
-                .iter_mut()
+            self.instances
+                .iter()
                 .rev()
-                .find(|(m, _)| m.module().id == Some(id))
+                .find(|(i, _)| self.machine.instance_module(*i).id == Some(id))
         } else {
-            self.machines.last_mut()
+            self.instances.last()
         };
 
-        // Note: ok_or_else is not available due to nested borrow of `self`
-        if let Some((m, mod_pos)) = searched {
-            Ok((m, *mod_pos))
-        } else {
-            Err(Error::run_error(
-                RunKind::ModuleNotFound(id),
-                self.source,
-                pos,
-            ))
-        }
+        searched
+            .copied()
+            .ok_or_else(|| Error::run_error(RunKind::ModuleNotFound(id), self.source, pos))
     }
 
-    fn invoke(&mut self, invoke: &wast::Invoke<'s>) -> Result<'s, Option<Value>> {
-        let (machine, mod_pos) = self.find(invoke.id, invoke.start)?;
+    fn invoke(&mut self, invoke: &wast::Invoke<'s>) -> Result<'s, Vec<Value>> {
+        let (instance, mod_pos) = self.find(invoke.id, invoke.start)?;
 
         let args: Box<[Value]> = invoke.args.iter().map(|c| c.to_value().unwrap()).collect();
-        let ret = machine
-            .invoke(&invoke.name, &args)
+        let ret = self
+            .machine
+            .invoke_instance(instance, &invoke.name, &args)
             .map_err(|err| Error::run_error(RunKind::Trapped(*err), self.source, mod_pos))?;
 
         Ok(ret)
@@ -374,7 +462,7 @@ impl<'a> Tester<'a> {
         mods
     }
 
-    fn test(&mut self, crasher: &CrashTester) {
+    fn test(&mut self, spectest: &ast::Module<'a>) {
         // Parse and validate modules at first
         let idx_to_mod = self.parse_embedded_modules();
 
@@ -389,9 +477,15 @@ impl<'a> Tester<'a> {
             return;
         }
 
-        let mut instances = Instances::new(&idx_to_mod, self.source);
+        let mut instances = match Instances::new(&idx_to_mod, spectest, self.source) {
+            Ok(instances) => instances,
+            Err(err) => {
+                self.check::<()>(Err(err));
+                return;
+            }
+        };
         for (idx, directive) in self.root.directives.iter().enumerate() {
-            let result = self.test_directive(idx, directive, &mut instances, crasher);
+            let result = self.test_directive(idx, directive, &mut instances);
             self.check(result);
         }
     }
@@ -399,9 +493,8 @@ impl<'a> Tester<'a> {
     fn test_directive<'m>(
         &self,
         idx: usize,
-        directive: &'a wast::Directive<'a>,
+        directive: &'m wast::Directive<'a>,
         instances: &mut Instances<'m, 'a>,
-        crasher: &CrashTester,
     ) -> Result<'a, ()> {
         use wast::Directive::*;
         match directive {
@@ -412,15 +505,21 @@ impl<'a> Tester<'a> {
                 invoke,
                 expected,
             }) => {
-                let ret = instances.invoke(invoke)?;
-                if let (Some(expected), Some(actual)) = (*expected, ret) {
-                    if !expected.matches(&actual) {
-                        return Err(Error::run_error(
-                            RunKind::InvokeUnexpectedReturn { actual, expected },
-                            self.source,
-                            *start,
-                        ));
-                    }
+                let actual = instances.invoke(invoke)?;
+                if actual.len() != expected.len()
+                    || expected
+                        .iter()
+                        .zip(actual.iter())
+                        .any(|(e, a)| !e.matches(a))
+                {
+                    return Err(Error::run_error(
+                        RunKind::InvokeUnexpectedReturn {
+                            actual,
+                            expected: expected.clone(),
+                        },
+                        self.source,
+                        *start,
+                    ));
                 }
                 Ok(())
             }
@@ -429,15 +528,15 @@ impl<'a> Tester<'a> {
                 get,
                 expected,
             }) => {
-                let (machine, _) = instances.find(get.id, *start)?;
-                if let Some(actual) = machine.get_global(&get.name) {
+                let (instance, _) = instances.find(get.id, *start)?;
+                if let Some(actual) = instances.machine.instance_global(instance, &get.name) {
                     if expected.matches(&actual) {
                         Ok(())
                     } else {
                         Err(Error::run_error(
                             RunKind::InvokeUnexpectedReturn {
-                                actual,
-                                expected: *expected,
+                                actual: vec![actual],
+                                expected: vec![*expected],
                             },
                             self.source,
                             *start,
@@ -465,16 +564,15 @@ impl<'a> Tester<'a> {
                         self.source,
                         *start,
                     )),
-                    Err(err) if matches!(err.kind(), ErrorKind::Run(RunKind::Trapped(_trap))) => {
+                    Err(err) => match err.kind() {
                         // Expected path. Execution was trapped
-                        //
-                        // TODO: Check trap reason is what we expected.
-                        // `expected` is an expected error message as string but we don't conform
-                        // the message. So we need to have logic for mapping from expected message
-                        // to our error.
-                        Ok(())
-                    }
-                    Err(err) => Err(err),
+                        ErrorKind::Run(RunKind::Trapped(trap))
+                            if error_matches(&trap.reason, expected) =>
+                        {
+                            Ok(())
+                        }
+                        _ => Err(err),
+                    },
                 }
             }
             AssertTrap(wast::AssertTrap {
@@ -483,26 +581,29 @@ impl<'a> Tester<'a> {
                 pred: wast::TrapPredicate::Module(root),
             }) => {
                 validate(root)?;
-                let importer = DefaultImporter::with_stdio(Discard, Discard);
-                let mut machine = Machine::instantiate(&root.module, importer).map_err(|err| {
-                    Error::run_error(RunKind::Trapped(*err), self.source, root.module.start)
-                })?;
-                match machine.execute() {
+                // Out-of-bounds element or data segment traps while instantiating the module
+                let result = instances
+                    .machine
+                    .instantiate_module(&root.module)
+                    .and_then(|instance| instances.machine.execute_instance(instance));
+                match result {
                     Ok(_) => Err(Error::run_error(
                         RunKind::InvokeTrapExpected {
-                            ret: None,
+                            ret: vec![],
+                            expected: expected.clone(),
+                        },
+                        self.source,
+                        *start,
+                    )),
+                    Err(trap) if error_matches(&trap.reason, expected) => Ok(()),
+                    Err(trap) => Err(Error::run_error(
+                        RunKind::UnexpectedError {
+                            actual: *trap,
                             expected: expected.clone(),
                         },
                         self.source,
                         *start,
                     )),
-                    Err(_trap) => {
-                        // TODO: Check trap reason is what we expected.
-                        // `expected` is an expected error message as string but we don't conform
-                        // the message. So we need to have logic for mapping from expected message
-                        // to our error.
-                        Ok(())
-                    }
                 }
             }
             AssertInvalid(wast::AssertInvalid {
@@ -568,19 +669,67 @@ impl<'a> Tester<'a> {
                 start,
                 expected,
                 invoke,
-            }) => {
-                let (_, mod_pos) = instances.find(invoke.id, invoke.start)?;
-                let stderr =
-                    crasher.test_crash(self.source, mod_pos, &invoke.name, &invoke.args)?;
-                match expected.as_str() {
-                    "call stack exhausted"
-                        if stderr.contains("fatal runtime error: stack overflow") =>
+            }) => match instances.invoke(invoke) {
+                Ok(ret) => Err(Error::run_error(
+                    RunKind::InvokeTrapExpected {
+                        ret,
+                        expected: expected.clone(),
+                    },
+                    self.source,
+                    *start,
+                )),
+                Err(err) => match err.kind() {
+                    ErrorKind::Run(RunKind::Trapped(trap))
+                        if matches!(trap.reason, TrapReason::StackExhausted { .. }) =>
                     {
                         Ok(())
                     }
-                    _ => Err(Error::run_error(
-                        RunKind::UnexpectedCrash {
-                            stderr,
+                    _ => Err(err),
+                },
+            },
+            AssertException(wast::AssertException { start, invoke }) => {
+                match instances.invoke(invoke) {
+                    Ok(ret) => Err(Error::run_error(
+                        RunKind::InvokeTrapExpected {
+                            ret,
+                            expected: "uncaught exception".to_string(),
+                        },
+                        self.source,
+                        *start,
+                    )),
+                    Err(err) => match err.kind() {
+                        ErrorKind::Run(RunKind::Trapped(trap))
+                            if matches!(trap.reason, TrapReason::UncaughtException { .. }) =>
+                        {
+                            Ok(())
+                        }
+                        _ => Err(err),
+                    },
+                }
+            }
+            Register(wast::Register { start, name, id }) => {
+                let (instance, _) = instances.find(*id, *start)?;
+                instances.machine.register(name.clone(), instance);
+                Ok(())
+            }
+            AssertUnlinkable(wast::AssertUnlinkable {
+                start,
+                wat,
+                expected,
+            }) => {
+                validate(wat)?;
+                match instances.machine.instantiate_module(&wat.module) {
+                    Ok(_) => Err(Error::run_error(
+                        RunKind::UnexpectedLinked {
+                            expected: expected.clone(),
+                        },
+                        self.source,
+                        *start,
+                    )),
+                    Err(err) if error_matches(&err.reason, expected) => Ok(()),
+                    Err(err) => Err(Error::run_error(
+                        RunKind::UnexpectedError {
+                            actual: *err,
                             expected: expected.clone(),
                         },
                         self.source,
@@ -588,12 +737,6 @@ impl<'a> Tester<'a> {
                     )),
                 }
             }
-            Register(wast::Register { start, .. })
-            | AssertUnlinkable(wast::AssertUnlinkable { start, .. }) => Err(Error::run_error(
-                RunKind::NotImplementedYet,
-                self.source,
-                *start,
-            )),
             Invoke(invoke) => instances.invoke(invoke).map(|_| ()),
         }
     }
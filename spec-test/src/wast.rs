@@ -28,6 +28,60 @@ pub enum Const {
     CanonicalNan,
     // nan:arithmetic
     ArithmeticNan,
+    // (ref.null func) or (ref.null extern)
+    RefNull(ast::RefType),
+    // (ref.extern {num})
+    RefExtern(u32),
+    // (ref.func) Any non-null function reference in results
+    RefFunc,
+    // (v128.const {shape} {lane}*)
+    V128(i128),
+    // (v128.const f32x4 {lane}*) and (v128.const f64x2 {lane}*) whose lanes may be NaN patterns
+    F32x4([FloatLane; 4]),
+    F64x2([FloatLane; 2]),
+}
+
+// Lane of v128 constant in float shapes
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum FloatLane {
+    Bits(u64),
+    // nan:canonical
+    CanonicalNan,
+    // nan:arithmetic
+    ArithmeticNan,
+}
+
+fn float_lanes_match(lanes: &[FloatLane], v: &Value) -> bool {
+    let v = match v {
+        Value::V128(v) => *v as u128,
+        _ => return false,
+    };
+    let (width, inf) = if lanes.len() == 4 {
+        (32, f32::INFINITY.to_bits() as u64)
+    } else {
+        (64, f64::INFINITY.to_bits())
+    };
+    let mask = u64::MAX >> (64 - width);
+    lanes.iter().enumerate().all(|(i, lane)| {
+        let bits = (v >> (i * width)) as u64 & mask;
+        match lane {
+            FloatLane::Bits(b) => bits == *b,
+            // Exponent is all ones and fraction is not zero. Sign bit is ignored
+            // TODO: Check payload for arithmetic NaN
+            FloatLane::CanonicalNan | FloatLane::ArithmeticNan => bits & (mask >> 1) > inf,
+        }
+    })
+}
+
+fn float_lanes_to_v128(lanes: &[FloatLane]) -> Option<i128> {
+    let width = 128 / lanes.len();
+    lanes
+        .iter()
+        .enumerate()
+        .try_fold(0, |acc, (i, lane)| match lane {
+            FloatLane::Bits(b) => Some(acc | (*b as i128) << (i * width)),
+            FloatLane::CanonicalNan | FloatLane::ArithmeticNan => None,
+        })
 }
 
 impl Const {
@@ -40,7 +94,12 @@ impl Const {
             F64(l) if l.is_nan() => {
                 matches!(v, Value::F64(r) if r.is_nan() && l.to_bits() == r.to_bits())
             }
-            I32(_) | I64(_) | F32(_) | F64(_) => &self.to_value().unwrap() == v,
+            I32(_) | I64(_) | F32(_) | F64(_) | V128(_) | RefNull(_) | RefExtern(_) => {
+                &self.to_value().unwrap() == v
+            }
+            RefFunc => matches!(v, Value::FuncRef(Some(_))),
+            F32x4(lanes) => float_lanes_match(&lanes, v),
+            F64x2(lanes) => float_lanes_match(&lanes, v),
             // TODO: Check payload for arithmetic NaN
             CanonicalNan | ArithmeticNan => match v {
                 Value::F32(f) => f.is_nan(),
@@ -57,6 +116,12 @@ impl Const {
             I64(i) => Some(Value::I64(i)),
             F32(f) => Some(Value::F32(f)),
             F64(f) => Some(Value::F64(f)),
+            V128(v) => Some(Value::V128(v)),
+            F32x4(lanes) => float_lanes_to_v128(&lanes).map(Value::V128),
+            F64x2(lanes) => float_lanes_to_v128(&lanes).map(Value::V128),
+            RefNull(ast::RefType::FuncRef) => Some(Value::FuncRef(None)),
+            RefNull(ast::RefType::ExternRef) => Some(Value::ExternRef(None)),
+            RefExtern(i) => Some(Value::ExternRef(Some(i))),
             _ => None,
         }
     }
@@ -76,6 +141,7 @@ pub enum Directive<'source> {
     AssertInvalid(AssertInvalid<'source>),
     AssertUnlinkable(AssertUnlinkable<'source>),
     AssertExhaustion(AssertExhaustion<'source>),
+    AssertException(AssertException<'source>),
     Register(Register<'source>),
     Invoke(Invoke<'source>),
     EmbeddedModule(EmbeddedModule),
@@ -91,6 +157,7 @@ impl<'s> Directive<'s> {
             Directive::AssertInvalid(a) => a.start,
             Directive::AssertUnlinkable(a) => a.start,
             Directive::AssertExhaustion(a) => a.start,
+            Directive::AssertException(a) => a.start,
             Directive::Register(r) => r.start,
             Directive::Invoke(i) => i.start,
             Directive::EmbeddedModule(m) => m.start,
@@ -121,13 +188,13 @@ pub struct GetGlobal<'source> {
     pub name: String,
 }
 
-// (assert_return (invoke {name} {constant}*) {constant}?)
+// (assert_return (invoke {name} {constant}*) {constant}*)
 // (assert_return (get {id}? {name}) {constant})
 pub enum AssertReturn<'source> {
     Invoke {
         start: usize,
         invoke: Invoke<'source>,
-        expected: Option<Const>,
+        expected: Vec<Const>,
     },
     Global {
         start: usize,
@@ -138,6 +205,8 @@ pub enum AssertReturn<'source> {
 
 // (assert_trap (invoke {name} {constant}*) {string})
 // (assert_trap (module ...) {string})
+// Module variant makes this enum large. Allow it since this code is used only in tests
+#[allow(clippy::large_enum_variant)]
 pub enum TrapPredicate<'source> {
     Invoke(Invoke<'source>),
     Module(ast::Root<'source, TextSource<'source>>),
@@ -178,3 +247,9 @@ pub struct AssertExhaustion<'source> {
     pub invoke: Invoke<'source>,
     pub expected: String,
 }
+
+// (assert_exception (invoke {name} {constant}*))
+pub struct AssertException<'source> {
+    pub start: usize,
+    pub invoke: Invoke<'source>,
+}
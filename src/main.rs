@@ -180,7 +180,9 @@ fn main() {
         Input::Text(text) => run_text(text),
     };
 
-    if let wain_exec::Run::Warning(msg) = result {
-        eprintln!("Warning: {}", msg);
+    match result {
+        wain_exec::Run::Success => {}
+        wain_exec::Run::Warning(msg) => eprintln!("Warning: {}", msg),
+        wain_exec::Run::Exit(status) => exit(status),
     }
 }
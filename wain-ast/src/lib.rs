@@ -22,6 +22,11 @@ pub type GlobalIdx = u32;
 pub type TypeIdx = u32;
 pub type LocalIdx = u32;
 pub type LabelIdx = u32;
+pub type ElemIdx = u32;
+pub type DataIdx = u32;
+pub type LaneIdx = u8;
+// https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md
+pub type TagIdx = u32;
 
 // https://webassembly.github.io/spec/core/syntax/modules.html
 pub struct Module<'s> {
@@ -35,6 +40,7 @@ pub struct Module<'s> {
     pub data: Vec<DataSegment<'s>>,
     pub memories: Vec<Memory<'s>>,
     pub globals: Vec<Global<'s>>,
+    pub tags: Vec<Tag<'s>>,
     pub entrypoint: Option<StartFunction>,
 }
 
@@ -58,6 +64,9 @@ pub enum ValType {
     I64,
     F32,
     F64,
+    V128,
+    FuncRef,
+    ExternRef,
 }
 impl ValType {
     pub fn bytes(self: ValType) -> usize {
@@ -66,8 +75,13 @@ impl ValType {
             ValType::I64 => 8,
             ValType::F32 => 4,
             ValType::F64 => 8,
+            ValType::V128 => 16,
+            ValType::FuncRef | ValType::ExternRef => 8,
         }
     }
+    pub fn is_ref(self: ValType) -> bool {
+        matches!(self, ValType::FuncRef | ValType::ExternRef)
+    }
 }
 impl AsRef<str> for ValType {
     fn as_ref(&self) -> &'_ str {
@@ -76,6 +90,9 @@ impl AsRef<str> for ValType {
             ValType::I64 => "i64",
             ValType::F32 => "f32",
             ValType::F64 => "f64",
+            ValType::V128 => "v128",
+            ValType::FuncRef => "funcref",
+            ValType::ExternRef => "externref",
         }
     }
 }
@@ -99,6 +116,29 @@ impl AsValType for f32 {
 impl AsValType for f64 {
     const VAL_TYPE: ValType = ValType::F64;
 }
+impl AsValType for i128 {
+    const VAL_TYPE: ValType = ValType::V128;
+}
+
+// https://webassembly.github.io/spec/core/syntax/types.html#reference-types
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum RefType {
+    FuncRef,
+    ExternRef,
+}
+impl From<RefType> for ValType {
+    fn from(ty: RefType) -> Self {
+        match ty {
+            RefType::FuncRef => ValType::FuncRef,
+            RefType::ExternRef => ValType::ExternRef,
+        }
+    }
+}
+impl fmt::Display for RefType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        ValType::from(*self).fmt(f)
+    }
+}
 
 // https://webassembly.github.io/spec/core/syntax/values.html#syntax-name
 //
@@ -109,20 +149,26 @@ impl AsValType for f64 {
 pub struct Name<'s>(pub Cow<'s, str>);
 
 // https://webassembly.github.io/spec/core/syntax/types.html#table-types
-// Note: elemtype is currently fixed to 'funcref'
 pub struct TableType {
+    pub elem: RefType,
     pub limit: Limits,
 }
 
 // https://webassembly.github.io/spec/core/syntax/types.html#limits
+// Limits are u64 for 64-bit memories. Limits of tables and 32-bit memories are within u32
 pub enum Limits {
-    Range(u32, u32),
-    From(u32),
+    Range(u64, u64),
+    From(u64),
 }
 
 // https://webassembly.github.io/spec/core/syntax/types.html#memory-types
 pub struct MemType {
     pub limit: Limits,
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#shared-linear-memory
+    pub shared: bool,
+    // https://github.com/WebAssembly/memory64/blob/main/proposals/memory64/Overview.md
+    // Memory is addressed with i64 instead of i32
+    pub memory64: bool,
 }
 
 // https://webassembly.github.io/spec/core/syntax/modules.html#exports
@@ -131,6 +177,7 @@ pub enum ExportKind {
     Table(TableIdx),
     Memory(MemIdx),
     Global(GlobalIdx),
+    Tag(TagIdx),
 }
 pub struct Export<'s> {
     pub start: usize,
@@ -160,8 +207,49 @@ pub struct Instruction {
 
 // https://webassembly.github.io/spec/core/syntax/instructions.html#syntax-memarg
 pub struct Mem {
-    pub align: Option<u8>, // TODO: Change this to Option<u32>
-    pub offset: Option<u32>,
+    pub align: Option<u8>,   // TODO: Change this to Option<u32>
+    pub offset: Option<u64>, // Offset can be larger than u32 only for 64-bit memories
+    pub memory: MemIdx,
+}
+
+// https://webassembly.github.io/spec/core/syntax/instructions.html#syntax-blocktype
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum BlockType {
+    Empty,          // [] -> []
+    Value(ValType), // [] -> [t]
+    Type(TypeIdx),  // Function type [t1*] -> [t2*] defined in module
+}
+impl BlockType {
+    // Types of values consumed by the block. Type index must have been validated
+    pub fn params<'a>(&'a self, types: &'a [FuncType]) -> &'a [ValType] {
+        match self {
+            BlockType::Empty | BlockType::Value(_) => &[],
+            BlockType::Type(idx) => &types[*idx as usize].params,
+        }
+    }
+    // Types of values produced by the block. Type index must have been validated
+    pub fn results<'a>(&'a self, types: &'a [FuncType]) -> &'a [ValType] {
+        match self {
+            BlockType::Empty => &[],
+            BlockType::Value(ty) => std::slice::from_ref(ty),
+            BlockType::Type(idx) => &types[*idx as usize].results,
+        }
+    }
+}
+
+// Handlers following 'try' block
+// https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#try-catch-blocks
+pub enum TryHandler {
+    Catch {
+        catches: Vec<Catch>,
+        catch_all: Option<Vec<Instruction>>,
+    },
+    Delegate(LabelIdx),
+}
+pub struct Catch {
+    pub start: usize,
+    pub tag: TagIdx,
+    pub body: Vec<Instruction>,
 }
 
 // https://webassembly.github.io/spec/core/syntax/instructions.html#instructions
@@ -169,15 +257,15 @@ pub enum InsnKind {
     // Control instructions
     // https://webassembly.github.io/spec/core/syntax/instructions.html#control-instructions
     Block {
-        ty: Option<ValType>, // resulttype
+        ty: BlockType,
         body: Vec<Instruction>,
     },
     Loop {
-        ty: Option<ValType>, // resulttype
+        ty: BlockType,
         body: Vec<Instruction>,
     },
     If {
-        ty: Option<ValType>, // resulttype
+        ty: BlockType,
         then_body: Vec<Instruction>,
         else_body: Vec<Instruction>,
     },
@@ -191,11 +279,36 @@ pub enum InsnKind {
     },
     Return,
     Call(FuncIdx),
-    CallIndirect(TypeIdx),
+    CallIndirect {
+        table: TableIdx,
+        ty: TypeIdx,
+    },
+    // Tail calls
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#control-instructions
+    ReturnCall(FuncIdx),
+    ReturnCallIndirect {
+        table: TableIdx,
+        ty: TypeIdx,
+    },
+    // Exception handling
+    // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md
+    Try {
+        ty: BlockType,
+        body: Vec<Instruction>,
+        handler: TryHandler,
+    },
+    Throw(TagIdx),
+    Rethrow(LabelIdx),
+    // Reference instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#reference-instructions
+    RefNull(RefType),
+    RefIsNull,
+    RefFunc(FuncIdx),
     // Parametric instructions
     // https://webassembly.github.io/spec/core/syntax/instructions.html#parametric-instructions
     Drop,
     Select,
+    TypedSelect(Vec<ValType>),
     // Variable instructions
     // https://webassembly.github.io/spec/core/syntax/instructions.html#variable-instructions
     LocalGet(LocalIdx),
@@ -228,8 +341,34 @@ pub enum InsnKind {
     I64Store8(Mem),
     I64Store16(Mem),
     I64Store32(Mem),
-    MemorySize,
-    MemoryGrow,
+    MemorySize(MemIdx),
+    MemoryGrow(MemIdx),
+    MemoryInit {
+        memory: MemIdx,
+        data: DataIdx,
+    },
+    DataDrop(DataIdx),
+    MemoryCopy {
+        dst: MemIdx,
+        src: MemIdx,
+    },
+    MemoryFill(MemIdx),
+    // Table instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#table-instructions
+    TableGet(TableIdx),
+    TableSet(TableIdx),
+    TableSize(TableIdx),
+    TableGrow(TableIdx),
+    TableFill(TableIdx),
+    TableCopy {
+        dst: TableIdx,
+        src: TableIdx,
+    },
+    TableInit {
+        table: TableIdx,
+        elem: ElemIdx,
+    },
+    ElemDrop(ElemIdx),
     // Numeric instructions
     // https://webassembly.github.io/spec/core/syntax/instructions.html#numeric-instructions
     // Constants
@@ -369,6 +508,352 @@ pub enum InsnKind {
     I64ReinterpretF64,
     F32ReinterpretI32,
     F64ReinterpretI64,
+    // Sign extension
+    I32Extend8S,
+    I32Extend16S,
+    I64Extend8S,
+    I64Extend16S,
+    I64Extend32S,
+    // Saturating truncation
+    I32TruncSatF32S,
+    I32TruncSatF32U,
+    I32TruncSatF64S,
+    I32TruncSatF64U,
+    I64TruncSatF32S,
+    I64TruncSatF32U,
+    I64TruncSatF64S,
+    I64TruncSatF64U,
+    // Vector instructions
+    // https://webassembly.github.io/spec/core/syntax/instructions.html#vector-instructions
+    V128Load(Mem),
+    V128Load8x8S(Mem),
+    V128Load8x8U(Mem),
+    V128Load16x4S(Mem),
+    V128Load16x4U(Mem),
+    V128Load32x2S(Mem),
+    V128Load32x2U(Mem),
+    V128Load8Splat(Mem),
+    V128Load16Splat(Mem),
+    V128Load32Splat(Mem),
+    V128Load64Splat(Mem),
+    V128Store(Mem),
+    V128Const(i128),
+    I8x16Shuffle([LaneIdx; 16]),
+    I8x16Swizzle,
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+    I8x16ExtractLaneS(LaneIdx),
+    I8x16ExtractLaneU(LaneIdx),
+    I8x16ReplaceLane(LaneIdx),
+    I16x8ExtractLaneS(LaneIdx),
+    I16x8ExtractLaneU(LaneIdx),
+    I16x8ReplaceLane(LaneIdx),
+    I32x4ExtractLane(LaneIdx),
+    I32x4ReplaceLane(LaneIdx),
+    I64x2ExtractLane(LaneIdx),
+    I64x2ReplaceLane(LaneIdx),
+    F32x4ExtractLane(LaneIdx),
+    F32x4ReplaceLane(LaneIdx),
+    F64x2ExtractLane(LaneIdx),
+    F64x2ReplaceLane(LaneIdx),
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    V128Not,
+    V128And,
+    V128Andnot,
+    V128Or,
+    V128Xor,
+    V128Bitselect,
+    V128AnyTrue,
+    V128Load8Lane {
+        mem: Mem,
+        lane: LaneIdx,
+    },
+    V128Load16Lane {
+        mem: Mem,
+        lane: LaneIdx,
+    },
+    V128Load32Lane {
+        mem: Mem,
+        lane: LaneIdx,
+    },
+    V128Load64Lane {
+        mem: Mem,
+        lane: LaneIdx,
+    },
+    V128Store8Lane {
+        mem: Mem,
+        lane: LaneIdx,
+    },
+    V128Store16Lane {
+        mem: Mem,
+        lane: LaneIdx,
+    },
+    V128Store32Lane {
+        mem: Mem,
+        lane: LaneIdx,
+    },
+    V128Store64Lane {
+        mem: Mem,
+        lane: LaneIdx,
+    },
+    V128Load32Zero(Mem),
+    V128Load64Zero(Mem),
+    F32x4DemoteF64x2Zero,
+    F64x2PromoteLowF32x4,
+    I8x16Abs,
+    I8x16Neg,
+    I8x16Popcnt,
+    I8x16AllTrue,
+    I8x16Bitmask,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    F64x2Ceil,
+    F64x2Floor,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    F64x2Trunc,
+    I8x16AvgrU,
+    I16x8ExtaddPairwiseI8x16S,
+    I16x8ExtaddPairwiseI8x16U,
+    I32x4ExtaddPairwiseI16x8S,
+    I32x4ExtaddPairwiseI16x8U,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8Q15mulrSatS,
+    I16x8AllTrue,
+    I16x8Bitmask,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8ExtendLowI8x16S,
+    I16x8ExtendHighI8x16S,
+    I16x8ExtendLowI8x16U,
+    I16x8ExtendHighI8x16U,
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    F64x2Nearest,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8AvgrU,
+    I16x8ExtmulLowI8x16S,
+    I16x8ExtmulHighI8x16S,
+    I16x8ExtmulLowI8x16U,
+    I16x8ExtmulHighI8x16U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4AllTrue,
+    I32x4Bitmask,
+    I32x4ExtendLowI16x8S,
+    I32x4ExtendHighI16x8S,
+    I32x4ExtendLowI16x8U,
+    I32x4ExtendHighI16x8U,
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtmulLowI16x8S,
+    I32x4ExtmulHighI16x8S,
+    I32x4ExtmulLowI16x8U,
+    I32x4ExtmulHighI16x8U,
+    I64x2Abs,
+    I64x2Neg,
+    I64x2AllTrue,
+    I64x2Bitmask,
+    I64x2ExtendLowI32x4S,
+    I64x2ExtendHighI32x4S,
+    I64x2ExtendLowI32x4U,
+    I64x2ExtendHighI32x4U,
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+    I64x2Add,
+    I64x2Sub,
+    I64x2Mul,
+    I64x2Eq,
+    I64x2Ne,
+    I64x2LtS,
+    I64x2GtS,
+    I64x2LeS,
+    I64x2GeS,
+    I64x2ExtmulLowI32x4S,
+    I64x2ExtmulHighI32x4S,
+    I64x2ExtmulLowI32x4U,
+    I64x2ExtmulHighI32x4U,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4Pmin,
+    F32x4Pmax,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2Pmin,
+    F64x2Pmax,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+    I32x4TruncSatF64x2SZero,
+    I32x4TruncSatF64x2UZero,
+    F64x2ConvertLowI32x4S,
+    F64x2ConvertLowI32x4U,
+    // Atomic instructions
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md
+    MemoryAtomicNotify(Mem),
+    MemoryAtomicWait32(Mem),
+    MemoryAtomicWait64(Mem),
+    AtomicFence,
+    I32AtomicLoad(Mem),
+    I64AtomicLoad(Mem),
+    I32AtomicLoad8U(Mem),
+    I32AtomicLoad16U(Mem),
+    I64AtomicLoad8U(Mem),
+    I64AtomicLoad16U(Mem),
+    I64AtomicLoad32U(Mem),
+    I32AtomicStore(Mem),
+    I64AtomicStore(Mem),
+    I32AtomicStore8(Mem),
+    I32AtomicStore16(Mem),
+    I64AtomicStore8(Mem),
+    I64AtomicStore16(Mem),
+    I64AtomicStore32(Mem),
+    I32AtomicRmwAdd(Mem),
+    I64AtomicRmwAdd(Mem),
+    I32AtomicRmw8AddU(Mem),
+    I32AtomicRmw16AddU(Mem),
+    I64AtomicRmw8AddU(Mem),
+    I64AtomicRmw16AddU(Mem),
+    I64AtomicRmw32AddU(Mem),
+    I32AtomicRmwSub(Mem),
+    I64AtomicRmwSub(Mem),
+    I32AtomicRmw8SubU(Mem),
+    I32AtomicRmw16SubU(Mem),
+    I64AtomicRmw8SubU(Mem),
+    I64AtomicRmw16SubU(Mem),
+    I64AtomicRmw32SubU(Mem),
+    I32AtomicRmwAnd(Mem),
+    I64AtomicRmwAnd(Mem),
+    I32AtomicRmw8AndU(Mem),
+    I32AtomicRmw16AndU(Mem),
+    I64AtomicRmw8AndU(Mem),
+    I64AtomicRmw16AndU(Mem),
+    I64AtomicRmw32AndU(Mem),
+    I32AtomicRmwOr(Mem),
+    I64AtomicRmwOr(Mem),
+    I32AtomicRmw8OrU(Mem),
+    I32AtomicRmw16OrU(Mem),
+    I64AtomicRmw8OrU(Mem),
+    I64AtomicRmw16OrU(Mem),
+    I64AtomicRmw32OrU(Mem),
+    I32AtomicRmwXor(Mem),
+    I64AtomicRmwXor(Mem),
+    I32AtomicRmw8XorU(Mem),
+    I32AtomicRmw16XorU(Mem),
+    I64AtomicRmw8XorU(Mem),
+    I64AtomicRmw16XorU(Mem),
+    I64AtomicRmw32XorU(Mem),
+    I32AtomicRmwXchg(Mem),
+    I64AtomicRmwXchg(Mem),
+    I32AtomicRmw8XchgU(Mem),
+    I32AtomicRmw16XchgU(Mem),
+    I64AtomicRmw8XchgU(Mem),
+    I64AtomicRmw16XchgU(Mem),
+    I64AtomicRmw32XchgU(Mem),
+    I32AtomicRmwCmpxchg(Mem),
+    I64AtomicRmwCmpxchg(Mem),
+    I32AtomicRmw8CmpxchgU(Mem),
+    I32AtomicRmw16CmpxchgU(Mem),
+    I64AtomicRmw8CmpxchgU(Mem),
+    I64AtomicRmw16CmpxchgU(Mem),
+    I64AtomicRmw32CmpxchgU(Mem),
 }
 impl InsnKind {
     pub fn name(&self) -> &'static str {
@@ -384,9 +869,17 @@ impl InsnKind {
             BrTable { .. } => "br_table",
             Return => "return",
             Call(_) => "call",
-            CallIndirect(_) => "call_indirect",
+            CallIndirect { .. } => "call_indirect",
+            ReturnCall(_) => "return_call",
+            ReturnCallIndirect { .. } => "return_call_indirect",
+            Try { .. } => "try",
+            Throw(_) => "throw",
+            Rethrow(_) => "rethrow",
+            RefNull(_) => "ref.null",
+            RefIsNull => "ref.is_null",
+            RefFunc(_) => "ref.func",
             Drop => "drop",
-            Select => "select",
+            Select | TypedSelect(_) => "select",
             LocalGet(_) => "local.get",
             LocalSet(_) => "local.set",
             LocalTee(_) => "local.tee",
@@ -415,8 +908,20 @@ impl InsnKind {
             I64Store8(_) => "i64.store8",
             I64Store16(_) => "i64.store16",
             I64Store32(_) => "i64.store32",
-            MemorySize => "memory.size",
-            MemoryGrow => "memory.grow",
+            MemorySize(_) => "memory.size",
+            MemoryGrow(_) => "memory.grow",
+            MemoryInit { .. } => "memory.init",
+            DataDrop(_) => "data.drop",
+            MemoryCopy { .. } => "memory.copy",
+            MemoryFill(_) => "memory.fill",
+            TableGet(_) => "table.get",
+            TableSet(_) => "table.set",
+            TableSize(_) => "table.size",
+            TableGrow(_) => "table.grow",
+            TableFill(_) => "table.fill",
+            TableCopy { .. } => "table.copy",
+            TableInit { .. } => "table.init",
+            ElemDrop(_) => "elem.drop",
             I32Const(_) => "i32.const",
             I64Const(_) => "i64.const",
             F32Const(_) => "f32.const",
@@ -549,16 +1054,340 @@ impl InsnKind {
             I64ReinterpretF64 => "i64.reinterpret_f64",
             F32ReinterpretI32 => "f32.reinterpret_i32",
             F64ReinterpretI64 => "f64.reinterpret_i64",
+            I32Extend8S => "i32.extend8_s",
+            I32Extend16S => "i32.extend16_s",
+            I64Extend8S => "i64.extend8_s",
+            I64Extend16S => "i64.extend16_s",
+            I64Extend32S => "i64.extend32_s",
+            I32TruncSatF32S => "i32.trunc_sat_f32_s",
+            I32TruncSatF32U => "i32.trunc_sat_f32_u",
+            I32TruncSatF64S => "i32.trunc_sat_f64_s",
+            I32TruncSatF64U => "i32.trunc_sat_f64_u",
+            I64TruncSatF32S => "i64.trunc_sat_f32_s",
+            I64TruncSatF32U => "i64.trunc_sat_f32_u",
+            I64TruncSatF64S => "i64.trunc_sat_f64_s",
+            I64TruncSatF64U => "i64.trunc_sat_f64_u",
+            V128Load(_) => "v128.load",
+            V128Load8x8S(_) => "v128.load8x8_s",
+            V128Load8x8U(_) => "v128.load8x8_u",
+            V128Load16x4S(_) => "v128.load16x4_s",
+            V128Load16x4U(_) => "v128.load16x4_u",
+            V128Load32x2S(_) => "v128.load32x2_s",
+            V128Load32x2U(_) => "v128.load32x2_u",
+            V128Load8Splat(_) => "v128.load8_splat",
+            V128Load16Splat(_) => "v128.load16_splat",
+            V128Load32Splat(_) => "v128.load32_splat",
+            V128Load64Splat(_) => "v128.load64_splat",
+            V128Store(_) => "v128.store",
+            V128Const(_) => "v128.const",
+            I8x16Shuffle(_) => "i8x16.shuffle",
+            I8x16Swizzle => "i8x16.swizzle",
+            I8x16Splat => "i8x16.splat",
+            I16x8Splat => "i16x8.splat",
+            I32x4Splat => "i32x4.splat",
+            I64x2Splat => "i64x2.splat",
+            F32x4Splat => "f32x4.splat",
+            F64x2Splat => "f64x2.splat",
+            I8x16ExtractLaneS(_) => "i8x16.extract_lane_s",
+            I8x16ExtractLaneU(_) => "i8x16.extract_lane_u",
+            I8x16ReplaceLane(_) => "i8x16.replace_lane",
+            I16x8ExtractLaneS(_) => "i16x8.extract_lane_s",
+            I16x8ExtractLaneU(_) => "i16x8.extract_lane_u",
+            I16x8ReplaceLane(_) => "i16x8.replace_lane",
+            I32x4ExtractLane(_) => "i32x4.extract_lane",
+            I32x4ReplaceLane(_) => "i32x4.replace_lane",
+            I64x2ExtractLane(_) => "i64x2.extract_lane",
+            I64x2ReplaceLane(_) => "i64x2.replace_lane",
+            F32x4ExtractLane(_) => "f32x4.extract_lane",
+            F32x4ReplaceLane(_) => "f32x4.replace_lane",
+            F64x2ExtractLane(_) => "f64x2.extract_lane",
+            F64x2ReplaceLane(_) => "f64x2.replace_lane",
+            I8x16Eq => "i8x16.eq",
+            I8x16Ne => "i8x16.ne",
+            I8x16LtS => "i8x16.lt_s",
+            I8x16LtU => "i8x16.lt_u",
+            I8x16GtS => "i8x16.gt_s",
+            I8x16GtU => "i8x16.gt_u",
+            I8x16LeS => "i8x16.le_s",
+            I8x16LeU => "i8x16.le_u",
+            I8x16GeS => "i8x16.ge_s",
+            I8x16GeU => "i8x16.ge_u",
+            I16x8Eq => "i16x8.eq",
+            I16x8Ne => "i16x8.ne",
+            I16x8LtS => "i16x8.lt_s",
+            I16x8LtU => "i16x8.lt_u",
+            I16x8GtS => "i16x8.gt_s",
+            I16x8GtU => "i16x8.gt_u",
+            I16x8LeS => "i16x8.le_s",
+            I16x8LeU => "i16x8.le_u",
+            I16x8GeS => "i16x8.ge_s",
+            I16x8GeU => "i16x8.ge_u",
+            I32x4Eq => "i32x4.eq",
+            I32x4Ne => "i32x4.ne",
+            I32x4LtS => "i32x4.lt_s",
+            I32x4LtU => "i32x4.lt_u",
+            I32x4GtS => "i32x4.gt_s",
+            I32x4GtU => "i32x4.gt_u",
+            I32x4LeS => "i32x4.le_s",
+            I32x4LeU => "i32x4.le_u",
+            I32x4GeS => "i32x4.ge_s",
+            I32x4GeU => "i32x4.ge_u",
+            F32x4Eq => "f32x4.eq",
+            F32x4Ne => "f32x4.ne",
+            F32x4Lt => "f32x4.lt",
+            F32x4Gt => "f32x4.gt",
+            F32x4Le => "f32x4.le",
+            F32x4Ge => "f32x4.ge",
+            F64x2Eq => "f64x2.eq",
+            F64x2Ne => "f64x2.ne",
+            F64x2Lt => "f64x2.lt",
+            F64x2Gt => "f64x2.gt",
+            F64x2Le => "f64x2.le",
+            F64x2Ge => "f64x2.ge",
+            V128Not => "v128.not",
+            V128And => "v128.and",
+            V128Andnot => "v128.andnot",
+            V128Or => "v128.or",
+            V128Xor => "v128.xor",
+            V128Bitselect => "v128.bitselect",
+            V128AnyTrue => "v128.any_true",
+            V128Load8Lane { .. } => "v128.load8_lane",
+            V128Load16Lane { .. } => "v128.load16_lane",
+            V128Load32Lane { .. } => "v128.load32_lane",
+            V128Load64Lane { .. } => "v128.load64_lane",
+            V128Store8Lane { .. } => "v128.store8_lane",
+            V128Store16Lane { .. } => "v128.store16_lane",
+            V128Store32Lane { .. } => "v128.store32_lane",
+            V128Store64Lane { .. } => "v128.store64_lane",
+            V128Load32Zero(_) => "v128.load32_zero",
+            V128Load64Zero(_) => "v128.load64_zero",
+            F32x4DemoteF64x2Zero => "f32x4.demote_f64x2_zero",
+            F64x2PromoteLowF32x4 => "f64x2.promote_low_f32x4",
+            I8x16Abs => "i8x16.abs",
+            I8x16Neg => "i8x16.neg",
+            I8x16Popcnt => "i8x16.popcnt",
+            I8x16AllTrue => "i8x16.all_true",
+            I8x16Bitmask => "i8x16.bitmask",
+            I8x16NarrowI16x8S => "i8x16.narrow_i16x8_s",
+            I8x16NarrowI16x8U => "i8x16.narrow_i16x8_u",
+            F32x4Ceil => "f32x4.ceil",
+            F32x4Floor => "f32x4.floor",
+            F32x4Trunc => "f32x4.trunc",
+            F32x4Nearest => "f32x4.nearest",
+            I8x16Shl => "i8x16.shl",
+            I8x16ShrS => "i8x16.shr_s",
+            I8x16ShrU => "i8x16.shr_u",
+            I8x16Add => "i8x16.add",
+            I8x16AddSatS => "i8x16.add_sat_s",
+            I8x16AddSatU => "i8x16.add_sat_u",
+            I8x16Sub => "i8x16.sub",
+            I8x16SubSatS => "i8x16.sub_sat_s",
+            I8x16SubSatU => "i8x16.sub_sat_u",
+            F64x2Ceil => "f64x2.ceil",
+            F64x2Floor => "f64x2.floor",
+            I8x16MinS => "i8x16.min_s",
+            I8x16MinU => "i8x16.min_u",
+            I8x16MaxS => "i8x16.max_s",
+            I8x16MaxU => "i8x16.max_u",
+            F64x2Trunc => "f64x2.trunc",
+            I8x16AvgrU => "i8x16.avgr_u",
+            I16x8ExtaddPairwiseI8x16S => "i16x8.extadd_pairwise_i8x16_s",
+            I16x8ExtaddPairwiseI8x16U => "i16x8.extadd_pairwise_i8x16_u",
+            I32x4ExtaddPairwiseI16x8S => "i32x4.extadd_pairwise_i16x8_s",
+            I32x4ExtaddPairwiseI16x8U => "i32x4.extadd_pairwise_i16x8_u",
+            I16x8Abs => "i16x8.abs",
+            I16x8Neg => "i16x8.neg",
+            I16x8Q15mulrSatS => "i16x8.q15mulr_sat_s",
+            I16x8AllTrue => "i16x8.all_true",
+            I16x8Bitmask => "i16x8.bitmask",
+            I16x8NarrowI32x4S => "i16x8.narrow_i32x4_s",
+            I16x8NarrowI32x4U => "i16x8.narrow_i32x4_u",
+            I16x8ExtendLowI8x16S => "i16x8.extend_low_i8x16_s",
+            I16x8ExtendHighI8x16S => "i16x8.extend_high_i8x16_s",
+            I16x8ExtendLowI8x16U => "i16x8.extend_low_i8x16_u",
+            I16x8ExtendHighI8x16U => "i16x8.extend_high_i8x16_u",
+            I16x8Shl => "i16x8.shl",
+            I16x8ShrS => "i16x8.shr_s",
+            I16x8ShrU => "i16x8.shr_u",
+            I16x8Add => "i16x8.add",
+            I16x8AddSatS => "i16x8.add_sat_s",
+            I16x8AddSatU => "i16x8.add_sat_u",
+            I16x8Sub => "i16x8.sub",
+            I16x8SubSatS => "i16x8.sub_sat_s",
+            I16x8SubSatU => "i16x8.sub_sat_u",
+            F64x2Nearest => "f64x2.nearest",
+            I16x8Mul => "i16x8.mul",
+            I16x8MinS => "i16x8.min_s",
+            I16x8MinU => "i16x8.min_u",
+            I16x8MaxS => "i16x8.max_s",
+            I16x8MaxU => "i16x8.max_u",
+            I16x8AvgrU => "i16x8.avgr_u",
+            I16x8ExtmulLowI8x16S => "i16x8.extmul_low_i8x16_s",
+            I16x8ExtmulHighI8x16S => "i16x8.extmul_high_i8x16_s",
+            I16x8ExtmulLowI8x16U => "i16x8.extmul_low_i8x16_u",
+            I16x8ExtmulHighI8x16U => "i16x8.extmul_high_i8x16_u",
+            I32x4Abs => "i32x4.abs",
+            I32x4Neg => "i32x4.neg",
+            I32x4AllTrue => "i32x4.all_true",
+            I32x4Bitmask => "i32x4.bitmask",
+            I32x4ExtendLowI16x8S => "i32x4.extend_low_i16x8_s",
+            I32x4ExtendHighI16x8S => "i32x4.extend_high_i16x8_s",
+            I32x4ExtendLowI16x8U => "i32x4.extend_low_i16x8_u",
+            I32x4ExtendHighI16x8U => "i32x4.extend_high_i16x8_u",
+            I32x4Shl => "i32x4.shl",
+            I32x4ShrS => "i32x4.shr_s",
+            I32x4ShrU => "i32x4.shr_u",
+            I32x4Add => "i32x4.add",
+            I32x4Sub => "i32x4.sub",
+            I32x4Mul => "i32x4.mul",
+            I32x4MinS => "i32x4.min_s",
+            I32x4MinU => "i32x4.min_u",
+            I32x4MaxS => "i32x4.max_s",
+            I32x4MaxU => "i32x4.max_u",
+            I32x4DotI16x8S => "i32x4.dot_i16x8_s",
+            I32x4ExtmulLowI16x8S => "i32x4.extmul_low_i16x8_s",
+            I32x4ExtmulHighI16x8S => "i32x4.extmul_high_i16x8_s",
+            I32x4ExtmulLowI16x8U => "i32x4.extmul_low_i16x8_u",
+            I32x4ExtmulHighI16x8U => "i32x4.extmul_high_i16x8_u",
+            I64x2Abs => "i64x2.abs",
+            I64x2Neg => "i64x2.neg",
+            I64x2AllTrue => "i64x2.all_true",
+            I64x2Bitmask => "i64x2.bitmask",
+            I64x2ExtendLowI32x4S => "i64x2.extend_low_i32x4_s",
+            I64x2ExtendHighI32x4S => "i64x2.extend_high_i32x4_s",
+            I64x2ExtendLowI32x4U => "i64x2.extend_low_i32x4_u",
+            I64x2ExtendHighI32x4U => "i64x2.extend_high_i32x4_u",
+            I64x2Shl => "i64x2.shl",
+            I64x2ShrS => "i64x2.shr_s",
+            I64x2ShrU => "i64x2.shr_u",
+            I64x2Add => "i64x2.add",
+            I64x2Sub => "i64x2.sub",
+            I64x2Mul => "i64x2.mul",
+            I64x2Eq => "i64x2.eq",
+            I64x2Ne => "i64x2.ne",
+            I64x2LtS => "i64x2.lt_s",
+            I64x2GtS => "i64x2.gt_s",
+            I64x2LeS => "i64x2.le_s",
+            I64x2GeS => "i64x2.ge_s",
+            I64x2ExtmulLowI32x4S => "i64x2.extmul_low_i32x4_s",
+            I64x2ExtmulHighI32x4S => "i64x2.extmul_high_i32x4_s",
+            I64x2ExtmulLowI32x4U => "i64x2.extmul_low_i32x4_u",
+            I64x2ExtmulHighI32x4U => "i64x2.extmul_high_i32x4_u",
+            F32x4Abs => "f32x4.abs",
+            F32x4Neg => "f32x4.neg",
+            F32x4Sqrt => "f32x4.sqrt",
+            F32x4Add => "f32x4.add",
+            F32x4Sub => "f32x4.sub",
+            F32x4Mul => "f32x4.mul",
+            F32x4Div => "f32x4.div",
+            F32x4Min => "f32x4.min",
+            F32x4Max => "f32x4.max",
+            F32x4Pmin => "f32x4.pmin",
+            F32x4Pmax => "f32x4.pmax",
+            F64x2Abs => "f64x2.abs",
+            F64x2Neg => "f64x2.neg",
+            F64x2Sqrt => "f64x2.sqrt",
+            F64x2Add => "f64x2.add",
+            F64x2Sub => "f64x2.sub",
+            F64x2Mul => "f64x2.mul",
+            F64x2Div => "f64x2.div",
+            F64x2Min => "f64x2.min",
+            F64x2Max => "f64x2.max",
+            F64x2Pmin => "f64x2.pmin",
+            F64x2Pmax => "f64x2.pmax",
+            I32x4TruncSatF32x4S => "i32x4.trunc_sat_f32x4_s",
+            I32x4TruncSatF32x4U => "i32x4.trunc_sat_f32x4_u",
+            F32x4ConvertI32x4S => "f32x4.convert_i32x4_s",
+            F32x4ConvertI32x4U => "f32x4.convert_i32x4_u",
+            I32x4TruncSatF64x2SZero => "i32x4.trunc_sat_f64x2_s_zero",
+            I32x4TruncSatF64x2UZero => "i32x4.trunc_sat_f64x2_u_zero",
+            F64x2ConvertLowI32x4S => "f64x2.convert_low_i32x4_s",
+            F64x2ConvertLowI32x4U => "f64x2.convert_low_i32x4_u",
+            MemoryAtomicNotify(_) => "memory.atomic.notify",
+            MemoryAtomicWait32(_) => "memory.atomic.wait32",
+            MemoryAtomicWait64(_) => "memory.atomic.wait64",
+            AtomicFence => "atomic.fence",
+            I32AtomicLoad(_) => "i32.atomic.load",
+            I64AtomicLoad(_) => "i64.atomic.load",
+            I32AtomicLoad8U(_) => "i32.atomic.load8_u",
+            I32AtomicLoad16U(_) => "i32.atomic.load16_u",
+            I64AtomicLoad8U(_) => "i64.atomic.load8_u",
+            I64AtomicLoad16U(_) => "i64.atomic.load16_u",
+            I64AtomicLoad32U(_) => "i64.atomic.load32_u",
+            I32AtomicStore(_) => "i32.atomic.store",
+            I64AtomicStore(_) => "i64.atomic.store",
+            I32AtomicStore8(_) => "i32.atomic.store8",
+            I32AtomicStore16(_) => "i32.atomic.store16",
+            I64AtomicStore8(_) => "i64.atomic.store8",
+            I64AtomicStore16(_) => "i64.atomic.store16",
+            I64AtomicStore32(_) => "i64.atomic.store32",
+            I32AtomicRmwAdd(_) => "i32.atomic.rmw.add",
+            I64AtomicRmwAdd(_) => "i64.atomic.rmw.add",
+            I32AtomicRmw8AddU(_) => "i32.atomic.rmw8.add_u",
+            I32AtomicRmw16AddU(_) => "i32.atomic.rmw16.add_u",
+            I64AtomicRmw8AddU(_) => "i64.atomic.rmw8.add_u",
+            I64AtomicRmw16AddU(_) => "i64.atomic.rmw16.add_u",
+            I64AtomicRmw32AddU(_) => "i64.atomic.rmw32.add_u",
+            I32AtomicRmwSub(_) => "i32.atomic.rmw.sub",
+            I64AtomicRmwSub(_) => "i64.atomic.rmw.sub",
+            I32AtomicRmw8SubU(_) => "i32.atomic.rmw8.sub_u",
+            I32AtomicRmw16SubU(_) => "i32.atomic.rmw16.sub_u",
+            I64AtomicRmw8SubU(_) => "i64.atomic.rmw8.sub_u",
+            I64AtomicRmw16SubU(_) => "i64.atomic.rmw16.sub_u",
+            I64AtomicRmw32SubU(_) => "i64.atomic.rmw32.sub_u",
+            I32AtomicRmwAnd(_) => "i32.atomic.rmw.and",
+            I64AtomicRmwAnd(_) => "i64.atomic.rmw.and",
+            I32AtomicRmw8AndU(_) => "i32.atomic.rmw8.and_u",
+            I32AtomicRmw16AndU(_) => "i32.atomic.rmw16.and_u",
+            I64AtomicRmw8AndU(_) => "i64.atomic.rmw8.and_u",
+            I64AtomicRmw16AndU(_) => "i64.atomic.rmw16.and_u",
+            I64AtomicRmw32AndU(_) => "i64.atomic.rmw32.and_u",
+            I32AtomicRmwOr(_) => "i32.atomic.rmw.or",
+            I64AtomicRmwOr(_) => "i64.atomic.rmw.or",
+            I32AtomicRmw8OrU(_) => "i32.atomic.rmw8.or_u",
+            I32AtomicRmw16OrU(_) => "i32.atomic.rmw16.or_u",
+            I64AtomicRmw8OrU(_) => "i64.atomic.rmw8.or_u",
+            I64AtomicRmw16OrU(_) => "i64.atomic.rmw16.or_u",
+            I64AtomicRmw32OrU(_) => "i64.atomic.rmw32.or_u",
+            I32AtomicRmwXor(_) => "i32.atomic.rmw.xor",
+            I64AtomicRmwXor(_) => "i64.atomic.rmw.xor",
+            I32AtomicRmw8XorU(_) => "i32.atomic.rmw8.xor_u",
+            I32AtomicRmw16XorU(_) => "i32.atomic.rmw16.xor_u",
+            I64AtomicRmw8XorU(_) => "i64.atomic.rmw8.xor_u",
+            I64AtomicRmw16XorU(_) => "i64.atomic.rmw16.xor_u",
+            I64AtomicRmw32XorU(_) => "i64.atomic.rmw32.xor_u",
+            I32AtomicRmwXchg(_) => "i32.atomic.rmw.xchg",
+            I64AtomicRmwXchg(_) => "i64.atomic.rmw.xchg",
+            I32AtomicRmw8XchgU(_) => "i32.atomic.rmw8.xchg_u",
+            I32AtomicRmw16XchgU(_) => "i32.atomic.rmw16.xchg_u",
+            I64AtomicRmw8XchgU(_) => "i64.atomic.rmw8.xchg_u",
+            I64AtomicRmw16XchgU(_) => "i64.atomic.rmw16.xchg_u",
+            I64AtomicRmw32XchgU(_) => "i64.atomic.rmw32.xchg_u",
+            I32AtomicRmwCmpxchg(_) => "i32.atomic.rmw.cmpxchg",
+            I64AtomicRmwCmpxchg(_) => "i64.atomic.rmw.cmpxchg",
+            I32AtomicRmw8CmpxchgU(_) => "i32.atomic.rmw8.cmpxchg_u",
+            I32AtomicRmw16CmpxchgU(_) => "i32.atomic.rmw16.cmpxchg_u",
+            I64AtomicRmw8CmpxchgU(_) => "i64.atomic.rmw8.cmpxchg_u",
+            I64AtomicRmw16CmpxchgU(_) => "i64.atomic.rmw16.cmpxchg_u",
+            I64AtomicRmw32CmpxchgU(_) => "i64.atomic.rmw32.cmpxchg_u",
         }
     }
 }
 
 // https://webassembly.github.io/spec/core/syntax/modules.html#element-segments
+pub enum ElemMode {
+    Passive,
+    Active {
+        idx: TableIdx,
+        offset: Vec<Instruction>, // expr
+    },
+    Declarative,
+}
 pub struct ElemSegment {
     pub start: usize,
-    pub idx: TableIdx,
-    pub offset: Vec<Instruction>, // expr
-    pub init: Vec<FuncIdx>,
+    pub ty: RefType,
+    pub mode: ElemMode,
+    pub init: Vec<Vec<Instruction>>, // vec(expr)
 }
 
 // https://webassembly.github.io/spec/core/syntax/modules.html#tables
@@ -569,10 +1398,16 @@ pub struct Table<'s> {
 }
 
 // https://webassembly.github.io/spec/core/syntax/modules.html#data-segments
+pub enum DataMode {
+    Passive,
+    Active {
+        idx: MemIdx,
+        offset: Vec<Instruction>, // expr
+    },
+}
 pub struct DataSegment<'s> {
     pub start: usize,
-    pub idx: MemIdx,
-    pub offset: Vec<Instruction>, // expr
+    pub mode: DataMode,
     pub data: Cow<'s, [u8]>,
 }
 
@@ -596,6 +1431,13 @@ pub struct Global<'s> {
     pub kind: GlobalKind<'s>,
 }
 
+// https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#tag-definitions
+pub struct Tag<'s> {
+    pub start: usize,
+    pub idx: TypeIdx, // Function type with no results
+    pub import: Option<Import<'s>>,
+}
+
 // https://webassembly.github.io/spec/core/syntax/modules.html#start-function
 pub struct StartFunction {
     pub start: usize,
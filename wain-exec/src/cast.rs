@@ -1,6 +1,11 @@
+use crate::trap::TrapReason;
+
 // Float to int conversion. When the float value cannot be represented as target integer type,
 // it causes undefined behavior. This is a bug of rustc. We need to handle it until the bug is fixed.
 //   https://github.com/rust-lang/rust/issues/10184
+//
+// Conversions in this section saturate the value. They are used for trunc_sat instructions.
+// https://webassembly.github.io/spec/core/exec/numerics.html#op-trunc-sat-u
 
 // >= and <= are always available for comparing max/min values. Thanks @kariya-mitsuru for discussion
 // https://mobile.twitter.com/Linda_pp/status/1245587716537909250
@@ -31,6 +36,37 @@ float_to_int!(f32_to_i64, f32, i64);
 float_to_int!(f64_to_i32, f64, i32);
 float_to_int!(f64_to_i64, f64, i64);
 
+// Trapping conversions for trunc instructions. NaN and values which are out of range of the integer
+// type after truncation cannot be converted.
+// https://webassembly.github.io/spec/core/exec/numerics.html#op-trunc-u
+macro_rules! checked_float_to_int {
+    ($name:ident, $float:ty, $int:ty) => {
+        pub fn $name(f: $float) -> Result<$int, TrapReason> {
+            // Both bounds are zero or power of two so they are exactly represented as float
+            const MIN: $float = <$int>::MIN as $float;
+            const END: $float = (<$int>::MAX / 2 + 1) as $float * 2.0;
+            if f.is_nan() {
+                return Err(TrapReason::InvalidConversionToInt);
+            }
+            let t = f.trunc();
+            if (MIN..END).contains(&t) {
+                Ok(t as $int)
+            } else {
+                Err(TrapReason::IntOverflow)
+            }
+        }
+    };
+}
+
+checked_float_to_int!(checked_f32_to_u32, f32, u32);
+checked_float_to_int!(checked_f32_to_u64, f32, u64);
+checked_float_to_int!(checked_f64_to_u32, f64, u32);
+checked_float_to_int!(checked_f64_to_u64, f64, u64);
+checked_float_to_int!(checked_f32_to_i32, f32, i32);
+checked_float_to_int!(checked_f32_to_i64, f32, i64);
+checked_float_to_int!(checked_f64_to_i32, f64, i32);
+checked_float_to_int!(checked_f64_to_i64, f64, i64);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,4 +157,105 @@ mod tests {
         assert_eq!(f64_to_i64(i64::MAX as f64 + 10000.0), i64::MAX);
         assert_eq!(f64_to_i64(f64::NAN), 0);
     }
+
+    #[test]
+    fn checked_float_to_uint() {
+        assert_eq!(checked_f32_to_u32(0.0).ok(), Some(0));
+        assert_eq!(checked_f32_to_u32(-0.9).ok(), Some(0));
+        assert_eq!(checked_f32_to_u32(4294967040.0).ok(), Some(4294967040));
+        assert!(matches!(
+            checked_f32_to_u32(-1.0),
+            Err(TrapReason::IntOverflow)
+        ));
+        assert!(matches!(
+            checked_f32_to_u32(4294967296.0),
+            Err(TrapReason::IntOverflow)
+        ));
+        assert!(matches!(
+            checked_f32_to_u32(f32::NAN),
+            Err(TrapReason::InvalidConversionToInt)
+        ));
+
+        assert_eq!(checked_f64_to_u32(4294967295.9).ok(), Some(u32::MAX));
+        assert!(matches!(
+            checked_f64_to_u32(4294967296.0),
+            Err(TrapReason::IntOverflow)
+        ));
+        assert!(matches!(
+            checked_f64_to_u32(f64::INFINITY),
+            Err(TrapReason::IntOverflow)
+        ));
+
+        assert_eq!(
+            checked_f32_to_u64(18446742974197923840.0).ok(),
+            Some(18446742974197923840)
+        );
+        assert!(matches!(
+            checked_f32_to_u64(18446744073709551616.0),
+            Err(TrapReason::IntOverflow)
+        ));
+        assert_eq!(
+            checked_f64_to_u64(18446744073709549568.0).ok(),
+            Some(18446744073709549568)
+        );
+        assert!(matches!(
+            checked_f64_to_u64(18446744073709551616.0),
+            Err(TrapReason::IntOverflow)
+        ));
+        assert!(matches!(
+            checked_f64_to_u64(-1.0),
+            Err(TrapReason::IntOverflow)
+        ));
+    }
+
+    #[test]
+    fn checked_float_to_int() {
+        assert_eq!(checked_f32_to_i32(-1.9).ok(), Some(-1));
+        assert_eq!(checked_f32_to_i32(-2147483648.0).ok(), Some(i32::MIN));
+        assert_eq!(checked_f32_to_i32(2147483520.0).ok(), Some(2147483520));
+        assert!(matches!(
+            checked_f32_to_i32(2147483648.0),
+            Err(TrapReason::IntOverflow)
+        ));
+        assert!(matches!(
+            checked_f32_to_i32(-2147483904.0),
+            Err(TrapReason::IntOverflow)
+        ));
+
+        assert_eq!(checked_f64_to_i32(2147483647.9).ok(), Some(i32::MAX));
+        assert_eq!(checked_f64_to_i32(-2147483648.9).ok(), Some(i32::MIN));
+        assert!(matches!(
+            checked_f64_to_i32(2147483648.0),
+            Err(TrapReason::IntOverflow)
+        ));
+        assert!(matches!(
+            checked_f64_to_i32(-2147483649.0),
+            Err(TrapReason::IntOverflow)
+        ));
+        assert!(matches!(
+            checked_f64_to_i32(f64::NAN),
+            Err(TrapReason::InvalidConversionToInt)
+        ));
+
+        assert_eq!(
+            checked_f32_to_i64(-9223372036854775808.0).ok(),
+            Some(i64::MIN)
+        );
+        assert!(matches!(
+            checked_f32_to_i64(9223372036854775808.0),
+            Err(TrapReason::IntOverflow)
+        ));
+        assert_eq!(
+            checked_f64_to_i64(9223372036854774784.0).ok(),
+            Some(9223372036854774784)
+        );
+        assert!(matches!(
+            checked_f64_to_i64(9223372036854775808.0),
+            Err(TrapReason::IntOverflow)
+        ));
+        assert!(matches!(
+            checked_f64_to_i64(f64::NEG_INFINITY),
+            Err(TrapReason::IntOverflow)
+        ));
+    }
 }
@@ -0,0 +1,946 @@
+use wain_ast as ast;
+use wain_ast::{InsnKind, ValType};
+
+// Function body is compiled into flat instruction sequence before execution. Structured control
+// instructions are lowered into jumps with resolved targets so that interpreter does not need to
+// manage labels at runtime.
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-br
+// Breaking to a label unwinds values on stack to the height at entering the label. Values as many
+// as the arity of the label are carried over the unwinding
+pub(crate) struct Branch {
+    pub(crate) target: usize,
+    // Number of values on stack relative to the base of call frame. Locals are included
+    pub(crate) height: usize,
+    pub(crate) keep: usize,
+}
+
+pub(crate) enum Op<'m> {
+    // Non-control instruction executed as it is
+    Exec(&'m ast::Instruction),
+    // Jump when the popped condition is zero. Compiled from 'if' instruction
+    If(&'m ast::Instruction, usize),
+    // Unconditional jump inserted at the end of 'then' clause of 'if' instruction
+    Jump(usize),
+    Br(&'m ast::Instruction, Branch),
+    BrIf(&'m ast::Instruction, Branch),
+    // The last branch is the default label
+    BrTable(&'m ast::Instruction, Box<[Branch]>),
+    Return(&'m ast::Instruction),
+    Call(&'m ast::Instruction, u32),
+    CallIndirect(&'m ast::Instruction, u32, u32),
+    // Tail calls replace the frame of the current function with the callee's frame
+    ReturnCall(&'m ast::Instruction, u32),
+    ReturnCallIndirect(&'m ast::Instruction, u32, u32),
+    Throw(&'m ast::Instruction, u32),
+    // Rethrow the exception caught by the 'catch' clause. The index of the caught exception is
+    // stored in the slot at the height relative to the base of call frame
+    Rethrow(&'m ast::Instruction, usize),
+    // Unwinding branch inserted at the end of 'catch' clause to remove the caught exception
+    EndCatch(Branch),
+}
+
+impl<'m> Op<'m> {
+    // Original instruction. Jump and end of catch clause do not have it since it was inserted by compiler
+    pub(crate) fn insn(&self) -> Option<&'m ast::Instruction> {
+        match self {
+            Op::Exec(insn)
+            | Op::If(insn, _)
+            | Op::Br(insn, _)
+            | Op::BrIf(insn, _)
+            | Op::BrTable(insn, _)
+            | Op::Return(insn)
+            | Op::Call(insn, _)
+            | Op::CallIndirect(insn, _, _)
+            | Op::ReturnCall(insn, _)
+            | Op::ReturnCallIndirect(insn, _, _)
+            | Op::Throw(insn, _)
+            | Op::Rethrow(insn, _) => Some(insn),
+            Op::Jump(_) | Op::EndCatch(_) => None,
+        }
+    }
+}
+
+// https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md
+// Exceptions thrown while executing the body of 'try' instruction are handled by its handler. The
+// body is compiled into ops in range start..end. Handlers of a function are ordered by their start
+// positions so that the innermost handler covering a position is found last
+pub(crate) struct Handler {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) kind: HandlerKind,
+}
+
+pub(crate) enum HandlerKind {
+    // Stack is unwound to the height and the index of the caught exception is pushed to a hidden
+    // slot, followed by the payload when the tag is matched
+    Catch {
+        height: usize,
+        catches: Box<[(u32, usize)]>, // (tag index, start of 'catch' clause)
+        catch_all: Option<usize>,
+    },
+    // Index of the handler the exception is delegated to. None means the caller of the function
+    Delegate(Option<usize>),
+}
+
+pub(crate) struct Compiled<'m> {
+    pub(crate) code: Box<[Op<'m>]>,
+    pub(crate) handlers: Box<[Handler]>,
+}
+
+struct Label {
+    // Height excluding parameters of the block
+    height: usize,
+    params: usize,
+    results: usize,
+    // Number of handlers enclosing the label. Exceptions delegated to the label are handled by the
+    // innermost one
+    handlers: usize,
+    // Loop jumps back to the start of its body. Other labels jump forward to their ends, which are
+    // not known until compiling the whole body. Positions of such branches are remembered to patch
+    // their targets later
+    loop_start: Option<usize>,
+    patches: Vec<(usize, usize)>, // (index of op, index of branch in br_table)
+}
+
+struct Compiler<'m, 's> {
+    module: &'m ast::Module<'s>,
+    height: usize, // Number of values on stack to calculate heights of labels
+    labels: Vec<Label>,
+    code: Vec<Op<'m>>,
+    handlers: Vec<Handler>,
+    active: Vec<usize>, // Indices of handlers whose 'try' bodies are being compiled
+}
+
+// Compile function body. The function must have been validated
+pub(crate) fn compile<'m, 's>(
+    module: &'m ast::Module<'s>,
+    fty: &ast::FuncType,
+    locals: &[ValType],
+    body: &'m [ast::Instruction],
+) -> Compiled<'m> {
+    let mut compiler = Compiler {
+        module,
+        height: fty.params.len() + locals.len(),
+        labels: vec![],
+        code: vec![],
+        handlers: vec![],
+        active: vec![],
+    };
+
+    // Breaking to the label of function body means returning from the function
+    compiler.enter_label(0, fty.results.len(), None);
+    compiler.compile_seq(body);
+    compiler.exit_label();
+
+    Compiled {
+        code: compiler.code.into_boxed_slice(),
+        handlers: compiler.handlers.into_boxed_slice(),
+    }
+}
+
+impl<'m, 's> Compiler<'m, 's> {
+    fn pop(&mut self) {
+        self.height -= 1;
+    }
+
+    fn enter_label(&mut self, params: usize, results: usize, loop_start: Option<usize>) {
+        self.labels.push(Label {
+            height: self.height - params,
+            params,
+            results,
+            handlers: self.active.len(),
+            loop_start,
+            patches: vec![],
+        });
+    }
+
+    // Pop the label and resolve branches to the end of the label. Stack may be polymorphic after
+    // unconditional branch so it is reset with the result type of the label
+    fn exit_label(&mut self) {
+        let label = self.labels.pop().unwrap();
+        let end = self.code.len();
+        for (pos, idx) in label.patches {
+            match &mut self.code[pos] {
+                Op::Jump(target) => *target = end,
+                Op::Br(_, br) | Op::BrIf(_, br) | Op::EndCatch(br) => br.target = end,
+                Op::BrTable(_, brs) => brs[idx].target = end,
+                _ => unreachable!("not a branch"),
+            }
+        }
+        self.height = label.height + label.results;
+    }
+
+    fn enter_block(&mut self, ty: &ast::BlockType, loop_start: Option<usize>) {
+        let types = &self.module.types;
+        self.enter_label(ty.params(types).len(), ty.results(types).len(), loop_start);
+    }
+
+    fn label_depth(&self, labelidx: u32) -> usize {
+        self.labels.len() - 1 - labelidx as usize
+    }
+
+    fn branch(&mut self, labelidx: u32, pos: usize, idx: usize) -> Branch {
+        let depth = self.label_depth(labelidx);
+        let label = &mut self.labels[depth];
+        if let Some(start) = label.loop_start {
+            // Breaking to label of loop carries its parameters instead of results
+            Branch {
+                target: start,
+                height: label.height,
+                keep: label.params,
+            }
+        } else {
+            label.patches.push((pos, idx));
+            Branch {
+                target: 0, // Patched later
+                height: label.height,
+                keep: label.results,
+            }
+        }
+    }
+
+    // Compile 'catch' or 'catch_all' clause. The caught exception in the hidden slot is removed at
+    // the end of the clause
+    fn compile_catch(&mut self, body: &'m [ast::Instruction], height: usize) -> usize {
+        let start = self.code.len();
+        self.height = height;
+        if self.compile_seq(body) {
+            let br = self.branch(0, self.code.len(), 0);
+            self.code.push(Op::EndCatch(br));
+        }
+        start
+    }
+
+    // Compile instruction sequence. Returns false when the end of sequence is unreachable
+    fn compile_seq(&mut self, insns: &'m [ast::Instruction]) -> bool {
+        for insn in insns {
+            if !self.compile_insn(insn) {
+                // Rest of instructions are never executed
+                return false;
+            }
+        }
+        true
+    }
+
+    // Returns false when the next instruction is unreachable
+    fn compile_insn(&mut self, insn: &'m ast::Instruction) -> bool {
+        use InsnKind::*;
+        match &insn.kind {
+            Block { ty, body } => {
+                self.enter_block(ty, None);
+                self.compile_seq(body);
+                self.exit_label();
+            }
+            Loop { ty, body } => {
+                self.enter_block(ty, Some(self.code.len()));
+                self.compile_seq(body);
+                self.exit_label();
+            }
+            If {
+                ty,
+                then_body,
+                else_body,
+            } => {
+                self.pop(); // Condition
+                let if_pos = self.code.len();
+                self.code.push(Op::If(insn, 0));
+                self.enter_block(ty, None);
+                let height = self.height;
+                if self.compile_seq(then_body) && !else_body.is_empty() {
+                    // Skip 'else' clause at the end of 'then' clause
+                    let pos = self.code.len();
+                    self.code.push(Op::Jump(0));
+                    self.labels.last_mut().unwrap().patches.push((pos, 0));
+                }
+                let else_pos = self.code.len();
+                if let Op::If(_, target) = &mut self.code[if_pos] {
+                    *target = else_pos;
+                }
+                // 'else' clause starts with the same stack as 'then' clause
+                self.height = height;
+                self.compile_seq(else_body);
+                self.exit_label();
+            }
+            Try { ty, body, handler } => {
+                let idx = self.handlers.len();
+                let start = self.code.len();
+                self.handlers.push(Handler {
+                    start,
+                    end: start,
+                    kind: HandlerKind::Delegate(None), // Replaced after compiling the body
+                });
+                self.active.push(idx);
+                self.enter_block(ty, None);
+                let reachable = self.compile_seq(body);
+                self.active.pop();
+                self.handlers[idx].end = self.code.len();
+
+                // Exceptions thrown in 'catch' clauses are not handled by this 'try'
+                let label = self.labels.last_mut().unwrap();
+                label.handlers -= 1;
+                let height = label.height;
+
+                let kind = match handler {
+                    ast::TryHandler::Catch { catches, catch_all } => {
+                        if reachable && (!catches.is_empty() || catch_all.is_some()) {
+                            // Skip clauses at the end of the body
+                            let pos = self.code.len();
+                            self.code.push(Op::Jump(0));
+                            self.labels.last_mut().unwrap().patches.push((pos, 0));
+                        }
+                        let mut clauses = Vec::with_capacity(catches.len());
+                        for catch in catches {
+                            let tag = &self.module.tags[catch.tag as usize];
+                            let params = self.module.types[tag.idx as usize].params.len();
+                            let start = self.compile_catch(&catch.body, height + 1 + params);
+                            clauses.push((catch.tag, start));
+                        }
+                        let catch_all = catch_all
+                            .as_ref()
+                            .map(|body| self.compile_catch(body, height + 1));
+                        self.exit_label();
+                        HandlerKind::Catch {
+                            height,
+                            catches: clauses.into_boxed_slice(),
+                            catch_all,
+                        }
+                    }
+                    ast::TryHandler::Delegate(labelidx) => {
+                        // The label of 'delegate' is resolved outside the 'try' block
+                        self.exit_label();
+                        let label = &self.labels[self.label_depth(*labelidx)];
+                        HandlerKind::Delegate(label.handlers.checked_sub(1).map(|i| self.active[i]))
+                    }
+                };
+                self.handlers[idx].kind = kind;
+            }
+            Throw(tagidx) => {
+                self.code.push(Op::Throw(insn, *tagidx));
+                return false;
+            }
+            Rethrow(labelidx) => {
+                let height = self.labels[self.label_depth(*labelidx)].height;
+                self.code.push(Op::Rethrow(insn, height));
+                return false;
+            }
+            Unreachable => {
+                self.code.push(Op::Exec(insn));
+                return false;
+            }
+            Br(labelidx) => {
+                let br = self.branch(*labelidx, self.code.len(), 0);
+                self.code.push(Op::Br(insn, br));
+                return false;
+            }
+            BrIf(labelidx) => {
+                self.pop(); // Condition
+                let br = self.branch(*labelidx, self.code.len(), 0);
+                self.code.push(Op::BrIf(insn, br));
+            }
+            BrTable {
+                labels,
+                default_label,
+            } => {
+                self.pop(); // Index
+                let pos = self.code.len();
+                let brs = labels
+                    .iter()
+                    .chain(std::iter::once(default_label))
+                    .enumerate()
+                    .map(|(i, l)| self.branch(*l, pos, i))
+                    .collect();
+                self.code.push(Op::BrTable(insn, brs));
+                return false;
+            }
+            Return => {
+                self.code.push(Op::Return(insn));
+                return false;
+            }
+            Call(funcidx) => {
+                let func = &self.module.funcs[*funcidx as usize];
+                let fty = &self.module.types[func.idx as usize];
+                self.height = self.height - fty.params.len() + fty.results.len();
+                self.code.push(Op::Call(insn, *funcidx));
+            }
+            CallIndirect { table, ty } => {
+                let fty = &self.module.types[*ty as usize];
+                self.pop(); // Index of table
+                self.height = self.height - fty.params.len() + fty.results.len();
+                self.code.push(Op::CallIndirect(insn, *table, *ty));
+            }
+            ReturnCall(funcidx) => {
+                self.code.push(Op::ReturnCall(insn, *funcidx));
+                return false;
+            }
+            ReturnCallIndirect { table, ty } => {
+                self.code.push(Op::ReturnCallIndirect(insn, *table, *ty));
+                return false;
+            }
+            kind => {
+                let (pops, pushes) = stack_effect(kind);
+                self.height = self.height - pops + pushes;
+                self.code.push(Op::Exec(insn));
+            }
+        }
+        true
+    }
+}
+
+// Number of values popped from stack and number of values pushed to stack by the instruction
+fn stack_effect(kind: &InsnKind) -> (usize, usize) {
+    use InsnKind::*;
+    match kind {
+        Nop | DataDrop(_) | ElemDrop(_) => (0, 0),
+        Drop | LocalSet(_) | GlobalSet(_) => (1, 0),
+        Select | TypedSelect(_) => (3, 1),
+        LocalGet(_) | GlobalGet(_) | MemorySize(_) | I32Const(_) | I64Const(_) | F32Const(_)
+        | F64Const(_) | RefNull(_) | RefFunc(_) | TableSize(_) => (0, 1),
+        LocalTee(_) | MemoryGrow(_) | RefIsNull | TableGet(_) => (1, 1),
+        TableSet(_) => (2, 0),
+        TableGrow(_) => (2, 1),
+        I32Load(_) | I64Load(_) | F32Load(_) | F64Load(_) | I32Load8S(_) | I32Load8U(_)
+        | I32Load16S(_) | I32Load16U(_) | I64Load8S(_) | I64Load8U(_) | I64Load16S(_)
+        | I64Load16U(_) | I64Load32S(_) | I64Load32U(_) => (1, 1),
+        I32Store(_) | I64Store(_) | F32Store(_) | F64Store(_) | I32Store8(_) | I32Store16(_)
+        | I64Store8(_) | I64Store16(_) | I64Store32(_) => (2, 0),
+        MemoryInit { .. }
+        | MemoryCopy { .. }
+        | MemoryFill(_)
+        | TableInit { .. }
+        | TableCopy { .. }
+        | TableFill(_) => (3, 0),
+        // Unary operators, test operators and conversions
+        I32Clz | I32Ctz | I32Popcnt | I64Clz | I64Ctz | I64Popcnt | F32Abs | F32Neg | F32Ceil
+        | F32Floor | F32Trunc | F32Nearest | F32Sqrt | F64Abs | F64Neg | F64Ceil | F64Floor
+        | F64Trunc | F64Nearest | F64Sqrt | I32Eqz | I64Eqz | I32WrapI64 | I32TruncF32S
+        | I32TruncF32U | I32TruncF64S | I32TruncF64U | I64ExtendI32S | I64ExtendI32U
+        | I64TruncF32S | I64TruncF32U | I64TruncF64S | I64TruncF64U | F32ConvertI32S
+        | F32ConvertI32U | F32ConvertI64S | F32ConvertI64U | F32DemoteF64 | F64ConvertI32S
+        | F64ConvertI32U | F64ConvertI64S | F64ConvertI64U | F64PromoteF32 | I32ReinterpretF32
+        | I64ReinterpretF64 | F32ReinterpretI32 | F64ReinterpretI64 | I32Extend8S
+        | I32Extend16S | I64Extend8S | I64Extend16S | I64Extend32S | I32TruncSatF32S
+        | I32TruncSatF32U | I32TruncSatF64S | I32TruncSatF64U | I64TruncSatF32S
+        | I64TruncSatF32U | I64TruncSatF64S | I64TruncSatF64U => (1, 1),
+        // Binary operators and relational operators
+        I32Add | I32Sub | I32Mul | I32DivS | I32DivU | I32RemS | I32RemU | I32And | I32Or
+        | I32Xor | I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr | I64Add | I64Sub | I64Mul
+        | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or | I64Xor | I64Shl | I64ShrS
+        | I64ShrU | I64Rotl | I64Rotr | F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max
+        | F32Copysign | F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64Copysign
+        | I32Eq | I32Ne | I32LtS | I32LtU | I32GtS | I32GtU | I32LeS | I32LeU | I32GeS | I32GeU
+        | I64Eq | I64Ne | I64LtS | I64LtU | I64GtS | I64GtU | I64LeS | I64LeU | I64GeS | I64GeU
+        | F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge | F64Eq | F64Ne | F64Lt | F64Gt | F64Le
+        | F64Ge => (2, 1),
+        // Vector instructions
+        V128Const(_) => (0, 1),
+        V128Load(_)
+        | V128Load8x8S(_)
+        | V128Load8x8U(_)
+        | V128Load16x4S(_)
+        | V128Load16x4U(_)
+        | V128Load32x2S(_)
+        | V128Load32x2U(_)
+        | V128Load8Splat(_)
+        | V128Load16Splat(_)
+        | V128Load32Splat(_)
+        | V128Load64Splat(_)
+        | I8x16Splat
+        | I16x8Splat
+        | I32x4Splat
+        | I64x2Splat
+        | F32x4Splat
+        | F64x2Splat
+        | I8x16ExtractLaneS(_)
+        | I8x16ExtractLaneU(_)
+        | I16x8ExtractLaneS(_)
+        | I16x8ExtractLaneU(_)
+        | I32x4ExtractLane(_)
+        | I64x2ExtractLane(_)
+        | F32x4ExtractLane(_)
+        | F64x2ExtractLane(_)
+        | V128Not
+        | V128AnyTrue
+        | V128Load32Zero(_)
+        | V128Load64Zero(_)
+        | F32x4DemoteF64x2Zero
+        | F64x2PromoteLowF32x4
+        | I8x16Abs
+        | I8x16Neg
+        | I8x16Popcnt
+        | I8x16AllTrue
+        | I8x16Bitmask
+        | F32x4Ceil
+        | F32x4Floor
+        | F32x4Trunc
+        | F32x4Nearest
+        | F64x2Ceil
+        | F64x2Floor
+        | F64x2Trunc
+        | I16x8ExtaddPairwiseI8x16S
+        | I16x8ExtaddPairwiseI8x16U
+        | I32x4ExtaddPairwiseI16x8S
+        | I32x4ExtaddPairwiseI16x8U
+        | I16x8Abs
+        | I16x8Neg
+        | I16x8AllTrue
+        | I16x8Bitmask
+        | I16x8ExtendLowI8x16S
+        | I16x8ExtendHighI8x16S
+        | I16x8ExtendLowI8x16U
+        | I16x8ExtendHighI8x16U
+        | F64x2Nearest
+        | I32x4Abs
+        | I32x4Neg
+        | I32x4AllTrue
+        | I32x4Bitmask
+        | I32x4ExtendLowI16x8S
+        | I32x4ExtendHighI16x8S
+        | I32x4ExtendLowI16x8U
+        | I32x4ExtendHighI16x8U
+        | I64x2Abs
+        | I64x2Neg
+        | I64x2AllTrue
+        | I64x2Bitmask
+        | I64x2ExtendLowI32x4S
+        | I64x2ExtendHighI32x4S
+        | I64x2ExtendLowI32x4U
+        | I64x2ExtendHighI32x4U
+        | F32x4Abs
+        | F32x4Neg
+        | F32x4Sqrt
+        | F64x2Abs
+        | F64x2Neg
+        | F64x2Sqrt
+        | I32x4TruncSatF32x4S
+        | I32x4TruncSatF32x4U
+        | F32x4ConvertI32x4S
+        | F32x4ConvertI32x4U
+        | I32x4TruncSatF64x2SZero
+        | I32x4TruncSatF64x2UZero
+        | F64x2ConvertLowI32x4S
+        | F64x2ConvertLowI32x4U => (1, 1),
+        V128Store(_)
+        | V128Store8Lane { .. }
+        | V128Store16Lane { .. }
+        | V128Store32Lane { .. }
+        | V128Store64Lane { .. } => (2, 0),
+        I8x16Shuffle(_)
+        | I8x16Swizzle
+        | I8x16ReplaceLane(_)
+        | I16x8ReplaceLane(_)
+        | I32x4ReplaceLane(_)
+        | I64x2ReplaceLane(_)
+        | F32x4ReplaceLane(_)
+        | F64x2ReplaceLane(_)
+        | I8x16Eq
+        | I8x16Ne
+        | I8x16LtS
+        | I8x16LtU
+        | I8x16GtS
+        | I8x16GtU
+        | I8x16LeS
+        | I8x16LeU
+        | I8x16GeS
+        | I8x16GeU
+        | I16x8Eq
+        | I16x8Ne
+        | I16x8LtS
+        | I16x8LtU
+        | I16x8GtS
+        | I16x8GtU
+        | I16x8LeS
+        | I16x8LeU
+        | I16x8GeS
+        | I16x8GeU
+        | I32x4Eq
+        | I32x4Ne
+        | I32x4LtS
+        | I32x4LtU
+        | I32x4GtS
+        | I32x4GtU
+        | I32x4LeS
+        | I32x4LeU
+        | I32x4GeS
+        | I32x4GeU
+        | F32x4Eq
+        | F32x4Ne
+        | F32x4Lt
+        | F32x4Gt
+        | F32x4Le
+        | F32x4Ge
+        | F64x2Eq
+        | F64x2Ne
+        | F64x2Lt
+        | F64x2Gt
+        | F64x2Le
+        | F64x2Ge
+        | V128And
+        | V128Andnot
+        | V128Or
+        | V128Xor
+        | V128Load8Lane { .. }
+        | V128Load16Lane { .. }
+        | V128Load32Lane { .. }
+        | V128Load64Lane { .. }
+        | I8x16NarrowI16x8S
+        | I8x16NarrowI16x8U
+        | I8x16Shl
+        | I8x16ShrS
+        | I8x16ShrU
+        | I8x16Add
+        | I8x16AddSatS
+        | I8x16AddSatU
+        | I8x16Sub
+        | I8x16SubSatS
+        | I8x16SubSatU
+        | I8x16MinS
+        | I8x16MinU
+        | I8x16MaxS
+        | I8x16MaxU
+        | I8x16AvgrU
+        | I16x8Q15mulrSatS
+        | I16x8NarrowI32x4S
+        | I16x8NarrowI32x4U
+        | I16x8Shl
+        | I16x8ShrS
+        | I16x8ShrU
+        | I16x8Add
+        | I16x8AddSatS
+        | I16x8AddSatU
+        | I16x8Sub
+        | I16x8SubSatS
+        | I16x8SubSatU
+        | I16x8Mul
+        | I16x8MinS
+        | I16x8MinU
+        | I16x8MaxS
+        | I16x8MaxU
+        | I16x8AvgrU
+        | I16x8ExtmulLowI8x16S
+        | I16x8ExtmulHighI8x16S
+        | I16x8ExtmulLowI8x16U
+        | I16x8ExtmulHighI8x16U
+        | I32x4Shl
+        | I32x4ShrS
+        | I32x4ShrU
+        | I32x4Add
+        | I32x4Sub
+        | I32x4Mul
+        | I32x4MinS
+        | I32x4MinU
+        | I32x4MaxS
+        | I32x4MaxU
+        | I32x4DotI16x8S
+        | I32x4ExtmulLowI16x8S
+        | I32x4ExtmulHighI16x8S
+        | I32x4ExtmulLowI16x8U
+        | I32x4ExtmulHighI16x8U
+        | I64x2Shl
+        | I64x2ShrS
+        | I64x2ShrU
+        | I64x2Add
+        | I64x2Sub
+        | I64x2Mul
+        | I64x2Eq
+        | I64x2Ne
+        | I64x2LtS
+        | I64x2GtS
+        | I64x2LeS
+        | I64x2GeS
+        | I64x2ExtmulLowI32x4S
+        | I64x2ExtmulHighI32x4S
+        | I64x2ExtmulLowI32x4U
+        | I64x2ExtmulHighI32x4U
+        | F32x4Add
+        | F32x4Sub
+        | F32x4Mul
+        | F32x4Div
+        | F32x4Min
+        | F32x4Max
+        | F32x4Pmin
+        | F32x4Pmax
+        | F64x2Add
+        | F64x2Sub
+        | F64x2Mul
+        | F64x2Div
+        | F64x2Min
+        | F64x2Max
+        | F64x2Pmin
+        | F64x2Pmax => (2, 1),
+        V128Bitselect => (3, 1),
+        // Atomic instructions
+        AtomicFence => (0, 0),
+        I32AtomicLoad(_) | I64AtomicLoad(_) | I32AtomicLoad8U(_) | I32AtomicLoad16U(_)
+        | I64AtomicLoad8U(_) | I64AtomicLoad16U(_) | I64AtomicLoad32U(_) => (1, 1),
+        I32AtomicStore(_) | I64AtomicStore(_) | I32AtomicStore8(_) | I32AtomicStore16(_)
+        | I64AtomicStore8(_) | I64AtomicStore16(_) | I64AtomicStore32(_) => (2, 0),
+        MemoryAtomicNotify(_)
+        | I32AtomicRmwAdd(_)
+        | I64AtomicRmwAdd(_)
+        | I32AtomicRmw8AddU(_)
+        | I32AtomicRmw16AddU(_)
+        | I64AtomicRmw8AddU(_)
+        | I64AtomicRmw16AddU(_)
+        | I64AtomicRmw32AddU(_)
+        | I32AtomicRmwSub(_)
+        | I64AtomicRmwSub(_)
+        | I32AtomicRmw8SubU(_)
+        | I32AtomicRmw16SubU(_)
+        | I64AtomicRmw8SubU(_)
+        | I64AtomicRmw16SubU(_)
+        | I64AtomicRmw32SubU(_)
+        | I32AtomicRmwAnd(_)
+        | I64AtomicRmwAnd(_)
+        | I32AtomicRmw8AndU(_)
+        | I32AtomicRmw16AndU(_)
+        | I64AtomicRmw8AndU(_)
+        | I64AtomicRmw16AndU(_)
+        | I64AtomicRmw32AndU(_)
+        | I32AtomicRmwOr(_)
+        | I64AtomicRmwOr(_)
+        | I32AtomicRmw8OrU(_)
+        | I32AtomicRmw16OrU(_)
+        | I64AtomicRmw8OrU(_)
+        | I64AtomicRmw16OrU(_)
+        | I64AtomicRmw32OrU(_)
+        | I32AtomicRmwXor(_)
+        | I64AtomicRmwXor(_)
+        | I32AtomicRmw8XorU(_)
+        | I32AtomicRmw16XorU(_)
+        | I64AtomicRmw8XorU(_)
+        | I64AtomicRmw16XorU(_)
+        | I64AtomicRmw32XorU(_)
+        | I32AtomicRmwXchg(_)
+        | I64AtomicRmwXchg(_)
+        | I32AtomicRmw8XchgU(_)
+        | I32AtomicRmw16XchgU(_)
+        | I64AtomicRmw8XchgU(_)
+        | I64AtomicRmw16XchgU(_)
+        | I64AtomicRmw32XchgU(_) => (2, 1),
+        MemoryAtomicWait32(_)
+        | MemoryAtomicWait64(_)
+        | I32AtomicRmwCmpxchg(_)
+        | I64AtomicRmwCmpxchg(_)
+        | I32AtomicRmw8CmpxchgU(_)
+        | I32AtomicRmw16CmpxchgU(_)
+        | I64AtomicRmw8CmpxchgU(_)
+        | I64AtomicRmw16CmpxchgU(_)
+        | I64AtomicRmw32CmpxchgU(_) => (3, 1),
+        Block { .. }
+        | Loop { .. }
+        | If { .. }
+        | Unreachable
+        | Br(_)
+        | BrIf(_)
+        | BrTable { .. }
+        | Return
+        | Call(_)
+        | CallIndirect { .. }
+        | ReturnCall(_)
+        | ReturnCallIndirect { .. }
+        | Try { .. }
+        | Throw(_)
+        | Rethrow(_) => {
+            unreachable!("stack effect of {} is calculated by compiler", kind.name())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse_module;
+
+    fn compile_func<R>(source: &str, f: impl FnOnce(&Compiled<'_>) -> R) -> R {
+        let ast = parse_module(source);
+        let module = &ast.module;
+        let func = &module.funcs[0];
+        let fty = &module.types[func.idx as usize];
+        match &func.kind {
+            ast::FuncKind::Body { locals, expr } => f(&compile(module, fty, locals, expr)),
+            ast::FuncKind::Import(_) => panic!("not a function body"),
+        }
+    }
+
+    fn kinds(code: &[Op<'_>]) -> Vec<&'static str> {
+        code.iter()
+            .map(|op| match op {
+                Op::Jump(_) => "jump",
+                Op::EndCatch(_) => "end_catch",
+                op => op.insn().unwrap().kind.name(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flatten_structured_control() {
+        let src = r#"
+            (module
+              (func (param i32) (result i32)
+                (local i64)
+                block (result i32)
+                  loop
+                    local.get 0
+                    br_if 0
+                    i32.const 1
+                    local.get 0
+                    br_if 1
+                    drop
+                  end
+                  local.get 0
+                  if (result i32)
+                    i32.const 2
+                  else
+                    i32.const 3
+                  end
+                end))
+        "#;
+        compile_func(src, |compiled| {
+            let code = &compiled.code;
+            assert_eq!(
+                kinds(code),
+                vec![
+                    "local.get",
+                    "br_if",
+                    "i32.const",
+                    "local.get",
+                    "br_if",
+                    "drop",
+                    "local.get",
+                    "if",
+                    "i32.const",
+                    "jump",
+                    "i32.const",
+                ],
+            );
+
+            match &code[1] {
+                Op::BrIf(_, br) => {
+                    // Jump back to start of loop
+                    assert_eq!(br.target, 0);
+                    assert_eq!(br.height, 2);
+                    assert_eq!(br.keep, 0);
+                }
+                _ => panic!("not br_if"),
+            }
+            match &code[4] {
+                Op::BrIf(_, br) => {
+                    // Jump to end of block carrying the result
+                    assert_eq!(br.target, code.len());
+                    assert_eq!(br.height, 2);
+                    assert_eq!(br.keep, 1);
+                }
+                _ => panic!("not br_if"),
+            }
+            match &code[7] {
+                Op::If(_, else_pc) => assert_eq!(*else_pc, 10),
+                _ => panic!("not if"),
+            }
+            match &code[9] {
+                Op::Jump(target) => assert_eq!(*target, code.len()),
+                _ => panic!("not jump"),
+            }
+        });
+    }
+
+    #[test]
+    fn skip_unreachable_code() {
+        let src = r#"
+            (module
+              (func (result i32)
+                block
+                  br 0
+                  i32.const 1
+                  drop
+                end
+                i32.const 2
+                return
+                i32.const 3))
+        "#;
+        compile_func(src, |compiled| {
+            let code = &compiled.code;
+            assert_eq!(kinds(code), vec!["br", "i32.const", "return"]);
+            match &code[0] {
+                Op::Br(_, br) => assert_eq!(br.target, 1),
+                _ => panic!("not br"),
+            }
+        });
+    }
+
+    #[test]
+    fn exception_handlers() {
+        let src = r#"
+            (module
+              (tag $e (param i32))
+              (func (result i32)
+                try (result i32)
+                  try
+                    i32.const 1
+                    throw $e
+                  delegate 0
+                  i32.const 2
+                catch $e
+                  i32.const 3
+                  i32.add
+                catch_all
+                  rethrow 0
+                end))
+        "#;
+        compile_func(src, |compiled| {
+            let code = &compiled.code;
+            assert_eq!(
+                kinds(code),
+                vec![
+                    "i32.const",
+                    "throw",
+                    "i32.const",
+                    "jump",
+                    "i32.const",
+                    "i32.add",
+                    "end_catch",
+                    "rethrow",
+                ],
+            );
+            match &code[3] {
+                Op::Jump(target) => assert_eq!(*target, code.len()),
+                _ => panic!("not jump"),
+            }
+            match &code[6] {
+                Op::EndCatch(br) => {
+                    // Remove the caught exception and carry the result
+                    assert_eq!(br.target, code.len());
+                    assert_eq!(br.height, 0);
+                    assert_eq!(br.keep, 1);
+                }
+                _ => panic!("not end of catch clause"),
+            }
+            match &code[7] {
+                Op::Rethrow(_, slot) => assert_eq!(*slot, 0),
+                _ => panic!("not rethrow"),
+            }
+
+            let handlers = &compiled.handlers;
+            assert_eq!(handlers.len(), 2);
+            assert_eq!((handlers[0].start, handlers[0].end), (0, 3));
+            match &handlers[0].kind {
+                HandlerKind::Catch {
+                    height,
+                    catches,
+                    catch_all,
+                } => {
+                    assert_eq!(*height, 0);
+                    assert_eq!(&**catches, &[(0, 4)]);
+                    assert_eq!(*catch_all, Some(7));
+                }
+                HandlerKind::Delegate(_) => panic!("not catch"),
+            }
+            // Inner 'try' delegates exceptions to the outer 'try'
+            assert_eq!((handlers[1].start, handlers[1].end), (0, 2));
+            match &handlers[1].kind {
+                HandlerKind::Delegate(target) => assert_eq!(*target, Some(0)),
+                HandlerKind::Catch { .. } => panic!("not delegate"),
+            }
+        });
+    }
+}
@@ -1,70 +1,133 @@
 use crate::trap::{Result, Trap};
 use crate::value::{LittleEndian, Value};
-use wain_ast::{Global, GlobalKind, InsnKind, ValType};
+use wain_ast::{Global, GlobalKind, InsnKind, Instruction, RefType, ValType};
 
-// Fixed-size any values store indexed in advance
+// Fixed-size any values store indexed in advance. Global variables of all module instances are
+// put in this store and referred by their addresses
 #[cfg_attr(test, derive(Debug))]
+#[derive(Default)]
 pub struct Globals {
-    bytes: Box<[u8]>,
-    offsets: Box<[usize]>,
+    bytes: Vec<u8>,
+    offsets: Vec<usize>,
 }
 
 impl Globals {
+    // https://webassembly.github.io/spec/core/exec/modules.html#alloc-global
+    pub(crate) fn alloc(&mut self, v: Value) -> u32 {
+        let addr = self.offsets.len() as u32;
+        self.offsets.push(self.bytes.len());
+        match v {
+            Value::I32(i) => self.bytes.extend_from_slice(&i.to_le_bytes()),
+            Value::I64(i) => self.bytes.extend_from_slice(&i.to_le_bytes()),
+            Value::F32(f) => self.bytes.extend_from_slice(&f.to_le_bytes()),
+            Value::F64(f) => self.bytes.extend_from_slice(&f.to_le_bytes()),
+            Value::V128(v) => self.bytes.extend_from_slice(&v.to_le_bytes()),
+            Value::FuncRef(r) | Value::ExternRef(r) => {
+                let offset = self.bytes.len();
+                self.bytes.resize(offset + ValType::FuncRef.bytes(), 0);
+                LittleEndian::write(&mut self.bytes, offset, r);
+            }
+        }
+        addr
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    // Remove global variables allocated at `len` or later addresses
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if let Some(&offset) = self.offsets.get(len) {
+            self.bytes.truncate(offset);
+            self.offsets.truncate(len);
+        }
+    }
+
     // 5. https://webassembly.github.io/spec/core/exec/modules.html#instantiation
-    pub(crate) fn instantiate<'s>(ast: &[Global<'s>]) -> Result<Self> {
-        let mut offsets = Vec::with_capacity(ast.len());
-
-        fn global_value<'s>(idx: usize, globals: &[Global<'s>]) -> Result<Value> {
-            let g = &globals[idx];
-            match &g.kind {
-                GlobalKind::Import(i) => {
-                    // Currently no global variable cannot be imported
-                    Err(Trap::unknown_import(i, "global variable", g.start))
-                }
+    // `imports` are addresses of imported global variables. Since imports precede other definitions,
+    // `imports[i]` is an address of i-th global variable. `funcs` are addresses of functions of the
+    // module for evaluating `ref.func`. Returns addresses of all global variables of the module.
+    pub(crate) fn instantiate<'s>(
+        &mut self,
+        ast: &[Global<'s>],
+        imports: &[u32],
+        funcs: &[u32],
+    ) -> Result<Vec<u32>> {
+        let mut addrs = Vec::with_capacity(ast.len());
+        for (idx, g) in ast.iter().enumerate() {
+            let addr = match &g.kind {
+                GlobalKind::Import(i) => *imports
+                    .get(idx)
+                    .ok_or_else(|| Trap::unknown_import(i, "global variable", g.start))?,
                 GlobalKind::Init(init) => {
-                    // By validation at least one instruction is guaranteed to be put in `init`
-                    match &init[init.len() - 1].kind {
-                        InsnKind::GlobalGet(idx) => global_value(*idx as usize, globals),
-                        InsnKind::I32Const(i) => Ok(Value::I32(*i)),
-                        InsnKind::I64Const(i) => Ok(Value::I64(*i)),
-                        InsnKind::F32Const(f) => Ok(Value::F32(*f)),
-                        InsnKind::F64Const(f) => Ok(Value::F64(*f)),
-                        _ => unreachable!("invalid instruction for constant"), // Never reach here thanks to validation
-                    }
+                    // Global variables preceding this one were already allocated
+                    let v = self.eval_const(init, ast, &addrs, funcs);
+                    self.alloc(v)
                 }
-            }
+            };
+            addrs.push(addr);
+        }
+        Ok(addrs)
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#expressions
+    // https://github.com/WebAssembly/extended-const/blob/main/proposals/extended-const/Overview.md
+    // Evaluate constant expression of global variable, element segment or data segment. `globals`
+    // are global variables of the module and `addrs` are their addresses. `funcs` are addresses of
+    // functions of the module for evaluating `ref.func`
+    pub(crate) fn eval_const(
+        &self,
+        expr: &[Instruction],
+        globals: &[Global<'_>],
+        addrs: &[u32],
+        funcs: &[u32],
+    ) -> Value {
+        fn binop(stack: &mut Vec<Value>, op32: fn(i32, i32) -> i32, op64: fn(i64, i64) -> i64) {
+            let rhs = stack.pop().unwrap();
+            let lhs = stack.pop().unwrap();
+            let v = match (lhs, rhs) {
+                (Value::I32(l), Value::I32(r)) => Value::I32(op32(l, r)),
+                (Value::I64(l), Value::I64(r)) => Value::I64(op64(l, r)),
+                _ => unreachable!("invalid operands for constant"),
+            };
+            stack.push(v);
         }
 
-        let mut idx = 0;
-        let mut bytes = vec![];
-        for i in 0..ast.len() {
-            offsets.push(idx);
-            // Do not allocate space for imported global variables. They are defined in other
-            // module instances
-            match global_value(i, ast)? {
-                Value::I32(i) => {
-                    bytes.extend_from_slice(&i.to_le_bytes());
-                    idx += 4;
+        // Types of operands were checked by validation
+        let mut stack = Vec::with_capacity(expr.len());
+        for insn in expr {
+            let v = match &insn.kind {
+                InsnKind::GlobalGet(idx) => {
+                    let idx = *idx as usize;
+                    self.get_any(addrs[idx], globals[idx].ty)
                 }
-                Value::I64(i) => {
-                    bytes.extend_from_slice(&i.to_le_bytes());
-                    idx += 8;
+                InsnKind::I32Const(i) => Value::I32(*i),
+                InsnKind::I64Const(i) => Value::I64(*i),
+                InsnKind::F32Const(f) => Value::F32(*f),
+                InsnKind::F64Const(f) => Value::F64(*f),
+                InsnKind::V128Const(v) => Value::V128(*v),
+                InsnKind::RefFunc(idx) => Value::FuncRef(Some(funcs[*idx as usize])),
+                InsnKind::RefNull(RefType::FuncRef) => Value::FuncRef(None),
+                InsnKind::RefNull(RefType::ExternRef) => Value::ExternRef(None),
+                InsnKind::I32Add | InsnKind::I64Add => {
+                    binop(&mut stack, i32::wrapping_add, i64::wrapping_add);
+                    continue;
                 }
-                Value::F32(f) => {
-                    bytes.extend_from_slice(&f.to_le_bytes());
-                    idx += 4;
+                InsnKind::I32Sub | InsnKind::I64Sub => {
+                    binop(&mut stack, i32::wrapping_sub, i64::wrapping_sub);
+                    continue;
                 }
-                Value::F64(f) => {
-                    bytes.extend_from_slice(&f.to_le_bytes());
-                    idx += 8;
+                InsnKind::I32Mul | InsnKind::I64Mul => {
+                    binop(&mut stack, i32::wrapping_mul, i64::wrapping_mul);
+                    continue;
                 }
-            }
+                _ => unreachable!("invalid instruction for constant"), // Never reach here thanks to validation
+            };
+            stack.push(v);
         }
 
-        Ok(Globals {
-            bytes: bytes.into_boxed_slice(),
-            offsets: offsets.into_boxed_slice(),
-        })
+        // By validation exactly one value remains on the stack
+        stack.pop().unwrap()
     }
 
     pub fn set<V: LittleEndian>(&mut self, idx: u32, v: V) {
@@ -79,6 +142,8 @@ impl Globals {
             Value::I64(i) => self.set(idx, i),
             Value::F32(f) => self.set(idx, f),
             Value::F64(f) => self.set(idx, f),
+            Value::V128(v) => self.set(idx, v),
+            Value::FuncRef(r) | Value::ExternRef(r) => self.set(idx, r),
         }
     }
 
@@ -94,6 +159,9 @@ impl Globals {
             ValType::I64 => Value::I64(self.get(idx)),
             ValType::F32 => Value::F32(self.get(idx)),
             ValType::F64 => Value::F64(self.get(idx)),
+            ValType::V128 => Value::V128(self.get(idx)),
+            ValType::FuncRef => Value::FuncRef(self.get(idx)),
+            ValType::ExternRef => Value::ExternRef(self.get(idx)),
         }
     }
 }
@@ -154,7 +222,9 @@ mod tests {
                 }]),
             },
         ];
-        let mut globals = Globals::instantiate(&ast).unwrap();
+        let mut globals = Globals::default();
+        let addrs = globals.instantiate(&ast, &[], &[]).unwrap();
+        assert_eq!(addrs, vec![0, 1, 2, 3, 4]);
 
         assert_eq!(globals.get::<i32>(0), 3);
         assert_eq!(globals.get::<i64>(1), 123456);
@@ -183,7 +253,7 @@ mod tests {
             }
         }
 
-        // For now all global variable imports cause an error
+        // Global variable imports cause an error when no value is given for them
         let globals = [Global {
             start: 0,
             mutable: true,
@@ -191,7 +261,80 @@ mod tests {
             kind: GlobalKind::Import(import()),
         }];
 
-        let err = Globals::instantiate(&globals).unwrap_err();
-        assert!(matches!(err.reason, TrapReason::UnknownImport{..}));
+        let err = Globals::default()
+            .instantiate(&globals, &[], &[])
+            .unwrap_err();
+        assert!(matches!(err.reason, TrapReason::UnknownImport { .. }));
+    }
+
+    #[test]
+    fn imported_globals() {
+        let globals = [
+            Global {
+                start: 0,
+                mutable: false,
+                ty: ValType::I64,
+                kind: GlobalKind::Import(Import {
+                    mod_name: Name(Cow::Borrowed("module")),
+                    name: Name(Cow::Borrowed("name")),
+                }),
+            },
+            Global {
+                start: 0,
+                mutable: false,
+                ty: ValType::I64,
+                kind: GlobalKind::Init(vec![Instruction {
+                    start: 0,
+                    kind: InsnKind::GlobalGet(0),
+                }]),
+            },
+        ];
+
+        let mut store = Globals::default();
+        store.alloc(Value::I32(0));
+        let imported = store.alloc(Value::I64(42));
+        let addrs = store.instantiate(&globals, &[imported], &[]).unwrap();
+        assert_eq!(addrs, vec![1, 2]);
+        assert_eq!(store.get::<i64>(1), 42);
+        assert_eq!(store.get::<i64>(2), 42);
+
+        // Imported global variable is shared
+        store.set(1, 10i64);
+        assert_eq!(store.get::<i64>(1), 10);
+    }
+
+    #[test]
+    fn reference_globals() {
+        let globals = [
+            Global {
+                start: 0,
+                mutable: true,
+                ty: ValType::FuncRef,
+                kind: GlobalKind::Init(vec![Instruction {
+                    start: 0,
+                    kind: InsnKind::RefFunc(1),
+                }]),
+            },
+            Global {
+                start: 0,
+                mutable: true,
+                ty: ValType::ExternRef,
+                kind: GlobalKind::Init(vec![Instruction {
+                    start: 0,
+                    kind: InsnKind::RefNull(RefType::ExternRef),
+                }]),
+            },
+        ];
+
+        let mut store = Globals::default();
+        let addrs = store.instantiate(&globals, &[], &[3, 5]).unwrap();
+        let funcref = store.get_any(addrs[0], ValType::FuncRef);
+        assert_eq!(funcref, Value::FuncRef(Some(5)));
+        let externref = store.get_any(addrs[1], ValType::ExternRef);
+        assert_eq!(externref, Value::ExternRef(None));
+
+        store.set_any(addrs[1], Value::ExternRef(Some(0)));
+        let externref = store.get_any(addrs[1], ValType::ExternRef);
+        assert_eq!(externref, Value::ExternRef(Some(0)));
     }
 }
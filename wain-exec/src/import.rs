@@ -1,50 +1,141 @@
-use crate::memory::Memory;
+use crate::memory::{Memories, Memory};
 use crate::stack::Stack;
+use crate::table::Table;
+use crate::value::Value;
+use std::cell::RefCell;
 use std::io::{Read, Write};
-use wain_ast::ValType;
+use std::rc::Rc;
+use wain_ast::{Limits, ValType};
 
 pub enum ImportInvalidError {
     NotFound,
     SignatureMismatch {
-        expected_params: &'static [ValType],
-        expected_ret: Option<ValType>,
+        expected_params: Box<[ValType]>,
+        expected_results: Box<[ValType]>,
     },
 }
 
 pub enum ImportInvokeError {
     Fatal { message: String },
+    // Terminate the execution with the exit status. This is not a trap. Machine::execute returns
+    // Run::Exit and Machine::invoke_resumable returns Invocation::Exited
+    Exit { status: i32 },
+}
+
+/// Global variable given by embedder. `mutable` must match to mutability of the imported global.
+///
+/// The value is shared by the embedder and all instances which import it. Cloned handles refer to the
+/// same value, so the embedder can see values written by guests after invocations and while host
+/// functions are called.
+#[derive(Clone)]
+pub struct HostGlobal {
+    value: Rc<RefCell<Value>>,
+    mutable: bool,
+}
+
+impl HostGlobal {
+    pub fn new(value: Value, mutable: bool) -> Self {
+        Self {
+            value: Rc::new(RefCell::new(value)),
+            mutable,
+        }
+    }
+
+    pub fn get(&self) -> Value {
+        self.value.borrow().clone()
+    }
+
+    /// Set the value seen by guests. Panics when the global variable is immutable or type of the
+    /// value is different from the type of the global variable.
+    pub fn set(&self, value: Value) {
+        assert!(
+            self.mutable,
+            "cannot set value to immutable global variable"
+        );
+        assert_eq!(value.valtype(), self.valtype(), "type of global variable");
+        self.replace(value);
+    }
+
+    pub fn is_mutable(&self) -> bool {
+        self.mutable
+    }
+
+    pub fn valtype(&self) -> ValType {
+        self.value.borrow().valtype()
+    }
+
+    pub(crate) fn replace(&self, value: Value) {
+        *self.value.borrow_mut() = value;
+    }
 }
 
 pub trait Importer {
     fn validate(
         &self,
+        mod_name: &str,
         name: &str,
         params: &[ValType],
-        ret: Option<ValType>,
+        results: &[ValType],
     ) -> Option<ImportInvalidError>;
     fn call(
         &mut self,
+        mod_name: &str,
         name: &str,
         stack: &mut Stack,
-        memory: &mut Memory,
+        memories: &mut Memories<'_>,
     ) -> Result<(), ImportInvokeError>;
+    // Memory, table and global variables can be imported optionally. Returning `None` means the
+    // importer does not provide the value.
+    fn import_memory(&mut self, _mod_name: &str, _name: &str) -> Option<Memory> {
+        None
+    }
+    fn import_table(&mut self, _mod_name: &str, _name: &str) -> Option<Table> {
+        None
+    }
+    fn import_global(&mut self, _mod_name: &str, _name: &str) -> Option<HostGlobal> {
+        None
+    }
 }
 
 pub fn check_func_signature(
     actual_params: &[ValType],
-    actual_ret: Option<ValType>,
+    actual_results: &[ValType],
     expected_params: &'static [ValType],
-    expected_ret: Option<ValType>,
+    expected_results: &'static [ValType],
 ) -> Option<ImportInvalidError> {
-    if actual_params.eq(expected_params) && actual_ret == expected_ret {
+    if actual_params.eq(expected_params) && actual_results.eq(expected_results) {
         return None;
     }
     Some(ImportInvalidError::SignatureMismatch {
-        expected_params,
-        expected_ret,
+        expected_params: expected_params.into(),
+        expected_results: expected_results.into(),
     })
 }
 
+// https://webassembly.github.io/spec/core/exec/modules.html#limits
+pub(crate) fn limits_match(actual_min: u64, actual_max: Option<u64>, expected: &Limits) -> bool {
+    match (expected, actual_max) {
+        (Limits::From(min), _) => actual_min >= *min,
+        (Limits::Range(min, max), Some(actual_max)) => actual_min >= *min && actual_max <= *max,
+        (Limits::Range(..), None) => false,
+    }
+}
+
+pub(crate) fn describe_limits(min: u64, max: Option<u64>) -> String {
+    if let Some(max) = max {
+        format!("{{min {}, max {}}}", min, max)
+    } else {
+        format!("{{min {}}}", min)
+    }
+}
+
+pub(crate) fn describe_ast_limits(limits: &Limits) -> String {
+    match limits {
+        Limits::Range(min, max) => describe_limits(*min, Some(*max)),
+        Limits::From(min) => describe_limits(*min, None),
+    }
+}
+
 pub struct DefaultImporter<R: Read, W: Write> {
     stdout: W,
     stdin: R,
@@ -116,24 +207,29 @@ impl<R: Read, W: Write> DefaultImporter<R, W> {
 impl<R: Read, W: Write> Importer for DefaultImporter<R, W> {
     fn validate(
         &self,
+        mod_name: &str,
         name: &str,
         params: &[ValType],
-        ret: Option<ValType>,
+        results: &[ValType],
     ) -> Option<ImportInvalidError> {
         use ValType::*;
+        if mod_name != "env" {
+            return Some(ImportInvalidError::NotFound);
+        }
         match name {
-            "putchar" => check_func_signature(params, ret, &[I32], Some(I32)),
-            "getchar" => check_func_signature(params, ret, &[], Some(I32)),
-            "memcpy" => check_func_signature(params, ret, &[I32, I32, I32], Some(I32)),
+            "putchar" => check_func_signature(params, results, &[I32], &[I32]),
+            "getchar" => check_func_signature(params, results, &[], &[I32]),
+            "memcpy" => check_func_signature(params, results, &[I32, I32, I32], &[I32]),
             _ => Some(ImportInvalidError::NotFound),
         }
     }
 
     fn call(
         &mut self,
+        _mod_name: &str,
         name: &str,
         stack: &mut Stack,
-        memory: &mut Memory,
+        memories: &mut Memories<'_>,
     ) -> Result<(), ImportInvokeError> {
         match name {
             "putchar" => {
@@ -144,7 +240,7 @@ impl<R: Read, W: Write> Importer for DefaultImporter<R, W> {
                 self.getchar(stack);
                 Ok(())
             }
-            "memcpy" => self.memcpy(stack, memory),
+            "memcpy" => self.memcpy(stack, memories),
             _ => unreachable!("fatal: invalid import function '{}'", name),
         }
     }
@@ -5,25 +5,43 @@ extern crate wain_ast;
 pub mod trap;
 
 mod cast;
+mod compile;
 mod globals;
 mod import;
+mod linker;
 mod machine;
 mod memory;
+mod simd;
 mod stack;
 mod table;
+mod typed;
 mod value;
+mod vfs;
+mod wasi;
+
+#[cfg(test)]
+mod test_util;
 
 pub use import::{
-    check_func_signature, DefaultImporter, ImportInvalidError, ImportInvokeError, Importer,
+    check_func_signature, DefaultImporter, HostGlobal, ImportInvalidError, ImportInvokeError,
+    Importer,
+};
+pub use linker::Linker;
+pub use machine::{
+    InstanceId, InterruptHandle, Invocation, Machine, Run, Suspension, DEFAULT_MAX_CALL_DEPTH,
+    DEFAULT_MAX_STACK_SIZE,
 };
-pub use machine::{Machine, Run};
-pub use memory::Memory;
+pub use memory::{Memories, Memory};
 pub use stack::Stack;
+pub use table::Table;
+pub use typed::{TypedFunc, WasmParams, WasmResults};
 pub use value::Value;
+pub use vfs::{FileSystem, HostFs, MemoryFile, MemoryFs, OpenOptions};
+pub use wasi::{WasiImporter, WASI_MODULE_NAME};
 
 use std::io;
 use trap::Result;
-use wain_ast::Module;
+use wain_ast::{FuncKind, Module};
 
 /// A convenient function to execute a WebAssembly module.
 ///
@@ -32,15 +50,31 @@ use wain_ast::Module;
 /// For standard I/O speed, this function locks io::Stdin and io::Stdout objects because currently
 /// getchar() and putchar() don't buffer its input/output. This behavior may change in the future.
 ///
+/// When the module imports functions from 'wasi_snapshot_preview1' module, WasiImporter is used
+/// instead of DefaultImporter. It has no command line argument, no environment variable and no
+/// preopened directory.
+///
 /// If the behavior is not acceptable, please make an abstract machine instance with
 /// Machine::instantiate and execute by Machine::execute method.
 ///
-/// You will need importer for initializing Machine struct. Please use DefaultImporter::with_stdio()
-/// or make your own importer struct which implements Importer trait.
+/// You will need importer for initializing Machine struct. Please use DefaultImporter::with_stdio(),
+/// WasiImporter::with_stdio(), register host functions to Linker, or make your own importer struct
+/// which implements Importer trait.
 pub fn execute(module: &Module<'_>) -> Result<Run> {
     let stdin = io::stdin();
     let stdout = io::stdout();
-    let importer = DefaultImporter::with_stdio(stdin.lock(), stdout.lock());
-    let mut machine = Machine::instantiate(module, importer)?;
-    machine.execute()
+    let uses_wasi = module.funcs.iter().any(|f| match &f.kind {
+        FuncKind::Import(import) => import.mod_name.0 == WASI_MODULE_NAME,
+        FuncKind::Body { .. } => false,
+    });
+    if uses_wasi {
+        let stderr = io::stderr();
+        let importer = WasiImporter::with_stdio(stdin.lock(), stdout.lock(), stderr.lock());
+        let mut machine = Machine::instantiate(module, importer)?;
+        machine.execute()
+    } else {
+        let importer = DefaultImporter::with_stdio(stdin.lock(), stdout.lock());
+        let mut machine = Machine::instantiate(module, importer)?;
+        machine.execute()
+    }
 }
@@ -0,0 +1,628 @@
+use crate::import::{HostGlobal, ImportInvalidError, ImportInvokeError, Importer};
+use crate::memory::{Memories, Memory};
+use crate::stack::Stack;
+use crate::table::Table;
+use crate::value::Value;
+use std::collections::HashMap;
+use wain_ast::ValType;
+
+type HostFn<'a> =
+    dyn FnMut(&[Value], &mut Memories<'_>) -> Result<Vec<Value>, ImportInvokeError> + 'a;
+
+struct HostFunc<'a> {
+    params: Box<[ValType]>,
+    results: Box<[ValType]>,
+    body: Box<HostFn<'a>>,
+}
+
+// Values registered with pairs of module name and name. Maps are nested so that they can be looked
+// up with borrowed `&str` names. Calling host functions does not allocate keys
+struct Registry<T>(HashMap<String, HashMap<String, T>>);
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+impl<T> Registry<T> {
+    fn insert(&mut self, mod_name: String, name: String, value: T) {
+        self.0.entry(mod_name).or_default().insert(name, value);
+    }
+
+    fn get(&self, mod_name: &str, name: &str) -> Option<&T> {
+        self.0.get(mod_name)?.get(name)
+    }
+
+    fn get_mut(&mut self, mod_name: &str, name: &str) -> Option<&mut T> {
+        self.0.get_mut(mod_name)?.get_mut(name)
+    }
+
+    fn remove(&mut self, mod_name: &str, name: &str) -> Option<T> {
+        self.0.get_mut(mod_name)?.remove(name)
+    }
+}
+
+/// Registry of host functions which can be imported by a module. Each function is registered with
+/// a pair of module name and function name, and with its signature. The signature is checked against
+/// the function type of the import at instantiation.
+///
+/// Arguments and result values are passed as `Value` so host functions don't need to touch `Stack`.
+///
+/// Memory, table and global variables can also be registered. Memory and table are moved to the
+/// module instance which imports them.
+#[derive(Default)]
+pub struct Linker<'a> {
+    funcs: Registry<HostFunc<'a>>,
+    memories: Registry<Memory>,
+    tables: Registry<Table>,
+    globals: Registry<HostGlobal>,
+    args: Vec<Value>, // Buffer reused for passing arguments to host functions
+}
+
+impl<'a> Linker<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a host function as `mod_name`.`name`. When the same name is already registered, it is
+    /// overwritten.
+    pub fn func<F>(
+        &mut self,
+        mod_name: impl Into<String>,
+        name: impl Into<String>,
+        params: &[ValType],
+        results: &[ValType],
+        body: F,
+    ) -> &mut Self
+    where
+        F: FnMut(&[Value], &mut Memories<'_>) -> Result<Vec<Value>, ImportInvokeError> + 'a,
+    {
+        let func = HostFunc {
+            params: params.into(),
+            results: results.into(),
+            body: Box::new(body),
+        };
+        self.funcs.insert(mod_name.into(), name.into(), func);
+        self
+    }
+
+    pub fn memory(
+        &mut self,
+        mod_name: impl Into<String>,
+        name: impl Into<String>,
+        memory: Memory,
+    ) -> &mut Self {
+        self.memories.insert(mod_name.into(), name.into(), memory);
+        self
+    }
+
+    pub fn table(
+        &mut self,
+        mod_name: impl Into<String>,
+        name: impl Into<String>,
+        table: Table,
+    ) -> &mut Self {
+        self.tables.insert(mod_name.into(), name.into(), table);
+        self
+    }
+
+    pub fn global(
+        &mut self,
+        mod_name: impl Into<String>,
+        name: impl Into<String>,
+        value: Value,
+        mutable: bool,
+    ) -> &mut Self {
+        self.host_global(mod_name, name, HostGlobal::new(value, mutable))
+    }
+
+    /// Register a global variable shared with host. Keep a clone of the handle to read values written
+    /// by guests or to set values seen by them.
+    pub fn host_global(
+        &mut self,
+        mod_name: impl Into<String>,
+        name: impl Into<String>,
+        global: HostGlobal,
+    ) -> &mut Self {
+        self.globals.insert(mod_name.into(), name.into(), global);
+        self
+    }
+
+    pub fn contains(&self, mod_name: &str, name: &str) -> bool {
+        self.funcs.get(mod_name, name).is_some()
+            || self.memories.get(mod_name, name).is_some()
+            || self.tables.get(mod_name, name).is_some()
+            || self.globals.get(mod_name, name).is_some()
+    }
+}
+
+fn describe_types(tys: impl Iterator<Item = ValType>) -> String {
+    tys.map(|ty| ty.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+impl<'a> Importer for Linker<'a> {
+    fn validate(
+        &self,
+        mod_name: &str,
+        name: &str,
+        params: &[ValType],
+        results: &[ValType],
+    ) -> Option<ImportInvalidError> {
+        let func = match self.funcs.get(mod_name, name) {
+            Some(func) => func,
+            None => return Some(ImportInvalidError::NotFound),
+        };
+        if func.params.as_ref() == params && func.results.as_ref() == results {
+            None
+        } else {
+            Some(ImportInvalidError::SignatureMismatch {
+                expected_params: func.params.clone(),
+                expected_results: func.results.clone(),
+            })
+        }
+    }
+
+    fn call(
+        &mut self,
+        mod_name: &str,
+        name: &str,
+        stack: &mut Stack,
+        memories: &mut Memories<'_>,
+    ) -> Result<(), ImportInvokeError> {
+        let Self { funcs, args, .. } = self;
+        let func = funcs.get_mut(mod_name, name).unwrap_or_else(|| {
+            unreachable!("fatal: invalid import function '{}::{}'", mod_name, name)
+        });
+
+        args.clear();
+        for ty in func.params.iter().rev() {
+            args.push(stack.pop_value(*ty));
+        }
+        args.reverse();
+
+        let results = (func.body)(args, memories)?;
+        if results
+            .iter()
+            .map(Value::valtype)
+            .ne(func.results.iter().copied())
+        {
+            return Err(ImportInvokeError::Fatal {
+                message: format!(
+                    "host function returned [{}] but its signature expects [{}]",
+                    describe_types(results.iter().map(Value::valtype)),
+                    describe_types(func.results.iter().copied()),
+                ),
+            });
+        }
+        for v in results {
+            stack.push_value(v);
+        }
+        Ok(())
+    }
+
+    fn import_memory(&mut self, mod_name: &str, name: &str) -> Option<Memory> {
+        self.memories.remove(mod_name, name)
+    }
+
+    fn import_table(&mut self, mod_name: &str, name: &str) -> Option<Table> {
+        self.tables.remove(mod_name, name)
+    }
+
+    fn import_global(&mut self, mod_name: &str, name: &str) -> Option<HostGlobal> {
+        self.globals.get(mod_name, name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::Machine;
+    use crate::test_util::parse_module;
+    use crate::trap::TrapReason;
+
+    const SOURCE: &str = r#"
+        (module
+          (import "math" "add" (func $add (param i32 i32) (result i32)))
+          (import "host" "store" (func $store (param i32)))
+          (memory 1)
+          (func (export "run") (param i32) (result i32)
+            local.get 0
+            call $store
+            local.get 0
+            i32.const 40
+            call $add))
+    "#;
+
+    #[test]
+    fn call_host_functions() {
+        let root = parse_module(SOURCE);
+
+        let mut stored = vec![];
+        {
+            let mut linker = Linker::new();
+            linker
+                .func(
+                    "math",
+                    "add",
+                    &[ValType::I32, ValType::I32],
+                    &[ValType::I32],
+                    |args, _| match args {
+                        [Value::I32(l), Value::I32(r)] => Ok(vec![Value::I32(l + r)]),
+                        _ => unreachable!(),
+                    },
+                )
+                .func("host", "store", &[ValType::I32], &[], |args, mem| {
+                    if let [Value::I32(i)] = args {
+                        mem.data_mut()[0] = *i as u8;
+                        stored.push(*i);
+                    }
+                    Ok(vec![])
+                });
+
+            let mut machine = Machine::instantiate(&root.module, linker).unwrap();
+            let ret = machine.invoke("run", &[Value::I32(2)]).unwrap();
+            assert_eq!(ret, vec![Value::I32(42)]);
+            assert_eq!(machine.memory().data()[0], 2);
+        }
+        assert_eq!(stored, vec![2]);
+    }
+
+    #[test]
+    fn host_function_with_multiple_memories() {
+        let root = parse_module(
+            r#"
+            (module
+              (import "host" "copy" (func $copy (param i32)))
+              (memory $a 1)
+              (memory $b 1)
+              (data (memory $b) (i32.const 0) "hi")
+              (func (export "run")
+                i32.const 2
+                call $copy))
+            "#,
+        );
+
+        let mut linker = Linker::new();
+        linker.func("host", "copy", &[ValType::I32], &[], |args, memories| {
+            // Copy bytes from memory $b to the default memory $a
+            let len = match args {
+                [Value::I32(len)] => *len as usize,
+                _ => unreachable!(),
+            };
+            assert_eq!(memories.len(), 2);
+            let bytes = memories.get(1).unwrap().data()[..len].to_vec();
+            memories.data_mut()[..len].copy_from_slice(&bytes);
+            assert!(memories.get_mut(2).is_none());
+            Ok(vec![])
+        });
+
+        let mut machine = Machine::instantiate(&root.module, linker).unwrap();
+        machine.invoke("run", &[]).unwrap();
+        assert_eq!(&machine.memory().data()[..3], b"hi\0");
+    }
+
+    #[test]
+    fn host_function_with_multiple_results() {
+        let root = parse_module(
+            r#"
+            (module
+              (import "math" "divmod" (func $divmod (param i32 i32) (result i32 i32)))
+              (import "math" "wrong" (func $wrong (result i32 i64)))
+              (func (export "run") (param i32 i32) (result i32 i32)
+                local.get 0
+                local.get 1
+                call $divmod)
+              (func (export "wrong") (result i32 i64)
+                call $wrong))
+            "#,
+        );
+
+        let mut linker = Linker::new();
+        linker
+            .func(
+                "math",
+                "divmod",
+                &[ValType::I32, ValType::I32],
+                &[ValType::I32, ValType::I32],
+                |args, _| match args {
+                    [Value::I32(l), Value::I32(r)] => {
+                        Ok(vec![Value::I32(l / r), Value::I32(l % r)])
+                    }
+                    _ => unreachable!(),
+                },
+            )
+            .func(
+                "math",
+                "wrong",
+                &[],
+                &[ValType::I32, ValType::I64],
+                |_, _| Ok(vec![Value::I32(0)]),
+            );
+
+        let mut machine = Machine::instantiate(&root.module, linker).unwrap();
+        let ret = machine
+            .invoke("run", &[Value::I32(17), Value::I32(5)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(3), Value::I32(2)]);
+        let err = machine.invoke("wrong", &[]).unwrap_err();
+        match err.reason {
+            TrapReason::ImportFuncCallFail { msg, .. } => assert_eq!(
+                msg,
+                "host function returned [i32] but its signature expects [i32 i64]"
+            ),
+            r => panic!("unexpected trap: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn import_not_found() {
+        let root = parse_module(SOURCE);
+
+        let mut linker = Linker::new();
+        linker.func("host", "store", &[ValType::I32], &[], |_, _| Ok(vec![]));
+        let err = Machine::instantiate(&root.module, linker).err().unwrap();
+        match err.reason {
+            TrapReason::UnknownImport { mod_name, name, .. } => {
+                assert_eq!(mod_name, "math");
+                assert_eq!(name, "add");
+            }
+            r => panic!("unexpected trap: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn signature_mismatch() {
+        let root = parse_module(SOURCE);
+
+        let mut linker = Linker::new();
+        linker
+            .func("math", "add", &[ValType::I64], &[ValType::I32], |_, _| {
+                Ok(vec![Value::I32(0)])
+            })
+            .func("host", "store", &[ValType::I32], &[], |_, _| Ok(vec![]));
+        let err = Machine::instantiate(&root.module, linker).err().unwrap();
+        match err.reason {
+            TrapReason::FuncSignatureMismatch {
+                import: Some((mod_name, name)),
+                expected_params,
+                ..
+            } => {
+                assert_eq!(mod_name, "math");
+                assert_eq!(name, "add");
+                assert_eq!(&*expected_params, &[ValType::I64]);
+            }
+            r => panic!("unexpected trap: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn host_returns_wrong_value() {
+        let root = parse_module(SOURCE);
+
+        let mut linker = Linker::new();
+        linker
+            .func(
+                "math",
+                "add",
+                &[ValType::I32, ValType::I32],
+                &[ValType::I32],
+                |_, _| Ok(vec![Value::I64(0)]),
+            )
+            .func("host", "store", &[ValType::I32], &[], |_, _| Ok(vec![]));
+        let mut machine = Machine::instantiate(&root.module, linker).unwrap();
+        let err = machine.invoke("run", &[Value::I32(2)]).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::ImportFuncCallFail { .. }));
+    }
+
+    #[test]
+    fn import_memory_table_and_globals() {
+        let root = parse_module(
+            r#"
+            (module
+              (import "env" "memory" (memory 1))
+              (import "env" "table" (table 2 funcref))
+              (import "env" "base" (global $base i32))
+              (import "env" "counter" (global $counter (mut i64)))
+              (data (global.get $base) "\2a")
+              (elem (i32.const 1) $f)
+              (type $t (func (result i32)))
+              (func $f (result i32)
+                global.get $base
+                i32.load8_u)
+              (func (export "run") (result i32)
+                i32.const 1
+                call_indirect (type $t))
+              (func (export "count") (result i64)
+                global.get $counter
+                i64.const 1
+                i64.add
+                global.set $counter
+                global.get $counter))
+        "#,
+        );
+
+        let mut linker = Linker::new();
+        linker
+            .memory("env", "memory", Memory::new(2, Some(3)))
+            .table("env", "table", Table::new(4, None))
+            .global("env", "base", Value::I32(16), false)
+            .global("env", "counter", Value::I64(10), true);
+
+        let mut machine = Machine::instantiate(&root.module, linker).unwrap();
+        assert_eq!(machine.memory().size(), 2);
+        assert_eq!(machine.memory().data()[16], 42);
+        assert_eq!(machine.invoke("run", &[]).unwrap(), vec![Value::I32(42)]);
+        assert_eq!(machine.invoke("count", &[]).unwrap(), vec![Value::I64(11)]);
+    }
+
+    #[test]
+    fn import_memory64() {
+        let root = parse_module(
+            r#"
+            (module
+              (import "env" "memory" (memory i64 1))
+              (func (export "store") (param i64 i32)
+                local.get 0
+                local.get 1
+                i32.store)
+              (func (export "size") (result i64)
+                memory.size))
+        "#,
+        );
+
+        let mut linker = Linker::new();
+        linker.memory("env", "memory", Memory::new64(2, Some(4)));
+
+        let mut machine = Machine::instantiate(&root.module, linker).unwrap();
+        assert!(machine.memory().is_memory64());
+        let args = [Value::I64(65536), Value::I32(42)];
+        machine.invoke("store", &args).unwrap();
+        assert_eq!(machine.memory().load::<i32>(65536, 0).unwrap(), 42);
+        assert_eq!(machine.invoke("size", &[]).unwrap(), vec![Value::I64(2)]);
+    }
+
+    #[test]
+    fn share_mutable_global_with_host() {
+        let counter_module = parse_module(
+            r#"
+            (module
+              (import "env" "counter" (global $counter (mut i64)))
+              (import "env" "observe" (func $observe))
+              (func (export "count") (result i64)
+                global.get $counter
+                i64.const 1
+                i64.add
+                global.set $counter
+                call $observe
+                global.get $counter))
+            "#,
+        );
+        let reader_module = parse_module(
+            r#"
+            (module
+              (import "env" "counter" (global $counter (mut i64)))
+              (export "counter" (global $counter))
+              (func (export "read") (result i64)
+                global.get $counter))
+            "#,
+        );
+
+        let counter = HostGlobal::new(Value::I64(10), true);
+        let observed = HostGlobal::new(Value::I64(0), true);
+        let mut linker = Linker::new();
+        {
+            let (counter, observed) = (counter.clone(), observed.clone());
+            linker.host_global("env", "counter", counter.clone()).func(
+                "env",
+                "observe",
+                &[],
+                &[],
+                move |_, _| {
+                    // Host function sees the value written by guest
+                    observed.set(counter.get());
+                    counter.set(Value::I64(100));
+                    Ok(vec![])
+                },
+            );
+        }
+
+        let mut machine = Machine::instantiate(&counter_module.module, linker).unwrap();
+        let counting = machine.latest_instance();
+        let reader = machine.instantiate_module(&reader_module.module).unwrap();
+
+        // Value written by host function is seen by guest after the call
+        let ret = machine.invoke_instance(counting, "count", &[]).unwrap();
+        assert_eq!(ret, vec![Value::I64(100)]);
+        assert_eq!(observed.get(), Value::I64(11));
+        assert_eq!(counter.get(), Value::I64(100));
+
+        // Another instance importing the same global variable sees the value
+        let ret = machine.invoke_instance(reader, "read", &[]).unwrap();
+        assert_eq!(ret, vec![Value::I64(100)]);
+
+        // Value set by host is seen by guests and through exports
+        counter.set(Value::I64(-1));
+        assert_eq!(machine.get_global("counter"), Some(Value::I64(-1)));
+        let ret = machine.invoke_instance(reader, "read", &[]).unwrap();
+        assert_eq!(ret, vec![Value::I64(-1)]);
+        machine.set_global("counter", Value::I64(5)).unwrap();
+        assert_eq!(counter.get(), Value::I64(5));
+    }
+
+    #[test]
+    fn incompatible_imports() {
+        fn instantiate_error(source: &str, linker: Linker<'_>) -> TrapReason {
+            let root = parse_module(source);
+            Machine::instantiate(&root.module, linker)
+                .err()
+                .unwrap()
+                .reason
+        }
+
+        // Memory size is smaller than min
+        let mut linker = Linker::new();
+        linker.memory("env", "m", Memory::new(1, None));
+        let reason = instantiate_error(r#"(module (import "env" "m" (memory 2)))"#, linker);
+        assert!(matches!(
+            reason,
+            TrapReason::IncompatibleImport { kind: "memory", .. }
+        ));
+
+        // Import requires max but memory has no max
+        let mut linker = Linker::new();
+        linker.memory("env", "m", Memory::new(1, None));
+        let reason = instantiate_error(r#"(module (import "env" "m" (memory 1 2)))"#, linker);
+        assert!(matches!(
+            reason,
+            TrapReason::IncompatibleImport { kind: "memory", .. }
+        ));
+
+        // 32-bit memory is given to 64-bit memory import
+        let mut linker = Linker::new();
+        linker.memory("env", "m", Memory::new(1, None));
+        let reason = instantiate_error(r#"(module (import "env" "m" (memory i64 1)))"#, linker);
+        assert!(matches!(
+            reason,
+            TrapReason::IncompatibleImport { kind: "memory", .. }
+        ));
+
+        // Table max is larger than import's max
+        let mut linker = Linker::new();
+        linker.table("env", "t", Table::new(1, Some(10)));
+        let reason =
+            instantiate_error(r#"(module (import "env" "t" (table 1 5 funcref)))"#, linker);
+        assert!(matches!(
+            reason,
+            TrapReason::IncompatibleImport { kind: "table", .. }
+        ));
+
+        // Mutability mismatch
+        let mut linker = Linker::new();
+        linker.global("env", "g", Value::I32(0), true);
+        let reason = instantiate_error(r#"(module (import "env" "g" (global i32)))"#, linker);
+        assert!(matches!(
+            reason,
+            TrapReason::IncompatibleImport {
+                kind: "global variable",
+                ..
+            }
+        ));
+
+        // Type mismatch
+        let mut linker = Linker::new();
+        linker.global("env", "g", Value::F32(0.0), false);
+        let reason = instantiate_error(r#"(module (import "env" "g" (global i32)))"#, linker);
+        assert!(matches!(
+            reason,
+            TrapReason::IncompatibleImport {
+                kind: "global variable",
+                ..
+            }
+        ));
+
+        // Not found
+        let reason = instantiate_error(r#"(module (import "env" "m" (memory 1)))"#, Linker::new());
+        assert!(matches!(reason, TrapReason::UnknownImport { .. }));
+    }
+}
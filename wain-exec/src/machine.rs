@@ -1,724 +1,2462 @@
 use crate::cast;
+use crate::compile::{compile, Branch, Handler, HandlerKind, Op};
 use crate::globals::Globals;
-use crate::import::{ImportInvalidError, ImportInvokeError, Importer};
-use crate::memory::Memory;
-use crate::stack::{CallFrame, Stack, StackAccess};
+use crate::import::{
+    describe_ast_limits, describe_limits, limits_match, HostGlobal, ImportInvalidError,
+    ImportInvokeError, Importer,
+};
+use crate::memory::{Memories, Memory};
+use crate::simd;
+use crate::stack::{Label, Stack, StackAccess};
 use crate::table::Table;
 use crate::trap::{Result, Trap, TrapReason};
+use crate::typed::{TypedFunc, WasmParams, WasmResults};
 use crate::value::{LittleEndian, Value};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::mem::size_of;
+use std::ops::Neg;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use wain_ast as ast;
-use wain_ast::AsValType;
 
 // Note: This implementation currently ignores Wasm's thread model since MVP does not support multiple
 // threads. https://webassembly.github.io/spec/core/exec/runtime.html#configurations
 
-// TODO: Handle external values for imports and exports
-
 #[cfg_attr(test, derive(Debug))]
 #[derive(PartialEq)]
 pub enum Run {
     Success,
     Warning(&'static str),
+    /// Execution was terminated by host function with the exit status (e.g. WASI `proc_exit`)
+    Exit(i32),
+}
+
+/// Result of resumable invocation by `Machine::invoke_resumable`.
+#[cfg_attr(test, derive(Debug))]
+#[derive(PartialEq)]
+pub enum Invocation {
+    /// Execution finished with the returned values
+    Finished(Vec<Value>),
+    /// Execution was suspended. It can be continued by `Machine::resume`
+    Suspended(Suspension),
+    /// Execution was terminated by host function with the exit status
+    Exited(i32),
 }
 
-enum ExecState {
-    Breaking(u32), // Breaking
-    Ret,           // Returning from current function call
-    Continue,      // Continuing execution
+/// Reason why execution was suspended.
+#[cfg_attr(test, derive(Debug))]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Suspension {
+    /// Remaining fuel is not enough to execute the next instruction
+    OutOfFuel,
+    /// Execution was interrupted by `InterruptHandle::interrupt`
+    Interrupted,
 }
 
-type ExecResult = Result<ExecState>;
+/// Handle to interrupt execution of Machine. It can be sent to other threads.
+#[derive(Clone)]
+pub struct InterruptHandle(Arc<AtomicBool>);
 
-// State of abtract machine to run wasm code. This struct contains both store and stack
-pub struct Machine<'module, 'source, I: Importer> {
+impl InterruptHandle {
+    /// Request to stop execution. Machine stops before executing the next instruction.
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+// Execution state is not held on Rust call stack. Instead, frames of function calls are pushed to a
+// vector in Machine so that execution can be suspended at any instruction and resumed later.
+// https://webassembly.github.io/spec/core/exec/runtime.html#activations-and-frames
+struct Frame<'m> {
+    base: usize, // Position of the first local variable (including params) on stack
+    func: u32,   // Address of the function
+    code: Rc<[Op<'m>]>,
+    pc: usize, // Position of the next instruction to be executed after returning from callee
+    caller: usize, // Instance of the caller. It is restored on returning from this function
+    results: usize, // Number of result values
+}
+
+// Invocation started from outside of machine. It is necessary to clean up or resume the execution
+struct Entry<'m> {
+    label: Label,
+    results: &'m [ast::ValType],
+    caller: usize,
+}
+
+// https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#exceptions
+// Exception is identified by the address of its tag. Payload values are given by 'throw' instruction
+#[derive(Clone)]
+struct Exception {
+    tag: u32,
+    payload: Box<[Value]>,
+}
+
+/// Default maximum depth of nested function calls. Machine traps when the depth exceeds this limit.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+/// Default maximum size of value stack in bytes. Machine traps when the size exceeds this limit.
+/// Every value on the stack consumes 16 bytes, so the stack can hold about 512K values by default.
+pub const DEFAULT_MAX_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+// Every instruction costs 1 unit of fuel by default
+fn default_fuel_cost(_: &ast::InsnKind) -> u64 {
+    1
+}
+
+/// ID of module instance in the store of Machine.
+#[cfg_attr(test, derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct InstanceId(usize);
+
+// https://webassembly.github.io/spec/core/exec/runtime.html#function-instances
+// Function is identified by the module instance which defines it and the index in the module.
+// Imported function defined by host is also represented with the index of 'import' in the module.
+#[derive(Clone, Copy)]
+struct FuncInst {
+    instance: usize,
+    idx: u32,
+}
+
+// https://webassembly.github.io/spec/core/exec/runtime.html#module-instances
+// Addresses of external values are indices of vectors in Machine
+struct Instance<'module, 'source> {
     module: &'module ast::Module<'source>,
-    table: Table, // Only one table is allowed for MVP
-    stack: Stack,
-    memory: Memory, // Only one memory is allowed for MVP
+    funcs: Vec<u32>,
+    tables: Vec<usize>,
+    memories: Vec<usize>,
+    globals: Vec<u32>,
+    tags: Vec<u32>,
+    code: Vec<Rc<[Op<'module>]>>, // Compiled function bodies. Empty for imported functions
+    handlers: Vec<Box<[Handler]>>, // Exception handlers of compiled function bodies
+    elems: Vec<Vec<Option<u32>>>, // Evaluated element segments. Dropped segments are empty
+    data: Vec<&'module [u8]>,     // Data segments. Dropped segments are empty
+}
+
+// Addresses of external values resolved for imports of a module
+struct Imports {
+    funcs: Vec<u32>,
+    tables: Vec<usize>,
+    memories: Vec<usize>,
+    globals: Vec<u32>,
+    tags: Vec<u32>,
+}
+
+// External values imported from host. They are shared by all instances which import them
+enum HostExtern {
+    Table(usize),
+    Memory(usize),
+    Global {
+        addr: u32,
+        ty: ast::ValType,
+        mutable: bool,
+    },
+}
+
+// State of abtract machine to run wasm code. This struct contains both store and stack.
+//
+// The store can contain multiple module instances. Exports of an instance can be registered with a
+// module name and later instances can import them. Methods without instance ID operate on the latest
+// instance.
+pub struct Machine<'module, 'source, I: Importer> {
+    instances: Vec<Instance<'module, 'source>>,
+    latest: usize, // Latest instance which was instantiated successfully
+    funcs: Vec<FuncInst>,
+    tables: Vec<Table>,
+    memories: Vec<Memory>,
     globals: Globals,
+    // Tag instance only has its type. Tags are distinguished by their addresses
+    // https://webassembly.github.io/exception-handling/core/exec/runtime.html#tag-instances
+    tags: Vec<&'module ast::FuncType>,
+    registered: HashMap<String, usize>,
+    host_externs: HashMap<(String, String), HostExtern>,
+    // Mutable global variables shared with host. While guest code is running, their values are in
+    // `globals`. They are copied from host before running guest code and copied back after it
+    host_globals: Vec<(u32, HostGlobal)>,
+    current: usize, // Instance which defines the function being executed
+    stack: Stack,
+    frames: Vec<Frame<'module>>,
+    // Exceptions caught by 'catch' clauses with the positions of the slots referring them on stack
+    caught: Vec<(usize, Exception)>,
+    suspended: Option<Entry<'module>>,
+    interrupt: Arc<AtomicBool>,
+    max_call_depth: usize,
+    max_stack_size: usize,
+    fuel: Option<u64>, // None means fuel metering is disabled
+    fuel_consumed: u64,
+    fuel_cost: fn(&ast::InsnKind) -> u64,
     importer: I,
 }
 
 impl<'m, 's, I: Importer> Machine<'m, 's, I> {
-    // https://webassembly.github.io/spec/core/exec/modules.html#instantiation
     pub fn instantiate(module: &'m ast::Module<'s>, importer: I) -> Result<Self> {
-        // TODO: 2., 3., 4. Validate external values before instantiate globals
+        let mut machine = Self {
+            instances: vec![],
+            latest: 0,
+            funcs: vec![],
+            tables: vec![],
+            memories: vec![],
+            globals: Globals::default(),
+            tags: vec![],
+            registered: HashMap::new(),
+            host_externs: HashMap::new(),
+            host_globals: vec![],
+            current: 0,
+            stack: Stack::default(),
+            frames: vec![],
+            caught: vec![],
+            suspended: None,
+            interrupt: Arc::new(AtomicBool::new(false)),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_stack_size: DEFAULT_MAX_STACK_SIZE,
+            fuel: None,
+            fuel_consumed: 0,
+            fuel_cost: default_fuel_cost,
+            importer,
+        };
+        machine.instantiate_module(module)?;
+        Ok(machine)
+    }
 
-        fn unknown_import<'s>(import: &ast::Import<'s>, at: usize) -> Box<Trap> {
-            Trap::new(
-                TrapReason::UnknownImport {
-                    mod_name: import.mod_name.0.to_string(),
-                    name: import.name.0.to_string(),
-                    kind: "function",
-                },
-                at,
-            )
-        }
+    /// Set the maximum depth of nested function calls. When a function call exceeds the depth,
+    /// execution traps with `TrapReason::StackExhausted`. The default value is
+    /// `DEFAULT_MAX_CALL_DEPTH`.
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_call_depth = depth;
+    }
 
-        for func in module.funcs.iter() {
-            match &func.kind {
-                ast::FuncKind::Body { .. } => break, // All imports precedes other definitions
-                ast::FuncKind::Import(i) => {
-                    let mod_name = &i.mod_name.0;
-                    if mod_name == "env" {
-                        let fty = &module.types[func.idx as usize];
-                        let name = &i.name.0;
-                        match importer.validate(name, &fty.params, fty.results.get(0).copied()) {
-                            Some(ImportInvalidError::NotFound) => {
-                                return Err(unknown_import(i, func.start));
-                            }
-                            Some(ImportInvalidError::SignatureMismatch {
-                                expected_params,
-                                expected_ret,
-                            }) => {
-                                return Err(Trap::new(
-                                    TrapReason::FuncSignatureMismatch {
-                                        import: Some((mod_name.to_string(), name.to_string())),
-                                        expected_params: expected_params.iter().copied().collect(),
-                                        expected_results: expected_ret.into_iter().collect(),
-                                        actual_params: fty.params.iter().copied().collect(),
-                                        actual_results: fty.results.clone().into_boxed_slice(),
-                                    },
-                                    func.start,
-                                ))
-                            }
-                            None => { /* do nothing */ }
-                        }
-                    } else {
-                        return Err(unknown_import(i, func.start));
-                    }
-                }
-            }
-        }
+    /// Set the maximum size of value stack in bytes. When the stack grows beyond the size on calling
+    /// a function, execution traps with `TrapReason::StackExhausted`. The default value is
+    /// `DEFAULT_MAX_STACK_SIZE`.
+    pub fn set_max_stack_size(&mut self, size: usize) {
+        self.max_stack_size = size;
+    }
 
-        // 5. global initialization values determined by module and externval
-        let globals = Globals::instantiate(&module.globals)?;
+    /// Enable fuel metering and set the amount of fuel. Executing an instruction consumes fuel and
+    /// execution traps with `TrapReason::OutOfFuel` when the remaining fuel is not enough to
+    /// execute the next instruction. Fuel metering is disabled by default.
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = Some(fuel);
+    }
 
-        // 6. a new module instance allocated from module in store S
-        // https://webassembly.github.io/spec/core/exec/modules.html#alloc-module
+    /// Set the function to calculate how much fuel is consumed by executing an instruction. By
+    /// default every instruction costs 1. `block` and `loop` instructions consume no fuel since
+    /// they are resolved when compiling function bodies.
+    pub fn set_fuel_cost(&mut self, cost: fn(&ast::InsnKind) -> u64) {
+        self.fuel_cost = cost;
+    }
+
+    /// Handle to interrupt execution of this machine from other threads. When the execution is
+    /// interrupted, `Machine::invoke_resumable` returns `Invocation::Suspended` and other methods to
+    /// execute functions trap with `TrapReason::Interrupted`.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle(self.interrupt.clone())
+    }
+
+    /// Remaining fuel. `None` when fuel metering is disabled.
+    pub fn remaining_fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Total amount of fuel consumed by executed instructions so far.
+    pub fn consumed_fuel(&self) -> u64 {
+        self.fuel_consumed
+    }
 
-        // 6.2 allocate functions (nothing to do since we run abstract tree directly)
+    // https://webassembly.github.io/spec/core/exec/modules.html#instantiation
+    /// Instantiate a module in the store of this machine. Imports of the module are resolved with
+    /// instances registered by `Machine::register` at first, then with the importer.
+    pub fn instantiate_module(&mut self, module: &'m ast::Module<'s>) -> Result<InstanceId> {
+        let instance = self.instances.len();
 
-        // 6.3 allocate table
-        let mut table = Table::allocate(&module.tables)?;
-        // 6.4 allocate memory
-        let mut memory = Memory::allocate(&module.memories)?;
+        // 2., 3., 4. Validate external values before instantiate globals
+        // https://webassembly.github.io/spec/core/exec/modules.html#import-matching
+        let num_funcs = self.funcs.len();
+        let imports = match self.resolve_imports(module, instance) {
+            Ok(imports) => imports,
+            Err(err) => {
+                self.funcs.truncate(num_funcs); // Remove functions allocated for this instance
+                return Err(err);
+            }
+        };
 
-        // 7. and 8. push empty frame (unnecessary for now)
-        let stack = Stack::default();
+        // Values allocated for the instance are removed from the store when the allocation fails.
+        // Values imported from host were already cached and are kept for later instances
+        let (num_tables, num_memories) = (self.tables.len(), self.memories.len());
+        let (num_globals, num_tags) = (self.globals.len(), self.tags.len());
+        match self.allocate_instance(module, instance, imports) {
+            // 7. and 8. push empty frame (unnecessary for now)
+            Ok(inst) => self.instances.push(inst),
+            Err(err) => {
+                self.funcs.truncate(num_funcs);
+                self.tables.truncate(num_tables);
+                self.memories.truncate(num_memories);
+                self.globals.truncate(num_globals);
+                self.tags.truncate(num_tags);
+                return Err(err);
+            }
+        }
 
-        // 9. add element segments to table
-        for elem in module.elems.iter() {
-            table.new_elem(elem, &globals)?;
+        // 9. initialize table by active element segments with table.init and drop them with
+        // elem.drop in order. When a segment is out of bounds, instantiation traps but the
+        // elements written by preceding segments remain. The failed instance is kept in the store
+        // since the elements may refer to its functions, but it does not become the latest one
+        for (idx, elem) in module.elems.iter().enumerate() {
+            if let ast::ElemMode::Active { idx: table, offset } = &elem.mode {
+                let offset = self.const_offset(offset, instance);
+                let inst = &mut self.instances[instance];
+                let segment = std::mem::take(&mut inst.elems[idx]);
+                let table = &mut self.tables[inst.tables[*table as usize]];
+                table.init(offset, 0, segment.len(), &segment, elem.start)?;
+            }
         }
 
-        // 10. add data segments to memory
-        for data in module.data.iter() {
-            memory.new_data(data, &globals)?;
+        // 10. initialize memory by active data segments with memory.init and drop them with
+        // data.drop in order
+        for (idx, data) in module.data.iter().enumerate() {
+            if let ast::DataMode::Active {
+                idx: memory,
+                offset,
+            } = &data.mode
+            {
+                let offset = self.const_offset(offset, instance);
+                let inst = &mut self.instances[instance];
+                let len = data.data.len();
+                let memory = &mut self.memories[inst.memories[*memory as usize]];
+                memory.init(offset, 0, len, &data.data, data.start)?;
+                inst.data[idx] = &[];
+            }
         }
 
         // 11. and 12. pop frame (unnecessary for now)
 
-        Ok(Self {
-            module,
-            table,
-            stack,
-            memory,
-            globals,
-            importer,
-        })
+        self.latest = instance;
+        Ok(InstanceId(instance))
     }
 
-    pub fn module(&self) -> &'m ast::Module<'s> {
-        &self.module
-    }
+    // 6. a new module instance allocated from module in store S with resolved imports. Values are
+    // allocated in the store but the instance is not added yet
+    fn allocate_instance(
+        &mut self,
+        module: &'m ast::Module<'s>,
+        instance: usize,
+        imports: Imports,
+    ) -> Result<Instance<'m, 's>> {
+        let Imports {
+            mut funcs,
+            mut tables,
+            mut memories,
+            globals: imported_globals,
+            mut tags,
+        } = imports;
 
-    pub fn memory(&self) -> &Memory {
-        &self.memory
-    }
+        // https://webassembly.github.io/spec/core/exec/modules.html#alloc-module
 
-    pub fn get_global(&self, name: &str) -> Option<Value> {
-        self.module
-            .exports
+        // 6.2 allocate functions. They are allocated before globals since `ref.func` in
+        // initialization values of globals refers their addresses
+        for (idx, func) in module.funcs.iter().enumerate() {
+            if let ast::FuncKind::Body { .. } = func.kind {
+                funcs.push(self.funcs.len() as u32);
+                self.funcs.push(FuncInst {
+                    instance,
+                    idx: idx as u32,
+                });
+            }
+        }
+
+        // 5. global initialization values determined by module and externval
+        let globals = self
+            .globals
+            .instantiate(&module.globals, &imported_globals, &funcs)?;
+
+        // 6.3 allocate tables. Imported tables precede other definitions
+        for table in &module.tables[tables.len()..] {
+            self.tables.push(Table::allocate(table)?);
+            tables.push(self.tables.len() - 1);
+        }
+        // 6.4 allocate memories. Imported memories precede other definitions
+        for memory in &module.memories[memories.len()..] {
+            self.memories.push(Memory::allocate(memory)?);
+            memories.push(self.memories.len() - 1);
+        }
+        if memories.is_empty() {
+            self.memories.push(Memory::dummy());
+            memories.push(self.memories.len() - 1);
+        }
+        // Allocate tags. Imported tags precede other definitions
+        for tag in &module.tags[tags.len()..] {
+            self.tags.push(&module.types[tag.idx as usize]);
+            tags.push(self.tags.len() as u32 - 1);
+        }
+
+        let mut code = Vec::with_capacity(module.funcs.len());
+        let mut handlers = Vec::with_capacity(module.funcs.len());
+        for func in &module.funcs {
+            match &func.kind {
+                ast::FuncKind::Import(_) => {
+                    code.push(Rc::from(vec![]));
+                    handlers.push(Box::from(vec![]));
+                }
+                ast::FuncKind::Body { locals, expr } => {
+                    let fty = &module.types[func.idx as usize];
+                    let compiled = compile(module, fty, locals, expr);
+                    code.push(Rc::from(compiled.code));
+                    handlers.push(compiled.handlers);
+                }
+            }
+        }
+
+        // Element segments are evaluated with the auxiliary frame. Declarative segments are
+        // dropped immediately
+        let elems = module
+            .elems
             .iter()
-            .find_map(|e| match e.kind {
-                ast::ExportKind::Global(idx) if e.name.0 == name => Some(idx),
-                _ => None,
-            })
-            .map(|idx| {
-                let ty = self.module.globals[idx as usize].ty;
-                self.globals.get_any(idx, ty)
+            .map(|elem| match elem.mode {
+                ast::ElemMode::Declarative => vec![],
+                _ => elem
+                    .init
+                    .iter()
+                    .map(|expr| self.const_ref(module, expr, &funcs, &globals))
+                    .collect(),
             })
+            .collect();
+        let data = module.data.iter().map(|d| d.data.as_ref()).collect();
+
+        Ok(Instance {
+            module,
+            funcs,
+            tables,
+            memories,
+            globals,
+            tags,
+            code,
+            handlers,
+            elems,
+            data,
+        })
     }
 
-    // Returns if it has return value on stack or not
-    fn invoke_import(
-        &mut self,
-        import: &ast::Import<'s>,
-        has_ret: bool,
-        pos: usize,
-    ) -> Result<bool> {
-        if import.mod_name.0 == "env" {
-            match self
-                .importer
-                .call(&import.name.0, &mut self.stack, &mut self.memory)
-            {
-                Ok(()) => return Ok(has_ret),
-                Err(ImportInvokeError::Fatal { message }) => {
-                    return Err(Trap::new(
-                        TrapReason::ImportFuncCallFail {
-                            mod_name: import.mod_name.0.to_string(),
-                            name: import.name.0.to_string(),
-                            msg: message,
-                        },
-                        pos,
-                    ))
+    // Resolve imports of the module. Returns addresses of imported functions, table, memory and
+    // global variables
+    fn resolve_imports(&mut self, module: &'m ast::Module<'s>, instance: usize) -> Result<Imports> {
+        let mut funcs = vec![];
+        for (idx, func) in module.funcs.iter().enumerate() {
+            match &func.kind {
+                ast::FuncKind::Body { .. } => break, // All imports precedes other definitions
+                ast::FuncKind::Import(i) => {
+                    let fty = &module.types[func.idx as usize];
+                    let addr = if let Some(exporter) = self.registered.get(i.mod_name.0.as_ref()) {
+                        self.import_func_from_instance(*exporter, i, fty, func.start)?
+                    } else {
+                        self.import_func_from_host(i, fty, func.start)?;
+                        self.funcs.push(FuncInst {
+                            instance,
+                            idx: idx as u32,
+                        });
+                        self.funcs.len() as u32 - 1
+                    };
+                    funcs.push(addr);
                 }
             }
         }
-        unreachable!(
-            "fatal: invalid import at runtime: {}::{}",
-            import.mod_name.0, import.name.0
-        );
-    }
-
-    // https://webassembly.github.io/spec/core/exec/instructions.html#function-calls
-    // Returns if it has return value on stack or not
-    fn invoke_by_funcidx(&mut self, funcidx: u32) -> Result<bool> {
-        let func = &self.module.funcs[funcidx as usize];
-        let fty = &self.module.types[func.idx as usize];
 
-        // Call this function with params
-        let (locals, body) = match &func.kind {
-            ast::FuncKind::Import(i) => {
-                return self.invoke_import(i, !fty.results.is_empty(), func.start)
+        let mut tables = vec![];
+        for table in module.tables.iter() {
+            match &table.import {
+                Some(i) => tables.push(self.import_table(i, &table.ty, table.start)?),
+                None => break, // All imports precedes other definitions
             }
-            ast::FuncKind::Body { locals, expr } => (locals, expr),
-        };
-
-        // Push call frame
-        let frame = CallFrame::new(&self.stack, &fty.params, locals);
+        }
 
-        self.stack.extend_zero_values(&locals);
+        let mut memories = vec![];
+        for memory in module.memories.iter() {
+            match &memory.import {
+                Some(i) => memories.push(self.import_memory(i, &memory.ty, memory.start)?),
+                None => break, // All imports precedes other definitions
+            }
+        }
 
-        for insn in body.iter() {
-            match insn.execute(self, &frame)? {
-                ExecState::Continue => {}
-                // When using br or br_if outside control instructions, it unwinds execution in
-                // the function body. Label with empty continuation is put before invoking the
-                // function body (11.). It means that breaking outside control instructions will be
-                // caught by this label.
-                ExecState::Ret | ExecState::Breaking(_) => break,
+        let mut globals = vec![];
+        for global in module.globals.iter() {
+            match &global.kind {
+                ast::GlobalKind::Init(_) => break, // All imports precedes other definitions
+                ast::GlobalKind::Import(i) => globals.push(self.import_global(i, global)?),
             }
         }
 
-        if fty.results.is_empty() {
-            self.stack.restore(frame.base_addr, frame.base_idx); // Pop call frame
-            Ok(false)
-        } else {
-            // Push 1st result value since number of result type is 1 or 0 for MVP
-            let v: Value = self.stack.pop();
-            self.stack.restore(frame.base_addr, frame.base_idx); // Pop call frame
-            self.stack.push(v); // push result value
-            Ok(true)
-        }
-    }
-
-    pub fn invoke(&mut self, name: impl AsRef<str>, args: &[Value]) -> Result<Option<Value>> {
-        fn find_func_to_invoke<'s>(
-            name: &str,
-            exports: &[ast::Export<'s>],
-        ) -> Result<(u32, usize)> {
-            for export in exports {
-                if export.name.0 == name {
-                    let actual = match export.kind {
-                        ast::ExportKind::Func(idx) => return Ok((idx, export.start)),
-                        ast::ExportKind::Table(_) => "table",
-                        ast::ExportKind::Memory(_) => "memory",
-                        ast::ExportKind::Global(_) => "global variable",
-                    };
-                    return Err(Trap::new(
-                        TrapReason::WrongInvokeTarget {
-                            name: name.to_string(),
-                            actual: Some(actual),
-                        },
-                        export.start,
-                    ));
+        let mut tags = vec![];
+        for tag in module.tags.iter() {
+            match &tag.import {
+                Some(i) => {
+                    let ty = &module.types[tag.idx as usize];
+                    tags.push(self.import_tag(i, ty, tag.start)?);
                 }
+                None => break, // All imports precedes other definitions
             }
-            Err(Trap::new(
-                TrapReason::WrongInvokeTarget {
-                    name: name.to_string(),
-                    actual: None,
-                },
-                0,
-            ))
         }
 
-        let name = name.as_ref();
-        let (funcidx, start) = find_func_to_invoke(name, &self.module.exports)?;
-        let arg_types = &self.module.types[self.module.funcs[funcidx as usize].idx as usize].params;
+        Ok(Imports {
+            funcs,
+            tables,
+            memories,
+            globals,
+            tags,
+        })
+    }
 
-        // Check parameter types
-        if args
+    // Find an export of the registered instance
+    fn find_export(
+        &self,
+        instance: usize,
+        import: &ast::Import<'s>,
+        kind: &'static str,
+        at: usize,
+    ) -> Result<&'m ast::ExportKind> {
+        let module = self.instances[instance].module;
+        module
+            .exports
             .iter()
-            .map(Value::valtype)
-            .ne(arg_types.iter().copied())
-        {
+            .find(|e| e.name.0 == import.name.0)
+            .map(|e| &e.kind)
+            .ok_or_else(|| Trap::unknown_import(import, kind, at))
+    }
+
+    fn import_func_from_instance(
+        &self,
+        exporter: usize,
+        import: &ast::Import<'s>,
+        fty: &ast::FuncType,
+        at: usize,
+    ) -> Result<u32> {
+        let inst = &self.instances[exporter];
+        let idx = match self.find_export(exporter, import, "function", at)? {
+            ast::ExportKind::Func(idx) => *idx,
+            kind => {
+                return Err(Trap::incompatible_import(
+                    import,
+                    "function",
+                    "function".to_string(),
+                    export_kind_name(kind).to_string(),
+                    at,
+                ))
+            }
+        };
+        let expected = &inst.module.types[inst.module.funcs[idx as usize].idx as usize];
+        if expected.params != fty.params || expected.results != fty.results {
             return Err(Trap::new(
-                TrapReason::InvokeInvalidArgs {
-                    name: name.to_string(),
-                    args: args.iter().cloned().collect(),
-                    arg_types: arg_types.iter().copied().collect(),
+                TrapReason::FuncSignatureMismatch {
+                    import: Some((import.mod_name.0.to_string(), import.name.0.to_string())),
+                    expected_params: expected.params.clone().into_boxed_slice(),
+                    expected_results: expected.results.clone().into_boxed_slice(),
+                    actual_params: fty.params.clone().into_boxed_slice(),
+                    actual_results: fty.results.clone().into_boxed_slice(),
                 },
-                start,
+                at,
             ));
         }
+        Ok(inst.funcs[idx as usize])
+    }
 
-        // Push values to stack for invoking the function
-        for arg in args {
-            self.stack.push(arg.clone());
+    fn import_func_from_host(
+        &self,
+        import: &ast::Import<'s>,
+        fty: &ast::FuncType,
+        at: usize,
+    ) -> Result<()> {
+        let (mod_name, name) = (&import.mod_name.0, &import.name.0);
+        match self
+            .importer
+            .validate(mod_name, name, &fty.params, &fty.results)
+        {
+            Some(ImportInvalidError::NotFound) => Err(Trap::unknown_import(import, "function", at)),
+            Some(ImportInvalidError::SignatureMismatch {
+                expected_params,
+                expected_results,
+            }) => Err(Trap::new(
+                TrapReason::FuncSignatureMismatch {
+                    import: Some((mod_name.to_string(), name.to_string())),
+                    expected_params,
+                    expected_results,
+                    actual_params: fty.params.iter().copied().collect(),
+                    actual_results: fty.results.clone().into_boxed_slice(),
+                },
+                at,
+            )),
+            None => Ok(()),
         }
+    }
 
-        if self.invoke_by_funcidx(funcidx)? {
-            Ok(Some(self.stack.pop()))
+    // https://webassembly.github.io/spec/core/exec/modules.html#tables
+    fn import_table(
+        &mut self,
+        import: &ast::Import<'s>,
+        ty: &ast::TableType,
+        at: usize,
+    ) -> Result<usize> {
+        let addr = if let Some(exporter) = self.registered.get(import.mod_name.0.as_ref()) {
+            match self.find_export(*exporter, import, "table", at)? {
+                ast::ExportKind::Table(idx) => self.instances[*exporter].tables[*idx as usize],
+                kind => {
+                    return Err(Trap::incompatible_import(
+                        import,
+                        "table",
+                        "table".to_string(),
+                        export_kind_name(kind).to_string(),
+                        at,
+                    ))
+                }
+            }
         } else {
-            Ok(None)
+            let key = (import.mod_name.0.to_string(), import.name.0.to_string());
+            match self.host_externs.get(&key) {
+                Some(HostExtern::Table(addr)) => *addr,
+                Some(_) => return Err(Trap::unknown_import(import, "table", at)),
+                None => {
+                    let table = self
+                        .importer
+                        .import_table(&key.0, &key.1)
+                        .ok_or_else(|| Trap::unknown_import(import, "table", at))?;
+                    self.tables.push(table);
+                    let addr = self.tables.len() - 1;
+                    self.host_externs.insert(key, HostExtern::Table(addr));
+                    addr
+                }
+            }
+        };
+
+        let table = &self.tables[addr];
+        if table.elem_type() != ty.elem {
+            return Err(Trap::incompatible_import(
+                import,
+                "table",
+                ty.elem.to_string(),
+                table.elem_type().to_string(),
+                at,
+            ));
+        }
+        let (size, max) = (table.size().into(), table.max().map(u64::from));
+        if !limits_match(size, max, &ty.limit) {
+            return Err(Trap::incompatible_import(
+                import,
+                "table",
+                describe_ast_limits(&ty.limit),
+                describe_limits(size, max),
+                at,
+            ));
         }
+        Ok(addr)
     }
 
-    // As the last step of instantiation, invoke start function
-    pub fn execute(&mut self) -> Result<Run> {
-        // 15. If the start function is not empty, invoke it
-        if let Some(start) = &self.module.entrypoint {
-            // Execute entrypoint
-            return self.invoke_by_funcidx(start.idx).map(|_| Run::Success);
+    // https://webassembly.github.io/spec/core/exec/modules.html#memories
+    fn import_memory(
+        &mut self,
+        import: &ast::Import<'s>,
+        ty: &ast::MemType,
+        at: usize,
+    ) -> Result<usize> {
+        let addr = if let Some(exporter) = self.registered.get(import.mod_name.0.as_ref()) {
+            match self.find_export(*exporter, import, "memory", at)? {
+                ast::ExportKind::Memory(idx) => self.instances[*exporter].memories[*idx as usize],
+                kind => {
+                    return Err(Trap::incompatible_import(
+                        import,
+                        "memory",
+                        "memory".to_string(),
+                        export_kind_name(kind).to_string(),
+                        at,
+                    ))
+                }
+            }
+        } else {
+            let key = (import.mod_name.0.to_string(), import.name.0.to_string());
+            match self.host_externs.get(&key) {
+                Some(HostExtern::Memory(addr)) => *addr,
+                Some(_) => return Err(Trap::unknown_import(import, "memory", at)),
+                None => {
+                    let memory = self
+                        .importer
+                        .import_memory(&key.0, &key.1)
+                        .ok_or_else(|| Trap::unknown_import(import, "memory", at))?;
+                    self.memories.push(memory);
+                    let addr = self.memories.len() - 1;
+                    self.host_externs.insert(key, HostExtern::Memory(addr));
+                    addr
+                }
+            }
+        };
+
+        let memory = &self.memories[addr];
+        if !limits_match(memory.size().into(), memory.max(), &ty.limit) {
+            return Err(Trap::incompatible_import(
+                import,
+                "memory",
+                describe_ast_limits(&ty.limit),
+                describe_limits(memory.size().into(), memory.max()),
+                at,
+            ));
+        }
+        // https://github.com/WebAssembly/memory64/blob/main/proposals/memory64/Overview.md#import-export
+        if memory.is_memory64() != ty.memory64 {
+            fn describe(memory64: bool) -> String {
+                if memory64 { "i64" } else { "i32" }.to_string() + " memory"
+            }
+            return Err(Trap::incompatible_import(
+                import,
+                "memory",
+                describe(ty.memory64),
+                describe(memory.is_memory64()),
+                at,
+            ));
+        }
+        // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#import-export
+        if memory.is_shared() != ty.shared {
+            fn describe(shared: bool) -> String {
+                if shared {
+                    "shared memory"
+                } else {
+                    "unshared memory"
+                }
+                .to_string()
+            }
+            return Err(Trap::incompatible_import(
+                import,
+                "memory",
+                describe(ty.shared),
+                describe(memory.is_shared()),
+                at,
+            ));
         }
+        Ok(addr)
+    }
 
-        // Note: This behavior is not described in spec. But current Clang does not emit 'start' section
-        // even if a main function is included in the source. Instead, wasm-ld recognizes '_start' exported
-        // function as entrypoint. Here the behavior is implemented
-        for export in self.module.exports.iter() {
-            if export.name.0 == "_start" {
-                if let ast::ExportKind::Func(idx) = &export.kind {
-                    return self.invoke_by_funcidx(*idx).map(|_| Run::Success);
+    // https://webassembly.github.io/spec/core/exec/modules.html#globals
+    fn import_global(&mut self, import: &ast::Import<'s>, global: &ast::Global<'s>) -> Result<u32> {
+        fn describe(mutable: bool, ty: ast::ValType) -> String {
+            if mutable {
+                format!("mut {}", ty)
+            } else {
+                ty.to_string()
+            }
+        }
+
+        let at = global.start;
+        let (addr, ty, mutable) =
+            if let Some(exporter) = self.registered.get(import.mod_name.0.as_ref()) {
+                match self.find_export(*exporter, import, "global variable", at)? {
+                    ast::ExportKind::Global(idx) => {
+                        let inst = &self.instances[*exporter];
+                        let g = &inst.module.globals[*idx as usize];
+                        (inst.globals[*idx as usize], g.ty, g.mutable)
+                    }
+                    kind => {
+                        return Err(Trap::incompatible_import(
+                            import,
+                            "global variable",
+                            "global variable".to_string(),
+                            export_kind_name(kind).to_string(),
+                            at,
+                        ))
+                    }
+                }
+            } else {
+                let key = (import.mod_name.0.to_string(), import.name.0.to_string());
+                match self.host_externs.get(&key) {
+                    Some(HostExtern::Global { addr, ty, mutable }) => (*addr, *ty, *mutable),
+                    Some(_) => return Err(Trap::unknown_import(import, "global variable", at)),
+                    None => {
+                        let global = self
+                            .importer
+                            .import_global(&key.0, &key.1)
+                            .ok_or_else(|| Trap::unknown_import(import, "global variable", at))?;
+                        let (ty, mutable) = (global.valtype(), global.is_mutable());
+                        let addr = self.globals.alloc(global.get());
+                        if mutable {
+                            self.host_globals.push((addr, global));
+                        }
+                        self.host_externs
+                            .insert(key, HostExtern::Global { addr, ty, mutable });
+                        (addr, ty, mutable)
+                    }
                 }
+            };
+
+        if ty != global.ty || mutable != global.mutable {
+            return Err(Trap::incompatible_import(
+                import,
+                "global variable",
+                describe(global.mutable, global.ty),
+                describe(mutable, ty),
+                at,
+            ));
+        }
+        Ok(addr)
+    }
+
+    // https://webassembly.github.io/exception-handling/core/exec/modules.html#tags
+    // Host does not provide tags. They can be imported only from registered instances
+    fn import_tag(&self, import: &ast::Import<'s>, ty: &ast::FuncType, at: usize) -> Result<u32> {
+        let exporter = match self.registered.get(import.mod_name.0.as_ref()) {
+            Some(exporter) => *exporter,
+            None => return Err(Trap::unknown_import(import, "tag", at)),
+        };
+        let addr = match self.find_export(exporter, import, "tag", at)? {
+            ast::ExportKind::Tag(idx) => self.instances[exporter].tags[*idx as usize],
+            kind => {
+                return Err(Trap::incompatible_import(
+                    import,
+                    "tag",
+                    "tag".to_string(),
+                    export_kind_name(kind).to_string(),
+                    at,
+                ))
             }
+        };
+
+        let params = &self.tags[addr as usize].params;
+        if *params != ty.params {
+            let describe = |params: &[ast::ValType]| {
+                let params: Vec<_> = params.iter().map(AsRef::<str>::as_ref).collect();
+                format!("[{}] -> []", params.join(" "))
+            };
+            return Err(Trap::incompatible_import(
+                import,
+                "tag",
+                describe(&ty.params),
+                describe(params),
+                at,
+            ));
         }
+        Ok(addr)
+    }
 
-        Ok(Run::Warning("no entrypoint found. 'start' section nor '_start' exported function is set to the module"))
+    // Evaluate offset of element segment or data segment
+    fn const_offset(&self, expr: &[ast::Instruction], instance: usize) -> usize {
+        let inst = &self.instances[instance];
+        let globals = &inst.module.globals;
+        // By validation of constant expression, type of the value must be i32 or i64 for 64-bit
+        // memory
+        match self
+            .globals
+            .eval_const(expr, globals, &inst.globals, &inst.funcs)
+        {
+            Value::I32(offset) => offset as u32 as usize,
+            Value::I64(offset) => to_usize(offset as u64),
+            _ => unreachable!("unexpected value for offset"),
+        }
     }
 
-    fn mem_addr(&mut self, mem: &ast::Mem) -> usize {
-        let mut addr = self.stack.pop::<i32>() as usize;
-        if let Some(off) = mem.offset {
-            addr += off as usize;
+    // Evaluate constant expression of element segment. `funcs` and `globals` are addresses of
+    // the instance being instantiated
+    fn const_ref(
+        &self,
+        module: &ast::Module<'s>,
+        expr: &[ast::Instruction],
+        funcs: &[u32],
+        globals: &[u32],
+    ) -> Option<u32> {
+        // By validation, type of the value must be a reference type
+        match self
+            .globals
+            .eval_const(expr, &module.globals, globals, funcs)
+        {
+            Value::FuncRef(r) | Value::ExternRef(r) => r,
+            _ => unreachable!("unexpected value for element"),
         }
-        addr
     }
 
-    fn load<V: LittleEndian>(&mut self, mem: &ast::Mem, at: usize) -> Result<V> {
-        let addr = self.mem_addr(mem);
-        Ok(self.memory.load(addr, at)?)
+    /// Register exports of the instance with the module name. Later instantiated modules can import
+    /// them with the name.
+    pub fn register(&mut self, name: impl Into<String>, instance: InstanceId) {
+        self.registered.insert(name.into(), instance.0);
     }
 
-    fn store<V: LittleEndian>(&mut self, mem: &ast::Mem, v: V, at: usize) -> Result<()> {
-        let addr = self.mem_addr(mem);
-        self.memory.store(addr, v, at)?;
-        Ok(())
+    /// ID of the latest instance. Methods without instance ID operate on this instance.
+    pub fn latest_instance(&self) -> InstanceId {
+        InstanceId(self.latest)
     }
 
-    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-unop
-    fn unop<T, F>(&mut self, op: F)
-    where
-        T: StackAccess + LittleEndian,
-        F: FnOnce(T) -> T,
-    {
-        // Instead of popping value and pushing the result, directly modify stack top for optimization
-        let ret = op(self.stack.top());
-        self.stack.write_top_bytes(ret);
+    pub fn module(&self) -> &'m ast::Module<'s> {
+        self.instance_module(self.latest_instance())
     }
 
-    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-binop
-    fn binop<T, F>(&mut self, op: F)
-    where
-        T: StackAccess + LittleEndian,
-        F: FnOnce(T, T) -> T,
-    {
-        // Instead of popping value and pushing the result, directly modify stack top for optimization
-        let c2 = self.stack.pop();
-        let c1 = self.stack.top();
-        let ret = op(c1, c2);
-        self.stack.write_top_bytes(ret);
+    pub fn instance_module(&self, instance: InstanceId) -> &'m ast::Module<'s> {
+        self.instances[instance.0].module
     }
 
-    fn binop_trap<T, F>(&mut self, op: F) -> Result<()>
-    where
-        T: StackAccess + LittleEndian,
-        F: FnOnce(T, T) -> Result<T>,
-    {
-        // Instead of popping value and pushing the result, directly modify stack top for optimization
-        let c2 = self.stack.pop();
-        let c1 = self.stack.top();
-        let ret = op(c1, c2)?;
-        self.stack.write_top_bytes(ret);
+    pub fn memory(&self) -> &Memory {
+        self.instance_memory(self.latest_instance())
+    }
+
+    /// Default memory (memory index 0) of the instance.
+    pub fn instance_memory(&self, instance: InstanceId) -> &Memory {
+        &self.memories[self.instances[instance.0].memories[0]]
+    }
+
+    /// Memory at the memory index of the instance. `None` when the index is out of range.
+    pub fn instance_memory_at(&self, instance: InstanceId, idx: u32) -> Option<&Memory> {
+        let addr = *self.instances[instance.0].memories.get(idx as usize)?;
+        Some(&self.memories[addr])
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.instance_global(self.latest_instance(), name)
+    }
+
+    pub fn instance_global(&self, instance: InstanceId, name: &str) -> Option<Value> {
+        let inst = &self.instances[instance.0];
+        inst.module
+            .exports
+            .iter()
+            .find_map(|e| match e.kind {
+                ast::ExportKind::Global(idx) if e.name.0 == name => Some(idx),
+                _ => None,
+            })
+            .map(|idx| {
+                let addr = inst.globals[idx as usize];
+                // Host may have updated the global variable shared with it
+                match self.host_globals.iter().find(|(a, _)| *a == addr) {
+                    Some((_, global)) => global.get(),
+                    None => self
+                        .globals
+                        .get_any(addr, inst.module.globals[idx as usize].ty),
+                }
+            })
+    }
+
+    // Copy values of global variables shared with host into the store before running guest code
+    fn load_host_globals(&mut self) {
+        for (addr, global) in &self.host_globals {
+            self.globals.set_any(*addr, global.get());
+        }
+    }
+
+    // Copy values of global variables shared with host back after running guest code
+    fn save_host_globals(&self) {
+        for (addr, global) in &self.host_globals {
+            global.replace(self.globals.get_any(*addr, global.valtype()));
+        }
+    }
+
+    pub fn get_tag(&self, name: &str) -> Option<u32> {
+        self.instance_tag(self.latest_instance(), name)
+    }
+
+    /// Address of the exported tag. It identifies the tag of `TrapReason::UncaughtException`.
+    pub fn instance_tag(&self, instance: InstanceId, name: &str) -> Option<u32> {
+        let inst = &self.instances[instance.0];
+        inst.module.exports.iter().find_map(|e| match e.kind {
+            ast::ExportKind::Tag(idx) if e.name.0 == name => Some(inst.tags[idx as usize]),
+            _ => None,
+        })
+    }
+
+    pub fn get_func_ref(&self, name: &str) -> Option<u32> {
+        self.instance_func_ref(self.latest_instance(), name)
+    }
+
+    /// Address of the exported function. It can be stored in tables and global variables as
+    /// `Value::FuncRef`.
+    pub fn instance_func_ref(&self, instance: InstanceId, name: &str) -> Option<u32> {
+        let inst = &self.instances[instance.0];
+        match self.lookup_export(instance, name)?.kind {
+            ast::ExportKind::Func(idx) => Some(inst.funcs[idx as usize]),
+            _ => None,
+        }
+    }
+
+    pub fn get_memory(&self, name: &str) -> Option<&Memory> {
+        self.instance_exported_memory(self.latest_instance(), name)
+    }
+
+    pub fn get_memory_mut(&mut self, name: &str) -> Option<&mut Memory> {
+        self.instance_exported_memory_mut(self.latest_instance(), name)
+    }
+
+    /// Memory exported by the instance with the name.
+    pub fn instance_exported_memory(&self, instance: InstanceId, name: &str) -> Option<&Memory> {
+        let addr = self.exported_memory_addr(instance, name)?;
+        Some(&self.memories[addr])
+    }
+
+    /// Memory exported by the instance with the name. Host can exchange buffers with the instance
+    /// by reading and writing the memory between invocations.
+    pub fn instance_exported_memory_mut(
+        &mut self,
+        instance: InstanceId,
+        name: &str,
+    ) -> Option<&mut Memory> {
+        let addr = self.exported_memory_addr(instance, name)?;
+        Some(&mut self.memories[addr])
+    }
+
+    fn exported_memory_addr(&self, instance: InstanceId, name: &str) -> Option<usize> {
+        match self.lookup_export(instance, name)?.kind {
+            ast::ExportKind::Memory(idx) => Some(self.instances[instance.0].memories[idx as usize]),
+            _ => None,
+        }
+    }
+
+    pub fn get_table(&self, name: &str) -> Option<&Table> {
+        self.instance_exported_table(self.latest_instance(), name)
+    }
+
+    /// Table exported by the instance with the name. Elements are addresses of functions for
+    /// funcref tables.
+    pub fn instance_exported_table(&self, instance: InstanceId, name: &str) -> Option<&Table> {
+        match self.lookup_export(instance, name)?.kind {
+            ast::ExportKind::Table(idx) => {
+                Some(&self.tables[self.instances[instance.0].tables[idx as usize]])
+            }
+            _ => None,
+        }
+    }
+
+    pub fn set_table_elem(&mut self, name: &str, idx: u32, val: Value) -> Result<()> {
+        self.instance_set_table_elem(self.latest_instance(), name, idx, val)
+    }
+
+    /// Set the reference value to the element of the exported table. Type of the value must match
+    /// to the element type of the table.
+    pub fn instance_set_table_elem(
+        &mut self,
+        instance: InstanceId,
+        name: &str,
+        idx: u32,
+        val: Value,
+    ) -> Result<()> {
+        let export = self.lookup_export(instance, name);
+        let (tableidx, start) = match export.map(|e| (&e.kind, e.start)) {
+            Some((ast::ExportKind::Table(tableidx), start)) => (*tableidx, start),
+            _ => return Err(Self::unknown_export(name, "table")),
+        };
+        let addr = self.instances[instance.0].tables[tableidx as usize];
+        let expected = self.tables[addr].elem_type().into();
+        let elem = self.check_extern_value(name, expected, &val, start)?;
+        self.tables[addr].set(idx as usize, elem, start)
+    }
+
+    /// Set the value to the exported mutable global variable. Type of the value must match to the
+    /// type of the global variable.
+    pub fn set_global(&mut self, name: &str, val: Value) -> Result<()> {
+        self.instance_set_global(self.latest_instance(), name, val)
+    }
+
+    pub fn instance_set_global(
+        &mut self,
+        instance: InstanceId,
+        name: &str,
+        val: Value,
+    ) -> Result<()> {
+        let export = self.lookup_export(instance, name);
+        let (globalidx, start) = match export.map(|e| (&e.kind, e.start)) {
+            Some((ast::ExportKind::Global(globalidx), start)) => (*globalidx, start),
+            _ => return Err(Self::unknown_export(name, "global variable")),
+        };
+        let inst = &self.instances[instance.0];
+        let global = &inst.module.globals[globalidx as usize];
+        if !global.mutable {
+            return Err(Trap::new(
+                TrapReason::SetImmutableGlobal {
+                    name: name.to_string(),
+                },
+                start,
+            ));
+        }
+        let addr = inst.globals[globalidx as usize];
+        self.check_extern_value(name, global.ty, &val, start)?;
+        self.globals.set_any(addr, val);
+        self.save_host_globals();
         Ok(())
     }
 
-    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-testop
-    fn testop<T, F>(&mut self, op: F)
-    where
-        T: StackAccess + LittleEndian,
-        F: FnOnce(T) -> bool,
-    {
-        let ret = op(self.stack.top());
-        self.stack.write_top::<T, i32>(if ret { 1 } else { 0 });
+    // Find an export of the instance by name for accessing it from host
+    fn lookup_export(&self, instance: InstanceId, name: &str) -> Option<&'m ast::Export<'s>> {
+        let module = self.instances[instance.0].module;
+        module.exports.iter().find(|e| e.name.0 == name)
     }
 
-    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-relop
-    fn relop<T, F>(&mut self, op: F)
-    where
-        T: StackAccess + LittleEndian,
-        F: FnOnce(T, T) -> bool,
-    {
-        let c2 = self.stack.pop();
-        let c1 = self.stack.top();
-        let ret = op(c1, c2);
-        self.stack.write_top::<T, i32>(if ret { 1i32 } else { 0 });
+    fn unknown_export(name: &str, kind: &'static str) -> Box<Trap> {
+        Trap::new(
+            TrapReason::UnknownExport {
+                name: name.to_string(),
+                kind,
+            },
+            0,
+        )
     }
 
-    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-cvtop
-    fn cvtop<T, U, F>(&mut self, op: F)
-    where
-        T: StackAccess,
-        U: StackAccess + LittleEndian + AsValType,
-        F: FnOnce(T) -> U,
-    {
-        let ret = op(self.stack.top());
-        self.stack.write_top::<T, U>(ret);
+    // Check the value given by host before storing it to the exported global variable or table.
+    // Function reference must refer to a function in the store since it may be called later.
+    // Returns the reference for reference types
+    fn check_extern_value(
+        &self,
+        name: &str,
+        expected: ast::ValType,
+        val: &Value,
+        start: usize,
+    ) -> Result<Option<u32>> {
+        let actual = val.valtype();
+        if actual != expected {
+            return Err(Trap::new(
+                TrapReason::ExportTypeMismatch {
+                    name: name.to_string(),
+                    expected,
+                    actual,
+                },
+                start,
+            ));
+        }
+        match val {
+            Value::FuncRef(Some(addr)) if *addr as usize >= self.funcs.len() => {
+                Err(Trap::new(TrapReason::UnknownFuncRef(*addr), start))
+            }
+            Value::FuncRef(r) | Value::ExternRef(r) => Ok(*r),
+            _ => Ok(None),
+        }
     }
-}
 
-trait Execute<'f, 'm, 's, I: Importer> {
-    fn execute(&self, machine: &mut Machine<'m, 's, I>, frame: &CallFrame<'f>) -> ExecResult;
-}
+    // Module of the function currently being executed
+    fn current_module(&self) -> &'m ast::Module<'s> {
+        self.instances[self.current].module
+    }
+
+    fn current_memory(&mut self, memidx: u32) -> &mut Memory {
+        &mut self.memories[self.instances[self.current].memories[memidx as usize]]
+    }
+
+    fn table_addr(&self, tableidx: u32) -> usize {
+        self.instances[self.current].tables[tableidx as usize]
+    }
+
+    fn global_addr(&self, globalidx: u32) -> u32 {
+        self.instances[self.current].globals[globalidx as usize]
+    }
 
-// https://webassembly.github.io/spec/core/exec/instructions.html#blocks
-impl<'f, 'm, 's, I: Importer> Execute<'f, 'm, 's, I> for Vec<ast::Instruction> {
-    fn execute(&self, machine: &mut Machine<'m, 's, I>, frame: &CallFrame<'f>) -> ExecResult {
-        // Run instruction sequence as block
-        for insn in self.iter() {
-            match insn.execute(machine, frame)? {
-                ExecState::Continue => {}
-                state => return Ok(state), // Stop executing this block on return or break
+    fn invoke_import(
+        &mut self,
+        import: &ast::Import<'s>,
+        instance: usize,
+        pos: usize,
+    ) -> Result<()> {
+        self.save_host_globals();
+        let mut memories = Memories::new(&mut self.memories, &self.instances[instance].memories);
+        let result = self.importer.call(
+            &import.mod_name.0,
+            &import.name.0,
+            &mut self.stack,
+            &mut memories,
+        );
+        self.load_host_globals();
+        match result {
+            Ok(()) => Ok(()),
+            Err(ImportInvokeError::Fatal { message }) => Err(Trap::new(
+                TrapReason::ImportFuncCallFail {
+                    mod_name: import.mod_name.0.to_string(),
+                    name: import.name.0.to_string(),
+                    msg: message,
+                },
+                pos,
+            )),
+            // Exit is propagated as trap to unwind the execution. It is converted into the result
+            // of execution by `exited`
+            Err(ImportInvokeError::Exit { status }) => {
+                Err(Trap::new(TrapReason::Exit { status }, pos))
             }
         }
-        Ok(ExecState::Continue)
     }
-}
 
-// https://webassembly.github.io/spec/core/exec/instructions.html
-impl<'f, 'm, 's, I: Importer> Execute<'f, 'm, 's, I> for ast::Instruction {
-    #[allow(clippy::cognitive_complexity)]
-    fn execute(&self, machine: &mut Machine<'m, 's, I>, frame: &CallFrame<'f>) -> ExecResult {
-        use ast::InsnKind::*;
-        #[allow(clippy::float_cmp)]
-        match &self.kind {
-            // Control instructions
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-block
-            Block { ty, body } => {
-                let label = machine.stack.push_label(*ty);
-                match body.execute(machine, frame)? {
-                    ExecState::Continue => {}
-                    ExecState::Ret => return Ok(ExecState::Ret),
-                    ExecState::Breaking(0) => {}
-                    ExecState::Breaking(level) => return Ok(ExecState::Breaking(level - 1)),
-                }
-                machine.stack.pop_label(label);
-            }
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-loop
-            Loop { ty, body } => loop {
-                // Note: Difference between block and loop is the position on breaking. When reaching
-                // to the end of instruction sequence, loop instruction ends execution of subsequence.
-                let label = machine.stack.push_label(*ty);
-                match body.execute(machine, frame)? {
-                    ExecState::Continue => {
-                        machine.stack.pop_label(label);
-                        break;
+    // Position of the local variable of the function being executed on stack
+    fn local_idx(&self, localidx: u32) -> usize {
+        self.frames[self.frames.len() - 1].base + localidx as usize
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#function-calls
+    // Call function by its address in the store. It may be defined in another instance. Imported
+    // function is invoked immediately. Otherwise a new frame is pushed and its body is executed by
+    // the interpreter loop
+    fn call(&mut self, funcaddr: u32) -> Result<()> {
+        let FuncInst { instance, idx } = self.funcs[funcaddr as usize];
+        let module = self.instances[instance].module;
+        let func = &module.funcs[idx as usize];
+        let fty = &module.types[func.idx as usize];
+
+        let locals = match &func.kind {
+            ast::FuncKind::Import(i) => return self.invoke_import(i, instance, func.start),
+            ast::FuncKind::Body { locals, .. } => locals,
+        };
+
+        // Note: The spec does not define limits of call stack. They are implementation-defined
+        // https://webassembly.github.io/spec/core/appendix/implementation.html#execution
+        if self.frames.len() >= self.max_call_depth {
+            return Err(Trap::new(
+                TrapReason::StackExhausted {
+                    kind: "call stack",
+                    limit: self.max_call_depth,
+                },
+                func.start,
+            ));
+        }
+
+        // Push call frame. Note: Params were already pushed to stack
+        let base = self.stack.len() - fty.params.len();
+
+        self.stack.extend_zero_values(locals.len());
+
+        // Values pushed while executing the function body are bounded by validation. Checking the
+        // size at function entry is sufficient
+        if self.stack.size() > self.max_stack_size {
+            return Err(Trap::new(
+                TrapReason::StackExhausted {
+                    kind: "value stack",
+                    limit: self.max_stack_size,
+                },
+                func.start,
+            ));
+        }
+
+        self.frames.push(Frame {
+            base,
+            func: funcaddr,
+            code: self.instances[instance].code[idx as usize].clone(),
+            pc: 0,
+            caller: self.current,
+            results: fty.results.len(),
+        });
+
+        self.current = instance;
+        Ok(())
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#returning-from-a-function
+    fn return_from_func(&mut self) {
+        let frame = self.frames.pop().unwrap();
+        // Pop call frame. Result values are carried over
+        self.stack.unwind(frame.base, frame.results);
+        // Check emptiness first since returning from function is hot and exceptions are rare
+        if !self.caught.is_empty() {
+            self.discard_caught(frame.base);
+        }
+        self.current = frame.caller;
+    }
+
+    // Exceptions caught by 'catch' clauses at or above the height were discarded from stack
+    #[cold]
+    fn discard_caught(&mut self, height: usize) {
+        while matches!(self.caught.last(), Some((h, _)) if *h >= height) {
+            self.caught.pop();
+        }
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-br
+    // Returns position of the next instruction
+    fn br(&mut self, br: &Branch) -> usize {
+        let base = self.frames[self.frames.len() - 1].base;
+        self.stack.unwind(base + br.height, br.keep);
+        br.target
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-return-call
+    // The frame of the current function is popped before calling the callee so that the call stack
+    // and the value stack do not grow with tail calls. Arguments on top of stack are moved to the
+    // base of the popped frame
+    fn return_call(&mut self, funcaddr: u32) -> Result<()> {
+        let FuncInst { instance, idx } = self.funcs[funcaddr as usize];
+        let module = self.instances[instance].module;
+        let params = module.types[module.funcs[idx as usize].idx as usize]
+            .params
+            .len();
+        let frame = self.frames.pop().unwrap();
+        self.stack.unwind(frame.base, params);
+        self.discard_caught(frame.base);
+        // Callee returns to the caller of the current function
+        self.current = frame.caller;
+        self.call(funcaddr)
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-call-indirect
+    fn call_indirect(&mut self, tableidx: u32, typeidx: u32, pos: usize) -> Result<()> {
+        let funcaddr = self.indirect_funcaddr(tableidx, typeidx, pos)?;
+        self.call(funcaddr)
+    }
+
+    // Look up the function in the table with the index popped from stack and check its signature
+    fn indirect_funcaddr(&mut self, tableidx: u32, typeidx: u32, pos: usize) -> Result<u32> {
+        let expected = &self.current_module().types[typeidx as usize];
+        let elemidx: i32 = self.stack.pop();
+        let table = &self.tables[self.table_addr(tableidx)];
+        let funcaddr = table.at(elemidx as usize, pos)?;
+        // Function in table may be defined in another instance
+        let FuncInst { instance, idx } = self.funcs[funcaddr as usize];
+        let module = self.instances[instance].module;
+        let actual = &module.types[module.funcs[idx as usize].idx as usize];
+        if expected.params.iter().ne(actual.params.iter())
+            || expected.results.iter().ne(actual.results.iter())
+        {
+            return Err(Trap::new(
+                TrapReason::FuncSignatureMismatch {
+                    import: None,
+                    expected_params: expected.params.clone().into_boxed_slice(),
+                    expected_results: expected.results.clone().into_boxed_slice(),
+                    actual_params: actual.params.clone().into_boxed_slice(),
+                    actual_results: actual.results.clone().into_boxed_slice(),
+                },
+                pos,
+            ));
+        }
+        Ok(funcaddr)
+    }
+
+    // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#throwing-an-exception
+    // Pop call frames until a handler catching the exception is found. Execution continues from the
+    // 'catch' clause of the handler. When no handler catches it, the execution traps
+    fn throw(&mut self, exception: Exception, pos: usize) -> Result<()> {
+        while let Some(frame) = self.frames.last() {
+            if let Some((height, target, payload)) = self.find_handler(frame, exception.tag) {
+                let height = frame.base + height;
+                self.stack.unwind(height, 0);
+                self.discard_caught(height);
+                self.stack.push_slot(self.caught.len() as u128);
+                if payload {
+                    for v in exception.payload.iter() {
+                        self.stack.push_value(v.clone());
                     }
-                    ExecState::Ret => return Ok(ExecState::Ret),
-                    ExecState::Breaking(0) => continue,
-                    ExecState::Breaking(level) => return Ok(ExecState::Breaking(level - 1)),
                 }
+                self.caught.push((height, exception));
+                self.frames.last_mut().unwrap().pc = target;
+                return Ok(());
+            }
+            // Exception propagates to the caller
+            let frame = self.frames.pop().unwrap();
+            self.discard_caught(frame.base);
+            self.current = frame.caller;
+        }
+        Err(Trap::new(
+            TrapReason::UncaughtException {
+                tag: exception.tag,
+                payload: exception.payload,
             },
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-if
-            If {
-                ty,
-                then_body,
-                else_body,
-            } => {
-                let cond: i32 = machine.stack.pop();
-                let label = machine.stack.push_label(*ty);
-                let insns = if cond != 0 { then_body } else { else_body };
-                match insns.execute(machine, frame)? {
-                    ExecState::Continue => {}
-                    ExecState::Ret => return Ok(ExecState::Ret),
-                    ExecState::Breaking(0) => {}
-                    ExecState::Breaking(level) => return Ok(ExecState::Breaking(level - 1)),
+            pos,
+        ))
+    }
+
+    // Find the handler catching the exception thrown in the function of the frame. Returns the
+    // height of the handler, the start of 'catch' clause and whether the payload is pushed
+    fn find_handler(&self, frame: &Frame<'m>, tag: u32) -> Option<(usize, usize, bool)> {
+        let FuncInst { instance, idx } = self.funcs[frame.func as usize];
+        let inst = &self.instances[instance];
+        let handlers = &inst.handlers[idx as usize];
+        // Position of the instruction which threw the exception or called the throwing function
+        let pc = frame.pc - 1;
+        let covers = |h: &Handler| h.start <= pc && pc < h.end;
+
+        let mut found = handlers.iter().rposition(covers);
+        while let Some(i) = found {
+            match &handlers[i].kind {
+                HandlerKind::Catch {
+                    height,
+                    catches,
+                    catch_all,
+                } => {
+                    let caught = catches
+                        .iter()
+                        .find(|(tagidx, _)| inst.tags[*tagidx as usize] == tag);
+                    if let Some((_, start)) = caught {
+                        return Some((*height, *start, true));
+                    }
+                    if let Some(start) = catch_all {
+                        return Some((*height, *start, false));
+                    }
+                    // Handlers are nested. The next outer handler also covers the position
+                    found = handlers[..i].iter().rposition(covers);
                 }
-                machine.stack.pop_label(label);
+                HandlerKind::Delegate(target) => found = *target,
             }
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-unreachable
-            Unreachable => return Err(Trap::new(TrapReason::ReachUnreachable, self.start)),
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-nop
-            Nop => { /* yay! nothing to do */ }
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-br
-            Br(labelidx) => return Ok(ExecState::Breaking(*labelidx)),
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-br-if
-            BrIf(labelidx) => {
-                let cond: i32 = machine.stack.pop();
-                if cond != 0 {
-                    return Ok(ExecState::Breaking(*labelidx));
-                }
+        }
+        None
+    }
+
+    // Returns false when remaining fuel is not enough to execute the instruction
+    fn consume_fuel(&mut self, insn: &ast::Instruction) -> bool {
+        if let Some(fuel) = self.fuel {
+            let cost = (self.fuel_cost)(&insn.kind);
+            if fuel < cost {
+                return false;
             }
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-br-table
-            BrTable {
-                labels,
-                default_label,
-            } => {
-                let idx: i32 = machine.stack.pop();
-                let idx = idx as usize;
-                let labelidx = if idx < labels.len() {
-                    labels[idx]
-                } else {
-                    *default_label
+            self.fuel = Some(fuel - cost);
+            self.fuel_consumed += cost;
+        }
+        true
+    }
+
+    // Execute guest code. Global variables shared with host are updated when it stops
+    fn run(&mut self, resumable: bool) -> Result<Option<Suspension>> {
+        let result = self.run_frames(resumable);
+        self.save_host_globals();
+        result
+    }
+
+    // Interpreter loop. Execute instructions until all frames are popped or execution is suspended.
+    // When `resumable` is false, the execution traps instead of being suspended
+    fn run_frames(&mut self, resumable: bool) -> Result<Option<Suspension>> {
+        // Code and position of the current frame are cached in local variables while executing the
+        // function. They are written back to the frame on calling another function
+        while let Some(frame) = self.frames.last() {
+            let code = frame.code.clone();
+            let mut pc = frame.pc;
+
+            loop {
+                let op = match code.get(pc) {
+                    Some(op) => op,
+                    None => {
+                        // Reached the end of function body
+                        self.return_from_func();
+                        break;
+                    }
                 };
-                return Ok(ExecState::Breaking(labelidx));
-            }
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-return
-            Return => return Ok(ExecState::Ret),
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-call
-            Call(funcidx) => {
-                machine.invoke_by_funcidx(*funcidx)?;
-            }
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-call-indirect
-            CallIndirect(typeidx) => {
-                let expected = &machine.module.types[*typeidx as usize];
-                let elemidx: i32 = machine.stack.pop();
-                let funcidx = machine.table.at(elemidx as usize, self.start)?;
-                let func = &machine.module.funcs[funcidx as usize];
-                let actual = &machine.module.types[func.idx as usize];
-                if expected.params.iter().ne(actual.params.iter())
-                    || expected.results.iter().ne(actual.results.iter())
-                {
-                    return Err(Trap::new(
-                        TrapReason::FuncSignatureMismatch {
-                            import: None,
-                            expected_params: expected.params.clone().into_boxed_slice(),
-                            expected_results: expected.results.clone().into_boxed_slice(),
-                            actual_params: actual.params.clone().into_boxed_slice(),
-                            actual_results: actual.results.clone().into_boxed_slice(),
-                        },
-                        self.start,
-                    ));
+
+                if let Some(insn) = op.insn() {
+                    let suspension = if self.interrupt.load(Ordering::Relaxed) {
+                        self.interrupt.store(false, Ordering::Relaxed);
+                        Some(Suspension::Interrupted)
+                    } else if !self.consume_fuel(insn) {
+                        Some(Suspension::OutOfFuel)
+                    } else {
+                        None
+                    };
+
+                    if let Some(suspension) = suspension {
+                        if resumable {
+                            // Resume from this instruction later
+                            self.frames.last_mut().unwrap().pc = pc;
+                            return Ok(Some(suspension));
+                        }
+                        let reason = match suspension {
+                            Suspension::Interrupted => TrapReason::Interrupted,
+                            Suspension::OutOfFuel => TrapReason::OutOfFuel {
+                                consumed: self.fuel_consumed,
+                            },
+                        };
+                        return Err(Trap::new(reason, insn.start));
+                    }
+                }
+
+                pc += 1;
+                match op {
+                    Op::Exec(insn) => self.execute_insn(insn)?,
+                    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-if
+                    Op::If(_, else_pc) => {
+                        let cond: i32 = self.stack.pop();
+                        if cond == 0 {
+                            pc = *else_pc;
+                        }
+                    }
+                    Op::Jump(target) => pc = *target,
+                    Op::Br(_, br) => pc = self.br(br),
+                    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-br-if
+                    Op::BrIf(_, br) => {
+                        let cond: i32 = self.stack.pop();
+                        if cond != 0 {
+                            pc = self.br(br);
+                        }
+                    }
+                    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-br-table
+                    Op::BrTable(_, brs) => {
+                        let idx: i32 = self.stack.pop();
+                        let idx = idx as u32 as usize;
+                        let br = brs.get(idx).unwrap_or(&brs[brs.len() - 1]);
+                        pc = self.br(br);
+                    }
+                    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-return
+                    Op::Return(_) => {
+                        self.return_from_func();
+                        break;
+                    }
+                    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-call
+                    Op::Call(_, funcidx) => {
+                        self.frames.last_mut().unwrap().pc = pc;
+                        let funcaddr = self.instances[self.current].funcs[*funcidx as usize];
+                        self.call(funcaddr)?;
+                        break;
+                    }
+                    Op::CallIndirect(insn, tableidx, typeidx) => {
+                        self.frames.last_mut().unwrap().pc = pc;
+                        self.call_indirect(*tableidx, *typeidx, insn.start)?;
+                        break;
+                    }
+                    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-return-call
+                    Op::ReturnCall(_, funcidx) => {
+                        let funcaddr = self.instances[self.current].funcs[*funcidx as usize];
+                        self.return_call(funcaddr)?;
+                        break;
+                    }
+                    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-return-call-indirect
+                    Op::ReturnCallIndirect(insn, tableidx, typeidx) => {
+                        let funcaddr = self.indirect_funcaddr(*tableidx, *typeidx, insn.start)?;
+                        self.return_call(funcaddr)?;
+                        break;
+                    }
+                    Op::Throw(insn, tagidx) => {
+                        self.frames.last_mut().unwrap().pc = pc;
+                        let tag = self.instances[self.current].tags[*tagidx as usize];
+                        let mut payload: Vec<_> = self.tags[tag as usize]
+                            .params
+                            .iter()
+                            .rev()
+                            .map(|ty| self.stack.pop_value(*ty))
+                            .collect();
+                        payload.reverse();
+                        let payload = payload.into_boxed_slice();
+                        self.throw(Exception { tag, payload }, insn.start)?;
+                        break;
+                    }
+                    Op::Rethrow(insn, slot) => {
+                        self.frames.last_mut().unwrap().pc = pc;
+                        let base = self.frames[self.frames.len() - 1].base;
+                        let idx = self.stack.read_slot(base + slot) as usize;
+                        let exception = self.caught[idx].1.clone();
+                        self.throw(exception, insn.start)?;
+                        break;
+                    }
+                    Op::EndCatch(br) => {
+                        // The caught exception is removed from stack at the end of 'catch' clause
+                        pc = self.br(br);
+                        let base = self.frames[self.frames.len() - 1].base;
+                        self.discard_caught(base + br.height);
+                    }
                 }
-                machine.invoke_by_funcidx(funcidx)?;
             }
-            // Parametric instructions
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-drop
-            Drop => {
-                machine.stack.pop::<Value>();
+        }
+        Ok(None)
+    }
+
+    // Start executing the function as an entrypoint of execution
+    fn start_invocation(
+        &mut self,
+        funcaddr: u32,
+        args: &[Value],
+        resumable: bool,
+    ) -> Result<Invocation> {
+        let entry = self.enter_invocation(funcaddr, |stack| {
+            for arg in args {
+                stack.push_value(arg.clone());
             }
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-select
-            Select => {
-                let cond: i32 = machine.stack.pop();
-                let val2: Value = machine.stack.pop();
-                let val1: Value = machine.stack.pop();
-                machine.stack.push(if cond != 0 { val1 } else { val2 });
+        })?;
+        self.continue_invocation(entry, resumable)
+    }
+
+    // Push arguments and call the function. Execution of the function body is started by `run`
+    fn enter_invocation(
+        &mut self,
+        funcaddr: u32,
+        push_args: impl FnOnce(&mut Stack),
+    ) -> Result<Entry<'m>> {
+        if let Some(entry) = self.suspended.take() {
+            // Starting a new invocation discards the suspended execution
+            self.abort(entry);
+        }
+        self.load_host_globals();
+
+        let FuncInst { instance, idx } = self.funcs[funcaddr as usize];
+        let module = self.instances[instance].module;
+        let fty = &module.types[module.funcs[idx as usize].idx as usize];
+        let entry = Entry {
+            label: self.stack.push_label(0),
+            results: &fty.results,
+            caller: self.current,
+        };
+
+        // Push values to stack for invoking the function
+        push_args(&mut self.stack);
+
+        if let Err(err) = self.call(funcaddr) {
+            self.abort(entry);
+            return Err(err);
+        }
+        Ok(entry)
+    }
+
+    // Invoke the function with arguments and results which were already type-checked by
+    // `get_typed_func`. Values are directly pushed to and popped from stack
+    pub(crate) fn invoke_typed<P: WasmParams, R: WasmResults>(
+        &mut self,
+        funcaddr: u32,
+        params: P,
+    ) -> Result<R> {
+        let entry = self.enter_invocation(funcaddr, |stack| params.push(stack))?;
+        match self.run(false) {
+            Ok(None) => {
+                self.caught.clear();
+                Ok(R::pop(&mut self.stack))
             }
-            // Variable instructions
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-local-get
-            LocalGet(localidx) => {
-                let addr = frame.local_addr(*localidx);
-                match frame.local_type(*localidx) {
-                    ast::ValType::I32 => machine.stack.push(machine.stack.read::<i32>(addr)),
-                    ast::ValType::I64 => machine.stack.push(machine.stack.read::<i64>(addr)),
-                    ast::ValType::F32 => machine.stack.push(machine.stack.read::<f32>(addr)),
-                    ast::ValType::F64 => machine.stack.push(machine.stack.read::<f64>(addr)),
-                }
+            Ok(Some(_)) => unreachable!("execution is never suspended when not resumable"),
+            Err(err) => {
+                self.abort(entry);
+                Err(err)
             }
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-local-set
-            LocalSet(localidx) => {
-                let addr = frame.local_addr(*localidx);
-                let val = machine.stack.pop();
-                machine.stack.write_any(addr, val);
+        }
+    }
+
+    fn continue_invocation(&mut self, entry: Entry<'m>, resumable: bool) -> Result<Invocation> {
+        match self.run(resumable) {
+            Ok(None) => {
+                self.caught.clear();
+                let mut ret: Vec<_> = entry
+                    .results
+                    .iter()
+                    .rev()
+                    .map(|ty| self.stack.pop_value(*ty))
+                    .collect();
+                ret.reverse();
+                Ok(Invocation::Finished(ret))
             }
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-local-tee
-            LocalTee(localidx) => {
-                // Like local.set, but it does not change stack
-                let addr = frame.local_addr(*localidx);
-                let val = machine.stack.top();
-                machine.stack.write_any(addr, val);
+            Ok(Some(suspension)) => {
+                self.suspended = Some(entry);
+                Ok(Invocation::Suspended(suspension))
             }
-            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-global-get
-            GlobalGet(globalidx) => match machine.module.globals[*globalidx as usize].ty {
-                ast::ValType::I32 => machine.stack.push(machine.globals.get::<i32>(*globalidx)),
-                ast::ValType::I64 => machine.stack.push(machine.globals.get::<i64>(*globalidx)),
-                ast::ValType::F32 => machine.stack.push(machine.globals.get::<f32>(*globalidx)),
-                ast::ValType::F64 => machine.stack.push(machine.globals.get::<f64>(*globalidx)),
+            Err(err) => {
+                self.abort(entry);
+                Err(err)
+            }
+        }
+    }
+
+    // Discard the execution. Values left on stack are removed so that the machine can continue to
+    // be used
+    fn abort(&mut self, entry: Entry<'m>) {
+        self.frames.clear();
+        self.caught.clear();
+        self.stack.pop_label(entry.label);
+        self.current = entry.caller;
+    }
+
+    // Invoke function and run it until the end. It traps instead of being suspended
+    fn invoke_by_funcaddr(&mut self, funcaddr: u32, args: &[Value]) -> Result<Vec<Value>> {
+        match self.start_invocation(funcaddr, args, false)? {
+            Invocation::Finished(ret) => Ok(ret),
+            Invocation::Suspended(_) => {
+                unreachable!("execution is never suspended when not resumable")
+            }
+            Invocation::Exited(_) => unreachable!("exit is reported as trap"),
+        }
+    }
+
+    // Convert exit requested by host function into the result of resumable invocation
+    fn exited(result: Result<Invocation>) -> Result<Invocation> {
+        match result {
+            Err(err) => match err.reason {
+                TrapReason::Exit { status } => Ok(Invocation::Exited(status)),
+                _ => Err(err),
+            },
+            ok => ok,
+        }
+    }
+
+    pub fn invoke(&mut self, name: impl AsRef<str>, args: &[Value]) -> Result<Vec<Value>> {
+        self.invoke_instance(self.latest_instance(), name, args)
+    }
+
+    pub fn invoke_instance(
+        &mut self,
+        instance: InstanceId,
+        name: impl AsRef<str>,
+        args: &[Value],
+    ) -> Result<Vec<Value>> {
+        let funcaddr = self.func_to_invoke(instance, name.as_ref(), args)?;
+        self.invoke_by_funcaddr(funcaddr, args)
+    }
+
+    /// Look up the exported function and return a handle to call it with Rust values. Signature of
+    /// the function is checked here against `P` and `R` so calling the handle doesn't check types.
+    ///
+    /// ```ignore
+    /// let add = machine.get_typed_func::<(i32, i32), i32>("add")?;
+    /// assert_eq!(add.call(&mut machine, (1, 2))?, 3);
+    /// ```
+    pub fn get_typed_func<P: WasmParams, R: WasmResults>(
+        &self,
+        name: impl AsRef<str>,
+    ) -> Result<TypedFunc<P, R>> {
+        self.instance_typed_func(self.latest_instance(), name)
+    }
+
+    pub fn instance_typed_func<P: WasmParams, R: WasmResults>(
+        &self,
+        instance: InstanceId,
+        name: impl AsRef<str>,
+    ) -> Result<TypedFunc<P, R>> {
+        let (funcidx, start) = self.exported_func(instance, name.as_ref())?;
+        let inst = &self.instances[instance.0];
+        let module = inst.module;
+        let fty = &module.types[module.funcs[funcidx as usize].idx as usize];
+        let (params, results) = (P::valtypes(), R::valtypes());
+        if fty.params != params || fty.results != results {
+            return Err(Trap::new(
+                TrapReason::FuncSignatureMismatch {
+                    import: None,
+                    expected_params: params.into(),
+                    expected_results: results.into(),
+                    actual_params: fty.params.iter().copied().collect(),
+                    actual_results: fty.results.iter().copied().collect(),
+                },
+                start,
+            ));
+        }
+        Ok(TypedFunc::new(inst.funcs[funcidx as usize]))
+    }
+
+    /// Invoke the exported function like `Machine::invoke`, but the execution is suspended instead
+    /// of trapping when fuel runs out or it is interrupted. Suspended execution can be continued
+    /// from the same point by `Machine::resume`. When a host function terminates the execution with
+    /// exit status, `Invocation::Exited` is returned instead of `TrapReason::Exit` trap.
+    pub fn invoke_resumable(
+        &mut self,
+        name: impl AsRef<str>,
+        args: &[Value],
+    ) -> Result<Invocation> {
+        self.invoke_instance_resumable(self.latest_instance(), name, args)
+    }
+
+    pub fn invoke_instance_resumable(
+        &mut self,
+        instance: InstanceId,
+        name: impl AsRef<str>,
+        args: &[Value],
+    ) -> Result<Invocation> {
+        let funcaddr = self.func_to_invoke(instance, name.as_ref(), args)?;
+        Self::exited(self.start_invocation(funcaddr, args, true))
+    }
+
+    /// Resume the suspended execution. Please add fuel by `Machine::set_fuel` before resuming
+    /// execution suspended by `Suspension::OutOfFuel`.
+    pub fn resume(&mut self) -> Result<Invocation> {
+        match self.suspended.take() {
+            Some(entry) => {
+                self.load_host_globals();
+                Self::exited(self.continue_invocation(entry, true))
+            }
+            None => Err(Trap::new(TrapReason::NotSuspended, 0)),
+        }
+    }
+
+    /// Returns true when there is suspended execution which can be resumed.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
+
+    // Find exported function by name. Returns index of the function and offset of the export
+    fn exported_func(&self, instance: InstanceId, name: &str) -> Result<(u32, usize)> {
+        let exports = &self.instances[instance.0].module.exports;
+        for export in exports {
+            if export.name.0 == name {
+                let actual = match export.kind {
+                    ast::ExportKind::Func(idx) => return Ok((idx, export.start)),
+                    ast::ExportKind::Table(_) => "table",
+                    ast::ExportKind::Memory(_) => "memory",
+                    ast::ExportKind::Global(_) => "global variable",
+                    ast::ExportKind::Tag(_) => "tag",
+                };
+                return Err(Trap::new(
+                    TrapReason::WrongInvokeTarget {
+                        name: name.to_string(),
+                        actual: Some(actual),
+                    },
+                    export.start,
+                ));
+            }
+        }
+        Err(Trap::new(
+            TrapReason::WrongInvokeTarget {
+                name: name.to_string(),
+                actual: None,
             },
+            0,
+        ))
+    }
+
+    // Find exported function to invoke and check arguments. Returns address of the function
+    fn func_to_invoke(&self, instance: InstanceId, name: &str, args: &[Value]) -> Result<u32> {
+        let inst = &self.instances[instance.0];
+        let module = inst.module;
+        let (funcidx, start) = self.exported_func(instance, name)?;
+        let arg_types = &module.types[module.funcs[funcidx as usize].idx as usize].params;
+
+        // Check parameter types
+        if args
+            .iter()
+            .map(Value::valtype)
+            .ne(arg_types.iter().copied())
+        {
+            return Err(Trap::new(
+                TrapReason::InvokeInvalidArgs {
+                    name: name.to_string(),
+                    args: args.iter().cloned().collect(),
+                    arg_types: arg_types.iter().copied().collect(),
+                },
+                start,
+            ));
+        }
+
+        Ok(inst.funcs[funcidx as usize])
+    }
+
+    // As the last step of instantiation, invoke start function
+    pub fn execute(&mut self) -> Result<Run> {
+        self.execute_instance(self.latest_instance())
+    }
+
+    pub fn execute_instance(&mut self, instance: InstanceId) -> Result<Run> {
+        let inst = &self.instances[instance.0];
+
+        // 15. If the start function is not empty, invoke it
+        if let Some(start) = &inst.module.entrypoint {
+            // Execute entrypoint
+            let funcaddr = inst.funcs[start.idx as usize];
+            return self.run_entrypoint(funcaddr);
+        }
+
+        // Note: This behavior is not described in spec. But current Clang does not emit 'start' section
+        // even if a main function is included in the source. Instead, wasm-ld recognizes '_start' exported
+        // function as entrypoint. Here the behavior is implemented
+        for export in inst.module.exports.iter() {
+            if export.name.0 == "_start" {
+                if let ast::ExportKind::Func(idx) = &export.kind {
+                    let funcaddr = inst.funcs[*idx as usize];
+                    return self.run_entrypoint(funcaddr);
+                }
+            }
+        }
+
+        Ok(Run::Warning("no entrypoint found. 'start' section nor '_start' exported function is set to the module"))
+    }
+
+    fn run_entrypoint(&mut self, funcaddr: u32) -> Result<Run> {
+        match Self::exited(self.start_invocation(funcaddr, &[], false))? {
+            Invocation::Finished(_) => Ok(Run::Success),
+            Invocation::Exited(status) => Ok(Run::Exit(status)),
+            Invocation::Suspended(_) => {
+                unreachable!("execution is never suspended when not resumable")
+            }
+        }
+    }
+
+    // Address is i32 or i64 depending on index type of the memory. Since i32 value is zero-extended
+    // on stack, both can be popped as i64
+    // https://github.com/WebAssembly/memory64/blob/main/proposals/memory64/Overview.md#execution
+    fn mem_addr(&mut self, mem: &ast::Mem) -> usize {
+        let mut addr = self.stack.pop::<i64>() as u64;
+        if let Some(off) = mem.offset {
+            // Effective address of 64-bit memory may overflow. Saturated address is out of bounds
+            addr = addr.saturating_add(off);
+        }
+        to_usize(addr)
+    }
+
+    // Pop destination, source (or fill value) and length operands of bulk memory and table
+    // instructions. They are popped as i64 for operands of 64-bit memory
+    fn pop_bulk_operands(&mut self) -> (usize, usize, usize) {
+        let len = self.stack.pop::<i64>() as u64;
+        let src = self.stack.pop::<i64>() as u64;
+        let dst = self.stack.pop::<i64>() as u64;
+        (to_usize(dst), to_usize(src), to_usize(len))
+    }
+
+    fn load<V: LittleEndian>(&mut self, mem: &ast::Mem, at: usize) -> Result<V> {
+        let addr = self.mem_addr(mem);
+        self.current_memory(mem.memory).load(addr, at)
+    }
+
+    fn store<V: LittleEndian>(&mut self, mem: &ast::Mem, v: V, at: usize) -> Result<()> {
+        let addr = self.mem_addr(mem);
+        self.current_memory(mem.memory).store(addr, v, at)?;
+        Ok(())
+    }
+
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#alignment
+    // Atomic access traps when the effective address is not aligned to the access width
+    fn atomic_addr<V: LittleEndian>(&mut self, mem: &ast::Mem, at: usize) -> Result<usize> {
+        let addr = self.mem_addr(mem);
+        let align = size_of::<V>();
+        if addr & (align - 1) != 0 {
+            return Err(Trap::new(TrapReason::UnalignedAtomic { addr, align }, at));
+        }
+        Ok(addr)
+    }
+
+    fn atomic_load<V: LittleEndian>(&mut self, mem: &ast::Mem, at: usize) -> Result<V> {
+        let addr = self.atomic_addr::<V>(mem, at)?;
+        self.current_memory(mem.memory).load(addr, at)
+    }
+
+    fn atomic_store<V: LittleEndian>(&mut self, mem: &ast::Mem, v: V, at: usize) -> Result<()> {
+        let addr = self.atomic_addr::<V>(mem, at)?;
+        self.current_memory(mem.memory).store(addr, v, at)
+    }
+
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#read-modify-write
+    // Returns the value loaded before modification
+    fn atomic_rmw<V, F>(&mut self, mem: &ast::Mem, v: V, at: usize, op: F) -> Result<V>
+    where
+        V: LittleEndian + Copy,
+        F: FnOnce(V, V) -> V,
+    {
+        let addr = self.atomic_addr::<V>(mem, at)?;
+        let memory = self.current_memory(mem.memory);
+        let old: V = memory.load(addr, at)?;
+        memory.store(addr, op(old, v), at)?;
+        Ok(old)
+    }
+
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#compare-exchange
+    fn atomic_cmpxchg<V>(&mut self, mem: &ast::Mem, expected: V, new: V, at: usize) -> Result<V>
+    where
+        V: LittleEndian + Copy + PartialEq,
+    {
+        let addr = self.atomic_addr::<V>(mem, at)?;
+        let memory = self.current_memory(mem.memory);
+        let old: V = memory.load(addr, at)?;
+        if old == expected {
+            memory.store(addr, new, at)?;
+        }
+        Ok(old)
+    }
+
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#wait
+    // Returns 1 ("not-equal") or 2 ("timed-out"). Never returns 0 ("ok") since no other thread can
+    // notify the waiter. For the same reason, the wait times out immediately regardless of the
+    // timeout (negative means infinite). Blocking would only hang the host thread where neither fuel
+    // metering nor interruption can stop it
+    fn atomic_wait<V>(&mut self, mem: &ast::Mem, expected: V, at: usize) -> Result<i32>
+    where
+        V: LittleEndian + PartialEq,
+    {
+        let addr = self.atomic_addr::<V>(mem, at)?;
+        let memory = self.current_memory(mem.memory);
+        if !memory.is_shared() {
+            return Err(Trap::new(TrapReason::WaitOnUnsharedMemory, at));
+        }
+        let v: V = memory.load(addr, at)?;
+        if v != expected {
+            return Ok(1);
+        }
+        Ok(2)
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-unop
+    fn unop<T, F>(&mut self, op: F)
+    where
+        T: StackAccess,
+        F: FnOnce(T) -> T,
+    {
+        let ret = op(self.stack.top());
+        self.stack.write_top(ret);
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-binop
+    fn binop<T, F>(&mut self, op: F)
+    where
+        T: StackAccess,
+        F: FnOnce(T, T) -> T,
+    {
+        let c2 = self.stack.pop();
+        let c1 = self.stack.top();
+        let ret = op(c1, c2);
+        self.stack.write_top(ret);
+    }
+
+    fn binop_trap<T, F>(&mut self, op: F) -> Result<()>
+    where
+        T: StackAccess,
+        F: FnOnce(T, T) -> Result<T>,
+    {
+        let c2 = self.stack.pop();
+        let c1 = self.stack.top();
+        let ret = op(c1, c2)?;
+        self.stack.write_top(ret);
+        Ok(())
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-testop
+    fn testop<T, F>(&mut self, op: F)
+    where
+        T: StackAccess,
+        F: FnOnce(T) -> bool,
+    {
+        let ret = op(self.stack.top());
+        self.stack.write_top::<i32>(if ret { 1 } else { 0 });
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-relop
+    fn relop<T, F>(&mut self, op: F)
+    where
+        T: StackAccess,
+        F: FnOnce(T, T) -> bool,
+    {
+        let c2 = self.stack.pop();
+        let c1 = self.stack.top();
+        let ret = op(c1, c2);
+        self.stack.write_top::<i32>(if ret { 1 } else { 0 });
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-cvtop
+    fn cvtop<T, U, F>(&mut self, op: F)
+    where
+        T: StackAccess,
+        U: StackAccess,
+        F: FnOnce(T) -> U,
+    {
+        let ret = op(self.stack.top());
+        self.stack.write_top(ret);
+    }
+
+    // Lane-wise operations of vector instructions. Vector is interpreted as lanes of T
+    fn lanewise_unop<T, F>(&mut self, op: F)
+    where
+        T: LittleEndian,
+        F: FnMut(T) -> T,
+    {
+        self.unop::<i128, _>(|v| simd::map(v, op));
+    }
+
+    fn lanewise_binop<T, F>(&mut self, op: F)
+    where
+        T: LittleEndian,
+        F: FnMut(T, T) -> T,
+    {
+        self.binop::<i128, _>(|l, r| simd::zip(l, r, op));
+    }
+
+    // Comparison results in lanes of signed integer M whose bits are all set when true
+    fn lanewise_relop<T, M, F>(&mut self, mut op: F)
+    where
+        T: LittleEndian,
+        M: LittleEndian + From<bool> + Neg<Output = M>,
+        F: FnMut(T, T) -> bool,
+    {
+        self.binop::<i128, _>(|l, r| simd::zip(l, r, |l, r| simd::mask::<M>(op(l, r))));
+    }
+
+    fn lanewise_shift<T, F>(&mut self, mut op: F)
+    where
+        T: LittleEndian,
+        F: FnMut(T, u32) -> T,
+    {
+        let s: i32 = self.stack.pop();
+        self.lanewise_unop::<T, _>(|x| op(x, s as u32));
+    }
+
+    fn cvtop_trap<T, U, F>(&mut self, op: F, pos: usize) -> Result<()>
+    where
+        T: StackAccess,
+        U: StackAccess,
+        F: FnOnce(T) -> std::result::Result<U, TrapReason>,
+    {
+        let ret = op(self.stack.top()).map_err(|reason| Trap::new(reason, pos))?;
+        self.stack.write_top(ret);
+        Ok(())
+    }
+}
+
+// Address of 64-bit memory may not fit in usize on 32-bit platforms. Saturated value is always out
+// of bounds
+fn to_usize(u: u64) -> usize {
+    usize::try_from(u).unwrap_or(usize::MAX)
+}
+
+// https://webassembly.github.io/spec/core/exec/numerics.html#op-fnearest
+fn f32_nearest(f: f32) -> f32 {
+    // f32::round() is not available because behavior when two values are equally near is
+    // different. For example, 4.5f32.round() is 5.0 but (f32.nearest (f32.const 4.5)) is 4.0.
+    let fround = f.round();
+    if (f - fround).abs() == 0.5 && fround % 2.0 != 0.0 {
+        f.trunc()
+    } else {
+        fround
+    }
+}
+
+fn f64_nearest(f: f64) -> f64 {
+    // f64::round() is not available for the same reason as f32.nearest
+    let fround = f.round();
+    if (f - fround).abs() == 0.5 && fround % 2.0 != 0.0 {
+        f.trunc()
+    } else {
+        fround
+    }
+}
+
+fn export_kind_name(kind: &ast::ExportKind) -> &'static str {
+    match kind {
+        ast::ExportKind::Func(_) => "function",
+        ast::ExportKind::Table(_) => "table",
+        ast::ExportKind::Memory(_) => "memory",
+        ast::ExportKind::Global(_) => "global variable",
+        ast::ExportKind::Tag(_) => "tag",
+    }
+}
+
+impl<'m, 's, I: Importer> Machine<'m, 's, I> {
+    // https://webassembly.github.io/spec/core/exec/instructions.html
+    // This method is called only from the loop in `run`. It is too large to be inlined by the
+    // compiler's heuristics, but a function call per instruction makes execution much slower.
+    #[allow(clippy::cognitive_complexity)]
+    #[inline(always)]
+    fn execute_insn(&mut self, insn: &'m ast::Instruction) -> Result<()> {
+        use ast::InsnKind::*;
+        #[allow(clippy::float_cmp)]
+        match &insn.kind {
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-unreachable
+            Unreachable => return Err(Trap::new(TrapReason::ReachUnreachable, insn.start)),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-nop
+            Nop => { /* yay! nothing to do */ }
+            // Control flow instructions are compiled into other operations
+            Block { .. }
+            | Loop { .. }
+            | If { .. }
+            | Br(_)
+            | BrIf(_)
+            | BrTable { .. }
+            | Return
+            | Call(_)
+            | CallIndirect { .. }
+            | ReturnCall(_)
+            | ReturnCallIndirect { .. }
+            | Try { .. }
+            | Throw(_)
+            | Rethrow(_) => {
+                unreachable!("{} is not executed directly", insn.kind.name())
+            }
+            // Parametric instructions
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-drop
+            Drop => {
+                self.stack.pop_slot();
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-select
+            Select | TypedSelect(_) => {
+                let cond: i32 = self.stack.pop();
+                let val2 = self.stack.pop_slot();
+                if cond == 0 {
+                    self.stack.pop_slot();
+                    self.stack.push_slot(val2);
+                }
+            }
+            // Reference instructions
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-ref-null
+            RefNull(_) => self.stack.push(None::<u32>),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-ref-is-null
+            RefIsNull => {
+                let r: Option<u32> = self.stack.pop();
+                self.stack.push(r.is_none() as i32);
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-ref-func
+            RefFunc(funcidx) => {
+                let addr = self.instances[self.current].funcs[*funcidx as usize];
+                self.stack.push(Some(addr));
+            }
+            // Variable instructions
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-local-get
+            LocalGet(localidx) => {
+                let slot = self.stack.read_slot(self.local_idx(*localidx));
+                self.stack.push_slot(slot);
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-local-set
+            LocalSet(localidx) => {
+                let slot = self.stack.pop_slot();
+                self.stack.write_slot(self.local_idx(*localidx), slot);
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-local-tee
+            LocalTee(localidx) => {
+                // Like local.set, but it does not change stack
+                let slot = self.stack.top_slot();
+                self.stack.write_slot(self.local_idx(*localidx), slot);
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-global-get
+            GlobalGet(globalidx) => {
+                let addr = self.global_addr(*globalidx);
+                match self.current_module().globals[*globalidx as usize].ty {
+                    ast::ValType::I32 => self.stack.push(self.globals.get::<i32>(addr)),
+                    ast::ValType::I64 => self.stack.push(self.globals.get::<i64>(addr)),
+                    ast::ValType::F32 => self.stack.push(self.globals.get::<f32>(addr)),
+                    ast::ValType::F64 => self.stack.push(self.globals.get::<f64>(addr)),
+                    ast::ValType::V128 => self.stack.push(self.globals.get::<i128>(addr)),
+                    ast::ValType::FuncRef | ast::ValType::ExternRef => {
+                        self.stack.push(self.globals.get::<Option<u32>>(addr))
+                    }
+                }
+            }
             // https://webassembly.github.io/spec/core/exec/instructions.html#exec-global-set
-            GlobalSet(globalidx) => machine.globals.set_any(*globalidx, machine.stack.top()),
+            GlobalSet(globalidx) => {
+                let addr = self.global_addr(*globalidx);
+                let val = self
+                    .stack
+                    .pop_value(self.current_module().globals[*globalidx as usize].ty);
+                self.globals.set_any(addr, val);
+            }
             // Memory instructions
             // https://webassembly.github.io/spec/core/exec/instructions.html#and
             I32Load(mem) => {
-                let v: i32 = machine.load(mem, self.start)?;
-                machine.stack.push(v);
+                let v: i32 = self.load(mem, insn.start)?;
+                self.stack.push(v);
             }
             I64Load(mem) => {
-                let v: i64 = machine.load(mem, self.start)?;
-                machine.stack.push(v);
+                let v: i64 = self.load(mem, insn.start)?;
+                self.stack.push(v);
             }
             F32Load(mem) => {
-                let v: f32 = machine.load(mem, self.start)?;
-                machine.stack.push(v);
+                let v: f32 = self.load(mem, insn.start)?;
+                self.stack.push(v);
             }
             F64Load(mem) => {
-                let v: f64 = machine.load(mem, self.start)?;
-                machine.stack.push(v);
+                let v: f64 = self.load(mem, insn.start)?;
+                self.stack.push(v);
             }
             I32Load8S(mem) => {
-                let v: i8 = machine.load(mem, self.start)?;
-                machine.stack.push(v as i32);
+                let v: i8 = self.load(mem, insn.start)?;
+                self.stack.push(v as i32);
             }
             I32Load8U(mem) => {
-                let v: u8 = machine.load(mem, self.start)?;
-                machine.stack.push(v as i32);
+                let v: u8 = self.load(mem, insn.start)?;
+                self.stack.push(v as i32);
             }
             I32Load16S(mem) => {
-                let v: i16 = machine.load(mem, self.start)?;
-                machine.stack.push(v as i32);
+                let v: i16 = self.load(mem, insn.start)?;
+                self.stack.push(v as i32);
             }
             I32Load16U(mem) => {
-                let v: u16 = machine.load(mem, self.start)?;
-                machine.stack.push(v as i32);
+                let v: u16 = self.load(mem, insn.start)?;
+                self.stack.push(v as i32);
             }
             I64Load8S(mem) => {
-                let v: i8 = machine.load(mem, self.start)?;
-                machine.stack.push(v as i64);
+                let v: i8 = self.load(mem, insn.start)?;
+                self.stack.push(v as i64);
             }
             I64Load8U(mem) => {
-                let v: u8 = machine.load(mem, self.start)?;
-                machine.stack.push(v as i64);
+                let v: u8 = self.load(mem, insn.start)?;
+                self.stack.push(v as i64);
             }
             I64Load16S(mem) => {
-                let v: i16 = machine.load(mem, self.start)?;
-                machine.stack.push(v as i64);
+                let v: i16 = self.load(mem, insn.start)?;
+                self.stack.push(v as i64);
             }
             I64Load16U(mem) => {
-                let v: u16 = machine.load(mem, self.start)?;
-                machine.stack.push(v as i64);
+                let v: u16 = self.load(mem, insn.start)?;
+                self.stack.push(v as i64);
             }
             I64Load32S(mem) => {
-                let v: i32 = machine.load(mem, self.start)?;
-                machine.stack.push(v as i64);
+                let v: i32 = self.load(mem, insn.start)?;
+                self.stack.push(v as i64);
             }
             I64Load32U(mem) => {
-                let v: u32 = machine.load(mem, self.start)?;
-                machine.stack.push(v as i64);
+                let v: u32 = self.load(mem, insn.start)?;
+                self.stack.push(v as i64);
             }
             // https://webassembly.github.io/spec/core/exec/instructions.html#exec-storen
             I32Store(mem) => {
-                let v: i32 = machine.stack.pop();
-                machine.store(mem, v, self.start)?;
+                let v: i32 = self.stack.pop();
+                self.store(mem, v, insn.start)?;
             }
             I64Store(mem) => {
-                let v: i64 = machine.stack.pop();
-                machine.store(mem, v, self.start)?;
+                let v: i64 = self.stack.pop();
+                self.store(mem, v, insn.start)?;
             }
             F32Store(mem) => {
-                let v: f32 = machine.stack.pop();
-                machine.store(mem, v, self.start)?;
+                let v: f32 = self.stack.pop();
+                self.store(mem, v, insn.start)?;
             }
             F64Store(mem) => {
-                let v: f64 = machine.stack.pop();
-                machine.store(mem, v, self.start)?;
+                let v: f64 = self.stack.pop();
+                self.store(mem, v, insn.start)?;
             }
             I32Store8(mem) => {
-                let v: i32 = machine.stack.pop();
-                machine.store(mem, v as i8, self.start)?;
+                let v: i32 = self.stack.pop();
+                self.store(mem, v as i8, insn.start)?;
             }
             I32Store16(mem) => {
-                let v: i32 = machine.stack.pop();
-                machine.store(mem, v as i16, self.start)?;
+                let v: i32 = self.stack.pop();
+                self.store(mem, v as i16, insn.start)?;
             }
             I64Store8(mem) => {
-                let v: i64 = machine.stack.pop();
-                machine.store(mem, v as i8, self.start)?;
+                let v: i64 = self.stack.pop();
+                self.store(mem, v as i8, insn.start)?;
             }
             I64Store16(mem) => {
-                let v: i64 = machine.stack.pop();
-                machine.store(mem, v as i16, self.start)?;
+                let v: i64 = self.stack.pop();
+                self.store(mem, v as i16, insn.start)?;
             }
             I64Store32(mem) => {
-                let v: i64 = machine.stack.pop();
-                machine.store(mem, v as i32, self.start)?;
+                let v: i64 = self.stack.pop();
+                self.store(mem, v as i32, insn.start)?;
             }
             // https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-size
-            MemorySize => machine.stack.push(machine.memory.size() as i32),
+            // Number of pages is i64 for 64-bit memory. Since i32 value is zero-extended on stack, the
+            // non-negative number can be pushed as i64 in both cases
+            MemorySize(memidx) => {
+                let size = self.current_memory(*memidx).size();
+                self.stack.push(size as i64);
+            }
             // https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-grow
-            MemoryGrow => {
-                let pages: i32 = machine.stack.pop();
-                let prev_pages = machine.memory.grow(pages as u32);
-                machine.stack.push(prev_pages);
+            MemoryGrow(memidx) => {
+                // i32 operand is zero-extended on stack so it can be popped as i64
+                let pages = self.stack.pop::<i64>() as u64;
+                let memory = self.current_memory(*memidx);
+                let prev_pages = memory.grow(pages);
+                if memory.is_memory64() {
+                    self.stack.push(prev_pages);
+                } else {
+                    self.stack.push(prev_pages as i32);
+                }
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-init
+            MemoryInit { memory, data } => {
+                let (dst, src, len) = self.pop_bulk_operands();
+                let inst = &self.instances[self.current];
+                let data = inst.data[*data as usize];
+                let addr = inst.memories[*memory as usize];
+                self.memories[addr].init(dst, src, len, data, insn.start)?;
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-data-drop
+            DataDrop(dataidx) => self.instances[self.current].data[*dataidx as usize] = &[],
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-copy
+            MemoryCopy {
+                dst: dst_mem,
+                src: src_mem,
+            } => {
+                let (dst, src, len) = self.pop_bulk_operands();
+                let dst_addr = self.instances[self.current].memories[*dst_mem as usize];
+                let src_addr = self.instances[self.current].memories[*src_mem as usize];
+                if dst_addr == src_addr {
+                    self.memories[dst_addr].copy(dst, src, len, insn.start)?;
+                } else if dst_addr < src_addr {
+                    let (l, r) = self.memories.split_at_mut(src_addr);
+                    l[dst_addr].copy_from(&r[0], dst, src, len, insn.start)?;
+                } else {
+                    let (l, r) = self.memories.split_at_mut(dst_addr);
+                    r[0].copy_from(&l[src_addr], dst, src, len, insn.start)?;
+                }
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-fill
+            MemoryFill(memidx) => {
+                let (dst, val, len) = self.pop_bulk_operands();
+                self.current_memory(*memidx)
+                    .fill(dst, val as u8, len, insn.start)?;
+            }
+            // Table instructions
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-get
+            TableGet(tableidx) => {
+                let idx: i32 = self.stack.pop();
+                let addr = self.table_addr(*tableidx);
+                let r = self.tables[addr].get(idx as u32 as usize, insn.start)?;
+                self.stack.push(r);
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-set
+            TableSet(tableidx) => {
+                let r: Option<u32> = self.stack.pop();
+                let idx: i32 = self.stack.pop();
+                let addr = self.table_addr(*tableidx);
+                self.tables[addr].set(idx as u32 as usize, r, insn.start)?;
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-size
+            TableSize(tableidx) => {
+                let size = self.tables[self.table_addr(*tableidx)].size();
+                self.stack.push(size as i32);
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-grow
+            TableGrow(tableidx) => {
+                let delta: i32 = self.stack.pop();
+                let init: Option<u32> = self.stack.pop();
+                let addr = self.table_addr(*tableidx);
+                let prev = self.tables[addr].grow(delta as u32, init);
+                self.stack.push(prev);
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-fill
+            TableFill(tableidx) => {
+                let len: i32 = self.stack.pop();
+                let val: Option<u32> = self.stack.pop();
+                let dst: i32 = self.stack.pop();
+                let addr = self.table_addr(*tableidx);
+                let (dst, len) = (dst as u32 as usize, len as u32 as usize);
+                self.tables[addr].fill(dst, val, len, insn.start)?;
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-init
+            TableInit { table, elem } => {
+                let (dst, src, len) = self.pop_bulk_operands();
+                let inst = &self.instances[self.current];
+                let segment = &inst.elems[*elem as usize];
+                let addr = inst.tables[*table as usize];
+                self.tables[addr].init(dst, src, len, segment, insn.start)?;
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-elem-drop
+            ElemDrop(elemidx) => self.instances[self.current].elems[*elemidx as usize] = vec![],
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-copy
+            TableCopy {
+                dst: dst_table,
+                src: src_table,
+            } => {
+                let (dst, src, len) = self.pop_bulk_operands();
+                let dst_addr = self.table_addr(*dst_table);
+                let src_addr = self.table_addr(*src_table);
+                if dst_addr == src_addr {
+                    self.tables[dst_addr].copy(dst, src, len, insn.start)?;
+                } else if dst_addr < src_addr {
+                    let (l, r) = self.tables.split_at_mut(src_addr);
+                    l[dst_addr].copy_from(&r[0], dst, src, len, insn.start)?;
+                } else {
+                    let (l, r) = self.tables.split_at_mut(dst_addr);
+                    r[0].copy_from(&l[src_addr], dst, src, len, insn.start)?;
+                }
             }
             // Numeric instructions
             // https://webassembly.github.io/spec/core/exec/instructions.html#exec-const
-            I32Const(i) => machine.stack.push(*i),
-            I64Const(i) => machine.stack.push(*i),
-            F32Const(f) => machine.stack.push(*f),
-            F64Const(f) => machine.stack.push(*f),
+            I32Const(i) => self.stack.push(*i),
+            I64Const(i) => self.stack.push(*i),
+            F32Const(f) => self.stack.push(*f),
+            F64Const(f) => self.stack.push(*f),
             // Integer operations
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-iclz
-            I32Clz => machine.unop::<i32, _>(|v| v.leading_zeros() as i32),
-            I64Clz => machine.unop::<i64, _>(|v| v.leading_zeros() as i64),
+            I32Clz => self.unop::<i32, _>(|v| v.leading_zeros() as i32),
+            I64Clz => self.unop::<i64, _>(|v| v.leading_zeros() as i64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ictz
-            I32Ctz => machine.unop::<i32, _>(|v| v.trailing_zeros() as i32),
-            I64Ctz => machine.unop::<i64, _>(|v| v.trailing_zeros() as i64),
+            I32Ctz => self.unop::<i32, _>(|v| v.trailing_zeros() as i32),
+            I64Ctz => self.unop::<i64, _>(|v| v.trailing_zeros() as i64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ipopcnt
-            I32Popcnt => machine.unop::<i32, _>(|v| v.count_ones() as i32),
-            I64Popcnt => machine.unop::<i64, _>(|v| v.count_ones() as i64),
+            I32Popcnt => self.unop::<i32, _>(|v| v.count_ones() as i32),
+            I64Popcnt => self.unop::<i64, _>(|v| v.count_ones() as i64),
+            // https://webassembly.github.io/spec/core/exec/numerics.html#op-iextendn-s
+            I32Extend8S => self.unop::<i32, _>(|v| v as i8 as i32),
+            I32Extend16S => self.unop::<i32, _>(|v| v as i16 as i32),
+            I64Extend8S => self.unop::<i64, _>(|v| v as i8 as i64),
+            I64Extend16S => self.unop::<i64, _>(|v| v as i16 as i64),
+            I64Extend32S => self.unop::<i64, _>(|v| v as i32 as i64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-iadd
-            I32Add => machine.binop::<i32, _>(|l, r| l.wrapping_add(r)),
-            I64Add => machine.binop::<i64, _>(|l, r| l.wrapping_add(r)),
+            I32Add => self.binop::<i32, _>(|l, r| l.wrapping_add(r)),
+            I64Add => self.binop::<i64, _>(|l, r| l.wrapping_add(r)),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-isub
-            I32Sub => machine.binop::<i32, _>(|l, r| l.wrapping_sub(r)),
-            I64Sub => machine.binop::<i64, _>(|l, r| l.wrapping_sub(r)),
+            I32Sub => self.binop::<i32, _>(|l, r| l.wrapping_sub(r)),
+            I64Sub => self.binop::<i64, _>(|l, r| l.wrapping_sub(r)),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-imul
-            I32Mul => machine.binop::<i32, _>(|l, r| l.wrapping_mul(r)),
-            I64Mul => machine.binop::<i64, _>(|l, r| l.wrapping_mul(r)),
+            I32Mul => self.binop::<i32, _>(|l, r| l.wrapping_mul(r)),
+            I64Mul => self.binop::<i64, _>(|l, r| l.wrapping_mul(r)),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-idiv-s
             // Note: According to i32.wast and i64.wast, integer overflow on idiv_s should be trapped.
             // This is intended behavior: https://github.com/WebAssembly/spec/issues/1185#issuecomment-619412936
-            I32DivS => machine.binop_trap::<i32, _>(|l, r| match l.checked_div(r) {
+            I32DivS => self.binop_trap::<i32, _>(|l, r| match l.checked_div(r) {
                 Some(i) => Ok(i),
-                None => Err(Trap::new(TrapReason::DivByZeroOrOverflow, self.start)),
+                None => Err(Trap::new(TrapReason::DivByZeroOrOverflow, insn.start)),
             })?,
-            I64DivS => machine.binop_trap::<i64, _>(|l, r| match l.checked_div(r) {
+            I64DivS => self.binop_trap::<i64, _>(|l, r| match l.checked_div(r) {
                 Some(i) => Ok(i),
-                None => Err(Trap::new(TrapReason::DivByZeroOrOverflow, self.start)),
+                None => Err(Trap::new(TrapReason::DivByZeroOrOverflow, insn.start)),
             })?,
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-idiv-u
             I32DivU => {
-                machine.binop_trap::<i32, _>(|l, r| match (l as u32).checked_div(r as u32) {
+                self.binop_trap::<i32, _>(|l, r| match (l as u32).checked_div(r as u32) {
                     Some(u) => Ok(u as i32),
-                    None => Err(Trap::new(TrapReason::DivByZeroOrOverflow, self.start)),
+                    None => Err(Trap::new(TrapReason::DivByZeroOrOverflow, insn.start)),
                 })?
             }
             I64DivU => {
-                machine.binop_trap::<i64, _>(|l, r| match (l as u64).checked_div(r as u64) {
+                self.binop_trap::<i64, _>(|l, r| match (l as u64).checked_div(r as u64) {
                     Some(u) => Ok(u as i64),
-                    None => Err(Trap::new(TrapReason::DivByZeroOrOverflow, self.start)),
+                    None => Err(Trap::new(TrapReason::DivByZeroOrOverflow, insn.start)),
                 })?
             }
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-irem-s
@@ -726,212 +2464,967 @@ impl<'f, 'm, 's, I: Importer> Execute<'f, 'm, 's, I> for ast::Instruction {
             // in Rust, but Wasm test case says it should return 0. Note that Go has special rule
             // that x % -1 is 0 when x is the most negative value.
             // This is intended behavior: https://github.com/WebAssembly/spec/issues/1185#issuecomment-619412936
-            I32RemS => machine.binop_trap::<i32, _>(|l, r| {
+            I32RemS => self.binop_trap::<i32, _>(|l, r| {
                 if r == 0 {
-                    Err(Trap::new(TrapReason::RemZeroDivisor, self.start))
+                    Err(Trap::new(TrapReason::RemZeroDivisor, insn.start))
                 } else {
                     Ok(l.wrapping_rem(r))
                 }
             })?,
-            I64RemS => machine.binop_trap::<i64, _>(|l, r| {
+            I64RemS => self.binop_trap::<i64, _>(|l, r| {
                 if r == 0 {
-                    Err(Trap::new(TrapReason::RemZeroDivisor, self.start))
+                    Err(Trap::new(TrapReason::RemZeroDivisor, insn.start))
                 } else {
                     Ok(l.wrapping_rem(r))
                 }
             })?,
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-irem-u
-            I32RemU => machine.binop_trap::<i32, _>(|l, r| {
+            I32RemU => self.binop_trap::<i32, _>(|l, r| {
                 if r == 0 {
-                    Err(Trap::new(TrapReason::RemZeroDivisor, self.start))
+                    Err(Trap::new(TrapReason::RemZeroDivisor, insn.start))
                 } else {
                     Ok((l as u32 % r as u32) as i32) // for unsigned integers overflow never occurs
                 }
             })?,
-            I64RemU => machine.binop_trap::<i64, _>(|l, r| {
+            I64RemU => self.binop_trap::<i64, _>(|l, r| {
                 if r == 0 {
-                    Err(Trap::new(TrapReason::RemZeroDivisor, self.start))
+                    Err(Trap::new(TrapReason::RemZeroDivisor, insn.start))
                 } else {
                     Ok((l as u64 % r as u64) as i64) // for unsigned integers overflow never occurs
                 }
             })?,
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-iand
-            I32And => machine.binop::<i32, _>(|l, r| l & r),
-            I64And => machine.binop::<i64, _>(|l, r| l & r),
+            I32And => self.binop::<i32, _>(|l, r| l & r),
+            I64And => self.binop::<i64, _>(|l, r| l & r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ior
-            I32Or => machine.binop::<i32, _>(|l, r| l | r),
-            I64Or => machine.binop::<i64, _>(|l, r| l | r),
+            I32Or => self.binop::<i32, _>(|l, r| l | r),
+            I64Or => self.binop::<i64, _>(|l, r| l | r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ixor
-            I32Xor => machine.binop::<i32, _>(|l, r| l ^ r),
-            I64Xor => machine.binop::<i64, _>(|l, r| l ^ r),
+            I32Xor => self.binop::<i32, _>(|l, r| l ^ r),
+            I64Xor => self.binop::<i64, _>(|l, r| l ^ r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ishl
-            I32Shl => machine.binop::<i32, _>(|l, r| l.wrapping_shl(r as u32)),
-            I64Shl => machine.binop::<i64, _>(|l, r| l.wrapping_shl(r as u32)),
+            I32Shl => self.binop::<i32, _>(|l, r| l.wrapping_shl(r as u32)),
+            I64Shl => self.binop::<i64, _>(|l, r| l.wrapping_shl(r as u32)),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ishr-s
-            I32ShrS => machine.binop::<i32, _>(|l, r| l.wrapping_shr(r as u32)),
-            I64ShrS => machine.binop::<i64, _>(|l, r| l.wrapping_shr(r as u32)),
+            I32ShrS => self.binop::<i32, _>(|l, r| l.wrapping_shr(r as u32)),
+            I64ShrS => self.binop::<i64, _>(|l, r| l.wrapping_shr(r as u32)),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ishr-u
-            I32ShrU => machine.binop::<i32, _>(|l, r| (l as u32).wrapping_shr(r as u32) as i32),
-            I64ShrU => machine.binop::<i64, _>(|l, r| (l as u64).wrapping_shr(r as u32) as i64),
+            I32ShrU => self.binop::<i32, _>(|l, r| (l as u32).wrapping_shr(r as u32) as i32),
+            I64ShrU => self.binop::<i64, _>(|l, r| (l as u64).wrapping_shr(r as u32) as i64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-irotl
-            I32Rotl => machine.binop::<i32, _>(|l, r| l.rotate_left(r as u32)),
-            I64Rotl => machine.binop::<i64, _>(|l, r| l.rotate_left(r as u32)),
+            I32Rotl => self.binop::<i32, _>(|l, r| l.rotate_left(r as u32)),
+            I64Rotl => self.binop::<i64, _>(|l, r| l.rotate_left(r as u32)),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-irotr
-            I32Rotr => machine.binop::<i32, _>(|l, r| l.rotate_right(r as u32)),
-            I64Rotr => machine.binop::<i64, _>(|l, r| l.rotate_right(r as u32)),
+            I32Rotr => self.binop::<i32, _>(|l, r| l.rotate_right(r as u32)),
+            I64Rotr => self.binop::<i64, _>(|l, r| l.rotate_right(r as u32)),
             // Float number operations
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fabs
-            F32Abs => machine.unop::<f32, _>(|f| f.abs()),
-            F64Abs => machine.unop::<f64, _>(|f| f.abs()),
+            F32Abs => self.unop::<f32, _>(|f| f.abs()),
+            F64Abs => self.unop::<f64, _>(|f| f.abs()),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fneg
-            F32Neg => machine.unop::<f32, _>(|f| -f),
-            F64Neg => machine.unop::<f64, _>(|f| -f),
+            F32Neg => self.unop::<f32, _>(|f| -f),
+            F64Neg => self.unop::<f64, _>(|f| -f),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fceil
-            F32Ceil => machine.unop::<f32, _>(|f| f.ceil()),
-            F64Ceil => machine.unop::<f64, _>(|f| f.ceil()),
+            F32Ceil => self.unop::<f32, _>(|f| f.ceil()),
+            F64Ceil => self.unop::<f64, _>(|f| f.ceil()),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ffloor
-            F32Floor => machine.unop::<f32, _>(|f| f.floor()),
-            F64Floor => machine.unop::<f64, _>(|f| f.floor()),
+            F32Floor => self.unop::<f32, _>(|f| f.floor()),
+            F64Floor => self.unop::<f64, _>(|f| f.floor()),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ftrunc
-            F32Trunc => machine.unop::<f32, _>(|f| f.trunc()),
-            F64Trunc => machine.unop::<f64, _>(|f| f.trunc()),
+            F32Trunc => self.unop::<f32, _>(|f| f.trunc()),
+            F64Trunc => self.unop::<f64, _>(|f| f.trunc()),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fnearest
-            F32Nearest => machine.unop::<f32, _>(|f| {
-                // f32::round() is not available because behavior when two values are equally near
-                // is different. For example, 4.5f32.round() is 5.0 but (f32.nearest (f32.const 4.5))
-                // is 4.0.
-                let fround = f.round();
-                if (f - fround).abs() == 0.5 && fround % 2.0 != 0.0 {
-                    f.trunc()
-                } else {
-                    fround
-                }
-            }),
-            F64Nearest => machine.unop::<f64, _>(|f| {
-                // f64::round() is not available for the same reason as f32.nearest
-                let fround = f.round();
-                if (f - fround).abs() == 0.5 && fround % 2.0 != 0.0 {
-                    f.trunc()
-                } else {
-                    fround
-                }
-            }),
+            F32Nearest => self.unop::<f32, _>(f32_nearest),
+            F64Nearest => self.unop::<f64, _>(f64_nearest),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fsqrt
-            F32Sqrt => machine.unop::<f32, _>(|f| f.sqrt()),
-            F64Sqrt => machine.unop::<f64, _>(|f| f.sqrt()),
+            F32Sqrt => self.unop::<f32, _>(|f| f.sqrt()),
+            F64Sqrt => self.unop::<f64, _>(|f| f.sqrt()),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fadd
-            F32Add => machine.binop::<f32, _>(|l, r| l + r),
-            F64Add => machine.binop::<f64, _>(|l, r| l + r),
+            F32Add => self.binop::<f32, _>(|l, r| l + r),
+            F64Add => self.binop::<f64, _>(|l, r| l + r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fsub
-            F32Sub => machine.binop::<f32, _>(|l, r| l - r),
-            F64Sub => machine.binop::<f64, _>(|l, r| l - r),
+            F32Sub => self.binop::<f32, _>(|l, r| l - r),
+            F64Sub => self.binop::<f64, _>(|l, r| l - r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fmul
-            F32Mul => machine.binop::<f32, _>(|l, r| l * r),
-            F64Mul => machine.binop::<f64, _>(|l, r| l * r),
+            F32Mul => self.binop::<f32, _>(|l, r| l * r),
+            F64Mul => self.binop::<f64, _>(|l, r| l * r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fdiv
-            F32Div => machine.binop::<f32, _>(|l, r| l / r),
-            F64Div => machine.binop::<f64, _>(|l, r| l / r),
+            F32Div => self.binop::<f32, _>(|l, r| l / r),
+            F64Div => self.binop::<f64, _>(|l, r| l / r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fmin
-            F32Min => machine.binop::<f32, _>(|l, r| l.min(r)),
-            F64Min => machine.binop::<f64, _>(|l, r| l.min(r)),
+            F32Min => self.binop::<f32, _>(|l, r| l.min(r)),
+            F64Min => self.binop::<f64, _>(|l, r| l.min(r)),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fmax
-            F32Max => machine.binop::<f32, _>(|l, r| l.max(r)),
-            F64Max => machine.binop::<f64, _>(|l, r| l.max(r)),
+            F32Max => self.binop::<f32, _>(|l, r| l.max(r)),
+            F64Max => self.binop::<f64, _>(|l, r| l.max(r)),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fcopysign
-            F32Copysign => machine.binop::<f32, _>(|l, r| l.copysign(r)),
-            F64Copysign => machine.binop::<f64, _>(|l, r| l.copysign(r)),
+            F32Copysign => self.binop::<f32, _>(|l, r| l.copysign(r)),
+            F64Copysign => self.binop::<f64, _>(|l, r| l.copysign(r)),
             // Integer comparison
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ieqz
-            I32Eqz => machine.testop::<i32, _>(|i| i == 0),
-            I64Eqz => machine.testop::<i64, _>(|i| i == 0),
+            I32Eqz => self.testop::<i32, _>(|i| i == 0),
+            I64Eqz => self.testop::<i64, _>(|i| i == 0),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ieq
-            I32Eq => machine.relop::<i32, _>(|l, r| l == r),
-            I64Eq => machine.relop::<i64, _>(|l, r| l == r),
+            I32Eq => self.relop::<i32, _>(|l, r| l == r),
+            I64Eq => self.relop::<i64, _>(|l, r| l == r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ine
-            I32Ne => machine.relop::<i32, _>(|l, r| l != r),
-            I64Ne => machine.relop::<i64, _>(|l, r| l != r),
+            I32Ne => self.relop::<i32, _>(|l, r| l != r),
+            I64Ne => self.relop::<i64, _>(|l, r| l != r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ilt-s
-            I32LtS => machine.relop::<i32, _>(|l, r| l < r),
-            I64LtS => machine.relop::<i64, _>(|l, r| l < r),
+            I32LtS => self.relop::<i32, _>(|l, r| l < r),
+            I64LtS => self.relop::<i64, _>(|l, r| l < r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ilt-u
-            I32LtU => machine.relop::<i32, _>(|l, r| (l as u32) < r as u32),
-            I64LtU => machine.relop::<i64, _>(|l, r| (l as u64) < r as u64),
+            I32LtU => self.relop::<i32, _>(|l, r| (l as u32) < r as u32),
+            I64LtU => self.relop::<i64, _>(|l, r| (l as u64) < r as u64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-igt-s
-            I32GtS => machine.relop::<i32, _>(|l, r| l > r),
-            I64GtS => machine.relop::<i64, _>(|l, r| l > r),
+            I32GtS => self.relop::<i32, _>(|l, r| l > r),
+            I64GtS => self.relop::<i64, _>(|l, r| l > r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-igt-u
-            I32GtU => machine.relop::<i32, _>(|l, r| l as u32 > r as u32),
-            I64GtU => machine.relop::<i64, _>(|l, r| l as u64 > r as u64),
+            I32GtU => self.relop::<i32, _>(|l, r| l as u32 > r as u32),
+            I64GtU => self.relop::<i64, _>(|l, r| l as u64 > r as u64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ile-s
-            I32LeS => machine.relop::<i32, _>(|l, r| l <= r),
-            I64LeS => machine.relop::<i64, _>(|l, r| l <= r),
+            I32LeS => self.relop::<i32, _>(|l, r| l <= r),
+            I64LeS => self.relop::<i64, _>(|l, r| l <= r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ile-u
-            I32LeU => machine.relop::<i32, _>(|l, r| l as u32 <= r as u32),
-            I64LeU => machine.relop::<i64, _>(|l, r| l as u64 <= r as u64),
+            I32LeU => self.relop::<i32, _>(|l, r| l as u32 <= r as u32),
+            I64LeU => self.relop::<i64, _>(|l, r| l as u64 <= r as u64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ige-s
-            I32GeS => machine.relop::<i32, _>(|l, r| l >= r),
-            I64GeS => machine.relop::<i64, _>(|l, r| l >= r),
+            I32GeS => self.relop::<i32, _>(|l, r| l >= r),
+            I64GeS => self.relop::<i64, _>(|l, r| l >= r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-ige-u
-            I32GeU => machine.relop::<i32, _>(|l, r| l as u32 >= r as u32),
-            I64GeU => machine.relop::<i64, _>(|l, r| l as u64 >= r as u64),
+            I32GeU => self.relop::<i32, _>(|l, r| l as u32 >= r as u32),
+            I64GeU => self.relop::<i64, _>(|l, r| l as u64 >= r as u64),
             // Float number comparison
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-feq
-            F32Eq => machine.relop::<f32, _>(|l, r| l == r),
-            F64Eq => machine.relop::<f64, _>(|l, r| l == r),
+            F32Eq => self.relop::<f32, _>(|l, r| l == r),
+            F64Eq => self.relop::<f64, _>(|l, r| l == r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fne
-            F32Ne => machine.relop::<f32, _>(|l, r| l != r),
-            F64Ne => machine.relop::<f64, _>(|l, r| l != r),
+            F32Ne => self.relop::<f32, _>(|l, r| l != r),
+            F64Ne => self.relop::<f64, _>(|l, r| l != r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-flt
-            F32Lt => machine.relop::<f32, _>(|l, r| l < r),
-            F64Lt => machine.relop::<f64, _>(|l, r| l < r),
+            F32Lt => self.relop::<f32, _>(|l, r| l < r),
+            F64Lt => self.relop::<f64, _>(|l, r| l < r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fgt
-            F32Gt => machine.relop::<f32, _>(|l, r| l > r),
-            F64Gt => machine.relop::<f64, _>(|l, r| l > r),
+            F32Gt => self.relop::<f32, _>(|l, r| l > r),
+            F64Gt => self.relop::<f64, _>(|l, r| l > r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fle
-            F32Le => machine.relop::<f32, _>(|l, r| l <= r),
-            F64Le => machine.relop::<f64, _>(|l, r| l <= r),
+            F32Le => self.relop::<f32, _>(|l, r| l <= r),
+            F64Le => self.relop::<f64, _>(|l, r| l <= r),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-fge
-            F32Ge => machine.relop::<f32, _>(|l, r| l >= r),
-            F64Ge => machine.relop::<f64, _>(|l, r| l >= r),
+            F32Ge => self.relop::<f32, _>(|l, r| l >= r),
+            F64Ge => self.relop::<f64, _>(|l, r| l >= r),
             // Conversion
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-extend-u
-            I64ExtendI32U => machine.cvtop::<i32, i64, _>(|v| v as u32 as i64),
+            I64ExtendI32U => self.cvtop::<i32, i64, _>(|v| v as u32 as i64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-extend-s
-            I64ExtendI32S => machine.cvtop::<i32, i64, _>(|v| v as i64),
+            I64ExtendI32S => self.cvtop::<i32, i64, _>(|v| v as i64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-wrap
-            I32WrapI64 => machine.cvtop::<i64, i32, _>(|v| v as i32),
+            I32WrapI64 => self.cvtop::<i64, i32, _>(|v| v as i32),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-trunc-u
-            I32TruncF32U => machine.cvtop::<f32, i32, _>(|v| cast::f32_to_u32(v) as i32),
-            I32TruncF64U => machine.cvtop::<f64, i32, _>(|v| cast::f64_to_u32(v) as i32),
-            I64TruncF32U => machine.cvtop::<f32, i64, _>(|v| cast::f32_to_u64(v) as i64),
-            I64TruncF64U => machine.cvtop::<f64, i64, _>(|v| cast::f64_to_u64(v) as i64),
+            I32TruncF32U => self.cvtop_trap::<f32, i32, _>(
+                |v| cast::checked_f32_to_u32(v).map(|u| u as i32),
+                insn.start,
+            )?,
+            I32TruncF64U => self.cvtop_trap::<f64, i32, _>(
+                |v| cast::checked_f64_to_u32(v).map(|u| u as i32),
+                insn.start,
+            )?,
+            I64TruncF32U => self.cvtop_trap::<f32, i64, _>(
+                |v| cast::checked_f32_to_u64(v).map(|u| u as i64),
+                insn.start,
+            )?,
+            I64TruncF64U => self.cvtop_trap::<f64, i64, _>(
+                |v| cast::checked_f64_to_u64(v).map(|u| u as i64),
+                insn.start,
+            )?,
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-trunc-s
-            I32TruncF32S => machine.cvtop::<f32, i32, _>(cast::f32_to_i32),
-            I32TruncF64S => machine.cvtop::<f64, i32, _>(cast::f64_to_i32),
-            I64TruncF32S => machine.cvtop::<f32, i64, _>(cast::f32_to_i64),
-            I64TruncF64S => machine.cvtop::<f64, i64, _>(cast::f64_to_i64),
+            I32TruncF32S => self.cvtop_trap::<f32, i32, _>(cast::checked_f32_to_i32, insn.start)?,
+            I32TruncF64S => self.cvtop_trap::<f64, i32, _>(cast::checked_f64_to_i32, insn.start)?,
+            I64TruncF32S => self.cvtop_trap::<f32, i64, _>(cast::checked_f32_to_i64, insn.start)?,
+            I64TruncF64S => self.cvtop_trap::<f64, i64, _>(cast::checked_f64_to_i64, insn.start)?,
+            // https://webassembly.github.io/spec/core/exec/numerics.html#op-trunc-sat-u
+            I32TruncSatF32U => self.cvtop::<f32, i32, _>(|v| cast::f32_to_u32(v) as i32),
+            I32TruncSatF64U => self.cvtop::<f64, i32, _>(|v| cast::f64_to_u32(v) as i32),
+            I64TruncSatF32U => self.cvtop::<f32, i64, _>(|v| cast::f32_to_u64(v) as i64),
+            I64TruncSatF64U => self.cvtop::<f64, i64, _>(|v| cast::f64_to_u64(v) as i64),
+            // https://webassembly.github.io/spec/core/exec/numerics.html#op-trunc-sat-s
+            I32TruncSatF32S => self.cvtop::<f32, i32, _>(cast::f32_to_i32),
+            I32TruncSatF64S => self.cvtop::<f64, i32, _>(cast::f64_to_i32),
+            I64TruncSatF32S => self.cvtop::<f32, i64, _>(cast::f32_to_i64),
+            I64TruncSatF64S => self.cvtop::<f64, i64, _>(cast::f64_to_i64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-promote
-            F64PromoteF32 => machine.cvtop::<f32, f64, _>(|v| v as f64),
+            F64PromoteF32 => self.cvtop::<f32, f64, _>(|v| v as f64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-demote
-            F32DemoteF64 => machine.cvtop::<f64, f32, _>(|v| v as f32),
+            F32DemoteF64 => self.cvtop::<f64, f32, _>(|v| v as f32),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-convert-u
-            F32ConvertI32U => machine.cvtop::<i32, f32, _>(|v| v as u32 as f32),
-            F32ConvertI64U => machine.cvtop::<i64, f32, _>(|v| v as u64 as f32),
-            F64ConvertI32U => machine.cvtop::<i32, f64, _>(|v| v as u32 as f64),
-            F64ConvertI64U => machine.cvtop::<i64, f64, _>(|v| v as u64 as f64),
+            F32ConvertI32U => self.cvtop::<i32, f32, _>(|v| v as u32 as f32),
+            F32ConvertI64U => self.cvtop::<i64, f32, _>(|v| v as u64 as f32),
+            F64ConvertI32U => self.cvtop::<i32, f64, _>(|v| v as u32 as f64),
+            F64ConvertI64U => self.cvtop::<i64, f64, _>(|v| v as u64 as f64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-convert-s
-            F32ConvertI32S => machine.cvtop::<i32, f32, _>(|v| v as f32),
-            F32ConvertI64S => machine.cvtop::<i64, f32, _>(|v| v as f32),
-            F64ConvertI32S => machine.cvtop::<i32, f64, _>(|v| v as f64),
-            F64ConvertI64S => machine.cvtop::<i64, f64, _>(|v| v as f64),
+            F32ConvertI32S => self.cvtop::<i32, f32, _>(|v| v as f32),
+            F32ConvertI64S => self.cvtop::<i64, f32, _>(|v| v as f32),
+            F64ConvertI32S => self.cvtop::<i32, f64, _>(|v| v as f64),
+            F64ConvertI64S => self.cvtop::<i64, f64, _>(|v| v as f64),
             // https://webassembly.github.io/spec/core/exec/numerics.html#op-reinterpret
-            // Don't need to modify stack. Just changing type to t2 is enough.
-            I32ReinterpretF32 => machine.stack.write_top_type(i32::VAL_TYPE),
-            I64ReinterpretF64 => machine.stack.write_top_type(i64::VAL_TYPE),
-            F32ReinterpretI32 => machine.stack.write_top_type(f32::VAL_TYPE),
-            F64ReinterpretI64 => machine.stack.write_top_type(f64::VAL_TYPE),
+            // Don't need to modify stack since a slot holds bits of the value
+            I32ReinterpretF32 | I64ReinterpretF64 | F32ReinterpretI32 | F64ReinterpretI64 => {}
+            // Vector instructions
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-load
+            V128Load(mem) => {
+                let v: i128 = self.load(mem, insn.start)?;
+                self.stack.push(v);
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-load-extend
+            V128Load8x8S(mem) => {
+                let v: u64 = self.load(mem, insn.start)?;
+                self.stack.push(simd::extend::<i8, i16>(v as i128, false));
+            }
+            V128Load8x8U(mem) => {
+                let v: u64 = self.load(mem, insn.start)?;
+                self.stack.push(simd::extend::<u8, u16>(v as i128, false));
+            }
+            V128Load16x4S(mem) => {
+                let v: u64 = self.load(mem, insn.start)?;
+                self.stack.push(simd::extend::<i16, i32>(v as i128, false));
+            }
+            V128Load16x4U(mem) => {
+                let v: u64 = self.load(mem, insn.start)?;
+                self.stack.push(simd::extend::<u16, u32>(v as i128, false));
+            }
+            V128Load32x2S(mem) => {
+                let v: u64 = self.load(mem, insn.start)?;
+                self.stack.push(simd::extend::<i32, i64>(v as i128, false));
+            }
+            V128Load32x2U(mem) => {
+                let v: u64 = self.load(mem, insn.start)?;
+                self.stack.push(simd::extend::<u32, u64>(v as i128, false));
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-load-splat
+            V128Load8Splat(mem) => {
+                let v: u8 = self.load(mem, insn.start)?;
+                self.stack.push(simd::splat(v));
+            }
+            V128Load16Splat(mem) => {
+                let v: u16 = self.load(mem, insn.start)?;
+                self.stack.push(simd::splat(v));
+            }
+            V128Load32Splat(mem) => {
+                let v: u32 = self.load(mem, insn.start)?;
+                self.stack.push(simd::splat(v));
+            }
+            V128Load64Splat(mem) => {
+                let v: u64 = self.load(mem, insn.start)?;
+                self.stack.push(simd::splat(v));
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-load-zero
+            V128Load32Zero(mem) => {
+                let v: u32 = self.load(mem, insn.start)?;
+                self.stack.push(v as i128);
+            }
+            V128Load64Zero(mem) => {
+                let v: u64 = self.load(mem, insn.start)?;
+                self.stack.push(v as i128);
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-load-lane
+            V128Load8Lane { mem, lane } => {
+                let v: i128 = self.stack.pop();
+                let x: u8 = self.load(mem, insn.start)?;
+                self.stack.push(simd::replace_lane(v, *lane, x));
+            }
+            V128Load16Lane { mem, lane } => {
+                let v: i128 = self.stack.pop();
+                let x: u16 = self.load(mem, insn.start)?;
+                self.stack.push(simd::replace_lane(v, *lane, x));
+            }
+            V128Load32Lane { mem, lane } => {
+                let v: i128 = self.stack.pop();
+                let x: u32 = self.load(mem, insn.start)?;
+                self.stack.push(simd::replace_lane(v, *lane, x));
+            }
+            V128Load64Lane { mem, lane } => {
+                let v: i128 = self.stack.pop();
+                let x: u64 = self.load(mem, insn.start)?;
+                self.stack.push(simd::replace_lane(v, *lane, x));
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-store
+            V128Store(mem) => {
+                let v: i128 = self.stack.pop();
+                self.store(mem, v, insn.start)?;
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-store-lane
+            V128Store8Lane { mem, lane } => {
+                let v: i128 = self.stack.pop();
+                self.store(mem, simd::extract_lane::<u8>(v, *lane), insn.start)?;
+            }
+            V128Store16Lane { mem, lane } => {
+                let v: i128 = self.stack.pop();
+                self.store(mem, simd::extract_lane::<u16>(v, *lane), insn.start)?;
+            }
+            V128Store32Lane { mem, lane } => {
+                let v: i128 = self.stack.pop();
+                self.store(mem, simd::extract_lane::<u32>(v, *lane), insn.start)?;
+            }
+            V128Store64Lane { mem, lane } => {
+                let v: i128 = self.stack.pop();
+                self.store(mem, simd::extract_lane::<u64>(v, *lane), insn.start)?;
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vconst
+            V128Const(v) => self.stack.push(*v),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-shuffle
+            I8x16Shuffle(lanes) => self.binop::<i128, _>(|l, r| simd::shuffle(l, r, lanes)),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-swizzle
+            I8x16Swizzle => self.binop::<i128, _>(simd::swizzle),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-splat
+            I8x16Splat => self.cvtop::<i32, i128, _>(|x| simd::splat(x as i8)),
+            I16x8Splat => self.cvtop::<i32, i128, _>(|x| simd::splat(x as i16)),
+            I32x4Splat => self.cvtop::<i32, i128, _>(simd::splat),
+            I64x2Splat => self.cvtop::<i64, i128, _>(simd::splat),
+            F32x4Splat => self.cvtop::<f32, i128, _>(simd::splat),
+            F64x2Splat => self.cvtop::<f64, i128, _>(simd::splat),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-extract-lane
+            I8x16ExtractLaneS(lane) => {
+                self.cvtop::<i128, i32, _>(|v| simd::extract_lane::<i8>(v, *lane) as i32)
+            }
+            I8x16ExtractLaneU(lane) => {
+                self.cvtop::<i128, i32, _>(|v| simd::extract_lane::<u8>(v, *lane) as i32)
+            }
+            I16x8ExtractLaneS(lane) => {
+                self.cvtop::<i128, i32, _>(|v| simd::extract_lane::<i16>(v, *lane) as i32)
+            }
+            I16x8ExtractLaneU(lane) => {
+                self.cvtop::<i128, i32, _>(|v| simd::extract_lane::<u16>(v, *lane) as i32)
+            }
+            I32x4ExtractLane(lane) => self.cvtop::<i128, i32, _>(|v| simd::extract_lane(v, *lane)),
+            I64x2ExtractLane(lane) => self.cvtop::<i128, i64, _>(|v| simd::extract_lane(v, *lane)),
+            F32x4ExtractLane(lane) => self.cvtop::<i128, f32, _>(|v| simd::extract_lane(v, *lane)),
+            F64x2ExtractLane(lane) => self.cvtop::<i128, f64, _>(|v| simd::extract_lane(v, *lane)),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-replace-lane
+            I8x16ReplaceLane(lane) => {
+                let x: i32 = self.stack.pop();
+                self.unop::<i128, _>(|v| simd::replace_lane(v, *lane, x as i8));
+            }
+            I16x8ReplaceLane(lane) => {
+                let x: i32 = self.stack.pop();
+                self.unop::<i128, _>(|v| simd::replace_lane(v, *lane, x as i16));
+            }
+            I32x4ReplaceLane(lane) => {
+                let x: i32 = self.stack.pop();
+                self.unop::<i128, _>(|v| simd::replace_lane(v, *lane, x));
+            }
+            I64x2ReplaceLane(lane) => {
+                let x: i64 = self.stack.pop();
+                self.unop::<i128, _>(|v| simd::replace_lane(v, *lane, x));
+            }
+            F32x4ReplaceLane(lane) => {
+                let x: f32 = self.stack.pop();
+                self.unop::<i128, _>(|v| simd::replace_lane(v, *lane, x));
+            }
+            F64x2ReplaceLane(lane) => {
+                let x: f64 = self.stack.pop();
+                self.unop::<i128, _>(|v| simd::replace_lane(v, *lane, x));
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vrelop
+            I8x16Eq => self.lanewise_relop::<i8, i8, _>(|l, r| l == r),
+            I8x16Ne => self.lanewise_relop::<i8, i8, _>(|l, r| l != r),
+            I8x16LtS => self.lanewise_relop::<i8, i8, _>(|l, r| l < r),
+            I8x16LtU => self.lanewise_relop::<u8, i8, _>(|l, r| l < r),
+            I8x16GtS => self.lanewise_relop::<i8, i8, _>(|l, r| l > r),
+            I8x16GtU => self.lanewise_relop::<u8, i8, _>(|l, r| l > r),
+            I8x16LeS => self.lanewise_relop::<i8, i8, _>(|l, r| l <= r),
+            I8x16LeU => self.lanewise_relop::<u8, i8, _>(|l, r| l <= r),
+            I8x16GeS => self.lanewise_relop::<i8, i8, _>(|l, r| l >= r),
+            I8x16GeU => self.lanewise_relop::<u8, i8, _>(|l, r| l >= r),
+            I16x8Eq => self.lanewise_relop::<i16, i16, _>(|l, r| l == r),
+            I16x8Ne => self.lanewise_relop::<i16, i16, _>(|l, r| l != r),
+            I16x8LtS => self.lanewise_relop::<i16, i16, _>(|l, r| l < r),
+            I16x8LtU => self.lanewise_relop::<u16, i16, _>(|l, r| l < r),
+            I16x8GtS => self.lanewise_relop::<i16, i16, _>(|l, r| l > r),
+            I16x8GtU => self.lanewise_relop::<u16, i16, _>(|l, r| l > r),
+            I16x8LeS => self.lanewise_relop::<i16, i16, _>(|l, r| l <= r),
+            I16x8LeU => self.lanewise_relop::<u16, i16, _>(|l, r| l <= r),
+            I16x8GeS => self.lanewise_relop::<i16, i16, _>(|l, r| l >= r),
+            I16x8GeU => self.lanewise_relop::<u16, i16, _>(|l, r| l >= r),
+            I32x4Eq => self.lanewise_relop::<i32, i32, _>(|l, r| l == r),
+            I32x4Ne => self.lanewise_relop::<i32, i32, _>(|l, r| l != r),
+            I32x4LtS => self.lanewise_relop::<i32, i32, _>(|l, r| l < r),
+            I32x4LtU => self.lanewise_relop::<u32, i32, _>(|l, r| l < r),
+            I32x4GtS => self.lanewise_relop::<i32, i32, _>(|l, r| l > r),
+            I32x4GtU => self.lanewise_relop::<u32, i32, _>(|l, r| l > r),
+            I32x4LeS => self.lanewise_relop::<i32, i32, _>(|l, r| l <= r),
+            I32x4LeU => self.lanewise_relop::<u32, i32, _>(|l, r| l <= r),
+            I32x4GeS => self.lanewise_relop::<i32, i32, _>(|l, r| l >= r),
+            I32x4GeU => self.lanewise_relop::<u32, i32, _>(|l, r| l >= r),
+            I64x2Eq => self.lanewise_relop::<i64, i64, _>(|l, r| l == r),
+            I64x2Ne => self.lanewise_relop::<i64, i64, _>(|l, r| l != r),
+            I64x2LtS => self.lanewise_relop::<i64, i64, _>(|l, r| l < r),
+            I64x2GtS => self.lanewise_relop::<i64, i64, _>(|l, r| l > r),
+            I64x2LeS => self.lanewise_relop::<i64, i64, _>(|l, r| l <= r),
+            I64x2GeS => self.lanewise_relop::<i64, i64, _>(|l, r| l >= r),
+            F32x4Eq => self.lanewise_relop::<f32, i32, _>(|l, r| l == r),
+            F32x4Ne => self.lanewise_relop::<f32, i32, _>(|l, r| l != r),
+            F32x4Lt => self.lanewise_relop::<f32, i32, _>(|l, r| l < r),
+            F32x4Gt => self.lanewise_relop::<f32, i32, _>(|l, r| l > r),
+            F32x4Le => self.lanewise_relop::<f32, i32, _>(|l, r| l <= r),
+            F32x4Ge => self.lanewise_relop::<f32, i32, _>(|l, r| l >= r),
+            F64x2Eq => self.lanewise_relop::<f64, i64, _>(|l, r| l == r),
+            F64x2Ne => self.lanewise_relop::<f64, i64, _>(|l, r| l != r),
+            F64x2Lt => self.lanewise_relop::<f64, i64, _>(|l, r| l < r),
+            F64x2Gt => self.lanewise_relop::<f64, i64, _>(|l, r| l > r),
+            F64x2Le => self.lanewise_relop::<f64, i64, _>(|l, r| l <= r),
+            F64x2Ge => self.lanewise_relop::<f64, i64, _>(|l, r| l >= r),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vvunop
+            V128Not => self.unop::<i128, _>(|v| !v),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vvbinop
+            V128And => self.binop::<i128, _>(|l, r| l & r),
+            V128Andnot => self.binop::<i128, _>(|l, r| l & !r),
+            V128Or => self.binop::<i128, _>(|l, r| l | r),
+            V128Xor => self.binop::<i128, _>(|l, r| l ^ r),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vvternop
+            V128Bitselect => {
+                let c: i128 = self.stack.pop();
+                self.binop::<i128, _>(|l, r| l & c | r & !c);
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vvtestop
+            V128AnyTrue => self.testop::<i128, _>(|v| v != 0),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-all-true
+            I8x16AllTrue => self.testop::<i128, _>(simd::all_true::<i8>),
+            I16x8AllTrue => self.testop::<i128, _>(simd::all_true::<i16>),
+            I32x4AllTrue => self.testop::<i128, _>(simd::all_true::<i32>),
+            I64x2AllTrue => self.testop::<i128, _>(simd::all_true::<i64>),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-bitmask
+            I8x16Bitmask => self.cvtop::<i128, i32, _>(simd::bitmask::<i8>),
+            I16x8Bitmask => self.cvtop::<i128, i32, _>(simd::bitmask::<i16>),
+            I32x4Bitmask => self.cvtop::<i128, i32, _>(simd::bitmask::<i32>),
+            I64x2Bitmask => self.cvtop::<i128, i32, _>(simd::bitmask::<i64>),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vunop
+            I8x16Abs => self.lanewise_unop::<i8, _>(|x| x.wrapping_abs()),
+            I8x16Neg => self.lanewise_unop::<i8, _>(|x| x.wrapping_neg()),
+            I16x8Abs => self.lanewise_unop::<i16, _>(|x| x.wrapping_abs()),
+            I16x8Neg => self.lanewise_unop::<i16, _>(|x| x.wrapping_neg()),
+            I32x4Abs => self.lanewise_unop::<i32, _>(|x| x.wrapping_abs()),
+            I32x4Neg => self.lanewise_unop::<i32, _>(|x| x.wrapping_neg()),
+            I64x2Abs => self.lanewise_unop::<i64, _>(|x| x.wrapping_abs()),
+            I64x2Neg => self.lanewise_unop::<i64, _>(|x| x.wrapping_neg()),
+            I8x16Popcnt => self.lanewise_unop::<u8, _>(|x| x.count_ones() as u8),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vbinop
+            I8x16Add => self.lanewise_binop::<i8, _>(|l, r| l.wrapping_add(r)),
+            I8x16Sub => self.lanewise_binop::<i8, _>(|l, r| l.wrapping_sub(r)),
+            I16x8Add => self.lanewise_binop::<i16, _>(|l, r| l.wrapping_add(r)),
+            I16x8Sub => self.lanewise_binop::<i16, _>(|l, r| l.wrapping_sub(r)),
+            I32x4Add => self.lanewise_binop::<i32, _>(|l, r| l.wrapping_add(r)),
+            I32x4Sub => self.lanewise_binop::<i32, _>(|l, r| l.wrapping_sub(r)),
+            I64x2Add => self.lanewise_binop::<i64, _>(|l, r| l.wrapping_add(r)),
+            I64x2Sub => self.lanewise_binop::<i64, _>(|l, r| l.wrapping_sub(r)),
+            I16x8Mul => self.lanewise_binop::<i16, _>(|l, r| l.wrapping_mul(r)),
+            I32x4Mul => self.lanewise_binop::<i32, _>(|l, r| l.wrapping_mul(r)),
+            I64x2Mul => self.lanewise_binop::<i64, _>(|l, r| l.wrapping_mul(r)),
+            I8x16AddSatS => self.lanewise_binop::<i8, _>(|l, r| l.saturating_add(r)),
+            I8x16AddSatU => self.lanewise_binop::<u8, _>(|l, r| l.saturating_add(r)),
+            I8x16SubSatS => self.lanewise_binop::<i8, _>(|l, r| l.saturating_sub(r)),
+            I8x16SubSatU => self.lanewise_binop::<u8, _>(|l, r| l.saturating_sub(r)),
+            I16x8AddSatS => self.lanewise_binop::<i16, _>(|l, r| l.saturating_add(r)),
+            I16x8AddSatU => self.lanewise_binop::<u16, _>(|l, r| l.saturating_add(r)),
+            I16x8SubSatS => self.lanewise_binop::<i16, _>(|l, r| l.saturating_sub(r)),
+            I16x8SubSatU => self.lanewise_binop::<u16, _>(|l, r| l.saturating_sub(r)),
+            I8x16MinS => self.lanewise_binop::<i8, _>(|l, r| l.min(r)),
+            I8x16MinU => self.lanewise_binop::<u8, _>(|l, r| l.min(r)),
+            I8x16MaxS => self.lanewise_binop::<i8, _>(|l, r| l.max(r)),
+            I8x16MaxU => self.lanewise_binop::<u8, _>(|l, r| l.max(r)),
+            I16x8MinS => self.lanewise_binop::<i16, _>(|l, r| l.min(r)),
+            I16x8MinU => self.lanewise_binop::<u16, _>(|l, r| l.min(r)),
+            I16x8MaxS => self.lanewise_binop::<i16, _>(|l, r| l.max(r)),
+            I16x8MaxU => self.lanewise_binop::<u16, _>(|l, r| l.max(r)),
+            I32x4MinS => self.lanewise_binop::<i32, _>(|l, r| l.min(r)),
+            I32x4MinU => self.lanewise_binop::<u32, _>(|l, r| l.min(r)),
+            I32x4MaxS => self.lanewise_binop::<i32, _>(|l, r| l.max(r)),
+            I32x4MaxU => self.lanewise_binop::<u32, _>(|l, r| l.max(r)),
+            // https://webassembly.github.io/spec/core/exec/numerics.html#op-iavgr-u
+            I8x16AvgrU => {
+                self.lanewise_binop::<u8, _>(|l, r| ((l as u16 + r as u16 + 1) >> 1) as u8)
+            }
+            I16x8AvgrU => {
+                self.lanewise_binop::<u16, _>(|l, r| ((l as u32 + r as u32 + 1) >> 1) as u16)
+            }
+            // https://webassembly.github.io/spec/core/exec/numerics.html#op-iq15mulrsat-s
+            I16x8Q15mulrSatS => self.lanewise_binop::<i16, _>(|l, r| {
+                // Only -0x8000 * -0x8000 overflows
+                ((l as i32 * r as i32 + 0x4000) >> 15).min(i16::MAX as i32) as i16
+            }),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vshiftop
+            // Shift amount is taken modulo the lane width
+            I8x16Shl => self.lanewise_shift::<i8, _>(|x, s| x.wrapping_shl(s)),
+            I8x16ShrS => self.lanewise_shift::<i8, _>(|x, s| x.wrapping_shr(s)),
+            I8x16ShrU => self.lanewise_shift::<u8, _>(|x, s| x.wrapping_shr(s)),
+            I16x8Shl => self.lanewise_shift::<i16, _>(|x, s| x.wrapping_shl(s)),
+            I16x8ShrS => self.lanewise_shift::<i16, _>(|x, s| x.wrapping_shr(s)),
+            I16x8ShrU => self.lanewise_shift::<u16, _>(|x, s| x.wrapping_shr(s)),
+            I32x4Shl => self.lanewise_shift::<i32, _>(|x, s| x.wrapping_shl(s)),
+            I32x4ShrS => self.lanewise_shift::<i32, _>(|x, s| x.wrapping_shr(s)),
+            I32x4ShrU => self.lanewise_shift::<u32, _>(|x, s| x.wrapping_shr(s)),
+            I64x2Shl => self.lanewise_shift::<i64, _>(|x, s| x.wrapping_shl(s)),
+            I64x2ShrS => self.lanewise_shift::<i64, _>(|x, s| x.wrapping_shr(s)),
+            I64x2ShrU => self.lanewise_shift::<u64, _>(|x, s| x.wrapping_shr(s)),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-extend
+            I16x8ExtendLowI8x16S => self.unop::<i128, _>(|v| simd::extend::<i8, i16>(v, false)),
+            I16x8ExtendLowI8x16U => self.unop::<i128, _>(|v| simd::extend::<u8, u16>(v, false)),
+            I16x8ExtendHighI8x16S => self.unop::<i128, _>(|v| simd::extend::<i8, i16>(v, true)),
+            I16x8ExtendHighI8x16U => self.unop::<i128, _>(|v| simd::extend::<u8, u16>(v, true)),
+            I32x4ExtendLowI16x8S => self.unop::<i128, _>(|v| simd::extend::<i16, i32>(v, false)),
+            I32x4ExtendLowI16x8U => self.unop::<i128, _>(|v| simd::extend::<u16, u32>(v, false)),
+            I32x4ExtendHighI16x8S => self.unop::<i128, _>(|v| simd::extend::<i16, i32>(v, true)),
+            I32x4ExtendHighI16x8U => self.unop::<i128, _>(|v| simd::extend::<u16, u32>(v, true)),
+            I64x2ExtendLowI32x4S => self.unop::<i128, _>(|v| simd::extend::<i32, i64>(v, false)),
+            I64x2ExtendLowI32x4U => self.unop::<i128, _>(|v| simd::extend::<u32, u64>(v, false)),
+            I64x2ExtendHighI32x4S => self.unop::<i128, _>(|v| simd::extend::<i32, i64>(v, true)),
+            I64x2ExtendHighI32x4U => self.unop::<i128, _>(|v| simd::extend::<u32, u64>(v, true)),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-extmul
+            I16x8ExtmulLowI8x16S => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<i8, i16>(l, r, false))
+            }
+            I16x8ExtmulLowI8x16U => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<u8, u16>(l, r, false))
+            }
+            I16x8ExtmulHighI8x16S => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<i8, i16>(l, r, true))
+            }
+            I16x8ExtmulHighI8x16U => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<u8, u16>(l, r, true))
+            }
+            I32x4ExtmulLowI16x8S => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<i16, i32>(l, r, false))
+            }
+            I32x4ExtmulLowI16x8U => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<u16, u32>(l, r, false))
+            }
+            I32x4ExtmulHighI16x8S => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<i16, i32>(l, r, true))
+            }
+            I32x4ExtmulHighI16x8U => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<u16, u32>(l, r, true))
+            }
+            I64x2ExtmulLowI32x4S => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<i32, i64>(l, r, false))
+            }
+            I64x2ExtmulLowI32x4U => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<u32, u64>(l, r, false))
+            }
+            I64x2ExtmulHighI32x4S => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<i32, i64>(l, r, true))
+            }
+            I64x2ExtmulHighI32x4U => {
+                self.binop::<i128, _>(|l, r| simd::extmul::<u32, u64>(l, r, true))
+            }
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-extadd-pairwise
+            I16x8ExtaddPairwiseI8x16S => self.unop::<i128, _>(simd::extadd_pairwise::<i8, i16>),
+            I16x8ExtaddPairwiseI8x16U => self.unop::<i128, _>(simd::extadd_pairwise::<u8, u16>),
+            I32x4ExtaddPairwiseI16x8S => self.unop::<i128, _>(simd::extadd_pairwise::<i16, i32>),
+            I32x4ExtaddPairwiseI16x8U => self.unop::<i128, _>(simd::extadd_pairwise::<u16, u32>),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-dot
+            I32x4DotI16x8S => self.binop::<i128, _>(simd::dot_i16x8),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-narrow
+            I8x16NarrowI16x8S => self.binop::<i128, _>(|l, r| {
+                simd::narrow::<i16, i8, _>(l, r, |x| {
+                    x.max(i8::MIN as i16).min(i8::MAX as i16) as i8
+                })
+            }),
+            I8x16NarrowI16x8U => self.binop::<i128, _>(|l, r| {
+                simd::narrow::<i16, u8, _>(l, r, |x| x.max(0).min(u8::MAX as i16) as u8)
+            }),
+            I16x8NarrowI32x4S => self.binop::<i128, _>(|l, r| {
+                simd::narrow::<i32, i16, _>(l, r, |x| {
+                    x.max(i16::MIN as i32).min(i16::MAX as i32) as i16
+                })
+            }),
+            I16x8NarrowI32x4U => self.binop::<i128, _>(|l, r| {
+                simd::narrow::<i32, u16, _>(l, r, |x| x.max(0).min(u16::MAX as i32) as u16)
+            }),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vunop
+            F32x4Abs => self.lanewise_unop::<f32, _>(|x| x.abs()),
+            F32x4Neg => self.lanewise_unop::<f32, _>(|x| -x),
+            F32x4Sqrt => self.lanewise_unop::<f32, _>(|x| x.sqrt()),
+            F32x4Ceil => self.lanewise_unop::<f32, _>(|x| x.ceil()),
+            F32x4Floor => self.lanewise_unop::<f32, _>(|x| x.floor()),
+            F32x4Trunc => self.lanewise_unop::<f32, _>(|x| x.trunc()),
+            F32x4Nearest => self.lanewise_unop::<f32, _>(f32_nearest),
+            F64x2Abs => self.lanewise_unop::<f64, _>(|x| x.abs()),
+            F64x2Neg => self.lanewise_unop::<f64, _>(|x| -x),
+            F64x2Sqrt => self.lanewise_unop::<f64, _>(|x| x.sqrt()),
+            F64x2Ceil => self.lanewise_unop::<f64, _>(|x| x.ceil()),
+            F64x2Floor => self.lanewise_unop::<f64, _>(|x| x.floor()),
+            F64x2Trunc => self.lanewise_unop::<f64, _>(|x| x.trunc()),
+            F64x2Nearest => self.lanewise_unop::<f64, _>(f64_nearest),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vbinop
+            F32x4Add => self.lanewise_binop::<f32, _>(|l, r| l + r),
+            F32x4Sub => self.lanewise_binop::<f32, _>(|l, r| l - r),
+            F32x4Mul => self.lanewise_binop::<f32, _>(|l, r| l * r),
+            F32x4Div => self.lanewise_binop::<f32, _>(|l, r| l / r),
+            F32x4Min => self.lanewise_binop::<f32, _>(simd::f32_min),
+            F32x4Max => self.lanewise_binop::<f32, _>(simd::f32_max),
+            F32x4Pmin => self.lanewise_binop::<f32, _>(|l, r| if r < l { r } else { l }),
+            F32x4Pmax => self.lanewise_binop::<f32, _>(|l, r| if l < r { r } else { l }),
+            F64x2Add => self.lanewise_binop::<f64, _>(|l, r| l + r),
+            F64x2Sub => self.lanewise_binop::<f64, _>(|l, r| l - r),
+            F64x2Mul => self.lanewise_binop::<f64, _>(|l, r| l * r),
+            F64x2Div => self.lanewise_binop::<f64, _>(|l, r| l / r),
+            F64x2Min => self.lanewise_binop::<f64, _>(simd::f64_min),
+            F64x2Max => self.lanewise_binop::<f64, _>(simd::f64_max),
+            F64x2Pmin => self.lanewise_binop::<f64, _>(|l, r| if r < l { r } else { l }),
+            F64x2Pmax => self.lanewise_binop::<f64, _>(|l, r| if l < r { r } else { l }),
+            // https://webassembly.github.io/spec/core/exec/instructions.html#exec-vcvtop
+            I32x4TruncSatF32x4S => self.unop::<i128, _>(|v| simd::map(v, cast::f32_to_i32)),
+            I32x4TruncSatF32x4U => self.unop::<i128, _>(|v| simd::map(v, cast::f32_to_u32)),
+            I32x4TruncSatF64x2SZero => self.unop::<i128, _>(|v| simd::map(v, cast::f64_to_i32)),
+            I32x4TruncSatF64x2UZero => self.unop::<i128, _>(|v| simd::map(v, cast::f64_to_u32)),
+            F32x4ConvertI32x4S => {
+                self.unop::<i128, _>(|v| simd::map::<i32, f32, _>(v, |x| x as f32))
+            }
+            F32x4ConvertI32x4U => {
+                self.unop::<i128, _>(|v| simd::map::<u32, f32, _>(v, |x| x as f32))
+            }
+            F64x2ConvertLowI32x4S => self.unop::<i128, _>(|v| simd::extend::<i32, f64>(v, false)),
+            F64x2ConvertLowI32x4U => self.unop::<i128, _>(|v| simd::extend::<u32, f64>(v, false)),
+            F32x4DemoteF64x2Zero => {
+                self.unop::<i128, _>(|v| simd::map::<f64, f32, _>(v, |x| x as f32))
+            }
+            F64x2PromoteLowF32x4 => self.unop::<i128, _>(|v| simd::extend::<f32, f64>(v, false)),
+            // Atomic instructions
+            // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md
+            MemoryAtomicNotify(mem) => {
+                self.stack.pop::<i32>(); // Maximum number of waiters to wake up
+                self.atomic_load::<i32>(mem, insn.start)?;
+                // No thread is waiting since memories are accessed by a single thread
+                self.stack.push(0i32);
+            }
+            MemoryAtomicWait32(mem) => {
+                let _timeout: i64 = self.stack.pop();
+                let expected: i32 = self.stack.pop();
+                let ret = self.atomic_wait(mem, expected, insn.start)?;
+                self.stack.push(ret);
+            }
+            MemoryAtomicWait64(mem) => {
+                let _timeout: i64 = self.stack.pop();
+                let expected: i64 = self.stack.pop();
+                let ret = self.atomic_wait(mem, expected, insn.start)?;
+                self.stack.push(ret);
+            }
+            // Nothing to order since only one thread accesses memories
+            AtomicFence => {}
+            I32AtomicLoad(mem) => {
+                let v: i32 = self.atomic_load(mem, insn.start)?;
+                self.stack.push(v);
+            }
+            I64AtomicLoad(mem) => {
+                let v: i64 = self.atomic_load(mem, insn.start)?;
+                self.stack.push(v);
+            }
+            I32AtomicLoad8U(mem) => {
+                let v: u8 = self.atomic_load(mem, insn.start)?;
+                self.stack.push(v as i32);
+            }
+            I32AtomicLoad16U(mem) => {
+                let v: u16 = self.atomic_load(mem, insn.start)?;
+                self.stack.push(v as i32);
+            }
+            I64AtomicLoad8U(mem) => {
+                let v: u8 = self.atomic_load(mem, insn.start)?;
+                self.stack.push(v as i64);
+            }
+            I64AtomicLoad16U(mem) => {
+                let v: u16 = self.atomic_load(mem, insn.start)?;
+                self.stack.push(v as i64);
+            }
+            I64AtomicLoad32U(mem) => {
+                let v: u32 = self.atomic_load(mem, insn.start)?;
+                self.stack.push(v as i64);
+            }
+            I32AtomicStore(mem) => {
+                let v: i32 = self.stack.pop();
+                self.atomic_store(mem, v, insn.start)?;
+            }
+            I64AtomicStore(mem) => {
+                let v: i64 = self.stack.pop();
+                self.atomic_store(mem, v, insn.start)?;
+            }
+            I32AtomicStore8(mem) => {
+                let v: i32 = self.stack.pop();
+                self.atomic_store(mem, v as u8, insn.start)?;
+            }
+            I32AtomicStore16(mem) => {
+                let v: i32 = self.stack.pop();
+                self.atomic_store(mem, v as u16, insn.start)?;
+            }
+            I64AtomicStore8(mem) => {
+                let v: i64 = self.stack.pop();
+                self.atomic_store(mem, v as u8, insn.start)?;
+            }
+            I64AtomicStore16(mem) => {
+                let v: i64 = self.stack.pop();
+                self.atomic_store(mem, v as u16, insn.start)?;
+            }
+            I64AtomicStore32(mem) => {
+                let v: i64 = self.stack.pop();
+                self.atomic_store(mem, v as u32, insn.start)?;
+            }
+            I32AtomicRmwAdd(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, i32::wrapping_add)?;
+                self.stack.push(old);
+            }
+            I64AtomicRmwAdd(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, i64::wrapping_add)?;
+                self.stack.push(old);
+            }
+            I32AtomicRmw8AddU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, u8::wrapping_add)?;
+                self.stack.push(old as i32);
+            }
+            I32AtomicRmw16AddU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, u16::wrapping_add)?;
+                self.stack.push(old as i32);
+            }
+            I64AtomicRmw8AddU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, u8::wrapping_add)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw16AddU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, u16::wrapping_add)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw32AddU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u32, insn.start, u32::wrapping_add)?;
+                self.stack.push(old as i64);
+            }
+            I32AtomicRmwSub(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, i32::wrapping_sub)?;
+                self.stack.push(old);
+            }
+            I64AtomicRmwSub(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, i64::wrapping_sub)?;
+                self.stack.push(old);
+            }
+            I32AtomicRmw8SubU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, u8::wrapping_sub)?;
+                self.stack.push(old as i32);
+            }
+            I32AtomicRmw16SubU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, u16::wrapping_sub)?;
+                self.stack.push(old as i32);
+            }
+            I64AtomicRmw8SubU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, u8::wrapping_sub)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw16SubU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, u16::wrapping_sub)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw32SubU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u32, insn.start, u32::wrapping_sub)?;
+                self.stack.push(old as i64);
+            }
+            I32AtomicRmwAnd(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, |old, v| old & v)?;
+                self.stack.push(old);
+            }
+            I64AtomicRmwAnd(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, |old, v| old & v)?;
+                self.stack.push(old);
+            }
+            I32AtomicRmw8AndU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, |old, v| old & v)?;
+                self.stack.push(old as i32);
+            }
+            I32AtomicRmw16AndU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, |old, v| old & v)?;
+                self.stack.push(old as i32);
+            }
+            I64AtomicRmw8AndU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, |old, v| old & v)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw16AndU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, |old, v| old & v)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw32AndU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u32, insn.start, |old, v| old & v)?;
+                self.stack.push(old as i64);
+            }
+            I32AtomicRmwOr(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, |old, v| old | v)?;
+                self.stack.push(old);
+            }
+            I64AtomicRmwOr(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, |old, v| old | v)?;
+                self.stack.push(old);
+            }
+            I32AtomicRmw8OrU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, |old, v| old | v)?;
+                self.stack.push(old as i32);
+            }
+            I32AtomicRmw16OrU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, |old, v| old | v)?;
+                self.stack.push(old as i32);
+            }
+            I64AtomicRmw8OrU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, |old, v| old | v)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw16OrU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, |old, v| old | v)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw32OrU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u32, insn.start, |old, v| old | v)?;
+                self.stack.push(old as i64);
+            }
+            I32AtomicRmwXor(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, |old, v| old ^ v)?;
+                self.stack.push(old);
+            }
+            I64AtomicRmwXor(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, |old, v| old ^ v)?;
+                self.stack.push(old);
+            }
+            I32AtomicRmw8XorU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, |old, v| old ^ v)?;
+                self.stack.push(old as i32);
+            }
+            I32AtomicRmw16XorU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, |old, v| old ^ v)?;
+                self.stack.push(old as i32);
+            }
+            I64AtomicRmw8XorU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, |old, v| old ^ v)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw16XorU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, |old, v| old ^ v)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw32XorU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u32, insn.start, |old, v| old ^ v)?;
+                self.stack.push(old as i64);
+            }
+            I32AtomicRmwXchg(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, |_, v| v)?;
+                self.stack.push(old);
+            }
+            I64AtomicRmwXchg(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v, insn.start, |_, v| v)?;
+                self.stack.push(old);
+            }
+            I32AtomicRmw8XchgU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, |_, v| v)?;
+                self.stack.push(old as i32);
+            }
+            I32AtomicRmw16XchgU(mem) => {
+                let v: i32 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, |_, v| v)?;
+                self.stack.push(old as i32);
+            }
+            I64AtomicRmw8XchgU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u8, insn.start, |_, v| v)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw16XchgU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u16, insn.start, |_, v| v)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw32XchgU(mem) => {
+                let v: i64 = self.stack.pop();
+                let old = self.atomic_rmw(mem, v as u32, insn.start, |_, v| v)?;
+                self.stack.push(old as i64);
+            }
+            I32AtomicRmwCmpxchg(mem) => {
+                let replacement: i32 = self.stack.pop();
+                let expected: i32 = self.stack.pop();
+                let old = self.atomic_cmpxchg(mem, expected, replacement, insn.start)?;
+                self.stack.push(old);
+            }
+            I64AtomicRmwCmpxchg(mem) => {
+                let replacement: i64 = self.stack.pop();
+                let expected: i64 = self.stack.pop();
+                let old = self.atomic_cmpxchg(mem, expected, replacement, insn.start)?;
+                self.stack.push(old);
+            }
+            I32AtomicRmw8CmpxchgU(mem) => {
+                let replacement: i32 = self.stack.pop();
+                let expected: i32 = self.stack.pop();
+                let old =
+                    self.atomic_cmpxchg(mem, expected as u8, replacement as u8, insn.start)?;
+                self.stack.push(old as i32);
+            }
+            I32AtomicRmw16CmpxchgU(mem) => {
+                let replacement: i32 = self.stack.pop();
+                let expected: i32 = self.stack.pop();
+                let old =
+                    self.atomic_cmpxchg(mem, expected as u16, replacement as u16, insn.start)?;
+                self.stack.push(old as i32);
+            }
+            I64AtomicRmw8CmpxchgU(mem) => {
+                let replacement: i64 = self.stack.pop();
+                let expected: i64 = self.stack.pop();
+                let old =
+                    self.atomic_cmpxchg(mem, expected as u8, replacement as u8, insn.start)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw16CmpxchgU(mem) => {
+                let replacement: i64 = self.stack.pop();
+                let expected: i64 = self.stack.pop();
+                let old =
+                    self.atomic_cmpxchg(mem, expected as u16, replacement as u16, insn.start)?;
+                self.stack.push(old as i64);
+            }
+            I64AtomicRmw32CmpxchgU(mem) => {
+                let replacement: i64 = self.stack.pop();
+                let expected: i64 = self.stack.pop();
+                let old =
+                    self.atomic_cmpxchg(mem, expected as u32, replacement as u32, insn.start)?;
+                self.stack.push(old as i64);
+            }
         }
-        Ok(ExecState::Continue)
+        Ok(())
     }
 }
 
@@ -939,6 +3432,7 @@ impl<'f, 'm, 's, I: Importer> Execute<'f, 'm, 's, I> for ast::Instruction {
 mod tests {
     use super::*;
     use crate::import::DefaultImporter;
+    use crate::test_util::parse_module;
     use std::env;
     use std::fmt;
     use std::fs;
@@ -1000,4 +3494,1954 @@ mod tests {
         assert_eq!(run, Run::Success);
         assert_eq!(stdout, b"Hello, world\n");
     }
+
+    #[test]
+    fn link_instances() {
+        let lib = parse_module(
+            r#"
+            (module
+              (type $t (func (result i32)))
+              (memory (export "mem") 1)
+              (table (export "tab") 2 funcref)
+              (global $g (export "counter") (mut i32) (i32.const 0))
+              (elem (i32.const 0) $one)
+              (func $one (result i32) i32.const 1)
+              (func (export "store") (param i32 i32)
+                local.get 0
+                local.get 1
+                i32.store)
+              (func (export "incr") (result i32)
+                global.get $g
+                i32.const 1
+                i32.add
+                global.set $g
+                global.get $g)
+              (func (export "call_tab") (param i32) (result i32)
+                local.get 0
+                call_indirect (type $t)))
+        "#,
+        );
+        let main = parse_module(
+            r#"
+            (module
+              (import "lib" "store" (func $store (param i32 i32)))
+              (import "lib" "incr" (func $incr (result i32)))
+              (import "lib" "mem" (memory 1))
+              (import "lib" "tab" (table 2 funcref))
+              (import "lib" "counter" (global $counter (mut i32)))
+              (elem (i32.const 1) $two)
+              (func $two (result i32) i32.const 2)
+              (func (export "load") (param i32) (result i32)
+                local.get 0
+                i32.load)
+              (func (export "run") (result i32)
+                i32.const 8
+                i32.const 42
+                call $store
+                call $incr
+                drop
+                i32.const 10
+                global.set $counter
+                call $incr))
+        "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&lib.module, importer).unwrap();
+        let lib_id = machine.latest_instance();
+        machine.register("lib", lib_id);
+        let main_id = machine.instantiate_module(&main.module).unwrap();
+        assert_eq!(machine.latest_instance(), main_id);
+
+        assert_eq!(machine.invoke("run", &[]).unwrap(), vec![Value::I32(11)]);
+        // Memory is shared between instances
+        assert_eq!(
+            machine.invoke("load", &[Value::I32(8)]).unwrap(),
+            vec![Value::I32(42)],
+        );
+        assert_eq!(
+            machine.instance_memory(lib_id).load::<i32>(8, 0).unwrap(),
+            42
+        );
+        // Global variable is shared between instances
+        assert_eq!(
+            machine.instance_global(lib_id, "counter"),
+            Some(Value::I32(11)),
+        );
+        // Table is shared and function in table is called in the instance which defines it
+        for (idx, expected) in [(0, 1), (1, 2)].iter() {
+            let ret = machine
+                .invoke_instance(lib_id, "call_tab", &[Value::I32(*idx)])
+                .unwrap();
+            assert_eq!(ret, vec![Value::I32(*expected)]);
+        }
+    }
+
+    #[test]
+    fn link_error() {
+        let lib = parse_module(r#"(module (func (export "f")) (memory (export "m") 1))"#);
+        let missing = parse_module(r#"(module (import "lib" "g" (func)))"#);
+        let kind_mismatch = parse_module(r#"(module (import "lib" "m" (func)))"#);
+        let sig_mismatch = parse_module(r#"(module (import "lib" "f" (func (param i32))))"#);
+        let limit_mismatch = parse_module(r#"(module (import "lib" "m" (memory 2)))"#);
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&lib.module, importer).unwrap();
+        machine.register("lib", machine.latest_instance());
+
+        let err = machine.instantiate_module(&missing.module).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::UnknownImport { .. }));
+        let err = machine
+            .instantiate_module(&kind_mismatch.module)
+            .unwrap_err();
+        assert!(matches!(err.reason, TrapReason::IncompatibleImport { .. }));
+        let err = machine
+            .instantiate_module(&sig_mismatch.module)
+            .unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::FuncSignatureMismatch { .. }
+        ));
+        let err = machine
+            .instantiate_module(&limit_mismatch.module)
+            .unwrap_err();
+        assert!(matches!(err.reason, TrapReason::IncompatibleImport { .. }));
+    }
+
+    #[test]
+    fn stack_exhausted() {
+        let root = parse_module(
+            r#"
+            (module
+              (func $runaway (export "runaway") (call $runaway))
+              (func $mutual1 (export "mutual") (call $mutual2))
+              (func $mutual2 (call $mutual1))
+              (func $depth (export "depth") (param i32) (result i32)
+                local.get 0
+                i32.eqz
+                if (result i32)
+                  i32.const 0
+                else
+                  local.get 0
+                  i32.const 1
+                  i32.sub
+                  call $depth
+                  i32.const 1
+                  i32.add
+                end)
+              (func $locals (export "locals") (param i32)
+                (local i64 i64 i64 i64 i64 i64 i64 i64)
+                local.get 0
+                if
+                  local.get 0
+                  i32.const 1
+                  i32.sub
+                  call $locals
+                end)
+            )
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+        machine.set_max_call_depth(100);
+
+        for name in &["runaway", "mutual"] {
+            let err = machine.invoke(name, &[]).unwrap_err();
+            match err.reason {
+                TrapReason::StackExhausted { kind, limit } => {
+                    assert_eq!(kind, "call stack");
+                    assert_eq!(limit, 100);
+                }
+                reason => panic!("unexpected trap: {:?}", reason),
+            }
+        }
+
+        // Machine is still available after the trap
+        let ret = machine.invoke("depth", &[Value::I32(99)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(99)]);
+        let err = machine.invoke("depth", &[Value::I32(100)]).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::StackExhausted { .. }));
+
+        // One call of $locals consumes 9 slots of 16 bytes
+        machine.set_max_stack_size(144 * 10);
+        machine.invoke("locals", &[Value::I32(9)]).unwrap();
+        let err = machine.invoke("locals", &[Value::I32(10)]).unwrap_err();
+        match err.reason {
+            TrapReason::StackExhausted { kind, limit } => {
+                assert_eq!(kind, "value stack");
+                assert_eq!(limit, 1440);
+            }
+            reason => panic!("unexpected trap: {:?}", reason),
+        }
+    }
+
+    #[test]
+    fn fuel_metering() {
+        let root = parse_module(
+            r#"
+            (module
+              (func $add (export "add") (param i32 i32) (result i32)
+                local.get 0
+                local.get 1
+                i32.add)
+              (func (export "call_add") (result i32)
+                i32.const 1
+                i32.const 2
+                call $add)
+              (func (export "spin")
+                loop
+                  br 0
+                end)
+            )
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        // Fuel metering is disabled by default
+        machine
+            .invoke("add", &[Value::I32(1), Value::I32(2)])
+            .unwrap();
+        assert_eq!(machine.remaining_fuel(), None);
+        assert_eq!(machine.consumed_fuel(), 0);
+
+        machine.set_fuel(10);
+        let ret = machine.invoke("add", &[Value::I32(1), Value::I32(2)]);
+        assert_eq!(ret.unwrap(), vec![Value::I32(3)]);
+        assert_eq!(machine.remaining_fuel(), Some(7));
+        assert_eq!(machine.consumed_fuel(), 3);
+
+        let ret = machine.invoke("call_add", &[]);
+        assert_eq!(ret.unwrap(), vec![Value::I32(3)]);
+        assert_eq!(machine.remaining_fuel(), Some(1));
+        assert_eq!(machine.consumed_fuel(), 9);
+
+        let err = machine
+            .invoke("add", &[Value::I32(1), Value::I32(2)])
+            .unwrap_err();
+        assert!(matches!(err.reason, TrapReason::OutOfFuel { consumed: 10 }));
+        assert_eq!(machine.remaining_fuel(), Some(0));
+
+        // Infinite loop is stopped
+        machine.set_fuel(1000);
+        let err = machine.invoke("spin", &[]).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::OutOfFuel { consumed: 1010 }
+        ));
+
+        // Costs can be configured per instruction
+        machine.set_fuel_cost(|kind| match kind {
+            ast::InsnKind::Call(_) => 10,
+            _ => 1,
+        });
+        machine.set_fuel(15);
+        let ret = machine.invoke("call_add", &[]);
+        assert_eq!(ret.unwrap(), vec![Value::I32(3)]);
+        assert_eq!(machine.remaining_fuel(), Some(0));
+        assert_eq!(machine.consumed_fuel(), 1025);
+    }
+
+    #[test]
+    fn suspend_and_resume() {
+        let root = parse_module(
+            r#"
+            (module
+              (func $count (export "count") (param i32) (result i32) (local i32)
+                block
+                  loop
+                    local.get 1
+                    local.get 0
+                    i32.ge_s
+                    br_if 1
+                    local.get 1
+                    i32.const 1
+                    i32.add
+                    local.set 1
+                    br 0
+                  end
+                end
+                local.get 1)
+              (func (export "call_count") (param i32) (result i32)
+                local.get 0
+                call $count
+                i32.const 1
+                i32.add)
+              (func (export "spin")
+                loop
+                  br 0
+                end)
+            )
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        // Time slicing with fuel
+        machine.set_fuel(10);
+        let mut ret = machine
+            .invoke_resumable("call_count", &[Value::I32(100)])
+            .unwrap();
+        let mut slices = 1;
+        while ret == Invocation::Suspended(Suspension::OutOfFuel) {
+            assert!(machine.is_suspended());
+            machine.set_fuel(10);
+            ret = machine.resume().unwrap();
+            slices += 1;
+        }
+        assert_eq!(ret, Invocation::Finished(vec![Value::I32(101)]));
+        assert!(!machine.is_suspended());
+        assert!(slices > 90, "{}", slices);
+
+        let err = machine.resume().unwrap_err();
+        assert!(matches!(err.reason, TrapReason::NotSuspended));
+
+        // Starting a new invocation discards the suspended execution
+        machine.set_fuel(5);
+        let ret = machine.invoke_resumable("count", &[Value::I32(10)]);
+        assert_eq!(ret.unwrap(), Invocation::Suspended(Suspension::OutOfFuel));
+        machine.set_fuel(1000);
+        let ret = machine.invoke_resumable("count", &[Value::I32(3)]);
+        assert_eq!(ret.unwrap(), Invocation::Finished(vec![Value::I32(3)]));
+        assert_eq!(machine.stack.size(), 0);
+
+        // Interrupt from another thread
+        let mut machine = Machine::instantiate(
+            &root.module,
+            DefaultImporter::with_stdio(Discard, io::sink()),
+        )
+        .unwrap();
+        let handle = machine.interrupt_handle();
+        let thread = std::thread::spawn(move || handle.interrupt());
+        let ret = machine.invoke_resumable("spin", &[]).unwrap();
+        assert_eq!(ret, Invocation::Suspended(Suspension::Interrupted));
+        thread.join().unwrap();
+
+        // Interrupting execution which is not resumable causes a trap
+        machine.interrupt_handle().interrupt();
+        let err = machine.invoke("count", &[Value::I32(10)]).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::Interrupted));
+        assert!(!machine.is_suspended());
+        let ret = machine.invoke("count", &[Value::I32(10)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(10)]);
+    }
+
+    #[test]
+    fn exit_by_host_function() {
+        use crate::linker::Linker;
+
+        let root = parse_module(
+            r#"
+            (module
+              (import "env" "exit" (func $exit (param i32)))
+              (func $run (export "run") (param i32) (result i32)
+                local.get 0
+                i32.eqz
+                if
+                  i32.const 42
+                  call $exit
+                end
+                local.get 0)
+              (func (export "_start")
+                i32.const 0
+                call $run
+                drop))
+            "#,
+        );
+
+        let mut linker = Linker::new();
+        linker.func(
+            "env",
+            "exit",
+            &[ast::ValType::I32],
+            &[],
+            |args, _| match args {
+                [Value::I32(status)] => Err(ImportInvokeError::Exit { status: *status }),
+                _ => unreachable!(),
+            },
+        );
+        let mut machine = Machine::instantiate(&root.module, linker).unwrap();
+
+        assert_eq!(machine.execute().unwrap(), Run::Exit(42));
+        assert_eq!(machine.stack.size(), 0);
+
+        let ret = machine.invoke_resumable("run", &[Value::I32(0)]).unwrap();
+        assert_eq!(ret, Invocation::Exited(42));
+        assert!(!machine.is_suspended());
+        let ret = machine.invoke_resumable("run", &[Value::I32(1)]).unwrap();
+        assert_eq!(ret, Invocation::Finished(vec![Value::I32(1)]));
+
+        // Methods which return only values report the exit as trap
+        let err = machine.invoke("run", &[Value::I32(0)]).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::Exit { status: 42 }));
+        let ret = machine.invoke("run", &[Value::I32(3)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(3)]);
+    }
+
+    #[test]
+    fn typed_functions() {
+        let root = parse_module(
+            r#"
+            (module
+              (func (export "add") (param i32 i32) (result i32)
+                local.get 0
+                local.get 1
+                i32.add)
+              (func (export "swap") (param i64 f64) (result f64 i64)
+                local.get 1
+                local.get 0)
+              (func (export "nop"))
+              (func (export "trap") (param i32) (result i32)
+                unreachable)
+              (memory (export "mem") 1))
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        let add = machine.get_typed_func::<(i32, i32), i32>("add").unwrap();
+        assert_eq!(add.call(&mut machine, (1, 2)).unwrap(), 3);
+        assert_eq!(add.call(&mut machine, (-1, 1)).unwrap(), 0);
+        let swap = machine
+            .get_typed_func::<(i64, f64), (f64, i64)>("swap")
+            .unwrap();
+        assert_eq!(swap.call(&mut machine, (1, 2.5)).unwrap(), (2.5, 1));
+        let nop = machine.get_typed_func::<(), ()>("nop").unwrap();
+        nop.call(&mut machine, ()).unwrap();
+        assert_eq!(machine.stack.size(), 0);
+
+        // Trap in typed function leaves the machine reusable
+        let trap = machine.get_typed_func::<i32, i32>("trap").unwrap();
+        let err = trap.call(&mut machine, 0).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::ReachUnreachable));
+        assert_eq!(machine.stack.size(), 0);
+        assert_eq!(add.call(&mut machine, (3, 4)).unwrap(), 7);
+
+        // Signature is checked when getting the handle
+        let err = machine
+            .get_typed_func::<(i32, i64), i32>("add")
+            .unwrap_err();
+        match err.reason {
+            TrapReason::FuncSignatureMismatch {
+                import: None,
+                expected_params,
+                actual_params,
+                ..
+            } => {
+                assert_eq!(&*expected_params, &[ast::ValType::I32, ast::ValType::I64]);
+                assert_eq!(&*actual_params, &[ast::ValType::I32, ast::ValType::I32]);
+            }
+            reason => panic!("unexpected trap: {:?}", reason),
+        }
+        let err = machine
+            .get_typed_func::<(i64, f64), i64>("swap")
+            .unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::FuncSignatureMismatch { .. }
+        ));
+
+        let err = machine.get_typed_func::<(), ()>("mem").unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::WrongInvokeTarget {
+                actual: Some("memory"),
+                ..
+            }
+        ));
+        let err = machine.get_typed_func::<(), ()>("unknown").unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::WrongInvokeTarget { actual: None, .. }
+        ));
+    }
+
+    #[test]
+    fn access_exports_from_host() {
+        let root = parse_module(
+            r#"
+            (module
+              (memory (export "mem") 1 2)
+              (table (export "table") 2 funcref)
+              (table (export "externs") 1 externref)
+              (global (export "counter") (mut i32) (i32.const 0))
+              (global (export "const") i64 (i64.const 1))
+              (func $double (export "double") (param i32) (result i32)
+                local.get 0
+                i32.const 2
+                i32.mul)
+              ;; Sum bytes in memory[0..len) and add the counter
+              (func (export "sum") (param $len i32) (result i32)
+                (local $i i32)
+                (local $acc i32)
+                global.get 0
+                local.set $acc
+                block $break
+                  loop $continue
+                    local.get $i
+                    local.get $len
+                    i32.ge_u
+                    br_if $break
+                    local.get $acc
+                    local.get $i
+                    i32.load8_u
+                    i32.add
+                    local.set $acc
+                    local.get $i
+                    i32.const 1
+                    i32.add
+                    local.set $i
+                    br $continue
+                  end
+                end
+                local.get $acc)
+              (func (export "call_table") (param i32) (result i32)
+                local.get 0
+                i32.const 0
+                call_indirect (param i32) (result i32)))
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        // Memory
+        let mem = machine.get_memory_mut("mem").unwrap();
+        mem.write_bytes(0, &[1, 2, 3, 4]).unwrap();
+        mem.store(8, 0x1234_5678i32, 0).unwrap();
+        assert_eq!(mem.grow(1), 1);
+        assert_eq!(mem.grow(1), -1);
+        let err = mem.write_bytes(2 * 65536 - 1, &[0, 0]).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::OutOfBounds {
+                operation: "write",
+                ..
+            }
+        ));
+        let ret = machine.invoke("sum", &[Value::I32(4)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(10)]);
+
+        let mem = machine.get_memory("mem").unwrap();
+        assert_eq!(mem.size(), 2);
+        let mut buf = [0; 4];
+        mem.read_bytes(8, &mut buf).unwrap();
+        assert_eq!(buf, [0x78, 0x56, 0x34, 0x12]);
+        assert_eq!(mem.load::<i32>(8, 0).unwrap(), 0x1234_5678);
+        assert!(mem.read_bytes(2 * 65536, &mut buf).is_err());
+        assert!(machine.get_memory("table").is_none());
+        assert!(machine.get_memory("unknown").is_none());
+
+        // Global variables
+        machine.set_global("counter", Value::I32(100)).unwrap();
+        assert_eq!(machine.get_global("counter"), Some(Value::I32(100)));
+        let ret = machine.invoke("sum", &[Value::I32(4)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(110)]);
+
+        let err = machine.set_global("counter", Value::I64(1)).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::ExportTypeMismatch {
+                expected: ast::ValType::I32,
+                actual: ast::ValType::I64,
+                ..
+            }
+        ));
+        let err = machine.set_global("const", Value::I64(2)).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::SetImmutableGlobal { .. }));
+        assert_eq!(machine.get_global("const"), Some(Value::I64(1)));
+        let err = machine.set_global("mem", Value::I32(0)).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::UnknownExport {
+                kind: "global variable",
+                ..
+            }
+        ));
+
+        // Tables
+        let double = machine.get_func_ref("double").unwrap();
+        assert!(machine.get_func_ref("mem").is_none());
+        let err = machine.invoke("call_table", &[Value::I32(3)]).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::UninitializedElem(0)));
+        machine
+            .set_table_elem("table", 0, Value::FuncRef(Some(double)))
+            .unwrap();
+        let ret = machine.invoke("call_table", &[Value::I32(3)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(6)]);
+
+        let table = machine.get_table("table").unwrap();
+        assert_eq!(table.size(), 2);
+        assert_eq!(table.get(0, 0).unwrap(), Some(double));
+        assert_eq!(table.get(1, 0).unwrap(), None);
+
+        let err = machine
+            .set_table_elem("table", 2, Value::FuncRef(None))
+            .unwrap_err();
+        assert!(matches!(err.reason, TrapReason::OutOfBounds { .. }));
+        let err = machine
+            .set_table_elem("table", 0, Value::ExternRef(Some(1)))
+            .unwrap_err();
+        assert!(matches!(err.reason, TrapReason::ExportTypeMismatch { .. }));
+        let err = machine
+            .set_table_elem("table", 0, Value::FuncRef(Some(100)))
+            .unwrap_err();
+        assert!(matches!(err.reason, TrapReason::UnknownFuncRef(100)));
+        machine
+            .set_table_elem("externs", 0, Value::ExternRef(Some(42)))
+            .unwrap();
+        assert_eq!(
+            machine.get_table("externs").unwrap().get(0, 0).unwrap(),
+            Some(42)
+        );
+        let err = machine
+            .set_table_elem("double", 0, Value::FuncRef(None))
+            .unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::UnknownExport { kind: "table", .. }
+        ));
+    }
+
+    #[test]
+    fn multi_value() {
+        let root = parse_module(
+            r#"
+            (module
+              (type $pair (func (param i32 i32) (result i32 i32)))
+              (func $swap (export "swap") (param i32 i64) (result i64 i32)
+                local.get 1
+                local.get 0)
+              (func (export "call") (result i64 i32)
+                i32.const 5
+                i64.const 6
+                call $swap)
+              (func (export "block") (param i32) (result i32 i32 i32)
+                i32.const 1
+                local.get 0
+                i32.const 2
+                block (type $pair)
+                  i32.const 10
+                  i32.const 20
+                  local.get 0
+                  br_if 0
+                  drop
+                  drop
+                end)
+              (func (export "sum") (param i32) (result i32) (local i32)
+                i32.const 0
+                local.get 0
+                loop (param i32 i32) (result i32)
+                  local.set 1
+                  local.get 1
+                  i32.add
+                  local.get 1
+                  i32.const 1
+                  i32.sub
+                  local.get 1
+                  i32.const 1
+                  i32.ne
+                  br_if 0
+                  drop
+                end)
+              (func (export "if") (param i32) (result i32)
+                i32.const 3
+                i32.const 4
+                local.get 0
+                if (param i32 i32) (result i32)
+                  i32.add
+                else
+                  i32.mul
+                end)
+              (func (export "return") (result i32 i32)
+                i32.const 9
+                block
+                  i32.const 1
+                  i32.const 2
+                  return
+                end
+                unreachable)
+            )
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        let ret = machine.invoke("swap", &[Value::I32(1), Value::I64(2)]);
+        assert_eq!(ret.unwrap(), vec![Value::I64(2), Value::I32(1)]);
+        let ret = machine.invoke("call", &[]);
+        assert_eq!(ret.unwrap(), vec![Value::I64(6), Value::I32(5)]);
+        let ret = machine.invoke("block", &[Value::I32(7)]);
+        assert_eq!(
+            ret.unwrap(),
+            vec![Value::I32(1), Value::I32(10), Value::I32(20)],
+        );
+        let ret = machine.invoke("block", &[Value::I32(0)]);
+        assert_eq!(
+            ret.unwrap(),
+            vec![Value::I32(1), Value::I32(0), Value::I32(2)],
+        );
+        let ret = machine.invoke("sum", &[Value::I32(4)]);
+        assert_eq!(ret.unwrap(), vec![Value::I32(10)]);
+        let ret = machine.invoke("if", &[Value::I32(1)]);
+        assert_eq!(ret.unwrap(), vec![Value::I32(7)]);
+        let ret = machine.invoke("if", &[Value::I32(0)]);
+        assert_eq!(ret.unwrap(), vec![Value::I32(12)]);
+        let ret = machine.invoke("return", &[]);
+        assert_eq!(ret.unwrap(), vec![Value::I32(1), Value::I32(2)]);
+        assert_eq!(machine.stack.size(), 0);
+    }
+
+    #[test]
+    fn sign_extension() {
+        let root = parse_module(
+            r#"
+            (module
+              (func (export "i32.extend8_s") (param i32) (result i32)
+                local.get 0
+                i32.extend8_s)
+              (func (export "i32.extend16_s") (param i32) (result i32)
+                local.get 0
+                i32.extend16_s)
+              (func (export "i64.extend8_s") (param i64) (result i64)
+                local.get 0
+                i64.extend8_s)
+              (func (export "i64.extend16_s") (param i64) (result i64)
+                local.get 0
+                i64.extend16_s)
+              (func (export "i64.extend32_s") (param i64) (result i64)
+                local.get 0
+                i64.extend32_s)
+            )
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        for (name, arg, expected) in &[
+            ("i32.extend8_s", Value::I32(0x7f), Value::I32(127)),
+            ("i32.extend8_s", Value::I32(0x80), Value::I32(-128)),
+            ("i32.extend8_s", Value::I32(0x1234_5680), Value::I32(-128)),
+            ("i32.extend16_s", Value::I32(0x7fff), Value::I32(32767)),
+            ("i32.extend16_s", Value::I32(0x8000), Value::I32(-32768)),
+            ("i64.extend8_s", Value::I64(0xff), Value::I64(-1)),
+            ("i64.extend16_s", Value::I64(0x1_8000), Value::I64(-32768)),
+            ("i64.extend32_s", Value::I64(0x7fff), Value::I64(0x7fff)),
+            ("i64.extend32_s", Value::I64(0x1_ffff_ffff), Value::I64(-1)),
+        ] {
+            let ret = machine.invoke(name, std::slice::from_ref(arg)).unwrap();
+            assert_eq!(ret, vec![expected.clone()], "{}", name);
+        }
+    }
+
+    #[test]
+    fn bulk_memory() {
+        let root = parse_module(
+            r#"
+            (module
+              (type $t (func (result i32)))
+              (memory (export "mem") 1)
+              (table 4 funcref)
+              (data (i32.const 0) "ab")
+              (data $d "hello")
+              (elem $e func $ten $twenty)
+              (func $ten (result i32) i32.const 10)
+              (func $twenty (result i32) i32.const 20)
+              (func (export "memory.init") (param i32 i32 i32)
+                local.get 0
+                local.get 1
+                local.get 2
+                memory.init $d)
+              (func (export "data.drop")
+                data.drop $d)
+              (func (export "memory.copy") (param i32 i32 i32)
+                local.get 0
+                local.get 1
+                local.get 2
+                memory.copy)
+              (func (export "memory.fill") (param i32 i32 i32)
+                local.get 0
+                local.get 1
+                local.get 2
+                memory.fill)
+              (func (export "load8") (param i32) (result i32)
+                local.get 0
+                i32.load8_u)
+              (func (export "table.init") (param i32 i32 i32)
+                local.get 0
+                local.get 1
+                local.get 2
+                table.init $e)
+              (func (export "elem.drop")
+                elem.drop $e)
+              (func (export "table.copy") (param i32 i32 i32)
+                local.get 0
+                local.get 1
+                local.get 2
+                table.copy)
+              (func (export "call") (param i32) (result i32)
+                local.get 0
+                call_indirect (type $t))
+            )
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        fn args(dst: i32, src: i32, len: i32) -> [Value; 3] {
+            [Value::I32(dst), Value::I32(src), Value::I32(len)]
+        }
+
+        // Active data segment was written on instantiation
+        assert_eq!(&machine.memory().data()[..3], b"ab\0");
+
+        machine.invoke("memory.init", &args(10, 1, 3)).unwrap();
+        assert_eq!(&machine.memory().data()[10..13], b"ell");
+        // Overlapping ranges are copied as if through an intermediate buffer
+        machine.invoke("memory.copy", &args(11, 10, 3)).unwrap();
+        assert_eq!(&machine.memory().data()[10..14], b"eell");
+        machine.invoke("memory.copy", &args(10, 11, 3)).unwrap();
+        assert_eq!(&machine.memory().data()[10..14], b"elll");
+        machine.invoke("memory.fill", &args(20, 0x1ff, 2)).unwrap();
+        assert_eq!(&machine.memory().data()[19..23], b"\0\xff\xff\0");
+
+        let err = machine
+            .invoke("memory.init", &args(65535, 0, 2))
+            .unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::OutOfBounds {
+                operation: "memory.init",
+                what: "memory",
+                ..
+            }
+        ));
+        let err = machine
+            .invoke("memory.fill", &args(0, 0, 65537))
+            .unwrap_err();
+        assert!(matches!(err.reason, TrapReason::OutOfBounds { .. }));
+        let err = machine
+            .invoke("memory.copy", &args(0, 65536, 1))
+            .unwrap_err();
+        assert!(matches!(err.reason, TrapReason::OutOfBounds { .. }));
+
+        // Dropped segment behaves as an empty segment
+        machine.invoke("data.drop", &[]).unwrap();
+        machine.invoke("memory.init", &args(0, 0, 0)).unwrap();
+        let err = machine.invoke("memory.init", &args(0, 0, 1)).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::OutOfBounds {
+                what: "data segment",
+                ..
+            }
+        ));
+
+        let err = machine.invoke("call", &[Value::I32(1)]).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::UninitializedElem(1)));
+        machine.invoke("table.init", &args(1, 0, 2)).unwrap();
+        machine.invoke("table.copy", &args(0, 1, 2)).unwrap();
+        for (idx, expected) in &[(0, 10), (1, 20), (2, 20)] {
+            let ret = machine.invoke("call", &[Value::I32(*idx)]).unwrap();
+            assert_eq!(ret, vec![Value::I32(*expected)], "index {}", idx);
+        }
+
+        machine.invoke("elem.drop", &[]).unwrap();
+        let err = machine.invoke("table.init", &args(0, 0, 1)).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::OutOfBounds {
+                what: "element segment",
+                ..
+            }
+        ));
+        let err = machine.invoke("table.copy", &args(3, 0, 2)).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::OutOfBounds { .. }));
+    }
+
+    #[test]
+    fn segments_out_of_bounds_on_instantiation() {
+        let lib = parse_module(r#"(module (memory (export "mem") 1))"#);
+        let main = parse_module(
+            r#"
+            (module
+              (import "lib" "mem" (memory 1))
+              (data (i32.const 0) "a")
+              (data (i32.const 65535) "bc")
+              (data (i32.const 1) "d"))
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&lib.module, importer).unwrap();
+        let lib_id = machine.latest_instance();
+        machine.register("lib", lib_id);
+
+        let err = machine.instantiate_module(&main.module).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::OutOfBounds { .. }));
+        // Segments are written in order until the out-of-bounds one
+        assert_eq!(&machine.instance_memory(lib_id).data()[..2], b"a\0");
+        assert_eq!(machine.instance_memory(lib_id).data()[65535], 0);
+    }
+
+    #[test]
+    fn reinstantiate_after_failure() {
+        let lib = parse_module(
+            r#"
+            (module
+              (type $t (func (result i32)))
+              (table (export "tab") 2 funcref)
+              (memory (export "mem") 1)
+              (func (export "call_tab") (param i32) (result i32)
+                local.get 0
+                call_indirect (type $t)))
+            "#,
+        );
+        let elem_oob = parse_module(
+            r#"
+            (module
+              (import "lib" "tab" (table 2 funcref))
+              (global (export "g") i32 (i32.const 1))
+              (elem (i32.const 0) $f)
+              (elem (i32.const 2) $f)
+              (func $f (result i32) i32.const 7))
+            "#,
+        );
+        let data_oob = parse_module(
+            r#"
+            (module
+              (import "lib" "mem" (memory 1))
+              (global (export "g") i32 (i32.const 2))
+              (data (i32.const 65535) "ab"))
+            "#,
+        );
+        let alloc_failure = parse_module(
+            r#"
+            (module
+              (global (export "g") i32 (i32.const 3))
+              (table 1 funcref)
+              (func)
+              (memory i64 0x1_0000_0000))
+            "#,
+        );
+        let ok = parse_module(r#"(module (global (export "g") i32 (i32.const 4)))"#);
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&lib.module, importer).unwrap();
+        let lib_id = machine.latest_instance();
+        machine.register("lib", lib_id);
+
+        let err = machine.instantiate_module(&elem_oob.module).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::OutOfBounds { .. }));
+        // The failed instance remains in the store since the shared table refers its function
+        let ret = machine
+            .invoke_instance(lib_id, "call_tab", &[Value::I32(0)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(7)]);
+        assert_eq!(machine.latest_instance(), lib_id);
+
+        let err = machine.instantiate_module(&data_oob.module).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::OutOfBounds { .. }));
+        assert_eq!(machine.latest_instance(), lib_id);
+
+        let store = (
+            machine.funcs.len(),
+            machine.tables.len(),
+            machine.memories.len(),
+            machine.globals.len(),
+        );
+        let err = machine
+            .instantiate_module(&alloc_failure.module)
+            .unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::MemoryAllocationFailure { .. }
+        ));
+        assert_eq!(machine.latest_instance(), lib_id);
+        // Nothing allocated for the failed instance remains in the store
+        let after = (
+            machine.funcs.len(),
+            machine.tables.len(),
+            machine.memories.len(),
+            machine.globals.len(),
+        );
+        assert_eq!(after, store);
+
+        let ok_id = machine.instantiate_module(&ok.module).unwrap();
+        assert_eq!(machine.latest_instance(), ok_id);
+        assert_eq!(machine.get_global("g"), Some(Value::I32(4)));
+        assert_eq!(machine.instance_global(ok_id, "g"), Some(Value::I32(4)));
+    }
+
+    #[test]
+    fn reference_types() {
+        let root = parse_module(
+            r#"
+            (module
+              (type $t (func (result i32)))
+              (table $funcs 2 funcref)
+              (table $externs 1 4 externref)
+              (global $f (mut funcref) (ref.func $ten))
+              (elem (table $funcs) (i32.const 0) funcref (ref.func $ten) (ref.null func))
+              (elem declare func $twenty)
+              (func $ten (result i32) i32.const 10)
+              (func $twenty (result i32) i32.const 20)
+              (func (export "is_null") (param externref) (result i32)
+                local.get 0
+                ref.is_null)
+              (func (export "null_local") (result i32)
+                (local funcref)
+                local.get 0
+                ref.is_null)
+              (func (export "select") (param externref externref i32) (result externref)
+                local.get 0
+                local.get 1
+                local.get 2
+                select (result externref))
+              (func (export "call") (param i32) (result i32)
+                local.get 0
+                call_indirect $funcs (type $t))
+              (func (export "set_twenty") (param i32)
+                local.get 0
+                ref.func $twenty
+                table.set $funcs)
+              (func (export "set_global") (param i32)
+                local.get 0
+                global.get $f
+                table.set $funcs)
+              (func (export "get_extern") (param i32) (result externref)
+                local.get 0
+                table.get $externs)
+              (func (export "grow_extern") (param externref i32) (result i32)
+                local.get 0
+                local.get 1
+                table.grow $externs)
+              (func (export "fill_extern") (param i32 externref i32)
+                local.get 0
+                local.get 1
+                local.get 2
+                table.fill $externs)
+              (func (export "size_extern") (result i32)
+                table.size $externs)
+            )
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        let ret = machine
+            .invoke("is_null", &[Value::ExternRef(None)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(1)]);
+        let ret = machine
+            .invoke("is_null", &[Value::ExternRef(Some(0))])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(0)]);
+        // Local variables of reference types are initialized with null
+        let ret = machine.invoke("null_local", &[]).unwrap();
+        assert_eq!(ret, vec![Value::I32(1)]);
+
+        let (a, b) = (Value::ExternRef(Some(1)), Value::ExternRef(Some(2)));
+        let ret = machine
+            .invoke("select", &[a.clone(), b.clone(), Value::I32(0)])
+            .unwrap();
+        assert_eq!(ret, vec![b]);
+
+        let ret = machine.invoke("call", &[Value::I32(0)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(10)]);
+        let err = machine.invoke("call", &[Value::I32(1)]).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::UninitializedElem(1)));
+        machine.invoke("set_twenty", &[Value::I32(1)]).unwrap();
+        let ret = machine.invoke("call", &[Value::I32(1)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(20)]);
+        machine.invoke("set_global", &[Value::I32(1)]).unwrap();
+        let ret = machine.invoke("call", &[Value::I32(1)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(10)]);
+
+        let ret = machine
+            .invoke("grow_extern", &[a.clone(), Value::I32(2)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(1)]);
+        // Growing beyond the maximum size fails
+        let ret = machine
+            .invoke("grow_extern", &[a.clone(), Value::I32(2)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(-1)]);
+        let ret = machine.invoke("size_extern", &[]).unwrap();
+        assert_eq!(ret, vec![Value::I32(3)]);
+        let ret = machine.invoke("get_extern", &[Value::I32(0)]).unwrap();
+        assert_eq!(ret, vec![Value::ExternRef(None)]);
+        let ret = machine.invoke("get_extern", &[Value::I32(2)]).unwrap();
+        assert_eq!(ret, vec![a]);
+
+        let args = [Value::I32(0), Value::ExternRef(Some(7)), Value::I32(2)];
+        machine.invoke("fill_extern", &args).unwrap();
+        let ret = machine.invoke("get_extern", &[Value::I32(1)]).unwrap();
+        assert_eq!(ret, vec![Value::ExternRef(Some(7))]);
+        let err = machine.invoke("get_extern", &[Value::I32(3)]).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::OutOfBounds {
+                operation: "table.get",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn multiple_tables() {
+        let lib = parse_module(
+            r#"
+            (module
+              (table (export "tab") 2 funcref)
+              (elem (i32.const 1) func $lib)
+              (func $lib (result i32) i32.const 1))
+            "#,
+        );
+        let main = parse_module(
+            r#"
+            (module
+              (type $t (func (result i32)))
+              (import "lib" "tab" (table $imported 2 funcref))
+              (table $own 2 funcref)
+              (elem (table $own) (i32.const 0) func $main)
+              (func $main (result i32) i32.const 2)
+              (func (export "call") (param i32 i32) (result i32)
+                local.get 0
+                i32.eqz
+                if (result i32)
+                  local.get 1
+                  call_indirect $imported (type $t)
+                else
+                  local.get 1
+                  call_indirect $own (type $t)
+                end)
+              (func (export "copy") (param i32 i32 i32)
+                local.get 0
+                local.get 1
+                local.get 2
+                table.copy $imported $own)
+            )
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&lib.module, importer).unwrap();
+        let lib_id = machine.latest_instance();
+        machine.register("lib", lib_id);
+        machine.instantiate_module(&main.module).unwrap();
+
+        fn args(table: i32, idx: i32) -> [Value; 2] {
+            [Value::I32(table), Value::I32(idx)]
+        }
+
+        let ret = machine.invoke("call", &args(0, 1)).unwrap();
+        assert_eq!(ret, vec![Value::I32(1)]);
+        let ret = machine.invoke("call", &args(1, 0)).unwrap();
+        assert_eq!(ret, vec![Value::I32(2)]);
+        let err = machine.invoke("call", &args(1, 1)).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::UninitializedElem(1)));
+
+        // Copy elements from own table to imported table shared with 'lib'
+        let copy = [Value::I32(0), Value::I32(0), Value::I32(1)];
+        machine.invoke("copy", &copy).unwrap();
+        let ret = machine.invoke("call", &args(0, 0)).unwrap();
+        assert_eq!(ret, vec![Value::I32(2)]);
+    }
+
+    #[test]
+    fn multiple_memories() {
+        let lib = parse_module(r#"(module (memory (export "mem") 1))"#);
+        let main = parse_module(
+            r#"
+            (module
+              (import "lib" "mem" (memory $shared 1))
+              (memory $own 1 2)
+              (data (memory $own) (i32.const 0) "abc")
+              (func (export "load") (param i32) (result i32)
+                local.get 0
+                i32.load8_u $own)
+              (func (export "store") (param i32 i32)
+                local.get 0
+                local.get 1
+                i32.store8 $shared offset=1)
+              (func (export "copy") (param i32 i32 i32)
+                local.get 0
+                local.get 1
+                local.get 2
+                memory.copy $shared $own)
+              (func (export "grow") (param i32) (result i32)
+                local.get 0
+                memory.grow $own)
+              (func (export "size") (result i32)
+                memory.size $own)
+            )
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&lib.module, importer).unwrap();
+        let lib_id = machine.latest_instance();
+        machine.register("lib", lib_id);
+        let main_id = machine.instantiate_module(&main.module).unwrap();
+
+        let ret = machine.invoke("load", &[Value::I32(1)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(b'b' as i32)]);
+        machine
+            .invoke("store", &[Value::I32(0), Value::I32(b'x' as i32)])
+            .unwrap();
+        assert_eq!(machine.instance_memory(lib_id).data()[1], b'x');
+
+        let args = [Value::I32(2), Value::I32(0), Value::I32(3)];
+        machine.invoke("copy", &args).unwrap();
+        assert_eq!(&machine.instance_memory(lib_id).data()[..5], b"\0xabc");
+
+        let ret = machine.invoke("grow", &[Value::I32(1)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(1)]);
+        let ret = machine.invoke("grow", &[Value::I32(1)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(-1)]);
+        let ret = machine.invoke("size", &[]).unwrap();
+        assert_eq!(ret, vec![Value::I32(2)]);
+        assert_eq!(machine.instance_memory(lib_id).size(), 1);
+
+        let own = machine.instance_memory_at(main_id, 1).unwrap();
+        assert_eq!(&own.data()[..3], b"abc");
+        assert!(machine.instance_memory_at(main_id, 2).is_none());
+    }
+
+    #[test]
+    fn vector_instructions() {
+        let root = parse_module(
+            r#"
+            (module
+              (memory 1)
+              (data (i32.const 0) "\01\02\03\04\05\06\07\08\f9\fa\fb\fc\fd\fe\ff\80")
+              (global $g (mut v128) (v128.const i32x4 1 2 3 4))
+              (func (export "load") (param i32) (result v128)
+                local.get 0
+                v128.load)
+              (func (export "load8x8_s") (param i32) (result v128)
+                local.get 0
+                v128.load8x8_s offset=8)
+              (func (export "load16_lane") (param i32 v128) (result v128)
+                local.get 0
+                local.get 1
+                v128.load16_lane 7)
+              (func (export "store32_lane") (param i32 v128) (result i32)
+                local.get 0
+                local.get 1
+                v128.store32_lane 2
+                local.get 0
+                i32.load)
+              (func (export "i8x16.add") (param v128 v128) (result v128)
+                local.get 0
+                local.get 1
+                i8x16.add)
+              (func (export "i16x8.add_sat_s") (param v128 v128) (result v128)
+                local.get 0
+                local.get 1
+                i16x8.add_sat_s)
+              (func (export "i32x4.lt_u") (param v128 v128) (result v128)
+                local.get 0
+                local.get 1
+                i32x4.lt_u)
+              (func (export "i8x16.shuffle") (param v128 v128) (result v128)
+                local.get 0
+                local.get 1
+                i8x16.shuffle 31 0 30 1 29 2 28 3 27 4 26 5 25 6 24 7)
+              (func (export "i8x16.bitmask") (param v128) (result i32)
+                local.get 0
+                i8x16.bitmask)
+              (func (export "i64x2.shl") (param v128 i32) (result v128)
+                local.get 0
+                local.get 1
+                i64x2.shl)
+              (func (export "f32x4.min") (param v128 v128) (result v128)
+                local.get 0
+                local.get 1
+                f32x4.min)
+              (func (export "splat_extract") (param f64) (result f64)
+                local.get 0
+                f64x2.splat
+                f64x2.extract_lane 1)
+              (func (export "global") (param i32) (result v128)
+                global.get $g
+                local.get 0
+                i32x4.replace_lane 3
+                global.set $g
+                global.get $g)
+            )
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        fn i32x4(lanes: [i32; 4]) -> Value {
+            let mut v = 0;
+            for (i, l) in lanes.iter().enumerate() {
+                v |= (*l as u32 as i128) << (i * 32);
+            }
+            Value::V128(v)
+        }
+        fn f32x4(lanes: [f32; 4]) -> Value {
+            i32x4([
+                lanes[0].to_bits() as i32,
+                lanes[1].to_bits() as i32,
+                lanes[2].to_bits() as i32,
+                lanes[3].to_bits() as i32,
+            ])
+        }
+
+        for (name, args, expected) in &[
+            (
+                "load",
+                vec![Value::I32(0)],
+                Value::V128(0x80fffefd_fcfbfaf9_08070605_04030201_u128 as i128),
+            ),
+            (
+                "load8x8_s",
+                vec![Value::I32(0)],
+                Value::V128(0xff80_ffff_fffe_fffd_fffc_fffb_fffa_fff9_u128 as i128),
+            ),
+            (
+                "load16_lane",
+                vec![Value::I32(2), Value::V128(0)],
+                Value::V128(0x0403 << 112),
+            ),
+            (
+                "store32_lane",
+                vec![Value::I32(4), i32x4([1, 2, 3, 4])],
+                Value::I32(3),
+            ),
+            (
+                "i8x16.add",
+                vec![Value::V128(-1), Value::V128(0x0102)],
+                Value::V128(-1 << 16 | 0x0001),
+            ),
+            (
+                "i16x8.add_sat_s",
+                vec![Value::V128(0x7fff_8000_0001), Value::V128(0x0001_ffff_0001)],
+                Value::V128(0x7fff_8000_0002),
+            ),
+            (
+                "i32x4.lt_u",
+                vec![i32x4([0, -1, 1, 5]), i32x4([1, 0, 1, -1])],
+                i32x4([-1, 0, 0, -1]),
+            ),
+            (
+                "i8x16.shuffle",
+                vec![
+                    Value::V128(0x0f0e0d0c_0b0a0908_07060504_03020100),
+                    Value::V128(0x1f1e1d1c_1b1a1918_17161514_13121110),
+                ],
+                Value::V128(0x07180619_051a041b_031c021d_011e001f),
+            ),
+            (
+                "i8x16.bitmask",
+                vec![Value::V128(0x80ff_0001)],
+                Value::I32(0b1100),
+            ),
+            (
+                "i64x2.shl",
+                vec![Value::V128(1 << 64 | 3), Value::I32(65)],
+                Value::V128(2 << 64 | 6),
+            ),
+            (
+                "f32x4.min",
+                vec![f32x4([1.0, 0.0, -0.0, 2.0]), f32x4([2.0, -0.0, 0.0, -3.5])],
+                f32x4([1.0, -0.0, -0.0, -3.5]),
+            ),
+            ("splat_extract", vec![Value::F64(1.5)], Value::F64(1.5)),
+            ("global", vec![Value::I32(-1)], i32x4([1, 2, 3, -1])),
+        ] {
+            let ret = machine.invoke(name, args).unwrap();
+            assert_eq!(ret, vec![expected.clone()], "{}", name);
+        }
+
+        let nan = machine
+            .invoke("f32x4.min", &[f32x4([f32::NAN; 4]), f32x4([0.0; 4])])
+            .unwrap();
+        match &nan[0] {
+            Value::V128(v) => assert!(f32::from_bits(*v as u32).is_nan(), "{:x}", v),
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+
+    #[test]
+    fn tail_calls() {
+        let root = parse_module(
+            r#"
+            (module
+              (type $t (func (param i64 i64) (result i64)))
+              (import "env" "putchar" (func $putchar (param i32) (result i32)))
+              (table 2 funcref)
+              (elem (i32.const 0) func $sum_indirect $putchar)
+              (func $sum (export "sum") (param i64 i64) (result i64)
+                local.get 0
+                i64.eqz
+                if (result i64)
+                  local.get 1
+                else
+                  local.get 0
+                  i64.const 1
+                  i64.sub
+                  local.get 1
+                  local.get 0
+                  i64.add
+                  return_call $sum
+                end)
+              (func $sum_indirect (export "sum_indirect") (param i64 i64) (result i64)
+                local.get 0
+                i64.eqz
+                if (result i64)
+                  local.get 1
+                else
+                  local.get 0
+                  i64.const 1
+                  i64.sub
+                  local.get 1
+                  local.get 0
+                  i64.add
+                  i32.const 0
+                  return_call_indirect (type $t)
+                end)
+              (func (export "mismatch") (result i64)
+                i64.const 0
+                i64.const 0
+                i32.const 1
+                return_call_indirect (type $t))
+              (func (export "putchar") (param i32) (result i32)
+                local.get 0
+                return_call $putchar)
+            )
+            "#,
+        );
+
+        let mut stdout = vec![];
+        let importer = DefaultImporter::with_stdio(Discard, &mut stdout);
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+        // Tail calls reuse the frame so neither the call stack nor the value stack grows
+        machine.set_max_call_depth(10);
+        machine.set_max_stack_size(16 * 10);
+
+        let args = [Value::I64(100000), Value::I64(0)];
+        for name in &["sum", "sum_indirect"] {
+            let ret = machine.invoke(name, &args).unwrap();
+            assert_eq!(ret, vec![Value::I64(5000050000)], "{}", name);
+        }
+
+        let err = machine.invoke("mismatch", &[]).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::FuncSignatureMismatch { .. }
+        ));
+
+        let ret = machine
+            .invoke("putchar", &[Value::I32(b'a' as i32)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(b'a' as i32)]);
+        drop(machine);
+        assert_eq!(stdout, b"a");
+    }
+
+    #[test]
+    fn exception_handling() {
+        let lib = parse_module(
+            r#"
+            (module
+              (tag $e (export "e") (param i32))
+              (func (export "throw") (param i32)
+                local.get 0
+                throw $e))
+            "#,
+        );
+        let main = parse_module(
+            r#"
+            (module
+              (import "lib" "e" (tag $e (param i32)))
+              (import "lib" "throw" (func $throw (param i32)))
+              (tag $empty)
+              (tag $pair (export "pair") (param i64 f32))
+              (func $may_throw (param i32) (result i32)
+                local.get 0
+                i32.eqz
+                if
+                  throw $empty
+                end
+                local.get 0
+                i32.const 0
+                i32.lt_s
+                if
+                  local.get 0
+                  call $throw
+                end
+                local.get 0)
+              (func (export "catch") (param i32) (result i32)
+                try (result i32)
+                  local.get 0
+                  call $may_throw
+                  i32.const 1
+                  i32.add
+                catch $e
+                  i32.const 100
+                  i32.add
+                catch_all
+                  i32.const -1
+                end)
+              (func (export "rethrow") (param i32) (result i32)
+                try (result i32)
+                  try (result i32)
+                    local.get 0
+                    call $may_throw
+                  catch_all
+                    rethrow 0
+                  end
+                catch $e
+                end)
+              (func (export "delegate") (param i32) (result i32)
+                try (result i32)
+                  block (result i32)
+                    try (result i32)
+                      local.get 0
+                      call $may_throw
+                    delegate 1
+                  end
+                catch $e
+                  i32.const 10
+                  i32.mul
+                end)
+              (func (export "loop") (param i32) (result i32)
+                (local i32)
+                loop $l
+                  try
+                    local.get 0
+                    call $throw
+                  catch $e
+                    local.get 1
+                    i32.add
+                    local.set 1
+                  end
+                  local.get 0
+                  i32.const 1
+                  i32.sub
+                  local.tee 0
+                  br_if $l
+                end
+                local.get 1)
+              (func (export "uncaught") (result i32)
+                try (result i32)
+                  i64.const 3
+                  f32.const 0.5
+                  throw $pair
+                catch $e
+                end))
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&lib.module, importer).unwrap();
+        machine.register("lib", machine.latest_instance());
+        machine.instantiate_module(&main.module).unwrap();
+
+        for (name, arg, expected) in &[
+            ("catch", 1, 2),
+            // Exception thrown in another instance is caught with its payload
+            ("catch", -3, 97),
+            ("catch", 0, -1),
+            ("rethrow", 2, 2),
+            ("rethrow", -4, -4),
+            ("delegate", 5, 5),
+            ("delegate", -5, -50),
+            ("loop", 4, 10),
+        ] {
+            let ret = machine.invoke(name, &[Value::I32(*arg)]).unwrap();
+            assert_eq!(ret, vec![Value::I32(*expected)], "{}({})", name, arg);
+        }
+
+        let err = machine.invoke("rethrow", &[Value::I32(0)]).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::UncaughtException { ref payload, .. } if payload.is_empty()
+        ));
+        let err = machine.invoke("uncaught", &[]).unwrap_err();
+        match err.reason {
+            TrapReason::UncaughtException { tag, payload } => {
+                assert_eq!(Some(tag), machine.get_tag("pair"));
+                assert_eq!(&*payload, &[Value::I64(3), Value::F32(0.5)]);
+            }
+            reason => panic!("unexpected trap: {:?}", reason),
+        }
+
+        // Machine is still available after the uncaught exception
+        let ret = machine.invoke("catch", &[Value::I32(-1)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(99)]);
+    }
+
+    #[test]
+    fn caught_exceptions_are_discarded() {
+        let root = parse_module(
+            r#"
+            (module
+              (tag $e (param i32))
+              ;; Catch exceptions at different stack heights
+              (func $rec (param i32)
+                local.get 0
+                i32.eqz
+                br_if 0
+                try
+                  local.get 0
+                  throw $e
+                catch $e
+                  drop
+                end
+                local.get 0
+                i32.const 1
+                i32.sub
+                call $rec)
+              (func $return_in_catch
+                try
+                  i32.const 0
+                  throw $e
+                catch $e
+                  return
+                end)
+              (func $tail_call_in_catch (param i32)
+                try
+                  local.get 0
+                  throw $e
+                catch $e
+                  return_call $rec
+                end)
+              (func (export "run") (param i32)
+                loop $l
+                  i32.const 10
+                  call $rec
+                  call $return_in_catch
+                  i32.const 3
+                  call $tail_call_in_catch
+                  local.get 0
+                  i32.const 1
+                  i32.sub
+                  local.tee 0
+                  br_if $l
+                end))
+            "#,
+        );
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        // Check exceptions caught while looping do not remain after their 'catch' clauses
+        machine.set_fuel(7);
+        let mut state = machine.invoke_resumable("run", &[Value::I32(100)]).unwrap();
+        let mut steps = 0;
+        while let Invocation::Suspended(Suspension::OutOfFuel) = state {
+            assert!(machine.caught.len() <= 1, "{}", machine.caught.len());
+            let len = machine.stack.len();
+            assert!(machine.caught.iter().all(|(h, _)| *h < len));
+            machine.set_fuel(7);
+            state = machine.resume().unwrap();
+            steps += 1;
+        }
+        assert_eq!(state, Invocation::Finished(vec![]));
+        assert!(steps > 100, "{}", steps);
+    }
+
+    #[test]
+    fn extended_constant_expressions() {
+        let lib = parse_module(r#"(module (global (export "base") i32 (i32.const 16)))"#);
+        let main = parse_module(
+            r#"
+            (module
+              (import "lib" "base" (global $base i32))
+              (memory 1)
+              (table 8 funcref)
+              (global $g (export "g") i64
+                (i64.mul (i64.const 3) (i64.sub (i64.const 10) (i64.const 3))))
+              (global $end (export "end") i32
+                global.get $base
+                i32.const 4
+                i32.add
+                i32.const 2
+                i32.mul)
+              (global $wrap (export "wrap") i32
+                (i32.add (i32.const 0x7fffffff) (i32.const 1)))
+              (data (offset (i32.sub (global.get $base) (i32.const 1))) "abc")
+              (elem (offset (i32.mul (i32.const 2) (i32.const 3))) $f)
+              (func $f (result i32) i32.const 42)
+              (func (export "call") (param i32) (result i32)
+                local.get 0
+                call_indirect (result i32)))
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&lib.module, importer).unwrap();
+        machine.register("lib", machine.latest_instance());
+        machine.instantiate_module(&main.module).unwrap();
+
+        assert_eq!(machine.get_global("g"), Some(Value::I64(21)));
+        assert_eq!(machine.get_global("end"), Some(Value::I32(40)));
+        assert_eq!(machine.get_global("wrap"), Some(Value::I32(i32::MIN)));
+        assert_eq!(&machine.memory().data()[14..19], b"\0abc\0");
+        let ret = machine.invoke("call", &[Value::I32(6)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(42)]);
+    }
+
+    #[test]
+    fn memory64() {
+        let main = parse_module(
+            r#"
+            (module
+              (memory $m (export "mem") i64 1 3)
+              (memory $small 1)
+              (data (memory $m) (i64.const 0x10) "abcd")
+              (func (export "load") (param i64) (result i32)
+                local.get 0
+                i32.load8_u offset=1)
+              (func (export "load_far") (result i64)
+                i64.const 0
+                i64.load offset=0x1_0000_0000)
+              (func (export "store") (param i64 i64)
+                local.get 0
+                local.get 1
+                i64.store)
+              (func (export "size") (result i64)
+                memory.size)
+              (func (export "grow") (param i64) (result i64)
+                local.get 0
+                memory.grow)
+              (func (export "fill") (param i64 i32 i64)
+                local.get 0
+                local.get 1
+                local.get 2
+                memory.fill)
+              (func (export "copy") (param i32 i64 i32)
+                local.get 0
+                local.get 1
+                local.get 2
+                memory.copy $small $m)
+              (func (export "small") (param i32) (result i32)
+                local.get 0
+                i32.load8_u $small)
+              (func (export "grow_small") (result i32)
+                i32.const 1
+                memory.grow $small))
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&main.module, importer).unwrap();
+        let id = machine.latest_instance();
+        assert!(machine.instance_memory(id).is_memory64());
+
+        let ret = machine.invoke("load", &[Value::I64(0x10)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(b'b' as i32)]);
+        machine
+            .invoke("store", &[Value::I64(0x20), Value::I64(0x0102)])
+            .unwrap();
+        assert_eq!(&machine.memory().data()[0x20..0x22], &[2, 1]);
+
+        // Addresses larger than u32 and negative addresses are out of bounds
+        for args in &[[Value::I64(0x1_0000_0000)], [Value::I64(-1)]] {
+            let err = machine.invoke("load", args).unwrap_err();
+            assert!(matches!(
+                err.reason,
+                TrapReason::LoadMemoryOutOfRange { .. }
+            ));
+        }
+        let err = machine.invoke("load_far", &[]).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::LoadMemoryOutOfRange { .. }
+        ));
+        let args = [Value::I64(-1), Value::I64(0)];
+        let err = machine.invoke("store", &args).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::LoadMemoryOutOfRange { .. }
+        ));
+
+        // The number of pages is i64
+        let ret = machine.invoke("grow", &[Value::I64(1)]).unwrap();
+        assert_eq!(ret, vec![Value::I64(1)]);
+        let ret = machine
+            .invoke("grow", &[Value::I64(0x1_0000_0000)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I64(-1)]);
+        let ret = machine.invoke("size", &[]).unwrap();
+        assert_eq!(ret, vec![Value::I64(2)]);
+        // Growing 32-bit memory still results in i32
+        let ret = machine.invoke("grow_small", &[]).unwrap();
+        assert_eq!(ret, vec![Value::I32(1)]);
+
+        let args = [Value::I64(0x10000), Value::I32(7), Value::I64(3)];
+        machine.invoke("fill", &args).unwrap();
+        assert_eq!(&machine.memory().data()[0x10000..0x10004], &[7, 7, 7, 0]);
+        let args = [Value::I64(-1), Value::I32(7), Value::I64(2)];
+        let err = machine.invoke("fill", &args).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::OutOfBounds { .. }));
+
+        // Length of memory.copy between 32-bit and 64-bit memories is i32
+        let args = [Value::I32(1), Value::I64(0x10), Value::I32(4)];
+        machine.invoke("copy", &args).unwrap();
+        let ret = machine.invoke("small", &[Value::I32(4)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(b'd' as i32)]);
+
+        // Index type of imported memory must match
+        machine.register("lib", id);
+        let memory32 = parse_module(r#"(module (import "lib" "mem" (memory 1)))"#);
+        let err = machine.instantiate_module(&memory32.module).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::IncompatibleImport { .. }));
+        let memory64 = parse_module(r#"(module (import "lib" "mem" (memory i64 1)))"#);
+        machine.instantiate_module(&memory64.module).unwrap();
+    }
+
+    #[test]
+    fn atomic_instructions() {
+        let main = parse_module(
+            r#"
+            (module
+              (memory (export "mem") 1 1 shared)
+              (memory $unshared 1)
+              (func (export "add8") (param i32) (result i32)
+                i32.const 0
+                local.get 0
+                i32.atomic.rmw8.add_u)
+              (func (export "sub") (param i64) (result i64)
+                i32.const 8
+                local.get 0
+                i64.atomic.rmw.sub)
+              (func (export "cmpxchg16") (param i32 i32) (result i32)
+                i32.const 16
+                local.get 0
+                local.get 1
+                i32.atomic.rmw16.cmpxchg_u)
+              (func (export "xchg") (param i32 i32) (result i32)
+                local.get 0
+                local.get 1
+                i32.atomic.rmw.xchg)
+              (func (export "load16") (param i32) (result i32)
+                local.get 0
+                i32.atomic.load16_u)
+              (func (export "store") (param i32 i64)
+                local.get 0
+                local.get 1
+                i64.atomic.store32
+                atomic.fence)
+              (func (export "wait") (param i32 i64) (result i32)
+                i32.const 24
+                local.get 0
+                local.get 1
+                memory.atomic.wait32)
+              (func (export "wait_unshared") (result i32)
+                i32.const 0
+                i64.const 0
+                i64.const 0
+                memory.atomic.wait64 $unshared)
+              (func (export "notify") (param i32) (result i32)
+                local.get 0
+                i32.const 1
+                memory.atomic.notify))
+            "#,
+        );
+
+        let importer = DefaultImporter::with_stdio(Discard, io::sink());
+        let mut machine = Machine::instantiate(&main.module, importer).unwrap();
+        let id = machine.latest_instance();
+        assert!(machine.instance_memory(id).is_shared());
+
+        // Read-modify-write instructions return the old value and wrap around in their width
+        for (arg, expected) in &[(0xff, 0), (2, 0xff), (1, 1)] {
+            let ret = machine.invoke("add8", &[Value::I32(*arg)]).unwrap();
+            assert_eq!(ret, vec![Value::I32(*expected)]);
+        }
+        let ret = machine.invoke("sub", &[Value::I64(1)]).unwrap();
+        assert_eq!(ret, vec![Value::I64(0)]);
+        let ret = machine.invoke("sub", &[Value::I64(0)]).unwrap();
+        assert_eq!(ret, vec![Value::I64(-1)]);
+
+        // Value is replaced only when the loaded value equals to the expected one
+        let ret = machine
+            .invoke("cmpxchg16", &[Value::I32(1), Value::I32(2)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(0)]);
+        let ret = machine
+            .invoke("cmpxchg16", &[Value::I32(0x10000), Value::I32(3)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(0)]);
+        let ret = machine.invoke("load16", &[Value::I32(16)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(3)]);
+
+        machine
+            .invoke("store", &[Value::I32(24), Value::I64(0x1_0000_0007)])
+            .unwrap();
+        let ret = machine
+            .invoke("xchg", &[Value::I32(24), Value::I32(9)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(7)]);
+
+        for (name, args) in &[
+            ("load16", vec![Value::I32(1)]),
+            ("store", vec![Value::I32(2), Value::I64(0)]),
+            ("xchg", vec![Value::I32(6), Value::I32(0)]),
+            ("notify", vec![Value::I32(3)]),
+        ] {
+            let err = machine.invoke(name, args).unwrap_err();
+            assert!(
+                matches!(err.reason, TrapReason::UnalignedAtomic { .. }),
+                "{}: {:?}",
+                name,
+                err.reason,
+            );
+        }
+        let err = machine.invoke("load16", &[Value::I32(65536)]).unwrap_err();
+        assert!(matches!(
+            err.reason,
+            TrapReason::LoadMemoryOutOfRange { .. }
+        ));
+
+        // No other thread can modify the memory or notify the waiter
+        let ret = machine
+            .invoke("wait", &[Value::I32(0), Value::I64(-1)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(1)]);
+        let ret = machine
+            .invoke("wait", &[Value::I32(9), Value::I64(0)])
+            .unwrap();
+        assert_eq!(ret, vec![Value::I32(2)]);
+        // Waiting never blocks the host thread even if the timeout is huge or infinite
+        let now = std::time::Instant::now();
+        for timeout in &[-1, i64::MAX] {
+            let ret = machine
+                .invoke("wait", &[Value::I32(9), Value::I64(*timeout)])
+                .unwrap();
+            assert_eq!(ret, vec![Value::I32(2)]);
+        }
+        assert!(now.elapsed() < std::time::Duration::from_secs(10));
+        let err = machine.invoke("wait_unshared", &[]).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::WaitOnUnsharedMemory));
+        let ret = machine.invoke("notify", &[Value::I32(0)]).unwrap();
+        assert_eq!(ret, vec![Value::I32(0)]);
+
+        // Sharedness of imported memory must match
+        machine.register("lib", id);
+        let unshared = parse_module(r#"(module (import "lib" "mem" (memory 1 1)))"#);
+        let err = machine.instantiate_module(&unshared.module).unwrap_err();
+        assert!(
+            matches!(err.reason, TrapReason::IncompatibleImport { .. }),
+            "{:?}",
+            err.reason
+        );
+        let shared = parse_module(r#"(module (import "lib" "mem" (memory 1 1 shared)))"#);
+        machine.instantiate_module(&shared.module).unwrap();
+    }
 }
@@ -1,97 +1,155 @@
-use crate::globals::Globals;
-use crate::trap::{Result, Trap, TrapReason};
+use crate::trap::{check_bounds, Result, Trap, TrapReason};
 use crate::value::LittleEndian;
 use std::any;
+use std::convert::TryFrom;
 use std::mem::size_of;
+use std::ops::{Deref, DerefMut};
 use wain_ast as ast;
 
 const PAGE_SIZE: usize = 65536; // 64Ki
 const MAX_MEMORY_BYTES: usize = u32::MAX as usize; // Address space of Wasm is 32bits
 
+// https://github.com/WebAssembly/memory64/blob/main/proposals/memory64/Overview.md
+// 64-bit memory can have 2^48 pages in theory. Its size is limited to 2^32 - 1 pages so that the
+// number of pages fits in u32
+const MAX_MEMORY64_PAGES: u64 = u32::MAX as u64;
+
 // Memory instance
 //
 // Note: It is more efficient to implement memory buffer by memory mapped buffer. However there is
 // no way to use mmap without unsafe.
 pub struct Memory {
-    max: Option<u32>,
+    max: Option<u64>,
     data: Vec<u8>,
+    shared: bool,
+    memory64: bool,
 }
 
 impl Memory {
-    // https://webassembly.github.io/spec/core/exec/modules.html#alloc-mem
-    pub fn allocate(memories: &[ast::Memory]) -> Result<Self> {
-        // Note: Only one memory exists thanks to validation
-        assert!(memories.len() <= 1);
-        if let Some(memory) = memories.get(0) {
-            if let Some(i) = &memory.import {
-                Err(Trap::unknown_import(i, "memory", memory.start))
-            } else {
-                let (min, max) = match &memory.ty.limit {
-                    ast::Limits::Range(min, max) => (*min, Some(*max)),
-                    ast::Limits::From(min) => (*min, None),
-                };
-                let data = if min == 0 {
-                    vec![]
-                } else {
-                    let len = (min as usize) * PAGE_SIZE;
-                    vec![0; len]
-                };
-                Ok(Self { max, data })
-            }
+    // Create memory instance with `min` pages. This is used for making memory imported by modules
+    pub fn new(min: u32, max: Option<u32>) -> Self {
+        let data = if min == 0 {
+            vec![]
         } else {
-            // When no table is set use dummy empty table
-            Ok(Self {
-                max: Some(0),
-                data: vec![],
-            })
+            let len = (min as usize) * PAGE_SIZE;
+            vec![0; len]
+        };
+        Self {
+            max: max.map(u64::from),
+            data,
+            shared: false,
+            memory64: false,
         }
     }
 
-    // 10. and 14. https://webassembly.github.io/spec/core/exec/modules.html#instantiation
-    pub fn new_data(&mut self, segment: &ast::DataSegment, globals: &Globals) -> Result<()> {
-        // By validation of constant expression, at least one instruction in the sequence is guaranteed
-        // and type must be i32
-        let offset = match &segment.offset[segment.offset.len() - 1].kind {
-            ast::InsnKind::GlobalGet(idx) => globals.get(*idx),
-            ast::InsnKind::I32Const(i) => *i,
-            _ => unreachable!("unexpected instruction for element offset"),
+    // Create 64-bit memory with `min` pages. This is used for making memory imported by modules as
+    // i64 memory. Panics when `min` pages cannot be allocated
+    pub fn new64(min: u64, max: Option<u64>) -> Self {
+        let mut memory = Self {
+            max,
+            data: vec![],
+            shared: false,
+            memory64: true,
         };
-        let offset = offset as usize;
-        let data = &segment.data;
-        let end_addr = offset + data.len();
+        assert!(
+            memory.grow(min) >= 0,
+            "cannot allocate {} pages for 64-bit memory",
+            min,
+        );
+        memory
+    }
 
-        if let Some(max) = self.max {
-            let max = max as usize;
-            if end_addr > max * PAGE_SIZE {
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#shared-linear-memory
+    // Shared memory must have the maximum size. This is used for making shared memory imported by
+    // modules
+    pub fn new_shared(min: u32, max: u32) -> Self {
+        let mut memory = Self::new(min, Some(max));
+        memory.shared = true;
+        memory
+    }
+
+    // https://webassembly.github.io/spec/core/exec/modules.html#alloc-mem
+    pub fn allocate(memory: &ast::Memory) -> Result<Self> {
+        if let Some(i) = &memory.import {
+            Err(Trap::unknown_import(i, "memory", memory.start))
+        } else {
+            let (min, max) = match &memory.ty.limit {
+                ast::Limits::Range(min, max) => (*min, Some(*max)),
+                ast::Limits::From(min) => (*min, None),
+            };
+            let mut allocated = Self {
+                max,
+                data: vec![],
+                shared: memory.ty.shared,
+                memory64: memory.ty.memory64,
+            };
+            // Minimum size of 64-bit memory may be too large to allocate
+            if allocated.grow(min) < 0 {
                 return Err(Trap::new(
-                    TrapReason::OutOfLimit {
-                        max,
-                        idx: end_addr,
-                        kind: "data element",
-                    },
-                    segment.start,
+                    TrapReason::MemoryAllocationFailure { pages: min },
+                    memory.start,
                 ));
             }
+            Ok(allocated)
         }
+    }
 
-        if self.data.len() <= end_addr {
-            return Err(Trap::new(
-                TrapReason::DataSegmentOutOfBuffer {
-                    segment_end: end_addr,
-                    buffer_size: self.data.len(),
-                },
-                segment.start,
-            ));
+    // When no memory is defined in module, dummy empty memory is used as the default memory
+    pub(crate) fn dummy() -> Self {
+        Self {
+            max: Some(0),
+            data: vec![],
+            shared: false,
+            memory64: false,
         }
+    }
 
-        // TODO: copy_from_slice is slower here. I need to investigate the reason
-        #[allow(clippy::manual_memcpy)]
-        {
-            for i in 0..data.len() {
-                self.data[offset + i] = data[i];
-            }
-        }
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-init
+    // Active data segments are also written by this method on instantiation
+    pub(crate) fn init(
+        &mut self,
+        dst: usize,
+        src: usize,
+        len: usize,
+        segment: &[u8],
+        at: usize,
+    ) -> Result<()> {
+        check_bounds(src, len, segment.len(), "memory.init", "data segment", at)?;
+        check_bounds(dst, len, self.data.len(), "memory.init", "memory", at)?;
+        self.data[dst..dst + len].copy_from_slice(&segment[src..src + len]);
+        Ok(())
+    }
 
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-copy
+    pub(crate) fn copy(&mut self, dst: usize, src: usize, len: usize, at: usize) -> Result<()> {
+        check_bounds(src, len, self.data.len(), "memory.copy", "memory", at)?;
+        check_bounds(dst, len, self.data.len(), "memory.copy", "memory", at)?;
+        // Source and destination ranges may overlap
+        self.data.copy_within(src..src + len, dst);
+        Ok(())
+    }
+
+    // memory.copy between two different memories
+    pub(crate) fn copy_from(
+        &mut self,
+        other: &Memory,
+        dst: usize,
+        src: usize,
+        len: usize,
+        at: usize,
+    ) -> Result<()> {
+        check_bounds(src, len, other.data.len(), "memory.copy", "memory", at)?;
+        check_bounds(dst, len, self.data.len(), "memory.copy", "memory", at)?;
+        self.data[dst..dst + len].copy_from_slice(&other.data[src..src + len]);
+        Ok(())
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-fill
+    pub(crate) fn fill(&mut self, dst: usize, val: u8, len: usize, at: usize) -> Result<()> {
+        check_bounds(dst, len, self.data.len(), "memory.fill", "memory", at)?;
+        for b in self.data[dst..dst + len].iter_mut() {
+            *b = val;
+        }
         Ok(())
     }
 
@@ -99,25 +157,60 @@ impl Memory {
         (self.data.len() / PAGE_SIZE) as u32
     }
 
-    pub fn grow(&mut self, num_pages: u32) -> i32 {
+    pub fn max(&self) -> Option<u64> {
+        self.max
+    }
+
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// Memory is addressed with i64 instead of i32.
+    pub fn is_memory64(&self) -> bool {
+        self.memory64
+    }
+
+    /// Grow the memory by `num_pages` pages. Returns the previous number of pages or -1 on failure.
+    pub fn grow(&mut self, num_pages: u64) -> i64 {
         // https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-grow
         let prev = self.size();
-        let next = prev + num_pages;
+        let next = match u64::from(prev).checked_add(num_pages) {
+            Some(next) => next,
+            None => return -1,
+        };
         if let Some(max) = self.max {
             if next > max {
                 return -1;
             }
         }
-        let next_len = (next as usize) * PAGE_SIZE;
-        if next_len > MAX_MEMORY_BYTES {
+        if self.memory64 {
+            if next > MAX_MEMORY64_PAGES {
+                return -1;
+            }
+        } else if next > (MAX_MEMORY_BYTES / PAGE_SIZE) as u64 {
             // Note: WebAssembly spec does not limit max size of memory when no limit is specified
             // to memory section. However, an address value is u32. When memory size is larger than
             // UINT32_MAX, there is no way to refer it (except for using static offset value).
             // And memory_grow.wast expects allocating more than 2^32 - 1 to fail.
             return -1;
         }
+        let next_len = match usize::try_from(next)
+            .ok()
+            .and_then(|n| n.checked_mul(PAGE_SIZE))
+        {
+            Some(len) => len,
+            None => return -1,
+        };
+        // Allocation of huge 64-bit memory may fail. Report it as failure of growing memory
+        if self
+            .data
+            .try_reserve_exact(next_len - self.data.len())
+            .is_err()
+        {
+            return -1;
+        }
         self.data.resize(next_len, 0);
-        prev as i32
+        prev as i64
     }
 
     fn check_addr<V: LittleEndian>(
@@ -126,7 +219,8 @@ impl Memory {
         at: usize,
         operation: &'static str,
     ) -> Result<()> {
-        if addr + size_of::<V>() > self.data.len() {
+        // Address popped from 64-bit memory may be close to usize::MAX
+        if addr.saturating_add(size_of::<V>()) > self.data.len() {
             Err(Trap::new(
                 TrapReason::LoadMemoryOutOfRange {
                     max: self.data.len(),
@@ -154,6 +248,20 @@ impl Memory {
         Ok(())
     }
 
+    /// Copy bytes at `addr` into `buf`. Out-of-bounds access is reported as trap.
+    pub fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> Result<()> {
+        check_bounds(addr, buf.len(), self.data.len(), "read", "memory", 0)?;
+        buf.copy_from_slice(&self.data[addr..addr + buf.len()]);
+        Ok(())
+    }
+
+    /// Copy `bytes` to `addr`. Out-of-bounds access is reported as trap.
+    pub fn write_bytes(&mut self, addr: usize, bytes: &[u8]) -> Result<()> {
+        check_bounds(addr, bytes.len(), self.data.len(), "write", "memory", 0)?;
+        self.data[addr..addr + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
     pub fn data(&self) -> &'_ [u8] {
         &self.data
     }
@@ -162,3 +270,49 @@ impl Memory {
         &mut self.data
     }
 }
+
+/// Memories of the module instance which calls a host function. Memory indices of the instance
+/// are used to access them. This dereferences to the default memory (memory index 0) since most
+/// host functions only use it.
+pub struct Memories<'a> {
+    store: &'a mut [Memory],
+    addrs: &'a [usize], // Addresses of memories in store indexed by memory indices
+}
+
+impl<'a> Memories<'a> {
+    pub(crate) fn new(store: &'a mut [Memory], addrs: &'a [usize]) -> Self {
+        Self { store, addrs }
+    }
+
+    /// Number of memories of the instance.
+    pub fn len(&self) -> usize {
+        self.addrs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addrs.is_empty()
+    }
+
+    pub fn get(&self, idx: u32) -> Option<&Memory> {
+        let addr = *self.addrs.get(idx as usize)?;
+        Some(&self.store[addr])
+    }
+
+    pub fn get_mut(&mut self, idx: u32) -> Option<&mut Memory> {
+        let addr = *self.addrs.get(idx as usize)?;
+        Some(&mut self.store[addr])
+    }
+}
+
+impl<'a> Deref for Memories<'a> {
+    type Target = Memory;
+    fn deref(&self) -> &Memory {
+        &self.store[self.addrs[0]]
+    }
+}
+
+impl<'a> DerefMut for Memories<'a> {
+    fn deref_mut(&mut self) -> &mut Memory {
+        &mut self.store[self.addrs[0]]
+    }
+}
@@ -0,0 +1,288 @@
+// Helpers for vector instructions. v128 value is held as i128 and its lanes are interpreted in
+// little endian. Lane type `T` decides the shape of vector (i8 for i8x16, f32 for f32x4, ...).
+// https://webassembly.github.io/spec/core/exec/instructions.html#vector-instructions
+use crate::value::LittleEndian;
+use std::mem::size_of;
+use std::ops::{Add, Mul, Neg};
+
+fn num_lanes<T>() -> usize {
+    16 / size_of::<T>()
+}
+
+pub fn lanes<T: LittleEndian>(v: i128) -> impl Iterator<Item = T> {
+    let bytes = v.to_le_bytes();
+    (0..num_lanes::<T>()).map(move |i| T::read(&bytes, i * size_of::<T>()))
+}
+
+// Lanes which are not given by the iterator are filled with zeros
+pub fn from_lanes<T: LittleEndian>(lanes: impl Iterator<Item = T>) -> i128 {
+    let mut bytes = [0; 16];
+    for (i, lane) in lanes.take(num_lanes::<T>()).enumerate() {
+        T::write(&mut bytes, i * size_of::<T>(), lane);
+    }
+    i128::from_le_bytes(bytes)
+}
+
+// Lanes of lower half or higher half of the vector
+fn half<T: LittleEndian>(v: i128, high: bool) -> impl Iterator<Item = T> {
+    let n = num_lanes::<T>() / 2;
+    lanes(v).skip(if high { n } else { 0 }).take(n)
+}
+
+// All bits of lane are set when the condition is true. Otherwise all bits are cleared
+pub fn mask<T: From<bool> + Neg<Output = T>>(cond: bool) -> T {
+    -T::from(cond)
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-splat
+pub fn splat<T: LittleEndian + Copy>(x: T) -> i128 {
+    from_lanes(std::iter::repeat(x))
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-extract-lane
+pub fn extract_lane<T: LittleEndian>(v: i128, lane: u8) -> T {
+    T::read(&v.to_le_bytes(), lane as usize * size_of::<T>())
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-replace-lane
+pub fn replace_lane<T: LittleEndian>(v: i128, lane: u8, x: T) -> i128 {
+    let mut bytes = v.to_le_bytes();
+    T::write(&mut bytes, lane as usize * size_of::<T>(), x);
+    i128::from_le_bytes(bytes)
+}
+
+// Apply the operation to each lane
+pub fn map<T, U, F>(v: i128, f: F) -> i128
+where
+    T: LittleEndian,
+    U: LittleEndian,
+    F: FnMut(T) -> U,
+{
+    from_lanes(lanes(v).map(f))
+}
+
+// Apply the operation to each pair of lanes at the same position of two vectors
+pub fn zip<T, U, F>(l: i128, r: i128, mut f: F) -> i128
+where
+    T: LittleEndian,
+    U: LittleEndian,
+    F: FnMut(T, T) -> U,
+{
+    from_lanes(lanes(l).zip(lanes(r)).map(|(l, r)| f(l, r)))
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-extend
+pub fn extend<T, U>(v: i128, high: bool) -> i128
+where
+    T: LittleEndian,
+    U: LittleEndian + From<T>,
+{
+    from_lanes(half::<T>(v, high).map(U::from))
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-extmul
+pub fn extmul<T, U>(l: i128, r: i128, high: bool) -> i128
+where
+    T: LittleEndian,
+    U: LittleEndian + From<T> + Mul<Output = U>,
+{
+    let products = half::<T>(l, high)
+        .zip(half::<T>(r, high))
+        .map(|(l, r)| U::from(l) * U::from(r));
+    from_lanes(products)
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-extadd-pairwise
+pub fn extadd_pairwise<T, U>(v: i128) -> i128
+where
+    T: LittleEndian,
+    U: LittleEndian + From<T> + Add<Output = U>,
+{
+    let mut it = lanes::<T>(v);
+    let mut sums = Vec::with_capacity(num_lanes::<U>());
+    while let (Some(l), Some(r)) = (it.next(), it.next()) {
+        sums.push(U::from(l) + U::from(r));
+    }
+    from_lanes(sums.into_iter())
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-narrow
+pub fn narrow<T, U, F>(l: i128, r: i128, f: F) -> i128
+where
+    T: LittleEndian,
+    U: LittleEndian,
+    F: FnMut(T) -> U,
+{
+    from_lanes(lanes(l).chain(lanes(r)).map(f))
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-dot
+pub fn dot_i16x8(l: i128, r: i128) -> i128 {
+    let mut products = lanes::<i16>(l)
+        .zip(lanes::<i16>(r))
+        .map(|(l, r)| l as i32 * r as i32);
+    let mut sums = Vec::with_capacity(4);
+    while let (Some(l), Some(r)) = (products.next(), products.next()) {
+        sums.push(l.wrapping_add(r));
+    }
+    from_lanes(sums.into_iter())
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-bitmask
+pub fn bitmask<T: LittleEndian + Default + PartialOrd>(v: i128) -> i32 {
+    lanes::<T>(v)
+        .enumerate()
+        .filter(|(_, x)| *x < T::default())
+        .fold(0, |acc, (i, _)| acc | 1 << i)
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-all-true
+pub fn all_true<T: LittleEndian + Default + PartialEq>(v: i128) -> bool {
+    lanes::<T>(v).all(|x| x != T::default())
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-swizzle
+pub fn swizzle(v: i128, s: i128) -> i128 {
+    let bytes = v.to_le_bytes();
+    map::<u8, u8, _>(s, |i| bytes.get(i as usize).copied().unwrap_or(0))
+}
+
+// https://webassembly.github.io/spec/core/exec/instructions.html#exec-vec-shuffle
+pub fn shuffle(l: i128, r: i128, lanes: &[u8; 16]) -> i128 {
+    let mut bytes = [0; 32];
+    bytes[..16].copy_from_slice(&l.to_le_bytes());
+    bytes[16..].copy_from_slice(&r.to_le_bytes());
+    from_lanes(lanes.iter().map(|i| bytes[*i as usize]))
+}
+
+// Scalar min/max of Rust return the other operand when one of them is NaN and may return either
+// of +0 and -0. fmin and fmax of Wasm propagate NaN and order -0 before +0.
+// https://webassembly.github.io/spec/core/exec/numerics.html#op-fmin
+macro_rules! float_min_max {
+    ($min:ident, $max:ident, $float:ty) => {
+        pub fn $min(l: $float, r: $float) -> $float {
+            if l.is_nan() || r.is_nan() {
+                <$float>::NAN
+            } else if l == 0.0 && r == 0.0 {
+                if l.is_sign_negative() {
+                    l
+                } else {
+                    r
+                }
+            } else {
+                l.min(r)
+            }
+        }
+
+        pub fn $max(l: $float, r: $float) -> $float {
+            if l.is_nan() || r.is_nan() {
+                <$float>::NAN
+            } else if l == 0.0 && r == 0.0 {
+                if l.is_sign_positive() {
+                    l
+                } else {
+                    r
+                }
+            } else {
+                l.max(r)
+            }
+        }
+    };
+}
+
+float_min_max!(f32_min, f32_max, f32);
+float_min_max!(f64_min, f64_max, f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lanes_roundtrip() {
+        let v = 0x0f0e0d0c_0b0a0908_07060504_03020100i128;
+        let bytes: Vec<u8> = lanes(v).collect();
+        assert_eq!(bytes, (0..16).collect::<Vec<u8>>());
+        assert_eq!(from_lanes(bytes.into_iter()), v);
+        assert_eq!(extract_lane::<i32>(v, 1), 0x07060504);
+        assert_eq!(
+            replace_lane::<i16>(v, 7, -1),
+            0xffff0d0c_0b0a0908_07060504_03020100u128 as i128
+        );
+        assert_eq!(from_lanes([1i64].iter().copied()), 1);
+    }
+
+    #[test]
+    fn splat_and_mask() {
+        assert_eq!(splat(-1i8), -1);
+        assert_eq!(splat(1.0f32), 0x3f800000_3f800000_3f800000_3f800000);
+        let eq = zip::<i32, i32, _>(
+            splat(3i32),
+            from_lanes([3, 2, 3, 0].iter().copied()),
+            |l, r| mask(l == r),
+        );
+        assert_eq!(lanes::<i32>(eq).collect::<Vec<_>>(), vec![-1, 0, -1, 0]);
+    }
+
+    #[test]
+    fn widen_and_narrow() {
+        let v = from_lanes((0..16).map(|i| -(i as i8)));
+        let lo: Vec<i16> = lanes(extend::<i8, i16>(v, false)).collect();
+        assert_eq!(lo, vec![0, -1, -2, -3, -4, -5, -6, -7]);
+        let hi: Vec<u16> = lanes(extend::<u8, u16>(v, true)).collect();
+        assert_eq!(hi, vec![248, 247, 246, 245, 244, 243, 242, 241]);
+        let sums: Vec<i16> = lanes(extadd_pairwise::<i8, i16>(v)).collect();
+        assert_eq!(sums, vec![-1, -5, -9, -13, -17, -21, -25, -29]);
+        let products: Vec<i64> =
+            lanes(extmul::<i32, i64>(splat(i32::MIN), splat(2i32), true)).collect();
+        assert_eq!(products, vec![i32::MIN as i64 * 2; 2]);
+
+        let l = from_lanes([300i16, -300, 1, -1, 0, 127, 128, -129].iter().copied());
+        let n = narrow::<i16, i8, _>(l, l, |x| x.max(i8::MIN as i16).min(i8::MAX as i16) as i8);
+        let n: Vec<i8> = lanes(n).take(8).collect();
+        assert_eq!(n, vec![127, -128, 1, -1, 0, 127, 127, -128]);
+    }
+
+    #[test]
+    fn dot_product() {
+        let l = from_lanes([1i16, 2, 3, 4, i16::MIN, i16::MIN, 0, 0].iter().copied());
+        let r = from_lanes([5i16, 6, 7, 8, i16::MIN, i16::MIN, 0, 0].iter().copied());
+        let v: Vec<i32> = lanes(dot_i16x8(l, r)).collect();
+        assert_eq!(v, vec![17, 53, i32::MIN, 0]);
+    }
+
+    #[test]
+    fn bitmask_and_all_true() {
+        let v = from_lanes([-1i32, 0, -5, 3].iter().copied());
+        assert_eq!(bitmask::<i32>(v), 0b0101);
+        assert!(!all_true::<i32>(v));
+        assert!(all_true::<i64>(v));
+        assert_eq!(bitmask::<i8>(-1), 0xffff);
+    }
+
+    #[test]
+    fn swizzle_and_shuffle() {
+        let v = from_lanes((0..16).map(|i| i as u8 + 100));
+        let s = from_lanes([15u8, 0, 16, 255, 1].iter().copied());
+        let b: Vec<u8> = lanes(swizzle(v, s)).take(5).collect();
+        assert_eq!(b, vec![115, 100, 0, 0, 101]);
+
+        let r = splat(7u8);
+        let mut idx = [0; 16];
+        idx[0] = 31;
+        idx[1] = 2;
+        let b: Vec<u8> = lanes(shuffle(v, r, &idx)).take(3).collect();
+        assert_eq!(b, vec![7, 102, 100]);
+    }
+
+    #[test]
+    fn float_min_max() {
+        assert!(f32_min(f32::NAN, 1.0).is_nan());
+        assert!(f64_max(1.0, f64::NAN).is_nan());
+        assert!(f32_min(0.0, -0.0).is_sign_negative());
+        assert!(f32_min(-0.0, 0.0).is_sign_negative());
+        assert!(f64_max(-0.0, 0.0).is_sign_positive());
+        assert!(f64_max(0.0, -0.0).is_sign_positive());
+        assert_eq!(f32_min(1.0, 2.0), 1.0);
+        assert_eq!(f64_max(1.0, 2.0), 2.0);
+    }
+}
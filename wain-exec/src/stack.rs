@@ -1,283 +1,207 @@
-use crate::value::{LittleEndian, Value};
-use std::cmp::Ordering;
-use std::convert::{TryFrom, TryInto};
-use std::f32;
-use std::f64;
-use std::fmt;
+use crate::value::Value;
 use std::mem::size_of;
-use wain_ast::{AsValType, ValType};
-
-// Vec<Value> consumes too much space since its element size is always 64bits.
-// To use space more efficiently, here use u32 for storing values as bytes.
+use wain_ast::ValType;
+
+// Values on stack are stored in untyped 128bit slots. Types of values were already checked by
+// validation so they are not kept at runtime. Types are only necessary to reconstruct `Value`
+// for embedders (arguments of host functions, return values of invoked functions). They are
+// given from function signatures.
+//
+// Every value consumes one slot regardless of its type so that a local variable or a label can
+// be located by index without calculating its address from types of preceding values. A slot is
+// as wide as v128, the widest value type. Measured slowdown of scalar-heavy code compared with
+// 64bit slots is a few percent at most.
 
 #[derive(Default)]
 pub struct Stack {
-    bytes: Vec<u8>,      // Bytes buffer for actual values
-    types: Vec<ValType>, // this stack is necessary to pop arbitrary value
+    slots: Vec<u128>,
 }
 
+// Conversion between value and slot. 32bit and 64bit values are zero-extended so that
+// reinterpreting a value between integer and float does not need to modify the slot.
 pub trait StackAccess: Sized {
-    fn pop(stack: &mut Stack) -> Self {
-        let v = Self::top(stack);
-        stack.erase_top(size_of::<Self>());
-        v
-    }
-    fn push(stack: &mut Stack, v: Self);
-    fn top(stack: &mut Stack) -> Self;
+    fn from_slot(slot: u128) -> Self;
+    fn to_slot(self) -> u128;
 }
 
 impl StackAccess for i32 {
-    fn push(stack: &mut Stack, v: Self) {
-        stack.push_bytes(&v.to_le_bytes(), ValType::I32);
+    fn from_slot(slot: u128) -> Self {
+        slot as u32 as i32
     }
-    fn top(stack: &mut Stack) -> Self {
-        assert_eq!(stack.top_type(), ValType::I32);
-        i32::from_le_bytes(stack.top_bytes())
+    fn to_slot(self) -> u128 {
+        self as u32 as u128
     }
 }
 
 impl StackAccess for i64 {
-    fn push(stack: &mut Stack, v: Self) {
-        stack.push_bytes(&v.to_le_bytes(), ValType::I64);
+    fn from_slot(slot: u128) -> Self {
+        slot as i64
     }
-    fn top(stack: &mut Stack) -> Self {
-        assert_eq!(stack.top_type(), ValType::I64);
-        i64::from_le_bytes(stack.top_bytes())
+    fn to_slot(self) -> u128 {
+        self as u64 as u128
     }
 }
 
 impl StackAccess for f32 {
-    fn push(stack: &mut Stack, v: Self) {
-        stack.push_bytes(&v.to_le_bytes(), ValType::F32);
+    fn from_slot(slot: u128) -> Self {
+        f32::from_bits(slot as u32)
     }
-    fn top(stack: &mut Stack) -> Self {
-        assert_eq!(stack.top_type(), ValType::F32);
-        f32::from_le_bytes(stack.top_bytes())
+    fn to_slot(self) -> u128 {
+        self.to_bits() as u128
     }
 }
 
 impl StackAccess for f64 {
-    fn push(stack: &mut Stack, v: Self) {
-        stack.push_bytes(&v.to_le_bytes(), ValType::F64);
+    fn from_slot(slot: u128) -> Self {
+        f64::from_bits(slot as u64)
     }
-    fn top(stack: &mut Stack) -> Self {
-        assert_eq!(stack.top_type(), ValType::F64);
-        f64::from_le_bytes(stack.top_bytes())
+    fn to_slot(self) -> u128 {
+        self.to_bits() as u128
     }
 }
 
-impl StackAccess for Value {
-    fn pop(stack: &mut Stack) -> Self {
-        match stack.types[stack.types.len() - 1] {
-            ValType::I32 => Value::I32(StackAccess::pop(stack)),
-            ValType::I64 => Value::I64(StackAccess::pop(stack)),
-            ValType::F32 => Value::F32(StackAccess::pop(stack)),
-            ValType::F64 => Value::F64(StackAccess::pop(stack)),
-        }
+// Null reference is 0 so that zero-initialized local variables of reference types are null
+impl StackAccess for Option<u32> {
+    fn from_slot(slot: u128) -> Self {
+        slot.checked_sub(1).map(|a| a as u32)
     }
-    fn push(stack: &mut Stack, v: Self) {
-        match v {
-            Value::I32(i) => StackAccess::push(stack, i),
-            Value::I64(i) => StackAccess::push(stack, i),
-            Value::F32(f) => StackAccess::push(stack, f),
-            Value::F64(f) => StackAccess::push(stack, f),
-        }
-    }
-    fn top(stack: &mut Stack) -> Self {
-        match stack.types[stack.types.len() - 1] {
-            ValType::I32 => Value::I32(StackAccess::top(stack)),
-            ValType::I64 => Value::I64(StackAccess::top(stack)),
-            ValType::F32 => Value::F32(StackAccess::top(stack)),
-            ValType::F64 => Value::F64(StackAccess::top(stack)),
-        }
+    fn to_slot(self) -> u128 {
+        self.map_or(0, |a| a as u128 + 1)
     }
 }
 
-impl Stack {
-    // Note: Here I don't use std::slice::from_raw since its unsafe
+impl StackAccess for i128 {
+    fn from_slot(slot: u128) -> Self {
+        slot as i128
+    }
+    fn to_slot(self) -> u128 {
+        self as u128
+    }
+}
 
-    fn top_type(&self) -> ValType {
-        self.types[self.types.len() - 1]
+fn value_to_slot(v: Value) -> u128 {
+    match v {
+        Value::I32(i) => i.to_slot(),
+        Value::I64(i) => i.to_slot(),
+        Value::F32(f) => f.to_slot(),
+        Value::F64(f) => f.to_slot(),
+        Value::V128(v) => v.to_slot(),
+        Value::FuncRef(r) | Value::ExternRef(r) => r.to_slot(),
     }
+}
 
-    fn push_bytes(&mut self, bytes: &[u8], ty: ValType) {
-        self.types.push(ty);
-        self.bytes.extend_from_slice(bytes);
+fn slot_to_value(slot: u128, ty: ValType) -> Value {
+    match ty {
+        ValType::I32 => Value::I32(StackAccess::from_slot(slot)),
+        ValType::I64 => Value::I64(StackAccess::from_slot(slot)),
+        ValType::F32 => Value::F32(StackAccess::from_slot(slot)),
+        ValType::F64 => Value::F64(StackAccess::from_slot(slot)),
+        ValType::V128 => Value::V128(StackAccess::from_slot(slot)),
+        ValType::FuncRef => Value::FuncRef(StackAccess::from_slot(slot)),
+        ValType::ExternRef => Value::ExternRef(StackAccess::from_slot(slot)),
     }
+}
 
+impl Stack {
     pub fn push<V: StackAccess>(&mut self, v: V) {
-        StackAccess::push(self, v);
+        self.slots.push(v.to_slot());
     }
 
-    fn top_bytes<'a, T>(&'a self) -> T
-    where
-        T: TryFrom<&'a [u8]>,
-        T::Error: fmt::Debug,
-    {
-        let len = self.bytes.len() - size_of::<T>();
-        self.bytes[len..].try_into().expect("top bytes")
+    pub fn pop<V: StackAccess>(&mut self) -> V {
+        V::from_slot(self.pop_slot())
     }
 
-    fn erase_top(&mut self, len: usize) {
-        self.types.pop();
-        self.bytes.truncate(self.bytes.len() - len);
+    pub fn top<V: StackAccess>(&self) -> V {
+        V::from_slot(self.top_slot())
     }
 
-    pub fn pop<V: StackAccess>(&mut self) -> V {
-        StackAccess::pop(self)
+    // Instead of popping value and pushing the result, directly modify stack top for optimization
+    pub fn write_top<V: StackAccess>(&mut self, v: V) {
+        let idx = self.slots.len() - 1;
+        self.slots[idx] = v.to_slot();
     }
 
-    pub fn top<V: StackAccess>(&mut self) -> V {
-        StackAccess::top(self)
+    pub fn push_value(&mut self, v: Value) {
+        self.slots.push(value_to_slot(v));
     }
 
-    pub fn write_top_bytes<V: LittleEndian>(&mut self, v: V) {
-        let addr = self.bytes.len() - size_of::<V>();
-        LittleEndian::write(&mut self.bytes, addr, v);
+    // Type of the value is necessary since slots don't remember types of values
+    pub fn pop_value(&mut self, ty: ValType) -> Value {
+        slot_to_value(self.pop_slot(), ty)
     }
 
-    pub fn write_top_type(&mut self, t: ValType) {
-        let len = self.types.len() - 1;
-        self.types[len] = t;
+    pub fn push_slot(&mut self, slot: u128) {
+        self.slots.push(slot);
     }
 
-    pub fn write_top<T: StackAccess, V: LittleEndian + AsValType>(&mut self, v: V) {
-        // Expect optimizations by compiler since conditions of these if statements can be
-        // calculated at compile time
-        //
-        // Note: The same value as size_of::<T>() can be obtained by self.top_type().bytes(), but
-        // it's runtime value preventing compiler from optimization.
-        match size_of::<T>().cmp(&size_of::<V>()) {
-            Ordering::Equal => {}
-            Ordering::Greater => {
-                let len = self.bytes.len() - (size_of::<T>() - size_of::<V>());
-                self.bytes.truncate(len);
-            }
-            Ordering::Less => {
-                let len = self.bytes.len() + (size_of::<V>() - size_of::<T>());
-                self.bytes.resize(len, 0);
-            }
-        }
-        self.write_top_bytes(v);
-        self.write_top_type(V::VAL_TYPE);
+    pub fn pop_slot(&mut self) -> u128 {
+        self.slots.pop().expect("pop value from empty stack")
     }
 
-    pub fn write<V: LittleEndian>(&mut self, addr: usize, v: V) {
-        LittleEndian::write(&mut self.bytes, addr, v);
+    pub fn top_slot(&self) -> u128 {
+        self.slots[self.slots.len() - 1]
     }
 
-    pub fn read<V: LittleEndian>(&self, addr: usize) -> V {
-        LittleEndian::read(&self.bytes, addr)
+    pub fn read_slot(&self, idx: usize) -> u128 {
+        self.slots[idx]
     }
 
-    pub fn write_any(&mut self, addr: usize, v: Value) {
-        match v {
-            Value::I32(i) => self.write(addr, i),
-            Value::I64(i) => self.write(addr, i),
-            Value::F32(f) => self.write(addr, f),
-            Value::F64(f) => self.write(addr, f),
-        }
+    pub fn write_slot(&mut self, idx: usize, slot: u128) {
+        self.slots[idx] = slot;
     }
 
-    fn top_addr(&self) -> usize {
-        self.bytes.len()
+    // Size of values on stack in bytes
+    pub fn size(&self) -> usize {
+        self.slots.len() * size_of::<u128>()
     }
 
-    fn top_idx(&self) -> usize {
-        self.types.len()
+    // Number of values on stack
+    pub fn len(&self) -> usize {
+        self.slots.len()
     }
 
-    pub fn restore(&mut self, addr: usize, type_idx: usize) {
-        self.bytes.truncate(addr);
-        self.types.truncate(type_idx);
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
     }
 
-    pub fn push_label(&self, ty: Option<ValType>) -> Label {
+    pub fn push_label(&self, results: usize) -> Label {
         Label {
-            addr: self.top_addr(),
-            type_idx: self.top_idx(),
-            has_result: ty.is_some(),
+            len: self.len(),
+            results,
         }
     }
 
     pub fn pop_label(&mut self, label: Label) {
-        // Part of 'br' instruction: https://webassembly.github.io/spec/core/exec/instructions.html#exec-br
-        if label.has_result {
-            let v: Value = self.pop();
-            self.restore(label.addr, label.type_idx);
-            self.push(v);
-        } else {
-            self.restore(label.addr, label.type_idx);
-        }
+        self.unwind(label.len, label.results);
     }
 
-    pub fn extend_zero_values(&mut self, types: &[ValType]) {
-        self.types.extend_from_slice(types);
-        let bytes = types.iter().fold(0, |acc, t| acc + t.bytes());
-        self.bytes.resize(self.bytes.len() + bytes, 0);
-    }
-}
-
-// Activations of function frames
-// This class is outside Machine because it has shorter lifetime. It only lives while the current
-// function is being invoked
-pub struct CallFrame<'func> {
-    pub base_addr: usize,
-    pub base_idx: usize,
-    local_addrs: Box<[usize]>, // Calculate local addresses in advance for random access
-    params: &'func [ValType],
-    locals: &'func [ValType],
-}
-
-impl<'f> CallFrame<'f> {
-    pub fn new(stack: &Stack, params: &'f [ValType], locals: &'f [ValType]) -> Self {
-        let mut addrs = Vec::with_capacity(params.len() + locals.len());
-
-        // Note: Params were already pushed to stack
-        let params_bytes = params.iter().fold(0, |acc, p| acc + p.bytes());
-        let base_addr = stack.top_addr() - params_bytes;
-        let base_idx = stack.top_idx() - params.len();
-
-        let mut addr = 0;
-        for p in params {
-            addrs.push(base_addr + addr);
-            addr += p.bytes();
-        }
-        for l in locals {
-            addrs.push(base_addr + addr);
-            addr += l.bytes();
-        }
-
-        Self {
-            base_addr,
-            base_idx,
-            local_addrs: addrs.into_boxed_slice(),
-            params,
-            locals,
+    // Part of 'br' instruction: https://webassembly.github.io/spec/core/exec/instructions.html#exec-br
+    // Remove values above the height. Top `keep` values are carried over
+    pub fn unwind(&mut self, len: usize, keep: usize) {
+        match keep {
+            0 => {}
+            1 => {
+                let top = self.top_slot();
+                self.slots[len] = top;
+            }
+            _ => {
+                let top = self.slots.len() - keep;
+                self.slots.copy_within(top.., len);
+            }
         }
+        self.slots.truncate(len + keep);
     }
 
-    pub fn local_addr(&self, localidx: u32) -> usize {
-        self.local_addrs[localidx as usize]
-    }
-
-    pub fn local_type(&self, localidx: u32) -> ValType {
-        let idx = localidx as usize;
-        if idx < self.params.len() {
-            self.params[idx]
-        } else if idx < self.params.len() + self.locals.len() {
-            self.locals[idx - self.params.len()]
-        } else {
-            // Unreachable thanks to validation
-            unreachable!("local type out of bounds")
-        }
+    // Local variables are initialized with zero. Zero slot represents zero of any type
+    pub fn extend_zero_values(&mut self, len: usize) {
+        self.slots.resize(self.slots.len() + len, 0);
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct Label {
-    addr: usize,
-    type_idx: usize,
-    has_result: bool,
+    len: usize,
+    results: usize,
 }
 
 #[cfg(test)]
@@ -386,7 +310,7 @@ mod tests {
             s.push(*i32v);
             s.push(*i64v);
             s.push(*f32v);
-            s.push(*f64v);
+            s.push_value(Value::F64(*f64v));
         }
 
         for (((i32v, i64v), f32v), f64v) in i32_s
@@ -401,27 +325,78 @@ mod tests {
             .rev()
         {
             if f64v.is_nan() {
-                match s.pop() {
+                match s.pop_value(ValType::F64) {
                     Value::F64(v) => assert!(v.is_nan()),
                     v => panic!("not match: {:?}", v),
                 }
             } else {
-                assert_eq!(s.top::<Value>(), Value::F64(*f64v));
-                assert_eq!(s.pop::<Value>(), Value::F64(*f64v));
+                assert_eq!(s.top::<f64>(), *f64v);
+                assert_eq!(s.pop_value(ValType::F64), Value::F64(*f64v));
             }
             if f32v.is_nan() {
-                match s.pop() {
+                match s.pop_value(ValType::F32) {
                     Value::F32(v) => assert!(v.is_nan()),
                     v => panic!("not match: {:?}", v),
                 }
             } else {
-                assert_eq!(s.top::<Value>(), Value::F32(*f32v));
-                assert_eq!(s.pop::<Value>(), Value::F32(*f32v));
+                assert_eq!(s.top::<f32>(), *f32v);
+                assert_eq!(s.pop_value(ValType::F32), Value::F32(*f32v));
             }
-            assert_eq!(s.top::<Value>(), Value::I64(*i64v));
-            assert_eq!(s.pop::<Value>(), Value::I64(*i64v));
-            assert_eq!(s.top::<Value>(), Value::I32(*i32v));
-            assert_eq!(s.pop::<Value>(), Value::I32(*i32v));
+            assert_eq!(s.top::<i64>(), *i64v);
+            assert_eq!(s.pop_value(ValType::I64), Value::I64(*i64v));
+            assert_eq!(s.top::<i32>(), *i32v);
+            assert_eq!(s.pop_value(ValType::I32), Value::I32(*i32v));
         }
     }
+
+    #[test]
+    fn reinterpret_slot() {
+        let mut s = Stack::default();
+        s.push(-1.5f32);
+        assert_eq!(s.top::<i32>(), (-1.5f32).to_bits() as i32);
+        s.push(i32::MIN);
+        assert_eq!(s.top::<f32>().to_bits(), i32::MIN as u32);
+        s.push(-2.5f64);
+        assert_eq!(s.top::<i64>(), (-2.5f64).to_bits() as i64);
+        assert_eq!(s.len(), 3);
+    }
+
+    #[test]
+    fn v128_value() {
+        let mut s = Stack::default();
+        s.push(-1i128);
+        s.push(1i32);
+        s.push_value(Value::V128(i128::MIN));
+        assert_eq!(s.len(), 3);
+
+        assert_eq!(s.pop_value(ValType::V128), Value::V128(i128::MIN));
+        assert_eq!(s.pop::<i32>(), 1);
+        assert_eq!(s.top::<i128>(), -1);
+        // Lower 64bits of v128 slot can be read as i64
+        assert_eq!(s.top::<i64>(), -1);
+    }
+
+    #[test]
+    fn unwind_values() {
+        let mut s = Stack::default();
+        s.push(1i32);
+        let label = s.push_label(1);
+        s.push(2.0f32);
+        s.push(3i64);
+        s.pop_label(label);
+        assert_eq!(s.len(), 2);
+        assert_eq!(s.pop::<i64>(), 3);
+
+        s.push(4i32);
+        s.push(5i32);
+        s.push(6i64);
+        s.unwind(1, 2);
+        assert_eq!(s.len(), 3);
+        assert_eq!(s.pop::<i64>(), 6);
+        assert_eq!(s.pop::<i32>(), 5);
+        assert_eq!(s.pop::<i32>(), 1);
+
+        s.unwind(0, 0);
+        assert!(s.is_empty());
+    }
 }
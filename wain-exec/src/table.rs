@@ -1,86 +1,139 @@
-use crate::globals::Globals;
-use crate::trap::{Result, Trap, TrapReason};
+use crate::trap::{check_bounds, Result, Trap, TrapReason};
 use wain_ast as ast;
 
+// Note: WebAssembly spec allows up to 2^32 - 1 elements. Since every element consumes memory
+// on allocation, growing a table is limited to a practical size.
+const MAX_TABLE_ELEMS: usize = 10_000_000;
+
 // Table instance
 pub struct Table {
+    ty: ast::RefType,
     max: Option<usize>,
-    elems: Vec<Option<u32>>, // function indices
+    elems: Vec<Option<u32>>, // function addresses or external references
 }
 
 impl Table {
+    // Create funcref table instance with `min` uninitialized elements. This is used for making
+    // table imported by modules
+    pub fn new(min: u32, max: Option<u32>) -> Self {
+        Self::with_elem_type(ast::RefType::FuncRef, min, max)
+    }
+
+    // Create table instance whose elements are references of the given type
+    pub fn with_elem_type(ty: ast::RefType, min: u32, max: Option<u32>) -> Self {
+        Self {
+            ty,
+            max: max.map(|m| m as usize),
+            elems: vec![None; min as usize],
+        }
+    }
+
     // https://webassembly.github.io/spec/core/exec/modules.html#alloc-table
-    pub fn allocate(tables: &[ast::Table]) -> Result<Self> {
-        // Note: Only one table exists thanks to validation
-        assert!(tables.len() <= 1);
-        if let Some(table) = tables.get(0) {
-            if let Some(i) = &table.import {
-                Err(Trap::unknown_import(i, "table", table.start))
-            } else {
-                let (min, max) = match &table.ty.limit {
-                    ast::Limits::Range(min, max) => (*min, Some(*max as usize)),
-                    ast::Limits::From(min) => (*min, None),
-                };
-                let elems = if min == 0 {
-                    vec![]
-                } else {
-                    let mut v = Vec::with_capacity(min as usize);
-                    v.resize(min as usize, None);
-                    v
-                };
-                Ok(Self { max, elems })
-            }
+    pub fn allocate(table: &ast::Table) -> Result<Self> {
+        if let Some(i) = &table.import {
+            Err(Trap::unknown_import(i, "table", table.start))
         } else {
-            // When no table is set use dummy empty table
-            Ok(Self {
-                max: Some(0),
-                elems: vec![],
-            })
+            // Parsers ensure that limits of tables are within u32
+            let (min, max) = match &table.ty.limit {
+                ast::Limits::Range(min, max) => (*min as u32, Some(*max as u32)),
+                ast::Limits::From(min) => (*min as u32, None),
+            };
+            Ok(Self::with_elem_type(table.ty.elem, min, max))
         }
     }
 
-    // 9. and 13. https://webassembly.github.io/spec/core/exec/modules.html#allocation
-    pub fn new_elem(&mut self, elem: &ast::ElemSegment, globals: &Globals) -> Result<()> {
-        // By validation of constant expression, at least one instruction in the sequence is guaranteed
-        // and type must be i32
-        let offset = match &elem.offset[elem.offset.len() - 1].kind {
-            ast::InsnKind::GlobalGet(idx) => globals.get(*idx),
-            ast::InsnKind::I32Const(i) => *i,
-            _ => unreachable!("unexpected instruction for element offset"),
-        };
-        let offset = offset as usize;
-        let end_idx = offset + elem.init.len();
-
-        if let Some(max) = self.max {
-            if end_idx > max {
-                return Err(Trap::new(
-                    TrapReason::OutOfLimit {
-                        max,
-                        idx: end_idx,
-                        kind: "table element",
-                    },
-                    elem.start,
-                ));
-            }
-        }
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-init
+    // Active element segments are also written by this method on instantiation. References in
+    // `segment` were already evaluated on instantiation
+    pub(crate) fn init(
+        &mut self,
+        dst: usize,
+        src: usize,
+        len: usize,
+        segment: &[Option<u32>],
+        at: usize,
+    ) -> Result<()> {
+        check_bounds(src, len, segment.len(), "table.init", "element segment", at)?;
+        check_bounds(dst, len, self.elems.len(), "table.init", "table", at)?;
+        self.elems[dst..dst + len].copy_from_slice(&segment[src..src + len]);
+        Ok(())
+    }
 
-        if self.elems.len() < end_idx {
-            return Err(Trap::new(
-                TrapReason::ElemSegmentLargerThanTable {
-                    segment_end: end_idx,
-                    table_size: self.elems.len(),
-                },
-                elem.start,
-            ));
-        }
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-copy
+    pub(crate) fn copy(&mut self, dst: usize, src: usize, len: usize, at: usize) -> Result<()> {
+        check_bounds(src, len, self.elems.len(), "table.copy", "table", at)?;
+        check_bounds(dst, len, self.elems.len(), "table.copy", "table", at)?;
+        // Source and destination ranges may overlap
+        self.elems.copy_within(src..src + len, dst);
+        Ok(())
+    }
 
-        for i in 0..elem.init.len() {
-            self.elems[offset + i] = Some(elem.init[i]);
+    // table.copy between two different tables
+    pub(crate) fn copy_from(
+        &mut self,
+        other: &Table,
+        dst: usize,
+        src: usize,
+        len: usize,
+        at: usize,
+    ) -> Result<()> {
+        check_bounds(src, len, other.elems.len(), "table.copy", "table", at)?;
+        check_bounds(dst, len, self.elems.len(), "table.copy", "table", at)?;
+        self.elems[dst..dst + len].copy_from_slice(&other.elems[src..src + len]);
+        Ok(())
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-fill
+    pub(crate) fn fill(
+        &mut self,
+        dst: usize,
+        val: Option<u32>,
+        len: usize,
+        at: usize,
+    ) -> Result<()> {
+        check_bounds(dst, len, self.elems.len(), "table.fill", "table", at)?;
+        for elem in &mut self.elems[dst..dst + len] {
+            *elem = val;
         }
+        Ok(())
+    }
+
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-get
+    pub fn get(&self, idx: usize, at: usize) -> Result<Option<u32>> {
+        check_bounds(idx, 1, self.elems.len(), "table.get", "table", at)?;
+        Ok(self.elems[idx])
+    }
 
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-set
+    pub fn set(&mut self, idx: usize, val: Option<u32>, at: usize) -> Result<()> {
+        check_bounds(idx, 1, self.elems.len(), "table.set", "table", at)?;
+        self.elems[idx] = val;
         Ok(())
     }
 
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-grow
+    pub fn grow(&mut self, delta: u32, init: Option<u32>) -> i32 {
+        let prev = self.elems.len();
+        let next = prev + delta as usize;
+        if next > self.max.unwrap_or(MAX_TABLE_ELEMS).min(MAX_TABLE_ELEMS) {
+            return -1;
+        }
+        self.elems.resize(next, init);
+        prev as i32
+    }
+
+    pub fn elem_type(&self) -> ast::RefType {
+        self.ty
+    }
+
+    pub fn size(&self) -> u32 {
+        self.elems.len() as u32
+    }
+
+    pub fn max(&self) -> Option<u32> {
+        self.max.map(|m| m as u32)
+    }
+
     pub fn at(&self, idx: usize, source_offset: usize) -> Result<u32> {
         if idx >= self.elems.len() {
             return Err(Trap::new(
@@ -0,0 +1,17 @@
+// Utilities shared by unit tests of this crate
+
+use wain_ast::Root;
+use wain_syntax_text::{parse, source::TextSource};
+use wain_validate::validate;
+
+// Parse and validate a module in text format. Panics when the source is not a valid module
+pub(crate) fn parse_module(source: &str) -> Root<'_, TextSource<'_>> {
+    let root = match parse(source) {
+        Ok(root) => root,
+        Err(err) => panic!("parse failed: {}", err),
+    };
+    if let Err(err) = validate(&root) {
+        panic!("validation failed: {}", err);
+    }
+    root
+}
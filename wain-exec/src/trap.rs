@@ -9,18 +9,13 @@ pub enum TrapReason {
         name: String,
         kind: &'static str,
     },
-    OutOfLimit {
-        max: usize,
-        idx: usize,
-        kind: &'static str,
-    },
-    DataSegmentOutOfBuffer {
-        segment_end: usize,
-        buffer_size: usize,
-    },
-    ElemSegmentLargerThanTable {
-        segment_end: usize,
-        table_size: usize,
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-memory-init
+    // https://webassembly.github.io/spec/core/exec/instructions.html#exec-table-init
+    OutOfBounds {
+        operation: &'static str,
+        what: &'static str,
+        end: usize,
+        size: usize,
     },
     ReachUnreachable,
     IdxOutOfTable {
@@ -35,6 +30,13 @@ pub enum TrapReason {
         actual_params: Box<[ValType]>,
         actual_results: Box<[ValType]>,
     },
+    IncompatibleImport {
+        mod_name: String,
+        name: String,
+        kind: &'static str,
+        expected: String,
+        actual: String,
+    },
     // 10. https://webassembly.github.io/spec/core/exec/instructions.html#and
     LoadMemoryOutOfRange {
         max: usize,
@@ -58,6 +60,55 @@ pub enum TrapReason {
     },
     RemZeroDivisor,
     DivByZeroOrOverflow,
+    InvalidConversionToInt,
+    IntOverflow,
+    StackExhausted {
+        kind: &'static str,
+        limit: usize,
+    },
+    OutOfFuel {
+        consumed: u64,
+    },
+    Interrupted,
+    NotSuspended,
+    // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#throwing-an-exception
+    // Exception thrown by 'throw' instruction was not caught by any handler. `tag` is the address
+    // of the tag in the store. See `Machine::instance_tag`
+    UncaughtException {
+        tag: u32,
+        payload: Box<[Value]>,
+    },
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#alignment
+    UnalignedAtomic {
+        addr: usize,
+        align: usize,
+    },
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#wait
+    WaitOnUnsharedMemory,
+    // Minimum size of memory is too large to allocate
+    MemoryAllocationFailure {
+        pages: u64,
+    },
+    // Host function terminated the execution with exit status. Only methods which cannot return
+    // the status as their result report this
+    Exit {
+        status: i32,
+    },
+    // Errors on accessing exports of instance from host. See `Machine::set_global` and
+    // `Machine::set_table_elem`
+    UnknownExport {
+        name: String,
+        kind: &'static str,
+    },
+    SetImmutableGlobal {
+        name: String,
+    },
+    ExportTypeMismatch {
+        name: String,
+        expected: ValType,
+        actual: ValType,
+    },
+    UnknownFuncRef(u32),
 }
 
 #[cfg_attr(test, derive(Debug))]
@@ -67,6 +118,25 @@ pub struct Trap {
 }
 
 impl Trap {
+    pub(crate) fn incompatible_import<'s>(
+        import: &Import<'s>,
+        kind: &'static str,
+        expected: String,
+        actual: String,
+        offset: usize,
+    ) -> Box<Self> {
+        Self::new(
+            TrapReason::IncompatibleImport {
+                mod_name: import.mod_name.0.to_string(),
+                name: import.name.0.to_string(),
+                kind,
+                expected,
+                actual,
+            },
+            offset,
+        )
+    }
+
     pub(crate) fn unknown_import<'s>(
         import: &Import<'s>,
         kind: &'static str,
@@ -87,6 +157,32 @@ impl Trap {
     }
 }
 
+// Check the range [start, start + len) is in bounds of memory, table or segment of `size` elements
+pub(crate) fn check_bounds(
+    start: usize,
+    len: usize,
+    size: usize,
+    operation: &'static str,
+    what: &'static str,
+    offset: usize,
+) -> Result<()> {
+    // Operands of 64-bit memory may be close to usize::MAX
+    let end = start.saturating_add(len);
+    if end > size {
+        Err(Trap::new(
+            TrapReason::OutOfBounds {
+                operation,
+                what,
+                end,
+                size,
+            },
+            offset,
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 struct JoinWritable<'a, D: fmt::Display>(&'a [D], &'static str);
 
 impl<'a, D: fmt::Display> fmt::Display for JoinWritable<'a, D> {
@@ -114,26 +210,15 @@ impl fmt::Display for Trap {
                 "unknown module '{}' or unknown {} value '{}' imported from the module",
                 mod_name, kind, name,
             )?,
-            OutOfLimit { max, idx, kind } => write!(
-                f,
-                "specified {} index 0x{:x} is out of limit 0x{:x}",
-                kind, idx, max,
-            )?,
-            DataSegmentOutOfBuffer {
-                segment_end,
-                buffer_size,
-            } => write!(
-                f,
-                "'data' segment ends at address 0x{:x} but memory buffer size is 0x{:x}",
-                segment_end, buffer_size,
-            )?,
-            ElemSegmentLargerThanTable {
-                segment_end,
-                table_size,
+            OutOfBounds {
+                operation,
+                what,
+                end,
+                size,
             } => write!(
                 f,
-                "'elem' segment ends at index {} but table length is {}",
-                segment_end, table_size,
+                "{} accesses {} out of bounds: range ends at 0x{:x} but its size is 0x{:x}",
+                operation, what, end, size,
             )?,
             ReachUnreachable => f.write_str("reached unreachable code")?,
             IdxOutOfTable { idx, table_size } => write!(
@@ -169,6 +254,17 @@ impl fmt::Display for Trap {
                     JoinWritable(actual_results, " "),
                 )?
             }
+            IncompatibleImport {
+                mod_name,
+                name,
+                kind,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "incompatible import type for {} '{}' of module '{}'. expected '{}' but got '{}'",
+                kind, name, mod_name, expected, actual,
+            )?,
             LoadMemoryOutOfRange {
                 max,
                 addr,
@@ -204,6 +300,36 @@ impl fmt::Display for Trap {
             )?,
             RemZeroDivisor => f.write_str("attempt to calculate reminder with zero divisor")?,
             DivByZeroOrOverflow => f.write_str("integer overflow or attempt to devide integer by zero")?,
+            InvalidConversionToInt => f.write_str("invalid conversion from NaN to integer")?,
+            IntOverflow => f.write_str("integer overflow while converting float to integer")?,
+            StackExhausted { kind, limit } => write!(f, "{} exhausted: exceeded the limit {}", kind, limit)?,
+            OutOfFuel { consumed } => write!(f, "ran out of fuel after consuming {} fuel", consumed)?,
+            Interrupted => f.write_str("execution was interrupted")?,
+            NotSuspended => f.write_str("cannot resume execution since no execution is suspended")?,
+            UncaughtException { tag, payload } => write!(
+                f,
+                "uncaught exception with tag {} and values [{}]",
+                tag,
+                JoinWritable(payload, ", "),
+            )?,
+            UnalignedAtomic { addr, align } => write!(
+                f,
+                "unaligned atomic access at 0x{:x}. address must be aligned to {} bytes",
+                addr, align,
+            )?,
+            WaitOnUnsharedMemory => f.write_str("memory.atomic.wait expected shared memory")?,
+            MemoryAllocationFailure { pages } => {
+                write!(f, "cannot allocate memory with {} pages", pages)?
+            }
+            Exit { status } => write!(f, "execution was terminated with exit status {}", status)?,
+            UnknownExport { name, kind } => write!(f, "{} '{}' is not exported", kind, name)?,
+            SetImmutableGlobal { name } => write!(f, "cannot set value to immutable global variable '{}'", name)?,
+            ExportTypeMismatch { name, expected, actual } => write!(
+                f,
+                "cannot set {} value to '{}' whose type is {}",
+                actual, name, expected,
+            )?,
+            UnknownFuncRef(addr) => write!(f, "function reference {} does not refer to any function", addr)?,
         }
         write!(
             f,
@@ -0,0 +1,113 @@
+use crate::import::Importer;
+use crate::machine::Machine;
+use crate::stack::{Stack, StackAccess};
+use crate::trap::Result;
+use std::marker::PhantomData;
+use wain_ast::{AsValType, ValType};
+
+/// Parameters of typed function. This is implemented for `()`, values of `i32`, `i64`, `f32`,
+/// `f64`, `i128` (v128) and tuples of them.
+pub trait WasmParams {
+    fn valtypes() -> Vec<ValType>;
+    fn push(self, stack: &mut Stack);
+}
+
+/// Results of typed function. This is implemented for the same types as `WasmParams`.
+pub trait WasmResults: Sized {
+    fn valtypes() -> Vec<ValType>;
+    fn pop(stack: &mut Stack) -> Self;
+}
+
+impl<T: AsValType + StackAccess> WasmParams for T {
+    fn valtypes() -> Vec<ValType> {
+        vec![T::VAL_TYPE]
+    }
+    fn push(self, stack: &mut Stack) {
+        stack.push(self);
+    }
+}
+
+impl<T: AsValType + StackAccess> WasmResults for T {
+    fn valtypes() -> Vec<ValType> {
+        vec![T::VAL_TYPE]
+    }
+    fn pop(stack: &mut Stack) -> Self {
+        stack.pop()
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($t:ident)*) => {
+        #[allow(non_snake_case)]
+        impl<$($t: AsValType + StackAccess),*> WasmParams for ($($t,)*) {
+            fn valtypes() -> Vec<ValType> {
+                vec![$($t::VAL_TYPE),*]
+            }
+            #[allow(unused_variables)]
+            fn push(self, stack: &mut Stack) {
+                let ($($t,)*) = self;
+                $(stack.push($t);)*
+            }
+        }
+
+        // Values are read from bottom to top since results are pushed in order
+        impl<$($t: AsValType + StackAccess),*> WasmResults for ($($t,)*) {
+            fn valtypes() -> Vec<ValType> {
+                vec![$($t::VAL_TYPE),*]
+            }
+            #[allow(unused_variables, unused_mut, unused_assignments)]
+            fn pop(stack: &mut Stack) -> Self {
+                let base = stack.len() - <Self as WasmResults>::valtypes().len();
+                let mut idx = base;
+                let ret = ($({
+                    let v = $t::from_slot(stack.read_slot(idx));
+                    idx += 1;
+                    v
+                },)*);
+                stack.unwind(base, 0);
+                ret
+            }
+        }
+    };
+}
+
+impl_tuple!();
+impl_tuple!(A);
+impl_tuple!(A B);
+impl_tuple!(A B C);
+impl_tuple!(A B C D);
+impl_tuple!(A B C D E);
+impl_tuple!(A B C D E F);
+impl_tuple!(A B C D E F G);
+impl_tuple!(A B C D E F G H);
+
+/// Handle of exported function whose signature was checked by `Machine::get_typed_func`. Arguments
+/// and results are passed as Rust values without checking their types on each call.
+///
+/// The handle must be called with the machine which created it.
+#[cfg_attr(test, derive(Debug))]
+pub struct TypedFunc<P, R> {
+    funcaddr: u32,
+    marker: PhantomData<fn(P) -> R>,
+}
+
+impl<P, R> Clone for TypedFunc<P, R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P, R> Copy for TypedFunc<P, R> {}
+
+impl<P: WasmParams, R: WasmResults> TypedFunc<P, R> {
+    pub(crate) fn new(funcaddr: u32) -> Self {
+        Self {
+            funcaddr,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn call<I: Importer>(&self, machine: &mut Machine<'_, '_, I>, params: P) -> Result<R> {
+        machine.invoke_typed(self.funcaddr, params)
+    }
+}
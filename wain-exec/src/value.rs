@@ -10,6 +10,11 @@ pub enum Value {
     I64(i64),
     F32(f32),
     F64(f64),
+    V128(i128),
+    // Address of function in store, or null
+    FuncRef(Option<u32>),
+    // Opaque value given by embedder, or null
+    ExternRef(Option<u32>),
 }
 
 impl Value {
@@ -19,6 +24,9 @@ impl Value {
             Value::I64(_) => ValType::I64,
             Value::F32(_) => ValType::F32,
             Value::F64(_) => ValType::F64,
+            Value::V128(_) => ValType::V128,
+            Value::FuncRef(_) => ValType::FuncRef,
+            Value::ExternRef(_) => ValType::ExternRef,
         }
     }
 }
@@ -30,6 +38,11 @@ impl fmt::Display for Value {
             Value::I64(v) => write!(f, "{}i64", v),
             Value::F32(v) => write!(f, "{}f32", v),
             Value::F64(v) => write!(f, "{}f64", v),
+            Value::V128(v) => write!(f, "0x{:032x}v128", v),
+            Value::FuncRef(Some(a)) => write!(f, "ref.func {}", a),
+            Value::FuncRef(None) => f.write_str("ref.null func"),
+            Value::ExternRef(Some(a)) => write!(f, "ref.extern {}", a),
+            Value::ExternRef(None) => f.write_str("ref.null extern"),
         }
     }
 }
@@ -76,6 +89,7 @@ impl_le_rw!(i32);
 impl_le_rw!(i64);
 impl_le_rw!(f32);
 impl_le_rw!(f64);
+impl_le_rw!(i128);
 // unsigned integers for load/store instructions
 impl LittleEndian for u8 {
     fn read(buf: &[u8], addr: usize) -> Self {
@@ -87,3 +101,15 @@ impl LittleEndian for u8 {
 }
 impl_le_rw!(u16);
 impl_le_rw!(u32);
+impl_le_rw!(u64);
+// References are stored as 64bit integers. 0 means null and other values are offset by 1
+impl LittleEndian for Option<u32> {
+    fn read(buf: &[u8], addr: usize) -> Self {
+        let v = u64::from_le_bytes(read_bytes(buf, addr));
+        v.checked_sub(1).map(|a| a as u32)
+    }
+    fn write(buf: &mut [u8], addr: usize, v: Self) {
+        let v = v.map_or(0, |a| a as u64 + 1);
+        write_bytes(buf, addr, &v.to_le_bytes());
+    }
+}
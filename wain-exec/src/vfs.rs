@@ -0,0 +1,427 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Options to open a file. Each field has the same meaning as the method of `std::fs::OpenOptions`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct OpenOptions {
+    pub read: bool,
+    pub write: bool,
+    pub append: bool,
+    pub create: bool,
+    pub create_new: bool,
+    pub truncate: bool,
+}
+
+/// Filesystem accessed by guest through importer. Paths given to the methods are already checked not
+/// to go out of directories exposed to guest.
+pub trait FileSystem {
+    type File: Read + Write + Seek;
+
+    fn open(&mut self, path: &Path, options: &OpenOptions) -> io::Result<Self::File>;
+    fn is_dir(&self, path: &Path) -> bool;
+    /// Resolve symbolic links in the path. This returns an error when the path does not exist.
+    /// Filesystems which have no symbolic link don't need to implement this.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(path.to_path_buf())
+    }
+    /// Check the path is a symbolic link without following it. Filesystems which have no symbolic
+    /// link don't need to implement this.
+    fn is_symlink(&self, _path: &Path) -> bool {
+        false
+    }
+}
+
+/// Filesystem of host. Directories exposed to guest are mapped to host directories and files are
+/// accessed with `std::fs`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct HostFs;
+
+impl FileSystem for HostFs {
+    type File = fs::File;
+
+    fn open(&mut self, path: &Path, options: &OpenOptions) -> io::Result<fs::File> {
+        fs::OpenOptions::new()
+            .read(options.read)
+            .write(options.write)
+            .append(options.append)
+            .create(options.create)
+            .create_new(options.create_new)
+            .truncate(options.truncate)
+            .open(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        path.is_symlink()
+    }
+}
+
+#[derive(Default)]
+struct MemoryFsState {
+    files: HashMap<PathBuf, Vec<u8>>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl MemoryFsState {
+    fn is_dir(&self, path: &Path) -> bool {
+        // Root directory always exists
+        path.parent().is_none() || self.dirs.contains(path)
+    }
+
+    fn create_dir_all(&mut self, path: &Path) {
+        for dir in path.ancestors() {
+            if !self.dirs.insert(dir.to_path_buf()) {
+                break;
+            }
+        }
+    }
+}
+
+// Paths like 'a//b' or './a' are normalized so that they can be used as keys
+fn normalize(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+/// Filesystem on memory. It does not touch the real disk so it is useful for running guest
+/// deterministically in tests. Cloned `MemoryFs` shares the same files so files written by guest can
+/// be read after moving the filesystem to importer.
+#[derive(Clone, Default)]
+pub struct MemoryFs {
+    state: Arc<Mutex<MemoryFsState>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MemoryFsState> {
+        // Panic while holding the lock does not break the state
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Create a file with the content. Parent directories are also created. When the file already
+    /// exists, it is overwritten.
+    pub fn write_file(&self, path: impl AsRef<Path>, content: impl Into<Vec<u8>>) {
+        let path = normalize(path.as_ref());
+        let mut state = self.state();
+        if let Some(parent) = path.parent() {
+            state.create_dir_all(parent);
+        }
+        state.files.insert(path, content.into());
+    }
+
+    /// Returns the content of the file. `None` means the file does not exist.
+    pub fn read_file(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        self.state().files.get(&normalize(path.as_ref())).cloned()
+    }
+
+    /// Create a directory. Parent directories are also created.
+    pub fn create_dir(&self, path: impl AsRef<Path>) {
+        self.state().create_dir_all(&normalize(path.as_ref()));
+    }
+}
+
+impl FileSystem for MemoryFs {
+    type File = MemoryFile;
+
+    fn open(&mut self, path: &Path, options: &OpenOptions) -> io::Result<MemoryFile> {
+        fn error(kind: io::ErrorKind, msg: &str) -> io::Result<MemoryFile> {
+            Err(io::Error::new(kind, msg))
+        }
+
+        // Same restrictions as std::fs::OpenOptions
+        let writable = options.write || options.append;
+        if !options.read && !writable {
+            return error(
+                io::ErrorKind::InvalidInput,
+                "file must be opened for read or write",
+            );
+        }
+        if ((options.create || options.create_new) && !writable)
+            || (options.truncate && !options.write)
+        {
+            return error(io::ErrorKind::InvalidInput, "file must be opened for write");
+        }
+
+        let path = normalize(path);
+        let mut state = self.state();
+        if state.is_dir(&path) {
+            return error(io::ErrorKind::Other, "is a directory");
+        }
+        match state.files.get_mut(&path) {
+            Some(_) if options.create_new => {
+                return error(io::ErrorKind::AlreadyExists, "file already exists")
+            }
+            Some(content) if options.truncate => content.clear(),
+            Some(_) => {}
+            None if options.create || options.create_new => {
+                let parent_exists = path.parent().map(|p| state.is_dir(p)).unwrap_or(false);
+                if !parent_exists {
+                    return error(io::ErrorKind::NotFound, "parent directory does not exist");
+                }
+                state.files.insert(path.clone(), vec![]);
+            }
+            None => return error(io::ErrorKind::NotFound, "file does not exist"),
+        }
+
+        Ok(MemoryFile {
+            state: self.state.clone(),
+            path,
+            pos: 0,
+            read: options.read,
+            write: writable,
+            append: options.append,
+        })
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.state().is_dir(&normalize(path))
+    }
+}
+
+// Guest can seek to any position before writing. Files are limited to the size which 32-bit guests
+// can handle so that writing at a huge position fails instead of exhausting host memory
+const MAX_MEMORY_FILE_SIZE: u64 = u32::MAX as u64;
+
+/// File opened from `MemoryFs`.
+pub struct MemoryFile {
+    state: Arc<Mutex<MemoryFsState>>,
+    path: PathBuf,
+    pos: u64,
+    read: bool,
+    write: bool,
+    append: bool,
+}
+
+impl MemoryFile {
+    fn with_content<T>(&self, f: impl FnOnce(&mut Vec<u8>) -> T) -> T {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        f(state.files.entry(self.path.clone()).or_default())
+    }
+}
+
+impl Read for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.read {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "file is not opened for read",
+            ));
+        }
+        let pos = self.pos as usize;
+        let size = self.with_content(|content| {
+            let src = content.get(pos..).unwrap_or(&[]);
+            let size = src.len().min(buf.len());
+            buf[..size].copy_from_slice(&src[..size]);
+            size
+        });
+        self.pos += size as u64;
+        Ok(size)
+    }
+}
+
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.write {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "file is not opened for write",
+            ));
+        }
+        let (pos, append) = (self.pos, self.append);
+        self.pos = self.with_content(|content| {
+            let start = if append { content.len() as u64 } else { pos };
+            let end = match start.checked_add(buf.len() as u64) {
+                Some(end) if end <= MAX_MEMORY_FILE_SIZE => end,
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "file size would exceed the limit of in-memory file",
+                    ))
+                }
+            };
+            let (start, end) = (start as usize, end as usize);
+            if content.len() < end {
+                content.resize(end, 0);
+            }
+            content[start..end].copy_from_slice(buf);
+            Ok(end as u64)
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                self.pos = offset;
+                return Ok(offset);
+            }
+            SeekFrom::Current(offset) => (self.pos, offset),
+            SeekFrom::End(offset) => (self.with_content(|c| c.len() as u64), offset),
+        };
+        let pos = if offset < 0 {
+            base.checked_sub(offset.unsigned_abs())
+        } else {
+            base.checked_add(offset as u64)
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::DefaultImporter;
+    use crate::machine::{Machine, Run};
+    use crate::test_util::parse_module;
+    use std::env;
+
+    fn options(f: impl FnOnce(&mut OpenOptions)) -> OpenOptions {
+        let mut opts = OpenOptions::default();
+        f(&mut opts);
+        opts
+    }
+
+    #[test]
+    fn memory_fs_files() {
+        let mut fs = MemoryFs::new();
+        fs.write_file("/a/b.txt", "hello");
+        fs.create_dir("/c");
+        assert!(fs.is_dir(Path::new("/")));
+        assert!(fs.is_dir(Path::new("/a")));
+        assert!(fs.is_dir(Path::new("/c/")));
+        assert!(!fs.is_dir(Path::new("/a/b.txt")));
+
+        let read = options(|o| o.read = true);
+        let mut f = fs.open(Path::new("/a//b.txt"), &read).unwrap();
+        let mut s = String::new();
+        f.read_to_string(&mut s).unwrap();
+        assert_eq!(s, "hello");
+        assert!(f.write(b"!").is_err());
+
+        // Write and seek
+        let write = options(|o| o.write = true);
+        let mut f = fs.open(Path::new("/a/b.txt"), &write).unwrap();
+        f.write_all(b"J").unwrap();
+        assert_eq!(f.seek(SeekFrom::End(2)).unwrap(), 7);
+        f.write_all(b"!").unwrap();
+        assert_eq!(f.seek(SeekFrom::Current(-3)).unwrap(), 5);
+        assert!(f.seek(SeekFrom::Current(-6)).is_err());
+        assert_eq!(fs.read_file("/a/b.txt").unwrap(), b"Jello\0\0!");
+
+        // Writing at a huge position fails without growing the file
+        for &pos in &[u64::MAX, MAX_MEMORY_FILE_SIZE] {
+            f.seek(SeekFrom::Start(pos)).unwrap();
+            let err = f.write(b"!").unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+        assert_eq!(fs.read_file("/a/b.txt").unwrap(), b"Jello\0\0!");
+
+        let append = options(|o| o.append = true);
+        let mut f = fs.open(Path::new("/a/b.txt"), &append).unwrap();
+        f.write_all(b"?").unwrap();
+        assert_eq!(fs.read_file("/a/b.txt").unwrap(), b"Jello\0\0!?");
+
+        let truncate = options(|o| {
+            o.write = true;
+            o.truncate = true;
+        });
+        fs.open(Path::new("/a/b.txt"), &truncate).unwrap();
+        assert_eq!(fs.read_file("/a/b.txt").unwrap(), b"");
+
+        // Errors on opening files
+        let err = |path: &str, opts: &OpenOptions| {
+            let mut fs = fs.clone();
+            match fs.open(Path::new(path), opts) {
+                Ok(_) => panic!("opening {} succeeded", path),
+                Err(err) => err.kind(),
+            }
+        };
+        let create = options(|o| {
+            o.write = true;
+            o.create = true;
+        });
+        let create_new = options(|o| {
+            o.write = true;
+            o.create_new = true;
+        });
+        assert_eq!(err("/x.txt", &read), io::ErrorKind::NotFound);
+        assert_eq!(err("/x/y.txt", &create), io::ErrorKind::NotFound);
+        assert_eq!(err("/a/b.txt", &create_new), io::ErrorKind::AlreadyExists);
+        assert_eq!(err("/a", &read), io::ErrorKind::Other);
+        assert_eq!(
+            err("/a/b.txt", &OpenOptions::default()),
+            io::ErrorKind::InvalidInput
+        );
+        let create_readonly = options(|o| {
+            o.read = true;
+            o.create = true;
+        });
+        assert_eq!(err("/d.txt", &create_readonly), io::ErrorKind::InvalidInput);
+
+        fs.open(Path::new("/c/d.txt"), &create_new).unwrap();
+        assert_eq!(fs.read_file("/c/d.txt").unwrap(), b"");
+    }
+
+    #[test]
+    fn guessing_game_with_prepared_input() {
+        let mut path = env::current_dir().unwrap();
+        path.pop();
+        path.push("examples");
+        path.push("guessing_game.wat");
+        let source = std::fs::read_to_string(path).unwrap();
+        let root = parse_module(&source);
+
+        let mut fs = MemoryFs::new();
+        fs.write_file("/input.txt", "50\n80\n75\n");
+        let read = options(|o| o.read = true);
+        let create = options(|o| {
+            o.write = true;
+            o.create = true;
+        });
+        let stdin = fs.open(Path::new("/input.txt"), &read).unwrap();
+        let stdout = fs.open(Path::new("/output.txt"), &create).unwrap();
+
+        let importer = DefaultImporter::with_stdio(stdin, stdout);
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+        assert_eq!(machine.execute().unwrap(), Run::Success);
+
+        let output = fs.read_file("/output.txt").unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Guess the number!\n\n\
+             Please input your guess in 1..100\n\
+             Too small!\n\n\
+             Please input your guess in 1..100\n\
+             Too big!\n\n\
+             Please input your guess in 1..100\n\
+             \nYou win! Tries: 3\n",
+        );
+    }
+}
@@ -0,0 +1,1074 @@
+// WASI snapshot_preview1 host functions
+// https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md
+use crate::import::{check_func_signature, ImportInvalidError, ImportInvokeError, Importer};
+use crate::memory::Memories;
+use crate::stack::Stack;
+use crate::vfs::{FileSystem, HostFs, OpenOptions};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use wain_ast::ValType;
+
+pub const WASI_MODULE_NAME: &str = "wasi_snapshot_preview1";
+
+type Errno = u16;
+
+// https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-errno-variant
+mod errno {
+    use super::Errno;
+    pub const SUCCESS: Errno = 0;
+    pub const ACCES: Errno = 2;
+    pub const BADF: Errno = 8;
+    pub const EXIST: Errno = 20;
+    pub const FAULT: Errno = 21;
+    pub const INVAL: Errno = 28;
+    pub const IO: Errno = 29;
+    pub const ISDIR: Errno = 31;
+    pub const NOENT: Errno = 44;
+    pub const NOTDIR: Errno = 54;
+    pub const SPIPE: Errno = 70;
+    pub const NOTCAPABLE: Errno = 76;
+}
+
+// https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-filetype-variant
+const FILETYPE_CHARACTER_DEVICE: u8 = 2;
+const FILETYPE_DIRECTORY: u8 = 3;
+const FILETYPE_REGULAR_FILE: u8 = 4;
+
+// https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-oflags-record
+const OFLAGS_CREAT: u32 = 1 << 0;
+const OFLAGS_DIRECTORY: u32 = 1 << 1;
+const OFLAGS_EXCL: u32 = 1 << 2;
+const OFLAGS_TRUNC: u32 = 1 << 3;
+
+const FDFLAGS_APPEND: u32 = 1 << 0;
+
+// https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-rights-record
+const RIGHTS_FD_READ: u64 = 1 << 1;
+const RIGHTS_FD_WRITE: u64 = 1 << 6;
+
+enum Fd<F> {
+    Stdin,
+    Stdout,
+    Stderr,
+    File(F),
+    // `preopen` is the path exposed to guest for preopened directories
+    Dir {
+        path: PathBuf,
+        preopen: Option<String>,
+    },
+}
+
+fn io_errno(err: &io::Error) -> Errno {
+    match err.kind() {
+        io::ErrorKind::NotFound => errno::NOENT,
+        io::ErrorKind::PermissionDenied => errno::ACCES,
+        io::ErrorKind::AlreadyExists => errno::EXIST,
+        io::ErrorKind::InvalidInput => errno::INVAL,
+        _ => errno::IO,
+    }
+}
+
+fn add_offset(ptr: u32, offset: u32) -> Result<u32, Errno> {
+    ptr.checked_add(offset).ok_or(errno::FAULT)
+}
+
+fn bytes(mem: &[u8], ptr: u32, len: u32) -> Result<&[u8], Errno> {
+    let end = add_offset(ptr, len)?;
+    mem.get(ptr as usize..end as usize).ok_or(errno::FAULT)
+}
+
+fn bytes_mut(mem: &mut [u8], ptr: u32, len: u32) -> Result<&mut [u8], Errno> {
+    let end = add_offset(ptr, len)?;
+    mem.get_mut(ptr as usize..end as usize).ok_or(errno::FAULT)
+}
+
+fn read_u32(mem: &[u8], ptr: u32) -> Result<u32, Errno> {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(bytes(mem, ptr, 4)?);
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn write_u32(mem: &mut [u8], ptr: u32, v: u32) -> Result<(), Errno> {
+    bytes_mut(mem, ptr, 4)?.copy_from_slice(&v.to_le_bytes());
+    Ok(())
+}
+
+fn write_u64(mem: &mut [u8], ptr: u32, v: u64) -> Result<(), Errno> {
+    bytes_mut(mem, ptr, 8)?.copy_from_slice(&v.to_le_bytes());
+    Ok(())
+}
+
+// Read array of `ciovec` or `iovec` records as pairs of (buf, buf_len). The whole array is checked
+// to be in memory before reading since `len` is given by guest. Total size of the buffers must fit
+// in u32 since the number of read or written bytes is returned to guest as u32
+fn iovecs(mem: &[u8], ptr: u32, len: u32) -> Result<Vec<(u32, u32)>, Errno> {
+    bytes(mem, ptr, len.checked_mul(8).ok_or(errno::FAULT)?)?;
+    let mut total = 0u32;
+    (0..len)
+        .map(|i| {
+            let at = ptr + i * 8;
+            let buf_len = read_u32(mem, at + 4)?;
+            total = total.checked_add(buf_len).ok_or(errno::INVAL)?;
+            Ok((read_u32(mem, at)?, buf_len))
+        })
+        .collect()
+}
+
+// Layout of args_get and environ_get. Each string is terminated with NUL
+fn strings_sizes(strings: &[String]) -> (u32, u32) {
+    let size = strings.iter().map(|s| s.len() + 1).sum::<usize>();
+    (strings.len() as u32, size as u32)
+}
+
+fn write_strings(mem: &mut [u8], strings: &[String], ptrs: u32, buf: u32) -> Result<(), Errno> {
+    let mut offset = buf;
+    for (i, s) in strings.iter().enumerate() {
+        write_u32(mem, add_offset(ptrs, i as u32 * 4)?, offset)?;
+        let len = s.len() as u32 + 1;
+        let dest = bytes_mut(mem, offset, len)?;
+        dest[..s.len()].copy_from_slice(s.as_bytes());
+        dest[s.len()] = 0;
+        offset = add_offset(offset, len)?;
+    }
+    Ok(())
+}
+
+// Resolve a guest path relative to a directory. Absolute paths and '..' which goes out of the
+// directory are not permitted
+fn resolve_path<F: FileSystem>(fs: &F, dir: &Path, path: &str) -> Result<PathBuf, Errno> {
+    let mut resolved = dir.to_path_buf();
+    let mut depth = 0;
+    for comp in Path::new(path).components() {
+        match comp {
+            Component::Normal(c) => {
+                resolved.push(c);
+                depth += 1;
+            }
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => {
+                resolved.pop();
+                depth -= 1;
+            }
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(errno::NOTCAPABLE)
+            }
+        }
+    }
+
+    let (parent, name) = match (resolved.parent(), resolved.file_name()) {
+        (Some(parent), Some(name)) if depth > 0 => (parent, name),
+        _ => return Ok(resolved), // The directory itself
+    };
+
+    // Symbolic links in the path may point to outside of the directory. The last component is not
+    // followed since the link may be dangling and opening it with O_CREAT would create its target
+    if fs.is_symlink(&resolved) {
+        return Err(errno::NOTCAPABLE);
+    }
+    let root = fs.canonicalize(dir).map_err(|e| io_errno(&e))?;
+    let parent = fs.canonicalize(parent).map_err(|e| io_errno(&e))?;
+    if !parent.starts_with(&root) {
+        return Err(errno::NOTCAPABLE);
+    }
+    Ok(parent.join(name))
+}
+
+/// Importer which implements WASI snapshot_preview1 functions imported from
+/// 'wasi_snapshot_preview1' module.
+///
+/// Guest can access filesystem only under directories registered by `preopen_dir()`. Without
+/// preopened directories, only standard I/O is available. Memory index 0 of the calling instance is
+/// used as the memory of guest.
+///
+/// Files are accessed through `FileSystem`. `with_stdio()` uses the host filesystem. `with_fs()` can
+/// use other filesystems like `MemoryFs`.
+pub struct WasiImporter<R: Read, W: Write, E: Write, F: FileSystem = HostFs> {
+    stdin: R,
+    stdout: W,
+    stderr: E,
+    fs: F,
+    args: Vec<String>,
+    env: Vec<String>,
+    fds: Vec<Option<Fd<F::File>>>,
+    started: Instant,
+    random: RandomState,
+    random_count: u64,
+}
+
+impl<R: Read, W: Write, E: Write, F: FileSystem> Drop for WasiImporter<R, W, E, F> {
+    fn drop(&mut self) {
+        let _ = self.stdout.flush();
+        let _ = self.stderr.flush();
+    }
+}
+
+impl<R: Read, W: Write, E: Write> WasiImporter<R, W, E> {
+    pub fn with_stdio(stdin: R, stdout: W, stderr: E) -> Self {
+        Self::with_fs(stdin, stdout, stderr, HostFs)
+    }
+}
+
+impl<R: Read, W: Write, E: Write, F: FileSystem> WasiImporter<R, W, E, F> {
+    pub fn with_fs(stdin: R, stdout: W, stderr: E, fs: F) -> Self {
+        Self {
+            stdin,
+            stdout,
+            stderr,
+            fs,
+            args: vec![],
+            env: vec![],
+            fds: vec![Some(Fd::Stdin), Some(Fd::Stdout), Some(Fd::Stderr)],
+            started: Instant::now(),
+            random: RandomState::new(),
+            random_count: 0,
+        }
+    }
+
+    /// Add a command line argument. The first argument is usually the program name.
+    pub fn arg(&mut self, arg: impl Into<String>) -> &mut Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Add an environment variable.
+    pub fn env(&mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> &mut Self {
+        self.env
+            .push(format!("{}={}", key.as_ref(), value.as_ref()));
+        self
+    }
+
+    /// Expose directory `path` of the filesystem to guest as `guest_path`. With the host filesystem,
+    /// `path` is a host directory. Guest can open files only under preopened directories so `path`
+    /// works as a sandbox root.
+    pub fn preopen_dir(
+        &mut self,
+        guest_path: impl Into<String>,
+        path: impl Into<PathBuf>,
+    ) -> &mut Self {
+        self.fds.push(Some(Fd::Dir {
+            path: path.into(),
+            preopen: Some(guest_path.into()),
+        }));
+        self
+    }
+
+    fn fd(&mut self, fd: u32) -> Result<&mut Fd<F::File>, Errno> {
+        match self.fds.get_mut(fd as usize) {
+            Some(Some(fd)) => Ok(fd),
+            _ => Err(errno::BADF),
+        }
+    }
+
+    fn new_fd(&mut self, fd: Fd<F::File>) -> u32 {
+        if let Some(idx) = self.fds.iter().position(Option::is_none) {
+            self.fds[idx] = Some(fd);
+            idx as u32
+        } else {
+            self.fds.push(Some(fd));
+            (self.fds.len() - 1) as u32
+        }
+    }
+
+    fn args_sizes_get(&self, mem: &mut [u8], argc: u32, size: u32) -> Result<(), Errno> {
+        let (len, bytes) = strings_sizes(&self.args);
+        write_u32(mem, argc, len)?;
+        write_u32(mem, size, bytes)
+    }
+
+    fn environ_sizes_get(&self, mem: &mut [u8], count: u32, size: u32) -> Result<(), Errno> {
+        let (len, bytes) = strings_sizes(&self.env);
+        write_u32(mem, count, len)?;
+        write_u32(mem, size, bytes)
+    }
+
+    // https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-clockid-variant
+    // CPU time clocks are approximated with the time elapsed since the importer was created.
+    fn clock_time(&self, id: u32) -> Result<u64, Errno> {
+        match id {
+            0 => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH);
+                now.map(|d| d.as_nanos() as u64).map_err(|_| errno::IO)
+            }
+            1..=3 => Ok(self.started.elapsed().as_nanos() as u64),
+            _ => Err(errno::INVAL),
+        }
+    }
+
+    fn random_get(&mut self, mem: &mut [u8], buf: u32, len: u32) -> Result<(), Errno> {
+        // Hasher built from RandomState is keyed with random seed from OS
+        for chunk in bytes_mut(mem, buf, len)?.chunks_mut(8) {
+            let mut hasher = self.random.build_hasher();
+            hasher.write_u64(self.random_count);
+            self.random_count += 1;
+            let bytes = hasher.finish().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+        Ok(())
+    }
+
+    fn fd_write(
+        &mut self,
+        mem: &mut [u8],
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        nwritten: u32,
+    ) -> Result<(), Errno> {
+        let vecs = iovecs(mem, iovs, iovs_len)?;
+        let writer: &mut dyn Write = match self.fds.get_mut(fd as usize) {
+            Some(Some(Fd::Stdout)) => &mut self.stdout,
+            Some(Some(Fd::Stderr)) => &mut self.stderr,
+            Some(Some(Fd::File(f))) => f,
+            _ => return Err(errno::BADF),
+        };
+        let mut total = 0u32;
+        for (buf, len) in vecs {
+            let buf = bytes(mem, buf, len)?;
+            writer.write_all(buf).map_err(|e| io_errno(&e))?;
+            total = total.checked_add(len).ok_or(errno::INVAL)?;
+        }
+        write_u32(mem, nwritten, total)
+    }
+
+    fn fd_read(
+        &mut self,
+        mem: &mut [u8],
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        nread: u32,
+    ) -> Result<(), Errno> {
+        let vecs = iovecs(mem, iovs, iovs_len)?;
+        let reader: &mut dyn Read = match self.fds.get_mut(fd as usize) {
+            Some(Some(Fd::Stdin)) => &mut self.stdin,
+            Some(Some(Fd::File(f))) => f,
+            Some(Some(Fd::Dir { .. })) => return Err(errno::ISDIR),
+            _ => return Err(errno::BADF),
+        };
+        let mut total = 0u32;
+        for (buf, len) in vecs {
+            let buf = bytes_mut(mem, buf, len)?;
+            let size = reader.read(buf).map_err(|e| io_errno(&e))?;
+            total = total.checked_add(size as u32).ok_or(errno::INVAL)?;
+            if size < buf.len() {
+                break;
+            }
+        }
+        write_u32(mem, nread, total)
+    }
+
+    fn fd_seek(
+        &mut self,
+        mem: &mut [u8],
+        fd: u32,
+        offset: i64,
+        whence: u32,
+        newoffset: u32,
+    ) -> Result<(), Errno> {
+        let file = match self.fd(fd)? {
+            Fd::File(f) => f,
+            Fd::Dir { .. } => return Err(errno::BADF),
+            _ => return Err(errno::SPIPE),
+        };
+        let pos = match whence {
+            0 if offset >= 0 => SeekFrom::Start(offset as u64),
+            1 => SeekFrom::Current(offset),
+            2 => SeekFrom::End(offset),
+            _ => return Err(errno::INVAL),
+        };
+        let pos = file.seek(pos).map_err(|e| io_errno(&e))?;
+        write_u64(mem, newoffset, pos)
+    }
+
+    fn fd_close(&mut self, fd: u32) -> Result<(), Errno> {
+        self.fd(fd)?;
+        self.fds[fd as usize] = None;
+        Ok(())
+    }
+
+    // https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-fdstat-record
+    fn fd_fdstat_get(&mut self, mem: &mut [u8], fd: u32, buf: u32) -> Result<(), Errno> {
+        let filetype = match self.fd(fd)? {
+            Fd::Stdin | Fd::Stdout | Fd::Stderr => FILETYPE_CHARACTER_DEVICE,
+            Fd::File(_) => FILETYPE_REGULAR_FILE,
+            Fd::Dir { .. } => FILETYPE_DIRECTORY,
+        };
+        let stat = bytes_mut(mem, buf, 24)?;
+        stat.iter_mut().for_each(|b| *b = 0);
+        stat[0] = filetype;
+        // All rights are granted. Access is restricted by preopened directories instead
+        stat[8..].iter_mut().for_each(|b| *b = 0xff);
+        Ok(())
+    }
+
+    // https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-prestat-variant
+    fn fd_prestat_get(&mut self, mem: &mut [u8], fd: u32, buf: u32) -> Result<(), Errno> {
+        let len = match self.fd(fd)? {
+            Fd::Dir {
+                preopen: Some(name),
+                ..
+            } => name.len() as u32,
+            _ => return Err(errno::BADF),
+        };
+        write_u32(mem, buf, 0)?; // Tag 0 is 'dir'
+        write_u32(mem, add_offset(buf, 4)?, len)
+    }
+
+    fn fd_prestat_dir_name(
+        &mut self,
+        mem: &mut [u8],
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    ) -> Result<(), Errno> {
+        let name = match self.fd(fd)? {
+            Fd::Dir {
+                preopen: Some(name),
+                ..
+            } => name,
+            _ => return Err(errno::BADF),
+        };
+        if (path_len as usize) < name.len() {
+            return Err(errno::INVAL);
+        }
+        bytes_mut(mem, path, name.len() as u32)?.copy_from_slice(name.as_bytes());
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn path_open(
+        &mut self,
+        mem: &mut [u8],
+        dirfd: u32,
+        path: u32,
+        path_len: u32,
+        oflags: u32,
+        rights: u64,
+        fdflags: u32,
+        opened: u32,
+    ) -> Result<(), Errno> {
+        let dir = match self.fd(dirfd)? {
+            Fd::Dir { path, .. } => path.clone(),
+            _ => return Err(errno::NOTDIR),
+        };
+        let path = std::str::from_utf8(bytes(mem, path, path_len)?).map_err(|_| errno::INVAL)?;
+        let path = resolve_path(&self.fs, &dir, path)?;
+        bytes_mut(mem, opened, 4)?; // Check the address before opening the file
+
+        let is_dir = self.fs.is_dir(&path);
+        let fd = if is_dir && oflags & (OFLAGS_CREAT | OFLAGS_TRUNC) == 0 {
+            Fd::Dir {
+                path,
+                preopen: None,
+            }
+        } else if oflags & OFLAGS_DIRECTORY != 0 {
+            if is_dir {
+                return Err(errno::NOTDIR);
+            }
+            // Check the file exists to know which error should be reported
+            let options = OpenOptions {
+                read: true,
+                ..OpenOptions::default()
+            };
+            return Err(match self.fs.open(&path, &options) {
+                Ok(_) => errno::NOTDIR,
+                Err(err) => io_errno(&err),
+            });
+        } else {
+            let read = rights & RIGHTS_FD_READ != 0;
+            let write = rights & RIGHTS_FD_WRITE != 0;
+            let append = fdflags & FDFLAGS_APPEND != 0;
+            let options = OpenOptions {
+                read: read || !write && !append,
+                write,
+                append,
+                create: oflags & OFLAGS_CREAT != 0,
+                create_new: oflags & OFLAGS_CREAT != 0 && oflags & OFLAGS_EXCL != 0,
+                truncate: oflags & OFLAGS_TRUNC != 0,
+            };
+            let file = self.fs.open(&path, &options).map_err(|e| {
+                if is_dir {
+                    errno::ISDIR
+                } else {
+                    io_errno(&e)
+                }
+            })?;
+            Fd::File(file)
+        };
+
+        let fd = self.new_fd(fd);
+        write_u32(mem, opened, fd)
+    }
+}
+
+impl<R: Read, W: Write, E: Write, F: FileSystem> Importer for WasiImporter<R, W, E, F> {
+    fn validate(
+        &self,
+        mod_name: &str,
+        name: &str,
+        params: &[ValType],
+        results: &[ValType],
+    ) -> Option<ImportInvalidError> {
+        use ValType::*;
+        if mod_name != WASI_MODULE_NAME {
+            return Some(ImportInvalidError::NotFound);
+        }
+        match name {
+            "args_get" | "args_sizes_get" | "environ_get" | "environ_sizes_get"
+            | "clock_res_get" | "random_get" | "fd_fdstat_get" | "fd_prestat_get" => {
+                check_func_signature(params, results, &[I32, I32], &[I32])
+            }
+            "clock_time_get" => check_func_signature(params, results, &[I32, I64, I32], &[I32]),
+            "fd_close" => check_func_signature(params, results, &[I32], &[I32]),
+            "fd_prestat_dir_name" => {
+                check_func_signature(params, results, &[I32, I32, I32], &[I32])
+            }
+            "fd_read" | "fd_write" => {
+                check_func_signature(params, results, &[I32, I32, I32, I32], &[I32])
+            }
+            "fd_seek" => check_func_signature(params, results, &[I32, I64, I32, I32], &[I32]),
+            "path_open" => check_func_signature(
+                params,
+                results,
+                &[I32, I32, I32, I32, I32, I64, I64, I32, I32],
+                &[I32],
+            ),
+            "proc_exit" => check_func_signature(params, results, &[I32], &[]),
+            _ => Some(ImportInvalidError::NotFound),
+        }
+    }
+
+    fn call(
+        &mut self,
+        _mod_name: &str,
+        name: &str,
+        stack: &mut Stack,
+        memories: &mut Memories<'_>,
+    ) -> Result<(), ImportInvokeError> {
+        if name == "proc_exit" {
+            let status: i32 = stack.pop();
+            return Err(ImportInvokeError::Exit { status });
+        }
+
+        let mem = match memories.get_mut(0) {
+            Some(mem) => mem.data_mut(),
+            None => {
+                return Err(ImportInvokeError::Fatal {
+                    message: format!("WASI function '{}' requires memory", name),
+                })
+            }
+        };
+
+        // All parameters of i32 are handled as unsigned integers
+        let pop = |stack: &mut Stack| stack.pop::<i32>() as u32;
+        let result = match name {
+            "args_get" => {
+                let buf = pop(stack);
+                write_strings(mem, &self.args, pop(stack), buf)
+            }
+            "args_sizes_get" => {
+                let size = pop(stack);
+                self.args_sizes_get(mem, pop(stack), size)
+            }
+            "environ_get" => {
+                let buf = pop(stack);
+                write_strings(mem, &self.env, pop(stack), buf)
+            }
+            "environ_sizes_get" => {
+                let size = pop(stack);
+                self.environ_sizes_get(mem, pop(stack), size)
+            }
+            "clock_res_get" => {
+                let res = pop(stack);
+                let id = pop(stack);
+                self.clock_time(id).and_then(|_| write_u64(mem, res, 1)) // Resolution is 1 nanosecond
+            }
+            "clock_time_get" => {
+                let time = pop(stack);
+                let _precision: i64 = stack.pop();
+                let id = pop(stack);
+                self.clock_time(id)
+                    .and_then(|now| write_u64(mem, time, now))
+            }
+            "random_get" => {
+                let len = pop(stack);
+                self.random_get(mem, pop(stack), len)
+            }
+            "fd_close" => self.fd_close(pop(stack)),
+            "fd_fdstat_get" => {
+                let buf = pop(stack);
+                self.fd_fdstat_get(mem, pop(stack), buf)
+            }
+            "fd_prestat_get" => {
+                let buf = pop(stack);
+                self.fd_prestat_get(mem, pop(stack), buf)
+            }
+            "fd_prestat_dir_name" => {
+                let len = pop(stack);
+                let path = pop(stack);
+                self.fd_prestat_dir_name(mem, pop(stack), path, len)
+            }
+            "fd_read" => {
+                let nread = pop(stack);
+                let len = pop(stack);
+                let iovs = pop(stack);
+                self.fd_read(mem, pop(stack), iovs, len, nread)
+            }
+            "fd_write" => {
+                let nwritten = pop(stack);
+                let len = pop(stack);
+                let iovs = pop(stack);
+                self.fd_write(mem, pop(stack), iovs, len, nwritten)
+            }
+            "fd_seek" => {
+                let newoffset = pop(stack);
+                let whence = pop(stack);
+                let offset: i64 = stack.pop();
+                let fd = pop(stack);
+                self.fd_seek(mem, fd, offset, whence, newoffset)
+            }
+            "path_open" => {
+                let opened = pop(stack);
+                let fdflags = pop(stack);
+                let _rights_inheriting: i64 = stack.pop();
+                let rights = stack.pop::<i64>() as u64;
+                let oflags = pop(stack);
+                let path_len = pop(stack);
+                let path = pop(stack);
+                let _dirflags = pop(stack);
+                let dirfd = pop(stack);
+                self.path_open(mem, dirfd, path, path_len, oflags, rights, fdflags, opened)
+            }
+            _ => unreachable!("fatal: invalid import function '{}'", name),
+        };
+
+        let errno = result.err().unwrap_or(errno::SUCCESS);
+        stack.push(errno as i32);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::{Invocation, Machine};
+    use crate::test_util::parse_module;
+    use crate::trap::TrapReason;
+    use crate::value::Value;
+    use crate::vfs::MemoryFs;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    // Imported functions are exported as-is so that tests can call them with arbitrary arguments
+    const SOURCE: &str = r#"
+        (module
+          (import "wasi_snapshot_preview1" "args_get" (func $args_get (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "environ_get" (func $environ_get (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "environ_sizes_get" (func $environ_sizes_get (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_prestat_get" (func $fd_prestat_get (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_prestat_dir_name" (func $fd_prestat_dir_name (param i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+          (memory (export "memory") 1)
+          ;; iovec {buf=16, buf_len=6} at 0 and {buf=22, buf_len=7} at 8
+          (data (i32.const 0) "\10\00\00\00\06\00\00\00\16\00\00\00\07\00\00\00")
+          (data (i32.const 16) "hello world!\n")
+          (data (i32.const 64) "input.txt")
+          (data (i32.const 80) "output.txt")
+          (data (i32.const 96) "../secret.txt")
+          (export "args_get" (func $args_get))
+          (export "args_sizes_get" (func $args_sizes_get))
+          (export "environ_get" (func $environ_get))
+          (export "environ_sizes_get" (func $environ_sizes_get))
+          (export "clock_time_get" (func $clock_time_get))
+          (export "random_get" (func $random_get))
+          (export "fd_read" (func $fd_read))
+          (export "fd_write" (func $fd_write))
+          (export "fd_seek" (func $fd_seek))
+          (export "fd_close" (func $fd_close))
+          (export "fd_prestat_get" (func $fd_prestat_get))
+          (export "fd_prestat_dir_name" (func $fd_prestat_dir_name))
+          (export "path_open" (func $path_open))
+          (export "proc_exit" (func $proc_exit)))
+    "#;
+
+    fn call<I: Importer>(machine: &mut Machine<'_, '_, I>, name: &str, args: &[Value]) -> i32 {
+        match machine.invoke(name, args).unwrap().as_slice() {
+            [Value::I32(errno)] => *errno,
+            _ => panic!("unexpected result of {}", name),
+        }
+    }
+
+    fn load_u32<I: Importer>(machine: &Machine<'_, '_, I>, addr: usize) -> u32 {
+        machine.memory().load(addr, 0).unwrap()
+    }
+
+    // Directory in temporary directory which is removed on drop
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("wain-wasi-{}-{}", name, process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("sandbox")).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn read_and_write_stdio() {
+        let root = parse_module(SOURCE);
+        let mut stdout = vec![];
+        let mut stderr = vec![];
+        {
+            let importer = WasiImporter::with_stdio(&b"abc"[..], &mut stdout, &mut stderr);
+            let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+            let args = [Value::I32(1), Value::I32(0), Value::I32(2), Value::I32(200)];
+            assert_eq!(call(&mut machine, "fd_write", &args), 0);
+            assert_eq!(load_u32(&machine, 200), 13);
+            let args = [Value::I32(2), Value::I32(8), Value::I32(1), Value::I32(200)];
+            assert_eq!(call(&mut machine, "fd_write", &args), 0);
+            assert_eq!(load_u32(&machine, 200), 7);
+
+            // Read into "hello " and "world!\n". Reading stops at end of input
+            let args = [Value::I32(0), Value::I32(0), Value::I32(2), Value::I32(200)];
+            assert_eq!(call(&mut machine, "fd_read", &args), 0);
+            assert_eq!(load_u32(&machine, 200), 3);
+            assert_eq!(&machine.memory().data()[16..29], b"abclo world!\n");
+
+            // Standard I/O is not seekable
+            let args = [Value::I32(0), Value::I64(0), Value::I32(0), Value::I32(200)];
+            assert_eq!(call(&mut machine, "fd_seek", &args), errno::SPIPE as i32);
+
+            // Closed or unknown file descriptor
+            assert_eq!(call(&mut machine, "fd_close", &[Value::I32(1)]), 0);
+            let args = [Value::I32(1), Value::I32(0), Value::I32(1), Value::I32(200)];
+            assert_eq!(call(&mut machine, "fd_write", &args), errno::BADF as i32);
+            assert_eq!(
+                call(&mut machine, "fd_close", &[Value::I32(3)]),
+                errno::BADF as i32
+            );
+
+            // Out of bounds iovec
+            let args = [
+                Value::I32(2),
+                Value::I32(65535),
+                Value::I32(1),
+                Value::I32(200),
+            ];
+            assert_eq!(call(&mut machine, "fd_write", &args), errno::FAULT as i32);
+            // Huge number of iovecs is rejected before allocating them
+            for &len in &[-1, 0x2000_0000, 8193] {
+                let args = [
+                    Value::I32(0),
+                    Value::I32(0),
+                    Value::I32(len),
+                    Value::I32(200),
+                ];
+                assert_eq!(call(&mut machine, "fd_read", &args), errno::FAULT as i32);
+            }
+            // Total size of iovecs overflows u32
+            let memory = machine.get_memory_mut("memory").unwrap();
+            memory.store(300, 0u32, 0).unwrap();
+            memory.store(304, u32::MAX, 0).unwrap();
+            memory.store(308, 0u32, 0).unwrap();
+            memory.store(312, 1u32, 0).unwrap();
+            for &name in &["fd_read", "fd_write"] {
+                let args = [
+                    Value::I32(2),
+                    Value::I32(300),
+                    Value::I32(2),
+                    Value::I32(200),
+                ];
+                assert_eq!(call(&mut machine, name, &args), errno::INVAL as i32);
+            }
+        }
+        assert_eq!(stdout, b"hello world!\n");
+        assert_eq!(stderr, b"world!\n");
+    }
+
+    #[test]
+    fn args_and_environ() {
+        let root = parse_module(SOURCE);
+        let mut importer = WasiImporter::with_stdio(io::empty(), io::sink(), io::sink());
+        importer.arg("prog").arg("-v").env("FOO", "bar");
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        assert_eq!(
+            call(
+                &mut machine,
+                "args_sizes_get",
+                &[Value::I32(200), Value::I32(204)]
+            ),
+            0
+        );
+        assert_eq!(load_u32(&machine, 200), 2);
+        assert_eq!(load_u32(&machine, 204), 8);
+        assert_eq!(
+            call(
+                &mut machine,
+                "args_get",
+                &[Value::I32(208), Value::I32(300)]
+            ),
+            0
+        );
+        assert_eq!(load_u32(&machine, 208), 300);
+        assert_eq!(load_u32(&machine, 212), 305);
+        assert_eq!(&machine.memory().data()[300..308], b"prog\0-v\0");
+
+        assert_eq!(
+            call(
+                &mut machine,
+                "environ_sizes_get",
+                &[Value::I32(200), Value::I32(204)]
+            ),
+            0
+        );
+        assert_eq!(load_u32(&machine, 200), 1);
+        assert_eq!(load_u32(&machine, 204), 8);
+        assert_eq!(
+            call(
+                &mut machine,
+                "environ_get",
+                &[Value::I32(208), Value::I32(400)]
+            ),
+            0
+        );
+        assert_eq!(load_u32(&machine, 208), 400);
+        assert_eq!(&machine.memory().data()[400..408], b"FOO=bar\0");
+
+        // Buffer is out of bounds of memory
+        let args = [Value::I32(208), Value::I32(65532)];
+        assert_eq!(call(&mut machine, "args_get", &args), errno::FAULT as i32);
+    }
+
+    #[test]
+    fn clock_and_random() {
+        let root = parse_module(SOURCE);
+        let importer = WasiImporter::with_stdio(io::empty(), io::sink(), io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        let args = [Value::I32(0), Value::I64(1), Value::I32(200)];
+        assert_eq!(call(&mut machine, "clock_time_get", &args), 0);
+        let now: u64 = machine.memory().load(200, 0).unwrap();
+        assert!(now > 0);
+        let args = [Value::I32(4), Value::I64(1), Value::I32(200)];
+        assert_eq!(
+            call(&mut machine, "clock_time_get", &args),
+            errno::INVAL as i32
+        );
+
+        assert_eq!(
+            call(
+                &mut machine,
+                "random_get",
+                &[Value::I32(1000), Value::I32(100)]
+            ),
+            0
+        );
+        assert!(machine.memory().data()[1000..1100].iter().any(|b| *b != 0));
+        let args = [Value::I32(65530), Value::I32(7)];
+        assert_eq!(call(&mut machine, "random_get", &args), errno::FAULT as i32);
+    }
+
+    // Run file operations in preopened directory '/data' which contains input.txt. Its parent
+    // directory contains secret.txt. The guest writes "conten" to output.txt
+    fn run_file_operations<F: FileSystem>(
+        importer: WasiImporter<io::Empty, io::Sink, io::Sink, F>,
+    ) {
+        let root = parse_module(SOURCE);
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+
+        // Preopened directories are enumerated from fd 3
+        assert_eq!(
+            call(
+                &mut machine,
+                "fd_prestat_get",
+                &[Value::I32(3), Value::I32(200)]
+            ),
+            0
+        );
+        assert_eq!(load_u32(&machine, 200), 0);
+        assert_eq!(load_u32(&machine, 204), 5);
+        let args = [Value::I32(3), Value::I32(208), Value::I32(5)];
+        assert_eq!(call(&mut machine, "fd_prestat_dir_name", &args), 0);
+        assert_eq!(&machine.memory().data()[208..213], b"/data");
+        let args = [Value::I32(4), Value::I32(200)];
+        assert_eq!(
+            call(&mut machine, "fd_prestat_get", &args),
+            errno::BADF as i32
+        );
+
+        let open =
+            |machine: &mut Machine<'_, '_, _>, path: i32, len: i32, oflags: i32, rights: i64| {
+                let args = [
+                    Value::I32(3),
+                    Value::I32(0),
+                    Value::I32(path),
+                    Value::I32(len),
+                    Value::I32(oflags),
+                    Value::I64(rights),
+                    Value::I64(0),
+                    Value::I32(0),
+                    Value::I32(200),
+                ];
+                call(machine, "path_open", &args)
+            };
+
+        // Read input.txt from offset 5
+        assert_eq!(open(&mut machine, 64, 9, 0, RIGHTS_FD_READ as i64), 0);
+        let fd = load_u32(&machine, 200) as i32;
+        assert_eq!(fd, 4);
+        let args = [
+            Value::I32(fd),
+            Value::I64(5),
+            Value::I32(0),
+            Value::I32(200),
+        ];
+        assert_eq!(call(&mut machine, "fd_seek", &args), 0);
+        let offset: u64 = machine.memory().load(200, 0).unwrap();
+        assert_eq!(offset, 5);
+        let args = [
+            Value::I32(fd),
+            Value::I32(0),
+            Value::I32(1),
+            Value::I32(200),
+        ];
+        assert_eq!(call(&mut machine, "fd_read", &args), 0);
+        assert_eq!(load_u32(&machine, 200), 6);
+        assert_eq!(&machine.memory().data()[16..22], b"conten");
+        assert_eq!(call(&mut machine, "fd_close", &[Value::I32(fd)]), 0);
+
+        // Create output.txt and write to it. Closed fd 4 is reused
+        let oflags = (OFLAGS_CREAT | OFLAGS_TRUNC) as i32;
+        assert_eq!(
+            open(&mut machine, 80, 10, oflags, RIGHTS_FD_WRITE as i64),
+            0
+        );
+        let fd = load_u32(&machine, 200) as i32;
+        assert_eq!(fd, 4);
+        let args = [
+            Value::I32(fd),
+            Value::I32(0),
+            Value::I32(1),
+            Value::I32(200),
+        ];
+        assert_eq!(call(&mut machine, "fd_write", &args), 0);
+        assert_eq!(call(&mut machine, "fd_close", &[Value::I32(fd)]), 0);
+
+        // Exclusive creation of existing file
+        let oflags = (OFLAGS_CREAT | OFLAGS_EXCL) as i32;
+        let ret = open(&mut machine, 80, 10, oflags, RIGHTS_FD_WRITE as i64);
+        assert_eq!(ret, errno::EXIST as i32);
+
+        // Files outside of the preopened directory cannot be opened
+        let ret = open(&mut machine, 96, 13, 0, RIGHTS_FD_READ as i64);
+        assert_eq!(ret, errno::NOTCAPABLE as i32);
+        let ret = open(&mut machine, 64, 4, 0, RIGHTS_FD_READ as i64);
+        assert_eq!(ret, errno::NOENT as i32);
+    }
+
+    #[test]
+    fn open_files_in_preopened_dir() {
+        let tmp = TempDir::new("preopen");
+        let sandbox = tmp.0.join("sandbox");
+        fs::write(sandbox.join("input.txt"), "file content").unwrap();
+        fs::write(tmp.0.join("secret.txt"), "secret").unwrap();
+
+        let mut importer = WasiImporter::with_stdio(io::empty(), io::sink(), io::sink());
+        importer.preopen_dir("/data", &sandbox);
+        run_file_operations(importer);
+        assert_eq!(fs::read(sandbox.join("output.txt")).unwrap(), b"conten");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_to_outside_of_preopened_dir() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = TempDir::new("symlink");
+        let sandbox = tmp.0.join("sandbox");
+        symlink(tmp.0.join("created.txt"), sandbox.join("dangling")).unwrap();
+        symlink(&tmp.0, sandbox.join("parent")).unwrap();
+
+        let mut importer = WasiImporter::with_stdio(io::empty(), io::sink(), io::sink());
+        importer.preopen_dir("/data", &sandbox);
+        let root = parse_module(SOURCE);
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+        let memory = machine.get_memory_mut("memory").unwrap();
+        memory.write_bytes(256, b"dangling").unwrap();
+        memory.write_bytes(272, b"parent/created.txt").unwrap();
+
+        for (path, len) in [(256, 8), (272, 18)].iter() {
+            let args = [
+                Value::I32(3),
+                Value::I32(0),
+                Value::I32(*path),
+                Value::I32(*len),
+                Value::I32(OFLAGS_CREAT as i32),
+                Value::I64(RIGHTS_FD_WRITE as i64),
+                Value::I64(0),
+                Value::I32(0),
+                Value::I32(200),
+            ];
+            let ret = call(&mut machine, "path_open", &args);
+            assert_eq!(ret, errno::NOTCAPABLE as i32);
+        }
+        assert!(!tmp.0.join("created.txt").exists());
+    }
+
+    #[test]
+    fn open_files_in_memory_fs() {
+        let fs = MemoryFs::new();
+        fs.write_file("/tmp/sandbox/input.txt", "file content");
+        fs.write_file("/tmp/secret.txt", "secret");
+
+        let mut importer = WasiImporter::with_fs(io::empty(), io::sink(), io::sink(), fs.clone());
+        importer.preopen_dir("/data", "/tmp/sandbox");
+        run_file_operations(importer);
+        assert_eq!(fs.read_file("/tmp/sandbox/output.txt").unwrap(), b"conten");
+        assert_eq!(
+            fs.read_file("/tmp/sandbox/input.txt").unwrap(),
+            b"file content"
+        );
+    }
+
+    #[test]
+    fn proc_exit() {
+        let root = parse_module(SOURCE);
+        let importer = WasiImporter::with_stdio(io::empty(), io::sink(), io::sink());
+        let mut machine = Machine::instantiate(&root.module, importer).unwrap();
+        let ret = machine.invoke_resumable("proc_exit", &[Value::I32(3)]);
+        assert_eq!(ret.unwrap(), Invocation::Exited(3));
+        let err = machine.invoke("proc_exit", &[Value::I32(0)]).unwrap_err();
+        assert!(matches!(err.reason, TrapReason::Exit { status: 0 }));
+    }
+
+    #[test]
+    fn unknown_function() {
+        let root = parse_module(
+            r#"(module (import "wasi_snapshot_preview1" "sock_accept" (func (param i32 i32 i32) (result i32))))"#,
+        );
+        let importer = WasiImporter::with_stdio(io::empty(), io::sink(), io::sink());
+        let err = Machine::instantiate(&root.module, importer).err().unwrap();
+        assert!(matches!(err.reason, TrapReason::UnknownImport { .. }));
+    }
+}
@@ -32,6 +32,14 @@ pub enum ErrorKind {
         num_codes: usize,
     },
     ExpectedEof(u8),
+    UnknownOpcode {
+        prefix: u8,
+        op: u32,
+    },
+    DataCountMismatch {
+        data_count: u32,
+        num_data: usize,
+    },
 }
 
 #[cfg_attr(test, derive(Debug))]
@@ -133,6 +141,17 @@ impl<'s> fmt::Display for Error<'s> {
                 "expected end of input but byte 0x{:02x} is still following",
                 b
             )?,
+            UnknownOpcode { prefix, op } => {
+                write!(f, "unknown opcode {} following prefix 0x{:02x}", op, prefix)?
+            }
+            DataCountMismatch {
+                data_count,
+                num_data,
+            } => write!(
+                f,
+                "data count section '{}' does not match to number of data segments '{}'",
+                data_count, num_data,
+            )?,
         }
         write!(f, " while parsing {}", self.when)?;
         describe_position(f, self.source, self.pos)
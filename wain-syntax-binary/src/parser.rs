@@ -22,6 +22,8 @@ fn section_name(id: u8) -> &'static str {
         9 => "element section",
         10 => "code section",
         11 => "data section",
+        12 => "data count section",
+        13 => "tag section",
         _ => unreachable!(),
     }
 }
@@ -254,6 +256,7 @@ impl<'s> Parse<'s> for Module<'s> {
         let mut tables = vec![];
         let mut memories = vec![];
         let mut globals = vec![];
+        let mut tags = vec![];
 
         // Import section
         if let [2, ..] = parser.input {
@@ -264,6 +267,7 @@ impl<'s> Parse<'s> for Module<'s> {
                     ImportDesc::Table(t) => tables.push(t),
                     ImportDesc::Memory(m) => memories.push(m),
                     ImportDesc::Global(g) => globals.push(g),
+                    ImportDesc::Tag(t) => tags.push(t),
                 }
             }
         }
@@ -302,6 +306,19 @@ impl<'s> Parse<'s> for Module<'s> {
 
         parser.ignore_custom_sections()?;
 
+        // Tag section
+        // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#tag-section
+        if let [13, ..] = parser.input {
+            let mut inner = parser.section_parser()?;
+            let vec = inner.parse_vec()?;
+            tags.reserve(vec.count);
+            for tag in vec {
+                tags.push(tag?);
+            }
+        }
+
+        parser.ignore_custom_sections()?;
+
         // Global section
         if let [0x06, ..] = parser.input {
             let mut inner = parser.section_parser()?;
@@ -333,6 +350,17 @@ impl<'s> Parse<'s> for Module<'s> {
 
         parser.ignore_custom_sections()?;
 
+        // Data count section
+        // https://webassembly.github.io/spec/core/binary/modules.html#data-count-section
+        let data_count = if let [12, ..] = parser.input {
+            let mut inner = parser.section_parser()?;
+            Some(inner.parse_int::<u32>()?)
+        } else {
+            None
+        };
+
+        parser.ignore_custom_sections()?;
+
         // Code section
         if let [10, ..] = parser.input {
             let mut inner = parser.section_parser()?;
@@ -358,7 +386,15 @@ impl<'s> Parse<'s> for Module<'s> {
 
         parser.ignore_custom_sections()?;
 
-        let data = parse_section(parser, 11)?;
+        let data: Vec<DataSegment<'s>> = parse_section(parser, 11)?;
+        if let Some(count) = data_count {
+            if count as usize != data.len() {
+                return Err(parser.error(ErrorKind::DataCountMismatch {
+                    data_count: count,
+                    num_data: data.len(),
+                }));
+            }
+        }
 
         parser.ignore_custom_sections()?;
 
@@ -377,6 +413,7 @@ impl<'s> Parse<'s> for Module<'s> {
             data,
             memories,
             globals,
+            tags,
             entrypoint,
         })
     }
@@ -423,7 +460,25 @@ impl<'s> Parse<'s> for ValType {
             0x7e => Ok(ValType::I64),
             0x7d => Ok(ValType::F32),
             0x7c => Ok(ValType::F64),
-            b => Err(parser.unexpected_byte([0x7f, 0x7e, 0x7d, 0x7c], b, "value type")),
+            0x7b => Ok(ValType::V128),
+            0x70 => Ok(ValType::FuncRef),
+            0x6f => Ok(ValType::ExternRef),
+            b => Err(parser.unexpected_byte(
+                [0x7f, 0x7e, 0x7d, 0x7c, 0x7b, 0x70, 0x6f],
+                b,
+                "value type",
+            )),
+        }
+    }
+}
+
+// https://webassembly.github.io/spec/core/binary/types.html#reference-types
+impl<'s> Parse<'s> for RefType {
+    fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
+        match parser.consume("reference type")? {
+            0x70 => Ok(RefType::FuncRef),
+            0x6f => Ok(RefType::ExternRef),
+            b => Err(parser.unexpected_byte([0x70, 0x6f], b, "reference type")),
         }
     }
 }
@@ -434,6 +489,7 @@ enum ImportDesc<'s> {
     Table(Table<'s>),
     Memory(Memory<'s>),
     Global(Global<'s>),
+    Tag(Tag<'s>),
 }
 impl<'s> Parse<'s> for ImportDesc<'s> {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
@@ -467,7 +523,19 @@ impl<'s> Parse<'s> for ImportDesc<'s> {
                     kind: GlobalKind::Import(import),
                 }))
             }
-            b => Err(parser.unexpected_byte([0x00, 0x01, 0x02, 0x03], b, "import description")),
+            0x04 => {
+                let TagType(idx) = parser.parse()?;
+                Ok(ImportDesc::Tag(Tag {
+                    start,
+                    idx,
+                    import: Some(import),
+                }))
+            }
+            b => Err(parser.unexpected_byte(
+                [0x00, 0x01, 0x02, 0x03, 0x04],
+                b,
+                "import description",
+            )),
         }
     }
 }
@@ -475,8 +543,8 @@ impl<'s> Parse<'s> for ImportDesc<'s> {
 // https://webassembly.github.io/spec/core/binary/types.html#binary-tabletype
 impl<'s> Parse<'s> for TableType {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
-        parser.parse_flag(0x70, "funcref of table type")?;
         Ok(TableType {
+            elem: parser.parse()?,
             limit: parser.parse()?,
         })
     }
@@ -486,18 +554,47 @@ impl<'s> Parse<'s> for TableType {
 impl<'s> Parse<'s> for Limits {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
         match parser.consume("limit")? {
-            0x00 => Ok(Limits::From(parser.parse_int()?)),
-            0x01 => Ok(Limits::Range(parser.parse_int()?, parser.parse_int()?)),
+            0x00 => Ok(Limits::From(parser.parse_int::<u32>()?.into())),
+            0x01 => {
+                let min = parser.parse_int::<u32>()?;
+                let max = parser.parse_int::<u32>()?;
+                Ok(Limits::Range(min.into(), max.into()))
+            }
             b => Err(parser.unexpected_byte([0x00, 0x01], b, "limit")),
         }
     }
 }
 
 // https://webassembly.github.io/spec/core/binary/types.html#memory-types
+// Threads proposal sets bit 1 of the flags of limits for shared memory
+// https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#binary-format
+// Memory64 proposal sets bit 2 for 64-bit memory. Its limits are encoded in u64
+// https://github.com/WebAssembly/memory64/blob/main/proposals/memory64/Overview.md#binary-format
 impl<'s> Parse<'s> for MemType {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
+        let flags = parser.consume("limit of memory type")?;
+        if flags > 0x07 {
+            let expected = [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07];
+            return Err(parser.unexpected_byte(expected, flags, "memory type"));
+        }
+        let shared = flags & 0x02 != 0;
+        let memory64 = flags & 0x04 != 0;
+        let parse_limit = |parser: &mut Parser<'s>| -> Result<'s, u64> {
+            if memory64 {
+                parser.parse_int()
+            } else {
+                Ok(parser.parse_int::<u32>()?.into())
+            }
+        };
+        let limit = if flags & 0x01 == 0 {
+            Limits::From(parse_limit(parser)?)
+        } else {
+            Limits::Range(parse_limit(parser)?, parse_limit(parser)?)
+        };
         Ok(MemType {
-            limit: parser.parse()?,
+            limit,
+            shared,
+            memory64,
         })
     }
 }
@@ -516,6 +613,30 @@ impl<'s> Parse<'s> for GlobalType {
     }
 }
 
+// https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#tag-section
+struct TagType(TypeIdx);
+impl<'s> Parse<'s> for TagType {
+    fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
+        // Only 0x00 (exception) is defined as attribute of tag
+        match parser.consume("attribute of tag type")? {
+            0x00 => Ok(TagType(parser.parse()?)),
+            b => Err(parser.unexpected_byte([0x00], b, "attribute of tag type")),
+        }
+    }
+}
+
+impl<'s> Parse<'s> for Tag<'s> {
+    fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
+        let start = parser.current_pos();
+        let TagType(idx) = parser.parse()?;
+        Ok(Tag {
+            start,
+            idx,
+            import: None,
+        })
+    }
+}
+
 // https://webassembly.github.io/spec/core/binary/modules.html#binary-tablesec
 impl<'s> Parse<'s> for Table<'s> {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
@@ -568,15 +689,45 @@ impl<'s> Parse<'s> for Expr {
     }
 }
 
-// https://webassembly.github.io/spec/core/binary/types.html#binary-blocktype
-struct BlockType(Option<ValType>);
+// Parse instructions until one of the given bytes appears. The terminating byte is not consumed
+fn parse_insns_until<'s>(parser: &mut Parser<'s>, ends: &[u8]) -> Result<'s, Vec<Instruction>> {
+    let mut insns = vec![];
+    loop {
+        match parser.input {
+            [b, ..] if ends.contains(b) => return Ok(insns),
+            _ => insns.push(parser.parse()?),
+        }
+    }
+}
+
+// https://webassembly.github.io/spec/core/binary/instructions.html#binary-blocktype
 impl<'s> Parse<'s> for BlockType {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
-        if let [0x40, ..] = parser.input {
-            parser.eat(1);
-            return Ok(BlockType(None));
+        match parser.input {
+            [0x40, ..] => {
+                parser.eat(1);
+                Ok(BlockType::Empty)
+            }
+            [0x7f, ..]
+            | [0x7e, ..]
+            | [0x7d, ..]
+            | [0x7c, ..]
+            | [0x7b, ..]
+            | [0x70, ..]
+            | [0x6f, ..] => Ok(BlockType::Value(parser.parse()?)),
+            _ => {
+                // Type index is encoded as positive signed 33bit integer not to conflict with the
+                // encodings above
+                let idx: i64 = parser.parse_int()?;
+                if idx < 0 || idx > u32::MAX as i64 {
+                    return Err(parser.error(ErrorKind::IntOverflow {
+                        ty: "s33",
+                        got: Some(idx as u64),
+                    }));
+                }
+                Ok(BlockType::Type(idx as u32))
+            }
         }
-        Ok(BlockType(Some(parser.parse()?)))
     }
 }
 
@@ -591,17 +742,17 @@ impl<'s> Parse<'s> for Instruction {
             0x00 => Unreachable,
             0x01 => Nop,
             0x02 => {
-                let BlockType(ty) = parser.parse()?;
+                let ty = parser.parse()?;
                 let Expr(body) = parser.parse()?;
                 Block { ty, body }
             }
             0x03 => {
-                let BlockType(ty) = parser.parse()?;
+                let ty = parser.parse()?;
                 let Expr(body) = parser.parse()?;
                 Loop { ty, body }
             }
             0x04 => {
-                let BlockType(ty) = parser.parse()?;
+                let ty = parser.parse()?;
 
                 let mut then_body = vec![];
                 let has_else = loop {
@@ -626,6 +777,37 @@ impl<'s> Parse<'s> for Instruction {
                     else_body,
                 }
             }
+            // Exception handling
+            // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#changes-to-the-binary-model
+            0x06 => {
+                let ty = parser.parse()?;
+                let body = parse_insns_until(parser, &[0x07, 0x18, 0x19, 0x0b])?;
+                let handler = if let [0x18, ..] = parser.input {
+                    parser.eat(1);
+                    TryHandler::Delegate(parser.parse()?)
+                } else {
+                    let mut catches = vec![];
+                    while let [0x07, ..] = parser.input {
+                        let start = parser.current_pos();
+                        parser.eat(1);
+                        let tag = parser.parse()?;
+                        let body = parse_insns_until(parser, &[0x07, 0x19, 0x0b])?;
+                        catches.push(Catch { start, tag, body });
+                    }
+                    let catch_all = if let [0x19, ..] = parser.input {
+                        parser.eat(1);
+                        let Expr(body) = parser.parse()?;
+                        Some(body)
+                    } else {
+                        parser.eat(1); // Eat 'end'
+                        None
+                    };
+                    TryHandler::Catch { catches, catch_all }
+                };
+                Try { ty, body, handler }
+            }
+            0x08 => Throw(parser.parse()?),
+            0x09 => Rethrow(parser.parse()?),
             0x0c => Br(parser.parse()?),
             0x0d => BrIf(parser.parse()?),
             0x0e => BrTable {
@@ -635,14 +817,26 @@ impl<'s> Parse<'s> for Instruction {
             0x0f => Return,
             0x10 => Call(parser.parse()?),
             0x11 => {
-                let insn = CallIndirect(parser.parse()?);
-                parser.parse_flag(0x00, "reserved byte in call_indirect")?;
-                insn
+                let ty = parser.parse()?;
+                let table = parser.parse()?;
+                CallIndirect { table, ty }
+            }
+            0x12 => ReturnCall(parser.parse()?),
+            0x13 => {
+                let ty = parser.parse()?;
+                let table = parser.parse()?;
+                ReturnCallIndirect { table, ty }
             }
+            // Reference instructions
+            // https://webassembly.github.io/spec/core/binary/instructions.html#reference-instructions
+            0xd0 => RefNull(parser.parse()?),
+            0xd1 => RefIsNull,
+            0xd2 => RefFunc(parser.parse()?),
             // Parametric instructions
             // https://webassembly.github.io/spec/core/binary/instructions.html#parametric-instructions
             0x1a => Drop,
             0x1b => Select,
+            0x1c => TypedSelect(parser.parse_vec()?.into_vec()?),
             // Variable instructions
             // https://webassembly.github.io/spec/core/binary/instructions.html#variable-instructions
             0x20 => LocalGet(parser.parse()?),
@@ -650,6 +844,10 @@ impl<'s> Parse<'s> for Instruction {
             0x22 => LocalTee(parser.parse()?),
             0x23 => GlobalGet(parser.parse()?),
             0x24 => GlobalSet(parser.parse()?),
+            // Table instructions
+            // https://webassembly.github.io/spec/core/binary/instructions.html#table-instructions
+            0x25 => TableGet(parser.parse()?),
+            0x26 => TableSet(parser.parse()?),
             // Memory instructions
             // https://webassembly.github.io/spec/core/binary/instructions.html#memory-instructions
             0x28 => I32Load(parser.parse()?),
@@ -675,14 +873,8 @@ impl<'s> Parse<'s> for Instruction {
             0x3c => I64Store8(parser.parse()?),
             0x3d => I64Store16(parser.parse()?),
             0x3e => I64Store32(parser.parse()?),
-            0x3f => {
-                parser.parse_flag(0x00, "reserved byte in memory.size")?;
-                MemorySize
-            }
-            0x40 => {
-                parser.parse_flag(0x00, "reserved byte in memory.grow")?;
-                MemoryGrow
-            }
+            0x3f => MemorySize(parser.parse()?),
+            0x40 => MemoryGrow(parser.parse()?),
             // Numeric instructions
             // constants
             0x41 => I32Const(parser.parse_int()?),
@@ -818,6 +1010,403 @@ impl<'s> Parse<'s> for Instruction {
             0xbd => I64ReinterpretF64,
             0xbe => F32ReinterpretI32,
             0xbf => F64ReinterpretI64,
+            0xc0 => I32Extend8S,
+            0xc1 => I32Extend16S,
+            0xc2 => I64Extend8S,
+            0xc3 => I64Extend16S,
+            0xc4 => I64Extend32S,
+            0xfc => {
+                let op: u32 = parser.parse_int()?;
+                match op {
+                    0 => I32TruncSatF32S,
+                    1 => I32TruncSatF32U,
+                    2 => I32TruncSatF64S,
+                    3 => I32TruncSatF64U,
+                    4 => I64TruncSatF32S,
+                    5 => I64TruncSatF32U,
+                    6 => I64TruncSatF64S,
+                    7 => I64TruncSatF64U,
+                    8 => {
+                        let data = parser.parse()?;
+                        let memory = parser.parse()?;
+                        MemoryInit { memory, data }
+                    }
+                    9 => DataDrop(parser.parse()?),
+                    10 => {
+                        let dst = parser.parse()?;
+                        let src = parser.parse()?;
+                        MemoryCopy { dst, src }
+                    }
+                    11 => MemoryFill(parser.parse()?),
+                    12 => {
+                        let elem = parser.parse()?;
+                        let table = parser.parse()?;
+                        TableInit { table, elem }
+                    }
+                    13 => ElemDrop(parser.parse()?),
+                    14 => {
+                        let dst = parser.parse()?;
+                        let src = parser.parse()?;
+                        TableCopy { dst, src }
+                    }
+                    15 => TableGrow(parser.parse()?),
+                    16 => TableSize(parser.parse()?),
+                    17 => TableFill(parser.parse()?),
+                    _ => return Err(parser.error(ErrorKind::UnknownOpcode { prefix: 0xfc, op })),
+                }
+            }
+            // Vector instructions
+            // https://webassembly.github.io/spec/core/binary/instructions.html#vector-instructions
+            0xfd => {
+                let op: u32 = parser.parse_int()?;
+                match op {
+                    0 => V128Load(parser.parse()?),
+                    1 => V128Load8x8S(parser.parse()?),
+                    2 => V128Load8x8U(parser.parse()?),
+                    3 => V128Load16x4S(parser.parse()?),
+                    4 => V128Load16x4U(parser.parse()?),
+                    5 => V128Load32x2S(parser.parse()?),
+                    6 => V128Load32x2U(parser.parse()?),
+                    7 => V128Load8Splat(parser.parse()?),
+                    8 => V128Load16Splat(parser.parse()?),
+                    9 => V128Load32Splat(parser.parse()?),
+                    10 => V128Load64Splat(parser.parse()?),
+                    11 => V128Store(parser.parse()?),
+                    12 => V128Const(parser.parse()?),
+                    13 => {
+                        let mut lanes = [0; 16];
+                        for lane in lanes.iter_mut() {
+                            *lane = parser.consume("lane index")?;
+                        }
+                        I8x16Shuffle(lanes)
+                    }
+                    14 => I8x16Swizzle,
+                    15 => I8x16Splat,
+                    16 => I16x8Splat,
+                    17 => I32x4Splat,
+                    18 => I64x2Splat,
+                    19 => F32x4Splat,
+                    20 => F64x2Splat,
+                    21 => I8x16ExtractLaneS(parser.consume("lane index")?),
+                    22 => I8x16ExtractLaneU(parser.consume("lane index")?),
+                    23 => I8x16ReplaceLane(parser.consume("lane index")?),
+                    24 => I16x8ExtractLaneS(parser.consume("lane index")?),
+                    25 => I16x8ExtractLaneU(parser.consume("lane index")?),
+                    26 => I16x8ReplaceLane(parser.consume("lane index")?),
+                    27 => I32x4ExtractLane(parser.consume("lane index")?),
+                    28 => I32x4ReplaceLane(parser.consume("lane index")?),
+                    29 => I64x2ExtractLane(parser.consume("lane index")?),
+                    30 => I64x2ReplaceLane(parser.consume("lane index")?),
+                    31 => F32x4ExtractLane(parser.consume("lane index")?),
+                    32 => F32x4ReplaceLane(parser.consume("lane index")?),
+                    33 => F64x2ExtractLane(parser.consume("lane index")?),
+                    34 => F64x2ReplaceLane(parser.consume("lane index")?),
+                    35 => I8x16Eq,
+                    36 => I8x16Ne,
+                    37 => I8x16LtS,
+                    38 => I8x16LtU,
+                    39 => I8x16GtS,
+                    40 => I8x16GtU,
+                    41 => I8x16LeS,
+                    42 => I8x16LeU,
+                    43 => I8x16GeS,
+                    44 => I8x16GeU,
+                    45 => I16x8Eq,
+                    46 => I16x8Ne,
+                    47 => I16x8LtS,
+                    48 => I16x8LtU,
+                    49 => I16x8GtS,
+                    50 => I16x8GtU,
+                    51 => I16x8LeS,
+                    52 => I16x8LeU,
+                    53 => I16x8GeS,
+                    54 => I16x8GeU,
+                    55 => I32x4Eq,
+                    56 => I32x4Ne,
+                    57 => I32x4LtS,
+                    58 => I32x4LtU,
+                    59 => I32x4GtS,
+                    60 => I32x4GtU,
+                    61 => I32x4LeS,
+                    62 => I32x4LeU,
+                    63 => I32x4GeS,
+                    64 => I32x4GeU,
+                    65 => F32x4Eq,
+                    66 => F32x4Ne,
+                    67 => F32x4Lt,
+                    68 => F32x4Gt,
+                    69 => F32x4Le,
+                    70 => F32x4Ge,
+                    71 => F64x2Eq,
+                    72 => F64x2Ne,
+                    73 => F64x2Lt,
+                    74 => F64x2Gt,
+                    75 => F64x2Le,
+                    76 => F64x2Ge,
+                    77 => V128Not,
+                    78 => V128And,
+                    79 => V128Andnot,
+                    80 => V128Or,
+                    81 => V128Xor,
+                    82 => V128Bitselect,
+                    83 => V128AnyTrue,
+                    84 => V128Load8Lane {
+                        mem: parser.parse()?,
+                        lane: parser.consume("lane index")?,
+                    },
+                    85 => V128Load16Lane {
+                        mem: parser.parse()?,
+                        lane: parser.consume("lane index")?,
+                    },
+                    86 => V128Load32Lane {
+                        mem: parser.parse()?,
+                        lane: parser.consume("lane index")?,
+                    },
+                    87 => V128Load64Lane {
+                        mem: parser.parse()?,
+                        lane: parser.consume("lane index")?,
+                    },
+                    88 => V128Store8Lane {
+                        mem: parser.parse()?,
+                        lane: parser.consume("lane index")?,
+                    },
+                    89 => V128Store16Lane {
+                        mem: parser.parse()?,
+                        lane: parser.consume("lane index")?,
+                    },
+                    90 => V128Store32Lane {
+                        mem: parser.parse()?,
+                        lane: parser.consume("lane index")?,
+                    },
+                    91 => V128Store64Lane {
+                        mem: parser.parse()?,
+                        lane: parser.consume("lane index")?,
+                    },
+                    92 => V128Load32Zero(parser.parse()?),
+                    93 => V128Load64Zero(parser.parse()?),
+                    94 => F32x4DemoteF64x2Zero,
+                    95 => F64x2PromoteLowF32x4,
+                    96 => I8x16Abs,
+                    97 => I8x16Neg,
+                    98 => I8x16Popcnt,
+                    99 => I8x16AllTrue,
+                    100 => I8x16Bitmask,
+                    101 => I8x16NarrowI16x8S,
+                    102 => I8x16NarrowI16x8U,
+                    103 => F32x4Ceil,
+                    104 => F32x4Floor,
+                    105 => F32x4Trunc,
+                    106 => F32x4Nearest,
+                    107 => I8x16Shl,
+                    108 => I8x16ShrS,
+                    109 => I8x16ShrU,
+                    110 => I8x16Add,
+                    111 => I8x16AddSatS,
+                    112 => I8x16AddSatU,
+                    113 => I8x16Sub,
+                    114 => I8x16SubSatS,
+                    115 => I8x16SubSatU,
+                    116 => F64x2Ceil,
+                    117 => F64x2Floor,
+                    118 => I8x16MinS,
+                    119 => I8x16MinU,
+                    120 => I8x16MaxS,
+                    121 => I8x16MaxU,
+                    122 => F64x2Trunc,
+                    123 => I8x16AvgrU,
+                    124 => I16x8ExtaddPairwiseI8x16S,
+                    125 => I16x8ExtaddPairwiseI8x16U,
+                    126 => I32x4ExtaddPairwiseI16x8S,
+                    127 => I32x4ExtaddPairwiseI16x8U,
+                    128 => I16x8Abs,
+                    129 => I16x8Neg,
+                    130 => I16x8Q15mulrSatS,
+                    131 => I16x8AllTrue,
+                    132 => I16x8Bitmask,
+                    133 => I16x8NarrowI32x4S,
+                    134 => I16x8NarrowI32x4U,
+                    135 => I16x8ExtendLowI8x16S,
+                    136 => I16x8ExtendHighI8x16S,
+                    137 => I16x8ExtendLowI8x16U,
+                    138 => I16x8ExtendHighI8x16U,
+                    139 => I16x8Shl,
+                    140 => I16x8ShrS,
+                    141 => I16x8ShrU,
+                    142 => I16x8Add,
+                    143 => I16x8AddSatS,
+                    144 => I16x8AddSatU,
+                    145 => I16x8Sub,
+                    146 => I16x8SubSatS,
+                    147 => I16x8SubSatU,
+                    148 => F64x2Nearest,
+                    149 => I16x8Mul,
+                    150 => I16x8MinS,
+                    151 => I16x8MinU,
+                    152 => I16x8MaxS,
+                    153 => I16x8MaxU,
+                    155 => I16x8AvgrU,
+                    156 => I16x8ExtmulLowI8x16S,
+                    157 => I16x8ExtmulHighI8x16S,
+                    158 => I16x8ExtmulLowI8x16U,
+                    159 => I16x8ExtmulHighI8x16U,
+                    160 => I32x4Abs,
+                    161 => I32x4Neg,
+                    163 => I32x4AllTrue,
+                    164 => I32x4Bitmask,
+                    167 => I32x4ExtendLowI16x8S,
+                    168 => I32x4ExtendHighI16x8S,
+                    169 => I32x4ExtendLowI16x8U,
+                    170 => I32x4ExtendHighI16x8U,
+                    171 => I32x4Shl,
+                    172 => I32x4ShrS,
+                    173 => I32x4ShrU,
+                    174 => I32x4Add,
+                    177 => I32x4Sub,
+                    181 => I32x4Mul,
+                    182 => I32x4MinS,
+                    183 => I32x4MinU,
+                    184 => I32x4MaxS,
+                    185 => I32x4MaxU,
+                    186 => I32x4DotI16x8S,
+                    188 => I32x4ExtmulLowI16x8S,
+                    189 => I32x4ExtmulHighI16x8S,
+                    190 => I32x4ExtmulLowI16x8U,
+                    191 => I32x4ExtmulHighI16x8U,
+                    192 => I64x2Abs,
+                    193 => I64x2Neg,
+                    195 => I64x2AllTrue,
+                    196 => I64x2Bitmask,
+                    199 => I64x2ExtendLowI32x4S,
+                    200 => I64x2ExtendHighI32x4S,
+                    201 => I64x2ExtendLowI32x4U,
+                    202 => I64x2ExtendHighI32x4U,
+                    203 => I64x2Shl,
+                    204 => I64x2ShrS,
+                    205 => I64x2ShrU,
+                    206 => I64x2Add,
+                    209 => I64x2Sub,
+                    213 => I64x2Mul,
+                    214 => I64x2Eq,
+                    215 => I64x2Ne,
+                    216 => I64x2LtS,
+                    217 => I64x2GtS,
+                    218 => I64x2LeS,
+                    219 => I64x2GeS,
+                    220 => I64x2ExtmulLowI32x4S,
+                    221 => I64x2ExtmulHighI32x4S,
+                    222 => I64x2ExtmulLowI32x4U,
+                    223 => I64x2ExtmulHighI32x4U,
+                    224 => F32x4Abs,
+                    225 => F32x4Neg,
+                    227 => F32x4Sqrt,
+                    228 => F32x4Add,
+                    229 => F32x4Sub,
+                    230 => F32x4Mul,
+                    231 => F32x4Div,
+                    232 => F32x4Min,
+                    233 => F32x4Max,
+                    234 => F32x4Pmin,
+                    235 => F32x4Pmax,
+                    236 => F64x2Abs,
+                    237 => F64x2Neg,
+                    239 => F64x2Sqrt,
+                    240 => F64x2Add,
+                    241 => F64x2Sub,
+                    242 => F64x2Mul,
+                    243 => F64x2Div,
+                    244 => F64x2Min,
+                    245 => F64x2Max,
+                    246 => F64x2Pmin,
+                    247 => F64x2Pmax,
+                    248 => I32x4TruncSatF32x4S,
+                    249 => I32x4TruncSatF32x4U,
+                    250 => F32x4ConvertI32x4S,
+                    251 => F32x4ConvertI32x4U,
+                    252 => I32x4TruncSatF64x2SZero,
+                    253 => I32x4TruncSatF64x2UZero,
+                    254 => F64x2ConvertLowI32x4S,
+                    255 => F64x2ConvertLowI32x4U,
+                    _ => return Err(parser.error(ErrorKind::UnknownOpcode { prefix: 0xfd, op })),
+                }
+            }
+            // Atomic instructions
+            // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#instruction-encoding
+            0xfe => {
+                let op: u32 = parser.parse_int()?;
+                match op {
+                    0x00 => MemoryAtomicNotify(parser.parse()?),
+                    0x01 => MemoryAtomicWait32(parser.parse()?),
+                    0x02 => MemoryAtomicWait64(parser.parse()?),
+                    0x03 => match parser.consume("reserved byte of atomic.fence")? {
+                        0x00 => AtomicFence,
+                        b => return Err(parser.unexpected_byte([0x00], b, "atomic.fence")),
+                    },
+                    0x10 => I32AtomicLoad(parser.parse()?),
+                    0x11 => I64AtomicLoad(parser.parse()?),
+                    0x12 => I32AtomicLoad8U(parser.parse()?),
+                    0x13 => I32AtomicLoad16U(parser.parse()?),
+                    0x14 => I64AtomicLoad8U(parser.parse()?),
+                    0x15 => I64AtomicLoad16U(parser.parse()?),
+                    0x16 => I64AtomicLoad32U(parser.parse()?),
+                    0x17 => I32AtomicStore(parser.parse()?),
+                    0x18 => I64AtomicStore(parser.parse()?),
+                    0x19 => I32AtomicStore8(parser.parse()?),
+                    0x1a => I32AtomicStore16(parser.parse()?),
+                    0x1b => I64AtomicStore8(parser.parse()?),
+                    0x1c => I64AtomicStore16(parser.parse()?),
+                    0x1d => I64AtomicStore32(parser.parse()?),
+                    0x1e => I32AtomicRmwAdd(parser.parse()?),
+                    0x1f => I64AtomicRmwAdd(parser.parse()?),
+                    0x20 => I32AtomicRmw8AddU(parser.parse()?),
+                    0x21 => I32AtomicRmw16AddU(parser.parse()?),
+                    0x22 => I64AtomicRmw8AddU(parser.parse()?),
+                    0x23 => I64AtomicRmw16AddU(parser.parse()?),
+                    0x24 => I64AtomicRmw32AddU(parser.parse()?),
+                    0x25 => I32AtomicRmwSub(parser.parse()?),
+                    0x26 => I64AtomicRmwSub(parser.parse()?),
+                    0x27 => I32AtomicRmw8SubU(parser.parse()?),
+                    0x28 => I32AtomicRmw16SubU(parser.parse()?),
+                    0x29 => I64AtomicRmw8SubU(parser.parse()?),
+                    0x2a => I64AtomicRmw16SubU(parser.parse()?),
+                    0x2b => I64AtomicRmw32SubU(parser.parse()?),
+                    0x2c => I32AtomicRmwAnd(parser.parse()?),
+                    0x2d => I64AtomicRmwAnd(parser.parse()?),
+                    0x2e => I32AtomicRmw8AndU(parser.parse()?),
+                    0x2f => I32AtomicRmw16AndU(parser.parse()?),
+                    0x30 => I64AtomicRmw8AndU(parser.parse()?),
+                    0x31 => I64AtomicRmw16AndU(parser.parse()?),
+                    0x32 => I64AtomicRmw32AndU(parser.parse()?),
+                    0x33 => I32AtomicRmwOr(parser.parse()?),
+                    0x34 => I64AtomicRmwOr(parser.parse()?),
+                    0x35 => I32AtomicRmw8OrU(parser.parse()?),
+                    0x36 => I32AtomicRmw16OrU(parser.parse()?),
+                    0x37 => I64AtomicRmw8OrU(parser.parse()?),
+                    0x38 => I64AtomicRmw16OrU(parser.parse()?),
+                    0x39 => I64AtomicRmw32OrU(parser.parse()?),
+                    0x3a => I32AtomicRmwXor(parser.parse()?),
+                    0x3b => I64AtomicRmwXor(parser.parse()?),
+                    0x3c => I32AtomicRmw8XorU(parser.parse()?),
+                    0x3d => I32AtomicRmw16XorU(parser.parse()?),
+                    0x3e => I64AtomicRmw8XorU(parser.parse()?),
+                    0x3f => I64AtomicRmw16XorU(parser.parse()?),
+                    0x40 => I64AtomicRmw32XorU(parser.parse()?),
+                    0x41 => I32AtomicRmwXchg(parser.parse()?),
+                    0x42 => I64AtomicRmwXchg(parser.parse()?),
+                    0x43 => I32AtomicRmw8XchgU(parser.parse()?),
+                    0x44 => I32AtomicRmw16XchgU(parser.parse()?),
+                    0x45 => I64AtomicRmw8XchgU(parser.parse()?),
+                    0x46 => I64AtomicRmw16XchgU(parser.parse()?),
+                    0x47 => I64AtomicRmw32XchgU(parser.parse()?),
+                    0x48 => I32AtomicRmwCmpxchg(parser.parse()?),
+                    0x49 => I64AtomicRmwCmpxchg(parser.parse()?),
+                    0x4a => I32AtomicRmw8CmpxchgU(parser.parse()?),
+                    0x4b => I32AtomicRmw16CmpxchgU(parser.parse()?),
+                    0x4c => I64AtomicRmw8CmpxchgU(parser.parse()?),
+                    0x4d => I64AtomicRmw16CmpxchgU(parser.parse()?),
+                    0x4e => I64AtomicRmw32CmpxchgU(parser.parse()?),
+                    _ => return Err(parser.error(ErrorKind::UnknownOpcode { prefix: 0xfe, op })),
+                }
+            }
             // https://webassembly.github.io/spec/core/binary/instructions.html#numeric-instructions
             b => return Err(parser.unexpected_byte([], b, "instruction")),
         };
@@ -826,13 +1415,25 @@ impl<'s> Parse<'s> for Instruction {
 }
 
 // https://webassembly.github.io/spec/core/binary/instructions.html#binary-memarg
+// Multi-memory proposal sets bit 6 of alignment when memory index follows it
+// https://github.com/WebAssembly/multi-memory/blob/main/proposals/multi-memory/Overview.md
 impl<'s> Parse<'s> for Mem {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
-        let align: u32 = parser.parse_int()?;
+        let mut align: u32 = parser.parse_int()?;
+        let memory = if align & 0x40 != 0 {
+            align &= !0x40;
+            parser.parse()?
+        } else {
+            0
+        };
         let offset = parser.parse_int()?;
         let align = if align == 0 { None } else { Some(align as u8) };
         let offset = if offset == 0 { None } else { Some(offset) };
-        Ok(Mem { align, offset })
+        Ok(Mem {
+            align,
+            offset,
+            memory,
+        })
     }
 }
 
@@ -864,6 +1465,21 @@ impl<'s> Parse<'s> for f64 {
     }
 }
 
+// https://webassembly.github.io/spec/core/binary/instructions.html#vector-instructions
+// Operand of v128.const is 16 bytes in little endian
+impl<'s> Parse<'s> for i128 {
+    fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
+        if parser.input.len() < 16 {
+            return Err(parser.error(ErrorKind::UnexpectedEof {
+                expected: "128bit vector constant",
+            }));
+        }
+        let buf: [u8; 16] = parser.input[..16].try_into().unwrap();
+        parser.eat(16);
+        Ok(i128::from_le_bytes(buf))
+    }
+}
+
 // https://webassembly.github.io/spec/core/binary/modules.html#binary-export
 impl<'s> Parse<'s> for Export<'s> {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
@@ -874,9 +1490,10 @@ impl<'s> Parse<'s> for Export<'s> {
             0x01 => ExportKind::Table(parser.parse()?),
             0x02 => ExportKind::Memory(parser.parse()?),
             0x03 => ExportKind::Global(parser.parse()?),
+            0x04 => ExportKind::Tag(parser.parse()?),
             b => {
                 return Err(parser.unexpected_byte(
-                    [0x00, 0x01, 0x02, 0x03],
+                    [0x00, 0x01, 0x02, 0x03, 0x04],
                     b,
                     "export description",
                 ));
@@ -896,17 +1513,66 @@ impl<'s> Parse<'s> for StartFunction {
     }
 }
 
+// Function index in element segment is a shorthand of constant expression 'ref.func {funcidx}'
+struct FuncIdxExpr(Vec<Instruction>);
+impl<'s> Parse<'s> for FuncIdxExpr {
+    fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
+        let start = parser.current_pos();
+        let kind = InsnKind::RefFunc(parser.parse()?);
+        Ok(FuncIdxExpr(vec![Instruction { start, kind }]))
+    }
+}
+
 // https://webassembly.github.io/spec/core/binary/modules.html#binary-elem
 impl<'s> Parse<'s> for ElemSegment {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
         let start = parser.current_pos();
-        let idx = parser.parse()?;
-        let Expr(offset) = parser.parse()?;
-        let init = parser.parse_vec()?.into_vec()?;
+        // Bit 0 means passive or declarative, bit 1 means explicit table index (or declarative when
+        // bit 0 is also set) and bit 2 means element expressions instead of function indices
+        let flags = parser.consume("flags of element segment")?;
+        if flags > 0x07 {
+            return Err(parser.unexpected_byte(
+                [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07],
+                flags,
+                "flags of element segment",
+            ));
+        }
+        let mode = match flags & 0x03 {
+            0x00 => {
+                let Expr(offset) = parser.parse()?;
+                ElemMode::Active { idx: 0, offset }
+            }
+            0x01 => ElemMode::Passive,
+            0x02 => {
+                let idx = parser.parse()?;
+                let Expr(offset) = parser.parse()?;
+                ElemMode::Active { idx, offset }
+            }
+            _ => ElemMode::Declarative,
+        };
+        let ty = if flags & 0x03 == 0 {
+            RefType::FuncRef
+        } else if flags & 0x04 == 0 {
+            parser.parse_flag(0x00, "element kind of element segment")?;
+            RefType::FuncRef
+        } else {
+            parser.parse()?
+        };
+        let init = if flags & 0x04 == 0 {
+            parser
+                .parse_vec()?
+                .map(|e| e.map(|FuncIdxExpr(insns)| insns))
+                .collect::<Result<'_, _>>()?
+        } else {
+            parser
+                .parse_vec()?
+                .map(|e| e.map(|Expr(insns)| insns))
+                .collect::<Result<'_, _>>()?
+        };
         Ok(ElemSegment {
             start,
-            idx,
-            offset,
+            ty,
+            mode,
             init,
         })
     }
@@ -960,8 +1626,25 @@ impl<'s> Parse<'s> for Locals {
 impl<'s> Parse<'s> for DataSegment<'s> {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
         let start = parser.current_pos();
-        let idx = parser.parse()?;
-        let Expr(offset) = parser.parse()?;
+        let mode = match parser.consume("flags of data segment")? {
+            0x00 => {
+                let Expr(offset) = parser.parse()?;
+                DataMode::Active { idx: 0, offset }
+            }
+            0x01 => DataMode::Passive,
+            0x02 => {
+                let idx = parser.parse()?;
+                let Expr(offset) = parser.parse()?;
+                DataMode::Active { idx, offset }
+            }
+            b => {
+                return Err(parser.unexpected_byte(
+                    [0x00, 0x01, 0x02],
+                    b,
+                    "flags of data segment",
+                ))
+            }
+        };
 
         // Parse vec(byte) with zero allocation
         let size = parser.parse_int::<u32>()? as usize;
@@ -977,8 +1660,7 @@ impl<'s> Parse<'s> for DataSegment<'s> {
 
         Ok(DataSegment {
             start,
-            idx,
-            offset,
+            mode,
             data: Cow::Borrowed(data),
         })
     }
@@ -1063,6 +1745,7 @@ mod tests {
         assert_eq!(t.len(), 1);
         assert!(matches!(&t[0], Table {
             ty: TableType {
+                elem: RefType::FuncRef,
                 limit: Limits::Range(1, 1),
             },
             import: None,
@@ -1074,6 +1757,8 @@ mod tests {
         assert!(matches!(&m[0], Memory {
             ty: MemType {
                 limit: Limits::From(2),
+                shared: false,
+                memory64: false,
             },
             import: None,
             ..
@@ -1081,13 +1766,15 @@ mod tests {
 
         let d = &root.module.data;
         assert_eq!(d.len(), 1);
-        assert_eq!(d[0].idx, 0);
         assert!(matches!(
-            d[0].offset.as_slice(),
-            [Instruction {
-                kind: InsnKind::I32Const(1024),
-                ..
-            }]
+            &d[0].mode,
+            DataMode::Active { idx: 0, offset } if matches!(
+                offset.as_slice(),
+                [Instruction {
+                    kind: InsnKind::I32Const(1024),
+                    ..
+                }]
+            )
         ));
         assert_eq!(d[0].data.as_ref(), b"Hello, world\n\0".as_ref());
 
@@ -1122,4 +1809,435 @@ mod tests {
         let mut parser = Parser::new(&bin);
         let _: Root<'_, _> = unwrap(parser.parse());
     }
+
+    #[test]
+    fn block_type() {
+        fn parse(bytes: &[u8]) -> BlockType {
+            let mut parser = Parser::new(bytes);
+            let ty = unwrap(parser.parse());
+            assert!(parser.input.is_empty());
+            ty
+        }
+
+        assert_eq!(parse(&[0x40]), BlockType::Empty);
+        assert_eq!(parse(&[0x7f]), BlockType::Value(ValType::I32));
+        assert_eq!(parse(&[0x7c]), BlockType::Value(ValType::F64));
+        assert_eq!(parse(&[0x00]), BlockType::Type(0));
+        assert_eq!(parse(&[0x3f]), BlockType::Type(63));
+        assert_eq!(parse(&[0xc0, 0x00]), BlockType::Type(64));
+
+        assert_eq!(parse(&[0x7b]), BlockType::Value(ValType::V128));
+
+        let mut parser = Parser::new(&[0x7a]);
+        assert!(parser.parse::<BlockType>().is_err());
+    }
+
+    #[test]
+    fn memory_index() {
+        // i32.load without memory index
+        let mut parser = Parser::new(&[0x28, 0x02, 0x04]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(
+            insn.kind,
+            InsnKind::I32Load(Mem {
+                align: Some(2),
+                offset: Some(4),
+                memory: 0
+            })
+        ));
+
+        // Bit 6 of alignment indicates that memory index follows
+        let mut parser = Parser::new(&[0x36, 0x42, 0x01, 0x08]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(
+            insn.kind,
+            InsnKind::I32Store(Mem {
+                align: Some(2),
+                offset: Some(8),
+                memory: 1
+            })
+        ));
+        assert!(parser.input.is_empty());
+
+        let mut parser = Parser::new(&[0x3f, 0x02]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::MemorySize(2)));
+
+        let mut parser = Parser::new(&[0xfc, 0x0a, 0x01, 0x02]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::MemoryCopy { dst: 1, src: 2 }));
+    }
+
+    #[test]
+    fn prefixed_opcode() {
+        let mut parser = Parser::new(&[0xfc, 0x00]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::I32TruncSatF32S));
+
+        let mut parser = Parser::new(&[0xfc, 0x87, 0x00]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::I64TruncSatF64U));
+        assert!(parser.input.is_empty());
+
+        let mut parser = Parser::new(&[0xfc, 0x08, 0x03, 0x00]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(
+            insn.kind,
+            InsnKind::MemoryInit { memory: 0, data: 3 }
+        ));
+        assert!(parser.input.is_empty());
+
+        let mut parser = Parser::new(&[0xfc, 0x0e, 0x01, 0x00]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::TableCopy { dst: 1, src: 0 }));
+        assert!(parser.input.is_empty());
+
+        let mut parser = Parser::new(&[0xfc, 0x0c, 0x02, 0x01]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::TableInit { table: 1, elem: 2 }));
+        assert!(parser.input.is_empty());
+
+        let mut parser = Parser::new(&[0xfc, 0x11, 0x03]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::TableFill(3)));
+        assert!(parser.input.is_empty());
+
+        // Memory index of memory.fill
+        let mut parser = Parser::new(&[0xfc, 0x0b, 0x01]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::MemoryFill(1)));
+        let mut parser = Parser::new(&[0xfc, 0x0b]);
+        assert!(parser.parse::<Instruction>().is_err());
+
+        let mut parser = Parser::new(&[0xfc, 0x7f]);
+        let err = match parser.parse::<Instruction>() {
+            Ok(_) => panic!("unknown opcode was parsed"),
+            Err(err) => err,
+        };
+        assert!(matches!(
+            err.kind,
+            ErrorKind::UnknownOpcode {
+                prefix: 0xfc,
+                op: 0x7f
+            }
+        ));
+    }
+
+    #[test]
+    fn vector_instructions() {
+        let mut bytes = vec![0xfd, 0x0c];
+        bytes.extend_from_slice(&0x0102_0304_i128.to_le_bytes());
+        let mut parser = Parser::new(&bytes);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::V128Const(0x0102_0304)));
+        assert!(parser.input.is_empty());
+
+        let mut parser = Parser::new(&[0xfd, 0x0c, 0x01, 0x02]);
+        assert!(parser.parse::<Instruction>().is_err());
+
+        let mut bytes = vec![0xfd, 0x0d];
+        bytes.extend(0..16);
+        let mut parser = Parser::new(&bytes);
+        let insn: Instruction = unwrap(parser.parse());
+        match insn.kind {
+            InsnKind::I8x16Shuffle(lanes) => {
+                assert_eq!(lanes, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15])
+            }
+            _ => panic!("i8x16.shuffle was not parsed"),
+        }
+        assert!(parser.input.is_empty());
+
+        let mut parser = Parser::new(&[0xfd, 0x15, 0x0f]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::I8x16ExtractLaneS(15)));
+
+        // Opcode is encoded in LEB128
+        let mut parser = Parser::new(&[0xfd, 0xff, 0x01]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::F64x2ConvertLowI32x4U));
+        assert!(parser.input.is_empty());
+
+        // v128.load16_lane with memarg and lane index
+        let mut parser = Parser::new(&[0xfd, 0x55, 0x01, 0x04, 0x07]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(
+            insn.kind,
+            InsnKind::V128Load16Lane {
+                mem: Mem {
+                    align: Some(1),
+                    offset: Some(4),
+                    memory: 0,
+                },
+                lane: 7,
+            }
+        ));
+        assert!(parser.input.is_empty());
+
+        let mut parser = Parser::new(&[0xfd, 0x9a, 0x01]);
+        let err = match parser.parse::<Instruction>() {
+            Ok(_) => panic!("unknown opcode was parsed"),
+            Err(err) => err,
+        };
+        assert!(matches!(
+            err.kind,
+            ErrorKind::UnknownOpcode {
+                prefix: 0xfd,
+                op: 154
+            }
+        ));
+    }
+
+    #[test]
+    fn tail_calls() {
+        let mut parser = Parser::new(&[0x12, 0x03]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::ReturnCall(3)));
+
+        // Type index comes before table index as call_indirect
+        let mut parser = Parser::new(&[0x13, 0x02, 0x01]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(
+            insn.kind,
+            InsnKind::ReturnCallIndirect { table: 1, ty: 2 }
+        ));
+        assert!(parser.input.is_empty());
+    }
+
+    #[test]
+    fn atomic_instructions() {
+        let mut parser = Parser::new(&[0x03, 0x01, 0x04]);
+        let ty: MemType = unwrap(parser.parse());
+        assert!(matches!(
+            ty,
+            MemType {
+                limit: Limits::Range(1, 4),
+                shared: true,
+                memory64: false,
+            }
+        ));
+
+        let mut parser = Parser::new(&[0xfe, 0x03, 0x00]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(insn.kind, InsnKind::AtomicFence));
+        assert!(parser.input.is_empty());
+
+        let mut parser = Parser::new(&[0xfe, 0x03, 0x01]);
+        assert!(parser.parse::<Instruction>().is_err());
+
+        // i64.atomic.rmw32.cmpxchg_u with memarg
+        let mut parser = Parser::new(&[0xfe, 0x4e, 0x02, 0x08]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(
+            insn.kind,
+            InsnKind::I64AtomicRmw32CmpxchgU(Mem {
+                align: Some(2),
+                offset: Some(8),
+                memory: 0,
+            })
+        ));
+        assert!(parser.input.is_empty());
+
+        let mut parser = Parser::new(&[0xfe, 0x4f]);
+        let err = match parser.parse::<Instruction>() {
+            Ok(_) => panic!("unknown opcode was parsed"),
+            Err(err) => err,
+        };
+        assert!(matches!(
+            err.kind,
+            ErrorKind::UnknownOpcode {
+                prefix: 0xfe,
+                op: 0x4f
+            }
+        ));
+    }
+
+    #[test]
+    fn memory64() {
+        // Limits of 64-bit memory are encoded in u64
+        let mut parser = Parser::new(&[0x05, 0x80, 0x80, 0x80, 0x80, 0x10, 0x80, 0x80, 0x04]);
+        let ty: MemType = unwrap(parser.parse());
+        assert!(matches!(
+            ty,
+            MemType {
+                limit: Limits::Range(0x1_0000_0000, 0x1_0000),
+                shared: false,
+                memory64: true,
+            }
+        ));
+        assert!(parser.input.is_empty());
+
+        let mut parser = Parser::new(&[0x04, 0x01]);
+        let ty: MemType = unwrap(parser.parse());
+        assert!(matches!(
+            ty,
+            MemType {
+                limit: Limits::From(1),
+                memory64: true,
+                ..
+            }
+        ));
+
+        // Limits of 32-bit memory must be within u32
+        let mut parser = Parser::new(&[0x00, 0x80, 0x80, 0x80, 0x80, 0x10]);
+        assert!(parser.parse::<MemType>().is_err());
+        let mut parser = Parser::new(&[0x08, 0x01]);
+        assert!(parser.parse::<MemType>().is_err());
+
+        // Offset of memarg is u64
+        let mut parser = Parser::new(&[0x28, 0x02, 0x80, 0x80, 0x80, 0x80, 0x10]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(matches!(
+            insn.kind,
+            InsnKind::I32Load(Mem {
+                align: Some(2),
+                offset: Some(0x1_0000_0000),
+                memory: 0,
+            })
+        ));
+    }
+
+    #[test]
+    fn exception_handling() {
+        // try (throw 0) catch 0 (drop) catch 1 catch_all (rethrow 0) end
+        let mut parser = Parser::new(&[
+            0x06, 0x40, 0x08, 0x00, 0x07, 0x00, 0x1a, 0x07, 0x01, 0x19, 0x09, 0x00, 0x0b,
+        ]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(parser.input.is_empty());
+        match insn.kind {
+            InsnKind::Try {
+                ty: BlockType::Empty,
+                body,
+                handler: TryHandler::Catch { catches, catch_all },
+            } => {
+                assert_eq!(body.len(), 1);
+                assert!(matches!(body[0].kind, InsnKind::Throw(0)));
+                assert_eq!(catches.len(), 2);
+                assert_eq!(catches[0].tag, 0);
+                assert_eq!(catches[0].body.len(), 1);
+                assert!(matches!(catches[0].body[0].kind, InsnKind::Drop));
+                assert_eq!(catches[1].tag, 1);
+                assert!(catches[1].body.is_empty());
+                let catch_all = catch_all.unwrap();
+                assert_eq!(catch_all.len(), 1);
+                assert!(matches!(catch_all[0].kind, InsnKind::Rethrow(0)));
+            }
+            _ => panic!("unexpected instruction: {}", insn.kind.name()),
+        }
+
+        // try (nop) end
+        let mut parser = Parser::new(&[0x06, 0x40, 0x01, 0x0b]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(parser.input.is_empty());
+        assert!(matches!(
+            insn.kind,
+            InsnKind::Try {
+                handler: TryHandler::Catch { ref catches, catch_all: None },
+                ..
+            } if catches.is_empty()
+        ));
+
+        // try (result i32) (i32.const 0) delegate 1
+        let mut parser = Parser::new(&[0x06, 0x7f, 0x41, 0x00, 0x18, 0x01]);
+        let insn: Instruction = unwrap(parser.parse());
+        assert!(parser.input.is_empty());
+        assert!(matches!(
+            insn.kind,
+            InsnKind::Try {
+                ty: BlockType::Value(ValType::I32),
+                handler: TryHandler::Delegate(1),
+                ..
+            }
+        ));
+
+        // Imported tag, defined tag and exported tag
+        let mut parser = Parser::new(&[0x01, 0x61, 0x01, 0x62, 0x04, 0x00, 0x02]);
+        let desc: ImportDesc<'_> = unwrap(parser.parse());
+        assert!(matches!(desc, ImportDesc::Tag(Tag { idx: 2, import: Some(_), .. })));
+        let mut parser = Parser::new(&[0x00, 0x01]);
+        let tag: Tag<'_> = unwrap(parser.parse());
+        assert!(matches!(tag, Tag { idx: 1, import: None, .. }));
+        let mut parser = Parser::new(&[0x01, 0x74, 0x04, 0x00]);
+        let export: Export<'_> = unwrap(parser.parse());
+        assert!(matches!(export.kind, ExportKind::Tag(0)));
+
+        // Unknown tag attribute
+        let mut parser = Parser::new(&[0x01, 0x00]);
+        assert!(parser.parse::<Tag<'_>>().is_err());
+    }
+
+    #[test]
+    fn segment_modes() {
+        // Active data segment for memory 0: flags, offset expression and bytes
+        let mut parser = Parser::new(&[0x00, 0x41, 0x08, 0x0b, 0x02, 0x61, 0x62]);
+        let data: DataSegment<'_> = unwrap(parser.parse());
+        assert!(matches!(&data.mode, DataMode::Active { idx: 0, offset } if offset.len() == 1));
+        assert_eq!(data.data.as_ref(), b"ab");
+
+        // Passive data segment only has bytes
+        let mut parser = Parser::new(&[0x01, 0x01, 0x61]);
+        let data: DataSegment<'_> = unwrap(parser.parse());
+        assert!(matches!(data.mode, DataMode::Passive));
+        assert_eq!(data.data.as_ref(), b"a");
+
+        // Active data segment with explicit memory index
+        let mut parser = Parser::new(&[0x02, 0x00, 0x41, 0x00, 0x0b, 0x00]);
+        let data: DataSegment<'_> = unwrap(parser.parse());
+        assert!(matches!(data.mode, DataMode::Active { idx: 0, .. }));
+        assert!(data.data.is_empty());
+
+        // Active element segment for table 0
+        let mut parser = Parser::new(&[0x00, 0x41, 0x00, 0x0b, 0x02, 0x03, 0x04]);
+        let elem: ElemSegment = unwrap(parser.parse());
+        assert!(matches!(&elem.mode, ElemMode::Active { idx: 0, offset } if offset.len() == 1));
+        assert_eq!(elem.ty, RefType::FuncRef);
+        assert!(matches!(
+            elem.init.as_slice(),
+            [a, b] if matches!(a.as_slice(), [Instruction { kind: InsnKind::RefFunc(3), .. }]) &&
+                      matches!(b.as_slice(), [Instruction { kind: InsnKind::RefFunc(4), .. }])
+        ));
+
+        // Passive element segment with element kind
+        let mut parser = Parser::new(&[0x01, 0x00, 0x01, 0x05]);
+        let elem: ElemSegment = unwrap(parser.parse());
+        assert!(matches!(elem.mode, ElemMode::Passive));
+        assert_eq!(elem.init.len(), 1);
+
+        // Active element segment with explicit table index and element kind
+        let mut parser = Parser::new(&[0x02, 0x00, 0x41, 0x00, 0x0b, 0x00, 0x00]);
+        let elem: ElemSegment = unwrap(parser.parse());
+        assert!(matches!(elem.mode, ElemMode::Active { idx: 0, .. }));
+        assert!(elem.init.is_empty());
+
+        // Declarative element segment with element kind
+        let mut parser = Parser::new(&[0x03, 0x00, 0x01, 0x00]);
+        let elem: ElemSegment = unwrap(parser.parse());
+        assert!(matches!(elem.mode, ElemMode::Declarative));
+        assert_eq!(elem.init.len(), 1);
+
+        // Passive element segment of externref with element expressions
+        let mut parser = Parser::new(&[0x05, 0x6f, 0x02, 0xd0, 0x6f, 0x0b, 0xd0, 0x6f, 0x0b]);
+        let elem: ElemSegment = unwrap(parser.parse());
+        assert!(matches!(elem.mode, ElemMode::Passive));
+        assert_eq!(elem.ty, RefType::ExternRef);
+        assert!(elem.init.iter().all(|e| matches!(
+            e.as_slice(),
+            [Instruction {
+                kind: InsnKind::RefNull(RefType::ExternRef),
+                ..
+            }]
+        )));
+
+        // Active element segment with explicit table index and element expressions
+        let mut parser = Parser::new(&[0x06, 0x01, 0x41, 0x00, 0x0b, 0x70, 0x01, 0xd2, 0x00, 0x0b]);
+        let elem: ElemSegment = unwrap(parser.parse());
+        assert!(matches!(elem.mode, ElemMode::Active { idx: 1, .. }));
+        assert_eq!(elem.init.len(), 1);
+
+        let mut parser = Parser::new(&[0x08, 0x00]);
+        assert!(parser.parse::<ElemSegment>().is_err());
+
+        let mut parser = Parser::new(&[0x03, 0x00]);
+        assert!(parser.parse::<DataSegment<'_>>().is_err());
+    }
 }
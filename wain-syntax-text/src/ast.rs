@@ -17,6 +17,9 @@ pub struct Parsed<'s> {
     pub table_indices: Indices<'s>,
     pub mem_indices: Indices<'s>,
     pub global_indices: Indices<'s>,
+    pub elem_indices: Indices<'s>,
+    pub data_indices: Indices<'s>,
+    pub tag_indices: Indices<'s>,
 }
 
 // Note: Since crate for syntax tree data structure is separated, all fields of AST node structs need
@@ -35,6 +38,7 @@ pub struct Module<'s> {
     pub data: Vec<Data<'s>>,
     pub memories: Vec<Memory<'s>>,
     pub globals: Vec<Global<'s>>,
+    pub tags: Vec<Tag<'s>>,
     pub entrypoint: Option<Start<'s>>,
 }
 
@@ -79,6 +83,17 @@ pub enum ValType {
     I64,
     F32,
     F64,
+    V128,
+    FuncRef,
+    ExternRef,
+}
+
+// https://webassembly.github.io/spec/core/text/types.html#text-reftype
+#[derive(PartialEq, Clone, Copy)]
+#[cfg_attr(test, derive(Debug))]
+pub enum RefType {
+    FuncRef,
+    ExternRef,
 }
 
 // https://webassembly.github.io/spec/core/text/modules.html#text-import
@@ -122,23 +137,25 @@ impl<'s> fmt::Display for Index<'s> {
 }
 
 // https://webassembly.github.io/spec/core/text/types.html#text-tabletype
-// Note: elemtype is currently fixed to 'funcref'
 #[cfg_attr(test, derive(Debug))]
 pub struct TableType {
     pub limit: Limits,
+    pub elem: RefType,
 }
 
 // https://webassembly.github.io/spec/core/text/types.html#text-limits
 #[cfg_attr(test, derive(Debug))]
 pub enum Limits {
-    Range { min: u32, max: u32 },
-    From { min: u32 },
+    Range { min: u64, max: u64 },
+    From { min: u64 },
 }
 
 // https://webassembly.github.io/spec/core/text/types.html#text-memtype
 #[cfg_attr(test, derive(Debug))]
 pub struct MemType {
     pub limit: Limits,
+    pub shared: bool,
+    pub memory64: bool,
 }
 
 // https://webassembly.github.io/spec/core/text/types.html#text-globaltype
@@ -164,6 +181,7 @@ pub enum ExportKind {
     Table,
     Memory,
     Global,
+    Tag,
 }
 
 // https://webassembly.github.io/spec/core/text/modules.html#text-func
@@ -200,9 +218,34 @@ pub struct Instruction<'s> {
 
 // https://webassembly.github.io/spec/core/text/instructions.html#text-memarg
 #[cfg_attr(test, derive(Debug))]
-pub struct Mem {
+pub struct Mem<'s> {
     pub align: Option<u8>,
-    pub offset: Option<u32>,
+    pub offset: Option<u64>,
+    pub memory: Index<'s>,
+}
+
+// https://webassembly.github.io/spec/core/text/instructions.html#text-blocktype
+#[cfg_attr(test, derive(Debug))]
+pub enum BlockType<'s> {
+    Empty,
+    Value(ValType),
+    TypeUse(TypeUse<'s>),
+}
+
+// https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#try-catch-blocks
+#[cfg_attr(test, derive(Debug))]
+pub enum TryHandler<'s> {
+    Catch {
+        catches: Vec<Catch<'s>>,
+        catch_all: Option<Vec<Instruction<'s>>>,
+    },
+    Delegate(Index<'s>),
+}
+#[cfg_attr(test, derive(Debug))]
+pub struct Catch<'s> {
+    pub start: usize,
+    pub tag: Index<'s>,
+    pub body: Vec<Instruction<'s>>,
 }
 
 #[cfg_attr(test, derive(Debug))]
@@ -211,19 +254,19 @@ pub enum InsnKind<'s> {
     // https://webassembly.github.io/spec/core/text/instructions.html#control-instructions
     Block {
         label: Option<&'s str>,
-        ty: Option<ValType>,
+        ty: BlockType<'s>,
         body: Vec<Instruction<'s>>,
         id: Option<&'s str>,
     },
     Loop {
         label: Option<&'s str>,
-        ty: Option<ValType>,
+        ty: BlockType<'s>,
         body: Vec<Instruction<'s>>,
         id: Option<&'s str>,
     },
     If {
         label: Option<&'s str>,
-        ty: Option<ValType>,
+        ty: BlockType<'s>,
         then_body: Vec<Instruction<'s>>,
         else_id: Option<&'s str>,
         else_body: Vec<Instruction<'s>>,
@@ -239,11 +282,36 @@ pub enum InsnKind<'s> {
     },
     Return,
     Call(Index<'s>),
-    CallIndirect(TypeUse<'s>),
+    CallIndirect {
+        table: Index<'s>,
+        ty: TypeUse<'s>,
+    },
+    ReturnCall(Index<'s>),
+    ReturnCallIndirect {
+        table: Index<'s>,
+        ty: TypeUse<'s>,
+    },
+    // Exception handling
+    // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md
+    Try {
+        label: Option<&'s str>,
+        ty: BlockType<'s>,
+        body: Vec<Instruction<'s>>,
+        handler: TryHandler<'s>,
+        id: Option<&'s str>,
+    },
+    Throw(Index<'s>),
+    Rethrow(Index<'s>),
+    // Reference instructions
+    // https://webassembly.github.io/spec/core/text/instructions.html#reference-instructions
+    RefNull(RefType),
+    RefIsNull,
+    RefFunc(Index<'s>),
     // Parametric instructions
     // https://webassembly.github.io/spec/core/text/instructions.html#parametric-instructions
     Drop,
     Select,
+    TypedSelect(Vec<ValType>),
     // Variable instructions
     // https://webassembly.github.io/spec/core/text/instructions.html#variable-instructions
     LocalGet(Index<'s>),
@@ -253,31 +321,57 @@ pub enum InsnKind<'s> {
     GlobalSet(Index<'s>),
     // Memory instructions
     // https://webassembly.github.io/spec/core/text/instructions.html#memory-instructions
-    I32Load(Mem),
-    I64Load(Mem),
-    F32Load(Mem),
-    F64Load(Mem),
-    I32Load8S(Mem),
-    I32Load8U(Mem),
-    I32Load16S(Mem),
-    I32Load16U(Mem),
-    I64Load8S(Mem),
-    I64Load8U(Mem),
-    I64Load16S(Mem),
-    I64Load16U(Mem),
-    I64Load32S(Mem),
-    I64Load32U(Mem),
-    I32Store(Mem),
-    I64Store(Mem),
-    F32Store(Mem),
-    F64Store(Mem),
-    I32Store8(Mem),
-    I32Store16(Mem),
-    I64Store8(Mem),
-    I64Store16(Mem),
-    I64Store32(Mem),
-    MemorySize,
-    MemoryGrow,
+    I32Load(Mem<'s>),
+    I64Load(Mem<'s>),
+    F32Load(Mem<'s>),
+    F64Load(Mem<'s>),
+    I32Load8S(Mem<'s>),
+    I32Load8U(Mem<'s>),
+    I32Load16S(Mem<'s>),
+    I32Load16U(Mem<'s>),
+    I64Load8S(Mem<'s>),
+    I64Load8U(Mem<'s>),
+    I64Load16S(Mem<'s>),
+    I64Load16U(Mem<'s>),
+    I64Load32S(Mem<'s>),
+    I64Load32U(Mem<'s>),
+    I32Store(Mem<'s>),
+    I64Store(Mem<'s>),
+    F32Store(Mem<'s>),
+    F64Store(Mem<'s>),
+    I32Store8(Mem<'s>),
+    I32Store16(Mem<'s>),
+    I64Store8(Mem<'s>),
+    I64Store16(Mem<'s>),
+    I64Store32(Mem<'s>),
+    MemorySize(Index<'s>),
+    MemoryGrow(Index<'s>),
+    MemoryInit {
+        memory: Index<'s>,
+        data: Index<'s>,
+    },
+    DataDrop(Index<'s>),
+    MemoryCopy {
+        dst: Index<'s>,
+        src: Index<'s>,
+    },
+    MemoryFill(Index<'s>),
+    // Table instructions
+    // https://webassembly.github.io/spec/core/text/instructions.html#table-instructions
+    TableGet(Index<'s>),
+    TableSet(Index<'s>),
+    TableSize(Index<'s>),
+    TableGrow(Index<'s>),
+    TableFill(Index<'s>),
+    TableCopy {
+        dst: Index<'s>,
+        src: Index<'s>,
+    },
+    TableInit {
+        table: Index<'s>,
+        elem: Index<'s>,
+    },
+    ElemDrop(Index<'s>),
     // Numeric instructions
     // https://webassembly.github.io/spec/core/text/instructions.html#numeric-instructions
     // Constants
@@ -417,13 +511,359 @@ pub enum InsnKind<'s> {
     I64ReinterpretF64,
     F32ReinterpretI32,
     F64ReinterpretI64,
+    // Sign extension
+    I32Extend8S,
+    I32Extend16S,
+    I64Extend8S,
+    I64Extend16S,
+    I64Extend32S,
+    // Saturating truncation
+    I32TruncSatF32S,
+    I32TruncSatF32U,
+    I32TruncSatF64S,
+    I32TruncSatF64U,
+    I64TruncSatF32S,
+    I64TruncSatF32U,
+    I64TruncSatF64S,
+    I64TruncSatF64U,
+    // Vector instructions
+    // https://webassembly.github.io/spec/core/text/instructions.html#vector-instructions
+    V128Load(Mem<'s>),
+    V128Load8x8S(Mem<'s>),
+    V128Load8x8U(Mem<'s>),
+    V128Load16x4S(Mem<'s>),
+    V128Load16x4U(Mem<'s>),
+    V128Load32x2S(Mem<'s>),
+    V128Load32x2U(Mem<'s>),
+    V128Load8Splat(Mem<'s>),
+    V128Load16Splat(Mem<'s>),
+    V128Load32Splat(Mem<'s>),
+    V128Load64Splat(Mem<'s>),
+    V128Store(Mem<'s>),
+    V128Const(i128),
+    I8x16Shuffle([u8; 16]),
+    I8x16Swizzle,
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+    I8x16ExtractLaneS(u8),
+    I8x16ExtractLaneU(u8),
+    I8x16ReplaceLane(u8),
+    I16x8ExtractLaneS(u8),
+    I16x8ExtractLaneU(u8),
+    I16x8ReplaceLane(u8),
+    I32x4ExtractLane(u8),
+    I32x4ReplaceLane(u8),
+    I64x2ExtractLane(u8),
+    I64x2ReplaceLane(u8),
+    F32x4ExtractLane(u8),
+    F32x4ReplaceLane(u8),
+    F64x2ExtractLane(u8),
+    F64x2ReplaceLane(u8),
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    V128Not,
+    V128And,
+    V128Andnot,
+    V128Or,
+    V128Xor,
+    V128Bitselect,
+    V128AnyTrue,
+    V128Load8Lane {
+        mem: Mem<'s>,
+        lane: u8,
+    },
+    V128Load16Lane {
+        mem: Mem<'s>,
+        lane: u8,
+    },
+    V128Load32Lane {
+        mem: Mem<'s>,
+        lane: u8,
+    },
+    V128Load64Lane {
+        mem: Mem<'s>,
+        lane: u8,
+    },
+    V128Store8Lane {
+        mem: Mem<'s>,
+        lane: u8,
+    },
+    V128Store16Lane {
+        mem: Mem<'s>,
+        lane: u8,
+    },
+    V128Store32Lane {
+        mem: Mem<'s>,
+        lane: u8,
+    },
+    V128Store64Lane {
+        mem: Mem<'s>,
+        lane: u8,
+    },
+    V128Load32Zero(Mem<'s>),
+    V128Load64Zero(Mem<'s>),
+    F32x4DemoteF64x2Zero,
+    F64x2PromoteLowF32x4,
+    I8x16Abs,
+    I8x16Neg,
+    I8x16Popcnt,
+    I8x16AllTrue,
+    I8x16Bitmask,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    F64x2Ceil,
+    F64x2Floor,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    F64x2Trunc,
+    I8x16AvgrU,
+    I16x8ExtaddPairwiseI8x16S,
+    I16x8ExtaddPairwiseI8x16U,
+    I32x4ExtaddPairwiseI16x8S,
+    I32x4ExtaddPairwiseI16x8U,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8Q15mulrSatS,
+    I16x8AllTrue,
+    I16x8Bitmask,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8ExtendLowI8x16S,
+    I16x8ExtendHighI8x16S,
+    I16x8ExtendLowI8x16U,
+    I16x8ExtendHighI8x16U,
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    F64x2Nearest,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8AvgrU,
+    I16x8ExtmulLowI8x16S,
+    I16x8ExtmulHighI8x16S,
+    I16x8ExtmulLowI8x16U,
+    I16x8ExtmulHighI8x16U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4AllTrue,
+    I32x4Bitmask,
+    I32x4ExtendLowI16x8S,
+    I32x4ExtendHighI16x8S,
+    I32x4ExtendLowI16x8U,
+    I32x4ExtendHighI16x8U,
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtmulLowI16x8S,
+    I32x4ExtmulHighI16x8S,
+    I32x4ExtmulLowI16x8U,
+    I32x4ExtmulHighI16x8U,
+    I64x2Abs,
+    I64x2Neg,
+    I64x2AllTrue,
+    I64x2Bitmask,
+    I64x2ExtendLowI32x4S,
+    I64x2ExtendHighI32x4S,
+    I64x2ExtendLowI32x4U,
+    I64x2ExtendHighI32x4U,
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+    I64x2Add,
+    I64x2Sub,
+    I64x2Mul,
+    I64x2Eq,
+    I64x2Ne,
+    I64x2LtS,
+    I64x2GtS,
+    I64x2LeS,
+    I64x2GeS,
+    I64x2ExtmulLowI32x4S,
+    I64x2ExtmulHighI32x4S,
+    I64x2ExtmulLowI32x4U,
+    I64x2ExtmulHighI32x4U,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4Pmin,
+    F32x4Pmax,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2Pmin,
+    F64x2Pmax,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+    I32x4TruncSatF64x2SZero,
+    I32x4TruncSatF64x2UZero,
+    F64x2ConvertLowI32x4S,
+    F64x2ConvertLowI32x4U,
+    // Atomic instructions
+    // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md
+    MemoryAtomicNotify(Mem<'s>),
+    MemoryAtomicWait32(Mem<'s>),
+    MemoryAtomicWait64(Mem<'s>),
+    AtomicFence,
+    I32AtomicLoad(Mem<'s>),
+    I64AtomicLoad(Mem<'s>),
+    I32AtomicLoad8U(Mem<'s>),
+    I32AtomicLoad16U(Mem<'s>),
+    I64AtomicLoad8U(Mem<'s>),
+    I64AtomicLoad16U(Mem<'s>),
+    I64AtomicLoad32U(Mem<'s>),
+    I32AtomicStore(Mem<'s>),
+    I64AtomicStore(Mem<'s>),
+    I32AtomicStore8(Mem<'s>),
+    I32AtomicStore16(Mem<'s>),
+    I64AtomicStore8(Mem<'s>),
+    I64AtomicStore16(Mem<'s>),
+    I64AtomicStore32(Mem<'s>),
+    I32AtomicRmwAdd(Mem<'s>),
+    I64AtomicRmwAdd(Mem<'s>),
+    I32AtomicRmw8AddU(Mem<'s>),
+    I32AtomicRmw16AddU(Mem<'s>),
+    I64AtomicRmw8AddU(Mem<'s>),
+    I64AtomicRmw16AddU(Mem<'s>),
+    I64AtomicRmw32AddU(Mem<'s>),
+    I32AtomicRmwSub(Mem<'s>),
+    I64AtomicRmwSub(Mem<'s>),
+    I32AtomicRmw8SubU(Mem<'s>),
+    I32AtomicRmw16SubU(Mem<'s>),
+    I64AtomicRmw8SubU(Mem<'s>),
+    I64AtomicRmw16SubU(Mem<'s>),
+    I64AtomicRmw32SubU(Mem<'s>),
+    I32AtomicRmwAnd(Mem<'s>),
+    I64AtomicRmwAnd(Mem<'s>),
+    I32AtomicRmw8AndU(Mem<'s>),
+    I32AtomicRmw16AndU(Mem<'s>),
+    I64AtomicRmw8AndU(Mem<'s>),
+    I64AtomicRmw16AndU(Mem<'s>),
+    I64AtomicRmw32AndU(Mem<'s>),
+    I32AtomicRmwOr(Mem<'s>),
+    I64AtomicRmwOr(Mem<'s>),
+    I32AtomicRmw8OrU(Mem<'s>),
+    I32AtomicRmw16OrU(Mem<'s>),
+    I64AtomicRmw8OrU(Mem<'s>),
+    I64AtomicRmw16OrU(Mem<'s>),
+    I64AtomicRmw32OrU(Mem<'s>),
+    I32AtomicRmwXor(Mem<'s>),
+    I64AtomicRmwXor(Mem<'s>),
+    I32AtomicRmw8XorU(Mem<'s>),
+    I32AtomicRmw16XorU(Mem<'s>),
+    I64AtomicRmw8XorU(Mem<'s>),
+    I64AtomicRmw16XorU(Mem<'s>),
+    I64AtomicRmw32XorU(Mem<'s>),
+    I32AtomicRmwXchg(Mem<'s>),
+    I64AtomicRmwXchg(Mem<'s>),
+    I32AtomicRmw8XchgU(Mem<'s>),
+    I32AtomicRmw16XchgU(Mem<'s>),
+    I64AtomicRmw8XchgU(Mem<'s>),
+    I64AtomicRmw16XchgU(Mem<'s>),
+    I64AtomicRmw32XchgU(Mem<'s>),
+    I32AtomicRmwCmpxchg(Mem<'s>),
+    I64AtomicRmwCmpxchg(Mem<'s>),
+    I32AtomicRmw8CmpxchgU(Mem<'s>),
+    I32AtomicRmw16CmpxchgU(Mem<'s>),
+    I64AtomicRmw8CmpxchgU(Mem<'s>),
+    I64AtomicRmw16CmpxchgU(Mem<'s>),
+    I64AtomicRmw32CmpxchgU(Mem<'s>),
 }
 
 impl<'s> InsnKind<'s> {
     pub fn is_block(&self) -> bool {
         use InsnKind::*;
         match self {
-            Block { .. } | Loop { .. } | If { .. } => true,
+            Block { .. } | Loop { .. } | If { .. } | Try { .. } => true,
             _ => false,
         }
     }
@@ -431,11 +871,21 @@ impl<'s> InsnKind<'s> {
 
 // https://webassembly.github.io/spec/core/text/modules.html#element-segments
 #[cfg_attr(test, derive(Debug))]
+pub enum ElemMode<'s> {
+    Passive,
+    Active {
+        idx: Index<'s>,
+        offset: Vec<Instruction<'s>>,
+    },
+    Declarative,
+}
+#[cfg_attr(test, derive(Debug))]
 pub struct Elem<'s> {
     pub start: usize,
-    pub idx: Index<'s>,
-    pub offset: Vec<Instruction<'s>>,
-    pub init: Vec<Index<'s>>,
+    pub id: Option<&'s str>,
+    pub ty: RefType,
+    pub mode: ElemMode<'s>,
+    pub init: Vec<Vec<Instruction<'s>>>,
 }
 
 // https://webassembly.github.io/spec/core/text/modules.html#tables
@@ -449,10 +899,18 @@ pub struct Table<'s> {
 
 // https://webassembly.github.io/spec/core/text/modules.html#text-data
 #[cfg_attr(test, derive(Debug))]
+pub enum DataMode<'s> {
+    Passive,
+    Active {
+        idx: Index<'s>,
+        offset: Vec<Instruction<'s>>,
+    },
+}
+#[cfg_attr(test, derive(Debug))]
 pub struct Data<'s> {
     pub start: usize,
-    pub idx: Index<'s>,
-    pub offset: Vec<Instruction<'s>>,
+    pub id: Option<&'s str>,
+    pub mode: DataMode<'s>,
     pub data: Cow<'s, [u8]>,
 }
 
@@ -479,6 +937,15 @@ pub struct Global<'s> {
     pub kind: GlobalKind<'s>,
 }
 
+// https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#tag-definitions
+#[cfg_attr(test, derive(Debug))]
+pub struct Tag<'s> {
+    pub start: usize,
+    pub id: Option<&'s str>,
+    pub ty: TypeUse<'s>,
+    pub import: Option<Import>,
+}
+
 // https://webassembly.github.io/spec/core/text/modules.html#text-start
 #[cfg_attr(test, derive(Debug))]
 pub struct Start<'s> {
@@ -494,7 +961,7 @@ mod tests {
     fn insn_is_block() {
         let insn = InsnKind::Block {
             label: None,
-            ty: None,
+            ty: BlockType::Empty,
             body: vec![],
             id: None,
         };
@@ -101,11 +101,12 @@ impl<'s> Composer<'s> {
             && self.target.tables.is_empty()
             && self.target.memories.is_empty()
             && self.target.globals.is_empty()
+            && self.target.tags.is_empty()
             || !self.composing_imports)
         {
             let msg = "when module M1 is merged into module M2, one of (1) or (2) must be met. \
                         (1) M1 has no 'import' section. \
-                        (2) M2 has no 'func', 'table', 'memory' sections"
+                        (2) M2 has no 'func', 'table', 'memory', 'tag' sections"
                 .to_string();
             return self.error(msg, self.target.start);
         }
@@ -119,6 +120,7 @@ impl<'s> Composer<'s> {
         self.target.data.append(&mut composed.data);
         self.target.memories.append(&mut composed.memories);
         self.target.globals.append(&mut composed.globals);
+        self.target.tags.append(&mut composed.tags);
         if let Some(start) = composed.entrypoint {
             self.target.entrypoint = Some(start);
         }
@@ -149,11 +151,23 @@ impl<'s> Composer<'s> {
     fn adjust_table_idx(&self, idx: &mut u32) {
         *idx += self.target.tables.len() as u32;
     }
+
+    fn adjust_elem_idx(&self, idx: &mut u32) {
+        *idx += self.target.elems.len() as u32;
+    }
+
+    fn adjust_data_idx(&self, idx: &mut u32) {
+        *idx += self.target.data.len() as u32;
+    }
+
+    fn adjust_tag_idx(&self, idx: &mut u32) {
+        *idx += self.target.tags.len() as u32;
+    }
 }
 
 // Adjust fields of AST nodes for composing one module into another.
-// Indices of functions, tables, memories, globals and types are module local things. When two modules
-// are composed, these indices in the merged module need to be updated.
+// Indices of functions, tables, memories, globals, types and segments are module local things. When two
+// modules are composed, these indices in the merged module need to be updated.
 // This visitor also checks the condition of composing two modules.
 trait Adjust<'s>: Sized {
     fn adjust(&mut self, composer: &mut Composer) -> Result<'s, ()>;
@@ -186,6 +200,7 @@ impl<'s> Adjust<'s> for Module<'s> {
         self.data.adjust(composer)?;
         self.memories.adjust(composer)?;
         self.globals.adjust(composer)?;
+        self.tags.adjust(composer)?;
         Ok(())
     }
 }
@@ -212,26 +227,186 @@ impl<'s> Adjust<'s> for Instruction {
     fn adjust(&mut self, composer: &mut Composer) -> Result<'s, ()> {
         use InsnKind::*;
         match &mut self.kind {
-            Block { body, .. } => body.adjust(composer)?,
-            Loop { body, .. } => body.adjust(composer)?,
+            Block { ty, body } | Loop { ty, body } => {
+                ty.adjust(composer)?;
+                body.adjust(composer)?;
+            }
             If {
+                ty,
                 then_body,
                 else_body,
-                ..
             } => {
+                ty.adjust(composer)?;
                 then_body.adjust(composer)?;
                 else_body.adjust(composer)?;
             }
-            Call(idx) => composer.adjust_func_idx(idx),
-            CallIndirect(idx) => composer.adjust_type_idx(idx),
+            Try { ty, body, handler } => {
+                ty.adjust(composer)?;
+                body.adjust(composer)?;
+                if let TryHandler::Catch { catches, catch_all } = handler {
+                    for catch in catches.iter_mut() {
+                        composer.adjust_tag_idx(&mut catch.tag);
+                        catch.body.adjust(composer)?;
+                    }
+                    catch_all.adjust(composer)?;
+                }
+            }
+            Throw(idx) => composer.adjust_tag_idx(idx),
+            Call(idx) | ReturnCall(idx) | RefFunc(idx) => composer.adjust_func_idx(idx),
+            CallIndirect { table, ty } | ReturnCallIndirect { table, ty } => {
+                composer.adjust_table_idx(table);
+                composer.adjust_type_idx(ty);
+            }
             GlobalGet(idx) => composer.adjust_global_idx(idx),
             GlobalSet(idx) => composer.adjust_global_idx(idx),
+            MemoryInit { memory, data } => {
+                composer.adjust_mem_idx(memory);
+                composer.adjust_data_idx(data);
+            }
+            DataDrop(idx) => composer.adjust_data_idx(idx),
+            MemorySize(idx) | MemoryGrow(idx) | MemoryFill(idx) => composer.adjust_mem_idx(idx),
+            MemoryCopy { dst, src } => {
+                composer.adjust_mem_idx(dst);
+                composer.adjust_mem_idx(src);
+            }
+            I32Load(mem)
+            | I64Load(mem)
+            | F32Load(mem)
+            | F64Load(mem)
+            | I32Load8S(mem)
+            | I32Load8U(mem)
+            | I32Load16S(mem)
+            | I32Load16U(mem)
+            | I64Load8S(mem)
+            | I64Load8U(mem)
+            | I64Load16S(mem)
+            | I64Load16U(mem)
+            | I64Load32S(mem)
+            | I64Load32U(mem)
+            | I32Store(mem)
+            | I64Store(mem)
+            | F32Store(mem)
+            | F64Store(mem)
+            | I32Store8(mem)
+            | I32Store16(mem)
+            | I64Store8(mem)
+            | I64Store16(mem)
+            | I64Store32(mem)
+            | V128Load(mem)
+            | V128Load8x8S(mem)
+            | V128Load8x8U(mem)
+            | V128Load16x4S(mem)
+            | V128Load16x4U(mem)
+            | V128Load32x2S(mem)
+            | V128Load32x2U(mem)
+            | V128Load8Splat(mem)
+            | V128Load16Splat(mem)
+            | V128Load32Splat(mem)
+            | V128Load64Splat(mem)
+            | V128Store(mem)
+            | V128Load32Zero(mem)
+            | V128Load64Zero(mem)
+            | V128Load8Lane { mem, .. }
+            | V128Load16Lane { mem, .. }
+            | V128Load32Lane { mem, .. }
+            | V128Load64Lane { mem, .. }
+            | V128Store8Lane { mem, .. }
+            | V128Store16Lane { mem, .. }
+            | V128Store32Lane { mem, .. }
+            | V128Store64Lane { mem, .. }
+            | MemoryAtomicNotify(mem)
+            | MemoryAtomicWait32(mem)
+            | MemoryAtomicWait64(mem)
+            | I32AtomicLoad(mem)
+            | I64AtomicLoad(mem)
+            | I32AtomicLoad8U(mem)
+            | I32AtomicLoad16U(mem)
+            | I64AtomicLoad8U(mem)
+            | I64AtomicLoad16U(mem)
+            | I64AtomicLoad32U(mem)
+            | I32AtomicStore(mem)
+            | I64AtomicStore(mem)
+            | I32AtomicStore8(mem)
+            | I32AtomicStore16(mem)
+            | I64AtomicStore8(mem)
+            | I64AtomicStore16(mem)
+            | I64AtomicStore32(mem)
+            | I32AtomicRmwAdd(mem)
+            | I64AtomicRmwAdd(mem)
+            | I32AtomicRmw8AddU(mem)
+            | I32AtomicRmw16AddU(mem)
+            | I64AtomicRmw8AddU(mem)
+            | I64AtomicRmw16AddU(mem)
+            | I64AtomicRmw32AddU(mem)
+            | I32AtomicRmwSub(mem)
+            | I64AtomicRmwSub(mem)
+            | I32AtomicRmw8SubU(mem)
+            | I32AtomicRmw16SubU(mem)
+            | I64AtomicRmw8SubU(mem)
+            | I64AtomicRmw16SubU(mem)
+            | I64AtomicRmw32SubU(mem)
+            | I32AtomicRmwAnd(mem)
+            | I64AtomicRmwAnd(mem)
+            | I32AtomicRmw8AndU(mem)
+            | I32AtomicRmw16AndU(mem)
+            | I64AtomicRmw8AndU(mem)
+            | I64AtomicRmw16AndU(mem)
+            | I64AtomicRmw32AndU(mem)
+            | I32AtomicRmwOr(mem)
+            | I64AtomicRmwOr(mem)
+            | I32AtomicRmw8OrU(mem)
+            | I32AtomicRmw16OrU(mem)
+            | I64AtomicRmw8OrU(mem)
+            | I64AtomicRmw16OrU(mem)
+            | I64AtomicRmw32OrU(mem)
+            | I32AtomicRmwXor(mem)
+            | I64AtomicRmwXor(mem)
+            | I32AtomicRmw8XorU(mem)
+            | I32AtomicRmw16XorU(mem)
+            | I64AtomicRmw8XorU(mem)
+            | I64AtomicRmw16XorU(mem)
+            | I64AtomicRmw32XorU(mem)
+            | I32AtomicRmwXchg(mem)
+            | I64AtomicRmwXchg(mem)
+            | I32AtomicRmw8XchgU(mem)
+            | I32AtomicRmw16XchgU(mem)
+            | I64AtomicRmw8XchgU(mem)
+            | I64AtomicRmw16XchgU(mem)
+            | I64AtomicRmw32XchgU(mem)
+            | I32AtomicRmwCmpxchg(mem)
+            | I64AtomicRmwCmpxchg(mem)
+            | I32AtomicRmw8CmpxchgU(mem)
+            | I32AtomicRmw16CmpxchgU(mem)
+            | I64AtomicRmw8CmpxchgU(mem)
+            | I64AtomicRmw16CmpxchgU(mem)
+            | I64AtomicRmw32CmpxchgU(mem) => composer.adjust_mem_idx(&mut mem.memory),
+            TableGet(idx) | TableSet(idx) | TableSize(idx) | TableGrow(idx) | TableFill(idx) => {
+                composer.adjust_table_idx(idx)
+            }
+            TableCopy { dst, src } => {
+                composer.adjust_table_idx(dst);
+                composer.adjust_table_idx(src);
+            }
+            TableInit { table, elem } => {
+                composer.adjust_table_idx(table);
+                composer.adjust_elem_idx(elem);
+            }
+            ElemDrop(idx) => composer.adjust_elem_idx(idx),
             _ => {}
         }
         Ok(())
     }
 }
 
+impl<'s> Adjust<'s> for BlockType {
+    fn adjust(&mut self, composer: &mut Composer) -> Result<'s, ()> {
+        if let BlockType::Type(idx) = self {
+            composer.adjust_type_idx(idx);
+        }
+        Ok(())
+    }
+}
+
 impl<'s> Adjust<'s> for Export<'s> {
     fn adjust(&mut self, composer: &mut Composer) -> Result<'s, ()> {
         match &mut self.kind {
@@ -239,6 +414,7 @@ impl<'s> Adjust<'s> for Export<'s> {
             ExportKind::Table(idx) => composer.adjust_table_idx(idx),
             ExportKind::Memory(idx) => composer.adjust_mem_idx(idx),
             ExportKind::Global(idx) => composer.adjust_global_idx(idx),
+            ExportKind::Tag(idx) => composer.adjust_tag_idx(idx),
         }
         Ok(())
     }
@@ -246,18 +422,26 @@ impl<'s> Adjust<'s> for Export<'s> {
 
 impl<'s> Adjust<'s> for ElemSegment {
     fn adjust(&mut self, composer: &mut Composer) -> Result<'s, ()> {
-        composer.adjust_table_idx(&mut self.idx);
-        for idx in self.init.iter_mut() {
-            composer.adjust_func_idx(idx);
+        self.init.adjust(composer)?;
+        match &mut self.mode {
+            ElemMode::Passive | ElemMode::Declarative => Ok(()),
+            ElemMode::Active { idx, offset } => {
+                composer.adjust_table_idx(idx);
+                offset.adjust(composer)
+            }
         }
-        self.offset.adjust(composer)
     }
 }
 
 impl<'s> Adjust<'s> for DataSegment<'s> {
     fn adjust(&mut self, composer: &mut Composer) -> Result<'s, ()> {
-        composer.adjust_mem_idx(&mut self.idx);
-        self.offset.adjust(composer)
+        match &mut self.mode {
+            DataMode::Passive => Ok(()),
+            DataMode::Active { idx, offset } => {
+                composer.adjust_mem_idx(idx);
+                offset.adjust(composer)
+            }
+        }
     }
 }
 
@@ -277,3 +461,11 @@ impl<'s> Adjust<'s> for Global<'s> {
         Ok(())
     }
 }
+
+impl<'s> Adjust<'s> for Tag<'s> {
+    fn adjust(&mut self, composer: &mut Composer) -> Result<'s, ()> {
+        composer.adjust_type_idx(&mut self.idx);
+        composer.saw_import(self.import.is_some());
+        Ok(())
+    }
+}
@@ -255,6 +255,9 @@ struct ParseContext<'s> {
     table_indices: Indices<'s>,
     mem_indices: Indices<'s>,
     global_indices: Indices<'s>,
+    elem_indices: Indices<'s>,
+    data_indices: Indices<'s>,
+    tag_indices: Indices<'s>,
 }
 
 impl<'s> ParseContext<'s> {
@@ -267,6 +270,9 @@ impl<'s> ParseContext<'s> {
             table_indices: Indices::new(source, "table", "module"),
             mem_indices: Indices::new(source, "memory", "module"),
             global_indices: Indices::new(source, "global", "module"),
+            elem_indices: Indices::new(source, "elem", "module"),
+            data_indices: Indices::new(source, "data", "module"),
+            tag_indices: Indices::new(source, "tag", "module"),
         }
     }
 }
@@ -433,6 +439,13 @@ impl<'s> Parser<'s> {
         })
     }
 
+    fn maybe_index(&mut self, expected: &'static str) -> Result<'s, Option<Index<'s>>> {
+        match self.peek(expected)?.0 {
+            Token::Int(..) | Token::Ident(_) => self.parse().map(Some),
+            _ => Ok(None),
+        }
+    }
+
     fn missing_paren<T>(
         &mut self,
         paren: char,
@@ -475,6 +488,26 @@ impl<'s> Parser<'s> {
         }
     }
 
+    fn parse_u64(&mut self, expected: &'static str) -> Result<'s, u64> {
+        match self.next_token(expected)? {
+            (Token::Int(Sign::Minus, base, s), offset) => {
+                self.error(ParseErrorKind::NumberMustBePositive(base, s), offset)
+            }
+            (Token::Int(_, base, s), offset) => parse_u64_str(self, s, base, Sign::Plus, offset),
+            (tok, offset) => self.unexpected_token(tok.clone(), expected, offset),
+        }
+    }
+
+    fn parse_u8(&mut self, expected: &'static str) -> Result<'s, u8> {
+        match self.next_token(expected)? {
+            (Token::Int(Sign::Minus, base, s), offset) => {
+                self.error(ParseErrorKind::NumberMustBePositive(base, s), offset)
+            }
+            (Token::Int(_, base, s), offset) => parse_u8_str(self, s, base, Sign::Plus, offset),
+            (tok, offset) => self.unexpected_token(tok.clone(), expected, offset),
+        }
+    }
+
     fn parse_bytes_encoded_in_string(&self, src: &str, offset: usize) -> Result<'s, Vec<u8>> {
         let mut buf: Vec<u8> = vec![];
         let mut chars = src.char_indices();
@@ -580,6 +613,7 @@ macro_rules! parse_uint_function {
 }
 
 parse_uint_function!(parse_u8_str, u8);
+parse_uint_function!(parse_u16_str, u16);
 parse_uint_function!(parse_u32_str, u32);
 parse_uint_function!(parse_u64_str, u64);
 
@@ -660,6 +694,179 @@ macro_rules! match_token {
     };
 }
 
+// Note: Integer operand takes range of iN::MIN <= i <= uN::MAX. When the value is over iN::MAX, it
+// is treated as uN value and bitcasted to iN
+macro_rules! parse_int_function {
+    ($name:ident, $parse_uint:ident, $uint:ty, $int:ty) => {
+        fn $name<'s>(parser: &mut Parser<'s>, expected: &'static str) -> Result<'s, $int> {
+            let ((sign, base, digits), offset) =
+                match_token!(parser, expected, Token::Int(s, b, d) => (s, b, d));
+            let u = $parse_uint(parser, digits, base, sign, offset)?;
+            if sign == Sign::Plus {
+                Ok(u as $int)
+            } else if u == <$int>::MAX as $uint + 1 {
+                // u as iN causes overflow
+                Ok(<$int>::MIN)
+            } else if u <= <$int>::MAX as $uint {
+                Ok(-(u as $int))
+            } else {
+                parser.cannot_parse_num(
+                    concat!("too small integer for ", stringify!($int)),
+                    digits,
+                    base,
+                    sign,
+                    offset,
+                )
+            }
+        }
+    };
+}
+
+parse_int_function!(parse_i8, parse_u8_str, u8, i8);
+parse_int_function!(parse_i16, parse_u16_str, u16, i16);
+parse_int_function!(parse_i32, parse_u32_str, u32, i32);
+parse_int_function!(parse_i64, parse_u64_str, u64, i64);
+
+fn parse_f32<'s>(parser: &mut Parser<'s>, expected: &'static str) -> Result<'s, f32> {
+    Ok(match parser.next_token(expected)? {
+        (Token::Float(sign, float), offset) => match float {
+            Float::Inf => match sign {
+                Sign::Plus => f32::INFINITY,
+                Sign::Minus => f32::NEG_INFINITY,
+            },
+            Float::Nan(None) => sign.apply(f32::NAN),
+            Float::Nan(Some(payload)) => {
+                // Encode  f32 NaN value via u32 assuming IEEE-754 format for NaN boxing.
+                // Palyload must be
+                //   - within 23bits (fraction of f32 is 23bits)
+                //   - >= 2^(23-1) meant that most significant bit must be 1 (since frac cannot be zero for NaN value)
+                // https://webassembly.github.io/spec/core/syntax/values.html#floating-point
+                let payload_u = parse_u32_str(parser, payload, NumBase::Hex, Sign::Plus, offset)?;
+                if payload_u == 0 || 0x80_0000 <= payload_u {
+                    return parser.cannot_parse_num(
+                        "payload of NaN for f32 must be in range of 1 <= payload < 2^23",
+                        payload,
+                        NumBase::Hex,
+                        Sign::Plus,
+                        offset,
+                    );
+                }
+                // NaN boxing. 1 <= payload_u < 2^23 and floating point number is in IEEE754 format.
+                // This will encode the payload into fraction of NaN.
+                //   0x{sign}11111111{payload}
+                let sign = match sign {
+                    Sign::Plus => 0,
+                    Sign::Minus => 1u32 << 31, // most significant bit is 1 for negative number
+                };
+                let exp = 0b1111_1111u32 << (31 - 8);
+                f32::from_bits(sign | exp | payload_u)
+            }
+            Float::Val { base, frac, exp } => {
+                // Note: Better algorithm should be considered
+                // https://github.com/rust-lang/rust/blob/3982d3514efbb65b3efac6bb006b3fa496d16663/src/libcore/num/dec2flt/algorithm.rs
+                let mut frac = sign.apply(parse_f32_str(parser, frac, base, sign, offset)?);
+                // In IEEE754, exp part is actually 8bits
+                if let Some((exp_sign, exp)) = exp {
+                    let exp = parse_u32_str(parser, exp, NumBase::Dec, exp_sign, offset)?;
+                    let step = match base {
+                        NumBase::Hex => 2.0,
+                        NumBase::Dec => 10.0,
+                    };
+                    // powi is not available because an error gets larger
+                    match exp_sign {
+                        Sign::Plus => {
+                            for _ in 0..exp {
+                                frac *= step;
+                            }
+                        }
+                        Sign::Minus => {
+                            for _ in 0..exp {
+                                frac /= step;
+                            }
+                        }
+                    }
+                    frac
+                } else {
+                    frac
+                }
+            }
+        },
+        (Token::Int(sign, base, digits), offset) => {
+            parse_f32_str(parser, digits, base, sign, offset)?
+        }
+        (tok, offset) => return parser.unexpected_token(tok, expected, offset),
+    })
+}
+
+fn parse_f64<'s>(parser: &mut Parser<'s>, expected: &'static str) -> Result<'s, f64> {
+    Ok(match parser.next_token(expected)? {
+        (Token::Float(sign, float), offset) => match float {
+            Float::Inf => match sign {
+                Sign::Plus => f64::INFINITY,
+                Sign::Minus => f64::NEG_INFINITY,
+            },
+            Float::Nan(None) => sign.apply(f64::NAN),
+            Float::Nan(Some(payload)) => {
+                // Encode f64 NaN value via u64 assuming IEEE-754 format for NaN boxing.
+                // Palyload must be
+                //   - within 52bits (since fraction of f64 is 52bits)
+                //   - >= 2^(52-1) meant that most significant bit must be 1 (since frac cannot be zero for NaN value)
+                // https://webassembly.github.io/spec/core/syntax/values.html#floating-point
+                let payload_u = parse_u64_str(parser, payload, NumBase::Hex, Sign::Plus, offset)?;
+                if payload_u == 0 || 0x10_0000_0000_0000 <= payload_u {
+                    return parser.cannot_parse_num(
+                        "payload of NaN for f64 must be in range of 1 <= payload < 2^52",
+                        payload,
+                        NumBase::Hex,
+                        Sign::Plus,
+                        offset,
+                    );
+                }
+                // NaN boxing. 1 <= payload_u < 2^52 and floating point number is in IEEE754 format.
+                // This will encode the payload into fraction of NaN.
+                //   0x{sign}11111111111{payload}
+                let sign = match sign {
+                    Sign::Plus => 0,
+                    Sign::Minus => 1u64 << 63, // most significant bit is 1 for negative number
+                };
+                let exp = 0b111_1111_1111u64 << (63 - 11);
+                f64::from_bits(sign | exp | payload_u)
+            }
+            Float::Val { base, frac, exp } => {
+                let mut frac = sign.apply(parse_f64_str(parser, frac, base, sign, offset)?);
+                // In IEEE754, exp part is actually 11bits
+                if let Some((exp_sign, exp)) = exp {
+                    let exp = parse_u32_str(parser, exp, base, exp_sign, offset)?;
+                    let step = match base {
+                        NumBase::Hex => 2.0,
+                        NumBase::Dec => 10.0,
+                    };
+                    // powi is not available because an error gets larger
+                    match exp_sign {
+                        Sign::Plus => {
+                            for _ in 0..exp {
+                                frac *= step;
+                            }
+                        }
+                        Sign::Minus => {
+                            for _ in 0..exp {
+                                frac /= step;
+                            }
+                        }
+                    }
+                    frac
+                } else {
+                    frac
+                }
+            }
+        },
+        (Token::Int(sign, base, digits), offset) => {
+            parse_f64_str(parser, digits, base, sign, offset)?
+        }
+        (tok, offset) => return parser.unexpected_token(tok, expected, offset),
+    })
+}
+
 pub trait Parse<'s>: Sized {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self>;
 }
@@ -674,6 +881,9 @@ impl<'s> Parse<'s> for Parsed<'s> {
             table_indices: parser.ctx.table_indices.move_out(),
             mem_indices: parser.ctx.mem_indices.move_out(),
             global_indices: parser.ctx.global_indices.move_out(),
+            elem_indices: parser.ctx.elem_indices.move_out(),
+            data_indices: parser.ctx.data_indices.move_out(),
+            tag_indices: parser.ctx.tag_indices.move_out(),
         })
     }
 }
@@ -692,6 +902,7 @@ enum ModuleField<'s> {
     Data(Data<'s>),
     Memory(MemoryAbbrev<'s>),
     Global(Global<'s>),
+    Tag(Tag<'s>),
     Start(Start<'s>),
 }
 
@@ -719,6 +930,7 @@ impl<'s> Parse<'s> for Module<'s> {
         let mut data = vec![];
         let mut memories = vec![];
         let mut globals = vec![];
+        let mut tags = vec![];
         let mut entrypoint = None;
 
         // Any import must be put before other definitions because indices of imports must precede
@@ -733,6 +945,7 @@ impl<'s> Parse<'s> for Module<'s> {
         let mut can_import_table = true;
         let mut can_import_mem = true;
         let mut can_import_global = true;
+        let mut can_import_tag = true;
 
         // Note: types are put in Parser struct field due to abbreviation of typeuse
         // https://webassembly.github.io/spec/core/text/modules.html#abbreviations
@@ -778,6 +991,13 @@ impl<'s> Parse<'s> for Module<'s> {
                     );
                 }
                 ModuleField::Import(ImportItem::Global(global)) => globals.push(global),
+                ModuleField::Import(ImportItem::Tag(tag)) if !can_import_tag => {
+                    return parser.error(
+                        ParseErrorKind::ImportMustPrecedeOtherDefs { what: "tag" },
+                        tag.start,
+                    );
+                }
+                ModuleField::Import(ImportItem::Tag(tag)) => tags.push(tag),
                 ModuleField::Export(export) => parser.ctx.exports.push(export),
                 ModuleField::Func(func) => {
                     if let FuncKind::Body { .. } = func.kind {
@@ -819,6 +1039,12 @@ impl<'s> Parse<'s> for Module<'s> {
                     }
                     globals.push(global);
                 }
+                ModuleField::Tag(tag) => {
+                    if tag.import.is_none() {
+                        can_import_tag = false;
+                    }
+                    tags.push(tag);
+                }
                 ModuleField::Start(start) => {
                     if let Some(prev) = entrypoint {
                         let offset = start.start;
@@ -856,6 +1082,7 @@ impl<'s> Parse<'s> for Module<'s> {
             data,
             memories,
             globals,
+            tags,
             entrypoint,
         })
     }
@@ -864,7 +1091,7 @@ impl<'s> Parse<'s> for Module<'s> {
 // https://webassembly.github.io/spec/core/text/modules.html#text-modulefield
 impl<'s> Parse<'s> for ModuleField<'s> {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
-        let expected = "one of 'type', 'import', 'export', 'func', 'elem', 'table', 'data', 'memory', 'global', 'start' sections in module";
+        let expected = "one of 'type', 'import', 'export', 'func', 'elem', 'table', 'data', 'memory', 'global', 'tag', 'start' sections in module";
         match parser.peek_fold_start(expected)? {
             (Some(kw), offset) => match kw {
                 "type" => Ok(ModuleField::Type(parser.parse()?)),
@@ -876,6 +1103,7 @@ impl<'s> Parse<'s> for ModuleField<'s> {
                 "data" => Ok(ModuleField::Data(parser.parse()?)),
                 "memory" => Ok(ModuleField::Memory(parser.parse()?)),
                 "global" => Ok(ModuleField::Global(parser.parse()?)),
+                "tag" => Ok(ModuleField::Tag(parser.parse()?)),
                 "start" => Ok(ModuleField::Start(parser.parse()?)),
                 _ => parser.error(ParseErrorKind::UnexpectedKeyword(kw), offset),
             },
@@ -964,6 +1192,9 @@ impl<'s> Parse<'s> for ValType {
             (Token::Keyword("i64"), _) => Ok(ValType::I64),
             (Token::Keyword("f32"), _) => Ok(ValType::F32),
             (Token::Keyword("f64"), _) => Ok(ValType::F64),
+            (Token::Keyword("v128"), _) => Ok(ValType::V128),
+            (Token::Keyword("funcref"), _) => Ok(ValType::FuncRef),
+            (Token::Keyword("externref"), _) => Ok(ValType::ExternRef),
             (Token::Keyword(id), offset) => {
                 parser.error(ParseErrorKind::InvalidValType(id), offset)
             }
@@ -972,6 +1203,18 @@ impl<'s> Parse<'s> for ValType {
     }
 }
 
+// https://webassembly.github.io/spec/core/text/types.html#text-reftype
+impl<'s> Parse<'s> for RefType {
+    fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
+        let expected = "'funcref' or 'externref' keyword for reference type";
+        match parser.next_token(expected)? {
+            (Token::Keyword("funcref"), _) => Ok(RefType::FuncRef),
+            (Token::Keyword("externref"), _) => Ok(RefType::ExternRef),
+            (tok, offset) => parser.unexpected_token(tok, expected, offset),
+        }
+    }
+}
+
 // https://webassembly.github.io/spec/core/text/types.html#text-result
 // Not impl for FuncResult considering abbreviation
 impl<'s> Parse<'s> for Vec<FuncResult> {
@@ -1078,6 +1321,7 @@ enum ImportItem<'s> {
     Table(Table<'s>),
     Memory(Memory<'s>),
     Global(Global<'s>),
+    Tag(Tag<'s>),
 }
 impl<'s> Parse<'s> for ImportItem<'s> {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
@@ -1086,7 +1330,7 @@ impl<'s> Parse<'s> for ImportItem<'s> {
         let import = parser.parse()?;
 
         parser.opening_paren("import item")?;
-        let (keyword, offset) = match_token!(parser, "one of 'func', 'table', 'memory', 'global'", Token::Keyword(kw) => kw);
+        let (keyword, offset) = match_token!(parser, "one of 'func', 'table', 'memory', 'global', 'tag'", Token::Keyword(kw) => kw);
 
         let id = parser.maybe_ident("identifier for import item")?;
 
@@ -1128,6 +1372,15 @@ impl<'s> Parse<'s> for ImportItem<'s> {
                     kind: GlobalKind::Import(import),
                 })
             }
+            "tag" => {
+                parser.ctx.tag_indices.new_idx(id, start)?;
+                ImportItem::Tag(Tag {
+                    start,
+                    id,
+                    ty: parser.parse()?,
+                    import: Some(import),
+                })
+            }
             kw => return parser.error(ParseErrorKind::UnexpectedKeyword(kw), offset),
         };
 
@@ -1163,77 +1416,85 @@ impl<'s> Parse<'s> for TypeUse<'s> {
             });
         }
 
-        // Handle abbreviation:
-        //   https://webassembly.github.io/spec/core/text/modules.html#abbreviations
-        //
-        // A typeuse may also be replaced entirely by inline parameter and result declarations.
-        // In that case, a type index is automatically inserted.
-        //
-        //   {param}* {result}* == (type {x}) {param}* {result}*
-        //
-        // where {x} is an existing function type which has the same signature.
-        // If no function exists, insert new function type in module
-        //
-        //   (type (func {param}* {result}*))
-        //
+        implicit_type_use(parser, start, params, results)
+    }
+}
 
-        if let Some(idx) = parser
-            .ctx
-            .types
-            .iter()
-            .enumerate()
-            .find_map(|(i, type_def)| {
-                // Find function type whose signature completely matches
-                if type_def
+// Handle abbreviation:
+//   https://webassembly.github.io/spec/core/text/modules.html#abbreviations
+//
+// A typeuse may also be replaced entirely by inline parameter and result declarations.
+// In that case, a type index is automatically inserted.
+//
+//   {param}* {result}* == (type {x}) {param}* {result}*
+//
+// where {x} is an existing function type which has the same signature.
+// If no function exists, insert new function type in module
+//
+//   (type (func {param}* {result}*))
+//
+fn implicit_type_use<'s>(
+    parser: &mut Parser<'s>,
+    start: usize,
+    params: Vec<Param<'s>>,
+    results: Vec<FuncResult>,
+) -> Result<'s, TypeUse<'s>> {
+    if let Some(idx) = parser
+        .ctx
+        .types
+        .iter()
+        .enumerate()
+        .find_map(|(i, type_def)| {
+            // Find function type whose signature completely matches
+            if type_def
+                .ty
+                .params
+                .iter()
+                .map(|t| t.ty)
+                .eq(params.iter().map(|t| t.ty))
+                && type_def
                     .ty
-                    .params
+                    .results
                     .iter()
                     .map(|t| t.ty)
-                    .eq(params.iter().map(|t| t.ty))
-                    && type_def
-                        .ty
-                        .results
-                        .iter()
-                        .map(|t| t.ty)
-                        .eq(results.iter().map(|t| t.ty))
-                {
-                    // Index of parser.ctx.types is available as typeidx here because all types are defined
-                    // with (type ...) syntax unlike memory, globals, tables and funcs.
-                    Some(i as u32)
-                } else {
-                    None
-                }
-            })
-        {
-            return Ok(TypeUse {
-                start,
-                idx: Index::Num(idx),
-                params,
-                results,
-            });
-        }
-
-        // When no existing function type found, generate and insert new one to current module
-        parser.ctx.types.push(TypeDef {
-            start,
-            id: None,
-            ty: FuncType {
-                start,
-                params: params.clone(),
-                results: results.clone(),
-            },
-        });
-
-        // Generated function type does not have explicit type index
-        let idx = parser.ctx.type_indices.new_idx(None, start)?;
-
-        Ok(TypeUse {
+                    .eq(results.iter().map(|t| t.ty))
+            {
+                // Index of parser.ctx.types is available as typeidx here because all types are defined
+                // with (type ...) syntax unlike memory, globals, tables and funcs.
+                Some(i as u32)
+            } else {
+                None
+            }
+        })
+    {
+        return Ok(TypeUse {
             start,
             idx: Index::Num(idx),
             params,
             results,
-        })
+        });
     }
+
+    // When no existing function type found, generate and insert new one to current module
+    parser.ctx.types.push(TypeDef {
+        start,
+        id: None,
+        ty: FuncType {
+            start,
+            params: params.clone(),
+            results: results.clone(),
+        },
+    });
+
+    // Generated function type does not have explicit type index
+    let idx = parser.ctx.type_indices.new_idx(None, start)?;
+
+    Ok(TypeUse {
+        start,
+        idx: Index::Num(idx),
+        params,
+        results,
+    })
 }
 
 // https://webassembly.github.io/spec/core/text/modules.html#indices
@@ -1257,22 +1518,18 @@ impl<'s> Parse<'s> for Index<'s> {
 impl<'s> Parse<'s> for TableType {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
         let limit = parser.parse()?;
-        match_token!(
-            parser,
-            "'funcref' keyword for table type",
-            Token::Keyword("funcref")
-        );
-        Ok(TableType { limit })
+        let elem = parser.parse()?;
+        Ok(TableType { limit, elem })
     }
 }
 
 // https://webassembly.github.io/spec/core/text/types.html#text-limits
 impl<'s> Parse<'s> for Limits {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
-        let min = parser.parse_u32("u32 for min table limit")?;
+        let min = parser.parse_u32("u32 for min table limit")?.into();
         Ok(match parser.peek("u32 for max table limit")? {
             (Token::Int(..), _) => {
-                let max = parser.parse_u32("u32 for min table limit")?;
+                let max = parser.parse_u32("u32 for min table limit")?.into();
                 Limits::Range { min, max }
             }
             _ => Limits::From { min },
@@ -1280,10 +1537,48 @@ impl<'s> Parse<'s> for Limits {
     }
 }
 
+// Parse 'i64' or 'i32' index type of memory. Returns true when the memory is 64-bit
+// https://github.com/WebAssembly/memory64/blob/main/proposals/memory64/Overview.md#text-format
+fn parse_memory64(parser: &mut Parser<'_>) -> bool {
+    match parser.tokens.peek() {
+        Some(Ok((Token::Keyword("i64"), _))) => {
+            parser.eat_token(); // Eat 'i64' keyword
+            true
+        }
+        Some(Ok((Token::Keyword("i32"), _))) => {
+            parser.eat_token(); // Eat 'i32' keyword
+            false
+        }
+        _ => false,
+    }
+}
+
 // https://webassembly.github.io/spec/core/text/types.html#text-memtype
+// https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#text-format
 impl<'s> Parse<'s> for MemType {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
-        parser.parse().map(|limit| MemType { limit })
+        let memory64 = parse_memory64(parser);
+        let limit = if memory64 {
+            let min = parser.parse_u64("u64 for min memory limit")?;
+            match parser.tokens.peek() {
+                Some(Ok((Token::Int(..), _))) => {
+                    let max = parser.parse_u64("u64 for max memory limit")?;
+                    Limits::Range { min, max }
+                }
+                _ => Limits::From { min },
+            }
+        } else {
+            parser.parse()?
+        };
+        let shared = matches!(parser.tokens.peek(), Some(Ok((Token::Keyword("shared"), _))));
+        if shared {
+            parser.eat_token(); // Eat 'shared' keyword
+        }
+        Ok(MemType {
+            limit,
+            shared,
+            memory64,
+        })
     }
 }
 
@@ -1329,6 +1624,7 @@ impl<'s> Parse<'s> for Export<'s> {
             "table" => ExportKind::Table,
             "memory" => ExportKind::Memory,
             "global" => ExportKind::Global,
+            "tag" => ExportKind::Tag,
             _ => return parser.error(ParseErrorKind::UnexpectedKeyword(keyword), offset),
         };
         let idx = parser.parse()?;
@@ -1451,7 +1747,61 @@ impl<'s> Parse<'s> for Vec<Local<'s>> {
     }
 }
 
-impl<'s> Parse<'s> for Mem {
+// https://webassembly.github.io/spec/core/text/instructions.html#vector-instructions
+// Operand of v128.const is a shape followed by values of all its lanes
+fn parse_v128<'s>(parser: &mut Parser<'s>) -> Result<'s, i128> {
+    let expected = "shape of v128.const operand";
+    let mut bytes = [0; 16];
+    match parser.next_token(expected)? {
+        (Token::Keyword("i8x16"), _) => {
+            for lane in bytes.chunks_exact_mut(1) {
+                let i = parse_i8(parser, "integer for i8x16 lane of v128.const")?;
+                lane.copy_from_slice(&i.to_le_bytes());
+            }
+        }
+        (Token::Keyword("i16x8"), _) => {
+            for lane in bytes.chunks_exact_mut(2) {
+                let i = parse_i16(parser, "integer for i16x8 lane of v128.const")?;
+                lane.copy_from_slice(&i.to_le_bytes());
+            }
+        }
+        (Token::Keyword("i32x4"), _) => {
+            for lane in bytes.chunks_exact_mut(4) {
+                let i = parse_i32(parser, "integer for i32x4 lane of v128.const")?;
+                lane.copy_from_slice(&i.to_le_bytes());
+            }
+        }
+        (Token::Keyword("i64x2"), _) => {
+            for lane in bytes.chunks_exact_mut(8) {
+                let i = parse_i64(parser, "integer for i64x2 lane of v128.const")?;
+                lane.copy_from_slice(&i.to_le_bytes());
+            }
+        }
+        (Token::Keyword("f32x4"), _) => {
+            for lane in bytes.chunks_exact_mut(4) {
+                let f = parse_f32(
+                    parser,
+                    "float number or integer for f32x4 lane of v128.const",
+                )?;
+                lane.copy_from_slice(&f.to_le_bytes());
+            }
+        }
+        (Token::Keyword("f64x2"), _) => {
+            for lane in bytes.chunks_exact_mut(8) {
+                let f = parse_f64(
+                    parser,
+                    "float number or integer for f64x2 lane of v128.const",
+                )?;
+                lane.copy_from_slice(&f.to_le_bytes());
+            }
+        }
+        (tok, offset) => return parser.unexpected_token(tok, expected, offset),
+    }
+    Ok(i128::from_le_bytes(bytes))
+}
+
+// Memory index precedes memarg. It can be omitted, defaulting to 0
+impl<'s> Parse<'s> for Mem<'s> {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
         fn base_and_digits(s: &str) -> (NumBase, &'_ str) {
             if s.starts_with("0x") {
@@ -1461,10 +1811,14 @@ impl<'s> Parse<'s> for Mem {
             }
         }
 
+        let memory = parser
+            .maybe_index("memory index for memory instruction")?
+            .unwrap_or(Index::Num(0));
+
         let offset = match parser.peek("'offset' keyword for memory instruction")? {
             (Token::Keyword(kw), offset) if kw.starts_with("offset=") => {
                 let (base, digits) = base_and_digits(&kw[7..]);
-                let u = parse_u32_str(parser, digits, base, Sign::Plus, offset)?;
+                let u = parse_u64_str(parser, digits, base, Sign::Plus, offset)?;
                 parser.eat_token(); // Eat 'offset' keyword
                 Some(u)
             }
@@ -1481,8 +1835,35 @@ impl<'s> Parse<'s> for Mem {
             _ => None,
         };
 
-        Ok(Mem { offset, align })
+        Ok(Mem {
+            offset,
+            align,
+            memory,
+        })
+    }
+}
+
+// Memory index and memarg precede lane index. When only one integer follows the instruction, it is
+// not a memory index but a lane index
+fn parse_mem_and_lane<'s>(parser: &mut Parser<'s>) -> Result<'s, (Mem<'s>, u8)> {
+    let expected = "lane index for memory lane instruction";
+    if let Token::Int(..) = parser.peek(expected)?.0 {
+        match parser.lookahead(expected)?.0 {
+            Token::Int(..) => {}
+            Token::Keyword(kw) if kw.starts_with("offset=") || kw.starts_with("align=") => {}
+            _ => {
+                let mem = Mem {
+                    align: None,
+                    offset: None,
+                    memory: Index::Num(0),
+                };
+                return Ok((mem, parser.parse_u8(expected)?));
+            }
+        }
     }
+    let mem = parser.parse()?;
+    let lane = parser.parse_u8(expected)?;
+    Ok((mem, lane))
 }
 
 // Instructions has special abbreviation. It can be folded and nested. This struct parses sequence
@@ -1506,16 +1887,52 @@ struct MaybeFoldedInsn<'s, 'p> {
 
 // Note: These are free functions not to modify `insns` field of MaybeFoldedInsn.
 
-fn parse_maybe_result_type<'s>(parser: &mut Parser<'s>) -> Result<'s, Option<ValType>> {
+// https://webassembly.github.io/spec/core/text/instructions.html#text-blocktype
+fn parse_block_type<'s>(parser: &mut Parser<'s>, insn: &'static str) -> Result<'s, BlockType<'s>> {
     // Note: This requires that next token exists
-    if let Some("result") = parser.peek_fold_start("result type")?.0 {
-        parser.eat_token(); // Eat '('
-        parser.eat_token(); // Eat 'result'
-        let ty = parser.parse()?;
-        parser.closing_paren("result type")?;
-        Ok(Some(ty))
+    let (keyword, start) = parser.peek_fold_start("block type")?;
+    let ty = match keyword {
+        Some("type") | Some("param") => parser.parse()?,
+        Some("result") => {
+            let mut results: Vec<FuncResult> = parser.parse()?;
+            // Abbreviation:
+            //   (result {valtype})? == (type {x}) where type {x} is [] -> [{valtype}?]
+            match results.len() {
+                0 => return Ok(BlockType::Empty),
+                1 => return Ok(BlockType::Value(results.pop().unwrap().ty)),
+                _ => implicit_type_use(parser, start, vec![], results)?,
+            }
+        }
+        _ => return Ok(BlockType::Empty),
+    };
+
+    // Parameters of block type cannot be referred by identifiers
+    if ty.params.iter().any(|p| p.id.is_some()) {
+        return parser.error(
+            ParseErrorKind::InvalidOperand {
+                insn,
+                msg: "parameter of block type must not have identifier",
+            },
+            start,
+        );
+    }
+
+    Ok(BlockType::TypeUse(ty))
+}
+
+// Peek keyword which starts a clause following body of 'try' instruction. In folded form, each
+// clause is enclosed with parens
+fn peek_try_clause<'s>(
+    parser: &Parser<'s>,
+    is_folded: bool,
+) -> Result<'s, (Option<&'s str>, usize)> {
+    if is_folded {
+        parser.peek_fold_start("clause of folded 'try'")
     } else {
-        Ok(None)
+        match parser.peek("'catch', 'catch_all', 'delegate' or 'end' in 'try'")? {
+            (Token::Keyword(kw), offset) => Ok((Some(*kw), offset)),
+            (_, offset) => Ok((None, offset)),
+        }
     }
 }
 
@@ -1536,7 +1953,8 @@ impl<'s, 'p> MaybeFoldedInsn<'s, 'p> {
             // https://webassembly.github.io/spec/core/text/instructions.html#control-instructions
             "block" | "loop" => {
                 let label = self.parser.maybe_ident("label for block or loop")?;
-                let ty = parse_maybe_result_type(self.parser)?;
+                let insn = if kw == "block" { "block" } else { "loop" };
+                let ty = parse_block_type(self.parser, insn)?;
                 let body = self.parser.parse()?;
                 let id = if end {
                     match_token!(
@@ -1569,7 +1987,7 @@ impl<'s, 'p> MaybeFoldedInsn<'s, 'p> {
                 // https://webassembly.github.io/spec/core/text/instructions.html#abbreviations
                 let is_folded = !end;
                 let label = self.parser.maybe_ident("label for block or loop")?;
-                let ty = parse_maybe_result_type(self.parser)?;
+                let ty = parse_block_type(self.parser, "if")?;
 
                 // Folded 'if' instruction abbreviation:
                 //   (if {label} {resulttype} {foldedinstr}* (then {instr}*) (else {instr}*))
@@ -1650,6 +2068,94 @@ impl<'s, 'p> MaybeFoldedInsn<'s, 'p> {
                     end_id,
                 }
             }
+            // Exception handling
+            // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#try-catch-blocks
+            "try" => {
+                let is_folded = !end;
+                let label = self.parser.maybe_ident("label for 'try'")?;
+                let ty = parse_block_type(self.parser, "try")?;
+
+                // Folded 'try' instruction:
+                //   (try {label} {blocktype} (do {instr}*) (catch {tagidx} {instr}*)* (catch_all {instr}*)?)
+                //   (try {label} {blocktype} (do {instr}*) (delegate {labelidx}))
+                if is_folded {
+                    self.parser.opening_paren("'do' clause in folded 'try'")?;
+                    match_token!(
+                        self.parser,
+                        "'do' keyword for folded 'try'",
+                        Token::Keyword("do")
+                    );
+                }
+                let body = self.parser.parse()?;
+                if is_folded {
+                    self.parser.closing_paren("'do' clause in folded 'try'")?;
+                }
+
+                let (clause, _) = peek_try_clause(self.parser, is_folded)?;
+                let handler = if let Some("delegate") = clause {
+                    if is_folded {
+                        self.parser.eat_token(); // Eat '('
+                    }
+                    self.parser.eat_token(); // Eat 'delegate'
+                    let label = self.parser.parse()?;
+                    if is_folded {
+                        self.parser.closing_paren("'delegate' clause in folded 'try'")?;
+                    }
+                    TryHandler::Delegate(label)
+                } else {
+                    let mut catches = vec![];
+                    let mut catch_all = None;
+                    loop {
+                        let (kw, start) = peek_try_clause(self.parser, is_folded)?;
+                        let is_catch_all = match kw {
+                            Some("catch") => false,
+                            Some("catch_all") => true,
+                            _ => break,
+                        };
+                        if is_folded {
+                            self.parser.eat_token(); // Eat '('
+                        }
+                        self.parser.eat_token(); // Eat 'catch' or 'catch_all'
+                        if is_catch_all {
+                            catch_all = Some(self.parser.parse()?);
+                        } else {
+                            let tag = self.parser.parse()?;
+                            let body = self.parser.parse()?;
+                            catches.push(Catch { start, tag, body });
+                        }
+                        if is_folded {
+                            self.parser.closing_paren("catch clause in folded 'try'")?;
+                        }
+                        if is_catch_all {
+                            // 'catch_all' must be the last clause
+                            break;
+                        }
+                    }
+                    TryHandler::Catch { catches, catch_all }
+                };
+
+                // 'try' with 'delegate' has no 'end'
+                let id = match handler {
+                    TryHandler::Catch { .. } if end => {
+                        match_token!(
+                            self.parser,
+                            "'end' keyword for 'try'",
+                            Token::Keyword("end")
+                        );
+                        self.parser.maybe_ident("ID for end of 'try'")?
+                    }
+                    _ => None,
+                };
+                InsnKind::Try {
+                    label,
+                    ty,
+                    body,
+                    handler,
+                    id,
+                }
+            }
+            "throw" => InsnKind::Throw(self.parser.parse()?),
+            "rethrow" => InsnKind::Rethrow(self.parser.parse()?),
             "unreachable" => InsnKind::Unreachable,
             "nop" => InsnKind::Nop,
             "br" => InsnKind::Br(self.parser.parse()?),
@@ -1678,11 +2184,47 @@ impl<'s, 'p> MaybeFoldedInsn<'s, 'p> {
             }
             "return" => InsnKind::Return,
             "call" => InsnKind::Call(self.parser.parse()?),
-            "call_indirect" => InsnKind::CallIndirect(self.parser.parse()?),
+            "call_indirect" => {
+                let table = self
+                    .parser
+                    .maybe_index("table index for 'call_indirect'")?
+                    .unwrap_or(Index::Num(0));
+                let ty = self.parser.parse()?;
+                InsnKind::CallIndirect { table, ty }
+            }
+            "return_call" => InsnKind::ReturnCall(self.parser.parse()?),
+            "return_call_indirect" => {
+                let table = self
+                    .parser
+                    .maybe_index("table index for 'return_call_indirect'")?
+                    .unwrap_or(Index::Num(0));
+                let ty = self.parser.parse()?;
+                InsnKind::ReturnCallIndirect { table, ty }
+            }
+            // Reference instructions
+            // https://webassembly.github.io/spec/core/text/instructions.html#reference-instructions
+            "ref.null" => {
+                // https://webassembly.github.io/spec/core/text/types.html#text-heaptype
+                let expected = "'func' or 'extern' keyword for heap type";
+                match self.parser.next_token(expected)? {
+                    (Token::Keyword("func"), _) => InsnKind::RefNull(RefType::FuncRef),
+                    (Token::Keyword("extern"), _) => InsnKind::RefNull(RefType::ExternRef),
+                    (tok, offset) => return self.parser.unexpected_token(tok, expected, offset),
+                }
+            }
+            "ref.is_null" => InsnKind::RefIsNull,
+            "ref.func" => InsnKind::RefFunc(self.parser.parse()?),
             // Parametric instructions
             // https://webassembly.github.io/spec/core/text/instructions.html#parametric-instructions
             "drop" => InsnKind::Drop,
-            "select" => InsnKind::Select,
+            "select" => {
+                let results: Vec<FuncResult> = self.parser.parse()?;
+                if results.is_empty() {
+                    InsnKind::Select
+                } else {
+                    InsnKind::TypedSelect(results.into_iter().map(|r| r.ty).collect())
+                }
+            }
             // Variable instructions
             // https://webassembly.github.io/spec/core/text/instructions.html#variable-instructions
             "local.get" => InsnKind::LocalGet(self.parser.parse()?),
@@ -1715,233 +2257,101 @@ impl<'s, 'p> MaybeFoldedInsn<'s, 'p> {
             "i64.store8" => InsnKind::I64Store8(self.parser.parse()?),
             "i64.store16" => InsnKind::I64Store16(self.parser.parse()?),
             "i64.store32" => InsnKind::I64Store32(self.parser.parse()?),
-            "memory.size" => InsnKind::MemorySize,
-            "memory.grow" => InsnKind::MemoryGrow,
-            // Numeric instructions
-            // https://webassembly.github.io/spec/core/text/instructions.html#numeric-instructions
-            // Constants
-            "i32.const" => {
-                // Note: i32.const operand takes range of i32::MIN <= i <= u32::MAX
-                // When the value is over i32::MAX, it is treated as u32 value and bitcasted to i32
-                let ((sign, base, digits), offset) = match_token!(self.parser, "integer for i32.const operand", Token::Int(s, b, d) => (s, b, d));
-                let u = parse_u32_str(self.parser, digits, base, sign, offset)?;
-                if sign == Sign::Plus {
-                    InsnKind::I32Const(u as i32)
-                } else if u == i32::MAX as u32 + 1 {
-                    // u as i32 causes overflow
-                    InsnKind::I32Const(i32::MIN)
-                } else if u <= i32::MAX as u32 {
-                    InsnKind::I32Const(-(u as i32))
-                } else {
-                    return self.parser.cannot_parse_num(
-                        "too small integer for i32",
-                        digits,
-                        base,
-                        sign,
-                        offset,
-                    );
+            "memory.size" | "memory.grow" | "memory.fill" => {
+                // Memory index can be omitted, defaulting to 0
+                let idx = self
+                    .parser
+                    .maybe_index("memory index for memory instruction")?
+                    .unwrap_or(Index::Num(0));
+                match kw {
+                    "memory.size" => InsnKind::MemorySize(idx),
+                    "memory.grow" => InsnKind::MemoryGrow(idx),
+                    _ => InsnKind::MemoryFill(idx),
                 }
             }
-            "i64.const" => {
-                // Note: i64.const operand takes range of i64::MIN <= i <= u64::MAX
-                // When the value is over i64::MAX, it is treated as u64 value and bitcasted to i64
-                let ((sign, base, digits), offset) = match_token!(self.parser, "integer for i64.const operand", Token::Int(s, b, d) => (s, b, d));
-                let u = parse_u64_str(self.parser, digits, base, sign, offset)?;
-                if sign == Sign::Plus {
-                    InsnKind::I64Const(u as i64)
-                } else if u == i64::MAX as u64 + 1 {
-                    // u as i64 causes overflow
-                    InsnKind::I64Const(i64::MIN)
-                } else if u <= i64::MAX as u64 {
-                    InsnKind::I64Const(-(u as i64))
+            "memory.init" => {
+                // Memory index can be omitted, defaulting to 0: memory.init {dataidx}
+                let idx = self.parser.parse()?;
+                if let Some(data) = self.parser.maybe_index("data index for 'memory.init'")? {
+                    InsnKind::MemoryInit { memory: idx, data }
                 } else {
-                    return self.parser.cannot_parse_num(
-                        "too small integer for i64",
-                        digits,
-                        base,
-                        sign,
-                        offset,
-                    );
+                    InsnKind::MemoryInit {
+                        memory: Index::Num(0),
+                        data: idx,
+                    }
                 }
             }
-            "f32.const" => {
-                let val = match self
-                    .parser
-                    .next_token("float number or integer for f32.const")?
-                {
-                    (Token::Float(sign, float), offset) => match float {
-                        Float::Inf => match sign {
-                            Sign::Plus => f32::INFINITY,
-                            Sign::Minus => f32::NEG_INFINITY,
-                        },
-                        Float::Nan(None) => sign.apply(f32::NAN),
-                        Float::Nan(Some(payload)) => {
-                            // Encode  f32 NaN value via u32 assuming IEEE-754 format for NaN boxing.
-                            // Palyload must be
-                            //   - within 23bits (fraction of f32 is 23bits)
-                            //   - >= 2^(23-1) meant that most significant bit must be 1 (since frac cannot be zero for NaN value)
-                            // https://webassembly.github.io/spec/core/syntax/values.html#floating-point
-                            let payload_u = parse_u32_str(
-                                self.parser,
-                                payload,
-                                NumBase::Hex,
-                                Sign::Plus,
-                                offset,
-                            )?;
-                            if payload_u == 0 || 0x80_0000 <= payload_u {
-                                return self.parser.cannot_parse_num(
-                                    "payload of NaN for f32 must be in range of 1 <= payload < 2^23",
-                                    payload,
-                                    NumBase::Hex,
-                                    Sign::Plus,
-                                    offset,
-                                );
-                            }
-                            // NaN boxing. 1 <= payload_u < 2^23 and floating point number is in IEEE754 format.
-                            // This will encode the payload into fraction of NaN.
-                            //   0x{sign}11111111{payload}
-                            let sign = match sign {
-                                Sign::Plus => 0,
-                                Sign::Minus => 1u32 << 31, // most significant bit is 1 for negative number
-                            };
-                            let exp = 0b1111_1111u32 << (31 - 8);
-                            f32::from_bits(sign | exp | payload_u)
-                        }
-                        Float::Val { base, frac, exp } => {
-                            // Note: Better algorithm should be considered
-                            // https://github.com/rust-lang/rust/blob/3982d3514efbb65b3efac6bb006b3fa496d16663/src/libcore/num/dec2flt/algorithm.rs
-                            let mut frac =
-                                sign.apply(parse_f32_str(self.parser, frac, base, sign, offset)?);
-                            // In IEEE754, exp part is actually 8bits
-                            if let Some((exp_sign, exp)) = exp {
-                                let exp = parse_u32_str(
-                                    self.parser,
-                                    exp,
-                                    NumBase::Dec,
-                                    exp_sign,
-                                    offset,
-                                )?;
-                                let step = match base {
-                                    NumBase::Hex => 2.0,
-                                    NumBase::Dec => 10.0,
-                                };
-                                // powi is not available because an error gets larger
-                                match exp_sign {
-                                    Sign::Plus => {
-                                        for _ in 0..exp {
-                                            frac *= step;
-                                        }
-                                    }
-                                    Sign::Minus => {
-                                        for _ in 0..exp {
-                                            frac /= step;
-                                        }
-                                    }
-                                }
-                                frac
-                            } else {
-                                frac
-                            }
-                        }
-                    },
-                    (Token::Int(sign, base, digits), offset) => {
-                        parse_f32_str(self.parser, digits, base, sign, offset)?
-                    }
-                    (tok, offset) => {
-                        return self.parser.unexpected_token(
-                            tok,
-                            "float number or integer for f32.const",
-                            offset,
-                        )
+            "data.drop" => InsnKind::DataDrop(self.parser.parse()?),
+            "memory.copy" => {
+                // Both memory indices can be omitted, defaulting to 0
+                if let Some(dst) = self.parser.maybe_index("memory index for 'memory.copy'")? {
+                    let src = self.parser.parse()?;
+                    InsnKind::MemoryCopy { dst, src }
+                } else {
+                    InsnKind::MemoryCopy {
+                        dst: Index::Num(0),
+                        src: Index::Num(0),
                     }
-                };
-                InsnKind::F32Const(val)
+                }
             }
-            "f64.const" => {
-                let val = match self
+            // Table instructions
+            // https://webassembly.github.io/spec/core/text/instructions.html#table-instructions
+            "table.get" | "table.set" | "table.size" | "table.grow" | "table.fill" => {
+                // Table index can be omitted, defaulting to 0
+                let idx = self
                     .parser
-                    .next_token("float number or integer for f64.const")?
-                {
-                    (Token::Float(sign, float), offset) => match float {
-                        Float::Inf => match sign {
-                            Sign::Plus => f64::INFINITY,
-                            Sign::Minus => f64::NEG_INFINITY,
-                        },
-                        Float::Nan(None) => sign.apply(f64::NAN),
-                        Float::Nan(Some(payload)) => {
-                            // Encode f64 NaN value via u64 assuming IEEE-754 format for NaN boxing.
-                            // Palyload must be
-                            //   - within 52bits (since fraction of f64 is 52bits)
-                            //   - >= 2^(52-1) meant that most significant bit must be 1 (since frac cannot be zero for NaN value)
-                            // https://webassembly.github.io/spec/core/syntax/values.html#floating-point
-                            let payload_u = parse_u64_str(
-                                self.parser,
-                                payload,
-                                NumBase::Hex,
-                                Sign::Plus,
-                                offset,
-                            )?;
-                            if payload_u == 0 || 0x10_0000_0000_0000 <= payload_u {
-                                return self.parser.cannot_parse_num(
-                                    "payload of NaN for f64 must be in range of 1 <= payload < 2^52",
-                                    payload,
-                                    NumBase::Hex,
-                                    Sign::Plus,
-                                    offset,
-                                );
-                            }
-                            // NaN boxing. 1 <= payload_u < 2^52 and floating point number is in IEEE754 format.
-                            // This will encode the payload into fraction of NaN.
-                            //   0x{sign}11111111111{payload}
-                            let sign = match sign {
-                                Sign::Plus => 0,
-                                Sign::Minus => 1u64 << 63, // most significant bit is 1 for negative number
-                            };
-                            let exp = 0b111_1111_1111u64 << (63 - 11);
-                            f64::from_bits(sign | exp | payload_u)
-                        }
-                        Float::Val { base, frac, exp } => {
-                            let mut frac =
-                                sign.apply(parse_f64_str(self.parser, frac, base, sign, offset)?);
-                            // In IEEE754, exp part is actually 11bits
-                            if let Some((exp_sign, exp)) = exp {
-                                let exp = parse_u32_str(self.parser, exp, base, exp_sign, offset)?;
-                                let step = match base {
-                                    NumBase::Hex => 2.0,
-                                    NumBase::Dec => 10.0,
-                                };
-                                // powi is not available because an error gets larger
-                                match exp_sign {
-                                    Sign::Plus => {
-                                        for _ in 0..exp {
-                                            frac *= step;
-                                        }
-                                    }
-                                    Sign::Minus => {
-                                        for _ in 0..exp {
-                                            frac /= step;
-                                        }
-                                    }
-                                }
-                                frac
-                            } else {
-                                frac
-                            }
-                        }
-                    },
-                    (Token::Int(sign, base, digits), offset) => {
-                        parse_f64_str(self.parser, digits, base, sign, offset)?
+                    .maybe_index("table index for table instruction")?
+                    .unwrap_or(Index::Num(0));
+                match kw {
+                    "table.get" => InsnKind::TableGet(idx),
+                    "table.set" => InsnKind::TableSet(idx),
+                    "table.size" => InsnKind::TableSize(idx),
+                    "table.grow" => InsnKind::TableGrow(idx),
+                    _ => InsnKind::TableFill(idx),
+                }
+            }
+            "table.copy" => {
+                // Both table indices can be omitted, defaulting to 0
+                if let Some(dst) = self.parser.maybe_index("table index for 'table.copy'")? {
+                    let src = self.parser.parse()?;
+                    InsnKind::TableCopy { dst, src }
+                } else {
+                    InsnKind::TableCopy {
+                        dst: Index::Num(0),
+                        src: Index::Num(0),
                     }
-                    (tok, offset) => {
-                        return self.parser.unexpected_token(
-                            tok,
-                            "float number or integer for f64.const",
-                            offset,
-                        )
+                }
+            }
+            "table.init" => {
+                // Table index can be omitted, defaulting to 0: table.init {elemidx}
+                let idx = self.parser.parse()?;
+                if let Some(elem) = self.parser.maybe_index("elem index for 'table.init'")? {
+                    InsnKind::TableInit { table: idx, elem }
+                } else {
+                    InsnKind::TableInit {
+                        table: Index::Num(0),
+                        elem: idx,
                     }
-                };
-                InsnKind::F64Const(val)
+                }
+            }
+            "elem.drop" => InsnKind::ElemDrop(self.parser.parse()?),
+            // Numeric instructions
+            // https://webassembly.github.io/spec/core/text/instructions.html#numeric-instructions
+            // Constants
+            "i32.const" => {
+                InsnKind::I32Const(parse_i32(self.parser, "integer for i32.const operand")?)
             }
+            "i64.const" => {
+                InsnKind::I64Const(parse_i64(self.parser, "integer for i64.const operand")?)
+            }
+            "f32.const" => InsnKind::F32Const(parse_f32(
+                self.parser,
+                "float number or integer for f32.const",
+            )?),
+            "f64.const" => InsnKind::F64Const(parse_f64(
+                self.parser,
+                "float number or integer for f64.const",
+            )?),
             "i32.clz" => InsnKind::I32Clz,
             "i32.ctz" => InsnKind::I32Ctz,
             "i32.popcnt" => InsnKind::I32Popcnt,
@@ -2065,6 +2475,355 @@ impl<'s, 'p> MaybeFoldedInsn<'s, 'p> {
             "i64.reinterpret_f64" => InsnKind::I64ReinterpretF64,
             "f32.reinterpret_i32" => InsnKind::F32ReinterpretI32,
             "f64.reinterpret_i64" => InsnKind::F64ReinterpretI64,
+            "i32.extend8_s" => InsnKind::I32Extend8S,
+            "i32.extend16_s" => InsnKind::I32Extend16S,
+            "i64.extend8_s" => InsnKind::I64Extend8S,
+            "i64.extend16_s" => InsnKind::I64Extend16S,
+            "i64.extend32_s" => InsnKind::I64Extend32S,
+            "i32.trunc_sat_f32_s" => InsnKind::I32TruncSatF32S,
+            "i32.trunc_sat_f32_u" => InsnKind::I32TruncSatF32U,
+            "i32.trunc_sat_f64_s" => InsnKind::I32TruncSatF64S,
+            "i32.trunc_sat_f64_u" => InsnKind::I32TruncSatF64U,
+            "i64.trunc_sat_f32_s" => InsnKind::I64TruncSatF32S,
+            "i64.trunc_sat_f32_u" => InsnKind::I64TruncSatF32U,
+            "i64.trunc_sat_f64_s" => InsnKind::I64TruncSatF64S,
+            "i64.trunc_sat_f64_u" => InsnKind::I64TruncSatF64U,
+            // Vector instructions
+            // https://webassembly.github.io/spec/core/text/instructions.html#vector-instructions
+            "v128.load" => InsnKind::V128Load(self.parser.parse()?),
+            "v128.load8x8_s" => InsnKind::V128Load8x8S(self.parser.parse()?),
+            "v128.load8x8_u" => InsnKind::V128Load8x8U(self.parser.parse()?),
+            "v128.load16x4_s" => InsnKind::V128Load16x4S(self.parser.parse()?),
+            "v128.load16x4_u" => InsnKind::V128Load16x4U(self.parser.parse()?),
+            "v128.load32x2_s" => InsnKind::V128Load32x2S(self.parser.parse()?),
+            "v128.load32x2_u" => InsnKind::V128Load32x2U(self.parser.parse()?),
+            "v128.load8_splat" => InsnKind::V128Load8Splat(self.parser.parse()?),
+            "v128.load16_splat" => InsnKind::V128Load16Splat(self.parser.parse()?),
+            "v128.load32_splat" => InsnKind::V128Load32Splat(self.parser.parse()?),
+            "v128.load64_splat" => InsnKind::V128Load64Splat(self.parser.parse()?),
+            "v128.store" => InsnKind::V128Store(self.parser.parse()?),
+            "v128.const" => InsnKind::V128Const(parse_v128(self.parser)?),
+            "i8x16.shuffle" => {
+                let mut lanes = [0; 16];
+                for lane in lanes.iter_mut() {
+                    *lane = self.parser.parse_u8("lane index for 'i8x16.shuffle'")?;
+                }
+                InsnKind::I8x16Shuffle(lanes)
+            }
+            "i8x16.swizzle" => InsnKind::I8x16Swizzle,
+            "i8x16.splat" => InsnKind::I8x16Splat,
+            "i16x8.splat" => InsnKind::I16x8Splat,
+            "i32x4.splat" => InsnKind::I32x4Splat,
+            "i64x2.splat" => InsnKind::I64x2Splat,
+            "f32x4.splat" => InsnKind::F32x4Splat,
+            "f64x2.splat" => InsnKind::F64x2Splat,
+            "i8x16.extract_lane_s"
+            | "i8x16.extract_lane_u"
+            | "i8x16.replace_lane"
+            | "i16x8.extract_lane_s"
+            | "i16x8.extract_lane_u"
+            | "i16x8.replace_lane"
+            | "i32x4.extract_lane"
+            | "i32x4.replace_lane"
+            | "i64x2.extract_lane"
+            | "i64x2.replace_lane"
+            | "f32x4.extract_lane"
+            | "f32x4.replace_lane"
+            | "f64x2.extract_lane"
+            | "f64x2.replace_lane" => {
+                let lane = self.parser.parse_u8("lane index for lane instruction")?;
+                match kw {
+                    "i8x16.extract_lane_s" => InsnKind::I8x16ExtractLaneS(lane),
+                    "i8x16.extract_lane_u" => InsnKind::I8x16ExtractLaneU(lane),
+                    "i8x16.replace_lane" => InsnKind::I8x16ReplaceLane(lane),
+                    "i16x8.extract_lane_s" => InsnKind::I16x8ExtractLaneS(lane),
+                    "i16x8.extract_lane_u" => InsnKind::I16x8ExtractLaneU(lane),
+                    "i16x8.replace_lane" => InsnKind::I16x8ReplaceLane(lane),
+                    "i32x4.extract_lane" => InsnKind::I32x4ExtractLane(lane),
+                    "i32x4.replace_lane" => InsnKind::I32x4ReplaceLane(lane),
+                    "i64x2.extract_lane" => InsnKind::I64x2ExtractLane(lane),
+                    "i64x2.replace_lane" => InsnKind::I64x2ReplaceLane(lane),
+                    "f32x4.extract_lane" => InsnKind::F32x4ExtractLane(lane),
+                    "f32x4.replace_lane" => InsnKind::F32x4ReplaceLane(lane),
+                    "f64x2.extract_lane" => InsnKind::F64x2ExtractLane(lane),
+                    _ => InsnKind::F64x2ReplaceLane(lane),
+                }
+            }
+            "i8x16.eq" => InsnKind::I8x16Eq,
+            "i8x16.ne" => InsnKind::I8x16Ne,
+            "i8x16.lt_s" => InsnKind::I8x16LtS,
+            "i8x16.lt_u" => InsnKind::I8x16LtU,
+            "i8x16.gt_s" => InsnKind::I8x16GtS,
+            "i8x16.gt_u" => InsnKind::I8x16GtU,
+            "i8x16.le_s" => InsnKind::I8x16LeS,
+            "i8x16.le_u" => InsnKind::I8x16LeU,
+            "i8x16.ge_s" => InsnKind::I8x16GeS,
+            "i8x16.ge_u" => InsnKind::I8x16GeU,
+            "i16x8.eq" => InsnKind::I16x8Eq,
+            "i16x8.ne" => InsnKind::I16x8Ne,
+            "i16x8.lt_s" => InsnKind::I16x8LtS,
+            "i16x8.lt_u" => InsnKind::I16x8LtU,
+            "i16x8.gt_s" => InsnKind::I16x8GtS,
+            "i16x8.gt_u" => InsnKind::I16x8GtU,
+            "i16x8.le_s" => InsnKind::I16x8LeS,
+            "i16x8.le_u" => InsnKind::I16x8LeU,
+            "i16x8.ge_s" => InsnKind::I16x8GeS,
+            "i16x8.ge_u" => InsnKind::I16x8GeU,
+            "i32x4.eq" => InsnKind::I32x4Eq,
+            "i32x4.ne" => InsnKind::I32x4Ne,
+            "i32x4.lt_s" => InsnKind::I32x4LtS,
+            "i32x4.lt_u" => InsnKind::I32x4LtU,
+            "i32x4.gt_s" => InsnKind::I32x4GtS,
+            "i32x4.gt_u" => InsnKind::I32x4GtU,
+            "i32x4.le_s" => InsnKind::I32x4LeS,
+            "i32x4.le_u" => InsnKind::I32x4LeU,
+            "i32x4.ge_s" => InsnKind::I32x4GeS,
+            "i32x4.ge_u" => InsnKind::I32x4GeU,
+            "f32x4.eq" => InsnKind::F32x4Eq,
+            "f32x4.ne" => InsnKind::F32x4Ne,
+            "f32x4.lt" => InsnKind::F32x4Lt,
+            "f32x4.gt" => InsnKind::F32x4Gt,
+            "f32x4.le" => InsnKind::F32x4Le,
+            "f32x4.ge" => InsnKind::F32x4Ge,
+            "f64x2.eq" => InsnKind::F64x2Eq,
+            "f64x2.ne" => InsnKind::F64x2Ne,
+            "f64x2.lt" => InsnKind::F64x2Lt,
+            "f64x2.gt" => InsnKind::F64x2Gt,
+            "f64x2.le" => InsnKind::F64x2Le,
+            "f64x2.ge" => InsnKind::F64x2Ge,
+            "v128.not" => InsnKind::V128Not,
+            "v128.and" => InsnKind::V128And,
+            "v128.andnot" => InsnKind::V128Andnot,
+            "v128.or" => InsnKind::V128Or,
+            "v128.xor" => InsnKind::V128Xor,
+            "v128.bitselect" => InsnKind::V128Bitselect,
+            "v128.any_true" => InsnKind::V128AnyTrue,
+            "v128.load8_lane" | "v128.load16_lane" | "v128.load32_lane" | "v128.load64_lane"
+            | "v128.store8_lane" | "v128.store16_lane" | "v128.store32_lane"
+            | "v128.store64_lane" => {
+                let (mem, lane) = parse_mem_and_lane(self.parser)?;
+                match kw {
+                    "v128.load8_lane" => InsnKind::V128Load8Lane { mem, lane },
+                    "v128.load16_lane" => InsnKind::V128Load16Lane { mem, lane },
+                    "v128.load32_lane" => InsnKind::V128Load32Lane { mem, lane },
+                    "v128.load64_lane" => InsnKind::V128Load64Lane { mem, lane },
+                    "v128.store8_lane" => InsnKind::V128Store8Lane { mem, lane },
+                    "v128.store16_lane" => InsnKind::V128Store16Lane { mem, lane },
+                    "v128.store32_lane" => InsnKind::V128Store32Lane { mem, lane },
+                    _ => InsnKind::V128Store64Lane { mem, lane },
+                }
+            }
+            "v128.load32_zero" => InsnKind::V128Load32Zero(self.parser.parse()?),
+            "v128.load64_zero" => InsnKind::V128Load64Zero(self.parser.parse()?),
+            "f32x4.demote_f64x2_zero" => InsnKind::F32x4DemoteF64x2Zero,
+            "f64x2.promote_low_f32x4" => InsnKind::F64x2PromoteLowF32x4,
+            "i8x16.abs" => InsnKind::I8x16Abs,
+            "i8x16.neg" => InsnKind::I8x16Neg,
+            "i8x16.popcnt" => InsnKind::I8x16Popcnt,
+            "i8x16.all_true" => InsnKind::I8x16AllTrue,
+            "i8x16.bitmask" => InsnKind::I8x16Bitmask,
+            "i8x16.narrow_i16x8_s" => InsnKind::I8x16NarrowI16x8S,
+            "i8x16.narrow_i16x8_u" => InsnKind::I8x16NarrowI16x8U,
+            "f32x4.ceil" => InsnKind::F32x4Ceil,
+            "f32x4.floor" => InsnKind::F32x4Floor,
+            "f32x4.trunc" => InsnKind::F32x4Trunc,
+            "f32x4.nearest" => InsnKind::F32x4Nearest,
+            "i8x16.shl" => InsnKind::I8x16Shl,
+            "i8x16.shr_s" => InsnKind::I8x16ShrS,
+            "i8x16.shr_u" => InsnKind::I8x16ShrU,
+            "i8x16.add" => InsnKind::I8x16Add,
+            "i8x16.add_sat_s" => InsnKind::I8x16AddSatS,
+            "i8x16.add_sat_u" => InsnKind::I8x16AddSatU,
+            "i8x16.sub" => InsnKind::I8x16Sub,
+            "i8x16.sub_sat_s" => InsnKind::I8x16SubSatS,
+            "i8x16.sub_sat_u" => InsnKind::I8x16SubSatU,
+            "f64x2.ceil" => InsnKind::F64x2Ceil,
+            "f64x2.floor" => InsnKind::F64x2Floor,
+            "i8x16.min_s" => InsnKind::I8x16MinS,
+            "i8x16.min_u" => InsnKind::I8x16MinU,
+            "i8x16.max_s" => InsnKind::I8x16MaxS,
+            "i8x16.max_u" => InsnKind::I8x16MaxU,
+            "f64x2.trunc" => InsnKind::F64x2Trunc,
+            "i8x16.avgr_u" => InsnKind::I8x16AvgrU,
+            "i16x8.extadd_pairwise_i8x16_s" => InsnKind::I16x8ExtaddPairwiseI8x16S,
+            "i16x8.extadd_pairwise_i8x16_u" => InsnKind::I16x8ExtaddPairwiseI8x16U,
+            "i32x4.extadd_pairwise_i16x8_s" => InsnKind::I32x4ExtaddPairwiseI16x8S,
+            "i32x4.extadd_pairwise_i16x8_u" => InsnKind::I32x4ExtaddPairwiseI16x8U,
+            "i16x8.abs" => InsnKind::I16x8Abs,
+            "i16x8.neg" => InsnKind::I16x8Neg,
+            "i16x8.q15mulr_sat_s" => InsnKind::I16x8Q15mulrSatS,
+            "i16x8.all_true" => InsnKind::I16x8AllTrue,
+            "i16x8.bitmask" => InsnKind::I16x8Bitmask,
+            "i16x8.narrow_i32x4_s" => InsnKind::I16x8NarrowI32x4S,
+            "i16x8.narrow_i32x4_u" => InsnKind::I16x8NarrowI32x4U,
+            "i16x8.extend_low_i8x16_s" => InsnKind::I16x8ExtendLowI8x16S,
+            "i16x8.extend_high_i8x16_s" => InsnKind::I16x8ExtendHighI8x16S,
+            "i16x8.extend_low_i8x16_u" => InsnKind::I16x8ExtendLowI8x16U,
+            "i16x8.extend_high_i8x16_u" => InsnKind::I16x8ExtendHighI8x16U,
+            "i16x8.shl" => InsnKind::I16x8Shl,
+            "i16x8.shr_s" => InsnKind::I16x8ShrS,
+            "i16x8.shr_u" => InsnKind::I16x8ShrU,
+            "i16x8.add" => InsnKind::I16x8Add,
+            "i16x8.add_sat_s" => InsnKind::I16x8AddSatS,
+            "i16x8.add_sat_u" => InsnKind::I16x8AddSatU,
+            "i16x8.sub" => InsnKind::I16x8Sub,
+            "i16x8.sub_sat_s" => InsnKind::I16x8SubSatS,
+            "i16x8.sub_sat_u" => InsnKind::I16x8SubSatU,
+            "f64x2.nearest" => InsnKind::F64x2Nearest,
+            "i16x8.mul" => InsnKind::I16x8Mul,
+            "i16x8.min_s" => InsnKind::I16x8MinS,
+            "i16x8.min_u" => InsnKind::I16x8MinU,
+            "i16x8.max_s" => InsnKind::I16x8MaxS,
+            "i16x8.max_u" => InsnKind::I16x8MaxU,
+            "i16x8.avgr_u" => InsnKind::I16x8AvgrU,
+            "i16x8.extmul_low_i8x16_s" => InsnKind::I16x8ExtmulLowI8x16S,
+            "i16x8.extmul_high_i8x16_s" => InsnKind::I16x8ExtmulHighI8x16S,
+            "i16x8.extmul_low_i8x16_u" => InsnKind::I16x8ExtmulLowI8x16U,
+            "i16x8.extmul_high_i8x16_u" => InsnKind::I16x8ExtmulHighI8x16U,
+            "i32x4.abs" => InsnKind::I32x4Abs,
+            "i32x4.neg" => InsnKind::I32x4Neg,
+            "i32x4.all_true" => InsnKind::I32x4AllTrue,
+            "i32x4.bitmask" => InsnKind::I32x4Bitmask,
+            "i32x4.extend_low_i16x8_s" => InsnKind::I32x4ExtendLowI16x8S,
+            "i32x4.extend_high_i16x8_s" => InsnKind::I32x4ExtendHighI16x8S,
+            "i32x4.extend_low_i16x8_u" => InsnKind::I32x4ExtendLowI16x8U,
+            "i32x4.extend_high_i16x8_u" => InsnKind::I32x4ExtendHighI16x8U,
+            "i32x4.shl" => InsnKind::I32x4Shl,
+            "i32x4.shr_s" => InsnKind::I32x4ShrS,
+            "i32x4.shr_u" => InsnKind::I32x4ShrU,
+            "i32x4.add" => InsnKind::I32x4Add,
+            "i32x4.sub" => InsnKind::I32x4Sub,
+            "i32x4.mul" => InsnKind::I32x4Mul,
+            "i32x4.min_s" => InsnKind::I32x4MinS,
+            "i32x4.min_u" => InsnKind::I32x4MinU,
+            "i32x4.max_s" => InsnKind::I32x4MaxS,
+            "i32x4.max_u" => InsnKind::I32x4MaxU,
+            "i32x4.dot_i16x8_s" => InsnKind::I32x4DotI16x8S,
+            "i32x4.extmul_low_i16x8_s" => InsnKind::I32x4ExtmulLowI16x8S,
+            "i32x4.extmul_high_i16x8_s" => InsnKind::I32x4ExtmulHighI16x8S,
+            "i32x4.extmul_low_i16x8_u" => InsnKind::I32x4ExtmulLowI16x8U,
+            "i32x4.extmul_high_i16x8_u" => InsnKind::I32x4ExtmulHighI16x8U,
+            "i64x2.abs" => InsnKind::I64x2Abs,
+            "i64x2.neg" => InsnKind::I64x2Neg,
+            "i64x2.all_true" => InsnKind::I64x2AllTrue,
+            "i64x2.bitmask" => InsnKind::I64x2Bitmask,
+            "i64x2.extend_low_i32x4_s" => InsnKind::I64x2ExtendLowI32x4S,
+            "i64x2.extend_high_i32x4_s" => InsnKind::I64x2ExtendHighI32x4S,
+            "i64x2.extend_low_i32x4_u" => InsnKind::I64x2ExtendLowI32x4U,
+            "i64x2.extend_high_i32x4_u" => InsnKind::I64x2ExtendHighI32x4U,
+            "i64x2.shl" => InsnKind::I64x2Shl,
+            "i64x2.shr_s" => InsnKind::I64x2ShrS,
+            "i64x2.shr_u" => InsnKind::I64x2ShrU,
+            "i64x2.add" => InsnKind::I64x2Add,
+            "i64x2.sub" => InsnKind::I64x2Sub,
+            "i64x2.mul" => InsnKind::I64x2Mul,
+            "i64x2.eq" => InsnKind::I64x2Eq,
+            "i64x2.ne" => InsnKind::I64x2Ne,
+            "i64x2.lt_s" => InsnKind::I64x2LtS,
+            "i64x2.gt_s" => InsnKind::I64x2GtS,
+            "i64x2.le_s" => InsnKind::I64x2LeS,
+            "i64x2.ge_s" => InsnKind::I64x2GeS,
+            "i64x2.extmul_low_i32x4_s" => InsnKind::I64x2ExtmulLowI32x4S,
+            "i64x2.extmul_high_i32x4_s" => InsnKind::I64x2ExtmulHighI32x4S,
+            "i64x2.extmul_low_i32x4_u" => InsnKind::I64x2ExtmulLowI32x4U,
+            "i64x2.extmul_high_i32x4_u" => InsnKind::I64x2ExtmulHighI32x4U,
+            "f32x4.abs" => InsnKind::F32x4Abs,
+            "f32x4.neg" => InsnKind::F32x4Neg,
+            "f32x4.sqrt" => InsnKind::F32x4Sqrt,
+            "f32x4.add" => InsnKind::F32x4Add,
+            "f32x4.sub" => InsnKind::F32x4Sub,
+            "f32x4.mul" => InsnKind::F32x4Mul,
+            "f32x4.div" => InsnKind::F32x4Div,
+            "f32x4.min" => InsnKind::F32x4Min,
+            "f32x4.max" => InsnKind::F32x4Max,
+            "f32x4.pmin" => InsnKind::F32x4Pmin,
+            "f32x4.pmax" => InsnKind::F32x4Pmax,
+            "f64x2.abs" => InsnKind::F64x2Abs,
+            "f64x2.neg" => InsnKind::F64x2Neg,
+            "f64x2.sqrt" => InsnKind::F64x2Sqrt,
+            "f64x2.add" => InsnKind::F64x2Add,
+            "f64x2.sub" => InsnKind::F64x2Sub,
+            "f64x2.mul" => InsnKind::F64x2Mul,
+            "f64x2.div" => InsnKind::F64x2Div,
+            "f64x2.min" => InsnKind::F64x2Min,
+            "f64x2.max" => InsnKind::F64x2Max,
+            "f64x2.pmin" => InsnKind::F64x2Pmin,
+            "f64x2.pmax" => InsnKind::F64x2Pmax,
+            "i32x4.trunc_sat_f32x4_s" => InsnKind::I32x4TruncSatF32x4S,
+            "i32x4.trunc_sat_f32x4_u" => InsnKind::I32x4TruncSatF32x4U,
+            "f32x4.convert_i32x4_s" => InsnKind::F32x4ConvertI32x4S,
+            "f32x4.convert_i32x4_u" => InsnKind::F32x4ConvertI32x4U,
+            "i32x4.trunc_sat_f64x2_s_zero" => InsnKind::I32x4TruncSatF64x2SZero,
+            "i32x4.trunc_sat_f64x2_u_zero" => InsnKind::I32x4TruncSatF64x2UZero,
+            "f64x2.convert_low_i32x4_s" => InsnKind::F64x2ConvertLowI32x4S,
+            "f64x2.convert_low_i32x4_u" => InsnKind::F64x2ConvertLowI32x4U,
+            "memory.atomic.notify" => InsnKind::MemoryAtomicNotify(self.parser.parse()?),
+            "memory.atomic.wait32" => InsnKind::MemoryAtomicWait32(self.parser.parse()?),
+            "memory.atomic.wait64" => InsnKind::MemoryAtomicWait64(self.parser.parse()?),
+            "atomic.fence" => InsnKind::AtomicFence,
+            "i32.atomic.load" => InsnKind::I32AtomicLoad(self.parser.parse()?),
+            "i64.atomic.load" => InsnKind::I64AtomicLoad(self.parser.parse()?),
+            "i32.atomic.load8_u" => InsnKind::I32AtomicLoad8U(self.parser.parse()?),
+            "i32.atomic.load16_u" => InsnKind::I32AtomicLoad16U(self.parser.parse()?),
+            "i64.atomic.load8_u" => InsnKind::I64AtomicLoad8U(self.parser.parse()?),
+            "i64.atomic.load16_u" => InsnKind::I64AtomicLoad16U(self.parser.parse()?),
+            "i64.atomic.load32_u" => InsnKind::I64AtomicLoad32U(self.parser.parse()?),
+            "i32.atomic.store" => InsnKind::I32AtomicStore(self.parser.parse()?),
+            "i64.atomic.store" => InsnKind::I64AtomicStore(self.parser.parse()?),
+            "i32.atomic.store8" => InsnKind::I32AtomicStore8(self.parser.parse()?),
+            "i32.atomic.store16" => InsnKind::I32AtomicStore16(self.parser.parse()?),
+            "i64.atomic.store8" => InsnKind::I64AtomicStore8(self.parser.parse()?),
+            "i64.atomic.store16" => InsnKind::I64AtomicStore16(self.parser.parse()?),
+            "i64.atomic.store32" => InsnKind::I64AtomicStore32(self.parser.parse()?),
+            "i32.atomic.rmw.add" => InsnKind::I32AtomicRmwAdd(self.parser.parse()?),
+            "i64.atomic.rmw.add" => InsnKind::I64AtomicRmwAdd(self.parser.parse()?),
+            "i32.atomic.rmw8.add_u" => InsnKind::I32AtomicRmw8AddU(self.parser.parse()?),
+            "i32.atomic.rmw16.add_u" => InsnKind::I32AtomicRmw16AddU(self.parser.parse()?),
+            "i64.atomic.rmw8.add_u" => InsnKind::I64AtomicRmw8AddU(self.parser.parse()?),
+            "i64.atomic.rmw16.add_u" => InsnKind::I64AtomicRmw16AddU(self.parser.parse()?),
+            "i64.atomic.rmw32.add_u" => InsnKind::I64AtomicRmw32AddU(self.parser.parse()?),
+            "i32.atomic.rmw.sub" => InsnKind::I32AtomicRmwSub(self.parser.parse()?),
+            "i64.atomic.rmw.sub" => InsnKind::I64AtomicRmwSub(self.parser.parse()?),
+            "i32.atomic.rmw8.sub_u" => InsnKind::I32AtomicRmw8SubU(self.parser.parse()?),
+            "i32.atomic.rmw16.sub_u" => InsnKind::I32AtomicRmw16SubU(self.parser.parse()?),
+            "i64.atomic.rmw8.sub_u" => InsnKind::I64AtomicRmw8SubU(self.parser.parse()?),
+            "i64.atomic.rmw16.sub_u" => InsnKind::I64AtomicRmw16SubU(self.parser.parse()?),
+            "i64.atomic.rmw32.sub_u" => InsnKind::I64AtomicRmw32SubU(self.parser.parse()?),
+            "i32.atomic.rmw.and" => InsnKind::I32AtomicRmwAnd(self.parser.parse()?),
+            "i64.atomic.rmw.and" => InsnKind::I64AtomicRmwAnd(self.parser.parse()?),
+            "i32.atomic.rmw8.and_u" => InsnKind::I32AtomicRmw8AndU(self.parser.parse()?),
+            "i32.atomic.rmw16.and_u" => InsnKind::I32AtomicRmw16AndU(self.parser.parse()?),
+            "i64.atomic.rmw8.and_u" => InsnKind::I64AtomicRmw8AndU(self.parser.parse()?),
+            "i64.atomic.rmw16.and_u" => InsnKind::I64AtomicRmw16AndU(self.parser.parse()?),
+            "i64.atomic.rmw32.and_u" => InsnKind::I64AtomicRmw32AndU(self.parser.parse()?),
+            "i32.atomic.rmw.or" => InsnKind::I32AtomicRmwOr(self.parser.parse()?),
+            "i64.atomic.rmw.or" => InsnKind::I64AtomicRmwOr(self.parser.parse()?),
+            "i32.atomic.rmw8.or_u" => InsnKind::I32AtomicRmw8OrU(self.parser.parse()?),
+            "i32.atomic.rmw16.or_u" => InsnKind::I32AtomicRmw16OrU(self.parser.parse()?),
+            "i64.atomic.rmw8.or_u" => InsnKind::I64AtomicRmw8OrU(self.parser.parse()?),
+            "i64.atomic.rmw16.or_u" => InsnKind::I64AtomicRmw16OrU(self.parser.parse()?),
+            "i64.atomic.rmw32.or_u" => InsnKind::I64AtomicRmw32OrU(self.parser.parse()?),
+            "i32.atomic.rmw.xor" => InsnKind::I32AtomicRmwXor(self.parser.parse()?),
+            "i64.atomic.rmw.xor" => InsnKind::I64AtomicRmwXor(self.parser.parse()?),
+            "i32.atomic.rmw8.xor_u" => InsnKind::I32AtomicRmw8XorU(self.parser.parse()?),
+            "i32.atomic.rmw16.xor_u" => InsnKind::I32AtomicRmw16XorU(self.parser.parse()?),
+            "i64.atomic.rmw8.xor_u" => InsnKind::I64AtomicRmw8XorU(self.parser.parse()?),
+            "i64.atomic.rmw16.xor_u" => InsnKind::I64AtomicRmw16XorU(self.parser.parse()?),
+            "i64.atomic.rmw32.xor_u" => InsnKind::I64AtomicRmw32XorU(self.parser.parse()?),
+            "i32.atomic.rmw.xchg" => InsnKind::I32AtomicRmwXchg(self.parser.parse()?),
+            "i64.atomic.rmw.xchg" => InsnKind::I64AtomicRmwXchg(self.parser.parse()?),
+            "i32.atomic.rmw8.xchg_u" => InsnKind::I32AtomicRmw8XchgU(self.parser.parse()?),
+            "i32.atomic.rmw16.xchg_u" => InsnKind::I32AtomicRmw16XchgU(self.parser.parse()?),
+            "i64.atomic.rmw8.xchg_u" => InsnKind::I64AtomicRmw8XchgU(self.parser.parse()?),
+            "i64.atomic.rmw16.xchg_u" => InsnKind::I64AtomicRmw16XchgU(self.parser.parse()?),
+            "i64.atomic.rmw32.xchg_u" => InsnKind::I64AtomicRmw32XchgU(self.parser.parse()?),
+            "i32.atomic.rmw.cmpxchg" => InsnKind::I32AtomicRmwCmpxchg(self.parser.parse()?),
+            "i64.atomic.rmw.cmpxchg" => InsnKind::I64AtomicRmwCmpxchg(self.parser.parse()?),
+            "i32.atomic.rmw8.cmpxchg_u" => InsnKind::I32AtomicRmw8CmpxchgU(self.parser.parse()?),
+            "i32.atomic.rmw16.cmpxchg_u" => InsnKind::I32AtomicRmw16CmpxchgU(self.parser.parse()?),
+            "i64.atomic.rmw8.cmpxchg_u" => InsnKind::I64AtomicRmw8CmpxchgU(self.parser.parse()?),
+            "i64.atomic.rmw16.cmpxchg_u" => InsnKind::I64AtomicRmw16CmpxchgU(self.parser.parse()?),
+            "i64.atomic.rmw32.cmpxchg_u" => InsnKind::I64AtomicRmw32CmpxchgU(self.parser.parse()?),
             _ => {
                 return self
                     .parser
@@ -2111,6 +2870,7 @@ impl<'s, 'p> MaybeFoldedInsn<'s, 'p> {
             // This assumes that instr* is always ending with
             //   - ')' and 'end' for end of instruction
             //   - 'else' for 'then' clause of 'if' instruction
+            //   - 'catch', 'catch_all' and 'delegate' for body of 'try' instruction
             //   - other than keyword except for above
             match self
                 .parser
@@ -2118,7 +2878,12 @@ impl<'s, 'p> MaybeFoldedInsn<'s, 'p> {
                 .0
             {
                 Token::LParen => self.parse_folded()?,
-                Token::RParen | Token::Keyword("end") | Token::Keyword("else") => return Ok(()),
+                Token::RParen
+                | Token::Keyword("end")
+                | Token::Keyword("else")
+                | Token::Keyword("catch")
+                | Token::Keyword("catch_all")
+                | Token::Keyword("delegate") => return Ok(()),
                 Token::Keyword(_) => {
                     let insn = self.parse_naked_insn(true)?;
                     self.insns.push(insn)
@@ -2145,50 +2910,116 @@ impl<'s> Parse<'s> for Elem<'s> {
         let start = parser.opening_paren("elem")?;
         match_token!(parser, "'elem' keyword", Token::Keyword("elem"));
 
-        // tableidx can be omitted
-        let idx = match parser.peek("table index of elem segment")?.0 {
-            Token::Int(..) | Token::Ident(_) => parser.parse()?,
-            _ => Index::Num(0),
-        };
+        let id = parser.maybe_ident("identifier for elem segment")?;
+        parser.ctx.elem_indices.new_idx(id, start)?;
 
-        let offset = if let Some("offset") = parser.peek_fold_start("offset in elem segment")?.0 {
-            parser.eat_token(); // Eat '('
-            parser.eat_token(); // Eat 'offset'
-            let expr = parser.parse()?;
-            parser.closing_paren("offset parameter of elem segment")?;
-            expr
+        // Passive element segment has no table use and no offset: (elem {id}? {elemlist})
+        // Declarative element segment is only for forward declaration of function references:
+        //   (elem {id}? declare {elemlist})
+        let mode = if let Token::Keyword("func")
+        | Token::Keyword("funcref")
+        | Token::Keyword("externref") = parser.peek("elem segment")?.0
+        {
+            ElemMode::Passive
+        } else if let Token::Keyword("declare") = parser.peek("elem segment")?.0 {
+            parser.eat_token(); // Eat 'declare'
+            ElemMode::Declarative
         } else {
-            // Abbreviation: {instr} == (offset {instr})
-            let mut parser = MaybeFoldedInsn::new(parser);
-            parser.parse_one()?;
-            parser.insns
-        };
+            // Table use can be omitted. Bare table index is also accepted for compatibility with
+            // Wasm MVP
+            let idx = match parser.peek_fold_start("table use of elem segment")?.0 {
+                Some("table") => {
+                    parser.eat_token(); // Eat '('
+                    parser.eat_token(); // Eat 'table'
+                    let idx = parser.parse()?;
+                    parser.closing_paren("table use of elem segment")?;
+                    idx
+                }
+                _ => match parser.peek("table index of elem segment")?.0 {
+                    Token::Int(..) => parser.parse()?,
+                    _ => Index::Num(0),
+                },
+            };
 
-        // This token is not defined in webassembly.github.io/spec but wasm2wat emits it. It seems that this is not
-        // included in Wasm MVP, but it is necessary to parse .wat files emitted by wasm2wat.
-        // https://github.com/WebAssembly/wabt/blob/142c52678acf7decf6a48190c395ed73bb91170a/src/wat-writer.cc#L1271
-        if let (Token::Keyword("func"), _) = parser.peek("")? {
-            parser.eat_token(); // eat 'func'
-        }
+            let offset = if let Some("offset") =
+                parser.peek_fold_start("offset in elem segment")?.0
+            {
+                parser.eat_token(); // Eat '('
+                parser.eat_token(); // Eat 'offset'
+                let expr = parser.parse()?;
+                parser.closing_paren("offset parameter of elem segment")?;
+                expr
+            } else {
+                // Abbreviation: {instr} == (offset {instr})
+                let mut parser = MaybeFoldedInsn::new(parser);
+                parser.parse_one()?;
+                parser.insns
+            };
 
-        let mut init = vec![];
-        loop {
-            if let (Token::RParen, _) = parser.peek("')' for elem segment")? {
-                break;
+            ElemMode::Active { idx, offset }
+        };
+
+        // https://webassembly.github.io/spec/core/text/modules.html#text-elemlist
+        //   {elemlist} := {reftype} {elemexpr}* | 'func' {funcidx}*
+        // 'func' can be omitted for compatibility with Wasm MVP: (elem (offset ...) {funcidx}*)
+        let (ty, init) = match parser.peek("element list of elem segment")?.0 {
+            Token::Keyword("funcref") | Token::Keyword("externref") => {
+                let ty = parser.parse()?;
+                (ty, parse_elem_exprs(parser)?)
             }
-            init.push(parser.parse()?);
-        }
+            Token::Keyword("func") => {
+                parser.eat_token(); // Eat 'func'
+                (RefType::FuncRef, parse_func_indices(parser)?)
+            }
+            _ => (RefType::FuncRef, parse_func_indices(parser)?),
+        };
 
         parser.closing_paren("elem")?;
         Ok(Elem {
             start,
-            idx,
-            offset,
+            id,
+            ty,
+            mode,
             init,
         })
     }
 }
 
+// Function index in element list is an abbreviation of element expression:
+//   {funcidx} == (ref.func {funcidx})
+fn parse_func_indices<'s>(parser: &mut Parser<'s>) -> Result<'s, Vec<Vec<Instruction<'s>>>> {
+    let mut init = vec![];
+    while let (Token::Int(..), start) | (Token::Ident(_), start) =
+        parser.peek("function indices in element list")?
+    {
+        let kind = InsnKind::RefFunc(parser.parse()?);
+        init.push(vec![Instruction { start, kind }]);
+    }
+    Ok(init)
+}
+
+// https://webassembly.github.io/spec/core/text/modules.html#text-elemexpr
+//   (item {expr}) or its abbreviation {instr}
+fn parse_elem_exprs<'s>(parser: &mut Parser<'s>) -> Result<'s, Vec<Vec<Instruction<'s>>>> {
+    let mut init = vec![];
+    loop {
+        match parser.peek_fold_start("element expressions in element list")? {
+            (Some("item"), _) => {
+                parser.eat_token(); // Eat '('
+                parser.eat_token(); // Eat 'item'
+                init.push(parser.parse()?);
+                parser.closing_paren("element expression")?;
+            }
+            (Some(_), _) => {
+                let mut parser = MaybeFoldedInsn::new(parser);
+                parser.parse_one()?;
+                init.push(parser.insns);
+            }
+            (None, _) => return Ok(init),
+        }
+    }
+}
+
 // Helper struct to resolve import/export/elem abbreviation in 'table' section
 // https://webassembly.github.io/spec/core/text/modules.html#text-table-abbrev
 #[cfg_attr(test, derive(Debug))]
@@ -2242,43 +3073,48 @@ impl<'s> Parse<'s> for TableAbbrev<'s> {
                         kw => return parser.error(ParseErrorKind::UnexpectedKeyword(kw), offset),
                     }
                 }
-                Token::Keyword("funcref") => {
-                    // (table {id}? funcref (elem  {funcidx}*)) ==
-                    //   (table {id}' n n funcref) (elem {id}' (i32.const 0) {funcidx}*)
-                    //   where n is length of {funcidx}*
-                    parser.eat_token(); // eat 'funcref' (elemtype)
+                Token::Keyword("funcref") | Token::Keyword("externref") => {
+                    // (table {id}? {reftype} (elem  {elemexpr}*)) ==
+                    //   (table {id}' n n {reftype})
+                    //   (elem (table {id}') (i32.const 0) {reftype} {elemexpr}*)
+                    //   where n is length of {elemexpr}*. {elemexpr}* can also be {funcidx}*
+                    let elem_ty = parser.parse()?;
                     let elem_start = parser.opening_paren("elem argument in table section")?;
+                    parser.ctx.elem_indices.new_idx(None, elem_start)?;
                     match_token!(
                         parser,
                         "'elem' keyword for table section",
                         Token::Keyword("elem")
                     );
 
-                    let mut init = vec![];
-                    while let Token::Int(..) | Token::Ident(_) =
-                        parser.peek("function indices in elem in table section")?.0
-                    {
-                        init.push(parser.parse()?);
-                    }
+                    let init = match parser.peek("elements in elem in table section")?.0 {
+                        Token::Int(..) | Token::Ident(_) => parse_func_indices(parser)?,
+                        _ => parse_elem_exprs(parser)?,
+                    };
 
                     parser.closing_paren("elem argument in table section")?;
                     parser.closing_paren("table")?;
-                    let n = init.len() as u32; // TODO: Check length <= 2^32
+                    let n = u64::from(init.len() as u32); // TODO: Check length <= 2^32
                     let table = Table {
                         start,
                         id,
                         ty: TableType {
                             limit: Limits::Range { min: n, max: n },
+                            elem: elem_ty,
                         },
                         import: None,
                     };
                     let elem = Elem {
                         start: elem_start,
-                        idx: Index::Num(idx),
-                        offset: vec![Instruction {
-                            start: elem_start,
-                            kind: InsnKind::I32Const(0),
-                        }],
+                        id: None,
+                        ty: elem_ty,
+                        mode: ElemMode::Active {
+                            idx: Index::Num(idx),
+                            offset: vec![Instruction {
+                                start: elem_start,
+                                kind: InsnKind::I32Const(0),
+                            }],
+                        },
                         init,
                     };
                     return Ok(TableAbbrev::Elem(table, elem));
@@ -2304,23 +3140,45 @@ impl<'s> Parse<'s> for Data<'s> {
         let start = parser.opening_paren("data")?;
         match_token!(parser, "'data' keyword", Token::Keyword("data"));
 
-        // the memory index can be omitted, defaulting to 𝟶.
-        let idx = match parser.peek("memory index for data")?.0 {
-            Token::Int(..) | Token::Ident(_) => parser.parse()?,
-            _ => Index::Num(0),
-        };
+        let id = parser.maybe_ident("identifier for data segment")?;
+        parser.ctx.data_indices.new_idx(id, start)?;
 
-        let offset = if let Some("offset") = parser.peek_fold_start("offset in data segment")?.0 {
-            parser.eat_token(); // Eat '('
-            parser.eat_token(); // Eat 'offset'
-            let offset = parser.parse()?;
-            parser.closing_paren("offset of data segment")?;
-            offset
+        // Passive data segment has no memory use and no offset: (data {id}? {datastring})
+        let mode = if let Token::String(_) | Token::RParen = parser.peek("data segment")?.0 {
+            DataMode::Passive
         } else {
-            // Abbreviation: {instr} == (offset {instr})
-            let mut parser = MaybeFoldedInsn::new(parser);
-            parser.parse_one()?;
-            parser.insns
+            // the memory use can be omitted, defaulting to 𝟶. Bare memory index is also
+            // accepted for compatibility with Wasm MVP
+            let idx = match parser.peek_fold_start("memory use of data segment")?.0 {
+                Some("memory") => {
+                    parser.eat_token(); // Eat '('
+                    parser.eat_token(); // Eat 'memory'
+                    let idx = parser.parse()?;
+                    parser.closing_paren("memory use of data segment")?;
+                    idx
+                }
+                _ => match parser.peek("memory index for data")?.0 {
+                    Token::Int(..) => parser.parse()?,
+                    _ => Index::Num(0),
+                },
+            };
+
+            let offset = if let Some("offset") =
+                parser.peek_fold_start("offset in data segment")?.0
+            {
+                parser.eat_token(); // Eat '('
+                parser.eat_token(); // Eat 'offset'
+                let offset = parser.parse()?;
+                parser.closing_paren("offset of data segment")?;
+                offset
+            } else {
+                // Abbreviation: {instr} == (offset {instr})
+                let mut parser = MaybeFoldedInsn::new(parser);
+                parser.parse_one()?;
+                parser.insns
+            };
+
+            DataMode::Active { idx, offset }
         };
 
         let mut data = vec![];
@@ -2329,8 +3187,8 @@ impl<'s> Parse<'s> for Data<'s> {
                 (Token::RParen, _) => {
                     return Ok(Data {
                         start,
-                        idx,
-                        offset,
+                        id,
+                        mode,
                         data: Cow::Owned(data),
                     });
                 }
@@ -2364,9 +3222,16 @@ impl<'s> Parse<'s> for MemoryAbbrev<'s> {
 
         let id = parser.maybe_ident("identifier for memory section")?;
         let idx = parser.ctx.mem_indices.new_idx(id, start)?;
+        let mut memory64 = false;
 
         loop {
             match parser.peek("argument of memory section")?.0 {
+                Token::Keyword("i64") | Token::Keyword("i32")
+                    if matches!(parser.lookahead("memory section")?.0, Token::LParen) =>
+                {
+                    // Index type precedes data abbreviation: (memory {id}? i64 (data ...))
+                    memory64 = parse_memory64(parser);
+                }
                 Token::LParen => {
                     parser.eat_token(); // eat '('
                     let (keyword, offset) = match_token!(parser, "'import' or 'export' or 'data' for memory section", Token::Keyword(k) => k);
@@ -2403,6 +3268,7 @@ impl<'s> Parse<'s> for MemoryAbbrev<'s> {
                             // (memory {id}? (data  {datastring})) ==
                             //   (memory {id}' m m) (data {id}' (i32.const 0) {datastring})
                             //   where n = data bytes, m = ceil(n / 64Ki), Note: 64Ki means page size
+                            parser.ctx.data_indices.new_idx(None, start)?;
 
                             let mut data = vec![];
                             loop {
@@ -2427,7 +3293,13 @@ impl<'s> Parse<'s> for MemoryAbbrev<'s> {
                             parser.closing_paren("memory")?;
 
                             // Infer memory limits from page size (64 * 1024 = 65536)
-                            let n = (data.len() as f64 / 65536.0).ceil() as u32;
+                            let n = (data.len() as f64 / 65536.0).ceil() as u64;
+                            // Offset is i64 for 64-bit memory
+                            let offset = if memory64 {
+                                InsnKind::I64Const(0)
+                            } else {
+                                InsnKind::I32Const(0)
+                            };
 
                             return Ok(MemoryAbbrev::Data(
                                 Memory {
@@ -2435,16 +3307,21 @@ impl<'s> Parse<'s> for MemoryAbbrev<'s> {
                                     id,
                                     ty: MemType {
                                         limit: Limits::Range { min: n, max: n },
+                                        shared: false,
+                                        memory64,
                                     },
                                     import: None,
                                 },
                                 Data {
                                     start,
-                                    idx: Index::Num(idx),
-                                    offset: vec![Instruction {
-                                        start,
-                                        kind: InsnKind::I32Const(0),
-                                    }],
+                                    id: None,
+                                    mode: DataMode::Active {
+                                        idx: Index::Num(idx),
+                                        offset: vec![Instruction {
+                                            start,
+                                            kind: offset,
+                                        }],
+                                    },
                                     data: Cow::Owned(data),
                                 },
                             ));
@@ -2547,6 +3424,55 @@ impl<'s> Parse<'s> for Global<'s> {
     }
 }
 
+// https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#tag-definitions
+impl<'s> Parse<'s> for Tag<'s> {
+    fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
+        let start = parser.opening_paren("tag")?;
+        match_token!(parser, "'tag' keyword", Token::Keyword("tag"));
+
+        let id = parser.maybe_ident("identifier for tag")?;
+        let idx = parser.ctx.tag_indices.new_idx(id, start)?;
+
+        // Note: Tag has import/export abbreviation as well as global
+        //   (tag {id}? (export {name}) ...) == (export {name} (tag {id}')) (tag {id}' ...)
+        //   (tag {id}? (import {name} {name}) {typeuse}) == (import {name} {name} (tag {id}? {typeuse}))
+        let mut import = None;
+        loop {
+            match parser.peek_fold_start("export, import or type use of tag")?.0 {
+                Some("export") => {
+                    parser.eat_token(); // Eat '('
+                    parser.eat_token(); // Eat 'export'
+                    let name = parser.parse()?;
+                    parser.closing_paren("export argument in tag")?;
+                    parser.ctx.exports.push(Export {
+                        start,
+                        name,
+                        kind: ExportKind::Tag,
+                        idx: Index::Num(idx),
+                    });
+                }
+                Some("import") => {
+                    parser.eat_token(); // Eat '('
+                    parser.eat_token(); // Eat 'import'
+                    import = Some(parser.parse()?);
+                    parser.closing_paren("import argument in tag")?;
+                    break;
+                }
+                _ => break,
+            }
+        }
+
+        let ty = parser.parse()?;
+        parser.closing_paren("tag")?;
+        Ok(Tag {
+            start,
+            id,
+            ty,
+            import,
+        })
+    }
+}
+
 // https://webassembly.github.io/spec/core/text/modules.html#text-start
 impl<'s> Parse<'s> for Start<'s> {
     fn parse(parser: &mut Parser<'s>) -> Result<'s, Self> {
@@ -2562,6 +3488,19 @@ impl<'s> Parse<'s> for Start<'s> {
 mod tests {
     use super::*;
 
+    // Function indices in element list are parsed as 'ref.func' expressions
+    fn func_indices<'a, 's>(init: &'a [Vec<Instruction<'s>>]) -> Vec<&'a Index<'s>> {
+        init.iter()
+            .map(|expr| match expr.as_slice() {
+                [Instruction {
+                    kind: InsnKind::RefFunc(idx),
+                    ..
+                }] => idx,
+                insns => panic!("not a function index: {:?}", insns),
+            })
+            .collect()
+    }
+
     #[test]
     fn lookahead() {
         let v = vec![1, 2, 3, 4];
@@ -2640,7 +3579,7 @@ mod tests {
             parser
         }};
         ($input:expr, $node:ty, $expect:pat) => {
-            assert_parse!($input, $node, $expect if true);
+            assert_parse!($input, $node, $expect if true)
         };
     }
 
@@ -2696,6 +3635,7 @@ mod tests {
                 table_indices,
                 mem_indices,
                 global_indices,
+                ..
             }
             if types.len() == 2
                && elems.is_empty()
@@ -3018,6 +3958,7 @@ mod tests {
         assert_parse!(r#"i64"#, ValType, ValType::I64);
         assert_parse!(r#"f32"#, ValType, ValType::F32);
         assert_parse!(r#"f64"#, ValType, ValType::F64);
+        assert_parse!(r#"v128"#, ValType, ValType::V128);
 
         assert_error!(r#"string"#, ValType, InvalidValType("string"));
         assert_error!(r#"$hello"#, ValType, UnexpectedToken{ expected: "keyword for value type", .. });
@@ -3382,18 +4323,26 @@ mod tests {
             r#"0 funcref"#,
             TableType,
             TableType {
-                limit: Limits::From { min: 0 }
+                limit: Limits::From { min: 0 },
+                elem: RefType::FuncRef,
             }
         );
         assert_parse!(
             r#"0 1 funcref"#,
             TableType,
             TableType {
-                limit: Limits::Range { min: 0, max: 1 }
+                limit: Limits::Range { min: 0, max: 1 },
+                elem: RefType::FuncRef,
             }
         );
 
-        assert_error!(r#"0 1 hi"#, TableType, UnexpectedToken{ expected: "'funcref' keyword for table type", .. });
+        assert_parse!(
+            r#"0 externref"#,
+            TableType,
+            TableType{ limit: Limits::From { min: 0 }, elem: RefType::ExternRef }
+        );
+
+        assert_error!(r#"0 1 hi"#, TableType, UnexpectedToken{ expected: "'funcref' or 'externref' keyword for reference type", .. });
         assert_error!(r#"hi"#, TableType, UnexpectedToken{ expected: "u32 for min table limit", .. });
     }
 
@@ -3403,9 +4352,50 @@ mod tests {
             r#"0 10"#,
             MemType,
             MemType {
-                limit: Limits::Range { min: 0, max: 10 }
+                limit: Limits::Range { min: 0, max: 10 },
+                shared: false,
+                memory64: false,
+            }
+        );
+        assert_parse!(
+            r#"1 2 shared"#,
+            MemType,
+            MemType {
+                limit: Limits::Range { min: 1, max: 2 },
+                shared: true,
+                memory64: false,
+            }
+        );
+        assert_parse!(
+            r#"i64 1 0x1_0000_0000_0000"#,
+            MemType,
+            MemType {
+                limit: Limits::Range { min: 1, max: 0x1_0000_0000_0000 },
+                shared: false,
+                memory64: true,
+            }
+        );
+        assert_parse!(
+            r#"i32 1 2"#,
+            MemType,
+            MemType {
+                limit: Limits::Range { min: 1, max: 2 },
+                shared: false,
+                memory64: false,
+            }
+        );
+        assert_parse!(
+            r#"i64 1 2 shared"#,
+            MemType,
+            MemType {
+                shared: true,
+                memory64: true,
+                ..
             }
         );
+
+        assert_error!(r#"1 0x1_0000_0000"#, MemType, CannotParseNum{ .. });
+        assert_error!(r#"i64 -1"#, MemType, NumberMustBePositive{ .. });
     }
 
     #[test]
@@ -3843,220 +4833,249 @@ mod tests {
         assert_insn!(
             r#"block end"#,
             [
-                Block{ label: None, ty: None, body, id: None }
+                Block{ label: None, ty: BlockType::Empty, body, id: None }
             ] if body.is_empty()
         );
         assert_insn!(
             r#"block $blk end $id"#,
             [
-                Block{ label: Some("$blk"), ty: None, body, id: Some("$id") }
+                Block{ label: Some("$blk"), ty: BlockType::Empty, body, id: Some("$id") }
             ] if body.is_empty()
         );
         assert_insn!(
             r#"block $blk (result i32) end"#,
             [
-                Block{ label: Some("$blk"), ty: Some(ValType::I32), body, id: None }
+                Block{ label: Some("$blk"), ty: BlockType::Value(ValType::I32), body, id: None }
             ] if body.is_empty()
         );
         assert_insn!(
             r#"block (result i32) end"#,
             [
-                Block{ label: None, ty: Some(ValType::I32), body, id: None }
+                Block{ label: None, ty: BlockType::Value(ValType::I32), body, id: None }
             ] if body.is_empty()
         );
         assert_insn!(
             r#"block nop end"#,
             [
-                Block{ label: None, ty: None, body, id: None }
+                Block{ label: None, ty: BlockType::Empty, body, id: None }
             ] if matches!(body[0].kind, Nop)
         );
         assert_insn!(
             r#"block $blk nop end"#,
             [
-                Block{ label: Some("$blk"), ty: None, body, id: None }
+                Block{ label: Some("$blk"), ty: BlockType::Empty, body, id: None }
             ] if matches!(body[0].kind, Nop)
         );
         assert_insn!(
             r#"block (result i32) nop end"#,
             [
-                Block{ label: None, ty: Some(ValType::I32), body, id: None }
+                Block{ label: None, ty: BlockType::Value(ValType::I32), body, id: None }
             ] if matches!(body[0].kind, Nop)
         );
         assert_insn!(
             r#"(block)"#,
             [
-                Block{ label: None, ty: None, body, id: None }
+                Block{ label: None, ty: BlockType::Empty, body, id: None }
             ] if body.is_empty()
         );
         assert_insn!(
             r#"(block $blk)"#,
             [
-                Block{ label: Some("$blk"), ty: None, body, id: None }
+                Block{ label: Some("$blk"), ty: BlockType::Empty, body, id: None }
             ] if body.is_empty()
         );
         assert_insn!(
             r#"(block (result i32))"#,
             [
-                Block{ label: None, ty: Some(ValType::I32), body, id: None }
+                Block{ label: None, ty: BlockType::Value(ValType::I32), body, id: None }
             ] if body.is_empty()
         );
         assert_insn!(
             r#"(block nop)"#,
             [
-                Block{ label: None, ty: None, body, id: None }
+                Block{ label: None, ty: BlockType::Empty, body, id: None }
             ] if matches!(body[0].kind, Nop)
         );
+        assert_insn!(
+            r#"(block (result i32 i64))"#,
+            [
+                Block{ ty: BlockType::TypeUse(TypeUse{ idx: Index::Num(0), params, results, .. }), .. }
+            ] if params.is_empty() && results.len() == 2
+        );
+        assert_insn!(
+            r#"block (param i32) (result f32) end"#,
+            [
+                Block{ ty: BlockType::TypeUse(TypeUse{ idx: Index::Num(0), params, results, .. }), .. }
+            ] if params.len() == 1 && results.len() == 1
+        );
+        assert_insn!(
+            r#"(block (type $t) (result i32))"#,
+            [
+                Block{ ty: BlockType::TypeUse(TypeUse{ idx: Index::Ident("$t"), params, results, .. }), .. }
+            ] if params.is_empty() && results.len() == 1
+        );
+        assert_insn!(
+            r#"(block (result))"#,
+            [
+                Block{ ty: BlockType::Empty, .. }
+            ]
+        );
+        assert_error!(
+            r#"(block (param $x i32))"#,
+            Vec<Instruction>,
+            InvalidOperand { insn: "block", .. }
+        );
         // Note: 'loop' instruction is parsed with the same logic as 'block' instruction. Only one test case is sufficient
         assert_insn!(
             r#"loop end"#,
             [
-                Loop{ label: None, ty: None, body, id: None }
+                Loop{ label: None, ty: BlockType::Empty, body, id: None }
             ] if body.is_empty()
         );
         assert_insn!(
             r#"if end"#,
             [
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"if else end"#,
             [
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"if $l (result i32) else $a end $b"#,
             [
-                If{ label: Some("$l"), ty: Some(ValType::I32), then_body, else_id: Some("$a"), else_body, end_id: Some("$b") }
+                If{ label: Some("$l"), ty: BlockType::Value(ValType::I32), then_body, else_id: Some("$a"), else_body, end_id: Some("$b") }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"if $l (result i32) else $a end $b"#,
             [
-                If{ label: Some("$l"), ty: Some(ValType::I32), then_body, else_id: Some("$a"), else_body, end_id: Some("$b") }
+                If{ label: Some("$l"), ty: BlockType::Value(ValType::I32), then_body, else_id: Some("$a"), else_body, end_id: Some("$b") }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"if $l (result i32) end $b"#,
             [
-                If{ label: Some("$l"), ty: Some(ValType::I32), then_body, else_id: None, else_body, end_id: Some("$b") }
+                If{ label: Some("$l"), ty: BlockType::Value(ValType::I32), then_body, else_id: None, else_body, end_id: Some("$b") }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"if $l end"#,
             [
-                If{ label: Some("$l"), ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: Some("$l"), ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"if (result i32) end"#,
             [
-                If{ label: None, ty: Some(ValType::I32), then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Value(ValType::I32), then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"if nop end"#,
             [
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if matches!(then_body[0].kind, Nop) && else_body.is_empty()
         );
         assert_insn!(
             r#"if $l nop end"#,
             [
-                If{ label: Some("$l"), ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: Some("$l"), ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if matches!(then_body[0].kind, Nop) && else_body.is_empty()
         );
         assert_insn!(
             r#"if $l (result i32) nop end"#,
             [
-                If{ label: Some("$l"), ty: Some(ValType::I32), then_body, else_id: None, else_body, end_id: None }
+                If{ label: Some("$l"), ty: BlockType::Value(ValType::I32), then_body, else_id: None, else_body, end_id: None }
             ] if matches!(then_body[0].kind, Nop) && else_body.is_empty()
         );
         assert_insn!(
             r#"if nop else unreachable end"#,
             [
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if matches!(then_body[0].kind, Nop) && matches!(else_body[0].kind, Unreachable)
         );
         assert_insn!(
             r#"(if (then))"#,
             [
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"(if $l (then))"#,
             [
-                If{ label: Some("$l"), ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: Some("$l"), ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"(if $l (result i32) (then))"#,
             [
-                If{ label: Some("$l"), ty: Some(ValType::I32), then_body, else_id: None, else_body, end_id: None }
+                If{ label: Some("$l"), ty: BlockType::Value(ValType::I32), then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"(if (then) (else))"#,
             [
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"(if $l (then) (else))"#,
             [
-                If{ label: Some("$l"), ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: Some("$l"), ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"(if $l (result i32) (then) (else))"#,
             [
-                If{ label: Some("$l"), ty: Some(ValType::I32), then_body, else_id: None, else_body, end_id: None }
+                If{ label: Some("$l"), ty: BlockType::Value(ValType::I32), then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"(if (then nop))"#,
             [
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if matches!(then_body[0].kind, Nop) && else_body.is_empty()
         );
         assert_insn!(
             r#"(if (then nop) (else nop))"#,
             [
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if matches!(then_body[0].kind, Nop) && matches!(else_body[0].kind, Nop)
         );
         assert_insn!(
             r#"(if (then (nop)) (else (nop)))"#,
             [
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if matches!(then_body[0].kind, Nop) && matches!(else_body[0].kind, Nop)
         );
         assert_insn!(
             r#"(if (then nop nop) (else nop nop))"#,
             [
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if then_body.len() == 2 && else_body.len() == 2
         );
         assert_insn!(
             r#"(if (then (nop (nop))) (else (nop (nop))))"#,
             [
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if then_body.len() == 2 && else_body.len() == 2
         );
         assert_insn!(
             r#"(if (nop) (then) (else))"#,
             [
                 Nop,
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(
             r#"(if (nop) (nop) (nop) (then))"#,
             [
                 Nop, Nop, Nop,
-                If{ label: None, ty: None, then_body, else_id: None, else_body, end_id: None }
+                If{ label: None, ty: BlockType::Empty, then_body, else_id: None, else_body, end_id: None }
             ] if then_body.is_empty() && else_body.is_empty()
         );
         assert_insn!(r#"unreachable"#, [Unreachable]);
@@ -4105,7 +5124,70 @@ mod tests {
         assert_insn!(r#"call $f"#, [Call(Index::Ident("$f"))]);
         assert_insn!(
             r#"call_indirect (type 0)"#,
-            [CallIndirect(TypeUse{ idx: Index::Num(0), .. })]
+            [CallIndirect{ table: Index::Num(0), ty: TypeUse{ idx: Index::Num(0), .. } }]
+        );
+        assert_insn!(r#"return_call $f"#, [ReturnCall(Index::Ident("$f"))]);
+        assert_insn!(
+            r#"return_call_indirect $t (type 1)"#,
+            [ReturnCallIndirect{ table: Index::Ident("$t"), ty: TypeUse{ idx: Index::Num(1), .. } }]
+        );
+        assert_insn!(
+            r#"(return_call_indirect (param i32) (local.get 0) (i32.const 0))"#,
+            [LocalGet(..), I32Const(0), ReturnCallIndirect{ table: Index::Num(0), .. }]
+        );
+        assert_insn!(r#"throw $e"#, [Throw(Index::Ident("$e"))]);
+        assert_insn!(r#"rethrow 0"#, [Rethrow(Index::Num(0))]);
+        assert_insn!(
+            r#"try $l (result i32) nop catch $e catch 1 nop nop catch_all nop end $l"#,
+            [
+                Try {
+                    label: Some("$l"),
+                    ty: BlockType::Value(ValType::I32),
+                    body,
+                    handler: TryHandler::Catch { catches, catch_all: Some(catch_all) },
+                    id: Some("$l"),
+                }
+            ] if body.len() == 1
+                && matches!(catches.as_slice(), [
+                    Catch { tag: Index::Ident("$e"), body: b1, .. },
+                    Catch { tag: Index::Num(1), body: b2, .. },
+                ] if b1.is_empty() && b2.len() == 2)
+                && catch_all.len() == 1
+        );
+        assert_insn!(
+            r#"try end"#,
+            [Try{ handler: TryHandler::Catch { catches, catch_all: None }, id: None, .. }] if catches.is_empty()
+        );
+        assert_insn!(
+            r#"try nop delegate $l nop"#,
+            [Try{ handler: TryHandler::Delegate(Index::Ident("$l")), id: None, .. }, Nop]
+        );
+        assert_insn!(
+            r#"(try (do (throw $e (i32.const 1))) (catch $e drop) (catch_all))"#,
+            [
+                Try {
+                    body,
+                    handler: TryHandler::Catch { catches, catch_all: Some(catch_all) },
+                    ..
+                }
+            ] if matches!(body.as_slice(), [Instruction { kind: I32Const(1), .. }, Instruction { kind: Throw(_), .. }])
+                && matches!(catches.as_slice(), [Catch { body, .. }] if body.len() == 1)
+                && catch_all.is_empty()
+        );
+        assert_insn!(
+            r#"(try $l (result i32) (do (i32.const 0)) (delegate 1))"#,
+            [Try{ label: Some("$l"), handler: TryHandler::Delegate(Index::Num(1)), .. }]
+        );
+
+        assert_error!(
+            r#"try catch_all catch $e end"#,
+            Vec<Instruction<'_>>,
+            UnexpectedToken{ got: Token::Keyword("catch"), .. }
+        );
+        assert_error!(
+            r#"(try (catch $e))"#,
+            Vec<Instruction<'_>>,
+            UnexpectedToken{ got: Token::Keyword("catch"), .. }
         );
 
         assert_error!(r#"br_table)"#, Vec<Instruction<'_>>, InvalidOperand{ .. });
@@ -4121,6 +5203,33 @@ mod tests {
         use InsnKind::*;
         assert_insn!(r#"drop"#, [Drop]);
         assert_insn!(r#"select"#, [Select]);
+        assert_insn!(
+            r#"select (result i32)"#,
+            [TypedSelect(tys)] if tys.as_slice() == [ValType::I32]
+        );
+        assert_insn!(
+            r#"(select (result externref) (local.get 0) (local.get 1) (i32.const 1))"#,
+            [LocalGet(..), LocalGet(..), I32Const(1), TypedSelect(tys)] if tys.as_slice() == [ValType::ExternRef]
+        );
+    }
+
+    #[test]
+    fn reference_instructions() {
+        use InsnKind::*;
+        assert_insn!(r#"ref.null func"#, [RefNull(RefType::FuncRef)]);
+        assert_insn!(r#"ref.null extern"#, [RefNull(RefType::ExternRef)]);
+        assert_insn!(r#"ref.is_null"#, [RefIsNull]);
+        assert_insn!(r#"ref.func $f"#, [RefFunc(Index::Ident("$f"))]);
+        assert_insn!(
+            r#"call_indirect $t (type 0)"#,
+            [CallIndirect{ table: Index::Ident("$t"), ty: TypeUse{ idx: Index::Num(0), .. } }]
+        );
+
+        assert_error!(
+            r#"ref.null funcref"#,
+            Vec<Instruction<'_>>,
+            UnexpectedToken{ expected: "'func' or 'extern' keyword for heap type", .. }
+        );
     }
 
     #[test]
@@ -4143,7 +5252,8 @@ mod tests {
             r#"i32.load"#,
             [I32Load(Mem {
                 align: None,
-                offset: None
+                offset: None,
+                memory: Index::Num(0),
             })]
         );
         assert_insn!(
@@ -4151,6 +5261,7 @@ mod tests {
             [I32Load(Mem {
                 align: Some(32),
                 offset: None,
+                ..
             })]
         );
         assert_insn!(
@@ -4158,6 +5269,7 @@ mod tests {
             [I32Load(Mem {
                 align: None,
                 offset: Some(10),
+                ..
             })]
         );
         assert_insn!(
@@ -4165,6 +5277,7 @@ mod tests {
             [I32Load(Mem {
                 align: Some(32),
                 offset: Some(10),
+                ..
             })]
         );
         assert_insn!(
@@ -4172,6 +5285,30 @@ mod tests {
             [I32Load(Mem {
                 align: Some(0x80),
                 offset: Some(0x1f),
+                ..
+            })]
+        );
+        assert_insn!(
+            r#"i32.load $m offset=4"#,
+            [I32Load(Mem {
+                offset: Some(4),
+                memory: Index::Ident("$m"),
+                ..
+            })]
+        );
+        // Offset for 64-bit memory may be larger than u32
+        assert_insn!(
+            r#"i64.load offset=0x1_0000_0000"#,
+            [I64Load(Mem {
+                offset: Some(0x1_0000_0000),
+                ..
+            })]
+        );
+        assert_insn!(
+            r#"i64.store 1"#,
+            [I64Store(Mem {
+                memory: Index::Num(1),
+                ..
             })]
         );
         assert_insn!(r#"i64.load"#, [I64Load(..)]);
@@ -4196,8 +5333,52 @@ mod tests {
         assert_insn!(r#"i64.store8"#, [I64Store8(..)]);
         assert_insn!(r#"i64.store16"#, [I64Store16(..)]);
         assert_insn!(r#"i64.store32"#, [I64Store32(..)]);
-        assert_insn!(r#"memory.size"#, [MemorySize]);
-        assert_insn!(r#"memory.grow"#, [MemoryGrow]);
+        assert_insn!(r#"memory.size"#, [MemorySize(Index::Num(0))]);
+        assert_insn!(r#"memory.size $m"#, [MemorySize(Index::Ident("$m"))]);
+        assert_insn!(r#"memory.grow"#, [MemoryGrow(Index::Num(0))]);
+        assert_insn!(r#"memory.grow 1"#, [MemoryGrow(Index::Num(1))]);
+        assert_insn!(
+            r#"memory.init 1"#,
+            [MemoryInit{ memory: Index::Num(0), data: Index::Num(1) }]
+        );
+        assert_insn!(
+            r#"memory.init $m $d"#,
+            [MemoryInit{ memory: Index::Ident("$m"), data: Index::Ident("$d") }]
+        );
+        assert_insn!(r#"data.drop $d"#, [DataDrop(Index::Ident("$d"))]);
+        assert_insn!(
+            r#"memory.copy"#,
+            [MemoryCopy{ dst: Index::Num(0), src: Index::Num(0) }]
+        );
+        assert_insn!(
+            r#"memory.copy $a $b"#,
+            [MemoryCopy{ dst: Index::Ident("$a"), src: Index::Ident("$b") }]
+        );
+        assert_insn!(r#"memory.fill"#, [MemoryFill(Index::Num(0))]);
+        assert_insn!(r#"memory.fill 2"#, [MemoryFill(Index::Num(2))]);
+        assert_insn!(
+            r#"table.init $e"#,
+            [TableInit{ table: Index::Num(0), elem: Index::Ident("$e") }]
+        );
+        assert_insn!(
+            r#"table.init $t 1"#,
+            [TableInit{ table: Index::Ident("$t"), elem: Index::Num(1) }]
+        );
+        assert_insn!(r#"elem.drop 0"#, [ElemDrop(Index::Num(0))]);
+        assert_insn!(
+            r#"table.copy"#,
+            [TableCopy{ dst: Index::Num(0), src: Index::Num(0) }]
+        );
+        assert_insn!(
+            r#"table.copy $t 1"#,
+            [TableCopy{ dst: Index::Ident("$t"), src: Index::Num(1) }]
+        );
+        assert_insn!(r#"table.get"#, [TableGet(Index::Num(0))]);
+        assert_insn!(r#"table.set $t"#, [TableSet(Index::Ident("$t"))]);
+        assert_insn!(r#"table.size 1"#, [TableSize(Index::Num(1))]);
+        assert_insn!(r#"table.grow"#, [TableGrow(Index::Num(0))]);
+        assert_insn!(r#"table.fill 2"#, [TableFill(Index::Num(2))]);
+        assert_insn!(r#"(table.get $t (i32.const 0))"#, [I32Const(0), TableGet(Index::Ident("$t"))]);
 
         assert_error!(
             r#"i32.load align=32 offset=10"#,
@@ -4525,6 +5706,117 @@ mod tests {
         assert_insn!(r#"i64.reinterpret_f64"#, [I64ReinterpretF64]);
         assert_insn!(r#"f32.reinterpret_i32"#, [F32ReinterpretI32]);
         assert_insn!(r#"f64.reinterpret_i64"#, [F64ReinterpretI64]);
+        assert_insn!(r#"i32.extend8_s"#, [I32Extend8S]);
+        assert_insn!(r#"i32.extend16_s"#, [I32Extend16S]);
+        assert_insn!(r#"i64.extend8_s"#, [I64Extend8S]);
+        assert_insn!(r#"i64.extend16_s"#, [I64Extend16S]);
+        assert_insn!(r#"i64.extend32_s"#, [I64Extend32S]);
+        assert_insn!(r#"i32.trunc_sat_f32_s"#, [I32TruncSatF32S]);
+        assert_insn!(r#"i32.trunc_sat_f32_u"#, [I32TruncSatF32U]);
+        assert_insn!(r#"i32.trunc_sat_f64_s"#, [I32TruncSatF64S]);
+        assert_insn!(r#"i32.trunc_sat_f64_u"#, [I32TruncSatF64U]);
+        assert_insn!(r#"i64.trunc_sat_f32_s"#, [I64TruncSatF32S]);
+        assert_insn!(r#"i64.trunc_sat_f32_u"#, [I64TruncSatF32U]);
+        assert_insn!(r#"i64.trunc_sat_f64_s"#, [I64TruncSatF64S]);
+        assert_insn!(r#"i64.trunc_sat_f64_u"#, [I64TruncSatF64U]);
+    }
+
+    #[test]
+    fn vector_instructions() {
+        use InsnKind::*;
+        assert_insn!(
+            r#"v128.const i32x4 1 2 3 4"#,
+            [V128Const(v)] if *v == 1 | 2 << 32 | 3 << 64 | 4 << 96
+        );
+        assert_insn!(
+            r#"v128.const i8x16 -1 255 -128 128 0 0 0 0 0 0 0 0 0 0 0 0x7f"#,
+            [V128Const(v)] if *v == 0x7f00_0000_0000_0000_0000_0000_8080_ffff
+        );
+        assert_insn!(
+            r#"v128.const i16x8 -1 0 0 0 0 0 0 0xffff"#,
+            [V128Const(v)] if *v == 0xffff_0000_0000_0000_0000_0000_0000_ffff_u128 as i128
+        );
+        assert_insn!(r#"v128.const i64x2 -1 -1"#, [V128Const(-1)]);
+        assert_insn!(
+            r#"v128.const f32x4 1.0 0 0 -0x1p+0"#,
+            [V128Const(v)] if *v == 0xbf80_0000_0000_0000_0000_0000_3f80_0000_u128 as i128
+        );
+        assert_insn!(
+            r#"v128.const f64x2 inf 0"#,
+            [V128Const(v)] if *v == 0x7ff0_0000_0000_0000
+        );
+        assert_insn!(
+            r#"i8x16.shuffle 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 31"#,
+            [I8x16Shuffle(l)] if *l == [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 31]
+        );
+        assert_insn!(r#"i8x16.extract_lane_s 15"#, [I8x16ExtractLaneS(15)]);
+        assert_insn!(r#"f64x2.replace_lane 1"#, [F64x2ReplaceLane(1)]);
+        assert_insn!(r#"i32x4.add"#, [I32x4Add]);
+        assert_insn!(r#"i32x4.trunc_sat_f64x2_u_zero"#, [I32x4TruncSatF64x2UZero]);
+        assert_insn!(
+            r#"v128.load offset=16 align=4"#,
+            [V128Load(Mem { align: Some(4), offset: Some(16), memory: Index::Num(0) })]
+        );
+        assert_insn!(
+            r#"v128.load8_lane 1"#,
+            [V128Load8Lane { mem: Mem { align: None, offset: None, memory: Index::Num(0) }, lane: 1 }]
+        );
+        assert_insn!(
+            r#"v128.load8_lane 1 2"#,
+            [V128Load8Lane { mem: Mem { align: None, offset: None, memory: Index::Num(1) }, lane: 2 }]
+        );
+        assert_insn!(
+            r#"v128.load32_lane 0 offset=4 3"#,
+            [V128Load32Lane { mem: Mem { offset: Some(4), memory: Index::Num(0), .. }, lane: 3 }]
+        );
+        assert_insn!(
+            r#"v128.store16_lane $m align=2 7"#,
+            [V128Store16Lane { mem: Mem { align: Some(2), memory: Index::Ident("$m"), .. }, lane: 7 }]
+        );
+
+        assert_error!(
+            r#"v128.const i32x4 1 2 3)"#,
+            Vec<Instruction<'_>>,
+            UnexpectedToken{ expected: "integer for i32x4 lane of v128.const", .. }
+        );
+        assert_error!(
+            r#"v128.const i8x16 256 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0"#,
+            Vec<Instruction<'_>>,
+            CannotParseNum{ .. }
+        );
+        assert_error!(
+            r#"v128.const i128 0"#,
+            Vec<Instruction<'_>>,
+            UnexpectedToken{ expected: "shape of v128.const operand", .. }
+        );
+        assert_error!(
+            r#"i8x16.extract_lane_s -1"#,
+            Vec<Instruction<'_>>,
+            NumberMustBePositive(..)
+        );
+        assert_error!(
+            r#"v128.load8_lane)"#,
+            Vec<Instruction<'_>>,
+            UnexpectedToken{ expected: "lane index for memory lane instruction", .. }
+        );
+    }
+
+    #[test]
+    fn atomic_instructions() {
+        use InsnKind::*;
+        assert_insn!(r#"memory.atomic.notify"#, [MemoryAtomicNotify(..)]);
+        assert_insn!(r#"memory.atomic.wait32"#, [MemoryAtomicWait32(..)]);
+        assert_insn!(r#"memory.atomic.wait64"#, [MemoryAtomicWait64(..)]);
+        assert_insn!(r#"atomic.fence"#, [AtomicFence]);
+        assert_insn!(r#"i32.atomic.load"#, [I32AtomicLoad(..)]);
+        assert_insn!(r#"i64.atomic.load32_u"#, [I64AtomicLoad32U(..)]);
+        assert_insn!(r#"i32.atomic.store16"#, [I32AtomicStore16(..)]);
+        assert_insn!(r#"i64.atomic.rmw.add"#, [I64AtomicRmwAdd(..)]);
+        assert_insn!(r#"i32.atomic.rmw8.xchg_u"#, [I32AtomicRmw8XchgU(..)]);
+        assert_insn!(
+            r#"i64.atomic.rmw32.cmpxchg_u 1 offset=8"#,
+            [I64AtomicRmw32CmpxchgU(Mem { align: None, offset: Some(8), memory: Index::Num(1) })]
+        );
     }
 
     #[test]
@@ -4534,8 +5826,7 @@ mod tests {
             r#"(elem i32.const 10)"#,
             Elem<'_>,
             Elem {
-                idx: Index::Num(0),
-                offset,
+                mode: ElemMode::Active { idx: Index::Num(0), offset },
                 init,
                 ..
             } if matches!(offset[0].kind, I32Const(10)) && init.is_empty()
@@ -4544,8 +5835,7 @@ mod tests {
             r#"(elem 0x1f i32.const 10)"#,
             Elem<'_>,
             Elem {
-                idx: Index::Num(0x1f),
-                offset,
+                mode: ElemMode::Active { idx: Index::Num(0x1f), offset },
                 init,
                 ..
             } if matches!(offset[0].kind, I32Const(10)) && init.is_empty()
@@ -4554,17 +5844,48 @@ mod tests {
             r#"(elem $e i32.const 10)"#,
             Elem<'_>,
             Elem {
-                idx: Index::Ident("$e"),
-                offset,
+                id: Some("$e"),
+                mode: ElemMode::Active { idx: Index::Num(0), offset },
                 init,
                 ..
             } if matches!(offset[0].kind, I32Const(10)) && init.is_empty()
         );
+        assert_parse!(
+            r#"(elem $e (table $t) (offset i32.const 10) func $f)"#,
+            Elem<'_>,
+            Elem {
+                id: Some("$e"),
+                mode: ElemMode::Active { idx: Index::Ident("$t"), offset },
+                init,
+                ..
+            } if matches!(offset[0].kind, I32Const(10)) &&
+                 matches!(func_indices(&init).as_slice(), [Index::Ident("$f")])
+        );
+        assert_parse!(
+            r#"(elem $e func $f 0)"#,
+            Elem<'_>,
+            Elem {
+                id: Some("$e"),
+                mode: ElemMode::Passive,
+                init,
+                ..
+            } if matches!(func_indices(&init).as_slice(), [Index::Ident("$f"), Index::Num(0)])
+        );
+        assert_parse!(
+            r#"(elem func)"#,
+            Elem<'_>,
+            Elem {
+                id: None,
+                mode: ElemMode::Passive,
+                init,
+                ..
+            } if init.is_empty()
+        );
         assert_parse!(
             r#"(elem (offset))"#,
             Elem<'_>,
             Elem {
-                offset,
+                mode: ElemMode::Active { offset, .. },
                 init,
                 ..
             } if offset.is_empty() && init.is_empty()
@@ -4573,7 +5894,7 @@ mod tests {
             r#"(elem (offset nop))"#,
             Elem<'_>,
             Elem {
-                offset,
+                mode: ElemMode::Active { offset, .. },
                 init,
                 ..
             } if matches!(offset[0].kind, Nop) && init.is_empty()
@@ -4582,7 +5903,7 @@ mod tests {
             r#"(elem (offset (nop (nop))))"#,
             Elem<'_>,
             Elem {
-                offset,
+                mode: ElemMode::Active { offset, .. },
                 init,
                 ..
             } if offset.len() == 2 && init.is_empty()
@@ -4591,7 +5912,7 @@ mod tests {
             r#"(elem (offset nop nop))"#,
             Elem<'_>,
             Elem {
-                offset,
+                mode: ElemMode::Active { offset, .. },
                 init,
                 ..
             } if offset.len() == 2 && init.is_empty()
@@ -4600,47 +5921,79 @@ mod tests {
             r#"(elem (offset nop) 0xf $f)"#,
             Elem<'_>,
             Elem {
-                offset,
+                mode: ElemMode::Active { offset, .. },
                 init,
                 ..
             } if offset.len() == 1 &&
-                 matches!(init.as_slice(), [Index::Num(0xf), Index::Ident("$f")])
+                 matches!(func_indices(&init).as_slice(), [Index::Num(0xf), Index::Ident("$f")])
         );
         assert_parse!(
             r#"(elem nop 0xf)"#,
             Elem<'_>,
             Elem {
-                offset,
+                mode: ElemMode::Active { offset, .. },
                 init,
                 ..
             } if matches!(offset[0].kind, Nop) &&
-                 matches!(init.as_slice(), [Index::Num(0xf)])
+                 matches!(func_indices(&init).as_slice(), [Index::Num(0xf)])
         );
         assert_parse!(
             r#"(elem nop $f)"#,
             Elem<'_>,
             Elem {
-                offset,
+                mode: ElemMode::Active { offset, .. },
                 init,
                 ..
             } if matches!(offset[0].kind, Nop) &&
-                 matches!(init.as_slice(), [Index::Ident("$f")])
+                 matches!(func_indices(&init).as_slice(), [Index::Ident("$f")])
         );
         assert_parse!(
             r#"(elem block end 0)"#,
             Elem<'_>,
-            Elem { offset, .. } if matches!(offset[0].kind, Block{..})
+            Elem { mode: ElemMode::Active { offset, .. }, .. } if matches!(offset[0].kind, Block{..})
         );
         assert_parse!(
             r#"(elem (i32.const 42) 0)"#,
             Elem<'_>,
-            Elem { offset, .. } if matches!(offset[0].kind, I32Const(42))
+            Elem { mode: ElemMode::Active { offset, .. }, .. } if matches!(offset[0].kind, I32Const(42))
         );
         assert_parse!(
             r#"(elem i32.const 0 func $f)"#,
             Elem<'_>,
             Elem { ..  }
         );
+        assert_parse!(
+            r#"(elem declare func $f)"#,
+            Elem<'_>,
+            Elem {
+                ty: RefType::FuncRef,
+                mode: ElemMode::Declarative,
+                init,
+                ..
+            } if matches!(func_indices(&init).as_slice(), [Index::Ident("$f")])
+        );
+        assert_parse!(
+            r#"(elem externref (ref.null extern) (item ref.null extern))"#,
+            Elem<'_>,
+            Elem {
+                ty: RefType::ExternRef,
+                mode: ElemMode::Passive,
+                init,
+                ..
+            } if init.len() == 2 &&
+                 init.iter().all(|e| matches!(e[0].kind, RefNull(RefType::ExternRef)))
+        );
+        assert_parse!(
+            r#"(elem (table 1) (i32.const 0) funcref (ref.func 0) (ref.null func))"#,
+            Elem<'_>,
+            Elem {
+                ty: RefType::FuncRef,
+                mode: ElemMode::Active { idx: Index::Num(1), .. },
+                init,
+                ..
+            } if matches!(init[0][0].kind, RefFunc(Index::Num(0))) &&
+                 matches!(init[1][0].kind, RefNull(RefType::FuncRef))
+        );
     }
 
     #[test]
@@ -4661,11 +6014,20 @@ mod tests {
             r#"(table $tbl funcref (elem 0 1))"#,
             TableAbbrev<'_>,
             TableAbbrev::Elem(
-                Table{ id: Some("$tbl"), ty: TableType{ limit: Limits::Range{ min: 2, max: 2 } }, .. },
-                Elem{ idx: Index::Num(0), offset, init, .. }
+                Table{ id: Some("$tbl"), ty: TableType{ limit: Limits::Range{ min: 2, max: 2 }, elem: RefType::FuncRef }, .. },
+                Elem{ mode: ElemMode::Active { idx: Index::Num(0), offset }, init, .. }
             )
             if matches!(offset[0].kind, InsnKind::I32Const(0)) &&
-               matches!(init[0], Index::Num(0)) && matches!(init[1], Index::Num(1))
+               matches!(func_indices(&init).as_slice(), [Index::Num(0), Index::Num(1)])
+        );
+        assert_parse!(
+            r#"(table externref (elem (ref.null extern)))"#,
+            TableAbbrev<'_>,
+            TableAbbrev::Elem(
+                Table{ ty: TableType{ limit: Limits::Range{ min: 1, max: 1 }, elem: RefType::ExternRef }, .. },
+                Elem{ ty: RefType::ExternRef, init, .. }
+            )
+            if matches!(init[0][0].kind, InsnKind::RefNull(RefType::ExternRef))
         );
         assert_parse!(
             r#"(table $tbl (import "m" "n") 2 2 funcref)"#,
@@ -4674,6 +6036,7 @@ mod tests {
                 id: Some("$tbl"),
                 ty: TableType {
                     limit: Limits::Range{ min: 2, max: 2 },
+                    elem: RefType::FuncRef,
                 },
                 import: Some(Import {
                     mod_name: Name(m),
@@ -4691,6 +6054,7 @@ mod tests {
                 ty:
                     TableType {
                         limit: Limits::From { min: 2 },
+                        elem: RefType::FuncRef,
                     },
                 ..
             })
@@ -4712,6 +6076,7 @@ mod tests {
                 ty:
                     TableType {
                         limit: Limits::From { min: 2 },
+                        elem: RefType::FuncRef,
                     },
                 ..
             })
@@ -4724,6 +6089,7 @@ mod tests {
                 id: Some("$tbl"),
                 ty: TableType {
                     limit: Limits::From{ min: 2 },
+                    elem: RefType::FuncRef,
                 },
                 import: Some(Import {
                     mod_name: Name(m),
@@ -4776,8 +6142,7 @@ mod tests {
             r#"(data 0 i32.const 0)"#,
             Data<'_>,
             Data{
-                idx: Index::Num(0),
-                offset,
+                mode: DataMode::Active { idx: Index::Num(0), offset },
                 data,
                 ..
             } if matches!(offset[0].kind, InsnKind::I32Const(0)) && data.is_empty()
@@ -4786,8 +6151,7 @@ mod tests {
             r#"(data 0 (offset i32.const 0))"#,
             Data<'_>,
             Data{
-                idx: Index::Num(0),
-                offset,
+                mode: DataMode::Active { idx: Index::Num(0), offset },
                 data,
                 ..
             } if matches!(offset[0].kind, InsnKind::I32Const(0)) && data.is_empty()
@@ -4816,14 +6180,40 @@ mod tests {
             r#"(data (i32.const 1024) "Hello, world\n\00")"#,
             Data<'_>,
             Data{
-                idx: Index::Num(0),
-                offset,
+                mode: DataMode::Active { idx: Index::Num(0), offset },
                 data,
                 ..
             } if matches!(offset[0].kind, InsnKind::I32Const(1024)) &&
                  data.as_ref() == b"Hello, world\n\0".as_ref()
         );
 
+        assert_parse!(
+            r#"(data $d (memory 0) (offset i32.const 0) "hello")"#,
+            Data<'_>,
+            Data{
+                id: Some("$d"),
+                mode: DataMode::Active { idx: Index::Num(0), offset },
+                data,
+                ..
+            } if matches!(offset[0].kind, InsnKind::I32Const(0)) &&
+                 data.as_ref() == b"hello".as_ref()
+        );
+        assert_parse!(
+            r#"(data $d "hello" " dogs!")"#,
+            Data<'_>,
+            Data{
+                id: Some("$d"),
+                mode: DataMode::Passive,
+                data,
+                ..
+            } if data.as_ref() == b"hello dogs!".as_ref()
+        );
+        assert_parse!(
+            r#"(data)"#,
+            Data<'_>,
+            Data{ id: None, mode: DataMode::Passive, data, .. } if data.is_empty()
+        );
+
         assert_error!(
             r#"(data 0 "hello")"#,
             Data<'_>,
@@ -4840,6 +6230,8 @@ mod tests {
                 id: None,
                 ty: MemType {
                     limit: Limits::From { min: 3 },
+                    shared: false,
+                    memory64: false,
                 },
                 ..
             })
@@ -4852,6 +6244,8 @@ mod tests {
                 ty:
                     MemType {
                         limit: Limits::Range { min: 1, max: 3 },
+                        shared: false,
+                        memory64: false,
                     },
                 ..
             })
@@ -4864,6 +6258,8 @@ mod tests {
                 ty:
                     MemType {
                         limit: Limits::Range { min: 1, max: 3 },
+                        shared: false,
+                        memory64: false,
                     },
                 ..
             })
@@ -4873,12 +6269,11 @@ mod tests {
             MemoryAbbrev<'_>,
             MemoryAbbrev::Data(
                 Memory{
-                    ty: MemType{ limit: Limits::Range{ min: 1, max: 1 } },
+                    ty: MemType{ limit: Limits::Range{ min: 1, max: 1 }, shared: false, memory64: false },
                     ..
                 },
                 Data {
-                    idx: Index::Num(0),
-                    offset,
+                    mode: DataMode::Active { idx: Index::Num(0), offset },
                     data,
                     ..
                 },
@@ -4886,6 +6281,33 @@ mod tests {
             if matches!(offset[0].kind, InsnKind::I32Const(0)) &&
                data.as_ref() == b"foobar".as_ref()
         );
+        assert_parse!(
+            r#"(memory i64 (data "foo"))"#,
+            MemoryAbbrev<'_>,
+            MemoryAbbrev::Data(
+                Memory{
+                    ty: MemType{ limit: Limits::Range{ min: 1, max: 1 }, shared: false, memory64: true },
+                    ..
+                },
+                Data {
+                    mode: DataMode::Active { idx: Index::Num(0), offset },
+                    ..
+                },
+            )
+            if matches!(offset[0].kind, InsnKind::I64Const(0))
+        );
+        assert_parse!(
+            r#"(memory i64 2)"#,
+            MemoryAbbrev<'_>,
+            MemoryAbbrev::Memory(Memory {
+                ty: MemType {
+                    limit: Limits::From { min: 2 },
+                    memory64: true,
+                    ..
+                },
+                ..
+            })
+        );
         assert_parse!(
             r#"(memory $m (import "m" "n") 2)"#,
             MemoryAbbrev<'_>,
@@ -4893,6 +6315,8 @@ mod tests {
                 id: Some("$m"),
                 ty: MemType {
                     limit: Limits::From{ min: 2 },
+                    shared: false,
+                    memory64: false,
                 },
                 import: Some(Import {
                     mod_name: Name(m),
@@ -4908,6 +6332,8 @@ mod tests {
             MemoryAbbrev::Memory(Memory {
                 ty: MemType {
                     limit: Limits::From { min: 0 },
+                    shared: false,
+                    memory64: false,
                 },
                 ..
             })
@@ -4927,6 +6353,8 @@ mod tests {
             MemoryAbbrev::Memory(Memory {
                 ty: MemType {
                     limit: Limits::From { min: 0 },
+                    shared: false,
+                    memory64: false,
                 },
                 ..
             })
@@ -4940,6 +6368,8 @@ mod tests {
                 id: Some("$m"),
                 ty: MemType {
                     limit: Limits::From{ min: 2 },
+                    shared: false,
+                    memory64: false,
                 },
                 import: Some(Import {
                     mod_name: Name(m),
@@ -5102,6 +6532,76 @@ mod tests {
         );
     }
 
+    #[test]
+    fn tag_field() {
+        assert_parse!(
+            r#"(tag $e (param i32 f64))"#,
+            Tag<'_>,
+            Tag {
+                id: Some("$e"),
+                ty: TypeUse { params, .. },
+                import: None,
+                ..
+            } if params.len() == 2
+        );
+        assert_parse!(
+            r#"(tag (type 0))"#,
+            Tag<'_>,
+            Tag {
+                id: None,
+                ty: TypeUse { idx: Index::Num(0), .. },
+                ..
+            }
+        );
+        let parser = assert_parse!(
+            r#"(tag $e (export "e1") (export "e2") (import "m" "n") (param i32))"#,
+            Tag<'_>,
+            Tag {
+                id: Some("$e"),
+                import: Some(Import {
+                    mod_name: Name(m),
+                    name: Name(n),
+                }),
+                ..
+            } if m == "m" && n == "n"
+        );
+        assert_eq!(parser.ctx.exports.len(), 2);
+        assert!(matches!(
+            &parser.ctx.exports[0],
+            Export {
+                kind: ExportKind::Tag,
+                idx: Index::Num(0),
+                ..
+            }
+        ));
+        assert_parse!(
+            r#"(import "m" "n" (tag $e (param i32)))"#,
+            ImportItem<'_>,
+            ImportItem::Tag(Tag {
+                id: Some("$e"),
+                import: Some(_),
+                ..
+            })
+        );
+        assert_parse!(
+            r#"(export "e" (tag $e))"#,
+            Export<'_>,
+            Export{ kind: ExportKind::Tag, idx: Index::Ident("$e"), .. }
+        );
+        assert_parse!(
+            r#"(module (import "m" "n" (tag)) (tag $e) (func throw $e))"#,
+            Module<'_>,
+            Module { tags, .. } if tags.len() == 2
+        );
+
+        assert_error!(
+            r#"(module (tag) (import "m" "n" (tag)))"#,
+            Module<'_>,
+            ImportMustPrecedeOtherDefs { what: "tag" }
+        );
+        assert_error!(r#"(tag $e (import "m" "n") (export "e"))"#, Tag<'_>, MissingParen { .. });
+    }
+
     #[test]
     fn start_function() {
         assert_parse!(r#"(start 3)"#, Start<'_>, Start{ idx: Index::Num(3), .. });
@@ -166,6 +166,9 @@ struct Context<'s> {
     table_indices: Indices<'s>,
     mem_indices: Indices<'s>,
     global_indices: Indices<'s>,
+    elem_indices: Indices<'s>,
+    data_indices: Indices<'s>,
+    tag_indices: Indices<'s>,
     local_indices: Indices<'s>,
     next_local_idx: u32,
     label_stack: LabelStack<'s>,
@@ -211,10 +214,30 @@ impl<'s> Context<'s> {
         self.resolve_index(&self.mem_indices, idx, offset, "memory")
     }
 
+    fn memarg(&self, mem: wat::Mem<'s>, offset: usize) -> Result<'s, wasm::Mem> {
+        Ok(wasm::Mem {
+            align: mem.align,
+            offset: mem.offset,
+            memory: self.resolve_mem_idx(mem.memory, offset)?,
+        })
+    }
+
     fn resolve_global_idx(&self, idx: wat::Index<'s>, offset: usize) -> Result<'s, u32> {
         self.resolve_index(&self.global_indices, idx, offset, "global")
     }
 
+    fn resolve_elem_idx(&self, idx: wat::Index<'s>, offset: usize) -> Result<'s, u32> {
+        self.resolve_index(&self.elem_indices, idx, offset, "elem segment")
+    }
+
+    fn resolve_data_idx(&self, idx: wat::Index<'s>, offset: usize) -> Result<'s, u32> {
+        self.resolve_index(&self.data_indices, idx, offset, "data segment")
+    }
+
+    fn resolve_tag_idx(&self, idx: wat::Index<'s>, offset: usize) -> Result<'s, u32> {
+        self.resolve_index(&self.tag_indices, idx, offset, "tag")
+    }
+
     fn start_func_scope(&mut self) {
         self.next_local_idx = 0;
         self.local_indices.clear();
@@ -255,6 +278,9 @@ pub fn wat2wasm<'s>(
         table_indices: parsed.table_indices,
         mem_indices: parsed.mem_indices,
         global_indices: parsed.global_indices,
+        elem_indices: parsed.elem_indices,
+        data_indices: parsed.data_indices,
+        tag_indices: parsed.tag_indices,
         local_indices: Indices::new(),
         next_local_idx: 0,
         label_stack: LabelStack::new(source),
@@ -299,6 +325,7 @@ impl<'s> Transform<'s> for wat::Module<'s> {
             data: self.data.transform(ctx)?,
             memories: self.memories.transform(ctx)?,
             globals: self.globals.transform(ctx)?,
+            tags: self.tags.transform(ctx)?,
             entrypoint: self.entrypoint.transform(ctx)?,
         })
     }
@@ -312,6 +339,32 @@ impl<'s> Transform<'s> for wat::ValType {
             wat::ValType::I64 => wasm::ValType::I64,
             wat::ValType::F32 => wasm::ValType::F32,
             wat::ValType::F64 => wasm::ValType::F64,
+            wat::ValType::V128 => wasm::ValType::V128,
+            wat::ValType::FuncRef => wasm::ValType::FuncRef,
+            wat::ValType::ExternRef => wasm::ValType::ExternRef,
+        })
+    }
+}
+
+impl<'s> Transform<'s> for wat::RefType {
+    type Target = wasm::RefType;
+    fn transform(self, _ctx: &mut Context<'s>) -> Result<'s, Self::Target> {
+        Ok(match self {
+            wat::RefType::FuncRef => wasm::RefType::FuncRef,
+            wat::RefType::ExternRef => wasm::RefType::ExternRef,
+        })
+    }
+}
+
+impl<'s> Transform<'s> for wat::BlockType<'s> {
+    type Target = wasm::BlockType;
+    fn transform(self, ctx: &mut Context<'s>) -> Result<'s, Self::Target> {
+        Ok(match self {
+            wat::BlockType::Empty => wasm::BlockType::Empty,
+            wat::BlockType::Value(ty) => wasm::BlockType::Value(ty.transform(ctx)?),
+            wat::BlockType::TypeUse(ty) => {
+                wasm::BlockType::Type(ctx.resolve_type_idx(ty.idx, ty.start)?)
+            }
         })
     }
 }
@@ -368,6 +421,10 @@ impl<'s> Transform<'s> for wat::Export<'s> {
                     let idx = ctx.resolve_global_idx(self.idx, start)?;
                     wasm::ExportKind::Global(idx)
                 }
+                wat::ExportKind::Tag => {
+                    let idx = ctx.resolve_tag_idx(self.idx, start)?;
+                    wasm::ExportKind::Tag(idx)
+                }
             },
         })
     }
@@ -383,16 +440,6 @@ impl<'s> Transform<'s> for wat::Import {
     }
 }
 
-impl<'s> Transform<'s> for wat::Mem {
-    type Target = wasm::Mem;
-    fn transform(self, _ctx: &mut Context<'s>) -> Result<'s, Self::Target> {
-        Ok(wasm::Mem {
-            align: self.align,
-            offset: self.offset,
-        })
-    }
-}
-
 impl<'s> Transform<'s> for wat::Instruction<'s> {
     type Target = wasm::Instruction;
     fn transform(self, ctx: &mut Context<'s>) -> Result<'s, Self::Target> {
@@ -462,12 +509,61 @@ impl<'s> Transform<'s> for wat::Instruction<'s> {
             },
             wat::InsnKind::Return => wasm::InsnKind::Return,
             wat::InsnKind::Call(idx) => wasm::InsnKind::Call(ctx.resolve_func_idx(idx, start)?),
-            wat::InsnKind::CallIndirect(ty) => {
-                wasm::InsnKind::CallIndirect(ctx.resolve_type_idx(ty.idx, start)?)
+            wat::InsnKind::CallIndirect { table, ty } => wasm::InsnKind::CallIndirect {
+                table: ctx.resolve_table_idx(table, start)?,
+                ty: ctx.resolve_type_idx(ty.idx, start)?,
+            },
+            wat::InsnKind::ReturnCall(idx) => {
+                wasm::InsnKind::ReturnCall(ctx.resolve_func_idx(idx, start)?)
+            }
+            wat::InsnKind::ReturnCallIndirect { table, ty } => wasm::InsnKind::ReturnCallIndirect {
+                table: ctx.resolve_table_idx(table, start)?,
+                ty: ctx.resolve_type_idx(ty.idx, start)?,
+            },
+            // Exception handling
+            wat::InsnKind::Try {
+                label,
+                ty,
+                body,
+                handler,
+                id,
+            } => {
+                // Handler clauses of 'try' are in the same label as its body. Label of 'delegate'
+                // is resolved outside the 'try' block
+                ctx.label_stack.push(label, id, start)?;
+                let body = body.transform(ctx)?;
+                let handler = match handler {
+                    wat::TryHandler::Catch { catches, catch_all } => {
+                        let catches = catches.transform(ctx)?;
+                        let catch_all = catch_all.transform(ctx)?;
+                        ctx.label_stack.pop();
+                        wasm::TryHandler::Catch { catches, catch_all }
+                    }
+                    wat::TryHandler::Delegate(idx) => {
+                        ctx.label_stack.pop();
+                        wasm::TryHandler::Delegate(ctx.label_stack.resolve(idx, start)?)
+                    }
+                };
+                wasm::InsnKind::Try {
+                    ty: ty.transform(ctx)?,
+                    body,
+                    handler,
+                }
+            }
+            wat::InsnKind::Throw(idx) => wasm::InsnKind::Throw(ctx.resolve_tag_idx(idx, start)?),
+            wat::InsnKind::Rethrow(idx) => {
+                wasm::InsnKind::Rethrow(ctx.label_stack.resolve(idx, start)?)
+            }
+            // Reference instructions
+            wat::InsnKind::RefNull(ty) => wasm::InsnKind::RefNull(ty.transform(ctx)?),
+            wat::InsnKind::RefIsNull => wasm::InsnKind::RefIsNull,
+            wat::InsnKind::RefFunc(idx) => {
+                wasm::InsnKind::RefFunc(ctx.resolve_func_idx(idx, start)?)
             }
             // Parametric instructions
             wat::InsnKind::Drop => wasm::InsnKind::Drop,
             wat::InsnKind::Select => wasm::InsnKind::Select,
+            wat::InsnKind::TypedSelect(tys) => wasm::InsnKind::TypedSelect(tys.transform(ctx)?),
             // Variable instructions
             wat::InsnKind::LocalGet(idx) => {
                 wasm::InsnKind::LocalGet(ctx.resolve_local_idx(idx, start)?)
@@ -485,31 +581,75 @@ impl<'s> Transform<'s> for wat::Instruction<'s> {
                 wasm::InsnKind::GlobalSet(ctx.resolve_global_idx(idx, start)?)
             }
             // Memory instructions
-            wat::InsnKind::I32Load(mem) => wasm::InsnKind::I32Load(mem.transform(ctx)?),
-            wat::InsnKind::I64Load(mem) => wasm::InsnKind::I64Load(mem.transform(ctx)?),
-            wat::InsnKind::F32Load(mem) => wasm::InsnKind::F32Load(mem.transform(ctx)?),
-            wat::InsnKind::F64Load(mem) => wasm::InsnKind::F64Load(mem.transform(ctx)?),
-            wat::InsnKind::I32Load8S(mem) => wasm::InsnKind::I32Load8S(mem.transform(ctx)?),
-            wat::InsnKind::I32Load8U(mem) => wasm::InsnKind::I32Load8U(mem.transform(ctx)?),
-            wat::InsnKind::I32Load16S(mem) => wasm::InsnKind::I32Load16S(mem.transform(ctx)?),
-            wat::InsnKind::I32Load16U(mem) => wasm::InsnKind::I32Load16U(mem.transform(ctx)?),
-            wat::InsnKind::I64Load8S(mem) => wasm::InsnKind::I64Load8S(mem.transform(ctx)?),
-            wat::InsnKind::I64Load8U(mem) => wasm::InsnKind::I64Load8U(mem.transform(ctx)?),
-            wat::InsnKind::I64Load16S(mem) => wasm::InsnKind::I64Load16S(mem.transform(ctx)?),
-            wat::InsnKind::I64Load16U(mem) => wasm::InsnKind::I64Load16U(mem.transform(ctx)?),
-            wat::InsnKind::I64Load32S(mem) => wasm::InsnKind::I64Load32S(mem.transform(ctx)?),
-            wat::InsnKind::I64Load32U(mem) => wasm::InsnKind::I64Load32U(mem.transform(ctx)?),
-            wat::InsnKind::I32Store(mem) => wasm::InsnKind::I32Store(mem.transform(ctx)?),
-            wat::InsnKind::I64Store(mem) => wasm::InsnKind::I64Store(mem.transform(ctx)?),
-            wat::InsnKind::F32Store(mem) => wasm::InsnKind::F32Store(mem.transform(ctx)?),
-            wat::InsnKind::F64Store(mem) => wasm::InsnKind::F64Store(mem.transform(ctx)?),
-            wat::InsnKind::I32Store8(mem) => wasm::InsnKind::I32Store8(mem.transform(ctx)?),
-            wat::InsnKind::I32Store16(mem) => wasm::InsnKind::I32Store16(mem.transform(ctx)?),
-            wat::InsnKind::I64Store8(mem) => wasm::InsnKind::I64Store8(mem.transform(ctx)?),
-            wat::InsnKind::I64Store16(mem) => wasm::InsnKind::I64Store16(mem.transform(ctx)?),
-            wat::InsnKind::I64Store32(mem) => wasm::InsnKind::I64Store32(mem.transform(ctx)?),
-            wat::InsnKind::MemorySize => wasm::InsnKind::MemorySize,
-            wat::InsnKind::MemoryGrow => wasm::InsnKind::MemoryGrow,
+            wat::InsnKind::I32Load(mem) => wasm::InsnKind::I32Load(ctx.memarg(mem, start)?),
+            wat::InsnKind::I64Load(mem) => wasm::InsnKind::I64Load(ctx.memarg(mem, start)?),
+            wat::InsnKind::F32Load(mem) => wasm::InsnKind::F32Load(ctx.memarg(mem, start)?),
+            wat::InsnKind::F64Load(mem) => wasm::InsnKind::F64Load(ctx.memarg(mem, start)?),
+            wat::InsnKind::I32Load8S(mem) => wasm::InsnKind::I32Load8S(ctx.memarg(mem, start)?),
+            wat::InsnKind::I32Load8U(mem) => wasm::InsnKind::I32Load8U(ctx.memarg(mem, start)?),
+            wat::InsnKind::I32Load16S(mem) => wasm::InsnKind::I32Load16S(ctx.memarg(mem, start)?),
+            wat::InsnKind::I32Load16U(mem) => wasm::InsnKind::I32Load16U(ctx.memarg(mem, start)?),
+            wat::InsnKind::I64Load8S(mem) => wasm::InsnKind::I64Load8S(ctx.memarg(mem, start)?),
+            wat::InsnKind::I64Load8U(mem) => wasm::InsnKind::I64Load8U(ctx.memarg(mem, start)?),
+            wat::InsnKind::I64Load16S(mem) => wasm::InsnKind::I64Load16S(ctx.memarg(mem, start)?),
+            wat::InsnKind::I64Load16U(mem) => wasm::InsnKind::I64Load16U(ctx.memarg(mem, start)?),
+            wat::InsnKind::I64Load32S(mem) => wasm::InsnKind::I64Load32S(ctx.memarg(mem, start)?),
+            wat::InsnKind::I64Load32U(mem) => wasm::InsnKind::I64Load32U(ctx.memarg(mem, start)?),
+            wat::InsnKind::I32Store(mem) => wasm::InsnKind::I32Store(ctx.memarg(mem, start)?),
+            wat::InsnKind::I64Store(mem) => wasm::InsnKind::I64Store(ctx.memarg(mem, start)?),
+            wat::InsnKind::F32Store(mem) => wasm::InsnKind::F32Store(ctx.memarg(mem, start)?),
+            wat::InsnKind::F64Store(mem) => wasm::InsnKind::F64Store(ctx.memarg(mem, start)?),
+            wat::InsnKind::I32Store8(mem) => wasm::InsnKind::I32Store8(ctx.memarg(mem, start)?),
+            wat::InsnKind::I32Store16(mem) => wasm::InsnKind::I32Store16(ctx.memarg(mem, start)?),
+            wat::InsnKind::I64Store8(mem) => wasm::InsnKind::I64Store8(ctx.memarg(mem, start)?),
+            wat::InsnKind::I64Store16(mem) => wasm::InsnKind::I64Store16(ctx.memarg(mem, start)?),
+            wat::InsnKind::I64Store32(mem) => wasm::InsnKind::I64Store32(ctx.memarg(mem, start)?),
+            wat::InsnKind::MemorySize(idx) => {
+                wasm::InsnKind::MemorySize(ctx.resolve_mem_idx(idx, start)?)
+            }
+            wat::InsnKind::MemoryGrow(idx) => {
+                wasm::InsnKind::MemoryGrow(ctx.resolve_mem_idx(idx, start)?)
+            }
+            wat::InsnKind::MemoryInit { memory, data } => wasm::InsnKind::MemoryInit {
+                memory: ctx.resolve_mem_idx(memory, start)?,
+                data: ctx.resolve_data_idx(data, start)?,
+            },
+            wat::InsnKind::DataDrop(idx) => {
+                wasm::InsnKind::DataDrop(ctx.resolve_data_idx(idx, start)?)
+            }
+            wat::InsnKind::MemoryCopy { dst, src } => wasm::InsnKind::MemoryCopy {
+                dst: ctx.resolve_mem_idx(dst, start)?,
+                src: ctx.resolve_mem_idx(src, start)?,
+            },
+            wat::InsnKind::MemoryFill(idx) => {
+                wasm::InsnKind::MemoryFill(ctx.resolve_mem_idx(idx, start)?)
+            }
+            wat::InsnKind::TableGet(idx) => {
+                wasm::InsnKind::TableGet(ctx.resolve_table_idx(idx, start)?)
+            }
+            wat::InsnKind::TableSet(idx) => {
+                wasm::InsnKind::TableSet(ctx.resolve_table_idx(idx, start)?)
+            }
+            wat::InsnKind::TableSize(idx) => {
+                wasm::InsnKind::TableSize(ctx.resolve_table_idx(idx, start)?)
+            }
+            wat::InsnKind::TableGrow(idx) => {
+                wasm::InsnKind::TableGrow(ctx.resolve_table_idx(idx, start)?)
+            }
+            wat::InsnKind::TableFill(idx) => {
+                wasm::InsnKind::TableFill(ctx.resolve_table_idx(idx, start)?)
+            }
+            wat::InsnKind::TableCopy { dst, src } => wasm::InsnKind::TableCopy {
+                dst: ctx.resolve_table_idx(dst, start)?,
+                src: ctx.resolve_table_idx(src, start)?,
+            },
+            wat::InsnKind::TableInit { table, elem } => wasm::InsnKind::TableInit {
+                table: ctx.resolve_table_idx(table, start)?,
+                elem: ctx.resolve_elem_idx(elem, start)?,
+            },
+            wat::InsnKind::ElemDrop(idx) => {
+                wasm::InsnKind::ElemDrop(ctx.resolve_elem_idx(idx, start)?)
+            }
             // Numeric instructions
             // https://webassembly.github.io/spec/core/text/instructions.html#numeric-instructions
             // Constants
@@ -649,6 +789,503 @@ impl<'s> Transform<'s> for wat::Instruction<'s> {
             wat::InsnKind::I64ReinterpretF64 => wasm::InsnKind::I64ReinterpretF64,
             wat::InsnKind::F32ReinterpretI32 => wasm::InsnKind::F32ReinterpretI32,
             wat::InsnKind::F64ReinterpretI64 => wasm::InsnKind::F64ReinterpretI64,
+            wat::InsnKind::I32Extend8S => wasm::InsnKind::I32Extend8S,
+            wat::InsnKind::I32Extend16S => wasm::InsnKind::I32Extend16S,
+            wat::InsnKind::I64Extend8S => wasm::InsnKind::I64Extend8S,
+            wat::InsnKind::I64Extend16S => wasm::InsnKind::I64Extend16S,
+            wat::InsnKind::I64Extend32S => wasm::InsnKind::I64Extend32S,
+            wat::InsnKind::I32TruncSatF32S => wasm::InsnKind::I32TruncSatF32S,
+            wat::InsnKind::I32TruncSatF32U => wasm::InsnKind::I32TruncSatF32U,
+            wat::InsnKind::I32TruncSatF64S => wasm::InsnKind::I32TruncSatF64S,
+            wat::InsnKind::I32TruncSatF64U => wasm::InsnKind::I32TruncSatF64U,
+            wat::InsnKind::I64TruncSatF32S => wasm::InsnKind::I64TruncSatF32S,
+            wat::InsnKind::I64TruncSatF32U => wasm::InsnKind::I64TruncSatF32U,
+            wat::InsnKind::I64TruncSatF64S => wasm::InsnKind::I64TruncSatF64S,
+            wat::InsnKind::I64TruncSatF64U => wasm::InsnKind::I64TruncSatF64U,
+            // Vector instructions
+            wat::InsnKind::V128Load(mem) => wasm::InsnKind::V128Load(ctx.memarg(mem, start)?),
+            wat::InsnKind::V128Load8x8S(mem) => {
+                wasm::InsnKind::V128Load8x8S(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::V128Load8x8U(mem) => {
+                wasm::InsnKind::V128Load8x8U(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::V128Load16x4S(mem) => {
+                wasm::InsnKind::V128Load16x4S(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::V128Load16x4U(mem) => {
+                wasm::InsnKind::V128Load16x4U(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::V128Load32x2S(mem) => {
+                wasm::InsnKind::V128Load32x2S(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::V128Load32x2U(mem) => {
+                wasm::InsnKind::V128Load32x2U(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::V128Load8Splat(mem) => {
+                wasm::InsnKind::V128Load8Splat(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::V128Load16Splat(mem) => {
+                wasm::InsnKind::V128Load16Splat(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::V128Load32Splat(mem) => {
+                wasm::InsnKind::V128Load32Splat(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::V128Load64Splat(mem) => {
+                wasm::InsnKind::V128Load64Splat(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::V128Store(mem) => wasm::InsnKind::V128Store(ctx.memarg(mem, start)?),
+            wat::InsnKind::V128Const(val) => wasm::InsnKind::V128Const(val),
+            wat::InsnKind::I8x16Shuffle(lanes) => wasm::InsnKind::I8x16Shuffle(lanes),
+            wat::InsnKind::I8x16Swizzle => wasm::InsnKind::I8x16Swizzle,
+            wat::InsnKind::I8x16Splat => wasm::InsnKind::I8x16Splat,
+            wat::InsnKind::I16x8Splat => wasm::InsnKind::I16x8Splat,
+            wat::InsnKind::I32x4Splat => wasm::InsnKind::I32x4Splat,
+            wat::InsnKind::I64x2Splat => wasm::InsnKind::I64x2Splat,
+            wat::InsnKind::F32x4Splat => wasm::InsnKind::F32x4Splat,
+            wat::InsnKind::F64x2Splat => wasm::InsnKind::F64x2Splat,
+            wat::InsnKind::I8x16ExtractLaneS(lane) => wasm::InsnKind::I8x16ExtractLaneS(lane),
+            wat::InsnKind::I8x16ExtractLaneU(lane) => wasm::InsnKind::I8x16ExtractLaneU(lane),
+            wat::InsnKind::I8x16ReplaceLane(lane) => wasm::InsnKind::I8x16ReplaceLane(lane),
+            wat::InsnKind::I16x8ExtractLaneS(lane) => wasm::InsnKind::I16x8ExtractLaneS(lane),
+            wat::InsnKind::I16x8ExtractLaneU(lane) => wasm::InsnKind::I16x8ExtractLaneU(lane),
+            wat::InsnKind::I16x8ReplaceLane(lane) => wasm::InsnKind::I16x8ReplaceLane(lane),
+            wat::InsnKind::I32x4ExtractLane(lane) => wasm::InsnKind::I32x4ExtractLane(lane),
+            wat::InsnKind::I32x4ReplaceLane(lane) => wasm::InsnKind::I32x4ReplaceLane(lane),
+            wat::InsnKind::I64x2ExtractLane(lane) => wasm::InsnKind::I64x2ExtractLane(lane),
+            wat::InsnKind::I64x2ReplaceLane(lane) => wasm::InsnKind::I64x2ReplaceLane(lane),
+            wat::InsnKind::F32x4ExtractLane(lane) => wasm::InsnKind::F32x4ExtractLane(lane),
+            wat::InsnKind::F32x4ReplaceLane(lane) => wasm::InsnKind::F32x4ReplaceLane(lane),
+            wat::InsnKind::F64x2ExtractLane(lane) => wasm::InsnKind::F64x2ExtractLane(lane),
+            wat::InsnKind::F64x2ReplaceLane(lane) => wasm::InsnKind::F64x2ReplaceLane(lane),
+            wat::InsnKind::I8x16Eq => wasm::InsnKind::I8x16Eq,
+            wat::InsnKind::I8x16Ne => wasm::InsnKind::I8x16Ne,
+            wat::InsnKind::I8x16LtS => wasm::InsnKind::I8x16LtS,
+            wat::InsnKind::I8x16LtU => wasm::InsnKind::I8x16LtU,
+            wat::InsnKind::I8x16GtS => wasm::InsnKind::I8x16GtS,
+            wat::InsnKind::I8x16GtU => wasm::InsnKind::I8x16GtU,
+            wat::InsnKind::I8x16LeS => wasm::InsnKind::I8x16LeS,
+            wat::InsnKind::I8x16LeU => wasm::InsnKind::I8x16LeU,
+            wat::InsnKind::I8x16GeS => wasm::InsnKind::I8x16GeS,
+            wat::InsnKind::I8x16GeU => wasm::InsnKind::I8x16GeU,
+            wat::InsnKind::I16x8Eq => wasm::InsnKind::I16x8Eq,
+            wat::InsnKind::I16x8Ne => wasm::InsnKind::I16x8Ne,
+            wat::InsnKind::I16x8LtS => wasm::InsnKind::I16x8LtS,
+            wat::InsnKind::I16x8LtU => wasm::InsnKind::I16x8LtU,
+            wat::InsnKind::I16x8GtS => wasm::InsnKind::I16x8GtS,
+            wat::InsnKind::I16x8GtU => wasm::InsnKind::I16x8GtU,
+            wat::InsnKind::I16x8LeS => wasm::InsnKind::I16x8LeS,
+            wat::InsnKind::I16x8LeU => wasm::InsnKind::I16x8LeU,
+            wat::InsnKind::I16x8GeS => wasm::InsnKind::I16x8GeS,
+            wat::InsnKind::I16x8GeU => wasm::InsnKind::I16x8GeU,
+            wat::InsnKind::I32x4Eq => wasm::InsnKind::I32x4Eq,
+            wat::InsnKind::I32x4Ne => wasm::InsnKind::I32x4Ne,
+            wat::InsnKind::I32x4LtS => wasm::InsnKind::I32x4LtS,
+            wat::InsnKind::I32x4LtU => wasm::InsnKind::I32x4LtU,
+            wat::InsnKind::I32x4GtS => wasm::InsnKind::I32x4GtS,
+            wat::InsnKind::I32x4GtU => wasm::InsnKind::I32x4GtU,
+            wat::InsnKind::I32x4LeS => wasm::InsnKind::I32x4LeS,
+            wat::InsnKind::I32x4LeU => wasm::InsnKind::I32x4LeU,
+            wat::InsnKind::I32x4GeS => wasm::InsnKind::I32x4GeS,
+            wat::InsnKind::I32x4GeU => wasm::InsnKind::I32x4GeU,
+            wat::InsnKind::F32x4Eq => wasm::InsnKind::F32x4Eq,
+            wat::InsnKind::F32x4Ne => wasm::InsnKind::F32x4Ne,
+            wat::InsnKind::F32x4Lt => wasm::InsnKind::F32x4Lt,
+            wat::InsnKind::F32x4Gt => wasm::InsnKind::F32x4Gt,
+            wat::InsnKind::F32x4Le => wasm::InsnKind::F32x4Le,
+            wat::InsnKind::F32x4Ge => wasm::InsnKind::F32x4Ge,
+            wat::InsnKind::F64x2Eq => wasm::InsnKind::F64x2Eq,
+            wat::InsnKind::F64x2Ne => wasm::InsnKind::F64x2Ne,
+            wat::InsnKind::F64x2Lt => wasm::InsnKind::F64x2Lt,
+            wat::InsnKind::F64x2Gt => wasm::InsnKind::F64x2Gt,
+            wat::InsnKind::F64x2Le => wasm::InsnKind::F64x2Le,
+            wat::InsnKind::F64x2Ge => wasm::InsnKind::F64x2Ge,
+            wat::InsnKind::V128Not => wasm::InsnKind::V128Not,
+            wat::InsnKind::V128And => wasm::InsnKind::V128And,
+            wat::InsnKind::V128Andnot => wasm::InsnKind::V128Andnot,
+            wat::InsnKind::V128Or => wasm::InsnKind::V128Or,
+            wat::InsnKind::V128Xor => wasm::InsnKind::V128Xor,
+            wat::InsnKind::V128Bitselect => wasm::InsnKind::V128Bitselect,
+            wat::InsnKind::V128AnyTrue => wasm::InsnKind::V128AnyTrue,
+            wat::InsnKind::V128Load8Lane { mem, lane } => wasm::InsnKind::V128Load8Lane {
+                mem: ctx.memarg(mem, start)?,
+                lane,
+            },
+            wat::InsnKind::V128Load16Lane { mem, lane } => wasm::InsnKind::V128Load16Lane {
+                mem: ctx.memarg(mem, start)?,
+                lane,
+            },
+            wat::InsnKind::V128Load32Lane { mem, lane } => wasm::InsnKind::V128Load32Lane {
+                mem: ctx.memarg(mem, start)?,
+                lane,
+            },
+            wat::InsnKind::V128Load64Lane { mem, lane } => wasm::InsnKind::V128Load64Lane {
+                mem: ctx.memarg(mem, start)?,
+                lane,
+            },
+            wat::InsnKind::V128Store8Lane { mem, lane } => wasm::InsnKind::V128Store8Lane {
+                mem: ctx.memarg(mem, start)?,
+                lane,
+            },
+            wat::InsnKind::V128Store16Lane { mem, lane } => wasm::InsnKind::V128Store16Lane {
+                mem: ctx.memarg(mem, start)?,
+                lane,
+            },
+            wat::InsnKind::V128Store32Lane { mem, lane } => wasm::InsnKind::V128Store32Lane {
+                mem: ctx.memarg(mem, start)?,
+                lane,
+            },
+            wat::InsnKind::V128Store64Lane { mem, lane } => wasm::InsnKind::V128Store64Lane {
+                mem: ctx.memarg(mem, start)?,
+                lane,
+            },
+            wat::InsnKind::V128Load32Zero(mem) => {
+                wasm::InsnKind::V128Load32Zero(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::V128Load64Zero(mem) => {
+                wasm::InsnKind::V128Load64Zero(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::F32x4DemoteF64x2Zero => wasm::InsnKind::F32x4DemoteF64x2Zero,
+            wat::InsnKind::F64x2PromoteLowF32x4 => wasm::InsnKind::F64x2PromoteLowF32x4,
+            wat::InsnKind::I8x16Abs => wasm::InsnKind::I8x16Abs,
+            wat::InsnKind::I8x16Neg => wasm::InsnKind::I8x16Neg,
+            wat::InsnKind::I8x16Popcnt => wasm::InsnKind::I8x16Popcnt,
+            wat::InsnKind::I8x16AllTrue => wasm::InsnKind::I8x16AllTrue,
+            wat::InsnKind::I8x16Bitmask => wasm::InsnKind::I8x16Bitmask,
+            wat::InsnKind::I8x16NarrowI16x8S => wasm::InsnKind::I8x16NarrowI16x8S,
+            wat::InsnKind::I8x16NarrowI16x8U => wasm::InsnKind::I8x16NarrowI16x8U,
+            wat::InsnKind::F32x4Ceil => wasm::InsnKind::F32x4Ceil,
+            wat::InsnKind::F32x4Floor => wasm::InsnKind::F32x4Floor,
+            wat::InsnKind::F32x4Trunc => wasm::InsnKind::F32x4Trunc,
+            wat::InsnKind::F32x4Nearest => wasm::InsnKind::F32x4Nearest,
+            wat::InsnKind::I8x16Shl => wasm::InsnKind::I8x16Shl,
+            wat::InsnKind::I8x16ShrS => wasm::InsnKind::I8x16ShrS,
+            wat::InsnKind::I8x16ShrU => wasm::InsnKind::I8x16ShrU,
+            wat::InsnKind::I8x16Add => wasm::InsnKind::I8x16Add,
+            wat::InsnKind::I8x16AddSatS => wasm::InsnKind::I8x16AddSatS,
+            wat::InsnKind::I8x16AddSatU => wasm::InsnKind::I8x16AddSatU,
+            wat::InsnKind::I8x16Sub => wasm::InsnKind::I8x16Sub,
+            wat::InsnKind::I8x16SubSatS => wasm::InsnKind::I8x16SubSatS,
+            wat::InsnKind::I8x16SubSatU => wasm::InsnKind::I8x16SubSatU,
+            wat::InsnKind::F64x2Ceil => wasm::InsnKind::F64x2Ceil,
+            wat::InsnKind::F64x2Floor => wasm::InsnKind::F64x2Floor,
+            wat::InsnKind::I8x16MinS => wasm::InsnKind::I8x16MinS,
+            wat::InsnKind::I8x16MinU => wasm::InsnKind::I8x16MinU,
+            wat::InsnKind::I8x16MaxS => wasm::InsnKind::I8x16MaxS,
+            wat::InsnKind::I8x16MaxU => wasm::InsnKind::I8x16MaxU,
+            wat::InsnKind::F64x2Trunc => wasm::InsnKind::F64x2Trunc,
+            wat::InsnKind::I8x16AvgrU => wasm::InsnKind::I8x16AvgrU,
+            wat::InsnKind::I16x8ExtaddPairwiseI8x16S => wasm::InsnKind::I16x8ExtaddPairwiseI8x16S,
+            wat::InsnKind::I16x8ExtaddPairwiseI8x16U => wasm::InsnKind::I16x8ExtaddPairwiseI8x16U,
+            wat::InsnKind::I32x4ExtaddPairwiseI16x8S => wasm::InsnKind::I32x4ExtaddPairwiseI16x8S,
+            wat::InsnKind::I32x4ExtaddPairwiseI16x8U => wasm::InsnKind::I32x4ExtaddPairwiseI16x8U,
+            wat::InsnKind::I16x8Abs => wasm::InsnKind::I16x8Abs,
+            wat::InsnKind::I16x8Neg => wasm::InsnKind::I16x8Neg,
+            wat::InsnKind::I16x8Q15mulrSatS => wasm::InsnKind::I16x8Q15mulrSatS,
+            wat::InsnKind::I16x8AllTrue => wasm::InsnKind::I16x8AllTrue,
+            wat::InsnKind::I16x8Bitmask => wasm::InsnKind::I16x8Bitmask,
+            wat::InsnKind::I16x8NarrowI32x4S => wasm::InsnKind::I16x8NarrowI32x4S,
+            wat::InsnKind::I16x8NarrowI32x4U => wasm::InsnKind::I16x8NarrowI32x4U,
+            wat::InsnKind::I16x8ExtendLowI8x16S => wasm::InsnKind::I16x8ExtendLowI8x16S,
+            wat::InsnKind::I16x8ExtendHighI8x16S => wasm::InsnKind::I16x8ExtendHighI8x16S,
+            wat::InsnKind::I16x8ExtendLowI8x16U => wasm::InsnKind::I16x8ExtendLowI8x16U,
+            wat::InsnKind::I16x8ExtendHighI8x16U => wasm::InsnKind::I16x8ExtendHighI8x16U,
+            wat::InsnKind::I16x8Shl => wasm::InsnKind::I16x8Shl,
+            wat::InsnKind::I16x8ShrS => wasm::InsnKind::I16x8ShrS,
+            wat::InsnKind::I16x8ShrU => wasm::InsnKind::I16x8ShrU,
+            wat::InsnKind::I16x8Add => wasm::InsnKind::I16x8Add,
+            wat::InsnKind::I16x8AddSatS => wasm::InsnKind::I16x8AddSatS,
+            wat::InsnKind::I16x8AddSatU => wasm::InsnKind::I16x8AddSatU,
+            wat::InsnKind::I16x8Sub => wasm::InsnKind::I16x8Sub,
+            wat::InsnKind::I16x8SubSatS => wasm::InsnKind::I16x8SubSatS,
+            wat::InsnKind::I16x8SubSatU => wasm::InsnKind::I16x8SubSatU,
+            wat::InsnKind::F64x2Nearest => wasm::InsnKind::F64x2Nearest,
+            wat::InsnKind::I16x8Mul => wasm::InsnKind::I16x8Mul,
+            wat::InsnKind::I16x8MinS => wasm::InsnKind::I16x8MinS,
+            wat::InsnKind::I16x8MinU => wasm::InsnKind::I16x8MinU,
+            wat::InsnKind::I16x8MaxS => wasm::InsnKind::I16x8MaxS,
+            wat::InsnKind::I16x8MaxU => wasm::InsnKind::I16x8MaxU,
+            wat::InsnKind::I16x8AvgrU => wasm::InsnKind::I16x8AvgrU,
+            wat::InsnKind::I16x8ExtmulLowI8x16S => wasm::InsnKind::I16x8ExtmulLowI8x16S,
+            wat::InsnKind::I16x8ExtmulHighI8x16S => wasm::InsnKind::I16x8ExtmulHighI8x16S,
+            wat::InsnKind::I16x8ExtmulLowI8x16U => wasm::InsnKind::I16x8ExtmulLowI8x16U,
+            wat::InsnKind::I16x8ExtmulHighI8x16U => wasm::InsnKind::I16x8ExtmulHighI8x16U,
+            wat::InsnKind::I32x4Abs => wasm::InsnKind::I32x4Abs,
+            wat::InsnKind::I32x4Neg => wasm::InsnKind::I32x4Neg,
+            wat::InsnKind::I32x4AllTrue => wasm::InsnKind::I32x4AllTrue,
+            wat::InsnKind::I32x4Bitmask => wasm::InsnKind::I32x4Bitmask,
+            wat::InsnKind::I32x4ExtendLowI16x8S => wasm::InsnKind::I32x4ExtendLowI16x8S,
+            wat::InsnKind::I32x4ExtendHighI16x8S => wasm::InsnKind::I32x4ExtendHighI16x8S,
+            wat::InsnKind::I32x4ExtendLowI16x8U => wasm::InsnKind::I32x4ExtendLowI16x8U,
+            wat::InsnKind::I32x4ExtendHighI16x8U => wasm::InsnKind::I32x4ExtendHighI16x8U,
+            wat::InsnKind::I32x4Shl => wasm::InsnKind::I32x4Shl,
+            wat::InsnKind::I32x4ShrS => wasm::InsnKind::I32x4ShrS,
+            wat::InsnKind::I32x4ShrU => wasm::InsnKind::I32x4ShrU,
+            wat::InsnKind::I32x4Add => wasm::InsnKind::I32x4Add,
+            wat::InsnKind::I32x4Sub => wasm::InsnKind::I32x4Sub,
+            wat::InsnKind::I32x4Mul => wasm::InsnKind::I32x4Mul,
+            wat::InsnKind::I32x4MinS => wasm::InsnKind::I32x4MinS,
+            wat::InsnKind::I32x4MinU => wasm::InsnKind::I32x4MinU,
+            wat::InsnKind::I32x4MaxS => wasm::InsnKind::I32x4MaxS,
+            wat::InsnKind::I32x4MaxU => wasm::InsnKind::I32x4MaxU,
+            wat::InsnKind::I32x4DotI16x8S => wasm::InsnKind::I32x4DotI16x8S,
+            wat::InsnKind::I32x4ExtmulLowI16x8S => wasm::InsnKind::I32x4ExtmulLowI16x8S,
+            wat::InsnKind::I32x4ExtmulHighI16x8S => wasm::InsnKind::I32x4ExtmulHighI16x8S,
+            wat::InsnKind::I32x4ExtmulLowI16x8U => wasm::InsnKind::I32x4ExtmulLowI16x8U,
+            wat::InsnKind::I32x4ExtmulHighI16x8U => wasm::InsnKind::I32x4ExtmulHighI16x8U,
+            wat::InsnKind::I64x2Abs => wasm::InsnKind::I64x2Abs,
+            wat::InsnKind::I64x2Neg => wasm::InsnKind::I64x2Neg,
+            wat::InsnKind::I64x2AllTrue => wasm::InsnKind::I64x2AllTrue,
+            wat::InsnKind::I64x2Bitmask => wasm::InsnKind::I64x2Bitmask,
+            wat::InsnKind::I64x2ExtendLowI32x4S => wasm::InsnKind::I64x2ExtendLowI32x4S,
+            wat::InsnKind::I64x2ExtendHighI32x4S => wasm::InsnKind::I64x2ExtendHighI32x4S,
+            wat::InsnKind::I64x2ExtendLowI32x4U => wasm::InsnKind::I64x2ExtendLowI32x4U,
+            wat::InsnKind::I64x2ExtendHighI32x4U => wasm::InsnKind::I64x2ExtendHighI32x4U,
+            wat::InsnKind::I64x2Shl => wasm::InsnKind::I64x2Shl,
+            wat::InsnKind::I64x2ShrS => wasm::InsnKind::I64x2ShrS,
+            wat::InsnKind::I64x2ShrU => wasm::InsnKind::I64x2ShrU,
+            wat::InsnKind::I64x2Add => wasm::InsnKind::I64x2Add,
+            wat::InsnKind::I64x2Sub => wasm::InsnKind::I64x2Sub,
+            wat::InsnKind::I64x2Mul => wasm::InsnKind::I64x2Mul,
+            wat::InsnKind::I64x2Eq => wasm::InsnKind::I64x2Eq,
+            wat::InsnKind::I64x2Ne => wasm::InsnKind::I64x2Ne,
+            wat::InsnKind::I64x2LtS => wasm::InsnKind::I64x2LtS,
+            wat::InsnKind::I64x2GtS => wasm::InsnKind::I64x2GtS,
+            wat::InsnKind::I64x2LeS => wasm::InsnKind::I64x2LeS,
+            wat::InsnKind::I64x2GeS => wasm::InsnKind::I64x2GeS,
+            wat::InsnKind::I64x2ExtmulLowI32x4S => wasm::InsnKind::I64x2ExtmulLowI32x4S,
+            wat::InsnKind::I64x2ExtmulHighI32x4S => wasm::InsnKind::I64x2ExtmulHighI32x4S,
+            wat::InsnKind::I64x2ExtmulLowI32x4U => wasm::InsnKind::I64x2ExtmulLowI32x4U,
+            wat::InsnKind::I64x2ExtmulHighI32x4U => wasm::InsnKind::I64x2ExtmulHighI32x4U,
+            wat::InsnKind::F32x4Abs => wasm::InsnKind::F32x4Abs,
+            wat::InsnKind::F32x4Neg => wasm::InsnKind::F32x4Neg,
+            wat::InsnKind::F32x4Sqrt => wasm::InsnKind::F32x4Sqrt,
+            wat::InsnKind::F32x4Add => wasm::InsnKind::F32x4Add,
+            wat::InsnKind::F32x4Sub => wasm::InsnKind::F32x4Sub,
+            wat::InsnKind::F32x4Mul => wasm::InsnKind::F32x4Mul,
+            wat::InsnKind::F32x4Div => wasm::InsnKind::F32x4Div,
+            wat::InsnKind::F32x4Min => wasm::InsnKind::F32x4Min,
+            wat::InsnKind::F32x4Max => wasm::InsnKind::F32x4Max,
+            wat::InsnKind::F32x4Pmin => wasm::InsnKind::F32x4Pmin,
+            wat::InsnKind::F32x4Pmax => wasm::InsnKind::F32x4Pmax,
+            wat::InsnKind::F64x2Abs => wasm::InsnKind::F64x2Abs,
+            wat::InsnKind::F64x2Neg => wasm::InsnKind::F64x2Neg,
+            wat::InsnKind::F64x2Sqrt => wasm::InsnKind::F64x2Sqrt,
+            wat::InsnKind::F64x2Add => wasm::InsnKind::F64x2Add,
+            wat::InsnKind::F64x2Sub => wasm::InsnKind::F64x2Sub,
+            wat::InsnKind::F64x2Mul => wasm::InsnKind::F64x2Mul,
+            wat::InsnKind::F64x2Div => wasm::InsnKind::F64x2Div,
+            wat::InsnKind::F64x2Min => wasm::InsnKind::F64x2Min,
+            wat::InsnKind::F64x2Max => wasm::InsnKind::F64x2Max,
+            wat::InsnKind::F64x2Pmin => wasm::InsnKind::F64x2Pmin,
+            wat::InsnKind::F64x2Pmax => wasm::InsnKind::F64x2Pmax,
+            wat::InsnKind::I32x4TruncSatF32x4S => wasm::InsnKind::I32x4TruncSatF32x4S,
+            wat::InsnKind::I32x4TruncSatF32x4U => wasm::InsnKind::I32x4TruncSatF32x4U,
+            wat::InsnKind::F32x4ConvertI32x4S => wasm::InsnKind::F32x4ConvertI32x4S,
+            wat::InsnKind::F32x4ConvertI32x4U => wasm::InsnKind::F32x4ConvertI32x4U,
+            wat::InsnKind::I32x4TruncSatF64x2SZero => wasm::InsnKind::I32x4TruncSatF64x2SZero,
+            wat::InsnKind::I32x4TruncSatF64x2UZero => wasm::InsnKind::I32x4TruncSatF64x2UZero,
+            wat::InsnKind::F64x2ConvertLowI32x4S => wasm::InsnKind::F64x2ConvertLowI32x4S,
+            wat::InsnKind::F64x2ConvertLowI32x4U => wasm::InsnKind::F64x2ConvertLowI32x4U,
+            wat::InsnKind::MemoryAtomicNotify(mem) => {
+                wasm::InsnKind::MemoryAtomicNotify(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::MemoryAtomicWait32(mem) => {
+                wasm::InsnKind::MemoryAtomicWait32(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::MemoryAtomicWait64(mem) => {
+                wasm::InsnKind::MemoryAtomicWait64(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::AtomicFence => wasm::InsnKind::AtomicFence,
+            wat::InsnKind::I32AtomicLoad(mem) => {
+                wasm::InsnKind::I32AtomicLoad(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicLoad(mem) => {
+                wasm::InsnKind::I64AtomicLoad(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicLoad8U(mem) => {
+                wasm::InsnKind::I32AtomicLoad8U(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicLoad16U(mem) => {
+                wasm::InsnKind::I32AtomicLoad16U(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicLoad8U(mem) => {
+                wasm::InsnKind::I64AtomicLoad8U(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicLoad16U(mem) => {
+                wasm::InsnKind::I64AtomicLoad16U(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicLoad32U(mem) => {
+                wasm::InsnKind::I64AtomicLoad32U(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicStore(mem) => {
+                wasm::InsnKind::I32AtomicStore(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicStore(mem) => {
+                wasm::InsnKind::I64AtomicStore(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicStore8(mem) => {
+                wasm::InsnKind::I32AtomicStore8(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicStore16(mem) => {
+                wasm::InsnKind::I32AtomicStore16(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicStore8(mem) => {
+                wasm::InsnKind::I64AtomicStore8(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicStore16(mem) => {
+                wasm::InsnKind::I64AtomicStore16(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicStore32(mem) => {
+                wasm::InsnKind::I64AtomicStore32(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmwAdd(mem) => {
+                wasm::InsnKind::I32AtomicRmwAdd(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmwAdd(mem) => {
+                wasm::InsnKind::I64AtomicRmwAdd(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw8AddU(mem) => {
+                wasm::InsnKind::I32AtomicRmw8AddU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw16AddU(mem) => {
+                wasm::InsnKind::I32AtomicRmw16AddU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw8AddU(mem) => {
+                wasm::InsnKind::I64AtomicRmw8AddU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw16AddU(mem) => {
+                wasm::InsnKind::I64AtomicRmw16AddU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw32AddU(mem) => {
+                wasm::InsnKind::I64AtomicRmw32AddU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmwSub(mem) => {
+                wasm::InsnKind::I32AtomicRmwSub(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmwSub(mem) => {
+                wasm::InsnKind::I64AtomicRmwSub(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw8SubU(mem) => {
+                wasm::InsnKind::I32AtomicRmw8SubU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw16SubU(mem) => {
+                wasm::InsnKind::I32AtomicRmw16SubU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw8SubU(mem) => {
+                wasm::InsnKind::I64AtomicRmw8SubU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw16SubU(mem) => {
+                wasm::InsnKind::I64AtomicRmw16SubU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw32SubU(mem) => {
+                wasm::InsnKind::I64AtomicRmw32SubU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmwAnd(mem) => {
+                wasm::InsnKind::I32AtomicRmwAnd(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmwAnd(mem) => {
+                wasm::InsnKind::I64AtomicRmwAnd(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw8AndU(mem) => {
+                wasm::InsnKind::I32AtomicRmw8AndU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw16AndU(mem) => {
+                wasm::InsnKind::I32AtomicRmw16AndU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw8AndU(mem) => {
+                wasm::InsnKind::I64AtomicRmw8AndU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw16AndU(mem) => {
+                wasm::InsnKind::I64AtomicRmw16AndU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw32AndU(mem) => {
+                wasm::InsnKind::I64AtomicRmw32AndU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmwOr(mem) => {
+                wasm::InsnKind::I32AtomicRmwOr(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmwOr(mem) => {
+                wasm::InsnKind::I64AtomicRmwOr(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw8OrU(mem) => {
+                wasm::InsnKind::I32AtomicRmw8OrU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw16OrU(mem) => {
+                wasm::InsnKind::I32AtomicRmw16OrU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw8OrU(mem) => {
+                wasm::InsnKind::I64AtomicRmw8OrU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw16OrU(mem) => {
+                wasm::InsnKind::I64AtomicRmw16OrU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw32OrU(mem) => {
+                wasm::InsnKind::I64AtomicRmw32OrU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmwXor(mem) => {
+                wasm::InsnKind::I32AtomicRmwXor(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmwXor(mem) => {
+                wasm::InsnKind::I64AtomicRmwXor(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw8XorU(mem) => {
+                wasm::InsnKind::I32AtomicRmw8XorU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw16XorU(mem) => {
+                wasm::InsnKind::I32AtomicRmw16XorU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw8XorU(mem) => {
+                wasm::InsnKind::I64AtomicRmw8XorU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw16XorU(mem) => {
+                wasm::InsnKind::I64AtomicRmw16XorU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw32XorU(mem) => {
+                wasm::InsnKind::I64AtomicRmw32XorU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmwXchg(mem) => {
+                wasm::InsnKind::I32AtomicRmwXchg(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmwXchg(mem) => {
+                wasm::InsnKind::I64AtomicRmwXchg(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw8XchgU(mem) => {
+                wasm::InsnKind::I32AtomicRmw8XchgU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw16XchgU(mem) => {
+                wasm::InsnKind::I32AtomicRmw16XchgU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw8XchgU(mem) => {
+                wasm::InsnKind::I64AtomicRmw8XchgU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw16XchgU(mem) => {
+                wasm::InsnKind::I64AtomicRmw16XchgU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw32XchgU(mem) => {
+                wasm::InsnKind::I64AtomicRmw32XchgU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmwCmpxchg(mem) => {
+                wasm::InsnKind::I32AtomicRmwCmpxchg(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmwCmpxchg(mem) => {
+                wasm::InsnKind::I64AtomicRmwCmpxchg(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw8CmpxchgU(mem) => {
+                wasm::InsnKind::I32AtomicRmw8CmpxchgU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I32AtomicRmw16CmpxchgU(mem) => {
+                wasm::InsnKind::I32AtomicRmw16CmpxchgU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw8CmpxchgU(mem) => {
+                wasm::InsnKind::I64AtomicRmw8CmpxchgU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw16CmpxchgU(mem) => {
+                wasm::InsnKind::I64AtomicRmw16CmpxchgU(ctx.memarg(mem, start)?)
+            }
+            wat::InsnKind::I64AtomicRmw32CmpxchgU(mem) => {
+                wasm::InsnKind::I64AtomicRmw32CmpxchgU(ctx.memarg(mem, start)?)
+            }
         };
         Ok(wasm::Instruction { start, kind })
     }
@@ -690,13 +1327,16 @@ impl<'s> Transform<'s> for wat::Elem<'s> {
         let start = self.start;
         Ok(wasm::ElemSegment {
             start,
-            idx: ctx.resolve_table_idx(self.idx, start)?,
-            offset: self.offset.transform(ctx)?,
-            init: self
-                .init
-                .into_iter()
-                .map(|idx| ctx.resolve_func_idx(idx, start))
-                .collect::<Result<'_, _>>()?,
+            ty: self.ty.transform(ctx)?,
+            mode: match self.mode {
+                wat::ElemMode::Passive => wasm::ElemMode::Passive,
+                wat::ElemMode::Active { idx, offset } => wasm::ElemMode::Active {
+                    idx: ctx.resolve_table_idx(idx, start)?,
+                    offset: offset.transform(ctx)?,
+                },
+                wat::ElemMode::Declarative => wasm::ElemMode::Declarative,
+            },
+            init: self.init.transform(ctx)?,
         })
     }
 }
@@ -717,6 +1357,7 @@ impl<'s> Transform<'s> for wat::Table<'s> {
         Ok(wasm::Table {
             start: self.start,
             ty: wasm::TableType {
+                elem: self.ty.elem.transform(ctx)?,
                 limit: self.ty.limit.transform(ctx)?,
             },
             import: self.import.transform(ctx)?,
@@ -729,8 +1370,13 @@ impl<'s> Transform<'s> for wat::Data<'s> {
     fn transform(self, ctx: &mut Context<'s>) -> Result<'s, Self::Target> {
         Ok(wasm::DataSegment {
             start: self.start,
-            idx: ctx.resolve_mem_idx(self.idx, self.start)?,
-            offset: self.offset.transform(ctx)?,
+            mode: match self.mode {
+                wat::DataMode::Passive => wasm::DataMode::Passive,
+                wat::DataMode::Active { idx, offset } => wasm::DataMode::Active {
+                    idx: ctx.resolve_mem_idx(idx, self.start)?,
+                    offset: offset.transform(ctx)?,
+                },
+            },
             data: self.data,
         })
     }
@@ -743,6 +1389,8 @@ impl<'s> Transform<'s> for wat::Memory<'s> {
             start: self.start,
             ty: wasm::MemType {
                 limit: self.ty.limit.transform(ctx)?,
+                shared: self.ty.shared,
+                memory64: self.ty.memory64,
             },
             import: self.import.transform(ctx)?,
         })
@@ -764,6 +1412,28 @@ impl<'s> Transform<'s> for wat::Global<'s> {
     }
 }
 
+impl<'s> Transform<'s> for wat::Catch<'s> {
+    type Target = wasm::Catch;
+    fn transform(self, ctx: &mut Context<'s>) -> Result<'s, Self::Target> {
+        Ok(wasm::Catch {
+            start: self.start,
+            tag: ctx.resolve_tag_idx(self.tag, self.start)?,
+            body: self.body.transform(ctx)?,
+        })
+    }
+}
+
+impl<'s> Transform<'s> for wat::Tag<'s> {
+    type Target = wasm::Tag<'s>;
+    fn transform(self, ctx: &mut Context<'s>) -> Result<'s, Self::Target> {
+        Ok(wasm::Tag {
+            start: self.start,
+            idx: ctx.resolve_type_idx(self.ty.idx, self.start)?,
+            import: self.import.transform(ctx)?,
+        })
+    }
+}
+
 impl<'s> Transform<'s> for wat::Start<'s> {
     type Target = wasm::StartFunction;
     fn transform(self, ctx: &mut Context<'s>) -> Result<'s, Self::Target> {
@@ -10,7 +10,6 @@ pub enum ErrorKind {
         upper: usize,
         what: &'static str,
     },
-    MultipleReturnTypes(Vec<ValType>),
     TypeMismatch {
         expected: ValType,
         actual: ValType,
@@ -20,8 +19,10 @@ pub enum ErrorKind {
         frame_start: usize,
         idx_in_op_stack: usize,
     },
-    LabelStackEmpty {
-        op: &'static str,
+    ArityMismatch {
+        expected: usize,
+        actual: usize,
+        what: &'static str,
     },
     SetImmutableGlobal {
         ty: ValType,
@@ -31,11 +32,11 @@ pub enum ErrorKind {
         align: u8,
         bits: u8,
     },
-    InvalidLimitRange(u32, u32),
+    InvalidLimitRange(u64, u64),
     LimitsOutOfRange {
-        value: u32,
-        min: u32,
-        max: u32,
+        value: u64,
+        min: u64,
+        max: u64,
         what: &'static str,
     },
     NotConstantInstruction(&'static str),
@@ -45,13 +46,22 @@ pub enum ErrorKind {
         params: Vec<ValType>,
         results: Vec<ValType>,
     },
-    MultipleTables(usize),
-    MultipleMemories(usize),
     AlreadyExported {
         name: String,
         prev_offset: usize,
     },
-    MemoryIsNotDefined,
+    NotReferenceType(ValType),
+    ReferenceTypeForSelect(ValType),
+    UndeclaredFuncRef(u32),
+    TailCallResults {
+        expected: Vec<ValType>,
+        actual: Vec<ValType>,
+    },
+    TagResults(Vec<ValType>),
+    NotCatchLabel(u32),
+    SharedMemoryWithoutMax,
+    NotConstantGlobal(u32),
+    TooLargeOffset(u64),
 }
 
 #[cfg_attr(test, derive(Debug))]
@@ -90,14 +100,6 @@ impl<S: Source> fmt::Display for Error<S> {
                 "{} index {} out of bounds 0 <= idx < {}",
                 what, idx, upper
             )?,
-            MultipleReturnTypes(tys) => {
-                let ss = tys.iter().map(AsRef::as_ref).collect::<Vec<&str>>();
-                write!(
-                    f,
-                    "multiple return types are not allowed for now but got [{}]",
-                    ss.join(", ")
-                )?
-            }
             TypeMismatch {
                 expected,
                 actual,
@@ -115,12 +117,12 @@ impl<S: Source> fmt::Display for Error<S> {
                 f,
                 "empty control frame cannot be empty at '{}' instruction. the frame started at byte offset {} and top of \
                  control frame is op_stack[{}]", op, frame_start, idx_in_op_stack)?,
-            LabelStackEmpty { op } => write!(f, "label stack for control instructions is unexpectedly empty at '{}' instruction", op)?,
+            ArityMismatch { expected, actual, what } => write!(f, "expected {} {} but got {}", expected, what, actual)?,
             SetImmutableGlobal{ ty, idx } => write!(f, "{} value cannot be set to immutable global variable {}", ty, idx)?,
             TooLargeAlign { align, bits } => write!(f, "align {} must not be larger than {}bits / 8", align, bits)?,
             InvalidLimitRange(min, max) => write!(f, "range for limits {}..{} is invalid", min, max)?,
             LimitsOutOfRange { value, min, max, what } => write!(f, "limit {} is out of range {}..{} at {}", value, min, max, what)?,
-            NotConstantInstruction(op) => write!(f, "instruction '{}' is not valid for constant. only 'global.get' or '*.const' are valid in constant expressions", op)?,
+            NotConstantInstruction(op) => write!(f, "instruction '{}' is not valid for constant. only 'global.get', 'ref.null', 'ref.func', '*.const' or 'i32' and 'i64' versions of 'add', 'sub' and 'mul' are valid in constant expressions", op)?,
             NoInstructionForConstant => write!(f, "at least one instruction is necessary for constant expressions")?,
             StartFunctionSignature{ idx, params, results } => write!(
                 f,
@@ -129,10 +131,25 @@ impl<S: Source> fmt::Display for Error<S> {
                 params.iter().map(AsRef::<str>::as_ref).collect::<Vec<_>>().join(" "),
                 results.iter().map(AsRef::<str>::as_ref).collect::<Vec<_>>().join(" "),
             )?,
-            MultipleTables(size) => write!(f, "number of tables must not be larger than 1 but got {}", size)?,
-            MultipleMemories(size) => write!(f, "number of memories must not be larger than 1 but got {}", size)?,
             AlreadyExported{ name, prev_offset } => write!(f, "'{}' was already exported at offset {}", name, prev_offset)?,
-            MemoryIsNotDefined => write!(f, "at least one memory section must be defined")?,
+            NotReferenceType(ty) => write!(f, "expected reference type but got type '{}'", ty)?,
+            ReferenceTypeForSelect(ty) => write!(f, "operands of 'select' without result type must be numeric but got type '{}'", ty)?,
+            UndeclaredFuncRef(idx) => write!(f, "function {} is referenced but not declared in element segments, exports or global initializers", idx)?,
+            TailCallResults{ expected, actual } => write!(
+                f,
+                "results [{}] of tail-called function must be the same as results [{}] of current function",
+                actual.iter().map(AsRef::<str>::as_ref).collect::<Vec<_>>().join(" "),
+                expected.iter().map(AsRef::<str>::as_ref).collect::<Vec<_>>().join(" "),
+            )?,
+            TagResults(results) => write!(
+                f,
+                "type of tag must have no result but found results [{}]",
+                results.iter().map(AsRef::<str>::as_ref).collect::<Vec<_>>().join(" "),
+            )?,
+            NotCatchLabel(idx) => write!(f, "label {} of 'rethrow' must refer to 'catch' or 'catch_all' clause", idx)?,
+            SharedMemoryWithoutMax => write!(f, "shared memory must have maximum limit")?,
+            TooLargeOffset(offset) => write!(f, "offset {} of memory access to 32-bit memory must be less than 2^32", offset)?,
+            NotConstantGlobal(idx) => write!(f, "global variable {} is not constant. only immutable imported global variables can be read in constant expressions", idx)?,
         }
 
         write!(f, " while validating {}", self.when)?;
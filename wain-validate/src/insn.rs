@@ -8,7 +8,6 @@
 
 use crate::error::{ErrorKind, Ordinal, Result};
 use crate::Context as OuterContext;
-use std::mem;
 use wain_ast::source::Source;
 use wain_ast::*;
 
@@ -30,11 +29,41 @@ impl Type {
     fn f64() -> Type {
         Type::Known(ValType::F64)
     }
+    fn v128() -> Type {
+        Type::Known(ValType::V128)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FrameKind {
+    Block,
+    Loop,
+    // Frame of 'catch' or 'catch_all' clause. Only this frame can be a target of 'rethrow'
+    // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#rethrows
+    Catch,
 }
 
-struct CtrlFrame {
-    idx: usize,
+// https://webassembly.github.io/spec/core/appendix/algorithm.html#data-structures
+struct CtrlFrame<'outer> {
     offset: usize,
+    kind: FrameKind,
+    start_types: &'outer [ValType],
+    end_types: &'outer [ValType],
+    // Height of operand stack at the start of the frame
+    height: usize,
+    // Unreachability of the rest of instruction sequence in this frame
+    unreachable: bool,
+}
+
+impl<'outer> CtrlFrame<'outer> {
+    // Branch to loop jumps to its start. Branch to other frames jumps to their end
+    fn label_types(&self) -> &'outer [ValType] {
+        if self.kind == FrameKind::Loop {
+            self.start_types
+        } else {
+            self.end_types
+        }
+    }
 }
 
 // https://webassembly.github.io/spec/core/valid/conventions.html#context
@@ -44,18 +73,14 @@ struct FuncBodyContext<'outer, 'module: 'outer, 'source: 'module, S: Source> {
     outer: &'outer OuterContext<'module, 'source, S>,
     // Types on stack to check operands of instructions such as unreachable, br, table_br
     op_stack: Vec<Type>,
-    // Index of current control frame
-    current_frame: CtrlFrame,
-    // Label stack to verify jump instructions. None means no type for the label
-    label_stack: Vec<Option<ValType>>,
+    // Control frames of blocks, loops and ifs. The bottom is the frame of function body
+    ctrl_frames: Vec<CtrlFrame<'outer>>,
     // The list of locals declared in the current function (including parameters), represented by their value type.
     // It's empty when validating outside function.
     params: &'outer [ValType],
     locals: &'outer [ValType],
-    // Return type of the current function if exists
-    ret_ty: Option<ValType>,
-    // Unreachability of current instruction sequence
-    unreachable: bool,
+    // Result types of the current function
+    results: &'outer [ValType],
 }
 
 impl<'outer, 'm, 's, S: Source> FuncBodyContext<'outer, 'm, 's, S> {
@@ -63,44 +88,39 @@ impl<'outer, 'm, 's, S: Source> FuncBodyContext<'outer, 'm, 's, S> {
         self.outer.error(kind, self.current_op, self.current_offset)
     }
 
-    fn ensure_ctrl_frame_not_empty(&self) -> Result<(), S> {
-        if self.op_stack.len() > self.current_frame.idx {
-            return Ok(());
-        }
-
-        if self.unreachable {
-            // Reach top of current control frame, but it's ok when unreachable. For example,
-            //
-            //   unreachable i32.add
-            //
-            // should be valid. In the case operands of i32.add are not checked. For example,
-            //
-            //   unreachable (i64.const 0) i32.add
-            //
-            // should be invalid. In the case one operand of i32.add is i64. To archive this check,
-            // popping operand stack has trick. Unknown type is simply ignored on check.
-            return Ok(());
-        }
-
-        // When not unreachable and stack hits top of current control frame, this instruction
-        // sequence is invalid if some value should have been pushed on stack
-        self.error(ErrorKind::CtrlFrameEmpty {
-            op: self.current_op,
-            frame_start: self.current_frame.offset,
-            idx_in_op_stack: self.current_frame.idx,
-        })
+    fn current_frame(&self) -> &CtrlFrame<'outer> {
+        &self.ctrl_frames[self.ctrl_frames.len() - 1]
     }
 
-    fn ensure_op_stack_top(&self, expected: Type) -> Result<Type, S> {
-        self.ensure_ctrl_frame_not_empty()?;
-        if self.op_stack.len() == self.current_frame.idx || self.op_stack.is_empty() {
-            assert!(self.unreachable);
-            return Ok(Type::Unknown);
+    fn pop_op_stack(&mut self, expected: Type) -> Result<Type, S> {
+        let frame = self.current_frame();
+        if self.op_stack.len() == frame.height {
+            if frame.unreachable {
+                // Reach top of current control frame, but it's ok when unreachable. For example,
+                //
+                //   unreachable i32.add
+                //
+                // should be valid. In the case operands of i32.add are not checked. For example,
+                //
+                //   unreachable (i64.const 0) i32.add
+                //
+                // should be invalid. In the case one operand of i32.add is i64. To archive this
+                // check, popping operand stack has trick. Unknown type is simply ignored on check.
+                return Ok(Type::Unknown);
+            }
+
+            // When not unreachable and stack hits top of current control frame, this instruction
+            // sequence is invalid if some value should have been pushed on stack
+            return self.error(ErrorKind::CtrlFrameEmpty {
+                op: self.current_op,
+                frame_start: frame.offset,
+                idx_in_op_stack: frame.height,
+            });
         }
 
-        let actual = self.op_stack[self.op_stack.len() - 1];
+        let actual = self.op_stack.pop().unwrap();
 
-        // Note: None here means unknown type due to unreachable
+        // Note: Unknown here means unknown type due to unreachable
         if let (Type::Known(expected), Type::Known(actual)) = (expected, actual) {
             if actual != expected {
                 return self.error(ErrorKind::TypeMismatch { expected, actual });
@@ -110,39 +130,77 @@ impl<'outer, 'm, 's, S: Source> FuncBodyContext<'outer, 'm, 's, S> {
         Ok(actual)
     }
 
-    fn pop_op_stack(&mut self, expected: Type) -> Result<Type, S> {
-        let ty = self.ensure_op_stack_top(expected)?;
-        self.op_stack.pop();
-        Ok(ty)
+    // Check the top value of stack and replace it with the expected type. This is used for
+    // instructions which pop a value and push a value of the same type
+    fn ensure_op_stack_top(&mut self, expected: Type) -> Result<(), S> {
+        let actual = self.pop_op_stack(expected)?;
+        self.op_stack.push(match expected {
+            Type::Known(_) => expected,
+            Type::Unknown => actual,
+        });
+        Ok(())
     }
 
-    fn push_control_frame(&mut self, offset: usize) -> CtrlFrame {
-        let idx = self.op_stack.len();
-        let new = CtrlFrame { idx, offset };
-        mem::replace(&mut self.current_frame, new)
+    fn pop_op_stack_types(&mut self, types: &[ValType]) -> Result<(), S> {
+        // Pop extracts values in reverse order
+        for ty in types.iter().rev() {
+            self.pop_op_stack(Type::Known(*ty))?;
+        }
+        Ok(())
     }
 
-    fn pop_control_frame(&mut self, prev: CtrlFrame) {
-        // control frame top is validated by pop_op_stack
-        assert!(self.current_frame.idx <= self.op_stack.len());
-        self.current_frame = prev;
+    fn push_op_stack_types(&mut self, types: &[ValType]) {
+        self.op_stack.extend(types.iter().map(|t| Type::Known(*t)));
     }
 
-    fn pop_label_stack(&mut self) -> Result<(), S> {
-        if let Some(ty) = self.label_stack.pop() {
-            if let Some(ty) = ty {
-                self.ensure_op_stack_top(Type::Known(ty))?;
-            }
-            Ok(())
-        } else {
-            self.error(ErrorKind::LabelStackEmpty {
-                op: self.current_op,
-            })
+    // https://webassembly.github.io/spec/core/appendix/algorithm.html#validation-of-opcode-sequences
+    fn push_ctrl_frame(
+        &mut self,
+        offset: usize,
+        kind: FrameKind,
+        start_types: &'outer [ValType],
+        end_types: &'outer [ValType],
+    ) {
+        self.ctrl_frames.push(CtrlFrame {
+            offset,
+            kind,
+            start_types,
+            end_types,
+            height: self.op_stack.len(),
+            unreachable: false,
+        });
+        self.push_op_stack_types(start_types);
+    }
+
+    fn pop_ctrl_frame(&mut self) -> Result<CtrlFrame<'outer>, S> {
+        let frame = self.current_frame();
+        let (end_types, height) = (frame.end_types, frame.height);
+        let num_values = self.op_stack.len() - height;
+        self.pop_op_stack_types(end_types)?;
+        if self.op_stack.len() != height {
+            return self.error(ErrorKind::ArityMismatch {
+                expected: end_types.len(),
+                actual: num_values,
+                what: "values at end of control frame",
+            });
         }
+        Ok(self.ctrl_frames.pop().unwrap())
+    }
+
+    // Mark the rest of current instruction sequence unreachable. Values pushed in the frame are
+    // no longer accessible
+    fn set_unreachable(&mut self) {
+        let frame = self.ctrl_frames.last_mut().unwrap();
+        self.op_stack.truncate(frame.height);
+        frame.unreachable = true;
     }
 
-    fn validate_label_idx(&self, idx: u32) -> Result<Option<ValType>, S> {
-        let len = self.label_stack.len();
+    fn validate_label_idx(&self, idx: u32) -> Result<&'outer [ValType], S> {
+        Ok(self.frame_from_label_idx(idx)?.label_types())
+    }
+
+    fn frame_from_label_idx(&self, idx: u32) -> Result<&CtrlFrame<'outer>, S> {
+        let len = self.ctrl_frames.len();
         if (idx as usize) >= len {
             return self.error(ErrorKind::IndexOutOfBounds {
                 idx,
@@ -150,11 +208,19 @@ impl<'outer, 'm, 's, S: Source> FuncBodyContext<'outer, 'm, 's, S> {
                 what: "label",
             });
         }
-        let ty = self.label_stack[len - 1 - (idx as usize)];
-        if let Some(ty) = ty {
-            self.ensure_op_stack_top(Type::Known(ty))?;
+        Ok(&self.ctrl_frames[len - 1 - (idx as usize)])
+    }
+
+    fn validate_block_type(
+        &self,
+        ty: &'outer BlockType,
+    ) -> Result<(&'outer [ValType], &'outer [ValType]), S> {
+        if let BlockType::Type(idx) = ty {
+            self.outer
+                .type_from_idx(*idx, self.current_op, self.current_offset)?;
         }
-        Ok(ty)
+        let types = &self.outer.module.types;
+        Ok((ty.params(types), ty.results(types)))
     }
 
     fn validate_local_idx(&self, idx: u32) -> Result<ValType, S> {
@@ -181,29 +247,170 @@ impl<'outer, 'm, 's, S: Source> FuncBodyContext<'outer, 'm, 's, S> {
         }
     }
 
-    fn validate_memarg(&self, mem: &Mem, bits: u8) -> Result<(), S> {
-        self.outer
-            .memory_from_idx(0, self.current_op, self.current_offset)?;
+    // Returns type of address, which is i64 for 64-bit memory
+    fn validate_memarg(&self, mem: &Mem, bits: u8) -> Result<Type, S> {
+        let addr_ty = self.validate_mem_idx(mem.memory)?;
         // The alignment must not be larger than the bit width of t divided by 8.
         if let Some(align) = mem.align {
             if align > bits / 8 {
                 return self.error(ErrorKind::TooLargeAlign { align, bits });
             }
         }
-        Ok(())
+        // https://github.com/WebAssembly/memory64/blob/main/proposals/memory64/Overview.md#validation
+        // Offset of memory access to 32-bit memory must be within u32
+        if let (Some(offset), ValType::I32) = (mem.offset, addr_ty) {
+            if offset > u32::MAX as u64 {
+                return self.error(ErrorKind::TooLargeOffset(offset));
+            }
+        }
+        Ok(Type::Known(addr_ty))
     }
 
     fn validate_load(&mut self, mem: &Mem, bits: u8, ty: ValType) -> Result<(), S> {
-        self.validate_memarg(mem, bits)?;
-        self.pop_op_stack(Type::i32())?; // load address
+        let addr_ty = self.validate_memarg(mem, bits)?;
+        self.pop_op_stack(addr_ty)?; // load address
         self.op_stack.push(Type::Known(ty));
         Ok(())
     }
 
     fn validate_store(&mut self, mem: &Mem, bits: u8, ty: ValType) -> Result<(), S> {
-        self.validate_memarg(mem, bits)?;
+        let addr_ty = self.validate_memarg(mem, bits)?;
         self.pop_op_stack(Type::Known(ty))?; // value to store
-        self.pop_op_stack(Type::i32())?; // store address
+        self.pop_op_stack(addr_ty)?; // store address
+        Ok(())
+    }
+
+    // Read-modify-write returns the old value: [i32 t] -> [t]
+    fn validate_atomic_rmw(&mut self, mem: &Mem, bits: u8, ty: ValType) -> Result<(), S> {
+        let addr_ty = self.validate_memarg(mem, bits)?;
+        self.pop_op_stack(Type::Known(ty))?; // operand
+        self.pop_op_stack(addr_ty)?; // address
+        self.op_stack.push(Type::Known(ty));
+        Ok(())
+    }
+
+    // [i32 t t] -> [t]
+    fn validate_atomic_cmpxchg(&mut self, mem: &Mem, bits: u8, ty: ValType) -> Result<(), S> {
+        let addr_ty = self.validate_memarg(mem, bits)?;
+        self.pop_op_stack(Type::Known(ty))?; // replacement
+        self.pop_op_stack(Type::Known(ty))?; // expected value
+        self.pop_op_stack(addr_ty)?; // address
+        self.op_stack.push(Type::Known(ty));
+        Ok(())
+    }
+
+    // [i32 t i64] -> [i32]
+    fn validate_atomic_wait(&mut self, mem: &Mem, bits: u8, ty: ValType) -> Result<(), S> {
+        let addr_ty = self.validate_memarg(mem, bits)?;
+        self.pop_op_stack(Type::i64())?; // timeout
+        self.pop_op_stack(Type::Known(ty))?; // expected value
+        self.pop_op_stack(addr_ty)?; // address
+        self.op_stack.push(Type::i32());
+        Ok(())
+    }
+
+    // [i32 i32] -> [i32]
+    fn validate_atomic_notify(&mut self, mem: &Mem) -> Result<(), S> {
+        let addr_ty = self.validate_memarg(mem, 32)?;
+        self.pop_op_stack(Type::i32())?; // count of waiters
+        self.pop_op_stack(addr_ty)?; // address
+        self.op_stack.push(Type::i32());
+        Ok(())
+    }
+
+    fn validate_lane_idx(&self, lane: u8, lanes: usize) -> Result<(), S> {
+        if lane as usize >= lanes {
+            return self.error(ErrorKind::IndexOutOfBounds {
+                idx: lane as u32,
+                upper: lanes,
+                what: "lane",
+            });
+        }
+        Ok(())
+    }
+
+    // Lane of vector is loaded from memory and replaced: [i32 v128] -> [v128]
+    fn validate_load_lane(&mut self, mem: &Mem, lane: u8, bits: u8) -> Result<(), S> {
+        let addr_ty = self.validate_memarg(mem, bits)?;
+        self.validate_lane_idx(lane, 128 / bits as usize)?;
+        self.pop_op_stack(Type::v128())?; // vector whose lane is replaced
+        self.pop_op_stack(addr_ty)?; // load address
+        self.op_stack.push(Type::v128());
+        Ok(())
+    }
+
+    // Lane of vector is stored to memory: [i32 v128] -> []
+    fn validate_store_lane(&mut self, mem: &Mem, lane: u8, bits: u8) -> Result<(), S> {
+        let addr_ty = self.validate_memarg(mem, bits)?;
+        self.validate_lane_idx(lane, 128 / bits as usize)?;
+        self.pop_op_stack(Type::v128())?; // vector whose lane is stored
+        self.pop_op_stack(addr_ty)?; // store address
+        Ok(())
+    }
+
+    fn validate_extract_lane(&mut self, lane: u8, lanes: usize, ty: ValType) -> Result<(), S> {
+        self.validate_lane_idx(lane, lanes)?;
+        self.validate_convert(ValType::V128, ty)
+    }
+
+    fn validate_replace_lane(&mut self, lane: u8, lanes: usize, ty: ValType) -> Result<(), S> {
+        self.validate_lane_idx(lane, lanes)?;
+        self.pop_op_stack(Type::Known(ty))?;
+        self.ensure_op_stack_top(Type::v128())
+    }
+
+    // Returns index type of the memory, which is i64 for 64-bit memory
+    fn validate_mem_idx(&self, idx: u32) -> Result<ValType, S> {
+        let memory = self
+            .outer
+            .memory_from_idx(idx, self.current_op, self.current_offset)?;
+        Ok(if memory.ty.memory64 {
+            ValType::I64
+        } else {
+            ValType::I32
+        })
+    }
+
+    fn validate_table_idx(&self, idx: u32) -> Result<ValType, S> {
+        let table = self
+            .outer
+            .table_from_idx(idx, self.current_op, self.current_offset)?;
+        Ok(table.ty.elem.into())
+    }
+
+    // Bulk memory and table instructions take destination, source (or fill value) and length
+    fn validate_bulk_operands(&mut self) -> Result<(), S> {
+        self.validate_bulk_operands_of(ValType::I32, ValType::I32, ValType::I32)
+    }
+
+    // Operands of bulk memory instructions for 64-bit memory are i64
+    // https://github.com/WebAssembly/memory64/blob/main/proposals/memory64/Overview.md#validation
+    fn validate_bulk_operands_of(
+        &mut self,
+        dst: ValType,
+        src: ValType,
+        len: ValType,
+    ) -> Result<(), S> {
+        self.pop_op_stack(Type::Known(len))?; // length
+        self.pop_op_stack(Type::Known(src))?; // source or fill value
+        self.pop_op_stack(Type::Known(dst))?; // destination
+        Ok(())
+    }
+
+    // Callee of tail call returns to the caller of the current function
+    fn validate_tail_call(&mut self, fty: &FuncType, what: &'static str) -> Result<(), S> {
+        if fty.results != self.results {
+            return self.error(ErrorKind::TailCallResults {
+                expected: self.results.to_vec(),
+                actual: fty.results.clone(),
+            });
+        }
+        // Pop extracts parameters in reverse order
+        for (i, ty) in fty.params.iter().enumerate().rev() {
+            self.pop_op_stack(Type::Known(*ty))
+                .map_err(|e| e.update_msg(format!("{} parameter at {}", Ordinal(i), what)))?;
+        }
+        self.set_unreachable();
         Ok(())
     }
 
@@ -221,77 +428,61 @@ pub(crate) fn validate_func_body<'outer, 'm, 's, S: Source>(
     outer: &'outer OuterContext<'m, 's, S>,
     start: usize,
 ) -> Result<(), S> {
-    // FuncType validated func_ty has at most one result type
-    let ret_ty = func_ty.results.get(0).copied();
     let mut ctx = FuncBodyContext {
         current_op: "",
         current_offset: start,
         outer,
         op_stack: vec![],
-        label_stack: vec![],
-        current_frame: CtrlFrame {
-            idx: 0,
-            offset: start,
-        },
+        ctrl_frames: vec![],
         params: &func_ty.params,
         locals,
-        ret_ty,
-        unreachable: false,
+        results: &func_ty.results,
     };
+    // Function body is validated as a block whose result types are the function's result types
+    ctx.push_ctrl_frame(start, FrameKind::Block, &[], &func_ty.results);
     body.validate(&mut ctx)?;
-    if let Some(ty) = ret_ty {
-        ctx.current_op = "function return type";
-        ctx.current_offset = start;
-        ctx.ensure_op_stack_top(Type::Known(ty))?;
-    }
+    ctx.current_op = "function return type";
+    ctx.current_offset = start;
+    ctx.pop_ctrl_frame()?;
     Ok(())
 }
 
 trait ValidateInsnSeq<'outer, 'm, 's, S: Source> {
-    fn validate(&self, ctx: &mut FuncBodyContext<'outer, 'm, 's, S>) -> Result<(), S>;
+    fn validate(&'outer self, ctx: &mut FuncBodyContext<'outer, 'm, 's, S>) -> Result<(), S>;
 }
 
 impl<'s, 'm, 'outer, S: Source, V: ValidateInsnSeq<'outer, 'm, 's, S>>
     ValidateInsnSeq<'outer, 'm, 's, S> for [V]
 {
-    fn validate(&self, ctx: &mut FuncBodyContext<'outer, 'm, 's, S>) -> Result<(), S> {
-        self.iter()
-            .map(|insn| insn.validate(ctx))
-            .collect::<Result<_, _>>()?;
-        ctx.unreachable = false; // clear flag
-        Ok(())
+    fn validate(&'outer self, ctx: &mut FuncBodyContext<'outer, 'm, 's, S>) -> Result<(), S> {
+        self.iter().map(|insn| insn.validate(ctx)).collect()
     }
 }
 
 // https://webassembly.github.io/spec/core/valid/instructions.html#instruction-sequences
 impl<'outer, 'm, 's, S: Source> ValidateInsnSeq<'outer, 'm, 's, S> for Instruction {
-    fn validate(&self, ctx: &mut FuncBodyContext<'outer, 'm, 's, S>) -> Result<(), S> {
+    fn validate(&'outer self, ctx: &mut FuncBodyContext<'outer, 'm, 's, S>) -> Result<(), S> {
         ctx.current_op = self.kind.name();
         ctx.current_offset = self.start;
         let start = self.start;
         use InsnKind::*;
         match &self.kind {
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-block
-            Block { ty, body } => {
-                let saved = ctx.push_control_frame(start);
-                ctx.label_stack.push(*ty);
-                body.validate(ctx)?;
-                ctx.pop_label_stack()?;
-                ctx.pop_control_frame(saved);
-                if let Some(ty) = ty {
-                    ctx.ensure_op_stack_top(Type::Known(*ty))?;
-                }
-            }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-loop
-            Loop { ty, body } => {
-                let saved = ctx.push_control_frame(start);
-                ctx.label_stack.push(*ty);
+            Block { ty, body } | Loop { ty, body } => {
+                let kind = if let Loop { .. } = &self.kind {
+                    FrameKind::Loop
+                } else {
+                    FrameKind::Block
+                };
+                let (params, results) = ctx.validate_block_type(ty)?;
+                ctx.pop_op_stack_types(params)?;
+                ctx.push_ctrl_frame(start, kind, params, results);
                 body.validate(ctx)?;
-                ctx.pop_label_stack()?;
-                ctx.pop_control_frame(saved);
-                if let Some(ty) = ty {
-                    ctx.ensure_op_stack_top(Type::Known(*ty))?;
-                }
+                ctx.current_op = self.kind.name();
+                ctx.current_offset = start;
+                ctx.pop_ctrl_frame()?;
+                ctx.push_op_stack_types(results);
             }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-if
             If {
@@ -299,78 +490,94 @@ impl<'outer, 'm, 's, S: Source> ValidateInsnSeq<'outer, 'm, 's, S> for Instructi
                 then_body,
                 else_body,
             } => {
+                let (params, results) = ctx.validate_block_type(ty)?;
                 // Condition
                 ctx.pop_op_stack(Type::i32())?;
-                ctx.label_stack.push(*ty);
+                ctx.pop_op_stack_types(params)?;
 
-                let saved = ctx.push_control_frame(start);
+                ctx.push_ctrl_frame(start, FrameKind::Block, params, results);
                 then_body.validate(ctx)?;
-                if let Some(ty) = ty {
-                    ctx.ensure_op_stack_top(Type::Known(*ty))?;
-                }
-                ctx.pop_control_frame(saved);
+                ctx.current_op = self.kind.name();
+                ctx.current_offset = start;
+                ctx.pop_ctrl_frame()?;
 
-                let saved = ctx.push_control_frame(start);
+                // When 'else' clause is omitted, it is validated as empty instruction sequence. It
+                // means the block type must be [t*] -> [t*]
+                ctx.push_ctrl_frame(start, FrameKind::Block, params, results);
                 else_body.validate(ctx)?;
-                if let Some(ty) = ty {
-                    ctx.ensure_op_stack_top(Type::Known(*ty))?;
-                }
-                ctx.pop_control_frame(saved);
+                ctx.current_op = self.kind.name();
+                ctx.current_offset = start;
+                ctx.pop_ctrl_frame()?;
 
-                ctx.pop_label_stack()?;
-                if let Some(ty) = ty {
-                    ctx.ensure_op_stack_top(Type::Known(*ty))?;
-                }
+                ctx.push_op_stack_types(results);
             }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-unreachable
-            Unreachable => ctx.unreachable = true,
+            Unreachable => ctx.set_unreachable(),
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-nop
             Nop => {}
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-br
             Br(labelidx) => {
-                ctx.validate_label_idx(*labelidx)?;
-                ctx.unreachable = true;
+                let types = ctx.validate_label_idx(*labelidx)?;
+                ctx.pop_op_stack_types(types)?;
+                ctx.set_unreachable();
             }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-br-if
             BrIf(labelidx) => {
                 // Condition
                 ctx.pop_op_stack(Type::i32())?;
-                ctx.validate_label_idx(*labelidx)?;
+                let types = ctx.validate_label_idx(*labelidx)?;
+                ctx.pop_op_stack_types(types)?;
+                ctx.push_op_stack_types(types);
             }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-br-table
             BrTable {
                 labels,
                 default_label,
             } => {
+                // Index of label
+                ctx.pop_op_stack(Type::i32())?;
                 let expected = ctx.validate_label_idx(*default_label)?;
                 for (i, idx) in labels.iter().enumerate() {
-                    let ty = ctx.validate_label_idx(*idx)?;
-                    if let (Some(l), Some(r)) = (&expected, &ty) {
-                        if l != r {
-                            return ctx
-                                .error(ErrorKind::TypeMismatch {
-                                    expected: *l,
-                                    actual: *r,
-                                })
-                                .map_err(|e| {
-                                    e.update_msg(format!(
-                                        "{} label {} at {}",
-                                        Ordinal(i),
-                                        idx,
-                                        ctx.current_op
-                                    ))
-                                });
-                        }
+                    let types = ctx.validate_label_idx(*idx)?;
+                    if types.len() != expected.len() {
+                        return ctx
+                            .error(ErrorKind::ArityMismatch {
+                                expected: expected.len(),
+                                actual: types.len(),
+                                what: "label types",
+                            })
+                            .map_err(|e| {
+                                e.update_msg(format!(
+                                    "{} label {} at {}",
+                                    Ordinal(i),
+                                    idx,
+                                    ctx.current_op
+                                ))
+                            });
+                    }
+                    // Operands are checked without consuming them since they are also checked
+                    // with types of other labels
+                    let mut popped = Vec::with_capacity(types.len());
+                    for ty in types.iter().rev() {
+                        let ty = ctx.pop_op_stack(Type::Known(*ty)).map_err(|e| {
+                            e.update_msg(format!(
+                                "{} label {} at {}",
+                                Ordinal(i),
+                                idx,
+                                ctx.current_op
+                            ))
+                        })?;
+                        popped.push(ty);
                     }
+                    ctx.op_stack.extend(popped.into_iter().rev());
                 }
-                ctx.unreachable = true;
+                ctx.pop_op_stack_types(expected)?;
+                ctx.set_unreachable();
             }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-return
             Return => {
-                if let Some(ty) = ctx.ret_ty {
-                    ctx.ensure_op_stack_top(Type::Known(ty))?;
-                }
-                ctx.unreachable = true;
+                ctx.pop_op_stack_types(ctx.results)?;
+                ctx.set_unreachable();
             }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-call
             Call(funcidx) => {
@@ -387,8 +594,14 @@ impl<'outer, 'm, 's, S: Source> ValidateInsnSeq<'outer, 'm, 's, S> for Instructi
                 }
             }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-call-indirect
-            CallIndirect(typeidx) => {
-                ctx.outer.table_from_idx(0, ctx.current_op, start)?;
+            CallIndirect { table, ty: typeidx } => {
+                let elem = ctx.validate_table_idx(*table)?;
+                if elem != ValType::FuncRef {
+                    return ctx.error(ErrorKind::TypeMismatch {
+                        expected: ValType::FuncRef,
+                        actual: elem,
+                    });
+                }
                 // Check table index
                 ctx.pop_op_stack(Type::i32())?;
                 let fty = ctx.outer.type_from_idx(*typeidx, ctx.current_op, start)?;
@@ -402,18 +615,143 @@ impl<'outer, 'm, 's, S: Source> ValidateInsnSeq<'outer, 'm, 's, S> for Instructi
                     ctx.op_stack.push(Type::Known(*ty));
                 }
             }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-return-call
+            ReturnCall(funcidx) => {
+                let func = ctx.outer.func_from_idx(*funcidx, ctx.current_op, start)?;
+                // func.idx was already validated
+                let fty = &ctx.outer.module.types[func.idx as usize];
+                ctx.validate_tail_call(fty, "return_call")?;
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-return-call-indirect
+            ReturnCallIndirect { table, ty: typeidx } => {
+                let elem = ctx.validate_table_idx(*table)?;
+                if elem != ValType::FuncRef {
+                    return ctx.error(ErrorKind::TypeMismatch {
+                        expected: ValType::FuncRef,
+                        actual: elem,
+                    });
+                }
+                // Check table index
+                ctx.pop_op_stack(Type::i32())?;
+                let fty = ctx.outer.type_from_idx(*typeidx, ctx.current_op, start)?;
+                ctx.validate_tail_call(fty, "return_call_indirect")?;
+            }
+            // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#try-catch-blocks
+            Try { ty, body, handler } => {
+                let (params, results) = ctx.validate_block_type(ty)?;
+                ctx.pop_op_stack_types(params)?;
+                ctx.push_ctrl_frame(start, FrameKind::Block, params, results);
+                body.validate(ctx)?;
+                ctx.current_op = self.kind.name();
+                ctx.current_offset = start;
+                ctx.pop_ctrl_frame()?;
+
+                match handler {
+                    TryHandler::Catch { catches, catch_all } => {
+                        // Each 'catch' clause starts with the values of exception thrown with the tag
+                        for catch in catches.iter() {
+                            let tag = ctx.outer.tag_from_idx(catch.tag, "catch", catch.start)?;
+                            // tag.idx was already validated
+                            let fty = &ctx.outer.module.types[tag.idx as usize];
+                            ctx.push_ctrl_frame(
+                                catch.start,
+                                FrameKind::Catch,
+                                &fty.params,
+                                results,
+                            );
+                            catch.body.validate(ctx)?;
+                            ctx.current_op = "catch";
+                            ctx.current_offset = catch.start;
+                            ctx.pop_ctrl_frame()?;
+                        }
+                        if let Some(body) = catch_all {
+                            ctx.push_ctrl_frame(start, FrameKind::Catch, &[], results);
+                            body.validate(ctx)?;
+                            ctx.current_op = "catch_all";
+                            ctx.current_offset = start;
+                            ctx.pop_ctrl_frame()?;
+                        }
+                    }
+                    // Label of 'delegate' is resolved outside the 'try' block
+                    TryHandler::Delegate(labelidx) => {
+                        ctx.validate_label_idx(*labelidx)?;
+                    }
+                }
+
+                ctx.push_op_stack_types(results);
+            }
+            // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#throwing-an-exception
+            Throw(tagidx) => {
+                let tag = ctx.outer.tag_from_idx(*tagidx, ctx.current_op, start)?;
+                // tag.idx was already validated
+                let fty = &ctx.outer.module.types[tag.idx as usize];
+                // Pop extracts parameters in reverse order
+                for (i, ty) in fty.params.iter().enumerate().rev() {
+                    ctx.pop_op_stack(Type::Known(*ty))
+                        .map_err(|e| e.update_msg(format!("{} value at throw", Ordinal(i))))?;
+                }
+                ctx.set_unreachable();
+            }
+            // https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#rethrows
+            Rethrow(labelidx) => {
+                if ctx.frame_from_label_idx(*labelidx)?.kind != FrameKind::Catch {
+                    return ctx.error(ErrorKind::NotCatchLabel(*labelidx));
+                }
+                ctx.set_unreachable();
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-ref-null
+            RefNull(ty) => ctx.op_stack.push(Type::Known((*ty).into())),
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-ref-is-null
+            RefIsNull => {
+                if let Type::Known(ty) = ctx.pop_op_stack(Type::Unknown)? {
+                    if !ty.is_ref() {
+                        return ctx.error(ErrorKind::NotReferenceType(ty));
+                    }
+                }
+                ctx.op_stack.push(Type::i32());
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-ref-func
+            RefFunc(funcidx) => {
+                ctx.outer.func_from_idx(*funcidx, ctx.current_op, start)?;
+                if !ctx.outer.refs.contains(funcidx) {
+                    return ctx.error(ErrorKind::UndeclaredFuncRef(*funcidx));
+                }
+                ctx.op_stack.push(Type::Known(ValType::FuncRef));
+            }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-drop
             Drop => {
                 ctx.pop_op_stack(Type::Unknown)?;
             }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-select
             Select => {
-                ctx.pop_op_stack(Type::Unknown)?;
-                ctx.pop_op_stack(Type::Unknown)?;
                 ctx.pop_op_stack(Type::i32())?;
-                // 'select' instruction is value-polymorphic. The value pushed here is
-                // one of the first or second value. The value is checked dynamically
-                ctx.op_stack.push(Type::Unknown);
+                // 'select' instruction is value-polymorphic. Both operands must have the same type
+                let ty = ctx.pop_op_stack(Type::Unknown)?;
+                let ty = match ctx.pop_op_stack(ty)? {
+                    Type::Unknown => ty,
+                    actual => actual,
+                };
+                // Reference types are only allowed with explicit result type
+                if let Type::Known(t) = ty {
+                    if t.is_ref() {
+                        return ctx.error(ErrorKind::ReferenceTypeForSelect(t));
+                    }
+                }
+                ctx.op_stack.push(ty);
+            }
+            TypedSelect(types) => {
+                if types.len() != 1 {
+                    return ctx.error(ErrorKind::ArityMismatch {
+                        expected: 1,
+                        actual: types.len(),
+                        what: "result types of 'select'",
+                    });
+                }
+                let ty = Type::Known(types[0]);
+                ctx.pop_op_stack(Type::i32())?;
+                ctx.pop_op_stack(ty)?;
+                ctx.pop_op_stack(ty)?;
+                ctx.op_stack.push(ty);
             }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-local-get
             LocalGet(localidx) => {
@@ -480,19 +818,96 @@ impl<'outer, 'm, 's, S: Source> ValidateInsnSeq<'outer, 'm, 's, S> for Instructi
             I64Store16(mem) => ctx.validate_store(mem, 16, ValType::I64)?,
             I64Store32(mem) => ctx.validate_store(mem, 32, ValType::I64)?,
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-memory-size
-            MemorySize => {
-                if ctx.outer.module.memories.is_empty() {
-                    return ctx.error(ErrorKind::MemoryIsNotDefined);
-                }
-                ctx.op_stack.push(Type::i32());
+            MemorySize(memidx) => {
+                let ty = ctx.validate_mem_idx(*memidx)?;
+                ctx.op_stack.push(Type::Known(ty));
             }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-memory-grow
-            MemoryGrow => {
-                if ctx.outer.module.memories.is_empty() {
-                    return ctx.error(ErrorKind::MemoryIsNotDefined);
+            MemoryGrow(memidx) => {
+                let ty = ctx.validate_mem_idx(*memidx)?;
+                // pop i32 and push i32 (i64 for 64-bit memory)
+                ctx.ensure_op_stack_top(Type::Known(ty))?;
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-memory-init
+            MemoryInit { memory, data } => {
+                let ty = ctx.validate_mem_idx(*memory)?;
+                ctx.outer.data_from_idx(*data, ctx.current_op, start)?;
+                ctx.validate_bulk_operands_of(ty, ValType::I32, ValType::I32)?;
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-data-drop
+            DataDrop(dataidx) => {
+                ctx.outer.data_from_idx(*dataidx, ctx.current_op, start)?;
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-memory-copy
+            MemoryCopy { dst, src } => {
+                let dst = ctx.validate_mem_idx(*dst)?;
+                let src = ctx.validate_mem_idx(*src)?;
+                // Length is i64 only when both memories are 64-bit
+                let len = if dst == ValType::I64 && src == ValType::I64 {
+                    ValType::I64
+                } else {
+                    ValType::I32
+                };
+                ctx.validate_bulk_operands_of(dst, src, len)?;
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-memory-fill
+            MemoryFill(memidx) => {
+                let ty = ctx.validate_mem_idx(*memidx)?;
+                ctx.validate_bulk_operands_of(ty, ValType::I32, ty)?;
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-table-get
+            TableGet(tableidx) => {
+                let elem = ctx.validate_table_idx(*tableidx)?;
+                ctx.pop_op_stack(Type::i32())?;
+                ctx.op_stack.push(Type::Known(elem));
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-table-set
+            TableSet(tableidx) => {
+                let elem = ctx.validate_table_idx(*tableidx)?;
+                ctx.pop_op_stack(Type::Known(elem))?;
+                ctx.pop_op_stack(Type::i32())?;
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-table-size
+            TableSize(tableidx) => {
+                ctx.validate_table_idx(*tableidx)?;
+                ctx.op_stack.push(Type::i32());
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-table-grow
+            TableGrow(tableidx) => {
+                let elem = ctx.validate_table_idx(*tableidx)?;
+                ctx.pop_op_stack(Type::i32())?; // delta
+                ctx.pop_op_stack(Type::Known(elem))?; // initial value
+                ctx.op_stack.push(Type::i32());
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-table-fill
+            TableFill(tableidx) => {
+                let elem = ctx.validate_table_idx(*tableidx)?;
+                ctx.pop_op_stack(Type::i32())?; // length
+                ctx.pop_op_stack(Type::Known(elem))?; // fill value
+                ctx.pop_op_stack(Type::i32())?; // destination
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-table-copy
+            TableCopy { dst, src } => {
+                let expected = ctx.validate_table_idx(*dst)?;
+                let actual = ctx.validate_table_idx(*src)?;
+                if expected != actual {
+                    return ctx.error(ErrorKind::TypeMismatch { expected, actual });
                 }
-                // pop i32 and push i32
-                ctx.ensure_op_stack_top(Type::i32())?;
+                ctx.validate_bulk_operands()?;
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-table-init
+            TableInit { table, elem } => {
+                let expected = ctx.validate_table_idx(*table)?;
+                let seg = ctx.outer.elem_from_idx(*elem, ctx.current_op, start)?;
+                let actual = seg.ty.into();
+                if expected != actual {
+                    return ctx.error(ErrorKind::TypeMismatch { expected, actual });
+                }
+                ctx.validate_bulk_operands()?;
+            }
+            // https://webassembly.github.io/spec/core/valid/instructions.html#valid-elem-drop
+            ElemDrop(elemidx) => {
+                ctx.outer.elem_from_idx(*elemidx, ctx.current_op, start)?;
             }
             I32Const(_) => {
                 ctx.op_stack.push(Type::i32());
@@ -508,10 +923,10 @@ impl<'outer, 'm, 's, S: Source> ValidateInsnSeq<'outer, 'm, 's, S> for Instructi
             }
             // https://webassembly.github.io/spec/core/valid/instructions.html#valid-unop
             // [t] -> [t]
-            I32Clz | I32Ctz | I32Popcnt => {
+            I32Clz | I32Ctz | I32Popcnt | I32Extend8S | I32Extend16S => {
                 ctx.ensure_op_stack_top(Type::i32())?;
             }
-            I64Clz | I64Ctz | I64Popcnt => {
+            I64Clz | I64Ctz | I64Popcnt | I64Extend8S | I64Extend16S | I64Extend32S => {
                 ctx.ensure_op_stack_top(Type::i64())?;
             }
             F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt => {
@@ -599,12 +1014,341 @@ impl<'outer, 'm, 's, S: Source> ValidateInsnSeq<'outer, 'm, 's, S> for Instructi
             I64ReinterpretF64 => ctx.validate_convert(ValType::F64, ValType::I64)?,
             F32ReinterpretI32 => ctx.validate_convert(ValType::I32, ValType::F32)?,
             F64ReinterpretI64 => ctx.validate_convert(ValType::I64, ValType::F64)?,
+            I32TruncSatF32S | I32TruncSatF32U => {
+                ctx.validate_convert(ValType::F32, ValType::I32)?
+            }
+            I32TruncSatF64S | I32TruncSatF64U => {
+                ctx.validate_convert(ValType::F64, ValType::I32)?
+            }
+            I64TruncSatF32S | I64TruncSatF32U => {
+                ctx.validate_convert(ValType::F32, ValType::I64)?
+            }
+            I64TruncSatF64S | I64TruncSatF64U => {
+                ctx.validate_convert(ValType::F64, ValType::I64)?
+            }
+            // Vector instructions
+            // https://webassembly.github.io/spec/core/valid/instructions.html#vector-instructions
+            V128Load(mem) => ctx.validate_load(mem, 128, ValType::V128)?,
+            V128Load8x8S(mem) | V128Load8x8U(mem) | V128Load16x4S(mem) | V128Load16x4U(mem)
+            | V128Load32x2S(mem) | V128Load32x2U(mem) | V128Load64Splat(mem)
+            | V128Load64Zero(mem) => ctx.validate_load(mem, 64, ValType::V128)?,
+            V128Load8Splat(mem) => ctx.validate_load(mem, 8, ValType::V128)?,
+            V128Load16Splat(mem) => ctx.validate_load(mem, 16, ValType::V128)?,
+            V128Load32Splat(mem) | V128Load32Zero(mem) => {
+                ctx.validate_load(mem, 32, ValType::V128)?
+            }
+            V128Store(mem) => ctx.validate_store(mem, 128, ValType::V128)?,
+            V128Load8Lane { mem, lane } => ctx.validate_load_lane(mem, *lane, 8)?,
+            V128Load16Lane { mem, lane } => ctx.validate_load_lane(mem, *lane, 16)?,
+            V128Load32Lane { mem, lane } => ctx.validate_load_lane(mem, *lane, 32)?,
+            V128Load64Lane { mem, lane } => ctx.validate_load_lane(mem, *lane, 64)?,
+            V128Store8Lane { mem, lane } => ctx.validate_store_lane(mem, *lane, 8)?,
+            V128Store16Lane { mem, lane } => ctx.validate_store_lane(mem, *lane, 16)?,
+            V128Store32Lane { mem, lane } => ctx.validate_store_lane(mem, *lane, 32)?,
+            V128Store64Lane { mem, lane } => ctx.validate_store_lane(mem, *lane, 64)?,
+            V128Const(_) => {
+                ctx.op_stack.push(Type::v128());
+            }
+            I8x16Shuffle(lanes) => {
+                for lane in lanes {
+                    ctx.validate_lane_idx(*lane, 32)?;
+                }
+                ctx.pop_op_stack(Type::v128())?;
+                ctx.ensure_op_stack_top(Type::v128())?;
+            }
+            // [t] -> [v128]
+            I8x16Splat | I16x8Splat | I32x4Splat => {
+                ctx.validate_convert(ValType::I32, ValType::V128)?
+            }
+            I64x2Splat => ctx.validate_convert(ValType::I64, ValType::V128)?,
+            F32x4Splat => ctx.validate_convert(ValType::F32, ValType::V128)?,
+            F64x2Splat => ctx.validate_convert(ValType::F64, ValType::V128)?,
+            I8x16ExtractLaneS(lane) | I8x16ExtractLaneU(lane) => {
+                ctx.validate_extract_lane(*lane, 16, ValType::I32)?
+            }
+            I8x16ReplaceLane(lane) => ctx.validate_replace_lane(*lane, 16, ValType::I32)?,
+            I16x8ExtractLaneS(lane) | I16x8ExtractLaneU(lane) => {
+                ctx.validate_extract_lane(*lane, 8, ValType::I32)?
+            }
+            I16x8ReplaceLane(lane) => ctx.validate_replace_lane(*lane, 8, ValType::I32)?,
+            I32x4ExtractLane(lane) => ctx.validate_extract_lane(*lane, 4, ValType::I32)?,
+            I32x4ReplaceLane(lane) => ctx.validate_replace_lane(*lane, 4, ValType::I32)?,
+            I64x2ExtractLane(lane) => ctx.validate_extract_lane(*lane, 2, ValType::I64)?,
+            I64x2ReplaceLane(lane) => ctx.validate_replace_lane(*lane, 2, ValType::I64)?,
+            F32x4ExtractLane(lane) => ctx.validate_extract_lane(*lane, 4, ValType::F32)?,
+            F32x4ReplaceLane(lane) => ctx.validate_replace_lane(*lane, 4, ValType::F32)?,
+            F64x2ExtractLane(lane) => ctx.validate_extract_lane(*lane, 2, ValType::F64)?,
+            F64x2ReplaceLane(lane) => ctx.validate_replace_lane(*lane, 2, ValType::F64)?,
+            // [v128] -> [v128]
+            V128Not
+            | F32x4DemoteF64x2Zero
+            | F64x2PromoteLowF32x4
+            | I8x16Abs
+            | I8x16Neg
+            | I8x16Popcnt
+            | F32x4Ceil
+            | F32x4Floor
+            | F32x4Trunc
+            | F32x4Nearest
+            | F64x2Ceil
+            | F64x2Floor
+            | F64x2Trunc
+            | I16x8ExtaddPairwiseI8x16S
+            | I16x8ExtaddPairwiseI8x16U
+            | I32x4ExtaddPairwiseI16x8S
+            | I32x4ExtaddPairwiseI16x8U
+            | I16x8Abs
+            | I16x8Neg
+            | I16x8ExtendLowI8x16S
+            | I16x8ExtendHighI8x16S
+            | I16x8ExtendLowI8x16U
+            | I16x8ExtendHighI8x16U
+            | F64x2Nearest
+            | I32x4Abs
+            | I32x4Neg
+            | I32x4ExtendLowI16x8S
+            | I32x4ExtendHighI16x8S
+            | I32x4ExtendLowI16x8U
+            | I32x4ExtendHighI16x8U
+            | I64x2Abs
+            | I64x2Neg
+            | I64x2ExtendLowI32x4S
+            | I64x2ExtendHighI32x4S
+            | I64x2ExtendLowI32x4U
+            | I64x2ExtendHighI32x4U
+            | F32x4Abs
+            | F32x4Neg
+            | F32x4Sqrt
+            | F64x2Abs
+            | F64x2Neg
+            | F64x2Sqrt
+            | I32x4TruncSatF32x4S
+            | I32x4TruncSatF32x4U
+            | F32x4ConvertI32x4S
+            | F32x4ConvertI32x4U
+            | I32x4TruncSatF64x2SZero
+            | I32x4TruncSatF64x2UZero
+            | F64x2ConvertLowI32x4S
+            | F64x2ConvertLowI32x4U => {
+                ctx.ensure_op_stack_top(Type::v128())?;
+            }
+            // [v128 v128] -> [v128]
+            I8x16Swizzle
+            | I8x16Eq
+            | I8x16Ne
+            | I8x16LtS
+            | I8x16LtU
+            | I8x16GtS
+            | I8x16GtU
+            | I8x16LeS
+            | I8x16LeU
+            | I8x16GeS
+            | I8x16GeU
+            | I16x8Eq
+            | I16x8Ne
+            | I16x8LtS
+            | I16x8LtU
+            | I16x8GtS
+            | I16x8GtU
+            | I16x8LeS
+            | I16x8LeU
+            | I16x8GeS
+            | I16x8GeU
+            | I32x4Eq
+            | I32x4Ne
+            | I32x4LtS
+            | I32x4LtU
+            | I32x4GtS
+            | I32x4GtU
+            | I32x4LeS
+            | I32x4LeU
+            | I32x4GeS
+            | I32x4GeU
+            | F32x4Eq
+            | F32x4Ne
+            | F32x4Lt
+            | F32x4Gt
+            | F32x4Le
+            | F32x4Ge
+            | F64x2Eq
+            | F64x2Ne
+            | F64x2Lt
+            | F64x2Gt
+            | F64x2Le
+            | F64x2Ge
+            | V128And
+            | V128Andnot
+            | V128Or
+            | V128Xor
+            | I8x16NarrowI16x8S
+            | I8x16NarrowI16x8U
+            | I8x16Add
+            | I8x16AddSatS
+            | I8x16AddSatU
+            | I8x16Sub
+            | I8x16SubSatS
+            | I8x16SubSatU
+            | I8x16MinS
+            | I8x16MinU
+            | I8x16MaxS
+            | I8x16MaxU
+            | I8x16AvgrU
+            | I16x8Q15mulrSatS
+            | I16x8NarrowI32x4S
+            | I16x8NarrowI32x4U
+            | I16x8Add
+            | I16x8AddSatS
+            | I16x8AddSatU
+            | I16x8Sub
+            | I16x8SubSatS
+            | I16x8SubSatU
+            | I16x8Mul
+            | I16x8MinS
+            | I16x8MinU
+            | I16x8MaxS
+            | I16x8MaxU
+            | I16x8AvgrU
+            | I16x8ExtmulLowI8x16S
+            | I16x8ExtmulHighI8x16S
+            | I16x8ExtmulLowI8x16U
+            | I16x8ExtmulHighI8x16U
+            | I32x4Add
+            | I32x4Sub
+            | I32x4Mul
+            | I32x4MinS
+            | I32x4MinU
+            | I32x4MaxS
+            | I32x4MaxU
+            | I32x4DotI16x8S
+            | I32x4ExtmulLowI16x8S
+            | I32x4ExtmulHighI16x8S
+            | I32x4ExtmulLowI16x8U
+            | I32x4ExtmulHighI16x8U
+            | I64x2Add
+            | I64x2Sub
+            | I64x2Mul
+            | I64x2Eq
+            | I64x2Ne
+            | I64x2LtS
+            | I64x2GtS
+            | I64x2LeS
+            | I64x2GeS
+            | I64x2ExtmulLowI32x4S
+            | I64x2ExtmulHighI32x4S
+            | I64x2ExtmulLowI32x4U
+            | I64x2ExtmulHighI32x4U
+            | F32x4Add
+            | F32x4Sub
+            | F32x4Mul
+            | F32x4Div
+            | F32x4Min
+            | F32x4Max
+            | F32x4Pmin
+            | F32x4Pmax
+            | F64x2Add
+            | F64x2Sub
+            | F64x2Mul
+            | F64x2Div
+            | F64x2Min
+            | F64x2Max
+            | F64x2Pmin
+            | F64x2Pmax => {
+                ctx.pop_op_stack(Type::v128())?;
+                ctx.ensure_op_stack_top(Type::v128())?;
+            }
+            // [v128 v128 v128] -> [v128]
+            V128Bitselect => {
+                ctx.pop_op_stack(Type::v128())?;
+                ctx.pop_op_stack(Type::v128())?;
+                ctx.ensure_op_stack_top(Type::v128())?;
+            }
+            // [v128] -> [i32]
+            V128AnyTrue | I8x16AllTrue | I8x16Bitmask | I16x8AllTrue | I16x8Bitmask
+            | I32x4AllTrue | I32x4Bitmask | I64x2AllTrue | I64x2Bitmask => {
+                ctx.validate_convert(ValType::V128, ValType::I32)?
+            }
+            // [v128 i32] -> [v128]
+            I8x16Shl | I8x16ShrS | I8x16ShrU | I16x8Shl | I16x8ShrS | I16x8ShrU | I32x4Shl
+            | I32x4ShrS | I32x4ShrU | I64x2Shl | I64x2ShrS | I64x2ShrU => {
+                ctx.pop_op_stack(Type::i32())?;
+                ctx.ensure_op_stack_top(Type::v128())?;
+            }
+            // Atomic instructions
+            // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md
+            MemoryAtomicNotify(mem) => ctx.validate_atomic_notify(mem)?,
+            MemoryAtomicWait32(mem) => ctx.validate_atomic_wait(mem, 32, ValType::I32)?,
+            MemoryAtomicWait64(mem) => ctx.validate_atomic_wait(mem, 64, ValType::I64)?,
+            AtomicFence => {}
+            I32AtomicLoad(mem) => ctx.validate_load(mem, 32, ValType::I32)?,
+            I64AtomicLoad(mem) => ctx.validate_load(mem, 64, ValType::I64)?,
+            I32AtomicLoad8U(mem) => ctx.validate_load(mem, 8, ValType::I32)?,
+            I32AtomicLoad16U(mem) => ctx.validate_load(mem, 16, ValType::I32)?,
+            I64AtomicLoad8U(mem) => ctx.validate_load(mem, 8, ValType::I64)?,
+            I64AtomicLoad16U(mem) => ctx.validate_load(mem, 16, ValType::I64)?,
+            I64AtomicLoad32U(mem) => ctx.validate_load(mem, 32, ValType::I64)?,
+            I32AtomicStore(mem) => ctx.validate_store(mem, 32, ValType::I32)?,
+            I64AtomicStore(mem) => ctx.validate_store(mem, 64, ValType::I64)?,
+            I32AtomicStore8(mem) => ctx.validate_store(mem, 8, ValType::I32)?,
+            I32AtomicStore16(mem) => ctx.validate_store(mem, 16, ValType::I32)?,
+            I64AtomicStore8(mem) => ctx.validate_store(mem, 8, ValType::I64)?,
+            I64AtomicStore16(mem) => ctx.validate_store(mem, 16, ValType::I64)?,
+            I64AtomicStore32(mem) => ctx.validate_store(mem, 32, ValType::I64)?,
+            I32AtomicRmwAdd(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I32)?,
+            I64AtomicRmwAdd(mem) => ctx.validate_atomic_rmw(mem, 64, ValType::I64)?,
+            I32AtomicRmw8AddU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I32)?,
+            I32AtomicRmw16AddU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I32)?,
+            I64AtomicRmw8AddU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I64)?,
+            I64AtomicRmw16AddU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I64)?,
+            I64AtomicRmw32AddU(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I64)?,
+            I32AtomicRmwSub(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I32)?,
+            I64AtomicRmwSub(mem) => ctx.validate_atomic_rmw(mem, 64, ValType::I64)?,
+            I32AtomicRmw8SubU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I32)?,
+            I32AtomicRmw16SubU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I32)?,
+            I64AtomicRmw8SubU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I64)?,
+            I64AtomicRmw16SubU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I64)?,
+            I64AtomicRmw32SubU(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I64)?,
+            I32AtomicRmwAnd(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I32)?,
+            I64AtomicRmwAnd(mem) => ctx.validate_atomic_rmw(mem, 64, ValType::I64)?,
+            I32AtomicRmw8AndU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I32)?,
+            I32AtomicRmw16AndU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I32)?,
+            I64AtomicRmw8AndU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I64)?,
+            I64AtomicRmw16AndU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I64)?,
+            I64AtomicRmw32AndU(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I64)?,
+            I32AtomicRmwOr(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I32)?,
+            I64AtomicRmwOr(mem) => ctx.validate_atomic_rmw(mem, 64, ValType::I64)?,
+            I32AtomicRmw8OrU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I32)?,
+            I32AtomicRmw16OrU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I32)?,
+            I64AtomicRmw8OrU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I64)?,
+            I64AtomicRmw16OrU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I64)?,
+            I64AtomicRmw32OrU(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I64)?,
+            I32AtomicRmwXor(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I32)?,
+            I64AtomicRmwXor(mem) => ctx.validate_atomic_rmw(mem, 64, ValType::I64)?,
+            I32AtomicRmw8XorU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I32)?,
+            I32AtomicRmw16XorU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I32)?,
+            I64AtomicRmw8XorU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I64)?,
+            I64AtomicRmw16XorU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I64)?,
+            I64AtomicRmw32XorU(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I64)?,
+            I32AtomicRmwXchg(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I32)?,
+            I64AtomicRmwXchg(mem) => ctx.validate_atomic_rmw(mem, 64, ValType::I64)?,
+            I32AtomicRmw8XchgU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I32)?,
+            I32AtomicRmw16XchgU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I32)?,
+            I64AtomicRmw8XchgU(mem) => ctx.validate_atomic_rmw(mem, 8, ValType::I64)?,
+            I64AtomicRmw16XchgU(mem) => ctx.validate_atomic_rmw(mem, 16, ValType::I64)?,
+            I64AtomicRmw32XchgU(mem) => ctx.validate_atomic_rmw(mem, 32, ValType::I64)?,
+            I32AtomicRmwCmpxchg(mem) => ctx.validate_atomic_cmpxchg(mem, 32, ValType::I32)?,
+            I64AtomicRmwCmpxchg(mem) => ctx.validate_atomic_cmpxchg(mem, 64, ValType::I64)?,
+            I32AtomicRmw8CmpxchgU(mem) => ctx.validate_atomic_cmpxchg(mem, 8, ValType::I32)?,
+            I32AtomicRmw16CmpxchgU(mem) => ctx.validate_atomic_cmpxchg(mem, 16, ValType::I32)?,
+            I64AtomicRmw8CmpxchgU(mem) => ctx.validate_atomic_cmpxchg(mem, 8, ValType::I64)?,
+            I64AtomicRmw16CmpxchgU(mem) => ctx.validate_atomic_cmpxchg(mem, 16, ValType::I64)?,
+            I64AtomicRmw32CmpxchgU(mem) => ctx.validate_atomic_cmpxchg(mem, 32, ValType::I64)?,
         }
         Ok(())
     }
 }
 
 // https://webassembly.github.io/spec/core/valid/instructions.html#constant-expressions
+// https://github.com/WebAssembly/extended-const/blob/main/proposals/extended-const/Overview.md
 pub(crate) fn validate_constant<'m, 's, S: Source>(
     insns: &[Instruction],
     ctx: &OuterContext<'m, 's, S>,
@@ -612,21 +1356,66 @@ pub(crate) fn validate_constant<'m, 's, S: Source>(
     when: &'static str,
     start: usize,
 ) -> Result<(), S> {
-    let mut last_ty = None;
+    if insns.is_empty() {
+        return ctx.error(ErrorKind::NoInstructionForConstant, when, start);
+    }
+
+    // Constant expression has no control instruction so operand types can be tracked with a
+    // simple stack
+    let mut stack = vec![];
     for insn in insns {
         let name = insn.kind.name();
         use InsnKind::*;
-        match &insn.kind {
+        let ty = match &insn.kind {
             GlobalGet(globalidx) => {
-                if let Some(global) = ctx.module.globals.get(*globalidx as usize) {
-                    last_ty = Some(global.ty);
-                } else {
+                let global = match ctx.module.globals.get(*globalidx as usize) {
+                    Some(global) => global,
+                    None => {
+                        return ctx
+                            .error(
+                                ErrorKind::IndexOutOfBounds {
+                                    idx: *globalidx,
+                                    upper: ctx.module.globals.len(),
+                                    what: "global variable read",
+                                },
+                                "",
+                                insn.start,
+                            )
+                            .map_err(|e| {
+                                e.update_msg(format!("constant expression in {} at {}", name, when))
+                            });
+                    }
+                };
+                // Only immutable imported global variables are constant
+                if global.mutable || !matches!(global.kind, GlobalKind::Import(_)) {
+                    return ctx
+                        .error(ErrorKind::NotConstantGlobal(*globalidx), "", insn.start)
+                        .map_err(|e| e.update_msg(format!("constant expression at {}", when)));
+                }
+                global.ty
+            }
+            RefFunc(funcidx) => {
+                ctx.func_from_idx(*funcidx, when, insn.start)?;
+                ValType::FuncRef
+            }
+            RefNull(ty) => (*ty).into(),
+            I32Const(_) => ValType::I32,
+            I64Const(_) => ValType::I64,
+            F32Const(_) => ValType::F32,
+            F64Const(_) => ValType::F64,
+            V128Const(_) => ValType::V128,
+            I32Add | I32Sub | I32Mul | I64Add | I64Sub | I64Mul => {
+                let ty = match &insn.kind {
+                    I32Add | I32Sub | I32Mul => ValType::I32,
+                    _ => ValType::I64,
+                };
+                if stack.len() < 2 {
                     return ctx
                         .error(
-                            ErrorKind::IndexOutOfBounds {
-                                idx: *globalidx,
-                                upper: ctx.module.globals.len(),
-                                what: "global variable read",
+                            ErrorKind::ArityMismatch {
+                                expected: 2,
+                                actual: stack.len(),
+                                what: "operands",
                             },
                             "",
                             insn.start,
@@ -635,34 +1424,56 @@ pub(crate) fn validate_constant<'m, 's, S: Source>(
                             e.update_msg(format!("constant expression in {} at {}", name, when))
                         });
                 }
+                for _ in 0..2 {
+                    let actual = stack.pop().unwrap();
+                    if actual != ty {
+                        return ctx
+                            .error(
+                                ErrorKind::TypeMismatch {
+                                    expected: ty,
+                                    actual,
+                                },
+                                "",
+                                insn.start,
+                            )
+                            .map_err(|e| {
+                                e.update_msg(format!("constant expression in {} at {}", name, when))
+                            });
+                    }
+                }
+                ty
             }
-            I32Const(_) => last_ty = Some(ValType::I32),
-            I64Const(_) => last_ty = Some(ValType::I64),
-            F32Const(_) => last_ty = Some(ValType::F32),
-            F64Const(_) => last_ty = Some(ValType::F64),
             _ => {
                 return ctx
                     .error(ErrorKind::NotConstantInstruction(name), "", insn.start)
                     .map_err(|e| e.update_msg(format!("constant expression at {}", when)));
             }
-        }
+        };
+        stack.push(ty);
     }
 
-    if let Some(ty) = last_ty {
-        if ty != expr_ty {
-            ctx.error(
+    match stack.as_slice() {
+        [ty] if *ty == expr_ty => Ok(()),
+        [ty] => ctx
+            .error(
                 ErrorKind::TypeMismatch {
                     expected: expr_ty,
-                    actual: ty,
+                    actual: *ty,
                 },
                 "",
                 start,
             )
-            .map_err(|e| e.update_msg(format!("type of constant expression at {}", when)))
-        } else {
-            Ok(())
-        }
-    } else {
-        ctx.error(ErrorKind::NoInstructionForConstant, when, start)
+            .map_err(|e| e.update_msg(format!("type of constant expression at {}", when))),
+        _ => ctx
+            .error(
+                ErrorKind::ArityMismatch {
+                    expected: 1,
+                    actual: stack.len(),
+                    what: "value",
+                },
+                "",
+                start,
+            )
+            .map_err(|e| e.update_msg(format!("result of constant expression at {}", when))),
     }
 }
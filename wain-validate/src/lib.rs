@@ -12,7 +12,7 @@ pub use error::{Error, Result};
 
 use error::ErrorKind;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use wain_ast::source::Source;
 use wain_ast::*;
 
@@ -21,6 +21,9 @@ use wain_ast::*;
 struct Context<'module, 'source: 'module, S: Source> {
     module: &'module Module<'source>,
     source: &'module S,
+    // Functions declared in the module outside function bodies. Only they can be referenced by
+    // 'ref.func' instructions in function bodies
+    refs: HashSet<FuncIdx>,
 }
 
 impl<'m, 's, S: Source> Context<'m, 's, S> {
@@ -85,16 +88,66 @@ impl<'m, 's, S: Source> Context<'m, 's, S> {
     ) -> Result<&'m Memory, S> {
         self.validate_idx(&self.module.memories, idx, "memory", when, offset)
     }
+
+    fn elem_from_idx(
+        &self,
+        idx: u32,
+        when: &'static str,
+        offset: usize,
+    ) -> Result<&'m ElemSegment, S> {
+        self.validate_idx(&self.module.elems, idx, "element segment", when, offset)
+    }
+
+    fn data_from_idx(
+        &self,
+        idx: u32,
+        when: &'static str,
+        offset: usize,
+    ) -> Result<&'m DataSegment<'s>, S> {
+        self.validate_idx(&self.module.data, idx, "data segment", when, offset)
+    }
+
+    fn tag_from_idx(&self, idx: u32, when: &'static str, offset: usize) -> Result<&'m Tag<'s>, S> {
+        self.validate_idx(&self.module.tags, idx, "tag", when, offset)
+    }
 }
 
 pub fn validate<'m, 's, S: Source>(root: &'m Root<'s, S>) -> Result<(), S> {
     let mut ctx = Context {
         module: &root.module,
         source: &root.source,
+        refs: declared_func_refs(&root.module),
     };
     root.module.validate(&mut ctx)
 }
 
+// Collect function indices which occur in the module except in functions and start function
+// https://webassembly.github.io/spec/core/valid/modules.html#valid-module
+fn declared_func_refs(module: &Module<'_>) -> HashSet<FuncIdx> {
+    let mut refs = HashSet::new();
+    let mut collect = |expr: &[Instruction]| {
+        for insn in expr {
+            if let InsnKind::RefFunc(idx) = insn.kind {
+                refs.insert(idx);
+            }
+        }
+    };
+    for elem in module.elems.iter() {
+        elem.init.iter().for_each(|expr| collect(expr));
+    }
+    for global in module.globals.iter() {
+        if let GlobalKind::Init(init) = &global.kind {
+            collect(init);
+        }
+    }
+    for export in module.exports.iter() {
+        if let ExportKind::Func(idx) = export.kind {
+            refs.insert(idx);
+        }
+    }
+    refs
+}
+
 trait Validate<'s, S: Source> {
     fn validate<'m>(&self, ctx: &mut Context<'m, 's, S>) -> Result<(), S>;
 }
@@ -118,6 +171,7 @@ impl<'s, S: Source, V: Validate<'s, S>> Validate<'s, S> for Option<V> {
 impl<'s, S: Source> Validate<'s, S> for Module<'s> {
     fn validate<'m>(&self, ctx: &mut Context<'m, 's, S>) -> Result<(), S> {
         self.types.validate(ctx)?;
+        self.tags.validate(ctx)?;
         self.funcs.validate(ctx)?;
         self.tables.validate(ctx)?;
         self.memories.validate(ctx)?;
@@ -127,21 +181,6 @@ impl<'s, S: Source> Validate<'s, S> for Module<'s> {
         self.entrypoint.validate(ctx)?;
         self.exports.validate(ctx)?;
 
-        if self.tables.len() > 1 {
-            return ctx.error(
-                ErrorKind::MultipleTables(self.tables.len()),
-                "tables in module",
-                self.start,
-            );
-        }
-        if self.memories.len() > 1 {
-            return ctx.error(
-                ErrorKind::MultipleMemories(self.memories.len()),
-                "memories in module",
-                self.start,
-            );
-        }
-
         // Export name in module must be unique
         let mut seen = HashMap::new();
         for (name, offset) in self.exports.iter().map(|e| (e.name.0.as_ref(), e.start)) {
@@ -162,17 +201,10 @@ impl<'s, S: Source> Validate<'s, S> for Module<'s> {
 }
 
 // https://webassembly.github.io/spec/core/valid/types.html#valid-functype
+// Function types are always valid since multi-value proposal allows multiple result types
 impl<'s, S: Source> Validate<'s, S> for FuncType {
-    fn validate<'m>(&self, ctx: &mut Context<'m, 's, S>) -> Result<(), S> {
-        if self.results.len() > 1 {
-            ctx.error(
-                ErrorKind::MultipleReturnTypes(self.results.clone()),
-                "result types in function type",
-                self.start,
-            )
-        } else {
-            Ok(())
-        }
+    fn validate<'m>(&self, _ctx: &mut Context<'m, 's, S>) -> Result<(), S> {
+        Ok(())
     }
 }
 
@@ -200,7 +232,8 @@ impl<'s, S: Source> Validate<'s, S> for Table<'s> {
 impl<'s, S: Source> Validate<'s, S> for Memory<'s> {
     fn validate<'m>(&self, ctx: &mut Context<'m, 's, S>) -> Result<(), S> {
         // https://webassembly.github.io/spec/core/valid/types.html#valid-memtype
-        let limit = 1 << 16;
+        // https://github.com/WebAssembly/memory64/blob/main/proposals/memory64/Overview.md#validation
+        let limit = if self.ty.memory64 { 1 << 48 } else { 1 << 16 };
         let invalid = match self.ty.limit {
             Limits::From(min) if min > limit => Some(min),
             Limits::Range(min, _) if min > limit => Some(min),
@@ -231,6 +264,17 @@ impl<'s, S: Source> Validate<'s, S> for Memory<'s> {
             }
         }
 
+        // https://github.com/WebAssembly/threads/blob/main/proposals/threads/Overview.md#shared-linear-memory
+        if self.ty.shared {
+            if let Limits::From(_) = self.ty.limit {
+                return ctx.error(
+                    ErrorKind::SharedMemoryWithoutMax,
+                    "shared memory type",
+                    self.start,
+                );
+            }
+        }
+
         Ok(())
     }
 }
@@ -254,19 +298,53 @@ impl<'s, S: Source> Validate<'s, S> for Global<'s> {
     }
 }
 
+// https://github.com/WebAssembly/exception-handling/blob/main/proposals/exception-handling/legacy/Exceptions.md#tag-definitions
+impl<'s, S: Source> Validate<'s, S> for Tag<'s> {
+    fn validate<'m>(&self, ctx: &mut Context<'m, 's, S>) -> Result<(), S> {
+        // Tag has a function type which describes values of exception. Exceptions have no result
+        let fty = ctx.type_from_idx(self.idx, "tag", self.start)?;
+        if !fty.results.is_empty() {
+            return ctx.error(
+                ErrorKind::TagResults(fty.results.clone()),
+                "type of tag",
+                self.start,
+            );
+        }
+        Ok(())
+    }
+}
+
 // https://webassembly.github.io/spec/core/valid/modules.html#element-segments
 impl<'s, S: Source> Validate<'s, S> for ElemSegment {
     fn validate<'m>(&self, ctx: &mut Context<'m, 's, S>) -> Result<(), S> {
-        ctx.table_from_idx(self.idx, "element segment", self.start)?;
-        crate::insn::validate_constant(
-            &self.offset,
-            ctx,
-            ValType::I32,
-            "offset expression in element segment",
-            self.start,
-        )?;
-        for funcidx in self.init.iter() {
-            ctx.func_from_idx(*funcidx, "init in element segment", self.start)?;
+        if let ElemMode::Active { idx, offset } = &self.mode {
+            let table = ctx.table_from_idx(*idx, "element segment", self.start)?;
+            if table.ty.elem != self.ty {
+                return ctx.error(
+                    ErrorKind::TypeMismatch {
+                        expected: table.ty.elem.into(),
+                        actual: self.ty.into(),
+                    },
+                    "table of active element segment",
+                    self.start,
+                );
+            }
+            crate::insn::validate_constant(
+                offset,
+                ctx,
+                ValType::I32,
+                "offset expression in element segment",
+                self.start,
+            )?;
+        }
+        for expr in self.init.iter() {
+            crate::insn::validate_constant(
+                expr,
+                ctx,
+                self.ty.into(),
+                "init in element segment",
+                self.start,
+            )?;
         }
         Ok(())
     }
@@ -275,14 +353,22 @@ impl<'s, S: Source> Validate<'s, S> for ElemSegment {
 // https://webassembly.github.io/spec/core/valid/modules.html#data-segments
 impl<'s, S: Source> Validate<'s, S> for DataSegment<'s> {
     fn validate<'m>(&self, ctx: &mut Context<'m, 's, S>) -> Result<(), S> {
-        ctx.memory_from_idx(self.idx, "data segment", self.start)?;
-        crate::insn::validate_constant(
-            &self.offset,
-            ctx,
-            ValType::I32,
-            "offset expression in data segment",
-            self.start,
-        )?;
+        if let DataMode::Active { idx, offset } = &self.mode {
+            let memory = ctx.memory_from_idx(*idx, "data segment", self.start)?;
+            // Offset is i64 for 64-bit memory
+            let ty = if memory.ty.memory64 {
+                ValType::I64
+            } else {
+                ValType::I32
+            };
+            crate::insn::validate_constant(
+                offset,
+                ctx,
+                ty,
+                "offset expression in data segment",
+                self.start,
+            )?;
+        }
         Ok(())
     }
 }
@@ -338,6 +424,9 @@ impl<'s, S: Source> Validate<'s, S> for Export<'s> {
             ExportKind::Global(idx) => {
                 ctx.global_from_idx(idx, "exported global variable", self.start)?;
             }
+            ExportKind::Tag(idx) => {
+                ctx.tag_from_idx(idx, "exported tag", self.start)?;
+            }
         }
         Ok(())
     }